@@ -0,0 +1,215 @@
+//! Address Input
+//!
+//! Turns what the user typed in an address bar into the URL to load, so the
+//! panel and embedders with their own address bar agree on it. The input is
+//! trimmed and rewritten by the [`AddressInputSettings::rewrites`], then taken
+//! for, in this order:
+//!
+//! - a keyword search if it starts with one of the
+//!   [`AddressInputSettings::keywords`] and a space,
+//! - a search if it starts with `?`,
+//! - a URL if it has a web scheme (`http`, `https`, `file`, `data`, `blob`),
+//!   an internal page if it has an internal one (`about`, `verso`), or a custom
+//!   scheme URL if its scheme is one of the
+//!   [`AddressInputSettings::custom_schemes`],
+//! - a file URL if it is an absolute path,
+//! - a URL if it starts with a host: `localhost`, an IP address, or a domain
+//!   whose last label looks like a top-level domain. Those are loaded over
+//!   HTTPS, except `localhost` and IP addresses over HTTP,
+//! - a search with [`AddressInputSettings::search_template`] otherwise.
+//!
+//! Top-level domains aren't checked against a list, so `notes.txt` is taken for
+//! a host, type `?notes.txt` to search it.
+
+use std::{net::Ipv4Addr, path::Path};
+
+use url::{Host, Url, form_urlencoded};
+use versoview_messages::{AddressInputKind, AddressInputSettings, AddressTarget};
+
+/// Schemes of the web, loaded as typed
+const WEB_SCHEMES: [&str; 5] = ["http", "https", "file", "data", "blob"];
+
+/// Schemes of the browser's own pages
+const INTERNAL_SCHEMES: [&str; 2] = ["about", "verso"];
+
+/// Interpret address bar input, `None` if it is empty or its search template is invalid
+pub fn interpret(input: &str, settings: &AddressInputSettings) -> Option<AddressTarget> {
+    let input = rewrite(input.trim(), settings);
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    if let Some((keyword, query)) = input.split_once(char::is_whitespace) {
+        if let Some(template) = settings.keywords.get(keyword) {
+            return search(template, query.trim());
+        }
+    }
+    if let Some(query) = input.strip_prefix('?') {
+        return search(&settings.search_template, query.trim());
+    }
+    if let Ok(url) = Url::parse(input) {
+        let scheme = url.scheme();
+        let kind = if WEB_SCHEMES.contains(&scheme) {
+            Some(AddressInputKind::Url)
+        } else if INTERNAL_SCHEMES.contains(&scheme) {
+            Some(AddressInputKind::Internal)
+        } else if settings
+            .custom_schemes
+            .iter()
+            .any(|custom| custom.eq_ignore_ascii_case(scheme))
+        {
+            Some(AddressInputKind::CustomScheme)
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            return Some(AddressTarget { kind, url });
+        }
+    }
+    if Path::new(input).is_absolute() {
+        if let Ok(url) = Url::from_file_path(input) {
+            return Some(AddressTarget {
+                kind: AddressInputKind::Url,
+                url,
+            });
+        }
+    }
+    if !input.contains(char::is_whitespace) {
+        if let Some(url) = host_url(input) {
+            return Some(AddressTarget {
+                kind: AddressInputKind::Url,
+                url,
+            });
+        }
+    }
+    search(&settings.search_template, input)
+}
+
+/// Apply the rewrites in order
+fn rewrite(input: &str, settings: &AddressInputSettings) -> String {
+    settings
+        .rewrites
+        .iter()
+        .filter(|rewrite| !rewrite.prefix.is_empty())
+        .fold(input.to_string(), |input, rewrite| {
+            match input.strip_prefix(&rewrite.prefix) {
+                Some(rest) => format!("{}{rest}", rewrite.replacement),
+                None => input,
+            }
+        })
+}
+
+/// URL of input starting with a host, `None` if it doesn't look like one
+fn host_url(input: &str) -> Option<Url> {
+    let mut url = Url::parse(&format!("https://{input}")).ok()?;
+    let plain_http = match url.host()? {
+        // `Url` reads `1.2` as `1.0.0.2`, only take four numbers for an address.
+        Host::Ipv4(_) => input
+            .split(['/', ':', '?', '#'])
+            .next()
+            .is_some_and(|host| host.parse::<Ipv4Addr>().is_ok())
+            .then_some(true)?,
+        Host::Ipv6(_) => true,
+        Host::Domain(domain) if domain == "localhost" || domain.ends_with(".localhost") => true,
+        Host::Domain(domain) => {
+            let (name, top_level) = domain.rsplit_once('.')?;
+            let looks_top_level = top_level.starts_with("xn--")
+                || (top_level.len() >= 2 && top_level.chars().all(|c| c.is_ascii_alphabetic()));
+            if name.is_empty() || !looks_top_level {
+                return None;
+            }
+            false
+        }
+    };
+    if plain_http {
+        url.set_scheme("http").ok()?;
+    }
+    Some(url)
+}
+
+/// Search for `query` with the search URL `template`
+fn search(template: &str, query: &str) -> Option<AddressTarget> {
+    let query: String = form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    let url = Url::parse(&template.replace("%s", &query))
+        .inspect_err(|error| log::warn!("Verso failed to search with {template}: {error}"))
+        .ok()?;
+    Some(AddressTarget {
+        kind: AddressInputKind::Search,
+        url,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use versoview_messages::AddressRewrite;
+
+    use super::*;
+
+    #[test]
+    fn test_interpret() {
+        let settings = AddressInputSettings {
+            keywords: [(
+                "w".to_string(),
+                "https://en.wikipedia.org/w/index.php?search=%s".to_string(),
+            )]
+            .into(),
+            rewrites: vec![AddressRewrite {
+                prefix: "gh/".to_string(),
+                replacement: "https://github.com/".to_string(),
+            }],
+            custom_schemes: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let interpret = |input| {
+            interpret(input, &settings)
+                .map(|target| (target.kind, target.url.to_string()))
+                .unwrap()
+        };
+        let url = |url: &str| (AddressInputKind::Url, url.to_string());
+        let search = |url: &str| (AddressInputKind::Search, url.to_string());
+
+        assert_eq!(interpret(" https://servo.org "), url("https://servo.org/"));
+        assert_eq!(interpret("servo.org/blog"), url("https://servo.org/blog"));
+        assert_eq!(interpret("localhost:8000"), url("http://localhost:8000/"));
+        assert_eq!(
+            interpret("192.168.1.1/admin"),
+            url("http://192.168.1.1/admin")
+        );
+        assert_eq!(interpret("[::1]:8080"), url("http://[::1]:8080/"));
+        assert_eq!(interpret("/tmp/page.html"), url("file:///tmp/page.html"));
+        assert_eq!(interpret("gh/servo"), url("https://github.com/servo"));
+        assert_eq!(
+            interpret("about:blank"),
+            (AddressInputKind::Internal, "about:blank".to_string())
+        );
+        assert_eq!(
+            interpret("app://launcher/"),
+            (
+                AddressInputKind::CustomScheme,
+                "app://launcher/".to_string()
+            )
+        );
+        assert_eq!(
+            interpret("rust ownership"),
+            search("https://duckduckgo.com/?q=rust+ownership")
+        );
+        assert_eq!(interpret("3.14"), search("https://duckduckgo.com/?q=3.14"));
+        assert_eq!(
+            interpret("servo"),
+            search("https://duckduckgo.com/?q=servo")
+        );
+        assert_eq!(
+            interpret("?servo.org"),
+            search("https://duckduckgo.com/?q=servo.org")
+        );
+        assert_eq!(
+            interpret("w Café & co"),
+            search("https://en.wikipedia.org/w/index.php?search=Caf%C3%A9+%26+co")
+        );
+        assert_eq!(
+            interpret("mailto:someone@example.com"),
+            search("https://duckduckgo.com/?q=mailto%3Asomeone%40example.com")
+        );
+        assert!(super::interpret("   ", &settings).is_none());
+    }
+}
@@ -0,0 +1,185 @@
+//! Allocation Profiling
+//!
+//! Attributes heap allocations made by the compositor process to the subsystem
+//! doing them, to find the churn behind memory growth in long sessions. Code is
+//! tagged with an [`AllocationScope`], and the bytes allocated and freed while a
+//! scope is alive are added to that tag's counters.
+//!
+//! Servo already installs jemalloc as the global allocator, so instead of
+//! wrapping it the scopes read jemalloc's per-thread allocation counters. This
+//! needs the `allocation-profiling` feature and a jemalloc platform; otherwise
+//! scopes are free and record nothing. Nested scopes are inclusive: an outer
+//! scope also counts what its inner scopes allocated.
+//!
+//! For allocation sites below the subsystem level, run with
+//! `MALLOC_CONF=prof:true` and call [`dump_heap_profile`], or run the process
+//! under heaptrack, which doesn't need any support from Verso.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+/// Allocation counters of one tagged subsystem
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocationSiteStats {
+    /// Bytes allocated while the scope was alive
+    pub allocated: u64,
+    /// Bytes freed while the scope was alive
+    pub deallocated: u64,
+    /// How many times the scope was entered
+    pub entries: u64,
+}
+
+impl AllocationSiteStats {
+    /// Bytes allocated and not freed within the scope, a hint of what it retains
+    pub fn retained(&self) -> u64 {
+        self.allocated.saturating_sub(self.deallocated)
+    }
+}
+
+static SITES: LazyLock<Mutex<HashMap<&'static str, AllocationSiteStats>>> =
+    LazyLock::new(Default::default);
+
+/// Whether scopes actually record anything in this build
+pub fn is_enabled() -> bool {
+    thread_counters().is_some()
+}
+
+/// Tags the allocations of the current thread until dropped
+#[must_use = "allocations are only attributed while the scope is alive"]
+pub struct AllocationScope {
+    tag: &'static str,
+    start: Option<(u64, u64)>,
+}
+
+impl AllocationScope {
+    /// Start attributing allocations of the current thread to `tag`
+    pub fn enter(tag: &'static str) -> Self {
+        Self {
+            tag,
+            start: thread_counters(),
+        }
+    }
+}
+
+impl Drop for AllocationScope {
+    fn drop(&mut self) {
+        let (Some((allocated, deallocated)), Some((end_allocated, end_deallocated))) =
+            (self.start, thread_counters())
+        else {
+            return;
+        };
+        record(
+            self.tag,
+            end_allocated.wrapping_sub(allocated),
+            end_deallocated.wrapping_sub(deallocated),
+        );
+    }
+}
+
+fn record(tag: &'static str, allocated: u64, deallocated: u64) {
+    let mut sites = SITES.lock().unwrap();
+    let site = sites.entry(tag).or_default();
+    site.allocated += allocated;
+    site.deallocated += deallocated;
+    site.entries += 1;
+}
+
+/// The `count` subsystems that allocated the most bytes, largest first
+pub fn top_sites(count: usize) -> Vec<(&'static str, AllocationSiteStats)> {
+    let mut sites: Vec<_> = SITES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(tag, stats)| (*tag, *stats))
+        .collect();
+    sites.sort_by(|a, b| b.1.allocated.cmp(&a.1.allocated).then(a.0.cmp(b.0)));
+    sites.truncate(count);
+    sites
+}
+
+/// Reset every counter
+pub fn reset() {
+    SITES.lock().unwrap().clear();
+}
+
+/// Write a jemalloc heap profile to `path`
+///
+/// Only works when jemalloc was started with profiling on (`MALLOC_CONF=prof:true`).
+pub fn dump_heap_profile(path: &std::path::Path) -> Result<(), String> {
+    #[cfg(all(feature = "allocation-profiling", not(windows)))]
+    {
+        let path =
+            std::ffi::CString::new(path.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+        // SAFETY: `prof.dump` takes a NUL-terminated path, which outlives the call.
+        unsafe { tikv_jemalloc_ctl::raw::write(b"prof.dump\0", path.as_ptr()) }
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(all(feature = "allocation-profiling", not(windows))))]
+    {
+        let _ = path;
+        Err("allocation profiling is not enabled in this build".to_string())
+    }
+}
+
+/// Total bytes allocated and freed by the current thread so far
+#[cfg(all(feature = "allocation-profiling", not(windows)))]
+fn thread_counters() -> Option<(u64, u64)> {
+    use tikv_jemalloc_ctl::thread::{ThreadLocal, allocatedp, deallocatedp};
+
+    thread_local! {
+        static COUNTERS: Option<(ThreadLocal<u64>, ThreadLocal<u64>)> =
+            allocatedp::read().ok().zip(deallocatedp::read().ok());
+    }
+    COUNTERS.with(|counters| {
+        counters
+            .as_ref()
+            .map(|(allocated, deallocated)| (allocated.get(), deallocated.get()))
+    })
+}
+
+#[cfg(not(all(feature = "allocation-profiling", not(windows))))]
+fn thread_counters() -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_sites_ordering() {
+        record("test.small", 10, 5);
+        record("test.large", 1000, 10);
+        record("test.large", 500, 0);
+
+        let top = top_sites(usize::MAX);
+        let large = top
+            .iter()
+            .position(|(tag, _)| *tag == "test.large")
+            .unwrap();
+        let small = top
+            .iter()
+            .position(|(tag, _)| *tag == "test.small")
+            .unwrap();
+        assert!(large < small);
+        assert_eq!(top[large].1.allocated, 1500);
+        assert_eq!(top[large].1.retained(), 1490);
+        assert_eq!(top[large].1.entries, 2);
+    }
+
+    #[test]
+    fn test_scope_records_when_enabled() {
+        {
+            let _scope = AllocationScope::enter("test.scope");
+            std::hint::black_box(vec![0u8; 4096]);
+        }
+        let recorded = top_sites(usize::MAX)
+            .into_iter()
+            .find(|(tag, _)| *tag == "test.scope");
+        if is_enabled() {
+            assert!(recorded.unwrap().1.allocated >= 4096);
+        } else {
+            assert!(recorded.is_none());
+        }
+    }
+}
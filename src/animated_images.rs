@@ -0,0 +1,712 @@
+//! Animated Image Scheduling
+//!
+//! Plays animated images (GIF, APNG and animated WebP) from the compositor
+//! instead of script timers. Servo only uploads the first frame of an image, so
+//! Verso loads the images that may be animated for the page itself, hands the
+//! body to Servo as is, and decodes every frame. The frames are matched to the
+//! image Servo uploaded by the pixels of their first frame.
+//!
+//! Frame switches are timed against the display refresh, all images due on the
+//! same frame are updated in one WebRender transaction, and playback stops for
+//! images scrolled away from the viewport, in throttled webviews or under
+//! critical memory pressure. Paused images resume on their current frame
+//! rather than racing to catch up.
+//!
+//! Images are recognized by their `.gif`, `.apng` and `.webp` extensions. They
+//! are passed to Servo without being converted to the output color space.
+//! Animated AVIF isn't decoded, those images stay on their first frame.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use base::id::WebViewId;
+use embedder_traits::WebResourceRequest;
+use image::{
+    AnimationDecoder, Frames, ImageFormat as EncodedFormat,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+};
+use ipc_channel::ipc::IpcSharedMemory;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use webrender_api::{ImageData, ImageDescriptor, ImageFormat, ImageKey};
+
+use crate::memory_pressure::MemoryPressureLevel;
+
+/// Frame delays at or below this are played at [`DEFAULT_FRAME_DELAY`], as other browsers do
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+
+/// Delay used for frames without a usable delay
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// Images with more decoded pixels than this stay on their first frame
+const MAX_ANIMATION_BYTES: usize = 64 * 1024 * 1024;
+
+/// How many of the images Servo uploaded last are kept to match decoded frames against
+const RECENT_IMAGES: usize = 256;
+
+/// How long decoded frames wait for Servo to upload their image
+const PENDING_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many pixels of a frame are compared to match it to an uploaded image
+const FINGERPRINT_SAMPLES: usize = 4096;
+
+/// Shortest time between two queries of where a page's images are
+pub const VIEW_REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Extensions of the images loaded by Verso to be played by the compositor
+const ANIMATED_EXTENSIONS: [&str; 3] = [".gif", ".apng", ".webp"];
+
+/// How many times an animation plays
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoopCount {
+    /// Loop forever
+    Infinite,
+    /// Play this many times, then stay on the last frame
+    Finite(u32),
+}
+
+impl LoopCount {
+    /// From the play count stored in APNG and WebP files, where 0 means forever
+    pub fn from_plays(plays: u32) -> Self {
+        match plays {
+            0 => Self::Infinite,
+            plays => Self::Finite(plays),
+        }
+    }
+}
+
+/// A decoded frame of an animated image
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DecodedFrame {
+    /// Premultiplied BGRA pixels of the whole image at this frame, as Servo uploads them
+    pub pixels: IpcSharedMemory,
+    /// How long the frame is shown
+    pub delay: Duration,
+}
+
+/// The frames of an image Verso loaded for a page
+#[derive(Serialize, Deserialize)]
+pub struct DecodedAnimation {
+    /// URL the image was loaded from
+    pub url: Url,
+    /// Width of the frames in pixels
+    pub width: u32,
+    /// Height of the frames in pixels
+    pub height: u32,
+    /// Frames in playback order
+    pub frames: Vec<DecodedFrame>,
+    /// How many times the frames play
+    pub loop_count: LoopCount,
+}
+
+/// Whether the request loads an image that may be animated
+pub fn is_animated_image_request(request: &WebResourceRequest) -> bool {
+    let path = request.url.path().to_ascii_lowercase();
+    !request.is_for_main_frame
+        && request.method == http::Method::GET
+        && ANIMATED_EXTENSIONS
+            .iter()
+            .any(|extension| path.ends_with(extension))
+}
+
+/// Decode every frame of an encoded image, `None` if it isn't animated or can't be played
+///
+/// This is CPU bound, don't call it on an async task.
+pub fn decode_animation(url: Url, encoded: &[u8]) -> Option<DecodedAnimation> {
+    let format = image::guess_format(encoded).ok()?;
+    let (frames, loop_count) = match format {
+        EncodedFormat::Gif => (
+            GifDecoder::new(Cursor::new(encoded)).ok()?.into_frames(),
+            gif_loop_count(encoded),
+        ),
+        EncodedFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(encoded)).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+            (
+                decoder.apng().ok()?.into_frames(),
+                chunk_loop_count(encoded, b"acTL", 4, |bytes| {
+                    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                }),
+            )
+        }
+        EncodedFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(encoded)).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            (
+                decoder.into_frames(),
+                chunk_loop_count(encoded, b"ANIM", 8, |bytes| {
+                    u16::from_le_bytes([bytes[0], bytes[1]]) as u32
+                }),
+            )
+        }
+        _ => return None,
+    };
+    let (width, height, frames) = collect_frames(&url, frames)?;
+    (frames.len() > 1).then_some(DecodedAnimation {
+        url,
+        width,
+        height,
+        frames,
+        loop_count,
+    })
+}
+
+fn collect_frames(url: &Url, frames: Frames<'_>) -> Option<(u32, u32, Vec<DecodedFrame>)> {
+    let mut size = None;
+    let mut bytes = 0;
+    let mut decoded = Vec::new();
+    for frame in frames {
+        let frame = frame
+            .inspect_err(|error| log::warn!("Verso failed to decode a frame of {url}: {error}"))
+            .ok()?;
+        let delay = Duration::from(frame.delay());
+        let buffer = frame.into_buffer();
+        if *size.get_or_insert(buffer.dimensions()) != buffer.dimensions() {
+            return None;
+        }
+        bytes += buffer.len();
+        if bytes > MAX_ANIMATION_BYTES {
+            log::info!("Verso plays only the first frame of {url}, its frames are too large");
+            return None;
+        }
+        decoded.push(DecodedFrame {
+            pixels: IpcSharedMemory::from_bytes(&premultiplied_bgra(buffer.into_raw())),
+            delay,
+        });
+    }
+    let (width, height) = size?;
+    Some((width, height, decoded))
+}
+
+/// Convert RGBA pixels to premultiplied BGRA, the format Servo uploads images in
+fn premultiplied_bgra(mut pixels: Vec<u8>) -> Vec<u8> {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        let premultiply = |color: u8| ((color as u32 * alpha + 127) / 255) as u8;
+        let (red, green, blue) = (pixel[0], pixel[1], pixel[2]);
+        pixel[0] = premultiply(blue);
+        pixel[1] = premultiply(green);
+        pixel[2] = premultiply(red);
+    }
+    pixels
+}
+
+/// Play count of a GIF, its NETSCAPE2.0 extension counts the repeats after the first play
+fn gif_loop_count(encoded: &[u8]) -> LoopCount {
+    const EXTENSION: &[u8] = b"NETSCAPE2.0";
+    let Some(start) = encoded
+        .windows(EXTENSION.len())
+        .position(|window| window == EXTENSION)
+    else {
+        return LoopCount::Finite(1);
+    };
+    match encoded.get(start + EXTENSION.len()..start + EXTENSION.len() + 4) {
+        Some(&[3, 1, low, high]) => match u16::from_le_bytes([low, high]) {
+            0 => LoopCount::Infinite,
+            repeats => LoopCount::Finite(repeats as u32 + 1),
+        },
+        _ => LoopCount::Finite(1),
+    }
+}
+
+/// Play count stored `offset` bytes after the type of a chunk, forever if there is none
+fn chunk_loop_count(
+    encoded: &[u8],
+    chunk: &[u8; 4],
+    offset: usize,
+    read: impl Fn(&[u8]) -> u32,
+) -> LoopCount {
+    encoded
+        .windows(4)
+        .position(|window| window == chunk)
+        .and_then(|start| encoded.get(start + 4 + offset..start + 8 + offset))
+        .map_or(LoopCount::Infinite, |plays| {
+            LoopCount::from_plays(read(plays))
+        })
+}
+
+/// Hash of the size and of a sample of the pixels of a premultiplied BGRA frame
+///
+/// Premultiplication may round translucent pixels differently than Servo does, so only
+/// their alpha is hashed.
+fn fingerprint(width: i32, height: i32, pixels: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (width, height).hash(&mut hasher);
+    let step = (pixels.len() / 4 / FINGERPRINT_SAMPLES).max(1);
+    for pixel in pixels.chunks_exact(4).step_by(step) {
+        if pixel[3] == u8::MAX {
+            hasher.write(pixel);
+        } else {
+            hasher.write_u8(pixel[3]);
+        }
+    }
+    hasher.finish()
+}
+
+struct AnimationFrame {
+    data: ImageData,
+    delay: Duration,
+}
+
+struct AnimatedImage {
+    webview_id: WebViewId,
+    url: Url,
+    descriptor: ImageDescriptor,
+    frames: Vec<AnimationFrame>,
+    loop_count: LoopCount,
+    current: usize,
+    completed_loops: u32,
+    next_frame_at: Instant,
+    in_view: bool,
+    throttled: bool,
+    finished: bool,
+}
+
+impl AnimatedImage {
+    fn delay(&self) -> Duration {
+        let delay = self.frames[self.current].delay;
+        if delay <= MIN_FRAME_DELAY {
+            DEFAULT_FRAME_DELAY
+        } else {
+            delay
+        }
+    }
+
+    fn is_playing(&self) -> bool {
+        self.in_view && !self.throttled && !self.finished
+    }
+
+    /// Move to the next frame, returns false once the last loop ended
+    fn advance(&mut self) -> bool {
+        if self.current + 1 < self.frames.len() {
+            self.current += 1;
+            return true;
+        }
+        self.completed_loops += 1;
+        if let LoopCount::Finite(plays) = self.loop_count {
+            if self.completed_loops >= plays {
+                self.finished = true;
+                return false;
+            }
+        }
+        self.current = 0;
+        true
+    }
+}
+
+/// An image Servo uploaded, waiting for its frames
+struct UploadedImage {
+    key: ImageKey,
+    descriptor: ImageDescriptor,
+    fingerprint: u64,
+}
+
+/// Frames waiting for Servo to upload their image
+struct PendingAnimation {
+    webview_id: WebViewId,
+    animation: DecodedAnimation,
+    fingerprint: u64,
+    received: Instant,
+}
+
+/// An image update produced by [`AnimatedImageScheduler::tick`]
+pub struct FrameUpdate {
+    /// Webview showing the image
+    pub webview_id: WebViewId,
+    /// Image to update
+    pub key: ImageKey,
+    /// Descriptor of the image
+    pub descriptor: ImageDescriptor,
+    /// Pixels of the new frame
+    pub data: ImageData,
+}
+
+/// Plays every animated image known to the compositor
+#[derive(Default)]
+pub struct AnimatedImageScheduler {
+    images: HashMap<ImageKey, AnimatedImage>,
+    uploaded: VecDeque<UploadedImage>,
+    pending: Vec<PendingAnimation>,
+    throttled: HashSet<WebViewId>,
+    in_view: HashMap<WebViewId, HashSet<Url>>,
+    paused_for_memory: bool,
+}
+
+impl AnimatedImageScheduler {
+    /// Servo uploaded an image, start playing it if its frames were decoded
+    pub fn on_image_added(
+        &mut self,
+        key: ImageKey,
+        descriptor: ImageDescriptor,
+        data: &ImageData,
+        now: Instant,
+    ) {
+        let ImageData::Raw(pixels) = data else {
+            return;
+        };
+        if descriptor.format != ImageFormat::BGRA8 {
+            return;
+        }
+        let fingerprint = fingerprint(descriptor.size.width, descriptor.size.height, pixels);
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|pending| pending.fingerprint == fingerprint)
+        {
+            let pending = self.pending.swap_remove(index);
+            self.start(key, descriptor, pending.webview_id, pending.animation, now);
+            return;
+        }
+        if self.uploaded.len() == RECENT_IMAGES {
+            self.uploaded.pop_front();
+        }
+        self.uploaded.push_back(UploadedImage {
+            key,
+            descriptor,
+            fingerprint,
+        });
+    }
+
+    /// The frames of an image a webview loaded were decoded
+    ///
+    /// The image starts playing once Servo uploaded its first frame, or right away if it
+    /// already did.
+    pub fn add(&mut self, webview_id: WebViewId, animation: DecodedAnimation, now: Instant) {
+        let fingerprint = fingerprint(
+            animation.width as i32,
+            animation.height as i32,
+            &animation.frames[0].pixels,
+        );
+        if let Some(index) = self
+            .uploaded
+            .iter()
+            .position(|uploaded| uploaded.fingerprint == fingerprint)
+        {
+            let uploaded = self.uploaded.remove(index).unwrap();
+            self.start(
+                uploaded.key,
+                uploaded.descriptor,
+                webview_id,
+                animation,
+                now,
+            );
+            return;
+        }
+        self.pending.push(PendingAnimation {
+            webview_id,
+            animation,
+            fingerprint,
+            received: now,
+        });
+    }
+
+    fn start(
+        &mut self,
+        key: ImageKey,
+        descriptor: ImageDescriptor,
+        webview_id: WebViewId,
+        animation: DecodedAnimation,
+        now: Instant,
+    ) {
+        if descriptor.size.width != animation.width as i32
+            || descriptor.size.height != animation.height as i32
+        {
+            return;
+        }
+        log::debug!(
+            "Verso plays {} frames of {}",
+            animation.frames.len(),
+            animation.url
+        );
+        let in_view = self
+            .in_view
+            .get(&webview_id)
+            .is_none_or(|in_view| in_view.contains(&animation.url));
+        let mut image = AnimatedImage {
+            webview_id,
+            url: animation.url,
+            descriptor,
+            frames: animation
+                .frames
+                .into_iter()
+                .map(|frame| AnimationFrame {
+                    data: ImageData::new(frame.pixels.to_vec()),
+                    delay: frame.delay,
+                })
+                .collect(),
+            loop_count: animation.loop_count,
+            current: 0,
+            completed_loops: 0,
+            next_frame_at: now,
+            in_view,
+            throttled: self.throttled.contains(&webview_id),
+            finished: false,
+        };
+        image.next_frame_at = now + image.delay();
+        self.images.insert(key, image);
+    }
+
+    /// Servo deleted an image, stop playing it
+    pub fn remove(&mut self, key: ImageKey) {
+        self.images.remove(&key);
+        self.uploaded.retain(|uploaded| uploaded.key != key);
+    }
+
+    /// Stop playing every image of a webview
+    pub fn remove_webview(&mut self, webview_id: WebViewId) {
+        self.images
+            .retain(|_, image| image.webview_id != webview_id);
+        self.pending
+            .retain(|pending| pending.webview_id != webview_id);
+        self.throttled.remove(&webview_id);
+        self.in_view.remove(&webview_id);
+    }
+
+    /// Whether a webview shows animated images, or will once Servo uploaded them
+    pub fn has_images(&self, webview_id: WebViewId) -> bool {
+        self.images
+            .values()
+            .any(|image| image.webview_id == webview_id)
+            || self
+                .pending
+                .iter()
+                .any(|pending| pending.webview_id == webview_id)
+    }
+
+    /// Pause the images of a webview that are away from its viewport, resume the others
+    pub fn set_in_view(&mut self, webview_id: WebViewId, in_view: &HashSet<Url>, now: Instant) {
+        for image in self.images.values_mut() {
+            if image.webview_id != webview_id {
+                continue;
+            }
+            let visible = in_view.contains(&image.url);
+            if visible && !image.in_view {
+                image.next_frame_at = now + image.delay();
+            }
+            image.in_view = visible;
+        }
+        self.in_view.insert(webview_id, in_view.clone());
+    }
+
+    /// Pause or resume every image of a webview, follows the webview's throttling
+    pub fn set_webview_throttled(&mut self, webview_id: WebViewId, throttled: bool, now: Instant) {
+        if throttled {
+            self.throttled.insert(webview_id);
+        } else {
+            self.throttled.remove(&webview_id);
+        }
+        for image in self.images.values_mut() {
+            if image.webview_id == webview_id {
+                if image.throttled && !throttled {
+                    image.next_frame_at = now + image.delay();
+                }
+                image.throttled = throttled;
+            }
+        }
+    }
+
+    /// Pause every image while memory is critically low
+    pub fn set_memory_pressure(&mut self, level: MemoryPressureLevel, now: Instant) {
+        let paused = level == MemoryPressureLevel::Critical;
+        if self.paused_for_memory && !paused {
+            for image in self.images.values_mut() {
+                image.next_frame_at = now + image.delay();
+            }
+        }
+        self.paused_for_memory = paused;
+    }
+
+    /// Whether any image is waiting for its next frame
+    pub fn is_animating(&self) -> bool {
+        !self.paused_for_memory && self.images.values().any(AnimatedImage::is_playing)
+    }
+
+    /// When the next frame of any image is due
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.paused_for_memory {
+            return None;
+        }
+        self.images
+            .values()
+            .filter(|image| image.is_playing())
+            .map(|image| image.next_frame_at)
+            .min()
+    }
+
+    /// Advance the images whose next frame is due by the frame presented at `now`
+    ///
+    /// A frame is due if switching now is closer to its time than switching on the
+    /// next refresh, `frame_duration` apart. Images that fell behind skip to the
+    /// frame they should be showing.
+    pub fn tick(&mut self, now: Instant, frame_duration: Duration) -> Vec<FrameUpdate> {
+        self.pending
+            .retain(|pending| now < pending.received + PENDING_TIMEOUT);
+        if self.paused_for_memory {
+            return Vec::new();
+        }
+        let due = now + frame_duration / 2;
+        let mut updates = Vec::new();
+        for (key, image) in self.images.iter_mut() {
+            if !image.is_playing() || image.next_frame_at > due {
+                continue;
+            }
+            let shown = image.current;
+            while image.next_frame_at <= due && image.advance() {
+                image.next_frame_at += image.delay();
+            }
+            if image.current != shown {
+                updates.push(FrameUpdate {
+                    webview_id: image.webview_id,
+                    key: *key,
+                    descriptor: image.descriptor,
+                    data: image.frames[image.current].data.clone(),
+                });
+            }
+        }
+        updates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+    use webrender_api::{IdNamespace, ImageDescriptorFlags};
+
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn frames(delays_ms: &[u64]) -> Vec<DecodedFrame> {
+        delays_ms
+            .iter()
+            .enumerate()
+            .map(|(index, delay)| DecodedFrame {
+                pixels: IpcSharedMemory::from_bytes(&[index as u8, 0, 0, 255]),
+                delay: Duration::from_millis(*delay),
+            })
+            .collect()
+    }
+
+    fn webview_id() -> WebViewId {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        WebViewId::new()
+    }
+
+    fn animation(loop_count: LoopCount) -> DecodedAnimation {
+        DecodedAnimation {
+            url: Url::parse("https://a.example/spinner.gif").unwrap(),
+            width: 1,
+            height: 1,
+            frames: frames(&[100, 0]),
+            loop_count,
+        }
+    }
+
+    fn scheduler(
+        loop_count: LoopCount,
+        now: Instant,
+    ) -> (AnimatedImageScheduler, ImageKey, WebViewId) {
+        let key = ImageKey::new(IdNamespace(1), 1);
+        let descriptor =
+            ImageDescriptor::new(1, 1, ImageFormat::BGRA8, ImageDescriptorFlags::empty());
+        let webview_id = webview_id();
+        let mut scheduler = AnimatedImageScheduler::default();
+        scheduler.on_image_added(key, descriptor, &ImageData::new(vec![0, 0, 0, 255]), now);
+        scheduler.add(webview_id, animation(loop_count), now);
+        (scheduler, key, webview_id)
+    }
+
+    fn shown(update: &FrameUpdate) -> u8 {
+        match &update.data {
+            ImageData::Raw(bytes) => bytes[0],
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_frames_follow_delays() {
+        let start = Instant::now();
+        let (mut scheduler, _, _) = scheduler(LoopCount::Infinite, start);
+        assert!(
+            scheduler
+                .tick(start + Duration::from_millis(50), FRAME)
+                .is_empty()
+        );
+
+        let updates = scheduler.tick(start + Duration::from_millis(95), FRAME);
+        assert_eq!(updates.iter().map(shown).collect::<Vec<_>>(), vec![1]);
+        // A zero delay plays at the default delay.
+        assert_eq!(
+            scheduler.next_deadline(),
+            Some(start + Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn test_frames_wait_for_upload() {
+        let start = Instant::now();
+        let key = ImageKey::new(IdNamespace(1), 1);
+        let descriptor =
+            ImageDescriptor::new(1, 1, ImageFormat::BGRA8, ImageDescriptorFlags::empty());
+        let webview_id = webview_id();
+        let mut scheduler = AnimatedImageScheduler::default();
+        scheduler.add(webview_id, animation(LoopCount::Infinite), start);
+        assert!(scheduler.has_images(webview_id));
+        assert!(!scheduler.is_animating());
+
+        // Another image doesn't take the frames.
+        scheduler.on_image_added(key, descriptor, &ImageData::new(vec![9, 9, 9, 255]), start);
+        assert!(!scheduler.is_animating());
+        scheduler.on_image_added(key, descriptor, &ImageData::new(vec![0, 0, 0, 255]), start);
+        assert!(scheduler.is_animating());
+    }
+
+    #[test]
+    fn test_loop_count() {
+        let start = Instant::now();
+        let (mut scheduler, _, _) = scheduler(LoopCount::from_plays(1), start);
+        scheduler.tick(start + Duration::from_millis(100), FRAME);
+        assert!(
+            scheduler
+                .tick(start + Duration::from_millis(200), FRAME)
+                .is_empty()
+        );
+        assert!(!scheduler.is_animating());
+        assert_eq!(LoopCount::from_plays(0), LoopCount::Infinite);
+
+        let mut gif = b"GIF89a".to_vec();
+        assert_eq!(gif_loop_count(&gif), LoopCount::Finite(1));
+        gif.extend_from_slice(b"NETSCAPE2.0\x03\x01\x02\x00");
+        assert_eq!(gif_loop_count(&gif), LoopCount::Finite(3));
+    }
+
+    #[test]
+    fn test_pause_resumes_without_catching_up() {
+        let start = Instant::now();
+        let (mut scheduler, _, webview_id) = scheduler(LoopCount::Infinite, start);
+        scheduler.set_in_view(webview_id, &HashSet::new(), start);
+        assert!(
+            scheduler
+                .tick(start + Duration::from_secs(10), FRAME)
+                .is_empty()
+        );
+        assert_eq!(scheduler.next_deadline(), None);
+
+        let resumed = start + Duration::from_secs(10);
+        let in_view = HashSet::from([Url::parse("https://a.example/spinner.gif").unwrap()]);
+        scheduler.set_in_view(webview_id, &in_view, resumed);
+        assert_eq!(
+            scheduler.next_deadline(),
+            Some(resumed + Duration::from_millis(100))
+        );
+
+        scheduler.set_memory_pressure(MemoryPressureLevel::Critical, resumed);
+        assert!(!scheduler.is_animating());
+    }
+}
@@ -0,0 +1,264 @@
+//! Security Audit Log
+//!
+//! Records every blocking decision made for a webview, with when and why it
+//! was made, so kiosk operators can find out why their content misbehaves:
+//! scripts cancelled because JavaScript is disabled, mixed content, Content
+//! Security Policy violations, failed integrity checks, denied permissions,
+//! navigations refused by the controller and requests that couldn't go through
+//! a network context.
+//!
+//! Servo enforces Content Security Policy itself and doesn't tell the embedder,
+//! so every page gets a document start script collecting its
+//! `securitypolicyviolation` events. Pages are asked for them without blocking
+//! when they finish loading and whenever the log is read, so violations made
+//! after the load are in the log from the following read on. Only the most
+//! recent [`MAX_ENTRIES`] entries of a window are kept.
+//!
+//! The log is read with [`ToVersoMessage::GetAuditLog`] or shown by the
+//! internal page at `verso://resources/components/audit.html`.
+//!
+//! [`ToVersoMessage::GetAuditLog`]: versoview_messages::ToVersoMessage::GetAuditLog
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::SystemTime,
+};
+
+use base::id::WebViewId;
+use embedder_traits::user_content_manager::UserScript;
+use serde::Deserialize;
+use url::Url;
+use versoview_messages::{AuditCategory, AuditEntry};
+
+/// Most entries kept per window, older ones are dropped first
+pub const MAX_ENTRIES: usize = 1000;
+
+/// Records enforced CSP violations in `window.__versoCspViolations`
+pub const CSP_VIOLATION_LISTENER_SCRIPT: &str = r#"
+(() => {
+    if (window.__versoCspViolations) {
+        return;
+    }
+    window.__versoCspViolations = [];
+    document.addEventListener("securitypolicyviolation", (event) => {
+        if (event.disposition !== "enforce" || window.__versoCspViolations.length >= 100) {
+            return;
+        }
+        window.__versoCspViolations.push({
+            blocked: event.blockedURI || "inline",
+            directive: event.effectiveDirective || event.violatedDirective,
+            source: event.sourceFile ? `${event.sourceFile}:${event.lineNumber}` : "",
+        });
+    }, true);
+})();
+"#;
+
+/// Returns the violations recorded by [`CSP_VIOLATION_LISTENER_SCRIPT`] as a JSON array and clears them
+pub const TAKE_CSP_VIOLATIONS_SCRIPT: &str = r#"
+(() => {
+    const violations = window.__versoCspViolations || [];
+    window.__versoCspViolations = [];
+    return JSON.stringify(violations);
+})()
+"#;
+
+#[derive(Debug, Deserialize)]
+struct CspViolation {
+    blocked: String,
+    directive: String,
+    source: String,
+}
+
+/// The user script collecting CSP violations in every page
+pub fn user_script() -> UserScript {
+    UserScript {
+        script: CSP_VIOLATION_LISTENER_SCRIPT.to_string(),
+        source_file: None,
+    }
+}
+
+/// Blocking decisions made for the webviews of a window
+#[derive(Default)]
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+    /// Permission asked by the tab that is waiting for an answer
+    pending_permissions: HashMap<WebViewId, String>,
+    /// Navigations waiting for the controller's answer
+    pending_navigations: HashMap<Vec<u8>, (WebViewId, Url)>,
+}
+
+impl AuditLog {
+    /// Record a decision made for a webview
+    pub fn record(
+        &mut self,
+        webview_id: WebViewId,
+        category: AuditCategory,
+        document_url: Option<Url>,
+        subject: impl Into<String>,
+        reason: impl Into<String>,
+    ) {
+        let entry = AuditEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            webview: format!("{webview_id:?}"),
+            category,
+            document_url,
+            subject: subject.into(),
+            reason: reason.into(),
+        };
+        log::debug!("Verso audit: {entry:?}");
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// A tab asks for a permission, recorded if it is denied
+    pub fn permission_requested(&mut self, tab_id: WebViewId, feature: String) {
+        self.pending_permissions.insert(tab_id, feature);
+    }
+
+    /// The permission asked by a tab was answered
+    pub fn permission_answered(
+        &mut self,
+        tab_id: WebViewId,
+        allowed: bool,
+        document_url: Option<Url>,
+    ) {
+        let Some(feature) = self.pending_permissions.remove(&tab_id) else {
+            return;
+        };
+        if !allowed {
+            self.record(
+                tab_id,
+                AuditCategory::PermissionDenied,
+                document_url,
+                feature,
+                "the user denied the permission prompt",
+            );
+        }
+    }
+
+    /// A navigation was handed to the controller, recorded if it is refused
+    pub fn navigation_requested(&mut self, id: Vec<u8>, webview_id: WebViewId, url: Url) {
+        self.pending_navigations.insert(id, (webview_id, url));
+    }
+
+    /// The controller answered a navigation
+    pub fn navigation_answered(&mut self, id: &[u8], allowed: bool) {
+        let Some((webview_id, url)) = self.pending_navigations.remove(id) else {
+            return;
+        };
+        if !allowed {
+            self.record(
+                webview_id,
+                AuditCategory::NavigationDenied,
+                None,
+                url,
+                "the controller refused the navigation",
+            );
+        }
+    }
+
+    /// Record the CSP violations a page handed over, as returned by [`TAKE_CSP_VIOLATIONS_SCRIPT`]
+    pub fn add_csp_violations(&mut self, webview_id: WebViewId, document_url: Url, report: &str) {
+        for violation in parse_csp_report(report) {
+            let reason = if violation.source.is_empty() {
+                format!("violates the page's {} directive", violation.directive)
+            } else {
+                format!(
+                    "violates the page's {} directive, from {}",
+                    violation.directive, violation.source
+                )
+            };
+            self.record(
+                webview_id,
+                AuditCategory::ContentSecurityPolicy,
+                Some(document_url.clone()),
+                violation.blocked,
+                reason,
+            );
+        }
+    }
+
+    /// Forget the pending prompts of a closed webview, its entries are kept
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.pending_permissions.remove(&webview_id);
+        self.pending_navigations
+            .retain(|_, (pending, _)| *pending != webview_id);
+    }
+
+    /// Recorded entries, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter()
+    }
+}
+
+fn parse_csp_report(report: &str) -> Vec<CspViolation> {
+    serde_json::from_str(report).unwrap_or_else(|error| {
+        log::warn!("Verso failed to parse the CSP violation report: {error}");
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    use super::*;
+
+    #[test]
+    fn test_pending_decisions() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let url = Url::parse("https://kiosk.example/").unwrap();
+        let mut log = AuditLog::default();
+
+        log.permission_requested(webview_id, "Geolocation".to_string());
+        log.permission_answered(webview_id, false, Some(url.clone()));
+        log.permission_answered(webview_id, false, Some(url.clone()));
+        log.navigation_requested(vec![1], webview_id, url.clone());
+        log.navigation_answered(&[1], true);
+        log.navigation_requested(vec![2], webview_id, url.clone());
+        log.navigation_answered(&[2], false);
+
+        let categories: Vec<_> = log.entries().map(|entry| entry.category).collect();
+        assert_eq!(
+            categories,
+            vec![
+                AuditCategory::PermissionDenied,
+                AuditCategory::NavigationDenied
+            ]
+        );
+        assert_eq!(log.entries().nth(1).unwrap().subject, url.as_str());
+    }
+
+    #[test]
+    fn test_capacity() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let mut log = AuditLog::default();
+        for index in 0..=MAX_ENTRIES {
+            log.record(
+                webview_id,
+                AuditCategory::ContentBlocked,
+                None,
+                index.to_string(),
+                "JavaScript is disabled",
+            );
+        }
+        assert_eq!(log.entries().count(), MAX_ENTRIES);
+        assert_eq!(log.entries().next().unwrap().subject, "1");
+    }
+
+    #[test]
+    fn test_parse_csp_report() {
+        let violations = parse_csp_report(
+            r#"[{"blocked":"https://cdn.example/a.js","directive":"script-src-elem","source":""}]"#,
+        );
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].directive, "script-src-elem");
+        assert!(parse_csp_report("not json").is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! Color Management
+//!
+//! Converts images to the output color space, so photos tagged with a
+//! wide-gamut ICC profile (Display P3, Adobe RGB, ...) don't look washed out.
+//!
+//! Servo decodes images without looking at their color profile, so conversion
+//! happens before Servo sees them: image requests are fetched by Verso, images
+//! with an embedded profile are converted and handed over as untagged PNGs.
+//! Conversions are cached by content, so an image shared by many pages is only
+//! converted once. JPEG and still PNG images are handled; anything else is
+//! passed through untouched. Decoding, conversion and encoding are CPU bound,
+//! so callers on the async runtime run [`ColorManager::convert`] on its
+//! blocking pool.
+
+use std::{
+    io::Cursor,
+    sync::{Arc, Mutex},
+};
+
+use embedder_traits::WebResourceRequest;
+use image::{
+    DynamicImage, ImageDecoder, ImageEncoder, ImageFormat, ImageReader,
+    codecs::png::{PngDecoder, PngEncoder},
+};
+use qcms::{DataType, Intent, Profile, Transform};
+use versoview_messages::ColorManagement;
+
+use crate::utils::content_cache::{ContentCache, content_key};
+
+/// Upper bound of the converted image cache, in bytes
+const CACHE_CAPACITY: usize = 64 * 1024 * 1024;
+
+/// File extensions treated as convertible images when the request has no fetch destination
+const IMAGE_EXTENSIONS: [&str; 3] = [".jpg", ".jpeg", ".png"];
+
+/// Converts images to the configured output color space
+pub struct ColorManager {
+    output: Box<Profile>,
+    output_is_srgb: bool,
+    /// Converted images by content hash, `None` for images used as is
+    cache: Mutex<ContentCache<Option<Arc<Vec<u8>>>>>,
+}
+
+impl ColorManager {
+    /// Create a color manager for the config, `None` if color management is disabled
+    pub fn new(config: &ColorManagement) -> Result<Option<Arc<Self>>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let (mut output, output_is_srgb) = match &config.output_profile {
+            Some(path) => {
+                let icc = std::fs::read(path)
+                    .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+                let profile = Profile::new_from_slice(&icc, false)
+                    .ok_or_else(|| format!("{} is not a valid ICC profile", path.display()))?;
+                (profile, false)
+            }
+            None => (Profile::new_sRGB(), true),
+        };
+        output.precache_output_transform();
+        Ok(Some(Arc::new(Self {
+            output,
+            output_is_srgb,
+            cache: Mutex::new(ContentCache::new(CACHE_CAPACITY)),
+        })))
+    }
+
+    /// Whether the request loads an image this manager may convert
+    pub fn handles(&self, request: &WebResourceRequest) -> bool {
+        !request.is_for_main_frame
+            && request.method == http::Method::GET
+            && is_image_request(
+                request
+                    .headers
+                    .get("sec-fetch-dest")
+                    .and_then(|value| value.to_str().ok()),
+                request.url.path(),
+            )
+    }
+
+    /// Convert an encoded image to the output color space
+    ///
+    /// Returns a PNG without a color profile, or `None` if the image can be used as is.
+    /// This is CPU bound, don't call it on an async task.
+    pub fn convert(&self, encoded: &[u8]) -> Option<Arc<Vec<u8>>> {
+        let key = content_key(encoded, ());
+        if let Some(converted) = self.cache.lock().unwrap().get(key) {
+            return converted;
+        }
+
+        let converted = self.convert_uncached(encoded).map(Arc::new);
+        self.cache.lock().unwrap().insert(key, converted.clone());
+        converted
+    }
+
+    fn convert_uncached(&self, encoded: &[u8]) -> Option<Vec<u8>> {
+        let reader = ImageReader::new(Cursor::new(encoded))
+            .with_guessed_format()
+            .ok()?;
+        let (icc, image) = match reader.format()? {
+            ImageFormat::Png => {
+                let mut decoder = PngDecoder::new(Cursor::new(encoded)).ok()?;
+                // Converting would keep only the first frame of an animation.
+                if decoder.is_apng().ok()? {
+                    return None;
+                }
+                let icc = decoder.icc_profile().ok()?;
+                (icc, DynamicImage::from_decoder(decoder).ok()?)
+            }
+            ImageFormat::Jpeg => {
+                let mut decoder = reader.into_decoder().ok()?;
+                let icc = decoder.icc_profile().ok()?;
+                (icc, DynamicImage::from_decoder(decoder).ok()?)
+            }
+            _ => return None,
+        };
+
+        let input = match icc {
+            Some(icc) => Profile::new_from_slice(&icc, false)?,
+            // Untagged images are sRGB, which is what we output anyway.
+            None if self.output_is_srgb => return None,
+            None => Profile::new_sRGB(),
+        };
+        let transform = Transform::new(
+            &input,
+            DataType::RGBA8,
+            &self.output,
+            DataType::RGBA8,
+            Intent::Perceptual,
+        )?;
+
+        let mut rgba = image.into_rgba8();
+        transform.apply(&mut rgba);
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png)
+            .write_image(
+                &rgba,
+                rgba.width(),
+                rgba.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+            .ok()?;
+        Some(png)
+    }
+}
+
+/// Whether a request fetches an image that may carry a color profile
+fn is_image_request(destination: Option<&str>, path: &str) -> bool {
+    match destination {
+        Some(destination) => destination == "image",
+        None => {
+            let path = path.to_ascii_lowercase();
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srgb_manager() -> Arc<ColorManager> {
+        ColorManager::new(&ColorManagement {
+            enabled: true,
+            output_profile: None,
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(
+            ColorManager::new(&ColorManagement::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_is_image_request() {
+        assert!(is_image_request(Some("image"), "/photo"));
+        assert!(!is_image_request(Some("script"), "/photo.jpg"));
+        assert!(is_image_request(None, "/Photo.JPG"));
+        assert!(!is_image_request(None, "/anim.gif"));
+    }
+
+    #[test]
+    fn test_untagged_srgb_passes_through() {
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png)
+            .write_image(&[255, 0, 0, 255], 1, 1, image::ExtendedColorType::Rgba8)
+            .unwrap();
+        let manager = srgb_manager();
+        assert!(manager.convert(&png).is_none());
+        assert!(manager.convert(b"not an image").is_none());
+    }
+}
@@ -1,12 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::ffi::c_void;
+use std::fs;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use base::cross_process_instant::CrossProcessInstant;
 use base::id::{PipelineId, WebViewId};
 use base::{Epoch, WebRenderEpochToU16};
+use compositing_traits::WebrenderExternalImageRegistry;
 use compositing_traits::display_list::{CompositorDisplayListInfo, HitTestInfo, ScrollTree};
 use compositing_traits::{
     CompositionPipeline, CompositorMsg, CompositorProxy, ImageUpdate, SendableFrameTree,
@@ -19,7 +22,7 @@ use crossbeam_channel::{Receiver, Sender};
 use dpi::PhysicalSize;
 use embedder_traits::{
     AnimationState, CompositorHitTestResult, Cursor, InputEvent, MouseButton, MouseButtonAction,
-    MouseButtonEvent, MouseMoveEvent, TouchEvent, TouchEventType, TouchId, UntrustedNodeAddress,
+    MouseButtonEvent, MouseMoveEvent, TouchEvent, TouchEventType, UntrustedNodeAddress,
     ViewportDetails,
 };
 use euclid::{Point2D, Scale, Size2D, Transform3D, Vector2D, vec2};
@@ -29,28 +32,87 @@ use log::{debug, error, trace, warn};
 use profile_traits::mem::{ProcessReports, Report, ReportKind};
 use profile_traits::time::{self as profile_time, ProfilerCategory};
 use profile_traits::{mem, path, time, time_profile};
+use servo_config::pref;
 use servo_geometry::{DeviceIndependentIntSize, DeviceIndependentPixel};
 use style_traits::CSSPixel;
+use versoview_messages::{
+    DeviceLostCause, DeviceLostEvent, EventQueue, FlightRecorderSettings, FramePresented,
+    PaintMetric, PaintMetricKind, PresentedWebView, RecordingReport, RecordingSettings,
+    RenderValidationReport, RenderValidationSettings, SharedTextureFrame, SharedTextureSettings,
+    SmoothScrolling, TextRendering,
+};
 use webrender::{RenderApi, Transaction};
 use webrender_api::units::{
-    DeviceIntPoint, DeviceIntRect, DevicePixel, DevicePoint, DeviceRect, DeviceSize, LayoutPoint,
-    LayoutRect, LayoutSize, LayoutVector2D, WorldPoint,
+    DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePixel, DevicePoint, DeviceRect, DeviceSize,
+    LayoutPoint, LayoutRect, LayoutSideOffsets, LayoutSize, LayoutVector2D, WorldPoint,
 };
 use webrender_api::{
-    BorderRadius, BoxShadowClipMode, BuiltDisplayList, ClipMode, ColorF, CommonItemProperties,
-    ComplexClipRegion, DirtyRect, DisplayListPayload, DocumentId, Epoch as WebRenderEpoch,
-    ExternalScrollId, FontInstanceFlags, FontInstanceKey, FontInstanceOptions, FontKey, HitTestFlags,
-    ImageKey, PipelineId as WebRenderPipelineId, PropertyBinding, ReferenceFrameKind, RenderReasons,
-    ImageDescriptor, ImageData,
-    SampledScrollOffset, ScrollLocation, SpaceAndClipInfo, SpatialId, SpatialTreeItemKey,
-    TransformStyle,
+    AlphaType, BoolParameter, BorderDetails, BorderRadius, BorderSide, BorderStyle,
+    BoxShadowClipMode, BuiltDisplayList, CaptureBits, ClipMode, ColorF, CommonItemProperties,
+    ComplexClipRegion, DebugCommand, DirtyRect, DisplayListPayload, DocumentId,
+    Epoch as WebRenderEpoch, ExternalScrollId, FilterOp, FontInstanceFlags, FontInstanceKey,
+    FontKey, HitTestFlags, ImageData, ImageDescriptor, ImageKey, ImageRendering, MemoryReport,
+    NormalBorder, Parameter, PipelineId as WebRenderPipelineId, PrimitiveFlags, PropertyBinding,
+    ReferenceFrameKind, RenderReasons, SampledScrollOffset, ScrollLocation, SpaceAndClipInfo,
+    SpatialId, SpatialTreeItemKey, TileSize, TransformStyle,
 };
 use winit::window::WindowId;
 
-use crate::rendering::RenderingContext;
-use crate::touch::{TouchAction, TouchHandler};
-use crate::window::Window;
+use crate::allocation_profiling::{self, AllocationScope};
+use crate::animated_images::{AnimatedImageScheduler, DecodedAnimation};
+use crate::compositor_text::{CompositorText, ShapingCache, TextRun};
+use crate::device_lost::DeviceLostWatchdog;
+use crate::display_list_transport::{DisplayListTransport, DisplayListTransportStats};
+use crate::drag_drop::DragSession;
+use crate::emoji;
+use crate::event_loop_monitor::{EventLoopMonitor, message_type};
 use crate::extended_compositor_msg::ExtendedCompositorMsg;
+use crate::external_layers::{
+    ExternalLayer, ExternalLayerId, ExternalLayerImages, ExternalLayers, ExternalTextureSource,
+};
+use crate::flight_recorder::{FlightRecorder, RecordedKind};
+use crate::frame_pacing::{
+    FramePacing, FramePacingConfig, detect_refresh_rate, monitor_refresh_rate,
+};
+use crate::frame_recording::FrameRecorder;
+use crate::memory_pressure::MemoryPressureMonitor;
+use crate::pinch_zoom::PinchZoom;
+use crate::readiness::FrameStability;
+use crate::render_validation::{RenderValidator, read_frame};
+use crate::renderer_options::RuntimeRendererOption;
+use crate::renderer_restart::{CreateRenderer, RetainedResources};
+use crate::rendering::{GpuIdentity, OffscreenFramebuffer, RenderingContext};
+use crate::screenshot::{self, ScreenshotQueue, ScreenshotRequest};
+use crate::scroll_sequence::{ScrollSequenceId, ScrollSequencer};
+use crate::shared_texture::SharedTextures;
+use crate::smooth_scroll::SmoothScroll;
+use crate::stats_hud::{self, ScrollCoalescing};
+use crate::text_rendering::TextRenderingPolicy;
+use crate::touch::{MouseToTouch, TouchAction, TouchHandler};
+use crate::window::Window;
+
+/// How many allocation profiling tags are included in memory reports
+const ALLOCATION_REPORT_SITES: usize = 16;
+/// Font size of the label under a dragging pointer
+const DRAG_LABEL_SIZE: f32 = 13.0;
+/// Offset of the drag label from the pointer
+const DRAG_LABEL_OFFSET: LayoutVector2D = LayoutVector2D::new(14.0, 18.0);
+/// Outline of the webview a drag is over
+const DRAG_OUTLINE_COLOR: ColorF = ColorF::new(0.2, 0.5, 1.0, 0.9);
+const DRAG_OUTLINE_WIDTH: f32 = 2.0;
+/// Font size of the HUD lines
+const HUD_TEXT_SIZE: f32 = 12.0;
+/// Distance of the HUD from the top right corner of the window
+const HUD_MARGIN: f32 = 10.0;
+/// Window id of a headless compositor's root pipeline, no winit window has it
+const HEADLESS_WINDOW_ID: u64 = 0;
+/// How often the refresh rate of the current window's monitor is checked again, displays
+/// switch rates without telling windows, like laptops changing power profile
+const REFRESH_RATE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// Smallest page zoom of a webview
+pub const MIN_PAGE_ZOOM: f32 = 0.25;
+/// Largest page zoom of a webview
+pub const MAX_PAGE_ZOOM: f32 = 5.0;
 
 /// Data used to construct a compositor.
 pub struct InitialCompositorState {
@@ -66,7 +128,7 @@ pub struct InitialCompositorState {
     pub mem_profiler_chan: mem::ProfilerChan,
     /// Instance of webrender API
     pub webrender: webrender::Renderer,
-    /// Webrender document ID
+    /// Webrender document ID of the first window
     pub webrender_document: DocumentId,
     /// Webrender API
     pub webrender_api: RenderApi,
@@ -74,18 +136,39 @@ pub struct InitialCompositorState {
     pub rendering_context: RenderingContext,
     /// Webrender GL handle
     pub webrender_gl: Rc<dyn gl::Gl>,
+    /// How text is anti-aliased and hinted
+    pub text_rendering: TextRenderingPolicy,
+    /// Whether to render color glyphs of color fonts
+    pub color_glyphs: bool,
+    /// What the flight recorder keeps and when it dumps
+    pub flight_recorder: FlightRecorderSettings,
+    /// Event loop iterations longer than this are logged as stalls, in milliseconds
+    pub stall_threshold_ms: Option<u64>,
+    /// When frames are compared with the software renderer
+    pub render_validation: RenderValidationSettings,
+    /// Smallest pinch zoom of a webview
+    pub min_pinch_zoom: f32,
+    /// Largest pinch zoom of a webview
+    pub max_pinch_zoom: f32,
+    /// How mouse wheel and keyboard scrolls are animated
+    pub smooth_scrolling: SmoothScrolling,
+    /// Whether WebRender redraws only what changed, presented as the damage of the frame
+    pub partial_present: bool,
+    /// Size of the tiles images larger than it are split into, `None` for WebRender's choice
+    pub image_tile_size: Option<u16>,
+    /// WebRender's handler for the textures of external layers
+    pub external_layer_images: ExternalLayerImages,
+    /// Registry allocating the external image ids of external layers
+    pub external_image_registry: Arc<std::sync::Mutex<WebrenderExternalImageRegistry>>,
+    /// Creates a renderer after a GPU reset lost the GL context, `None` to not restart it
+    pub create_renderer: Option<CreateRenderer>,
+    /// Composite the first window into textures shared with the controller instead of its
+    /// surface
+    pub shared_texture: Option<SharedTextureSettings>,
 }
 
-/// Various debug and profiling flags that WebRender supports.
-#[derive(Clone)]
-pub enum WebRenderDebugOption {
-    /// Set profiler flags to webrender.
-    Profiler,
-    /// Set texture cache flags to webrender.
-    TextureCacheDebug,
-    /// Set render target flags to webrender.
-    RenderTargetDebug,
-}
+/// Various debug and profiling flags that WebRender supports, shared with the controller.
+pub use versoview_messages::WebRenderDebugOption;
 
 /// Mouse event for the compositor.
 #[derive(Clone)]
@@ -112,8 +195,14 @@ pub struct IOCompositor {
     /// The pixel density of the display.
     scale_factor: Scale<f32, DeviceIndependentPixel, DevicePixel>,
 
-    /// The active webrender document.
-    webrender_document: DocumentId,
+    /// WebRender document of each window, so windows don't share one scene.
+    documents: HashMap<WindowId, WindowDocument>,
+
+    /// Window showing each webview, whose document gets the webview's display lists.
+    webview_windows: HashMap<WebViewId, WindowId>,
+
+    /// Windows whose document has a new frame while another window is current.
+    windows_to_redraw: HashSet<WindowId>,
 
     /// The port on which we receive messages.
     compositor_receiver: Receiver<CompositorMsg>,
@@ -130,6 +219,30 @@ pub struct IOCompositor {
     /// check if the surface is ready to present.
     pub ready_to_present: bool,
 
+    /// Window whose surface the frame ready to present was composited into.
+    pub presenting_window: Option<WindowId>,
+
+    /// Whether presented frames are kept for the controller, see [`Self::take_presented_frame`].
+    pub report_presented_frames: bool,
+
+    /// Frames presented so far.
+    frames_presented: u64,
+
+    /// Frame presented since the last [`Self::take_presented_frame`].
+    presented_frame: Option<FramePresented>,
+
+    /// Paint metrics sent to the constellation since the last [`Self::take_paint_metrics`].
+    paint_metrics: Vec<PaintMetric>,
+
+    /// Whether WebRender redraws only what changed since the back buffer was last drawn.
+    partial_present: bool,
+
+    /// Size of the tiles images larger than it are split into when added.
+    image_tile_size: Option<u16>,
+
+    /// Region WebRender redrew for the frame ready to present, `None` to present it whole.
+    present_damage: Option<Vec<DeviceIntRect>>,
+
     /// Tracks whether we are in the process of shutting down, or have shut down and should close
     /// the compositor.
     pub shutdown_state: ShutdownState,
@@ -149,6 +262,9 @@ pub struct IOCompositor {
     /// Pending scroll/zoom events.
     pending_scroll_zoom_events: Vec<ScrollZoomEvent>,
 
+    /// Orders scroll/zoom events and merges script scrolls with them.
+    scroll_sequencer: ScrollSequencer,
+
     /// Used by the logic that determines when it is safe to output an
     /// image for the reftest framework.
     ready_to_save_state: ReadyState,
@@ -171,11 +287,8 @@ pub struct IOCompositor {
     /// Current cursor position.
     cursor_pos: DevicePoint,
 
-    /// True to exit after page load ('-x').
-    wait_for_stable_image: bool,
-
-    /// True to translate mouse input into touch events.
-    convert_mouse_to_touch: bool,
+    /// Translates mouse input into touch events when on, see [`Self::set_mouse_to_touch`].
+    mouse_to_touch: MouseToTouch,
 
     /// The number of frames pending to receive from WebRender.
     pending_frames: usize,
@@ -189,6 +302,112 @@ pub struct IOCompositor {
     /// will want to avoid blocking on UI events, and just
     /// run the event loop at the vsync interval.
     pub is_animating: bool,
+
+    /// Used to shrink the text shaping cache when the system runs low on memory.
+    memory_pressure: MemoryPressureMonitor,
+
+    /// The GPU we last rendered on, to notice switches on dual-GPU machines.
+    gpu_identity: GpuIdentity,
+
+    /// GPU rendering moved to and not handled yet, see [`Self::take_gpu_switch`].
+    gpu_switch: Option<GpuIdentity>,
+
+    /// Checks every composite for a GPU driver reset.
+    device_lost_watchdog: DeviceLostWatchdog,
+
+    /// Cause of a GPU driver reset not handled yet, see [`Self::take_device_lost`].
+    device_lost: Option<DeviceLostCause>,
+
+    /// Creates the renderer in place of one lost with its GL context, see
+    /// [`Self::restart_renderer`].
+    create_renderer: Option<CreateRenderer>,
+
+    /// What a restarted renderer has to be given again, kept while restarts are possible.
+    retained_resources: Option<RetainedResources>,
+
+    /// Presentation timing, used to time smooth scrolls, pinch zoom resets and animated images.
+    frame_pacing: FramePacing,
+
+    /// Animated images played by the compositor.
+    animated_images: AnimatedImageScheduler,
+
+    /// When the refresh rate of the current window's monitor was last checked.
+    refresh_rate_checked: Instant,
+
+    /// How text is anti-aliased and hinted.
+    text_rendering: TextRenderingPolicy,
+
+    /// Font instances added by Servo, to add them again when the text rendering changes.
+    font_instances: HashMap<FontInstanceKey, (FontKey, f32, FontInstanceFlags)>,
+
+    /// Whether to render color glyphs of color fonts.
+    color_glyphs: bool,
+
+    /// Fonts with color glyph tables, rasterized with their embedded bitmaps.
+    color_fonts: HashSet<FontKey>,
+
+    /// System fonts found to have color glyph tables by the threads reading their files.
+    color_font_checks: (Sender<FontKey>, Receiver<FontKey>),
+
+    /// Recent input, compositor messages and frames, for debugging from the field.
+    pub flight_recorder: FlightRecorder,
+
+    /// Time spent per message type and stalls of the event loop.
+    pub event_loop_monitor: EventLoopMonitor,
+
+    /// Compares frames rendered by the GPU with the software renderer.
+    pub render_validation: RenderValidator,
+
+    /// Bytes and copies of the display lists received from script.
+    display_list_transport: DisplayListTransport,
+
+    /// Font and font instances of the text the compositor draws itself.
+    pub text: CompositorText,
+
+    /// Link, image or text being dragged between webviews.
+    pub drag: DragSession,
+
+    /// Whether the HUD with the GPU, viewport and devtools server is shown.
+    pub hud_visible: bool,
+
+    /// Whether the HUD shows the frame rate, dropped frames and scroll coalescing.
+    pub stats_hud_visible: bool,
+
+    /// When the stats in the HUD were last drawn.
+    stats_hud_drawn: Instant,
+
+    /// Scroll events received and the scrolls they were merged into.
+    scroll_coalescing: ScrollCoalescing,
+
+    /// Hashes of the composited frames while a page is waited on until it's stable.
+    pub frame_stability: FrameStability,
+
+    /// Screenshots waiting for their frame and those taken.
+    pub screenshots: ScreenshotQueue,
+
+    /// Recording of the composited frames in progress.
+    frame_recorder: Option<FrameRecorder>,
+
+    /// Textures of the embedder composited with the webviews.
+    external_layers: ExternalLayers,
+
+    /// Textures shared with the controller a window is composited into instead of its surface.
+    shared_textures: Option<SharedTextures>,
+
+    /// Whether each webview painted in a window can't be seen, see [`Self::update_occlusion`].
+    webview_occlusion: HashMap<WindowId, HashMap<WebViewId, bool>>,
+
+    /// Page zoom of the webviews not at 1.
+    page_zoom: HashMap<WebViewId, f32>,
+
+    /// Pinch zoom of the webviews and their animated resets.
+    pinch_zoom: PinchZoom,
+
+    /// Mouse wheel or keyboard scroll being animated.
+    smooth_scroll: SmoothScroll,
+
+    /// Whether the constellation last replied that every pipeline painted its latest epoch.
+    frame_tree_painted: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -199,17 +418,28 @@ struct ScrollEvent {
     cursor: DeviceIntPoint,
     /// The number of OS events that have been coalesced together into this one event.
     event_count: u32,
+    /// The sequence id of the last event coalesced into this one.
+    sequence: ScrollSequenceId,
 }
 
 #[derive(Clone, Copy)]
 enum ScrollZoomEvent {
     /// An pinch zoom event that magnifies the view by the given factor.
-    PinchZoom(f32),
+    PinchZoom(f32, ScrollSequenceId),
     /// A scroll event that scrolls the scroll node at the given location by the
     /// given amount.
     Scroll(ScrollEvent),
 }
 
+impl ScrollZoomEvent {
+    fn sequence(&self) -> ScrollSequenceId {
+        match self {
+            ScrollZoomEvent::PinchZoom(_, sequence) => *sequence,
+            ScrollZoomEvent::Scroll(scroll_event) => scroll_event.sequence,
+        }
+    }
+}
+
 /// Why we performed a composite. This is used for debugging.
 ///
 /// TODO: It would be good to have a bit more precision here about why a composite
@@ -225,6 +455,8 @@ enum CompositingReason {
     NewWebRenderFrame,
     /// The window has been resized and will need to be synchronously repainted.
     Resize,
+    /// A screenshot waits for the next frame.
+    Screenshot,
 }
 
 #[derive(Debug, PartialEq)]
@@ -320,6 +552,17 @@ impl PipelineResources {
     }
 }
 
+/// WebRender document of a window and the view it was last laid out for.
+#[derive(Clone, Copy, Debug)]
+struct WindowDocument {
+    /// The document.
+    id: DocumentId,
+    /// Size of the window's viewport.
+    viewport: DeviceSize,
+    /// The pixel density of the window's display.
+    scale_factor: Scale<f32, DeviceIndependentPixel, DevicePixel>,
+}
+
 struct PipelineDetails {
     /// The pipeline associated with this PipelineDetails object.
     pipeline: Option<CompositionPipeline>,
@@ -351,6 +594,9 @@ struct PipelineDetails {
     /// Resources that need compositor-side cleanup when a pipeline is removed.
     resources: PipelineResources,
 
+    /// Size in bytes of the most recent display list of this pipeline.
+    display_list_size: usize,
+
     /// The paint metric status of the first paint.
     pub first_paint_metric: PaintMetricState,
 
@@ -370,6 +616,7 @@ impl PipelineDetails {
             hit_test_items: Vec::new(),
             scroll_tree: ScrollTree::default(),
             resources: PipelineResources::default(),
+            display_list_size: 0,
             first_paint_metric: PaintMetricState::Waiting,
             first_contentful_paint_metric: PaintMetricState::Waiting,
         }
@@ -406,9 +653,12 @@ impl IOCompositor {
         viewport: DeviceSize,
         scale_factor: Scale<f32, DeviceIndependentPixel, DevicePixel>,
         state: InitialCompositorState,
-        wait_for_stable_image: bool,
         convert_mouse_to_touch: bool,
     ) -> Self {
+        let gpu_identity = state.rendering_context.gpu_identity();
+        let frame_pacing = FramePacing::new(
+            FramePacingConfig::default().with_present_mode(state.rendering_context.present_mode()),
+        );
         let compositor = IOCompositor {
             current_window,
             viewport,
@@ -419,24 +669,84 @@ impl IOCompositor {
             composition_request: CompositionRequest::NoCompositingNecessary,
             touch_handler: TouchHandler::new(),
             pending_scroll_zoom_events: Vec::new(),
+            scroll_sequencer: ScrollSequencer::default(),
             shutdown_state: ShutdownState::NotShuttingDown,
             frame_tree_id: FrameTreeId(0),
             constellation_chan: state.constellation_chan,
             time_profiler_chan: state.time_profiler_chan,
             ready_to_save_state: ReadyState::Unknown,
             webrender: Some(state.webrender),
-            webrender_document: state.webrender_document,
+            documents: HashMap::from([(
+                current_window,
+                WindowDocument {
+                    id: state.webrender_document,
+                    viewport,
+                    scale_factor,
+                },
+            )]),
+            webview_windows: HashMap::new(),
+            windows_to_redraw: HashSet::new(),
             webrender_api: state.webrender_api,
             rendering_context: state.rendering_context,
             webrender_gl: state.webrender_gl,
             cursor: Cursor::None,
             cursor_pos: DevicePoint::new(0.0, 0.0),
-            wait_for_stable_image,
-            convert_mouse_to_touch,
+            mouse_to_touch: MouseToTouch::new(convert_mouse_to_touch),
             pending_frames: 0,
             last_animation_tick: Instant::now(),
             is_animating: false,
             ready_to_present: false,
+            presenting_window: None,
+            report_presented_frames: false,
+            frames_presented: 0,
+            presented_frame: None,
+            paint_metrics: Vec::new(),
+            memory_pressure: MemoryPressureMonitor::default(),
+            gpu_identity,
+            gpu_switch: None,
+            device_lost_watchdog: DeviceLostWatchdog::default(),
+            device_lost: None,
+            retained_resources: state
+                .create_renderer
+                .as_ref()
+                .map(|_| RetainedResources::default()),
+            create_renderer: state.create_renderer,
+            frame_pacing,
+            animated_images: AnimatedImageScheduler::default(),
+            refresh_rate_checked: Instant::now(),
+            text_rendering: state.text_rendering,
+            font_instances: HashMap::new(),
+            color_glyphs: state.color_glyphs,
+            color_fonts: HashSet::new(),
+            color_font_checks: crossbeam_channel::unbounded(),
+            flight_recorder: FlightRecorder::new(state.flight_recorder),
+            event_loop_monitor: EventLoopMonitor::new(state.stall_threshold_ms),
+            render_validation: RenderValidator::new(state.render_validation),
+            display_list_transport: DisplayListTransport::default(),
+            text: CompositorText::default(),
+            drag: DragSession::default(),
+            hud_visible: false,
+            stats_hud_visible: false,
+            stats_hud_drawn: Instant::now(),
+            scroll_coalescing: ScrollCoalescing::default(),
+            frame_stability: FrameStability::default(),
+            screenshots: ScreenshotQueue::default(),
+            frame_recorder: None,
+            external_layers: ExternalLayers::new(
+                state.external_layer_images,
+                state.external_image_registry,
+            ),
+            shared_textures: state
+                .shared_texture
+                .map(|settings| SharedTextures::new(current_window, settings)),
+            webview_occlusion: HashMap::new(),
+            page_zoom: HashMap::new(),
+            pinch_zoom: PinchZoom::new(state.min_pinch_zoom, state.max_pinch_zoom),
+            smooth_scroll: SmoothScroll::new(state.smooth_scrolling),
+            partial_present: state.partial_present,
+            image_tile_size: state.image_tile_size,
+            present_damage: None,
+            frame_tree_painted: false,
         };
 
         // Make sure the GL state is OK
@@ -444,6 +754,26 @@ impl IOCompositor {
         compositor
     }
 
+    /// Create a compositor with no window, rendering into the offscreen framebuffer of a
+    /// [`RenderingContext::create_headless`] context.
+    ///
+    /// Show webviews with [`Self::send_headless_display_list`] and read the frames composited
+    /// by [`Self::perform_updates`] with [`Self::headless_frame`]. The flight recorder, render
+    /// validation and frame stability read the window framebuffer and see nothing.
+    pub fn new_headless(
+        scale_factor: Scale<f32, DeviceIndependentPixel, DevicePixel>,
+        state: InitialCompositorState,
+    ) -> Self {
+        let viewport = state.rendering_context.size2d().to_f32();
+        Self::new(
+            WindowId::from(HEADLESS_WINDOW_ID),
+            viewport,
+            scale_factor,
+            state,
+            false,
+        )
+    }
+
     /// Consume compositor itself and deinit webrender.
     pub fn deinit(&mut self) {
         if let Some(webrender) = self.webrender.take() {
@@ -451,6 +781,23 @@ impl IOCompositor {
         }
     }
 
+    /// Number of WebRender font, font instance and image keys held by live pipelines.
+    pub fn tracked_resource_count(&self) -> usize {
+        self.pipeline_details
+            .values()
+            .map(|details| {
+                details.resources.font_keys.len()
+                    + details.resources.font_instance_keys.len()
+                    + details.resources.image_keys.len()
+            })
+            .sum()
+    }
+
+    /// Get the display list transport statistics.
+    pub fn display_list_transport_stats(&self) -> &DisplayListTransportStats {
+        self.display_list_transport.stats()
+    }
+
     /// Get the current size of the rendering context.
     pub fn rendering_context_size(&self) -> Size2D<u32, DevicePixel> {
         self.rendering_context.size2d()
@@ -482,6 +829,232 @@ impl IOCompositor {
         }
     }
 
+    /// Release what can be rebuilt before the system sleeps.
+    ///
+    /// GPU caches are the first thing drivers invalidate across a sleep, so WebRender
+    /// is asked to drop them now instead of rendering from stale textures on resume.
+    pub fn on_system_suspend(&mut self) {
+        self.webrender_api.notify_memory_pressure();
+    }
+
+    /// Make the window render again after the system woke up.
+    ///
+    /// The GL context is checked against the window surface and the surface is recreated
+    /// when it became unusable. The animation clock restarts from now, and a new display
+    /// list and composite are requested so the window isn't left black.
+    pub fn on_system_resume(&mut self, window: &mut Window) {
+        if let Err(err) = self
+            .rendering_context
+            .make_gl_context_current(&window.surface)
+        {
+            warn!("GL context is not usable after resume ({err:?}), recreating the surface");
+            match self.rendering_context.create_surface(&window.window) {
+                Ok(surface) => {
+                    self.rendering_context.apply_present_mode(&surface);
+                    window.surface = surface;
+                }
+                Err(err) => {
+                    warn!("Failed to recreate the window surface after resume: {err:?}");
+                    return;
+                }
+            }
+        }
+
+        self.last_animation_tick = Instant::now();
+        self.frame_pacing.resync();
+        self.resize(window.size(), window);
+        self.composite_if_necessary(CompositingReason::Resize);
+    }
+
+    /// Check whether the GPU driving `window` changed, returns the new GPU if it did.
+    ///
+    /// winit has no notification for this, so it's called when a switch is likely: on
+    /// resume, on focus and when the window moves to a display with another scale
+    /// factor. Our context keeps reporting the GPU it was created on, so the GPU a new
+    /// context for the window would get is asked instead. The switch is handled with
+    /// [`Self::move_to_gpu`] once taken with [`Self::take_gpu_switch`].
+    pub fn check_gpu_switch(&mut self, window: &Window) -> Option<GpuIdentity> {
+        let probed = self.rendering_context.probe_gpu_identity(&window.window);
+        if let Err(err) = self
+            .rendering_context
+            .make_gl_context_current(&window.surface)
+        {
+            warn!("Failed to make GL context current: {:?}", err);
+        }
+        let gpu_identity = match probed {
+            Ok(gpu_identity) => gpu_identity,
+            Err(error) => {
+                warn!("Failed to ask which GPU renders the window: {error}");
+                return None;
+            }
+        };
+        if gpu_identity == self.gpu_identity {
+            return None;
+        }
+        warn!(
+            "GPU switched from {} to {}",
+            self.gpu_identity, gpu_identity
+        );
+        self.gpu_identity = gpu_identity.clone();
+        self.gpu_switch = Some(gpu_identity.clone());
+        Some(gpu_identity)
+    }
+
+    /// Take the GPU switch noticed by [`Self::check_gpu_switch`] and not handled yet.
+    pub fn take_gpu_switch(&mut self) -> Option<GpuIdentity> {
+        self.gpu_switch.take()
+    }
+
+    /// Render on the GPU a switch moved to.
+    ///
+    /// The renderer is restarted on a context of the new GPU if it can be, see
+    /// [`Self::can_restart_renderer`]. Otherwise the driver is left to migrate our
+    /// context, and WebRender's GPU caches are dropped so everything is uploaded again. Each window must then be made to render
+    /// again with [`Self::on_system_resume`].
+    pub fn move_to_gpu(
+        &mut self,
+        windows: &mut HashMap<WindowId, (Window, DocumentId)>,
+    ) -> Result<(), String> {
+        if self.can_restart_renderer() {
+            return self.restart_renderer(windows);
+        }
+        self.webrender_api.notify_memory_pressure();
+        Ok(())
+    }
+
+    /// Handle a GPU driver reset noticed while compositing, returns the event for the
+    /// controller.
+    ///
+    /// WebRender's GPU caches are dropped. If the context was lost with the reset, the renderer
+    /// is replaced with [`Self::restart_renderer`]. Each window must then be made to render
+    /// again with [`Self::on_system_resume`], which recreates its surface if the reset broke it.
+    pub fn take_device_lost(&mut self) -> Option<DeviceLostEvent> {
+        let cause = self.device_lost.take()?;
+        if self.can_restart_renderer() {
+            warn!("GPU driver reset ({cause:?}), restarting the renderer");
+        } else {
+            warn!("GPU driver reset ({cause:?}), freeing the renderer's caches");
+        }
+        self.webrender_api.notify_memory_pressure();
+        Some(DeviceLostEvent {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            cause,
+        })
+    }
+
+    /// Whether the renderer is replaced after a GPU reset, see [`Self::restart_renderer`].
+    pub fn can_restart_renderer(&self) -> bool {
+        self.create_renderer.is_some() && !self.rendering_context.is_headless()
+    }
+
+    /// Replace the GL context and the renderer lost on a GPU reset or left on the GPU before
+    /// a switch, see [`crate::renderer_restart`].
+    ///
+    /// Every window gets a surface of the new context and its document is added again under
+    /// the same id. The new renderer is given the fonts, images and display lists kept so far
+    /// with the scroll offsets of the compositor.
+    pub fn restart_renderer(
+        &mut self,
+        windows: &mut HashMap<WindowId, (Window, DocumentId)>,
+    ) -> Result<(), String> {
+        let Some(create_renderer) = &self.create_renderer else {
+            return Err("The renderer isn't restarted without a context lost on reset".into());
+        };
+        let first_window_id = if windows.contains_key(&self.current_window) {
+            self.current_window
+        } else {
+            *windows
+                .keys()
+                .next()
+                .ok_or("There's no window to render to")?
+        };
+
+        // The old renderer only frees what was in the lost context.
+        if let Some(webrender) = self.webrender.take() {
+            webrender.deinit();
+        }
+        if let Some(shared_textures) = self.shared_textures.as_mut() {
+            shared_textures.forget();
+        }
+        let (rendering_context, surface) = self
+            .rendering_context
+            .recreate(&windows[&first_window_id].0.window)
+            .map_err(|error| format!("Failed to create a GL context: {error}"))?;
+        let gl = rendering_context.gl.clone();
+        let (webrender, webrender_api_sender) = create_renderer(gl.clone())
+            .map_err(|error| format!("Failed to create a renderer: {error:?}"))?;
+        let webrender_api = webrender_api_sender.create_api();
+
+        let mut first_surface = Some(surface);
+        for (window_id, (window, document_id)) in windows.iter_mut() {
+            window.surface = match first_surface.take_if(|_| *window_id == first_window_id) {
+                Some(surface) => surface,
+                None => {
+                    let surface = rendering_context
+                        .create_surface(&window.window)
+                        .map_err(|error| format!("Failed to create a window surface: {error:?}"))?;
+                    rendering_context.apply_present_mode(&surface);
+                    surface
+                }
+            };
+            if let Some(document) = self.documents.get_mut(window_id) {
+                document.id = webrender_api
+                    .add_document_with_id(window.size().to_i32(), u64::from(*window_id) as u32);
+                *document_id = document.id;
+            }
+        }
+
+        self.webrender = Some(webrender);
+        self.webrender_api = webrender_api;
+        self.webrender_gl = gl;
+        self.rendering_context = rendering_context;
+        self.gpu_identity = self.rendering_context.gpu_identity();
+        self.device_lost_watchdog = DeviceLostWatchdog::default();
+        self.pending_frames = 0;
+
+        let mut transaction = Transaction::new();
+        if let Some(retained) = &self.retained_resources {
+            retained.replay_resources(&mut transaction, |desc| self.image_tiling(desc));
+        }
+        let text_font = self
+            .text
+            .font_key()
+            .and_then(|font_key| Some((font_key, self.text.font()?.data())));
+        if let Some((font_key, (data, index))) = text_font {
+            transaction.add_raw_font(font_key, data.to_vec(), index);
+        }
+        for (&instance_key, &(font_key, size, flags)) in &self.font_instances {
+            transaction.add_font_instance(
+                instance_key,
+                font_key,
+                size,
+                Some(self.text_rendering.font_instance_options(flags)),
+                self.text_rendering.platform_options(),
+                Vec::new(),
+            );
+        }
+        self.external_layers.replay(&mut transaction);
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
+
+        let display_lists: Vec<_> = self
+            .retained_resources
+            .iter()
+            .flat_map(|retained| retained.display_lists())
+            .collect();
+        for (pipeline_id, epoch, display_list) in display_lists {
+            let mut transaction = Transaction::new();
+            transaction.set_display_list(epoch, (pipeline_id, display_list));
+            self.update_transaction_with_all_scroll_offsets(&mut transaction);
+            self.webrender_api
+                .send_transaction(self.pipeline_document(pipeline_id.into()), transaction);
+        }
+        warn!("Renderer restarted on {}", self.gpu_identity);
+        Ok(())
+    }
+
     /// Tell compositor to start shutting down.
     pub fn maybe_start_shutting_down(&mut self) {
         if self.shutdown_state == ShutdownState::NotShuttingDown {
@@ -525,6 +1098,11 @@ impl IOCompositor {
         msg: CompositorMsg,
         windows: &mut HashMap<WindowId, (Window, DocumentId)>,
     ) -> bool {
+        let _allocation_scope = AllocationScope::enter("compositor.messages");
+        if self.flight_recorder.is_enabled() {
+            self.flight_recorder
+                .record(RecordedKind::Compositor, || format!("{msg:?}"));
+        }
         match self.shutdown_state {
             ShutdownState::NotShuttingDown => {}
             ShutdownState::ShuttingDown => {
@@ -538,27 +1116,7 @@ impl IOCompositor {
 
         match msg {
             CompositorMsg::CollectMemoryReport(sender) => {
-                let ops =
-                    wr_malloc_size_of::MallocSizeOfOps::new(servo_allocator::usable_size, None);
-                let report = self.webrender_api.report_memory(ops);
-                let reports = vec![
-                    Report {
-                        path: path!["webrender", "fonts"],
-                        kind: ReportKind::ExplicitJemallocHeapSize,
-                        size: report.fonts,
-                    },
-                    Report {
-                        path: path!["webrender", "images"],
-                        kind: ReportKind::ExplicitJemallocHeapSize,
-                        size: report.images,
-                    },
-                    Report {
-                        path: path!["webrender", "display-list"],
-                        kind: ReportKind::ExplicitJemallocHeapSize,
-                        size: report.display_list,
-                    },
-                ];
-                sender.send(ProcessReports::new(reports));
+                sender.send(ProcessReports::new(self.memory_reports()));
             }
 
             CompositorMsg::ChangeRunningAnimationsState(
@@ -594,7 +1152,8 @@ impl IOCompositor {
                     self.ready_to_save_state,
                     ReadyState::WaitingForConstellationReply
                 );
-                if is_ready && self.pending_frames == 0 {
+                self.frame_tree_painted = is_ready && self.pending_frames == 0;
+                if self.frame_tree_painted {
                     self.ready_to_save_state = ReadyState::ReadyToSaveImage;
                 } else {
                     self.ready_to_save_state = ReadyState::Unknown;
@@ -602,8 +1161,15 @@ impl IOCompositor {
                 self.composite_if_necessary(CompositingReason::Headless);
             }
 
-            CompositorMsg::SetThrottled(_webview_id, pipeline_id, throttled) => {
+            CompositorMsg::SetThrottled(webview_id, pipeline_id, throttled) => {
                 self.pipeline_details(pipeline_id).throttled = throttled;
+                if self.webviews.get(&webview_id) == Some(&pipeline_id) {
+                    self.animated_images.set_webview_throttled(
+                        webview_id,
+                        throttled,
+                        Instant::now(),
+                    );
+                }
                 self.process_animations(true);
             }
 
@@ -613,9 +1179,19 @@ impl IOCompositor {
                 let _ = sender.send(());
             }
 
-            CompositorMsg::NewWebRenderFrameReady(_document_id, recomposite_needed) => {
+            CompositorMsg::NewWebRenderFrameReady(document_id, recomposite_needed) => {
                 self.pending_frames -= 1;
 
+                if document_id != self.webrender_document() {
+                    // Another window's scene changed, it is composited once it may present.
+                    if let Some(window_id) = self.document_window(document_id) {
+                        if recomposite_needed || self.animation_callbacks_active() {
+                            self.windows_to_redraw.insert(window_id);
+                        }
+                    }
+                    return true;
+                }
+
                 if recomposite_needed {
                     if let Some(result) = self.hit_test_at_point(self.cursor_pos) {
                         self.update_cursor(self.cursor_pos, &result);
@@ -628,8 +1204,8 @@ impl IOCompositor {
             }
 
             CompositorMsg::LoadComplete(_) => {
-                // If we're painting in headless mode, schedule a recomposite.
-                if self.wait_for_stable_image {
+                // Pages waited on until they're stable are compared from their first composite.
+                if self.frame_stability.tracking {
                     self.composite_if_necessary(CompositingReason::Headless);
                 }
             }
@@ -661,43 +1237,54 @@ impl IOCompositor {
                 txn.set_display_list(WebRenderEpoch(0), (pipeline, Default::default()));
                 self.generate_frame(&mut txn, RenderReasons::SCENE);
                 self.webrender_api
-                    .send_transaction(self.webrender_document, txn);
+                    .send_transaction(self.pipeline_document(pipeline.into()), txn);
             }
 
-            CompositorMsg::SendScrollNode(_webview_id, pipeline_id, point, external_scroll_id) => {
+            CompositorMsg::SendScrollNode(webview_id, pipeline_id, point, external_scroll_id) => {
                 let pipeline_id = pipeline_id.into();
                 let pipeline_details = match self.pipeline_details.get_mut(&pipeline_id) {
                     Some(details) => details,
                     None => return true, // TODO: remove return true after we adapt to api based embder
                 };
 
-                let offset = LayoutVector2D::new(point.x, point.y);
+                // The script may not have seen the latest user scrolls of the node yet.
+                let script_offset = -LayoutVector2D::new(point.x, point.y);
+                let (merged_offset, sequence) = self.scroll_sequencer.merge_script_scroll(
+                    pipeline_id,
+                    external_scroll_id,
+                    script_offset,
+                    Instant::now(),
+                );
                 if !pipeline_details
                     .scroll_tree
                     .set_scroll_offsets_for_node_with_external_scroll_id(
                         external_scroll_id,
-                        -offset,
+                        merged_offset,
                     )
                 {
                     warn!("Could not scroll not with id: {external_scroll_id:?}");
                     return true; // TODO: remove return true after we adapt to api based embder
                 }
+                if merged_offset != script_offset {
+                    self.send_scroll_positions_to_layout_for_pipeline(&pipeline_id);
+                }
+                self.scroll_sequencer.set_sampled(sequence);
 
                 let mut txn = Transaction::new();
                 txn.set_scroll_offsets(
                     external_scroll_id,
                     vec![SampledScrollOffset {
-                        offset,
-                        generation: 0,
+                        offset: -merged_offset,
+                        generation: sequence.0,
                     }],
                 );
                 self.generate_frame(&mut txn, RenderReasons::APZ);
                 self.webrender_api
-                    .send_transaction(self.webrender_document, txn);
+                    .send_transaction(self.webview_document(webview_id), txn);
             }
 
             CompositorMsg::SendDisplayList {
-                webview_id: _,
+                webview_id,
                 display_list_descriptor,
                 display_list_receiver,
             } => {
@@ -719,7 +1306,7 @@ impl IOCompositor {
                             return true;
                         }
                     };
-                let items_data = match display_list_receiver.recv() {
+                let items_data = match self.display_list_transport.receive(&display_list_receiver) {
                     Ok(display_list_data) => display_list_data,
                     Err(error) => {
                         // TODO: remove return true after we adapt to api based embder
@@ -727,7 +1314,7 @@ impl IOCompositor {
                         return true;
                     }
                 };
-                let cache_data = match display_list_receiver.recv() {
+                let cache_data = match self.display_list_transport.receive(&display_list_receiver) {
                     Ok(display_list_data) => display_list_data,
                     Err(error) => {
                         // TODO: remove return true after we adapt to api based embder
@@ -735,7 +1322,8 @@ impl IOCompositor {
                         return true;
                     }
                 };
-                let spatial_tree = match display_list_receiver.recv() {
+                let spatial_tree = match self.display_list_transport.receive(&display_list_receiver)
+                {
                     Ok(display_list_data) => display_list_data,
                     Err(error) => {
                         // TODO: remove return true after we adapt to api based embder
@@ -743,18 +1331,39 @@ impl IOCompositor {
                         return true;
                     }
                 };
-                let built_display_list = BuiltDisplayList::from_data(
-                    DisplayListPayload {
-                        items_data,
-                        cache_data,
-                        spatial_tree,
-                    },
-                    display_list_descriptor,
-                );
+                let received_items = (items_data.as_ptr(), items_data.len());
+                let display_list_size = items_data.len() + cache_data.len() + spatial_tree.len();
+                let payload = DisplayListPayload {
+                    items_data,
+                    cache_data,
+                    spatial_tree,
+                };
+                if let Some(retained) = &mut self.retained_resources {
+                    retained.set_display_list(
+                        display_list_info.pipeline_id,
+                        display_list_info.epoch,
+                        display_list_descriptor,
+                        &payload,
+                    );
+                }
+                let built_display_list =
+                    BuiltDisplayList::from_data(payload, display_list_descriptor);
+                self.display_list_transport
+                    .built(received_items, built_display_list.items_data());
 
                 let pipeline_id = display_list_info.pipeline_id;
+                self.scroll_sequencer.acknowledge(pipeline_id.into());
+                if let Some(webview_id) = self
+                    .webviews
+                    .iter()
+                    .find(|(_, root_pipeline_id)| **root_pipeline_id == pipeline_id.into())
+                    .map(|(webview_id, _)| *webview_id)
+                {
+                    self.screenshots.painted(webview_id);
+                }
                 let details = self.pipeline_details(pipeline_id.into());
                 details.most_recent_display_list_epoch = Some(display_list_info.epoch);
+                details.display_list_size = display_list_size;
                 details.hit_test_items = display_list_info.hit_test_info;
                 details.install_new_scroll_tree(display_list_info.scroll_tree);
 
@@ -776,7 +1385,7 @@ impl IOCompositor {
                 self.update_transaction_with_all_scroll_offsets(&mut transaction);
                 self.generate_frame(&mut transaction, RenderReasons::SCENE);
                 self.webrender_api
-                    .send_transaction(self.webrender_document, transaction);
+                    .send_transaction(self.webview_document(webview_id), transaction);
             }
 
             CompositorMsg::HitTest(pipeline, point, flags, sender) => {
@@ -805,16 +1414,33 @@ impl IOCompositor {
                 for update in updates {
                     match update {
                         ImageUpdate::AddImage(key, desc, data) => {
-                            txn.add_image(key, desc, data.into(), None)
+                            let data = data.into();
+                            if let Some(retained) = &mut self.retained_resources {
+                                retained.set_image(key, desc, &data);
+                            }
+                            self.animated_images
+                                .on_image_added(key, desc, &data, Instant::now());
+                            let tiling = self.image_tiling(&desc);
+                            txn.add_image(key, desc, data, tiling)
+                        }
+                        ImageUpdate::DeleteImage(key) => {
+                            self.animated_images.remove(key);
+                            if let Some(retained) = &mut self.retained_resources {
+                                retained.delete_image(key);
+                            }
+                            txn.delete_image(key)
                         }
-                        ImageUpdate::DeleteImage(key) => txn.delete_image(key),
                         ImageUpdate::UpdateImage(key, desc, data) => {
-                            txn.update_image(key, desc, data.into(), &DirtyRect::All)
+                            let data = data.into();
+                            if let Some(retained) = &mut self.retained_resources {
+                                retained.set_image(key, desc, &data);
+                            }
+                            txn.update_image(key, desc, data, &DirtyRect::All)
                         }
                     }
                 }
                 self.webrender_api
-                    .send_transaction(self.webrender_document, txn);
+                    .send_transaction(self.webrender_document(), txn);
             }
 
             CompositorMsg::AddFont(font_key, data, index) => {
@@ -823,10 +1449,15 @@ impl IOCompositor {
             }
 
             CompositorMsg::AddSystemFont(font_key, native_handle) => {
+                #[cfg(linux)]
+                self.check_color_font(font_key, &native_handle);
+                if let Some(retained) = &mut self.retained_resources {
+                    retained.add_native_font(font_key, native_handle.clone());
+                }
                 let mut transaction = Transaction::new();
                 transaction.add_native_font(font_key, native_handle);
                 self.webrender_api
-                    .send_transaction(self.webrender_document, transaction);
+                    .send_transaction(self.webrender_document(), transaction);
             }
 
             CompositorMsg::AddFontInstance(font_instance_key, font_key, size, flags) => {
@@ -838,14 +1469,23 @@ impl IOCompositor {
                 let mut transaction = Transaction::new();
 
                 for instance in instance_keys.into_iter() {
+                    self.font_instances.remove(&instance);
+                    ShapingCache::shared()
+                        .lock()
+                        .unwrap()
+                        .remove_font_instance(instance);
                     transaction.delete_font_instance(instance);
                 }
                 for key in keys.into_iter() {
+                    self.color_fonts.remove(&key);
+                    if let Some(retained) = &mut self.retained_resources {
+                        retained.delete_font(key);
+                    }
                     transaction.delete_font(key);
                 }
 
                 self.webrender_api
-                    .send_transaction(self.webrender_document, transaction);
+                    .send_transaction(self.webrender_document(), transaction);
             }
 
             CompositorMsg::AddImage(key, desc, data) => {
@@ -1004,15 +1644,205 @@ impl IOCompositor {
             .expect("Insert then get failed!")
     }
 
+    /// Draw the external layers of the window under the webviews or over them.
+    fn push_external_layers(
+        &self,
+        builder: &mut webrender::api::DisplayListBuilder,
+        window_id: WindowId,
+        below: bool,
+        space: &SpaceAndClipInfo,
+    ) {
+        let zoom_factor = self.device_pixels_per_page_pixel().0;
+        for (layer, image_key) in self.external_layers.layers(window_id, below) {
+            let rect = LayoutRect::from_untyped(&(layer.rect / zoom_factor).to_untyped());
+            builder.push_image(
+                &CommonItemProperties::new(rect, *space),
+                rect,
+                ImageRendering::Auto,
+                AlphaType::PremultipliedAlpha,
+                image_key,
+                ColorF::WHITE,
+            );
+        }
+    }
+
+    /// Composite a texture of the embedder in the window, see [`ExternalTextureSource`].
+    pub fn add_external_layer(
+        &mut self,
+        window: &Window,
+        source: Box<dyn ExternalTextureSource>,
+        texture_size: DeviceIntSize,
+        layer: ExternalLayer,
+    ) -> ExternalLayerId {
+        let mut transaction = Transaction::new();
+        let image_key = self.webrender_api.generate_image_key();
+        let id = self.external_layers.add(
+            &mut transaction,
+            image_key,
+            window.id(),
+            source,
+            texture_size,
+            layer,
+        );
+        self.webrender_api
+            .send_transaction(self.window_document(window.id()), transaction);
+        self.send_root_pipeline_display_list(window);
+        id
+    }
+
+    /// Move or reorder an external layer.
+    pub fn set_external_layer(
+        &mut self,
+        id: ExternalLayerId,
+        layer: ExternalLayer,
+        windows: &HashMap<WindowId, (Window, DocumentId)>,
+    ) {
+        match self.external_layers.set(id, layer) {
+            Some(window_id) => {
+                if let Some((window, _)) = windows.get(&window_id) {
+                    self.send_root_pipeline_display_list(window);
+                }
+            }
+            None => warn!("Can't move the removed external layer {id:?}"),
+        }
+    }
+
+    /// Draw the new contents or size of an external layer's texture.
+    pub fn external_texture_changed(&mut self, id: ExternalLayerId, texture_size: DeviceIntSize) {
+        let mut transaction = Transaction::new();
+        let Some(window_id) =
+            self.external_layers
+                .texture_changed(&mut transaction, id, texture_size)
+        else {
+            warn!("Can't update the removed external layer {id:?}");
+            return;
+        };
+        self.generate_frame(&mut transaction, RenderReasons::SCENE);
+        self.webrender_api
+            .send_transaction(self.window_document(window_id), transaction);
+    }
+
+    /// Stop compositing an external layer.
+    pub fn remove_external_layer(
+        &mut self,
+        id: ExternalLayerId,
+        windows: &HashMap<WindowId, (Window, DocumentId)>,
+    ) {
+        let mut transaction = Transaction::new();
+        let window_id = self.external_layers.remove(&mut transaction, id);
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
+        if let Some((window, _)) = window_id.and_then(|window_id| windows.get(&window_id)) {
+            self.send_root_pipeline_display_list(window);
+        }
+    }
+
+    /// Throttle the webviews painted in the window that can't be seen, because the window is
+    /// hidden or a tab is painted over them, and resume those exposed again.
+    ///
+    /// Occluded webviews are left out of the root display list, and their pipelines stop
+    /// animating here and in script until they're exposed. Only tabs hide what is under them:
+    /// prompts and menus are painted over their tab with a translucent backdrop.
+    fn update_occlusion(&mut self, window: &Window) {
+        let hidden = window.occluded || window.size().is_empty();
+        // Decorated webviews have rounded corners, which show the corners of a larger webview.
+        let decorated = window.panel.is_some();
+        let painting_order = window.painting_order();
+        let occlusion: HashMap<WebViewId, bool> = painting_order
+            .iter()
+            .enumerate()
+            .map(|(index, webview)| {
+                let covered = painting_order[index + 1..].iter().any(|cover| {
+                    window.tab_manager.tab(cover.webview_id).is_some()
+                        && window.webview_opacity(cover.webview_id) >= 1.0
+                        && self.pinch_zoom.zoom(cover.webview_id) >= 1.0
+                        && if decorated {
+                            cover.rect == webview.rect
+                        } else {
+                            cover.rect.contains_box(&webview.rect)
+                        }
+                });
+                (
+                    webview.webview_id,
+                    hidden || webview.rect.is_empty() || covered,
+                )
+            })
+            .collect();
+        let previous = self
+            .webview_occlusion
+            .insert(window.id(), occlusion.clone())
+            .unwrap_or_default();
+        for (webview_id, occluded) in occlusion {
+            // Webviews no longer painted were throttled by the window, those painted again
+            // get their state whether it changed or not.
+            if previous.get(&webview_id) != Some(&occluded) {
+                self.set_webview_throttled(webview_id, occluded);
+            }
+        }
+    }
+
+    /// Whether the webview is painted in the window but can't be seen.
+    pub fn is_occluded(&self, window_id: WindowId, webview_id: WebViewId) -> bool {
+        self.webview_occlusion
+            .get(&window_id)
+            .and_then(|occlusion| occlusion.get(&webview_id))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Throttle or resume the pipelines of a webview, right away here and in script through the
+    /// constellation.
+    fn set_webview_throttled(&mut self, webview_id: WebViewId, throttled: bool) {
+        for details in self.pipeline_details.values_mut() {
+            if details
+                .pipeline
+                .as_ref()
+                .is_some_and(|pipeline| pipeline.webview_id == webview_id)
+            {
+                details.throttled = throttled;
+            }
+        }
+        let message = EmbedderToConstellationMessage::SetWebViewThrottled(webview_id, throttled);
+        if let Err(error) = self.constellation_chan.send(message) {
+            warn!("Sending SetWebViewThrottled to constellation failed ({error:?}).");
+        }
+        if !throttled {
+            self.process_animations(true);
+        }
+    }
+
     /// Set the root pipeline for our WebRender scene to a display list that consists of an iframe
     /// for each visible top-level browsing context, applying a transformation on the root for
     /// pinch zoom, page zoom, and HiDPI scaling.
     pub fn send_root_pipeline_display_list(&mut self, window: &Window) {
+        let _allocation_scope = AllocationScope::enter("compositor.display_list");
+        self.update_occlusion(window);
         let mut transaction = Transaction::new();
-        self.send_root_pipeline_display_list_in_transaction(&mut transaction, window);
+        let drag_label = self
+            .drag
+            .dragging(window.id())
+            .map(|drag| drag.data.label())
+            .and_then(|label| self.text_run(&mut transaction, &label, DRAG_LABEL_SIZE));
+        if self.stats_hud_visible {
+            self.stats_hud_drawn = Instant::now();
+        }
+        let hud = if self.hud_visible || self.stats_hud_visible {
+            self.hud_lines(window)
+                .iter()
+                .filter_map(|line| self.text_run(&mut transaction, line, HUD_TEXT_SIZE))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.send_root_pipeline_display_list_in_transaction(
+            &mut transaction,
+            window,
+            drag_label,
+            hud,
+        );
         self.generate_frame(&mut transaction, RenderReasons::SCENE);
         self.webrender_api
-            .send_transaction(self.webrender_document, transaction);
+            .send_transaction(self.window_document(window.id()), transaction);
     }
 
     /// Set the root pipeline for our WebRender scene to a display list that consists of an iframe
@@ -1022,11 +1852,13 @@ impl IOCompositor {
         &self,
         transaction: &mut Transaction,
         window: &Window,
+        drag_label: Option<TextRun>,
+        hud: Vec<TextRun>,
     ) {
         // Every display list needs a pipeline, but we'd like to choose one that is unlikely
         // to conflict with our content pipelines, which start at (1, 1). (0, 0) is WebRender's
         // dummy pipeline, so we choose (0, 1).
-        let root_pipeline = WebRenderPipelineId(u64::from(self.current_window) as u32, 1);
+        let root_pipeline = WebRenderPipelineId(u64::from(window.id()) as u32, 1);
         transaction.set_root_pipeline(root_pipeline);
 
         let mut builder = webrender::api::DisplayListBuilder::new(root_pipeline);
@@ -1046,7 +1878,7 @@ impl IOCompositor {
             SpatialTreeItemKey::new(0, 0),
         );
 
-        let viewport_size = self.rendering_context.size2d().to_f32().to_untyped();
+        let viewport_size = self.scene_size().to_untyped();
         let viewport_rect = LayoutRect::from_origin_and_size(
             LayoutPoint::zero(),
             LayoutSize::from_untyped(viewport_size),
@@ -1054,9 +1886,17 @@ impl IOCompositor {
 
         let root_clip_id = builder.define_clip_rect(zoom_reference_frame, viewport_rect);
         let root_clip_chain_id = builder.define_clip_chain(None, [root_clip_id]);
+        let root_space = SpaceAndClipInfo {
+            spatial_id: zoom_reference_frame,
+            clip_chain_id: root_clip_chain_id,
+        };
+        self.push_external_layers(&mut builder, window.id(), true, &root_space);
         // Only decorate the webviews if we're in the browser mode
         let should_decorate = window.panel.is_some();
         for webview in window.painting_order() {
+            if self.is_occluded(window.id(), webview.webview_id) {
+                continue;
+            }
             if let Some(pipeline_id) = self.webviews.get(&webview.webview_id) {
                 let scaled_webview_rect =
                     LayoutRect::from_untyped(&(webview.rect.to_f32() / zoom_factor).to_untyped());
@@ -1080,14 +1920,72 @@ impl IOCompositor {
                     }
                 };
 
-                builder.push_iframe(
-                    scaled_webview_rect,
-                    scaled_webview_rect,
-                    &root_space_and_clip,
-                    pipeline_id.into(),
-                    true,
-                );
-
+                // Playlist pages fading in are painted translucent over the previous one.
+                let opacity = window.webview_opacity(webview.webview_id);
+                if opacity < 1.0 {
+                    builder.push_simple_stacking_context_with_filters(
+                        LayoutPoint::zero(),
+                        zoom_reference_frame,
+                        PrimitiveFlags::empty(),
+                        &[FilterOp::Opacity(PropertyBinding::Value(opacity), opacity)],
+                        &[],
+                        &[],
+                    );
+                }
+
+                // A zoomed page is laid out in a viewport smaller or larger by its zoom and scaled
+                // to the webview. A pinched webview is scaled further around its center and
+                // clipped to its rect.
+                let page_zoom = self.page_zoom(webview.webview_id);
+                let pinch_zoom = self.pinch_zoom.zoom(webview.webview_id);
+                let zoomed = page_zoom != 1.0 || pinch_zoom != 1.0;
+                let (iframe_rect, iframe_space_and_clip) = if zoomed {
+                    let WebRenderPipelineId(namespace, index) = (*pipeline_id).into();
+                    let center = scaled_webview_rect.size().to_vector() / 2.0;
+                    let scale = page_zoom * pinch_zoom;
+                    let spatial_id = builder.push_reference_frame(
+                        scaled_webview_rect.min + center * (1.0 - pinch_zoom),
+                        zoom_reference_frame,
+                        TransformStyle::Flat,
+                        PropertyBinding::Value(Transform3D::scale(scale, scale, 1.)),
+                        ReferenceFrameKind::Transform {
+                            is_2d_scale_translation: true,
+                            should_snap: true,
+                            paired_with_perspective: false,
+                        },
+                        SpatialTreeItemKey::new(1, (u64::from(namespace) << 32) | u64::from(index)),
+                    );
+                    let clip_id =
+                        builder.define_clip_rect(zoom_reference_frame, scaled_webview_rect);
+                    let clip_chain_id = builder
+                        .define_clip_chain(Some(root_space_and_clip.clip_chain_id), [clip_id]);
+                    (
+                        LayoutRect::from_size(scaled_webview_rect.size() / page_zoom),
+                        SpaceAndClipInfo {
+                            spatial_id,
+                            clip_chain_id,
+                        },
+                    )
+                } else {
+                    (scaled_webview_rect, root_space_and_clip)
+                };
+
+                builder.push_iframe(
+                    iframe_rect,
+                    iframe_rect,
+                    &iframe_space_and_clip,
+                    pipeline_id.into(),
+                    true,
+                );
+
+                if zoomed {
+                    builder.pop_reference_frame();
+                }
+
+                if opacity < 1.0 {
+                    builder.pop_stacking_context();
+                }
+
                 if should_decorate {
                     let root_space = SpaceAndClipInfo {
                         spatial_id: zoom_reference_frame,
@@ -1113,6 +2011,74 @@ impl IOCompositor {
             }
         }
 
+        self.push_external_layers(&mut builder, window.id(), false, &root_space);
+
+        if let Some(drag) = self.drag.dragging(window.id()) {
+            // Outline the webview the drag is over.
+            if let Some(target) = window
+                .painting_order()
+                .into_iter()
+                .find(|webview| Some(webview.webview_id) == drag.target)
+            {
+                let rect =
+                    LayoutRect::from_untyped(&(target.rect.to_f32() / zoom_factor).to_untyped());
+                let side = BorderSide {
+                    color: DRAG_OUTLINE_COLOR,
+                    style: BorderStyle::Solid,
+                };
+                builder.push_border(
+                    &CommonItemProperties::new(rect, root_space),
+                    rect,
+                    LayoutSideOffsets::new_all_same(DRAG_OUTLINE_WIDTH),
+                    BorderDetails::Normal(NormalBorder {
+                        left: side,
+                        right: side,
+                        top: side,
+                        bottom: side,
+                        radius: BorderRadius::zero(),
+                        do_aa: true,
+                    }),
+                );
+            }
+            // Label what is dragged next to the pointer.
+            if let Some(run) = drag_label {
+                let origin = LayoutPoint::new(
+                    drag.point.x / zoom_factor + DRAG_LABEL_OFFSET.x,
+                    drag.point.y / zoom_factor + DRAG_LABEL_OFFSET.y,
+                );
+                let background =
+                    LayoutRect::from_origin_and_size(origin, run.size()).inflate(6., 3.);
+                builder.push_rect(
+                    &CommonItemProperties::new(background, root_space),
+                    background,
+                    ColorF::new(0.1, 0.1, 0.1, 0.85),
+                );
+                run.push(&mut builder, &root_space, origin, ColorF::WHITE);
+            }
+        }
+
+        // Stack the HUD lines in the top right corner.
+        if !hud.is_empty() {
+            let width = hud.iter().map(|run| run.size().width).fold(0., f32::max);
+            let height = hud.iter().map(|run| run.size().height).sum();
+            let mut origin = LayoutPoint::new(
+                viewport_size.width / zoom_factor - width - HUD_MARGIN,
+                HUD_MARGIN,
+            );
+            let background =
+                LayoutRect::from_origin_and_size(origin, LayoutSize::new(width, height))
+                    .inflate(6., 3.);
+            builder.push_rect(
+                &CommonItemProperties::new(background, root_space),
+                background,
+                ColorF::new(0.1, 0.1, 0.1, 0.85),
+            );
+            for run in &hud {
+                run.push(&mut builder, &root_space, origin, ColorF::WHITE);
+                origin.y += run.size().height;
+            }
+        }
+
         let built_display_list = builder.end();
 
         // NB: We are always passing 0 as the epoch here, but this doesn't seem to
@@ -1142,7 +2108,7 @@ impl IOCompositor {
                     external_id,
                     vec![SampledScrollOffset {
                         offset,
-                        generation: 0,
+                        generation: self.scroll_sequencer.sampled().0,
                     }],
                 );
             }
@@ -1164,7 +2130,12 @@ impl IOCompositor {
             debug!("{webview_id}'s pipeline has changed from {old_pipeline} to {pipeline_id}");
         }
 
-        if let Some((window, _)) = windows.get(&self.current_window) {
+        let window_id = windows
+            .values()
+            .find(|(window, _)| window.has_webview(webview_id))
+            .map_or(self.current_window, |(window, _)| window.id());
+        self.webview_windows.insert(webview_id, window_id);
+        if let Some((window, _)) = windows.get(&window_id) {
             self.send_root_pipeline_display_list(window);
         }
         self.create_or_update_pipeline_details_with_frame_tree(frame_tree, None);
@@ -1186,6 +2157,10 @@ impl IOCompositor {
                 if let Some(pipeline_id) = self.webviews.remove(&webview.webview_id) {
                     self.remove_pipeline_details_recursively(pipeline_id);
                 }
+                self.page_zoom.remove(&webview.webview_id);
+                self.pinch_zoom.remove(webview.webview_id);
+                self.animated_images.remove_webview(webview.webview_id);
+                self.webview_windows.remove(&webview.webview_id);
 
                 if close_window {
                     window_id = Some(window.id());
@@ -1202,6 +2177,7 @@ impl IOCompositor {
 
         if let Some(id) = window_id {
             windows.remove(&id);
+            self.remove_window_document(id);
         }
     }
 
@@ -1216,8 +2192,12 @@ impl IOCompositor {
         webview_id: WebViewId,
     ) {
         // The device pixel ratio used by the style system should include the scale from page pixels
-        // to device pixels, but not including any pinch zoom.
-        let hidpi_scale_factor = self.device_pixels_per_page_pixel_not_including_page_zoom();
+        // to device pixels and the page zoom of the webview, but not including any pinch zoom.
+        let hidpi_scale_factor = Scale::new(
+            self.device_pixels_per_page_pixel_not_including_page_zoom()
+                .get()
+                * self.page_zoom(webview_id),
+        );
         let size = rect.size().to_f32() / hidpi_scale_factor;
         let msg = EmbedderToConstellationMessage::ChangeViewportDetails(
             webview_id,
@@ -1273,10 +2253,24 @@ impl IOCompositor {
 
     fn remove_pipeline_details_recursively(&mut self, pipeline_id: PipelineId) {
         if let Some(details) = self.pipeline_details.remove(&pipeline_id) {
+            for key in &details.resources.font_instance_keys {
+                self.font_instances.remove(key);
+                ShapingCache::shared()
+                    .lock()
+                    .unwrap()
+                    .remove_font_instance(*key);
+            }
+            for key in &details.resources.font_keys {
+                self.color_fonts.remove(key);
+            }
+            if let Some(retained) = &mut self.retained_resources {
+                details.resources.clear(retained);
+                retained.remove_pipeline(pipeline_id.into());
+            }
             let mut txn = Transaction::new();
             details.resources.clear(&mut TransactionWrapper(&mut txn));
             self.webrender_api
-                .send_transaction(self.webrender_document, txn);
+                .send_transaction(self.webrender_document(), txn);
         }
 
         let children = self
@@ -1295,10 +2289,24 @@ impl IOCompositor {
 
     fn remove_pipeline_root_layer(&mut self, pipeline_id: PipelineId) {
         if let Some(details) = self.pipeline_details.remove(&pipeline_id) {
+            for key in &details.resources.font_instance_keys {
+                self.font_instances.remove(key);
+                ShapingCache::shared()
+                    .lock()
+                    .unwrap()
+                    .remove_font_instance(*key);
+            }
+            for key in &details.resources.font_keys {
+                self.color_fonts.remove(key);
+            }
+            if let Some(retained) = &mut self.retained_resources {
+                details.resources.clear(retained);
+                retained.remove_pipeline(pipeline_id.into());
+            }
             let mut txn = Transaction::new();
             details.resources.clear(&mut TransactionWrapper(&mut txn));
             self.webrender_api
-                .send_transaction(self.webrender_document, txn);
+                .send_transaction(self.webrender_document(), txn);
         }
     }
 
@@ -1310,9 +2318,123 @@ impl IOCompositor {
                 self.current_window,
                 window.id()
             );
+            let scale_factor = Scale::new(window.scale_factor() as f32);
+            let laid_out = self.documents.get(&window.id()).is_some_and(|document| {
+                document.viewport == window.size() && document.scale_factor == scale_factor
+            });
             self.current_window = window.id();
-            self.scale_factor = Scale::new(window.scale_factor() as f32);
-            self.resize(window.size(), window);
+            self.scale_factor = scale_factor;
+            self.update_refresh_rate(window);
+            if laid_out {
+                // The window's document still has the scene of its size and scale.
+                self.viewport = window.size();
+                self.composite_if_necessary(CompositingReason::Resize);
+            } else {
+                self.resize(window.size(), window);
+            }
+        }
+    }
+
+    /// Pace composites to the refresh rate of the monitor the window is on, 60Hz if it's unknown.
+    ///
+    /// Only the current window paces composites, others are ignored.
+    pub fn update_refresh_rate(&mut self, window: &Window) {
+        if window.id() != self.current_window {
+            return;
+        }
+        self.refresh_rate_checked = Instant::now();
+        let millihertz = window
+            .window
+            .current_monitor()
+            .as_ref()
+            .and_then(monitor_refresh_rate);
+        let refresh_rate = detect_refresh_rate(millihertz);
+        if refresh_rate != self.frame_pacing.target_refresh_rate() {
+            self.frame_pacing.set_target_refresh_rate(refresh_rate);
+        }
+    }
+
+    /// Create the WebRender document of a new window, laid out when the window becomes current.
+    pub fn add_window_document(&mut self, window: &Window) -> DocumentId {
+        let id = self
+            .webrender_api
+            .add_document_with_id(window.size().to_i32(), u64::from(window.id()) as u32);
+        self.documents.insert(
+            window.id(),
+            WindowDocument {
+                id,
+                viewport: DeviceSize::zero(),
+                scale_factor: Scale::new(window.scale_factor() as f32),
+            },
+        );
+        id
+    }
+
+    /// Delete the WebRender document of a closed window.
+    fn remove_window_document(&mut self, window_id: WindowId) {
+        let mut transaction = Transaction::new();
+        self.external_layers
+            .remove_window(&mut transaction, window_id);
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
+        self.windows_to_redraw.remove(&window_id);
+        self.webview_occlusion.remove(&window_id);
+        self.webview_windows
+            .retain(|_, webview_window| *webview_window != window_id);
+        if let Some(document) = self.documents.remove(&window_id) {
+            self.webrender_api.delete_document(document.id);
+        }
+    }
+
+    /// WebRender document of the current window.
+    fn webrender_document(&self) -> DocumentId {
+        self.documents
+            .get(&self.current_window)
+            .or_else(|| self.documents.values().next())
+            .map(|document| document.id)
+            .expect("The compositor has no WebRender document")
+    }
+
+    /// WebRender document of the window, the current one's if it has none.
+    pub fn window_document(&self, window_id: WindowId) -> DocumentId {
+        self.documents
+            .get(&window_id)
+            .map_or_else(|| self.webrender_document(), |document| document.id)
+    }
+
+    /// WebRender document of the window showing the webview.
+    fn webview_document(&self, webview_id: WebViewId) -> DocumentId {
+        match self.webview_windows.get(&webview_id) {
+            Some(window_id) => self.window_document(*window_id),
+            None => self.webrender_document(),
+        }
+    }
+
+    /// WebRender document of the window showing the pipeline.
+    fn pipeline_document(&self, pipeline_id: PipelineId) -> DocumentId {
+        match self
+            .pipeline_details
+            .get(&pipeline_id)
+            .and_then(|details| details.pipeline.as_ref())
+        {
+            Some(pipeline) => self.webview_document(pipeline.webview_id),
+            None => self.webrender_document(),
+        }
+    }
+
+    /// Window of a WebRender document.
+    fn document_window(&self, document_id: DocumentId) -> Option<WindowId> {
+        self.documents
+            .iter()
+            .find(|(_, document)| document.id == document_id)
+            .map(|(window_id, _)| *window_id)
+    }
+
+    /// Remember the viewport and scale of the current window's document.
+    fn update_window_document(&mut self) {
+        if let Some(document) = self.documents.get_mut(&self.current_window) {
+            document.viewport = self.viewport;
+            document.scale_factor = self.scale_factor;
         }
     }
 
@@ -1334,6 +2456,8 @@ impl IOCompositor {
         let show_tab_bar = window.tab_manager.count() > 1;
         let content_size = window.get_content_size(rect, show_tab_bar, window.show_bookmark);
         if let Some(tab_id) = window.tab_manager.current_tab_id() {
+            // Tiled tabs keep their rect, the embedder lays them out again.
+            let content_size = window.tiles.rect(tab_id).unwrap_or(content_size);
             let (tab_id, prompt_id) = window.tab_manager.set_size(tab_id, content_size);
             if let Some(tab_id) = tab_id {
                 self.on_resize_webview_event(tab_id, content_size);
@@ -1366,10 +2490,11 @@ impl IOCompositor {
             },
         );
         self.viewport = new_viewport;
+        self.update_window_document();
         let mut transaction = Transaction::new();
         transaction.set_document_view(DeviceIntRect::from_size(self.viewport.to_i32()));
         self.webrender_api
-            .send_transaction(self.webrender_document, transaction);
+            .send_transaction(self.webrender_document(), transaction);
         self.composite_if_necessary(CompositingReason::Resize);
     }
 
@@ -1381,6 +2506,7 @@ impl IOCompositor {
         }
 
         self.scale_factor = Scale::new(scale_factor);
+        self.update_window_document();
         self.update_after_zoom_or_hidpi_change(window);
         self.composite_if_necessary(CompositingReason::Resize);
         true
@@ -1428,35 +2554,32 @@ impl IOCompositor {
         if self.shutdown_state != ShutdownState::NotShuttingDown {
             return;
         }
-        if self.convert_mouse_to_touch {
-            match event {
-                InputEvent::MouseButton(event) => {
-                    match event.action {
-                        MouseButtonAction::Click => {}
-                        MouseButtonAction::Down => self.on_touch_down(
-                            webview_id,
-                            TouchEvent::new(TouchEventType::Down, TouchId(0), event.point),
-                        ),
-                        MouseButtonAction::Up => self.on_touch_up(
-                            webview_id,
-                            TouchEvent::new(TouchEventType::Up, TouchId(0), event.point),
-                        ),
-                    }
-                    return;
-                }
-                InputEvent::MouseMove(event) => {
-                    self.on_touch_move(
-                        webview_id,
-                        TouchEvent::new(TouchEventType::Move, TouchId(0), event.point),
-                    );
-                    return;
-                }
-                _ => {}
+        if let Some(touches) = self.mouse_to_touch.convert(webview_id, &event) {
+            for (webview_id, touch) in touches {
+                self.on_touch_event(webview_id, touch);
             }
+            return;
         }
         self.dispatch_input_event(webview_id, event);
     }
 
+    /// Turn the translation of mouse input into touch events on or off, lifting the emulated
+    /// finger if the button is held.
+    pub fn set_mouse_to_touch(&mut self, enabled: bool) {
+        if let Some((webview_id, cancel)) = self.mouse_to_touch.set_enabled(enabled) {
+            self.on_touch_cancel(webview_id, cancel);
+        }
+        log::info!(
+            "Verso {} mouse to touch emulation",
+            if enabled { "turned on" } else { "turned off" }
+        );
+    }
+
+    /// Whether mouse input is translated into touch events.
+    pub fn mouse_to_touch(&self) -> bool {
+        self.mouse_to_touch.enabled()
+    }
+
     /// Get the webview id from a point.
     pub(crate) fn webview_id_from_point(&self, point: DevicePoint) -> Option<WebViewId> {
         self.hit_test_at_point(point)
@@ -1466,6 +2589,34 @@ impl IOCompositor {
             .map(|pipeline| pipeline.webview_id)
     }
 
+    /// Hit test a webview at a point of its window, for embedders asking what is under the
+    /// cursor. Returns the topmost item of the webview's pages at the point, even under other
+    /// webviews, or `None` if there's none or the pages' display lists are being replaced.
+    pub fn hit_test(
+        &self,
+        webview_id: WebViewId,
+        point: DevicePoint,
+    ) -> Option<CompositorHitTestResult> {
+        self.hit_test_in_document(
+            self.webview_document(webview_id),
+            point,
+            HitTestFlags::FIND_ALL,
+            None,
+        )
+        .into_iter()
+        .find(|result| {
+            self.pipeline_details
+                .get(&result.pipeline_id)
+                .and_then(|details| details.pipeline.as_ref())
+                .is_some_and(|pipeline| pipeline.webview_id == webview_id)
+        })
+    }
+
+    /// Whether the pipeline is the page of the webview rather than one of its iframes.
+    pub fn is_webview_root_pipeline(&self, webview_id: WebViewId, pipeline_id: PipelineId) -> bool {
+        self.webviews.get(&webview_id) == Some(&pipeline_id)
+    }
+
     fn hit_test_at_point(&self, point: DevicePoint) -> Option<CompositorHitTestResult> {
         self.hit_test_at_point_with_flags_and_pipeline(point, HitTestFlags::empty(), None)
             .first()
@@ -1477,12 +2628,22 @@ impl IOCompositor {
         point: DevicePoint,
         flags: HitTestFlags,
         pipeline_id: Option<WebRenderPipelineId>,
+    ) -> Vec<CompositorHitTestResult> {
+        self.hit_test_in_document(self.webrender_document(), point, flags, pipeline_id)
+    }
+
+    fn hit_test_in_document(
+        &self,
+        document: DocumentId,
+        point: DevicePoint,
+        flags: HitTestFlags,
+        pipeline_id: Option<WebRenderPipelineId>,
     ) -> Vec<CompositorHitTestResult> {
         // DevicePoint and WorldPoint are the same for us.
         let world_point = WorldPoint::from_untyped(point.to_untyped());
-        let results =
-            self.webrender_api
-                .hit_test(self.webrender_document, pipeline_id, world_point, flags);
+        let results = self
+            .webrender_api
+            .hit_test(document, pipeline_id, world_point, flags);
 
         results
             .items
@@ -1548,6 +2709,8 @@ impl IOCompositor {
     }
 
     fn on_touch_down(&mut self, webview_id: WebViewId, event: TouchEvent) {
+        // A finger on the screen catches the page where it is.
+        self.smooth_scroll.stop();
         self.touch_handler.on_touch_down(event.id, event.point);
         self.send_touch_event(webview_id, event);
     }
@@ -1564,8 +2727,10 @@ impl IOCompositor {
                 // The order of these events doesn't matter, because zoom is handled by
                 // a root display list and the scroll event here is handled by the scroll
                 // applied to the content display list.
+                let sequence = self.scroll_sequencer.next();
                 self.pending_scroll_zoom_events
-                    .push(ScrollZoomEvent::PinchZoom(magnification));
+                    .push(ScrollZoomEvent::PinchZoom(magnification, sequence));
+                let sequence = self.scroll_sequencer.next();
                 self.pending_scroll_zoom_events
                     .push(ScrollZoomEvent::Scroll(ScrollEvent {
                         scroll_location: ScrollLocation::Delta(LayoutVector2D::from_untyped(
@@ -1573,6 +2738,7 @@ impl IOCompositor {
                         )),
                         cursor,
                         event_count: 1,
+                        sequence,
                     }));
             }
             TouchAction::DispatchEvent => self.send_touch_event(webview_id, event),
@@ -1646,25 +2812,54 @@ impl IOCompositor {
         }
     }
 
+    /// Handle a scroll by a mouse wheel notch or a scrolling key, animated if smooth scrolling is on.
+    pub fn on_smooth_scroll_event(&mut self, delta: LayoutVector2D, cursor: DeviceIntPoint) {
+        if self.shutdown_state != ShutdownState::NotShuttingDown {
+            return;
+        }
+        if self.smooth_scroll.scroll(cursor, delta, Instant::now()) {
+            self.composite_if_necessary(CompositingReason::Animation);
+        } else {
+            self.on_scroll_window_event(ScrollLocation::Delta(delta), cursor);
+        }
+    }
+
+    /// When the next frame of a smooth scroll is due, if one is animating.
+    pub fn next_smooth_scroll_frame(&self) -> Option<Instant> {
+        self.smooth_scroll
+            .animating()
+            .then(|| Instant::now() + self.frame_pacing.target_frame_duration())
+    }
+
     fn on_scroll_window_event(&mut self, scroll_location: ScrollLocation, cursor: DeviceIntPoint) {
+        let sequence = self.scroll_sequencer.next();
         self.pending_scroll_zoom_events
             .push(ScrollZoomEvent::Scroll(ScrollEvent {
                 scroll_location,
                 cursor,
                 event_count: 1,
+                sequence,
             }));
     }
 
-    fn process_pending_scroll_events(&mut self, _window: &Window) {
+    fn process_pending_scroll_events(&mut self, window: &Window) {
+        let _allocation_scope = AllocationScope::enter("compositor.scroll");
         // Batch up all scroll events into one, or else we'll do way too much painting.
         let mut combined_scroll_event: Option<ScrollEvent> = None;
-        let mut _combined_magnification = 1.0;
+        let mut combined_magnification = 1.0;
+        let mut scroll_events = 0;
+        let last_sequence = self
+            .pending_scroll_zoom_events
+            .iter()
+            .map(ScrollZoomEvent::sequence)
+            .max();
         for scroll_event in self.pending_scroll_zoom_events.drain(..) {
             match scroll_event {
-                ScrollZoomEvent::PinchZoom(magnification) => {
-                    _combined_magnification *= magnification
+                ScrollZoomEvent::PinchZoom(magnification, _) => {
+                    combined_magnification *= magnification
                 }
                 ScrollZoomEvent::Scroll(scroll_event_info) => {
+                    scroll_events += 1;
                     let combined_event = match combined_scroll_event.as_mut() {
                         None => {
                             combined_scroll_event = Some(scroll_event_info);
@@ -1684,6 +2879,7 @@ impl IOCompositor {
                             // deltas instead of summing them.
                             let old_event_count = Scale::new(combined_event.event_count as f32);
                             combined_event.event_count += 1;
+                            combined_event.sequence = scroll_event_info.sequence;
                             let new_event_count = Scale::new(combined_event.event_count as f32);
                             combined_event.scroll_location = ScrollLocation::Delta(
                                 (old_delta * old_event_count + new_delta) / new_event_count,
@@ -1704,41 +2900,67 @@ impl IOCompositor {
             }
         }
 
+        self.scroll_coalescing.record(scroll_events);
+
+        if combined_magnification != 1.0 {
+            if let Some(webview_id) = window.tab_manager.current_tab_id() {
+                self.pinch_zoom.magnify(webview_id, combined_magnification);
+                self.send_root_pipeline_display_list(window);
+            }
+        }
+
+        self.memory_pressure.check();
+        ShapingCache::shared()
+            .lock()
+            .unwrap()
+            .set_capacity_factor(self.memory_pressure.cache_reduction_factor());
+
         let scroll_result = combined_scroll_event.and_then(|combined_event| {
             self.scroll_node_at_device_point(
                 combined_event.cursor.to_f32(),
                 combined_event.scroll_location,
             )
+            .map(|result| (result, combined_event.sequence))
         });
 
         let mut transaction = Transaction::new();
 
-        if let Some((pipeline_id, external_id, offset)) = scroll_result {
+        if let Some(((pipeline_id, external_id, offset, delta), sequence)) = scroll_result {
             let offset = LayoutVector2D::new(-offset.x, -offset.y);
             transaction.set_scroll_offsets(
                 external_id,
                 vec![SampledScrollOffset {
                     offset,
-                    generation: 0,
+                    generation: sequence.0,
                 }],
             );
             self.send_scroll_positions_to_layout_for_pipeline(&pipeline_id);
+            self.scroll_sequencer.record_user_scroll(
+                pipeline_id,
+                external_id,
+                sequence,
+                delta,
+                Instant::now(),
+            );
+        }
+        if let Some(sequence) = last_sequence {
+            self.scroll_sequencer.set_sampled(sequence);
         }
 
         self.generate_frame(&mut transaction, RenderReasons::APZ);
         self.webrender_api
-            .send_transaction(self.webrender_document, transaction);
+            .send_transaction(self.webrender_document(), transaction);
     }
 
     /// Perform a hit test at the given [`DevicePoint`] and apply the [`ScrollLocation`]
     /// scrolling to the applicable scroll node under that point. If a scroll was
-    /// performed, returns the [`PipelineId`] of the node scrolled, the id, and the final
-    /// scroll delta.
+    /// performed, returns the [`PipelineId`] of the node scrolled, the id, the final
+    /// scroll offset and how much it changed.
     fn scroll_node_at_device_point(
         &mut self,
         cursor: DevicePoint,
         scroll_location: ScrollLocation,
-    ) -> Option<(PipelineId, ExternalScrollId, LayoutVector2D)> {
+    ) -> Option<(PipelineId, ExternalScrollId, LayoutVector2D, LayoutVector2D)> {
         let scroll_location = match scroll_location {
             ScrollLocation::Delta(delta) => {
                 let device_pixels_per_page = self.device_pixels_per_page_pixel();
@@ -1766,13 +2988,17 @@ impl IOCompositor {
         } in hit_test_results.iter()
         {
             if previous_pipeline_id.replace(pipeline_id) != Some(pipeline_id) {
-                let scroll_result = self
-                    .pipeline_details
-                    .get_mut(pipeline_id)?
-                    .scroll_tree
-                    .scroll_node_or_ancestor(scroll_tree_node, scroll_location);
+                let scroll_tree = &mut self.pipeline_details.get_mut(pipeline_id)?.scroll_tree;
+                let offsets_before: HashMap<ExternalScrollId, LayoutVector2D> = scroll_tree
+                    .nodes
+                    .iter()
+                    .filter_map(|node| Some((node.external_id()?, node.offset()?)))
+                    .collect();
+                let scroll_result =
+                    scroll_tree.scroll_node_or_ancestor(scroll_tree_node, scroll_location);
                 if let Some((external_id, offset)) = scroll_result {
-                    return Some((*pipeline_id, external_id, offset));
+                    let before = offsets_before.get(&external_id).copied().unwrap_or(offset);
+                    return Some((*pipeline_id, external_id, offset, offset - before));
                 }
             }
         }
@@ -1829,6 +3055,11 @@ impl IOCompositor {
         }
     }
 
+    /// The sequence id of the last scroll or zoom WebRender sampled, for tests.
+    pub fn last_scroll_sequence(&self) -> ScrollSequenceId {
+        self.scroll_sequencer.sampled()
+    }
+
     fn device_pixels_per_page_pixel(&self) -> Scale<f32, CSSPixel, DevicePixel> {
         self.device_pixels_per_page_pixel_not_including_page_zoom()
     }
@@ -1843,6 +3074,93 @@ impl IOCompositor {
         (self.viewport.to_f32() / self.scale_factor).to_i32()
     }
 
+    /// Show or hide the HUD with the GPU, viewport and devtools server of the window.
+    pub fn toggle_hud(&mut self, window: &Window) {
+        self.hud_visible = !self.hud_visible;
+        self.send_root_pipeline_display_list(window);
+    }
+
+    /// Show or hide the frame rate, frame times, dropped frames and scroll coalescing in the HUD.
+    pub fn toggle_stats_hud(&mut self, window: &Window) {
+        self.stats_hud_visible = !self.stats_hud_visible;
+        self.send_root_pipeline_display_list(window);
+    }
+
+    /// When the stats in the HUD are due to be drawn again, if they're shown.
+    pub fn next_stats_hud_refresh(&self) -> Option<Instant> {
+        self.stats_hud_visible
+            .then(|| self.stats_hud_drawn + stats_hud::REFRESH_INTERVAL)
+    }
+
+    /// Lines of the HUD.
+    fn hud_lines(&self, window: &Window) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.hud_visible {
+            let size = self.rendering_context.size2d();
+            let devtools = if pref!(devtools_server_enabled) {
+                format!("Devtools: port {}", pref!(devtools_server_port))
+            } else {
+                "Devtools: off".to_string()
+            };
+            lines.extend([
+                format!("GPU: {}", self.gpu_identity.renderer),
+                format!(
+                    "Viewport: {}x{} at {}x",
+                    size.width,
+                    size.height,
+                    self.scale_factor.get()
+                ),
+                devtools,
+                format!("Webviews: {}", window.painting_order().len()),
+            ]);
+        }
+        if self.stats_hud_visible {
+            lines.extend(stats_hud::lines(
+                &self.frame_pacing.stats(),
+                &self.scroll_coalescing,
+            ));
+        }
+        lines
+    }
+
+    /// Page zoom of a webview, 1 unless it was set.
+    pub fn page_zoom(&self, webview_id: WebViewId) -> f32 {
+        self.page_zoom.get(&webview_id).copied().unwrap_or(1.0)
+    }
+
+    /// Zoom the page of a webview in `window` by `factor`, clamped between [`MIN_PAGE_ZOOM`] and
+    /// [`MAX_PAGE_ZOOM`], and return the factor applied.
+    ///
+    /// Unlike pinch zoom, the page is laid out again, in a viewport the size of the webview
+    /// divided by the factor, and sees a device pixel ratio multiplied by it.
+    pub fn set_page_zoom(&mut self, window: &Window, webview_id: WebViewId, factor: f32) -> f32 {
+        let factor = if factor.is_nan() {
+            1.0
+        } else {
+            factor.clamp(MIN_PAGE_ZOOM, MAX_PAGE_ZOOM)
+        };
+        if factor == 1.0 {
+            self.page_zoom.remove(&webview_id);
+        } else {
+            self.page_zoom.insert(webview_id, factor);
+        }
+
+        if let Some(webview) = window
+            .painting_order()
+            .into_iter()
+            .find(|webview| webview.webview_id == webview_id)
+        {
+            self.send_window_size_message_for_top_level_browser_context(webview.rect, webview_id);
+        }
+        self.send_root_pipeline_display_list(window);
+        factor
+    }
+
+    /// Reset the page zoom of a webview in `window` to 1.
+    pub fn reset_zoom(&mut self, window: &Window, webview_id: WebViewId) {
+        self.set_page_zoom(window, webview_id, 1.0);
+    }
+
     /// Handle zoom reset event
     pub fn on_zoom_reset_window_event(&mut self, window: &Window) {
         if self.shutdown_state != ShutdownState::NotShuttingDown {
@@ -1873,6 +3191,76 @@ impl IOCompositor {
         self.send_root_pipeline_display_list(window);
     }
 
+    /// Animate the pinch zoom of a webview back to 1.
+    pub fn reset_pinch_zoom(&mut self, webview_id: WebViewId) {
+        if self.pinch_zoom.reset(webview_id, Instant::now()) {
+            self.composite_if_necessary(CompositingReason::Animation);
+        }
+    }
+
+    /// When the next frame of a pinch zoom reset is due, if one is animating.
+    pub fn next_pinch_zoom_frame(&self) -> Option<Instant> {
+        self.pinch_zoom
+            .animating()
+            .then(|| Instant::now() + self.frame_pacing.target_frame_duration())
+    }
+
+    /// Play the frames of an animated image a webview loaded, once Servo uploaded its first frame.
+    pub fn add_animated_image(&mut self, webview_id: WebViewId, animation: DecodedAnimation) {
+        self.animated_images
+            .add(webview_id, animation, Instant::now());
+    }
+
+    /// Whether the compositor plays animated images of the webview.
+    pub fn has_animated_images(&self, webview_id: WebViewId) -> bool {
+        self.animated_images.has_images(webview_id)
+    }
+
+    /// Pause the animated images of a webview that are away from its viewport, resume the others.
+    pub fn set_animated_images_in_view(
+        &mut self,
+        webview_id: WebViewId,
+        in_view: &HashSet<url::Url>,
+    ) {
+        self.animated_images
+            .set_in_view(webview_id, in_view, Instant::now());
+    }
+
+    /// When the next animated image frame is due, if any image is playing.
+    pub fn next_animated_image_frame(&self) -> Option<Instant> {
+        self.animated_images.next_deadline()
+    }
+
+    /// Switch the animated images whose next frame is due, in one transaction.
+    fn tick_animated_images(&mut self, now: Instant) {
+        self.animated_images
+            .set_memory_pressure(self.memory_pressure.current_level(), now);
+        let updates = self
+            .animated_images
+            .tick(now, self.frame_pacing.target_frame_duration());
+        if updates.is_empty() {
+            return;
+        }
+
+        let mut txn = Transaction::new();
+        let mut documents = HashSet::new();
+        for update in updates {
+            documents.insert(self.webview_document(update.webview_id));
+            if let Some(retained) = &mut self.retained_resources {
+                retained.set_image(update.key, update.descriptor, &update.data);
+            }
+            txn.update_image(update.key, update.descriptor, update.data, &DirtyRect::All);
+        }
+        self.webrender_api
+            .send_transaction(self.webrender_document(), txn);
+        // The images may be shown in other windows than the current one.
+        for document in documents {
+            let mut txn = Transaction::new();
+            self.generate_frame(&mut txn, RenderReasons::ANIMATED_PROPERTY);
+            self.webrender_api.send_transaction(document, txn);
+        }
+    }
+
     /// Simulate a pinch zoom
     pub fn on_pinch_zoom_window_event(&mut self, magnification: f32) {
         if self.shutdown_state != ShutdownState::NotShuttingDown {
@@ -1880,8 +3268,9 @@ impl IOCompositor {
         }
 
         // TODO: Scroll to keep the center in view?
+        let sequence = self.scroll_sequencer.next();
         self.pending_scroll_zoom_events
-            .push(ScrollZoomEvent::PinchZoom(magnification));
+            .push(ScrollZoomEvent::PinchZoom(magnification, sequence));
     }
 
     fn send_scroll_positions_to_layout_for_pipeline(&self, pipeline_id: &PipelineId) {
@@ -1944,7 +3333,7 @@ impl IOCompositor {
                     if let Some(WebRenderEpoch(epoch)) = self
                         .webrender
                         .as_ref()
-                        .and_then(|wr| wr.current_epoch(self.webrender_document, id.into()))
+                        .and_then(|wr| wr.current_epoch(self.pipeline_document(*id), id.into()))
                     {
                         let epoch = Epoch(epoch);
                         pipeline_epochs.insert(*id, epoch);
@@ -1979,27 +3368,39 @@ impl IOCompositor {
 
     /// Composite to the given target if any, or the current target otherwise.
     pub fn composite(&mut self, window: &Window) {
-        match self.composite_specific_target(window) {
-            Ok(_) => {
-                if self.wait_for_stable_image {
-                    println!(
-                        "Shutting down the Constellation after generating an output file or exit flag specified"
-                    );
-                    self.start_shutting_down();
-                }
-            }
-            Err(error) => {
-                trace!("Unable to composite: {error:?}");
-            }
-        }
+        self.composite_target(Some(window));
     }
 
-    /// Composite to the given target if any, or the current target otherwise.
-    fn composite_specific_target(&mut self, window: &Window) -> Result<(), UnableToComposite> {
-        if let Err(err) = self
+    /// Composite into the offscreen framebuffer of a headless compositor.
+    pub fn composite_headless(&mut self) {
+        self.composite_target(None);
+    }
+
+    fn composite_target(&mut self, window: Option<&Window>) {
+        self.composite_specific_target(window);
+        let robust = self
             .rendering_context
-            .make_gl_context_current(&window.surface)
+            .context_info()
+            .reset_strategy
+            .is_some();
+        if let Some(cause) = self
+            .device_lost_watchdog
+            .check(self.webrender_gl.as_ref(), robust)
         {
+            self.device_lost = Some(cause);
+        }
+    }
+
+    /// Composite to the surface of the window if any, or the offscreen framebuffer otherwise.
+    fn composite_specific_target(&mut self, window: Option<&Window>) {
+        let _allocation_scope = AllocationScope::enter("compositor.composite");
+        let current = match window {
+            Some(window) => self
+                .rendering_context
+                .make_gl_context_current(&window.surface),
+            None => self.rendering_context.make_offscreen_current(),
+        };
+        if let Err(err) = current {
             warn!("Failed to make GL context current: {:?}", err);
         }
         self.assert_no_gl_error();
@@ -2008,23 +3409,41 @@ impl IOCompositor {
             webrender.update();
         }
 
-        let wait_for_stable_image = self.wait_for_stable_image;
+        if self.screenshots.full_page_due() {
+            self.composite_full_page();
+            return;
+        }
 
-        if wait_for_stable_image {
-            // The current image may be ready to output. However, if there are animations active,
-            // tick those instead and continue waiting for the image output to be stable AND
-            // all active animations to complete.
-            if self.animations_active() {
-                self.process_animations(false);
-                return Err(UnableToComposite::NotReadyToPaintImage(
-                    NotReadyToPaint::AnimationsActive,
-                ));
-            }
-            if let Err(result) = self.is_ready_to_paint_image_output() {
-                return Err(UnableToComposite::NotReadyToPaintImage(result));
+        let shared_buffer = match (window, self.shared_textures.as_mut()) {
+            (Some(window), Some(shared_textures)) if shared_textures.window_id() == window.id() => {
+                match shared_textures.begin_frame(&self.rendering_context, self.viewport.to_u32()) {
+                    Ok(Some(buffer)) => Some(buffer),
+                    Ok(None) => {
+                        // The composition stays pending until the controller releases a texture.
+                        debug!("The controller holds every shared texture, skipping the frame");
+                        return;
+                    }
+                    Err(error) => {
+                        warn!("Failed to composite into shared textures: {error}");
+                        self.shared_textures = None;
+                        None
+                    }
+                }
             }
-        }
+            _ => None,
+        };
 
+        // With partial present, WebRender redraws what changed since the back buffer was last
+        // drawn, everything if its age is unknown.
+        let buffer_age = match window {
+            Some(_) if shared_buffer.is_some() => 0,
+            Some(window) if self.partial_present => {
+                self.rendering_context.buffer_age(&window.surface)
+            }
+            _ => 0,
+        };
+        let mut dirty_rects = None;
+        let composite_start = Instant::now();
         time_profile!(
             ProfilerCategory::Compositing,
             None,
@@ -2034,23 +3453,334 @@ impl IOCompositor {
                 // Paint the scene.
                 // TODO(gw): Take notice of any errors the renderer returns!
                 if let Some(webrender) = self.webrender.as_mut() {
-                    webrender
-                        .render(self.viewport.to_i32(), 0 /* buffer_age */)
-                        .ok();
+                    dirty_rects = webrender
+                        .render(self.viewport.to_i32(), buffer_age)
+                        .ok()
+                        .map(|results| results.dirty_rects);
                 }
             },
         );
+        self.present_damage = dirty_rects.filter(|_| buffer_age > 0);
+        if self.flight_recorder.is_enabled() {
+            let viewport = self.viewport.to_u32();
+            self.flight_recorder.capture_frame(
+                &*self.webrender_gl,
+                viewport.width,
+                viewport.height,
+            );
+            self.flight_recorder
+                .composite_finished(composite_start.elapsed());
+        }
+        if self.frame_stability.tracking {
+            let viewport = self.viewport.to_u32();
+            let pixels = read_frame(&*self.webrender_gl, viewport.width, viewport.height);
+            self.frame_stability.record(&pixels);
+        }
+        let screenshots = self.screenshots.take_due(false);
+        if !screenshots.is_empty() {
+            let frame = self.read_composited_frame();
+            for request in screenshots {
+                let result = frame
+                    .as_ref()
+                    .ok_or_else(|| "the frame can't be read".to_string())
+                    .and_then(|frame| {
+                        screenshot::capture(frame, request.rect, request.options.format)
+                    });
+                self.screenshots.finish(request, result);
+            }
+        }
+        let now = Instant::now();
+        if self
+            .frame_recorder
+            .as_ref()
+            .is_some_and(|recorder| recorder.frame_due(now))
+        {
+            match self.read_composited_frame() {
+                Some(frame) => {
+                    if let Some(recorder) = self.frame_recorder.as_mut() {
+                        recorder.record(frame, now);
+                    }
+                }
+                None => warn!("Failed to read the frame to record"),
+            }
+        }
+        if self.render_validation.take_due() {
+            match self.validate_rendered_frame() {
+                Ok(report) if report.divergent_pixels > 0 => warn!(
+                    "Software renderer diverges from {} on {} pixels in {:?}, see {:?}",
+                    report.hardware_renderer,
+                    report.divergent_pixels,
+                    report.divergent_region,
+                    report.output_directory
+                ),
+                Ok(report) => debug!(
+                    "Software renderer matches {} within {}",
+                    report.hardware_renderer, report.max_difference
+                ),
+                Err(error) => warn!("Failed to validate the frame: {error}"),
+            }
+        }
 
+        self.display_list_transport.frame_composited();
         self.send_pending_paint_metrics_messages_after_composite();
 
         self.composition_request = CompositionRequest::NoCompositingNecessary;
-        self.ready_to_present = true;
+        match (window, shared_buffer) {
+            (Some(window), Some(buffer)) => {
+                // The frame goes to the controller instead of the window surface.
+                if let Some(shared_textures) = self.shared_textures.as_mut() {
+                    shared_textures.end_frame(&*self.webrender_gl, buffer);
+                }
+                self.on_frame_presented(window.id());
+            }
+            _ => {
+                self.ready_to_present = true;
+                self.presenting_window = window.map(Window::id);
+            }
+        }
 
         self.process_animations(true);
+    }
 
+    /// Start recording the composited frames, stopping a recording in progress.
+    pub fn start_recording(&mut self, settings: RecordingSettings) -> Result<(), String> {
+        if let Some(recorder) = self.frame_recorder.take() {
+            if let Err(error) = recorder.stop() {
+                warn!("Failed to finish the previous recording: {error}");
+            }
+        }
+        self.frame_recorder = Some(FrameRecorder::start(settings, Instant::now())?);
         Ok(())
     }
 
+    /// Stop recording the composited frames, waiting for the encoder to finish.
+    pub fn stop_recording(&mut self) -> Result<RecordingReport, String> {
+        self.frame_recorder
+            .take()
+            .ok_or_else(|| "no recording is in progress".to_string())?
+            .stop()
+    }
+
+    /// Queue a capture of the next frame of a webview.
+    ///
+    /// For a full page capture, resize the webview to [`ScreenshotRequest::rect`] first.
+    pub fn queue_screenshot(&mut self, request: ScreenshotRequest) {
+        let full_page = request.options.full_page;
+        self.screenshots.push(request);
+        if full_page {
+            self.update_document_view();
+        }
+        self.composite_if_necessary(CompositingReason::Screenshot);
+    }
+
+    /// Rect of a webview at `rect` resized to a page `page_height` CSS pixels tall, cut to the
+    /// largest framebuffer the GPU supports.
+    pub fn full_page_rect(&self, rect: DeviceRect, page_height: f32) -> DeviceRect {
+        let mut max_size = [0];
+        unsafe {
+            self.webrender_gl
+                .get_integer_v(gl::MAX_RENDERBUFFER_SIZE, &mut max_size)
+        };
+        screenshot::full_page_rect(
+            rect,
+            page_height,
+            self.device_pixels_per_page_pixel().0,
+            max_size[0],
+        )
+    }
+
+    /// Size of the scene, the viewport or larger while a full page capture waits.
+    fn scene_size(&self) -> Size2D<f32, DevicePixel> {
+        let size = self.rendering_context.size2d().to_f32();
+        self.screenshots
+            .full_page_size()
+            .map_or(size, |full_page| full_page.max(size))
+    }
+
+    /// Set the WebRender document view to the scene.
+    fn update_document_view(&mut self) {
+        let size = self
+            .screenshots
+            .full_page_size()
+            .map_or(self.viewport, |full_page| full_page.max(self.viewport));
+        let mut transaction = Transaction::new();
+        transaction.set_document_view(DeviceIntRect::from_size(size.to_i32()));
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
+    }
+
+    /// Render the scene into an offscreen framebuffer as large as the webviews resized for
+    /// full page captures, and take the captures from it.
+    fn composite_full_page(&mut self) {
+        let size = self.scene_size();
+        let gl = self.webrender_gl.clone();
+        let framebuffer = OffscreenFramebuffer::create(
+            gl.as_ref(),
+            PhysicalSize::new(size.width as u32, size.height as u32),
+        );
+        if let Some(webrender) = self.webrender.as_mut() {
+            webrender.render(size.to_i32(), 0 /* buffer_age */).ok();
+        }
+        let frame = framebuffer.read_pixels(gl.as_ref());
+        framebuffer.delete(gl.as_ref());
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        if let Err(err) = self.rendering_context.make_offscreen_current() {
+            warn!("Failed to make GL context current: {:?}", err);
+        }
+
+        for request in self.screenshots.take_due(true) {
+            let result = frame
+                .as_ref()
+                .ok_or_else(|| "the frame can't be read".to_string())
+                .and_then(|frame| screenshot::capture(frame, request.rect, request.options.format));
+            self.screenshots.finish(request, result);
+        }
+        // The embedder resizes the webviews back.
+        self.update_document_view();
+        self.composite_if_necessary(CompositingReason::Resize);
+    }
+
+    /// Read the frame just rendered, top row first.
+    fn read_composited_frame(&self) -> Option<image::RgbaImage> {
+        if self.rendering_context.is_headless() {
+            return self.rendering_context.read_offscreen_pixels();
+        }
+        let viewport = self.viewport.to_u32();
+        let pixels = read_frame(&*self.webrender_gl, viewport.width, viewport.height);
+        let mut image = image::RgbaImage::from_raw(viewport.width, viewport.height, pixels)?;
+        // GL rows are bottom up.
+        image::imageops::flip_vertical_in_place(&mut image);
+        Some(image)
+    }
+
+    /// Whether WebRender has no frame pending, no animation runs and the constellation last
+    /// replied that every pipeline painted its latest epoch, asking it again for the next call.
+    pub fn frames_settled(&mut self) -> bool {
+        if self.ready_to_save_state != ReadyState::WaitingForConstellationReply {
+            self.ready_to_save_state = ReadyState::Unknown;
+            let _ = self.is_ready_to_paint_image_output();
+        }
+        self.pending_frames == 0 && !self.animations_active() && self.frame_tree_painted
+    }
+
+    /// Composite again, for the next frame to be compared with the last one.
+    pub fn composite_for_stability(&mut self) {
+        self.composite_if_necessary(CompositingReason::Headless);
+    }
+
+    /// Render the current frame of `window` again and compare it with the software renderer.
+    pub fn validate_rendering(
+        &mut self,
+        window: &Window,
+    ) -> Result<RenderValidationReport, String> {
+        if let Err(err) = self
+            .rendering_context
+            .make_gl_context_current(&window.surface)
+        {
+            warn!("Failed to make GL context current: {:?}", err);
+        }
+        let Some(webrender) = self.webrender.as_mut() else {
+            return Err("the renderer shut down".to_string());
+        };
+        webrender.update();
+        webrender
+            .render(self.viewport.to_i32(), 0 /* buffer_age */)
+            .map_err(|errors| format!("the renderer failed: {errors:?}"))?;
+        self.validate_rendered_frame()
+    }
+
+    /// Read the last frame of a headless compositor, `None` if it composites into a window.
+    pub fn headless_frame(&self) -> Option<image::RgbaImage> {
+        if let Err(err) = self.rendering_context.make_offscreen_current() {
+            warn!("Failed to make GL context current: {:?}", err);
+        }
+        self.rendering_context.read_offscreen_pixels()
+    }
+
+    /// Resize the offscreen framebuffer of a headless compositor.
+    pub fn resize_headless(&mut self, size: DeviceSize) {
+        if size.is_empty() || !self.rendering_context.is_headless() {
+            return;
+        }
+        if let Err(err) = self.rendering_context.make_offscreen_current() {
+            warn!("Failed to make GL context current: {:?}", err);
+        }
+        self.rendering_context.resize_offscreen(PhysicalSize {
+            width: size.width as u32,
+            height: size.height as u32,
+        });
+        self.viewport = size;
+        self.update_window_document();
+        let mut transaction = Transaction::new();
+        transaction.set_document_view(DeviceIntRect::from_size(self.viewport.to_i32()));
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
+        self.composite_if_necessary(CompositingReason::Resize);
+    }
+
+    /// Set the root display list of a headless compositor to the webviews, bottom first, each
+    /// covering the whole framebuffer.
+    pub fn send_headless_display_list(&mut self, webview_ids: &[WebViewId]) {
+        let root_pipeline = WebRenderPipelineId(u64::from(self.current_window) as u32, 1);
+        let mut transaction = Transaction::new();
+        transaction.set_root_pipeline(root_pipeline);
+
+        let mut builder = webrender::api::DisplayListBuilder::new(root_pipeline);
+        builder.begin();
+        let zoom_factor = self.device_pixels_per_page_pixel().0;
+        let zoom_reference_frame = builder.push_reference_frame(
+            LayoutPoint::zero(),
+            SpatialId::root_reference_frame(root_pipeline),
+            TransformStyle::Flat,
+            PropertyBinding::Value(Transform3D::scale(zoom_factor, zoom_factor, 1.)),
+            ReferenceFrameKind::Transform {
+                is_2d_scale_translation: true,
+                should_snap: true,
+                paired_with_perspective: false,
+            },
+            SpatialTreeItemKey::new(0, 0),
+        );
+        let viewport_size = self.rendering_context.size2d().to_f32().to_untyped() / zoom_factor;
+        let viewport_rect = LayoutRect::from_size(LayoutSize::from_untyped(viewport_size));
+        let clip_id = builder.define_clip_rect(zoom_reference_frame, viewport_rect);
+        let space_and_clip = SpaceAndClipInfo {
+            spatial_id: zoom_reference_frame,
+            clip_chain_id: builder.define_clip_chain(None, [clip_id]),
+        };
+        for pipeline_id in webview_ids
+            .iter()
+            .filter_map(|webview_id| self.webviews.get(webview_id))
+        {
+            builder.push_iframe(
+                viewport_rect,
+                viewport_rect,
+                &space_and_clip,
+                pipeline_id.into(),
+                true,
+            );
+        }
+        transaction.set_display_list(WebRenderEpoch(0), builder.end());
+        self.update_transaction_with_all_scroll_offsets(&mut transaction);
+        self.generate_frame(&mut transaction, RenderReasons::SCENE);
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
+    }
+
+    /// Compare the frame just rendered with the software renderer.
+    fn validate_rendered_frame(&mut self) -> Result<RenderValidationReport, String> {
+        let Some(webrender) = self.webrender.as_mut() else {
+            return Err("the renderer shut down".to_string());
+        };
+        let viewport = self.viewport.to_u32();
+        self.render_validation.validate(
+            webrender,
+            &self.webrender_api,
+            &*self.webrender_gl,
+            viewport.width,
+            viewport.height,
+        )
+    }
+
     fn composite_if_necessary(&mut self, reason: CompositingReason) {
         trace!(
             "Will schedule a composite {reason:?}. Previously was {:?}",
@@ -2082,23 +3812,32 @@ impl IOCompositor {
     ) -> bool {
         // Check for new messages coming from the other threads in the system.
         let mut compositor_messages = vec![];
-        let mut found_recomposite_msg = false;
+        let mut found_recomposite_documents = HashSet::new();
         while let Ok(msg) = self.compositor_receiver.try_recv() {
             match msg {
-                CompositorMsg::NewWebRenderFrameReady(..) if found_recomposite_msg => {
-                    // Only take one of duplicate NewWebRendeFrameReady messages, but do subtract
-                    // one frame from the pending frames.
+                CompositorMsg::NewWebRenderFrameReady(document_id, _)
+                    if !found_recomposite_documents.insert(document_id) =>
+                {
+                    // Only take one of duplicate NewWebRendeFrameReady messages of a document, but
+                    // do subtract one frame from the pending frames.
                     self.pending_frames -= 1;
                 }
-                CompositorMsg::NewWebRenderFrameReady(..) => {
-                    found_recomposite_msg = true;
-                    compositor_messages.push(msg)
-                }
+                CompositorMsg::NewWebRenderFrameReady(..) => compositor_messages.push(msg),
                 _ => compositor_messages.push(msg),
             }
         }
+        self.event_loop_monitor
+            .queue_drained(EventQueue::Compositor, compositor_messages.len());
         for msg in compositor_messages {
-            if !self.handle_browser_message(msg, windows) {
+            let message = message_type(&msg);
+            let handling = Instant::now();
+            let handled = self.handle_browser_message(msg, windows);
+            self.event_loop_monitor.message_handled(
+                EventQueue::Compositor,
+                &message,
+                handling.elapsed(),
+            );
+            if !handled {
                 return false;
             }
         }
@@ -2114,7 +3853,24 @@ impl IOCompositor {
             return false;
         }
 
+        self.receive_color_fonts();
         if let Some((window, _)) = windows.get(&self.current_window) {
+            let now = Instant::now();
+            if now >= self.refresh_rate_checked + REFRESH_RATE_CHECK_INTERVAL {
+                self.update_refresh_rate(window);
+            }
+            if self.pinch_zoom.tick(now)
+                || self
+                    .next_stats_hud_refresh()
+                    .is_some_and(|refresh| now >= refresh)
+            {
+                self.send_root_pipeline_display_list(window);
+            }
+            if let Some((cursor, delta)) = self.smooth_scroll.tick(now) {
+                self.on_scroll_window_event(ScrollLocation::Delta(delta), cursor);
+            }
+            self.tick_animated_images(now);
+
             match self.composition_request {
                 CompositionRequest::NoCompositingNecessary => {}
                 CompositionRequest::CompositeNow(_) => {
@@ -2126,10 +3882,226 @@ impl IOCompositor {
             if !self.pending_scroll_zoom_events.is_empty() {
                 self.process_pending_scroll_events(window)
             }
+            self.composite_other_window(windows);
+        } else if self.rendering_context.is_headless() {
+            if let CompositionRequest::CompositeNow(_) = self.composition_request {
+                self.composite_headless();
+            }
         }
         self.shutdown_state != ShutdownState::FinishedShuttingDown
     }
 
+    /// Composite a window whose document has a new frame while another window is current.
+    ///
+    /// Windows share the renderer and the frame waiting to be presented, so one window is
+    /// composited at a time, once the last frame was presented.
+    fn composite_other_window(&mut self, windows: &HashMap<WindowId, (Window, DocumentId)>) {
+        if self.ready_to_present {
+            return;
+        }
+        let Some(window_id) = self.windows_to_redraw.iter().next().copied() else {
+            return;
+        };
+        self.windows_to_redraw.remove(&window_id);
+        let (Some((window, _)), Some(document)) = (
+            windows.get(&window_id),
+            self.documents.get(&window_id).copied(),
+        ) else {
+            return;
+        };
+        let (current_viewport, current_scale_factor) = (self.viewport, self.scale_factor);
+        self.viewport = document.viewport;
+        self.scale_factor = document.scale_factor;
+        self.composite(window);
+        window.request_redraw();
+        self.viewport = current_viewport;
+        self.scale_factor = current_scale_factor;
+    }
+
+    /// Take the region to present of the frame ready to present, `None` to present it whole.
+    pub fn take_present_damage(&mut self) -> Option<Vec<DeviceIntRect>> {
+        self.present_damage.take()
+    }
+
+    /// Record that the window presented a frame.
+    pub fn on_frame_presented(&mut self, window_id: WindowId) {
+        self.frame_pacing.on_frame_presented();
+        self.frames_presented += 1;
+        if !self.report_presented_frames {
+            return;
+        }
+        let document = self.window_document(window_id);
+        let webviews = self
+            .webviews
+            .iter()
+            .filter(|(webview_id, _)| self.webview_windows.get(webview_id) == Some(&window_id))
+            .filter_map(|(webview_id, pipeline_id)| {
+                let epoch = self
+                    .webrender
+                    .as_ref()?
+                    .current_epoch(document, (*pipeline_id).into())?;
+                Some(PresentedWebView {
+                    webview: format!("{webview_id:?}"),
+                    epoch: epoch.0,
+                })
+            })
+            .collect();
+        self.presented_frame = Some(FramePresented {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            frame: self.frames_presented,
+            webviews,
+        });
+    }
+
+    /// Whether a window presented its first frame, so startup got as far as the screen.
+    pub fn has_presented_frame(&self) -> bool {
+        self.frames_presented > 0
+    }
+
+    /// Composite into a shared texture again, the controller is done with it.
+    pub fn release_shared_texture(&mut self, buffer: u32) {
+        match self.shared_textures.as_mut() {
+            Some(shared_textures) => shared_textures.release(buffer),
+            None => warn!("Shared texture {buffer} released without shared textures"),
+        }
+    }
+
+    /// Take the frames composited into shared textures since the last call.
+    pub fn take_shared_texture_frames(&mut self) -> Vec<SharedTextureFrame> {
+        self.shared_textures
+            .as_mut()
+            .map_or_else(Vec::new, SharedTextures::take_frames)
+    }
+
+    /// Take the paint metrics of the webviews composited since the last call.
+    pub fn take_paint_metrics(&mut self) -> Vec<PaintMetric> {
+        std::mem::take(&mut self.paint_metrics)
+    }
+
+    /// Take the frame presented since the last call, kept while
+    /// [`Self::report_presented_frames`] is set.
+    pub fn take_presented_frame(&mut self) -> Option<FramePresented> {
+        self.presented_frame.take()
+    }
+
+    /// Memory used by WebRender, on the CPU and the GPU, and by the pipelines being composited.
+    ///
+    /// GPU memory and the per pipeline display list sizes and resource counts are non-explicit,
+    /// since they aren't heap memory of the process or are already part of WebRender's.
+    fn memory_reports(&self) -> Vec<Report> {
+        let ops = wr_malloc_size_of::MallocSizeOfOps::new(servo_allocator::usable_size, None);
+        let report = self.webrender_api.report_memory(ops);
+        let mut reports = vec![
+            Report {
+                path: path!["webrender", "fonts"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: report.fonts,
+            },
+            Report {
+                path: path!["webrender", "images"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: report.images,
+            },
+            Report {
+                path: path!["webrender", "display-list"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: report.display_list,
+            },
+            Report {
+                path: path!["webrender", "gpu-cache", "metadata"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: report.gpu_cache_metadata,
+            },
+            Report {
+                path: path!["webrender", "gpu-cache", "cpu-mirror"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: report.gpu_cache_cpu_mirror,
+            },
+            Report {
+                path: path!["webrender", "rasterized-blobs"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: report.rasterized_blobs,
+            },
+        ];
+        if let Some(webrender) = self.webrender.as_ref() {
+            let gpu_report = webrender.report_memory(std::ptr::null_mut());
+            reports.extend(gpu_memory_reports(&gpu_report));
+        }
+        for (pipeline_id, details) in &self.pipeline_details {
+            let pipeline = format!("{pipeline_id:?}");
+            for (name, size) in [
+                ("display-list", details.display_list_size),
+                ("images", details.resources.image_keys.len()),
+                ("fonts", details.resources.font_keys.len()),
+                ("font-instances", details.resources.font_instance_keys.len()),
+            ] {
+                reports.push(Report {
+                    path: path!["verso", "pipelines", pipeline, name],
+                    kind: ReportKind::NonExplicitSize,
+                    size,
+                });
+            }
+        }
+        for (tag, stats) in allocation_profiling::top_sites(ALLOCATION_REPORT_SITES) {
+            reports.push(Report {
+                path: path!["verso", "allocations", tag, "allocated"],
+                kind: ReportKind::NonExplicitSize,
+                size: stats.allocated as usize,
+            });
+            reports.push(Report {
+                path: path!["verso", "allocations", tag, "retained"],
+                kind: ReportKind::NonExplicitSize,
+                size: stats.retained() as usize,
+            });
+        }
+        let shaping = ShapingCache::shared().lock().unwrap().stats();
+        for (name, size) in [
+            ("size", shaping.size),
+            ("hits", shaping.hits as usize),
+            ("misses", shaping.misses as usize),
+            ("hit-rate-permille", (shaping.hit_rate() * 1000.0) as usize),
+        ] {
+            reports.push(Report {
+                path: path!["verso", "shaping-cache", name],
+                kind: ReportKind::NonExplicitSize,
+                size,
+            });
+        }
+        reports
+    }
+
+    /// Save WebRender's scene and frame into `directory`, or into a new directory of
+    /// `webrender-captures` in the working or temporary directory, returning where it went.
+    pub fn capture_webrender_frame(&mut self, directory: Option<PathBuf>) -> Option<PathBuf> {
+        let directory = match directory {
+            Some(directory) => fs::create_dir_all(&directory).ok().map(|_| directory),
+            None => {
+                let capture_id = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_millis())
+                    .to_string();
+                [std::env::current_dir(), Ok(std::env::temp_dir())]
+                    .into_iter()
+                    .filter_map(|root| root.ok())
+                    .map(|root| root.join("webrender-captures").join(&capture_id))
+                    .find(|directory| fs::create_dir_all(directory).is_ok())
+            }
+        };
+        let Some(directory) = directory else {
+            warn!("Verso couldn't create a directory for the WebRender capture");
+            return None;
+        };
+        self.webrender_api
+            .save_capture(directory.clone(), CaptureBits::SCENE | CaptureBits::FRAME);
+        log::info!(
+            "Verso saved a WebRender capture into {}",
+            directory.display()
+        );
+        Some(directory)
+    }
+
     /// Update debug option of the webrender.
     pub fn toggle_webrender_debug(&mut self, option: WebRenderDebugOption) {
         let Some(webrender) = self.webrender.as_mut() else {
@@ -2151,20 +4123,112 @@ impl IOCompositor {
         let mut txn = Transaction::new();
         self.generate_frame(&mut txn, RenderReasons::TESTING);
         self.webrender_api
-            .send_transaction(self.webrender_document, txn);
+            .send_transaction(self.webrender_document(), txn);
     }
 
-    fn add_image(&mut self, key: ImageKey, desc: ImageDescriptor, data: ImageData, pipeline_id: Option<PipelineId>) {
+    fn add_image(
+        &mut self,
+        key: ImageKey,
+        desc: ImageDescriptor,
+        data: ImageData,
+        pipeline_id: Option<PipelineId>,
+    ) {
         if let Some(id) = pipeline_id {
             if let Some(details) = self.pipeline_details.get_mut(&id) {
                 details.resources.add_image(key);
             }
         }
 
+        if let Some(retained) = &mut self.retained_resources {
+            retained.set_image(key, desc, &data);
+        }
+        self.animated_images
+            .on_image_added(key, desc, &data, Instant::now());
+        let tiling = self.image_tiling(&desc);
         let mut txn = Transaction::new();
-        txn.add_image(key, desc, data.into(), None);
+        txn.add_image(key, desc, data, tiling);
         self.webrender_api
-            .send_transaction(self.webrender_document, txn);
+            .send_transaction(self.webrender_document(), txn);
+    }
+
+    /// Size of the tiles an image is split into, if it is larger than the configured tile size.
+    fn image_tiling(&self, desc: &ImageDescriptor) -> Option<TileSize> {
+        self.image_tile_size.filter(|&tile_size| {
+            desc.size.width > i32::from(tile_size) || desc.size.height > i32::from(tile_size)
+        })
+    }
+
+    /// Change a WebRender option that can be changed while it runs.
+    pub fn set_renderer_option(&mut self, option: RuntimeRendererOption) {
+        match option {
+            RuntimeRendererOption::PictureTileSize(size) => self
+                .webrender_api
+                .send_debug_cmd(DebugCommand::SetPictureTileSize(Some(size))),
+            RuntimeRendererOption::BatchLookbackCount(count) => self
+                .webrender_api
+                .send_debug_cmd(DebugCommand::SetBatchingLookback(count as u32)),
+            RuntimeRendererOption::Multithreading(enabled) => self
+                .webrender_api
+                .set_parameter(Parameter::Bool(BoolParameter::Multithreading, enabled)),
+            RuntimeRendererOption::ImageTileSize(size) => self.image_tile_size = Some(size),
+        }
+        log::info!("Verso set renderer option {option:?}");
+
+        let mut txn = Transaction::new();
+        self.generate_frame(&mut txn, RenderReasons::SCENE);
+        self.webrender_api
+            .send_transaction(self.webrender_document(), txn);
+    }
+
+    /// Check whether a system font has color glyphs on another thread, the whole file is read.
+    ///
+    /// The result is taken by [`Self::receive_color_fonts`].
+    #[cfg(linux)]
+    fn check_color_font(&self, font_key: FontKey, native_handle: &webrender_api::NativeFontHandle) {
+        let sender = self.color_font_checks.0.clone();
+        let (path, index) = (native_handle.path.clone(), native_handle.index);
+        if let Err(error) = std::thread::Builder::new()
+            .name("ColorFontCheck".to_owned())
+            .spawn(move || {
+                if std::fs::read(&path).is_ok_and(|data| emoji::is_color_font(&data, index)) {
+                    let _ = sender.send(font_key);
+                }
+            })
+        {
+            warn!("Failed to check whether a system font has color glyphs: {error}");
+        }
+    }
+
+    /// Take the system fonts found to have color glyphs, and add their instances added in the
+    /// meantime again with embedded bitmaps, under the same keys.
+    fn receive_color_fonts(&mut self) {
+        let mut transaction = Transaction::new();
+        let mut changed = false;
+        while let Ok(font_key) = self.color_font_checks.1.try_recv() {
+            self.color_fonts.insert(font_key);
+            if !self.color_glyphs {
+                continue;
+            }
+            for (&instance_key, (key, size, flags)) in self.font_instances.iter_mut() {
+                if *key != font_key {
+                    continue;
+                }
+                *flags |= FontInstanceFlags::EMBEDDED_BITMAPS;
+                transaction.add_font_instance(
+                    instance_key,
+                    font_key,
+                    *size,
+                    Some(self.text_rendering.font_instance_options(*flags)),
+                    self.text_rendering.platform_options(),
+                    Vec::new(),
+                );
+                changed = true;
+            }
+        }
+        if changed {
+            self.webrender_api
+                .send_transaction(self.webrender_document(), transaction);
+        }
     }
 
     fn add_font_instance(
@@ -2175,40 +4239,124 @@ impl IOCompositor {
         flags: FontInstanceFlags,
         pipeline_id: Option<PipelineId>,
     ) {
+        self.receive_color_fonts();
         if let Some(id) = pipeline_id {
             if let Some(details) = self.pipeline_details.get_mut(&id) {
                 details.resources.add_font_instance(instance_key);
             }
         }
 
-        let mut transaction = Transaction::new();
-        let font_instance_options = FontInstanceOptions {
-            flags,
-            ..Default::default()
+        let flags = if self.color_glyphs && self.color_fonts.contains(&font_key) {
+            flags | FontInstanceFlags::EMBEDDED_BITMAPS
+        } else {
+            flags
         };
+        self.font_instances
+            .insert(instance_key, (font_key, size, flags));
+        let mut transaction = Transaction::new();
         transaction.add_font_instance(
             instance_key,
             font_key,
             size,
-            Some(font_instance_options),
-            None,
+            Some(self.text_rendering.font_instance_options(flags)),
+            self.text_rendering.platform_options(),
             Vec::new(),
         );
         self.webrender_api
-            .send_transaction(self.webrender_document, transaction);
+            .send_transaction(self.webrender_document(), transaction);
+    }
+
+    /// Change how text is anti-aliased and hinted.
+    ///
+    /// Every font instance is replaced under its existing key, so display lists stay
+    /// valid and only the glyphs are rasterized again for the next frame.
+    pub fn set_text_rendering(&mut self, config: TextRendering) {
+        if !self.text_rendering.set_config(config) || self.font_instances.is_empty() {
+            return;
+        }
+
+        let mut transaction = Transaction::new();
+        for (&instance_key, &(font_key, size, flags)) in &self.font_instances {
+            transaction.add_font_instance(
+                instance_key,
+                font_key,
+                size,
+                Some(self.text_rendering.font_instance_options(flags)),
+                self.text_rendering.platform_options(),
+                Vec::new(),
+            );
+        }
+        self.generate_frame(&mut transaction, RenderReasons::RESOURCE_UPDATE);
+        self.webrender_api
+            .send_transaction(self.webrender_document(), transaction);
     }
 
-    fn add_font(&mut self, font_key: FontKey, index: u32, data: Arc<IpcSharedMemory>, pipeline_id: Option<PipelineId>) {
+    /// Shape a line of compositor text, for the HUD, overlays and placeholders.
+    ///
+    /// The font and the font instance of `size` are added to `transaction` the first time they
+    /// are used, so the run must be pushed to a display list sent in or after it.
+    pub fn text_run(
+        &mut self,
+        transaction: &mut Transaction,
+        text: &str,
+        size: f32,
+    ) -> Option<TextRun> {
+        let font_key = match self.text.font_key() {
+            Some(font_key) => font_key,
+            None => {
+                let (data, index) = self.text.font()?.data();
+                let data = data.to_vec();
+                let font_key = self.webrender_api.generate_font_key();
+                transaction.add_raw_font(font_key, data, index);
+                self.text.set_font_key(font_key);
+                font_key
+            }
+        };
+        let font_instance = match self.text.font_instance(size) {
+            Some(font_instance) => font_instance,
+            None => {
+                let font_instance = self.webrender_api.generate_font_instance_key();
+                let flags = FontInstanceFlags::empty();
+                self.font_instances
+                    .insert(font_instance, (font_key, size, flags));
+                transaction.add_font_instance(
+                    font_instance,
+                    font_key,
+                    size,
+                    Some(self.text_rendering.font_instance_options(flags)),
+                    self.text_rendering.platform_options(),
+                    Vec::new(),
+                );
+                self.text.set_font_instance(size, font_instance);
+                font_instance
+            }
+        };
+        self.text.run(text, size, font_instance)
+    }
+
+    fn add_font(
+        &mut self,
+        font_key: FontKey,
+        index: u32,
+        data: Arc<IpcSharedMemory>,
+        pipeline_id: Option<PipelineId>,
+    ) {
         if let Some(id) = pipeline_id {
             if let Some(details) = self.pipeline_details.get_mut(&id) {
                 details.resources.add_font(font_key);
             }
         }
 
+        if emoji::is_color_font(&data, index) {
+            self.color_fonts.insert(font_key);
+        }
+        if let Some(retained) = &mut self.retained_resources {
+            retained.add_raw_font(font_key, data.clone(), index);
+        }
         let mut transaction = Transaction::new();
         transaction.add_raw_font(font_key, (**data).into(), index);
         self.webrender_api
-            .send_transaction(self.webrender_document, transaction);
+            .send_transaction(self.webrender_document(), transaction);
     }
 
     /// Send all pending paint metrics messages after a composite operation, which may advance
@@ -2220,8 +4368,16 @@ impl IOCompositor {
     /// the list.
     fn send_pending_paint_metrics_messages_after_composite(&mut self) {
         let paint_time = CrossProcessInstant::now();
-        let document_id = self.webrender_document;
-        for (_, pipeline_id) in self.webviews.iter_mut() {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let current_document = self.webrender_document();
+        for (webview_id, pipeline_id) in self.webviews.iter_mut() {
+            let document_id = self
+                .webview_windows
+                .get(webview_id)
+                .and_then(|window_id| self.documents.get(window_id))
+                .map_or(current_document, |document| document.id);
             debug_assert!(self.pipeline_details.contains_key(pipeline_id));
             let pipeline = self.pipeline_details.get_mut(pipeline_id).unwrap();
             let Some(current_epoch) = self
@@ -2249,6 +4405,12 @@ impl IOCompositor {
                         warn!("Sending paint metric event to constellation failed ({error:?}).");
                     }
                     pipeline.first_paint_metric = PaintMetricState::Sent;
+                    self.paint_metrics.push(PaintMetric {
+                        webview: format!("{webview_id:?}"),
+                        kind: PaintMetricKind::FirstPaint,
+                        timestamp_ms,
+                        first_reflow,
+                    });
                 }
                 _ => {}
             }
@@ -2265,6 +4427,12 @@ impl IOCompositor {
                         warn!("Sending paint metric event to constellation failed ({error:?}).");
                     }
                     pipeline.first_contentful_paint_metric = PaintMetricState::Sent;
+                    self.paint_metrics.push(PaintMetric {
+                        webview: format!("{webview_id:?}"),
+                        kind: PaintMetricKind::FirstContentfulPaint,
+                        timestamp_ms,
+                        first_reflow,
+                    });
                 }
                 _ => {}
             }
@@ -2272,14 +4440,29 @@ impl IOCompositor {
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum UnableToComposite {
-    NotReadyToPaintImage(NotReadyToPaint),
+/// Reports of the GPU memory WebRender's renderer allocated, by what it is used for
+fn gpu_memory_reports(report: &MemoryReport) -> Vec<Report> {
+    [
+        ("gpu-cache", report.gpu_cache_textures),
+        ("vertex-data", report.vertex_data_textures),
+        ("render-targets", report.render_target_textures),
+        ("picture-tiles", report.picture_tile_textures),
+        ("texture-cache", report.atlas_textures),
+        ("standalone", report.standalone_textures),
+        ("depth-targets", report.depth_target_textures),
+        ("upload-pbos", report.texture_upload_pbos),
+    ]
+    .into_iter()
+    .map(|(name, size)| Report {
+        path: path!["webrender", "gpu", name],
+        kind: ReportKind::NonExplicitSize,
+        size,
+    })
+    .collect()
 }
 
 #[derive(Debug, PartialEq)]
 enum NotReadyToPaint {
-    AnimationsActive,
     JustNotifiedConstellation,
     WaitingOnConstellation,
 }
@@ -2305,8 +4488,8 @@ impl FrameTreeId {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use webrender_api::{FontKey, FontInstanceKey, ImageKey};
     use mockall::predicate::*;
+    use webrender_api::{FontInstanceKey, FontKey, ImageKey};
 
     #[test]
     fn test_pipeline_resources_clear() {
@@ -2320,22 +4503,50 @@ mod tests {
         resources.add_image(image_key);
 
         let mut mock_txn = MockTransactionTrait::new();
-        
-        mock_txn.expect_delete_font()
+
+        mock_txn
+            .expect_delete_font()
             .with(eq(font_key))
             .times(1)
             .return_const(());
-            
-        mock_txn.expect_delete_font_instance()
+
+        mock_txn
+            .expect_delete_font_instance()
             .with(eq(instance_key))
             .times(1)
             .return_const(());
-            
-        mock_txn.expect_delete_image()
+
+        mock_txn
+            .expect_delete_image()
             .with(eq(image_key))
             .times(1)
             .return_const(());
 
         resources.clear(&mut mock_txn);
     }
+
+    #[test]
+    fn test_gpu_memory_reports() {
+        let report = MemoryReport {
+            picture_tile_textures: 4096,
+            atlas_textures: 1024,
+            ..Default::default()
+        };
+        let reports = gpu_memory_reports(&report);
+        let size_of = |name: &str| {
+            reports
+                .iter()
+                .find(|report| report.path == path!["webrender", "gpu", name])
+                .map(|report| report.size)
+        };
+
+        assert_eq!(size_of("picture-tiles"), Some(4096));
+        assert_eq!(size_of("texture-cache"), Some(1024));
+        assert_eq!(size_of("render-targets"), Some(0));
+        assert!(
+            reports
+                .iter()
+                .all(|report| matches!(report.kind, ReportKind::NonExplicitSize))
+        );
+    }
 }
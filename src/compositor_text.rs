@@ -0,0 +1,577 @@
+//! Compositor Text
+//!
+//! Draws short strings straight into the compositor's own display lists, for the
+//! HUD, overlays, splash screens and error placeholders, without loading a page
+//! for them. The font is registered with WebRender like the fonts Servo adds, so
+//! its instances follow the text rendering settings, and runs are shaped once
+//! and kept in the [`ShapingCache`].
+//!
+//! Shaping is deliberately minimal: characters map to glyphs through the font's
+//! character map and advance by their horizontal metrics, left to right, without
+//! kerning, ligatures or font fallback. That's enough for labels and numbers in
+//! a single script, anything richer belongs in a webview. The font is the first
+//! common system sans-serif font found, unless one is set.
+//!
+//! The cache is bounded in bytes, the bound shrinks under memory pressure, evicting
+//! the least recently used runs first, and runs are dropped with their font
+//! instance. Page text isn't in it: Servo's layout shapes that with HarfBuzz in its
+//! own fonts crate, with caches Verso can't reach.
+
+use std::{
+    collections::HashMap,
+    mem::size_of,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use webrender_api::{
+    ColorF, CommonItemProperties, DisplayListBuilder, FontInstanceKey, FontKey, GlyphInstance,
+    SpaceAndClipInfo,
+    units::{LayoutPoint, LayoutRect, LayoutSize},
+};
+
+/// Sans-serif fonts looked up when no font is set, in order
+#[cfg(linux)]
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+];
+#[cfg(apple)]
+const SYSTEM_FONTS: &[&str] = &[
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/System/Library/Fonts/Geneva.ttf",
+    "/Library/Fonts/Arial.ttf",
+];
+#[cfg(windows)]
+const SYSTEM_FONTS: &[&str] = &[
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+#[cfg(not(any(linux, apple, windows)))]
+const SYSTEM_FONTS: &[&str] = &[];
+
+/// Script compositor text is shaped as, ISO 15924 "Common"
+const COMMON_SCRIPT: [u8; 4] = *b"Zyyy";
+
+/// Default upper bound of the shaping cache, in bytes
+const DEFAULT_CAPACITY: usize = 8 * 1024 * 1024;
+
+/// Estimated bookkeeping cost of a shaping cache entry, in bytes
+const ENTRY_OVERHEAD: usize = 64;
+
+static SHARED: LazyLock<Mutex<ShapingCache>> = LazyLock::new(Default::default);
+
+/// A font file compositor text is drawn with
+#[derive(Clone, Debug)]
+pub struct TextFont {
+    data: Arc<Vec<u8>>,
+    index: u32,
+}
+
+impl TextFont {
+    /// Load the face at `index` of a font file
+    pub fn load(path: &Path, index: u32) -> Result<Self, String> {
+        let data = std::fs::read(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        Self::from_data(data, index)
+            .map_err(|error| format!("invalid font {}: {error}", path.display()))
+    }
+
+    /// Use the face at `index` of font data
+    pub fn from_data(data: Vec<u8>, index: u32) -> Result<Self, String> {
+        ttf_parser::Face::parse(&data, index).map_err(|error| error.to_string())?;
+        Ok(Self {
+            data: Arc::new(data),
+            index,
+        })
+    }
+
+    /// The first common sans-serif font of the system
+    pub fn system_default() -> Option<Self> {
+        SYSTEM_FONTS
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .find_map(|path| Self::load(&path, 0).ok())
+    }
+
+    /// Shape `text` at `size` pixels per em
+    pub fn shape(&self, text: &str, size: f32) -> ShapedRun {
+        let Ok(face) = ttf_parser::Face::parse(&self.data, self.index) else {
+            return ShapedRun::default();
+        };
+        let scale = size / face.units_per_em() as f32;
+        let mut advance = 0.0;
+        let glyphs = text
+            .char_indices()
+            .filter(|(_, character)| !character.is_control())
+            .map(|(cluster, character)| {
+                let glyph = face
+                    .glyph_index(character)
+                    .unwrap_or(ttf_parser::GlyphId(0));
+                let glyph_advance =
+                    face.glyph_hor_advance(glyph).unwrap_or_default() as f32 * scale;
+                advance += glyph_advance;
+                ShapedGlyph {
+                    glyph: glyph.0 as u32,
+                    cluster: cluster as u32,
+                    advance: glyph_advance,
+                    offset: (0.0, 0.0),
+                }
+            })
+            .collect();
+        ShapedRun { glyphs, advance }
+    }
+
+    /// Ascent and descent at `size` pixels per em, the descent is positive
+    pub fn vertical_metrics(&self, size: f32) -> (f32, f32) {
+        let Ok(face) = ttf_parser::Face::parse(&self.data, self.index) else {
+            return (size, 0.0);
+        };
+        let scale = size / face.units_per_em() as f32;
+        (
+            face.ascender() as f32 * scale,
+            -(face.descender() as f32) * scale,
+        )
+    }
+
+    /// The font data and face index
+    pub fn data(&self) -> (&Arc<Vec<u8>>, u32) {
+        (&self.data, self.index)
+    }
+}
+
+/// A shaped line of compositor text, ready to be pushed to a display list
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    font_instance: FontInstanceKey,
+    run: Arc<ShapedRun>,
+    ascent: f32,
+    descent: f32,
+}
+
+impl TextRun {
+    /// Size of the line box
+    pub fn size(&self) -> LayoutSize {
+        LayoutSize::new(self.run.advance, self.ascent + self.descent)
+    }
+
+    /// Glyphs of the line with its top left corner at `origin`
+    pub fn glyphs(&self, origin: LayoutPoint) -> Vec<GlyphInstance> {
+        let baseline = origin.y + self.ascent;
+        let mut pen = origin.x;
+        self.run
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let instance = GlyphInstance {
+                    index: glyph.glyph,
+                    point: LayoutPoint::new(pen + glyph.offset.0, baseline + glyph.offset.1),
+                };
+                pen += glyph.advance;
+                instance
+            })
+            .collect()
+    }
+
+    /// Draw the line with its top left corner at `origin`
+    pub fn push(
+        &self,
+        builder: &mut DisplayListBuilder,
+        space_and_clip: &SpaceAndClipInfo,
+        origin: LayoutPoint,
+        color: ColorF,
+    ) {
+        let bounds = LayoutRect::from_origin_and_size(origin, self.size());
+        builder.push_text(
+            &CommonItemProperties::new(bounds, *space_and_clip),
+            bounds,
+            &self.glyphs(origin),
+            self.font_instance,
+            color,
+            None,
+        );
+    }
+}
+
+/// The font and font instances of compositor text
+#[derive(Debug, Default)]
+pub struct CompositorText {
+    font: Option<TextFont>,
+    font_key: Option<FontKey>,
+    /// Font instances by size in pixels per em, as bits
+    instances: HashMap<u32, FontInstanceKey>,
+    looked_up_system_font: bool,
+}
+
+impl CompositorText {
+    /// Draw with `font` instead of the system font, takes effect for runs shaped from now on
+    pub fn set_font(&mut self, font: TextFont) {
+        self.font = Some(font);
+        self.font_key = None;
+        self.instances.clear();
+        self.looked_up_system_font = true;
+    }
+
+    /// The font to draw with, looking up the system font the first time
+    pub fn font(&mut self) -> Option<&TextFont> {
+        if self.font.is_none() && !self.looked_up_system_font {
+            self.looked_up_system_font = true;
+            self.font = TextFont::system_default();
+            if self.font.is_none() {
+                log::warn!("No system font found for compositor text");
+            }
+        }
+        self.font.as_ref()
+    }
+
+    /// The registered font key, if the font was registered
+    pub fn font_key(&self) -> Option<FontKey> {
+        self.font_key
+    }
+
+    /// Note the key the font was registered under
+    pub fn set_font_key(&mut self, font_key: FontKey) {
+        self.font_key = Some(font_key);
+    }
+
+    /// The registered font instance of `size`
+    pub fn font_instance(&self, size: f32) -> Option<FontInstanceKey> {
+        self.instances.get(&size.to_bits()).copied()
+    }
+
+    /// Note the key the font instance of `size` was registered under
+    pub fn set_font_instance(&mut self, size: f32, font_instance: FontInstanceKey) {
+        self.instances.insert(size.to_bits(), font_instance);
+    }
+
+    /// Shape `text` with a registered font instance of `size`
+    pub fn run(
+        &mut self,
+        text: &str,
+        size: f32,
+        font_instance: FontInstanceKey,
+    ) -> Option<TextRun> {
+        let font = self.font()?.clone();
+        let run = ShapingCache::shared().lock().unwrap().get_or_shape(
+            ShapingKey {
+                font_instance,
+                script: COMMON_SCRIPT,
+                direction: TextDirection::Ltr,
+                text: text.into(),
+            },
+            |key| font.shape(&key.text, size),
+        );
+        let (ascent, descent) = font.vertical_metrics(size);
+        Some(TextRun {
+            font_instance,
+            run,
+            ascent,
+            descent,
+        })
+    }
+}
+
+/// Direction of a text run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextDirection {
+    /// Left to right
+    Ltr,
+    /// Right to left
+    Rtl,
+}
+
+/// What a shaped run depends on
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShapingKey {
+    /// Font instance the run is shaped with
+    pub font_instance: FontInstanceKey,
+    /// ISO 15924 script tag, e.g. `*b"Latn"`
+    pub script: [u8; 4],
+    /// Direction of the run
+    pub direction: TextDirection,
+    /// The text of the run
+    pub text: Box<str>,
+}
+
+/// A glyph positioned by the shaper
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// Glyph index in the font
+    pub glyph: u32,
+    /// Byte offset of the first character of the glyph's cluster
+    pub cluster: u32,
+    /// Horizontal advance, in pixels
+    pub advance: f32,
+    /// Offset from the pen position, in pixels
+    pub offset: (f32, f32),
+}
+
+/// A shaped text run
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapedRun {
+    /// Glyphs in visual order
+    pub glyphs: Vec<ShapedGlyph>,
+    /// Total advance of the run, in pixels
+    pub advance: f32,
+}
+
+/// Counters of a [`ShapingCache`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShapingCacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to shape
+    pub misses: u64,
+    /// Runs evicted to stay within the capacity
+    pub evictions: u64,
+    /// Runs currently cached
+    pub entries: usize,
+    /// Estimated size of the cached runs, in bytes
+    pub size: usize,
+}
+
+impl ShapingCacheStats {
+    /// Share of lookups answered from the cache, from 0 to 1
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+struct Entry {
+    run: Arc<ShapedRun>,
+    size: usize,
+    last_used: u64,
+}
+
+/// Shaped runs by [`ShapingKey`], evicting the least recently used
+pub struct ShapingCache {
+    entries: HashMap<ShapingKey, Entry>,
+    base_capacity: usize,
+    capacity: usize,
+    clock: u64,
+    stats: ShapingCacheStats,
+}
+
+impl Default for ShapingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ShapingCache {
+    /// Create a cache holding up to `capacity` bytes of runs
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            base_capacity: capacity,
+            capacity,
+            clock: 0,
+            stats: ShapingCacheStats::default(),
+        }
+    }
+
+    /// The cache shared by the whole process
+    pub fn shared() -> &'static Mutex<Self> {
+        &SHARED
+    }
+
+    /// The run for `key`, calling `shape` to shape it on a miss
+    pub fn get_or_shape(
+        &mut self,
+        key: ShapingKey,
+        shape: impl FnOnce(&ShapingKey) -> ShapedRun,
+    ) -> Arc<ShapedRun> {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.clock;
+            self.stats.hits += 1;
+            return entry.run.clone();
+        }
+
+        self.stats.misses += 1;
+        let run = Arc::new(shape(&key));
+        let size = key.text.len() + run.glyphs.len() * size_of::<ShapedGlyph>() + ENTRY_OVERHEAD;
+        if size <= self.capacity {
+            self.stats.size += size;
+            self.entries.insert(
+                key,
+                Entry {
+                    run: run.clone(),
+                    size,
+                    last_used: self.clock,
+                },
+            );
+            self.evict();
+        }
+        run
+    }
+
+    /// Scale the capacity by the memory pressure cache reduction factor, evicting as needed
+    pub fn set_capacity_factor(&mut self, factor: f32) {
+        self.capacity = (self.base_capacity as f64 * factor.clamp(0.0, 1.0) as f64) as usize;
+        self.evict();
+    }
+
+    /// Drop the runs shaped with a deleted font instance
+    pub fn remove_font_instance(&mut self, font_instance: FontInstanceKey) {
+        let stats = &mut self.stats;
+        self.entries.retain(|key, entry| {
+            let keep = key.font_instance != font_instance;
+            if !keep {
+                stats.size -= entry.size;
+            }
+            keep
+        });
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> ShapingCacheStats {
+        ShapingCacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Evict the least recently used runs until the cache fits in 3/4 of its capacity
+    ///
+    /// Evicting below the capacity keeps eviction rare when the cache is full.
+    fn evict(&mut self) {
+        if self.stats.size <= self.capacity {
+            return;
+        }
+        let target = self.capacity / 4 * 3;
+        let mut by_age: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort_unstable_by_key(|(last_used, _)| *last_used);
+        for (_, key) in by_age {
+            if self.stats.size <= target {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                self.stats.size -= entry.size;
+                self.stats.evictions += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use webrender_api::IdNamespace;
+
+    use super::*;
+
+    #[test]
+    fn test_glyph_positions() {
+        let run = TextRun {
+            font_instance: FontInstanceKey::default(),
+            run: Arc::new(ShapedRun {
+                glyphs: vec![
+                    ShapedGlyph {
+                        glyph: 7,
+                        cluster: 0,
+                        advance: 10.0,
+                        offset: (0.0, 0.0),
+                    },
+                    ShapedGlyph {
+                        glyph: 8,
+                        cluster: 1,
+                        advance: 6.0,
+                        offset: (1.0, -2.0),
+                    },
+                ],
+                advance: 16.0,
+            }),
+            ascent: 12.0,
+            descent: 4.0,
+        };
+        assert_eq!(run.size(), LayoutSize::new(16.0, 16.0));
+        let glyphs = run.glyphs(LayoutPoint::new(100.0, 50.0));
+        assert_eq!(glyphs[0].index, 7);
+        assert_eq!(glyphs[0].point, LayoutPoint::new(100.0, 62.0));
+        assert_eq!(glyphs[1].point, LayoutPoint::new(111.0, 60.0));
+    }
+
+    #[test]
+    fn test_invalid_font() {
+        assert!(TextFont::from_data(b"not a font".to_vec(), 0).is_err());
+        let mut text = CompositorText::default();
+        text.looked_up_system_font = true;
+        assert!(text.font().is_none());
+        assert!(text.run("FPS", 12.0, FontInstanceKey::default()).is_none());
+    }
+
+    fn key(text: &str) -> ShapingKey {
+        ShapingKey {
+            font_instance: FontInstanceKey::new(IdNamespace(1), 1),
+            script: *b"Latn",
+            direction: TextDirection::Ltr,
+            text: text.into(),
+        }
+    }
+
+    fn shape(key: &ShapingKey) -> ShapedRun {
+        ShapedRun {
+            glyphs: key
+                .text
+                .char_indices()
+                .map(|(cluster, c)| ShapedGlyph {
+                    glyph: c as u32,
+                    cluster: cluster as u32,
+                    advance: 8.0,
+                    offset: (0.0, 0.0),
+                })
+                .collect(),
+            advance: 8.0 * key.text.len() as f32,
+        }
+    }
+
+    #[test]
+    fn test_hits_and_misses() {
+        let mut cache = ShapingCache::default();
+        let first = cache.get_or_shape(key("hello"), shape);
+        let second = cache.get_or_shape(key("hello"), |_| unreachable!());
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let mut rtl = key("hello");
+        rtl.direction = TextDirection::Rtl;
+        cache.get_or_shape(rtl, shape);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+
+        cache.remove_font_instance(FontInstanceKey::new(IdNamespace(1), 1));
+        assert_eq!((cache.stats().entries, cache.stats().size), (0, 0));
+    }
+
+    #[test]
+    fn test_memory_pressure_evicts_least_recently_used() {
+        let mut cache = ShapingCache::new(4096);
+        for word in ["one", "two", "three", "four"] {
+            cache.get_or_shape(key(word), shape);
+        }
+        cache.get_or_shape(key("one"), shape);
+
+        cache.set_capacity_factor(0.1);
+        let stats = cache.stats();
+        assert!(stats.size <= 4096 / 10);
+        assert!(stats.evictions > 0);
+        // The most recently used run survives.
+        cache.get_or_shape(key("one"), |_| unreachable!());
+    }
+}
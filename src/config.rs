@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
@@ -17,9 +18,24 @@ use servo_config::{
     opts::{Opts, OutputOptions, set_options},
     prefs::Preferences,
 };
-use versoview_messages::{ConfigFromController, UserScript};
+use versoview_messages::{
+    AddressInputSettings, ClockOverride, ColorManagement, ConfigFromController, ContentSettings,
+    CursorTheme, EmojiSettings, FlightRecorderSettings, GestureBindings, GlContextSettings,
+    Http3Settings, JitPolicy, NetworkContext, OutputColorDepth, Playlist, PresentMode,
+    RecoveryPolicy, RenderValidationSettings, SafeModeSettings, ScriptPolicy, SessionManifest,
+    SharedTextureSettings, SmoothScrolling, StabilityOptions, TextRendering, UserScript,
+};
 use winit::window::{Fullscreen, WindowAttributes};
 
+use crate::{
+    renderer_options::RendererOptionsConfig,
+    rendering::parse_present_mode,
+    script_policy, session,
+    soak::SoakConfig,
+    svg_raster::{RasterSize, SvgRasterizer},
+    text_rendering,
+};
+
 /// Servo time profile settings
 #[derive(Clone, Debug)]
 pub struct ProfilerSettings {
@@ -60,11 +76,75 @@ pub struct CliArgs {
     pub userscripts_directory: Option<String>,
     /// Initial window's zoom level
     pub zoom_level: Option<f32>,
+    /// WebRender renderer option overrides in `name=value` form
+    pub renderer_options: Vec<String>,
+    /// Presentation mode
+    pub present_mode: Option<PresentMode>,
+    /// Don't run scripts provided by pages
+    pub disable_javascript: bool,
+    /// Don't apply styles provided by pages
+    pub disable_author_styles: bool,
+    /// Convert images to this output color space, `srgb` or the path of an ICC profile
+    pub color_profile: Option<String>,
+    /// Text rendering overrides in `name=value` form
+    pub text_rendering: Vec<String>,
+    /// Emoji font used when page fonts have no glyph for an emoji
+    pub emoji_font: Option<PathBuf>,
+    /// Render emoji in monochrome
+    pub no_color_emoji: bool,
+    /// Disable WebAssembly
+    pub disable_wasm: bool,
+    /// JavaScript JIT tiers allowed
+    pub jit: Option<JitPolicy>,
+    /// Most workers a page may run at once
+    pub max_workers: Option<u32>,
+    /// Use HTTP/3 for Verso's own requests where available
+    pub http3: bool,
+    /// Origins never reached over HTTP/3
+    pub http3_disabled_origins: Vec<String>,
+    /// Network contexts in `name=proxy` form
+    pub network_contexts: Vec<String>,
+    /// Network context of new webviews
+    pub default_network_context: Option<String>,
+    /// Order page requests by rendering impact
+    pub prioritize_requests: bool,
+    /// Windows and webviews to open at startup
+    pub session: Option<SessionManifest>,
+    /// Where to save the session on exit
+    pub export_session: Option<PathBuf>,
+    /// Automatic recovery of crashed or hung webviews
+    pub recovery: RecoveryPolicy,
+    /// Recent input, compositor messages and frames to keep for debugging
+    pub flight_recorder: FlightRecorderSettings,
+    /// Requirements on the GL context
+    pub gl_context: GlContextSettings,
+    /// Safe mode after repeated startup crashes
+    pub safe_mode: SafeModeSettings,
+    /// Log event loop iterations longer than this, in milliseconds
+    pub stall_threshold_ms: Option<u64>,
+    /// Kiosk playlist to rotate through
+    pub playlist: Option<Playlist>,
+    /// When frames are compared with the software renderer
+    pub render_validation: RenderValidationSettings,
+    /// Commands bound to gestures and key chords
+    pub gestures: GestureBindings,
+    /// How address bar input is turned into a URL
+    pub address_input: AddressInputSettings,
+    /// Cursors replacing the ones pages ask for
+    pub cursor_theme: CursorTheme,
+    /// Don't animate mouse wheel and keyboard scrolls
+    pub no_smooth_scrolling: bool,
+    /// Don't drag links, images and selected text out of pages
+    pub no_drag_and_drop: bool,
+    /// Run a soak test, set by the `soak` subcommand
+    pub soak: Option<SoakConfig>,
 }
 
 /// Parse CLI arguments to a [`CliArgs`]
 pub fn parse_cli_args() -> Result<CliArgs, getopts::Fail> {
     let args: Vec<String> = std::env::args().collect();
+    // `verso soak --hours N --urls a,b` runs a soak test, the rest of the options still apply.
+    let is_soak = args.get(1).is_some_and(|arg| arg == "soak");
 
     let mut opts = getopts::Options::new();
     opts.optopt("", "url", "URL to load on start", "docs.rs");
@@ -152,7 +232,211 @@ pub fn parse_cli_args() -> Result<CliArgs, getopts::Fail> {
 
     opts.optopt("", "zoom", "Initial window's zoom level", "1.5");
 
-    let matches: getopts::Matches = opts.parse(&args[1..])?;
+    opts.optmulti(
+        "",
+        "renderer-option",
+        "Override a WebRender renderer option, can be repeated",
+        "batch_lookback_count=10",
+    );
+    opts.optopt(
+        "",
+        "present-mode",
+        "How frames are presented: fifo (vsync), mailbox (falls back to fifo with GL) or immediate (may tear)",
+        "fifo",
+    );
+    opts.optflag(
+        "",
+        "disable-javascript",
+        "Don't run scripts provided by pages",
+    );
+    opts.optflag(
+        "",
+        "disable-author-styles",
+        "Don't apply styles provided by pages",
+    );
+    opts.optopt(
+        "",
+        "color-profile",
+        "Convert images with an embedded color profile to this output color space: srgb or the path of the display's ICC profile",
+        "srgb",
+    );
+    opts.optmulti(
+        "",
+        "text-rendering",
+        "Override text rendering, can be repeated: subpixel-aa=on|off, hinting=none|light|normal|full, lcd-filter=none|default|light|legacy, gamma=1.0..3.0",
+        "hinting=light",
+    );
+    opts.optopt(
+        "",
+        "emoji-font",
+        "Emoji font used when page fonts have no glyph for an emoji, defaults to the system's emoji font",
+        "/usr/share/fonts/NotoColorEmoji.ttf",
+    );
+    opts.optflag("", "no-color-emoji", "Render emoji in monochrome");
+    opts.optflag("", "disable-wasm", "Disable WebAssembly");
+    opts.optopt(
+        "",
+        "jit",
+        "JavaScript JIT tiers allowed: full, baseline or interpreter (no code generation, disables WebAssembly)",
+        "interpreter",
+    );
+    opts.optopt(
+        "",
+        "max-workers",
+        "Most workers a page may run at once (advisory, enforced by a page script)",
+        "4",
+    );
+    opts.optflag(
+        "",
+        "http3",
+        "Use HTTP/3 for favicons and other requests Verso makes itself, once origins advertise it (pages still load over TCP)",
+    );
+    opts.optmulti(
+        "",
+        "http3-disable",
+        "Never use HTTP/3 for Verso's own requests to this origin, can be repeated",
+        "https://example.com",
+    );
+    opts.optmulti(
+        "",
+        "network-context",
+        "Define a network context routing webviews through a proxy, can be repeated",
+        "tor=socks5h://127.0.0.1:9050",
+    );
+    opts.optopt(
+        "",
+        "default-network-context",
+        "Network context new webviews are routed through",
+        "tor",
+    );
+    opts.optflag(
+        "",
+        "prioritize-requests",
+        "Load style sheets, fonts and images in view before images below the fold and prefetches",
+    );
+    opts.optopt(
+        "",
+        "session",
+        "Open the windows and webviews of a session manifest",
+        "session.json",
+    );
+    opts.optopt(
+        "",
+        "export-session",
+        "Save the open windows and webviews as a session manifest on exit",
+        "session.json",
+    );
+    opts.optflag(
+        "",
+        "recovery",
+        "Reload, recreate or restart crashed and hung webviews with the default escalation rules",
+    );
+    opts.optopt(
+        "",
+        "recovery-policy",
+        "Recover crashed and hung webviews with the escalation rules of a JSON file",
+        "recovery.json",
+    );
+    opts.optopt(
+        "",
+        "flight-recorder",
+        "Keep the last seconds of input, compositor messages and frames, and dump them to a directory on crash or jank",
+        "/tmp/verso-flight",
+    );
+    opts.optflag(
+        "",
+        "gl-require-robustness",
+        "Fail to start unless the GL context has robust buffer access",
+    );
+    opts.optflag(
+        "",
+        "gl-lose-context-on-reset",
+        "Ask for a GL context that is lost on GPU resets instead of rendering garbage",
+    );
+    opts.optopt(
+        "",
+        "color-depth",
+        "Color depth of the window surface: standard, auto (10 bits if the display offers them), 10 or fp16",
+        "auto",
+    );
+    opts.optflag(
+        "",
+        "safe-mode",
+        "Start with WebGL, hardware media decoding, the shader cache and config overrides disabled",
+    );
+    opts.optflag(
+        "",
+        "no-safe-mode",
+        "Don't start in safe mode after repeated startup crashes",
+    );
+    opts.optopt(
+        "",
+        "stall-threshold",
+        "Log event loop iterations longer than this many milliseconds, 0 to never log",
+        "100",
+    );
+    opts.optopt(
+        "",
+        "playlist",
+        "Rotate the window through the pages of a JSON playlist file",
+        "playlist.json",
+    );
+    opts.optopt(
+        "",
+        "validate-rendering",
+        "Compare the first frame after each page load with the software renderer, keeping divergent frames in a directory",
+        "/tmp/verso-render-validation",
+    );
+    opts.optopt(
+        "",
+        "gestures",
+        "Bind gestures and key chords to commands from a JSON file",
+        "gestures.json",
+    );
+    opts.optopt(
+        "",
+        "search-template",
+        "Search URL of address bar input that isn't a URL, %s stands for the query",
+        "https://duckduckgo.com/?q=%s",
+    );
+    opts.optopt(
+        "",
+        "cursor-theme",
+        "Replace the cursors pages ask for with the ones of a JSON file",
+        "cursors.json",
+    );
+    opts.optflag(
+        "",
+        "no-smooth-scrolling",
+        "Scroll by whole mouse wheel notches and scrolling keys at once instead of animating",
+    );
+    opts.optflag(
+        "",
+        "no-drag-and-drop",
+        "Leave dragging links, images and selected text to the pages",
+    );
+
+    opts.optopt("", "hours", "Soak test: how long to run", "8");
+    opts.optopt(
+        "",
+        "urls",
+        "Soak test: comma separated URLs, or @FILE with one URL per line",
+        "https://example.com,https://servo.org",
+    );
+    opts.optopt(
+        "",
+        "max-rss-growth",
+        "Soak test: fail if RSS grows by more than this many MiB after warmup",
+        "256",
+    );
+    opts.optopt(
+        "",
+        "max-key-growth",
+        "Soak test: fail if tracked WebRender keys grow by more than this after warmup",
+        "2000",
+    );
+
+    let matches: getopts::Matches = opts.parse(&args[if is_soak { 2 } else { 1 }..])?;
     let url = matches
         .opt_str("url")
         .and_then(|url| match url::Url::parse(&url) {
@@ -243,6 +527,207 @@ pub fn parse_cli_args() -> Result<CliArgs, getopts::Fail> {
         None
     });
 
+    let renderer_options = matches.opt_strs("renderer-option");
+    let present_mode =
+        matches
+            .opt_str("present-mode")
+            .and_then(|mode| match parse_present_mode(&mode) {
+                Some(mode) => Some(mode),
+                None => {
+                    log::error!("Invalid present-mode command line argument: {mode}");
+                    None
+                }
+            });
+
+    let disable_javascript = matches.opt_present("disable-javascript");
+    let disable_author_styles = matches.opt_present("disable-author-styles");
+    let color_profile = matches.opt_str("color-profile");
+    let text_rendering = matches.opt_strs("text-rendering");
+    let emoji_font = matches.opt_str("emoji-font").map(PathBuf::from);
+    let no_color_emoji = matches.opt_present("no-color-emoji");
+    let disable_wasm = matches.opt_present("disable-wasm");
+    let jit = matches
+        .opt_str("jit")
+        .and_then(|jit| match script_policy::parse_jit_policy(&jit) {
+            Some(jit) => Some(jit),
+            None => {
+                log::error!("Invalid jit command line argument: {jit}");
+                None
+            }
+        });
+    let http3 = matches.opt_present("http3");
+    let http3_disabled_origins = matches.opt_strs("http3-disable");
+    let network_contexts = matches.opt_strs("network-context");
+    let default_network_context = matches.opt_str("default-network-context");
+    let prioritize_requests = matches.opt_present("prioritize-requests");
+    let session =
+        matches
+            .opt_str("session")
+            .and_then(|path| match session::load(Path::new(&path)) {
+                Ok(session) => Some(session),
+                Err(error) => {
+                    log::error!("Invalid session command line argument: {error}");
+                    None
+                }
+            });
+    let export_session = matches.opt_str("export-session").map(PathBuf::from);
+    let recovery = match matches.opt_str("recovery-policy") {
+        Some(path) => match fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|policy| {
+                serde_json::from_str::<RecoveryPolicy>(&policy).map_err(|error| error.to_string())
+            }) {
+            Ok(policy) => RecoveryPolicy {
+                enabled: true,
+                ..policy
+            },
+            Err(error) => {
+                log::error!("Invalid recovery-policy command line argument {path}: {error}");
+                RecoveryPolicy::default()
+            }
+        },
+        None => RecoveryPolicy {
+            enabled: matches.opt_present("recovery"),
+            ..Default::default()
+        },
+    };
+    let flight_recorder = match matches.opt_str("flight-recorder") {
+        Some(dump_directory) => FlightRecorderSettings {
+            enabled: true,
+            dump_directory: Some(PathBuf::from(dump_directory)),
+            ..Default::default()
+        },
+        None => FlightRecorderSettings::default(),
+    };
+    let render_validation = match matches.opt_str("validate-rendering") {
+        Some(output_directory) => RenderValidationSettings {
+            validate_on_load: true,
+            output_directory: Some(PathBuf::from(output_directory)),
+            ..Default::default()
+        },
+        None => RenderValidationSettings::default(),
+    };
+    let color_depth = match matches.opt_str("color-depth").as_deref() {
+        None | Some("standard") | Some("8") => OutputColorDepth::Standard,
+        Some("auto") => OutputColorDepth::Auto,
+        Some("10") | Some("rgb10") => OutputColorDepth::Rgb10,
+        Some("fp16") | Some("float16") => OutputColorDepth::Float16,
+        Some(color_depth) => {
+            log::error!("Invalid color-depth command line argument: {color_depth}");
+            OutputColorDepth::Standard
+        }
+    };
+    let gl_context = GlContextSettings {
+        require_robustness: matches.opt_present("gl-require-robustness"),
+        lose_context_on_reset: matches.opt_present("gl-lose-context-on-reset"),
+        color_depth,
+    };
+    let safe_mode = SafeModeSettings {
+        enabled: !matches.opt_present("no-safe-mode"),
+        force: matches.opt_present("safe-mode"),
+        ..Default::default()
+    };
+    let max_workers = match matches.opt_get::<u32>("max-workers") {
+        Ok(max_workers) => max_workers,
+        Err(e) => {
+            log::error!("Invalid max-workers command line argument: {e}");
+            None
+        }
+    };
+    let stall_threshold_ms = match matches.opt_get::<u64>("stall-threshold") {
+        Ok(Some(0)) => None,
+        Ok(Some(stall_threshold_ms)) => Some(stall_threshold_ms),
+        Ok(None) => ConfigFromController::default().stall_threshold_ms,
+        Err(e) => {
+            log::error!("Invalid stall-threshold command line argument: {e}");
+            ConfigFromController::default().stall_threshold_ms
+        }
+    };
+    let playlist = matches.opt_str("playlist").and_then(|path| {
+        match fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|playlist| {
+                serde_json::from_str::<Playlist>(&playlist).map_err(|error| error.to_string())
+            }) {
+            Ok(playlist) => Some(playlist),
+            Err(error) => {
+                log::error!("Invalid playlist command line argument {path}: {error}");
+                None
+            }
+        }
+    });
+    let gestures = matches
+        .opt_str("gestures")
+        .and_then(|path| {
+            match fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|gestures| {
+                    serde_json::from_str::<GestureBindings>(&gestures)
+                        .map_err(|error| error.to_string())
+                }) {
+                Ok(gestures) => Some(gestures),
+                Err(error) => {
+                    log::error!("Invalid gestures command line argument {path}: {error}");
+                    None
+                }
+            }
+        })
+        .unwrap_or_default();
+    let cursor_theme = matches
+        .opt_str("cursor-theme")
+        .and_then(|path| {
+            match fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|theme| {
+                    serde_json::from_str::<CursorTheme>(&theme).map_err(|error| error.to_string())
+                }) {
+                Ok(theme) => Some(theme),
+                Err(error) => {
+                    log::error!("Invalid cursor-theme command line argument {path}: {error}");
+                    None
+                }
+            }
+        })
+        .unwrap_or_default();
+    let mut address_input = AddressInputSettings::default();
+    if let Some(template) = matches.opt_str("search-template") {
+        if template.contains("%s") {
+            address_input.search_template = template;
+        } else {
+            log::error!("Invalid search-template command line argument, it has no %s: {template}");
+        }
+    }
+
+    let soak = is_soak.then(|| {
+        let mut soak = SoakConfig::default();
+        match matches.opt_get::<f64>("hours") {
+            Ok(Some(hours)) if hours > 0.0 => {
+                soak.duration = std::time::Duration::from_secs_f64(hours * 3600.0);
+            }
+            Ok(None) => {}
+            _ => log::error!("Invalid hours command line argument, expected a positive number"),
+        }
+        if let Some(urls) = matches.opt_str("urls") {
+            match SoakConfig::parse_urls(&urls) {
+                Ok(urls) => soak.urls = urls,
+                Err(e) => log::error!("Invalid urls command line argument: {e}"),
+            }
+        }
+        match matches.opt_get::<u64>("max-rss-growth") {
+            Ok(Some(mib)) => soak.max_rss_growth = mib * 1024 * 1024,
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to parse max-rss-growth command line argument: {e}"),
+        }
+        match matches.opt_get::<u64>("max-key-growth") {
+            Ok(Some(keys)) => soak.max_tracked_key_growth = keys,
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to parse max-key-growth command line argument: {e}"),
+        }
+        // Shorten the warmup for short runs so there is something left to measure.
+        soak.warmup = soak.warmup.min(soak.duration / 4);
+        soak
+    });
+
     Ok(CliArgs {
         url,
         resource_dir,
@@ -257,6 +742,37 @@ pub fn parse_cli_args() -> Result<CliArgs, getopts::Fail> {
         inner_size,
         position,
         no_maximized,
+        renderer_options,
+        present_mode,
+        disable_javascript,
+        disable_author_styles,
+        color_profile,
+        text_rendering,
+        emoji_font,
+        no_color_emoji,
+        disable_wasm,
+        jit,
+        max_workers,
+        http3,
+        http3_disabled_origins,
+        network_contexts,
+        default_network_context,
+        prioritize_requests,
+        session,
+        export_session,
+        recovery,
+        flight_recorder,
+        gl_context,
+        safe_mode,
+        stall_threshold_ms,
+        playlist,
+        render_validation,
+        gestures,
+        address_input,
+        cursor_theme,
+        no_smooth_scrolling: matches.opt_present("no-smooth-scrolling"),
+        no_drag_and_drop: matches.opt_present("no-drag-and-drop"),
+        soak,
     })
 }
 
@@ -279,9 +795,72 @@ pub struct Config {
     pub user_scripts: Vec<ServoUserScript>,
     /// Initial window's zoom level
     pub zoom_level: Option<f32>,
+    /// Smallest pinch zoom of a webview
+    pub min_pinch_zoom: f32,
+    /// Largest pinch zoom of a webview
+    pub max_pinch_zoom: f32,
     /// Path to resource directory. If None, Verso will try to get default directory. And if that
     /// still doesn't exist, all resource configuration will set to default values.
     pub resource_dir: PathBuf,
+    /// WebRender renderer option overrides
+    pub renderer_options: RendererOptionsConfig,
+    /// How frames are presented
+    pub present_mode: PresentMode,
+    /// JavaScript and author style switches applied to every webview
+    pub content_settings: ContentSettings,
+    /// Conversion of images to the display's color space
+    pub color_management: ColorManagement,
+    /// Text anti-aliasing and hinting
+    pub text_rendering: TextRendering,
+    /// Emoji font and color glyph rendering
+    pub emoji: EmojiSettings,
+    /// Script engine limits of the profile
+    pub script_policy: ScriptPolicy,
+    /// Time zone, locale and clocks every page sees
+    pub clock_override: Option<ClockOverride>,
+    /// HTTP/3 for embedder-side fetches
+    pub http3: Http3Settings,
+    /// Named network contexts webviews can be routed through
+    pub network_contexts: HashMap<String, NetworkContext>,
+    /// Network context of new webviews
+    pub default_network_context: Option<String>,
+    /// Order page requests by rendering impact
+    pub prioritize_requests: bool,
+    /// Windows and webviews to open at startup
+    pub session: Option<SessionManifest>,
+    /// Where to save the session on exit, only settable from the command line
+    pub export_session: Option<PathBuf>,
+    /// Automatic recovery of crashed or hung webviews
+    pub recovery: RecoveryPolicy,
+    /// Recent input, compositor messages and frames to keep for debugging
+    pub flight_recorder: FlightRecorderSettings,
+    /// Requirements on the GL context
+    pub gl_context: GlContextSettings,
+    /// Safe mode after repeated startup crashes
+    pub safe_mode: SafeModeSettings,
+    /// Event loop iterations longer than this are logged as stalls, in milliseconds
+    pub stall_threshold_ms: Option<u64>,
+    /// Kiosk playlist the first window rotates through
+    pub playlist: Option<Playlist>,
+    /// When frames are compared with the software renderer
+    pub render_validation: RenderValidationSettings,
+    /// Commands bound to gestures and key chords
+    pub gestures: GestureBindings,
+    /// How address bar input is turned into a URL
+    pub address_input: AddressInputSettings,
+    /// Cursors replacing the ones pages ask for
+    pub cursor_theme: CursorTheme,
+    /// When the pages tabs load count as settled, `None` to not wait on them
+    pub page_settled: Option<StabilityOptions>,
+    /// How mouse wheel and keyboard scrolls are animated
+    pub smooth_scrolling: SmoothScrolling,
+    /// Textures the first window's frames are composited into instead of presented, only
+    /// settable from the controller
+    pub shared_texture: Option<SharedTextureSettings>,
+    /// Drag links, images and selected text out of pages
+    pub drag_and_drop: bool,
+    /// Soak test to run, only settable from the command line
+    pub soak: Option<SoakConfig>,
 }
 
 impl Config {
@@ -294,8 +873,14 @@ impl Config {
         user_scripts.extend(
             load_userscripts(cli_args.userscripts_directory).expect("Failed to load userscript"),
         );
-        Self::from_controller_config(ConfigFromController {
-            url: cli_args.url,
+        let url = cli_args.url.or_else(|| {
+            cli_args
+                .soak
+                .as_ref()
+                .and_then(|soak| soak.urls.first().cloned())
+        });
+        let mut config = Self::from_controller_config(ConfigFromController {
+            url,
             with_panel: !cli_args.no_panel,
             devtools_port: cli_args.devtools_port,
             profiler_settings: cli_args.profiler_settings,
@@ -306,8 +891,108 @@ impl Config {
             maximized: !cli_args.no_maximized,
             position: cli_args.position.map(Into::into),
             inner_size: cli_args.inner_size.map(Into::into),
+            renderer_options: cli_args
+                .renderer_options
+                .iter()
+                .filter_map(|option| match option.split_once('=') {
+                    Some((name, value)) => Some((name.to_string(), value.to_string())),
+                    None => {
+                        log::error!("Invalid renderer option '{option}', expected NAME=VALUE");
+                        None
+                    }
+                })
+                .collect(),
+            present_mode: cli_args.present_mode.unwrap_or_default(),
+            content_settings: ContentSettings {
+                javascript_enabled: !cli_args.disable_javascript,
+                author_styles_enabled: !cli_args.disable_author_styles,
+            },
+            color_management: ColorManagement {
+                enabled: cli_args.color_profile.is_some(),
+                output_profile: cli_args
+                    .color_profile
+                    .filter(|profile| !profile.eq_ignore_ascii_case("srgb"))
+                    .map(PathBuf::from),
+            },
+            text_rendering: cli_args.text_rendering.iter().fold(
+                TextRendering::default(),
+                |mut config, option| {
+                    match option.split_once('=') {
+                        Some((name, value)) => {
+                            if let Err(e) = text_rendering::apply_override(&mut config, name, value)
+                            {
+                                log::error!("Ignoring text rendering option '{name}': {e}");
+                            }
+                        }
+                        None => log::error!(
+                            "Invalid text rendering option '{option}', expected NAME=VALUE"
+                        ),
+                    }
+                    config
+                },
+            ),
+            emoji: EmojiSettings {
+                font: cli_args.emoji_font,
+                color_glyphs: !cli_args.no_color_emoji,
+            },
+            script_policy: ScriptPolicy {
+                wasm_enabled: !cli_args.disable_wasm,
+                jit: cli_args.jit.unwrap_or_default(),
+                max_workers: cli_args.max_workers,
+            },
+            clock_override: None,
+            http3: Http3Settings {
+                enabled: cli_args.http3,
+                disabled_origins: cli_args
+                    .http3_disabled_origins
+                    .iter()
+                    .map(|origin| match url::Url::parse(origin) {
+                        Ok(url) => url.origin().ascii_serialization(),
+                        Err(_) => origin.clone(),
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            network_contexts: cli_args
+                .network_contexts
+                .iter()
+                .filter_map(|context| match context.split_once('=') {
+                    Some((name, proxy)) => Some((
+                        name.to_string(),
+                        NetworkContext {
+                            proxy: Some(proxy.to_string()),
+                            ..Default::default()
+                        },
+                    )),
+                    None => {
+                        log::error!("Invalid network context '{context}', expected NAME=PROXY");
+                        None
+                    }
+                })
+                .collect(),
+            default_network_context: cli_args.default_network_context,
+            prioritize_requests: cli_args.prioritize_requests,
+            session: cli_args.session,
+            recovery: cli_args.recovery,
+            flight_recorder: cli_args.flight_recorder,
+            gl_context: cli_args.gl_context,
+            safe_mode: cli_args.safe_mode,
+            stall_threshold_ms: cli_args.stall_threshold_ms,
+            playlist: cli_args.playlist,
+            render_validation: cli_args.render_validation,
+            gestures: cli_args.gestures,
+            address_input: cli_args.address_input,
+            cursor_theme: cli_args.cursor_theme,
+            smooth_scrolling: SmoothScrolling {
+                enabled: !cli_args.no_smooth_scrolling,
+                ..Default::default()
+            },
+            drag_and_drop: !cli_args.no_drag_and_drop,
             ..Default::default()
-        })
+        });
+        config.export_session = cli_args.export_session;
+        config.soak = cli_args.soak;
+        config
     }
 
     /// Create a new configuration for creating Verso instance from the controller config.
@@ -363,6 +1048,16 @@ impl Config {
                     trace_path: profiler_settings.trace_path,
                 });
 
+        let mut renderer_options = RendererOptionsConfig::default();
+        for (name, value) in &config.renderer_options {
+            if let Err(e) = renderer_options.apply_override(name, value) {
+                log::error!("Ignoring renderer option '{name}': {e}");
+            }
+        }
+        for warning in renderer_options.validate() {
+            log::warn!("Renderer options: {warning}");
+        }
+
         Self {
             url: config
                 .url
@@ -381,7 +1076,38 @@ impl Config {
                 })
                 .collect(),
             zoom_level: config.zoom_level,
+            min_pinch_zoom: config.min_pinch_zoom,
+            max_pinch_zoom: config.max_pinch_zoom,
             resource_dir,
+            renderer_options,
+            present_mode: config.present_mode,
+            content_settings: config.content_settings,
+            color_management: config.color_management,
+            text_rendering: config.text_rendering,
+            emoji: config.emoji,
+            script_policy: config.script_policy,
+            clock_override: config.clock_override,
+            http3: config.http3,
+            network_contexts: config.network_contexts,
+            default_network_context: config.default_network_context,
+            prioritize_requests: config.prioritize_requests,
+            session: config.session,
+            export_session: None,
+            recovery: config.recovery,
+            flight_recorder: config.flight_recorder,
+            gl_context: config.gl_context,
+            safe_mode: config.safe_mode,
+            stall_threshold_ms: config.stall_threshold_ms,
+            playlist: config.playlist,
+            render_validation: config.render_validation,
+            gestures: config.gestures,
+            address_input: config.address_input,
+            cursor_theme: config.cursor_theme,
+            page_settled: config.page_settled,
+            smooth_scrolling: config.smooth_scrolling,
+            shared_texture: config.shared_texture,
+            drag_and_drop: config.drag_and_drop,
+            soak: None,
         }
     }
 
@@ -416,14 +1142,16 @@ impl Config {
             };
 
         // Set the preferences of Servo.
-        servo_config::prefs::set(Preferences {
+        let mut preferences = Preferences {
             dom_svg_enabled: true, // Some pages fail to render if this is disabled
             devtools_server_enabled,
             devtools_server_port: devtools_port as i64,
             dom_notification_enabled: true, // experimental feature
             user_agent: self.user_agent.clone(),
             ..Default::default()
-        });
+        };
+        script_policy::apply_preferences(&self.script_policy, &mut preferences);
+        servo_config::prefs::set(preferences);
     }
 }
 
@@ -497,7 +1225,7 @@ impl ProtocolHandler for ResourceReader {
         let path = current_url.path();
         let path = self.0.join(path.strip_prefix('/').unwrap_or(path));
 
-        let response = if let Ok(file) = fs::read(path.clone()) {
+        let response = if let Ok(mut file) = fs::read(path.clone()) {
             let mut response = Response::new(
                 request.current_url(),
                 ResourceFetchTiming::new(request.timing_type()),
@@ -514,6 +1242,28 @@ impl ProtocolHandler for ResourceReader {
                         .typed_insert(ContentType::from(mime::TEXT_JAVASCRIPT)),
                     Some("json") => response.headers.typed_insert(ContentType::json()),
                     Some("html") => response.headers.typed_insert(ContentType::html()),
+                    // Rasterize SVGs requested at a size, e.g. `icon.svg?size=16&scale=2`.
+                    Some("svg") => match current_url
+                        .query()
+                        .and_then(RasterSize::from_query)
+                        .map(|size| SvgRasterizer::shared().rasterize(&file, size))
+                    {
+                        Some(Ok(png)) => {
+                            file = png.to_vec();
+                            response
+                                .headers
+                                .typed_insert(ContentType::from(mime::IMAGE_PNG))
+                        }
+                        Some(Err(error)) => {
+                            log::warn!("Failed to rasterize {current_url}: {error}");
+                            response
+                                .headers
+                                .typed_insert(ContentType::from(mime::IMAGE_SVG))
+                        }
+                        None => response
+                            .headers
+                            .typed_insert(ContentType::from(mime::IMAGE_SVG)),
+                    },
                     _ => response.headers.typed_insert(ContentType::octet_stream()),
                 }
             }
@@ -0,0 +1,134 @@
+//! Cursor Theme
+//!
+//! Cursors the embedder puts in place of the ones pages ask for, to match the
+//! look of an app or to enlarge them on a touch kiosk. A cursor is replaced by
+//! another cursor of the system theme or by an image. Images are decoded once
+//! and cached by the hash of their replacement, so moving between links and
+//! text doesn't decode them again. Servo resolves CSS `cursor: url(...)` to
+//! its fallback keyword before Verso sees it, so the theme is also how custom
+//! cursor images reach the windows.
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    str::FromStr,
+};
+
+use embedder_traits::Cursor;
+use versoview_messages::{CursorReplacement, CursorTheme};
+use winit::{
+    event_loop::ActiveEventLoop,
+    window::{Cursor as WindowCursor, CursorIcon, CustomCursor, CustomCursorSource},
+};
+
+use crate::window::cursor_icon;
+
+/// Window cursors of the pages' cursors, with the replacements of the theme
+#[derive(Debug, Default)]
+pub struct ThemedCursors {
+    theme: CursorTheme,
+    /// Cursors created from images by the hash of their replacement, `None` if it failed
+    images: HashMap<u64, Option<CustomCursor>>,
+}
+
+impl ThemedCursors {
+    /// Replace the cursors of the theme
+    pub fn new(theme: CursorTheme) -> Self {
+        Self {
+            theme,
+            images: HashMap::new(),
+        }
+    }
+
+    /// Replace the theme, keeping the cursors created from images
+    pub fn set_theme(&mut self, theme: CursorTheme) {
+        self.theme = theme;
+    }
+
+    /// The window cursor of a page's cursor, `None` if the page hides it
+    pub fn cursor(&mut self, evl: &ActiveEventLoop, cursor: Cursor) -> Option<WindowCursor> {
+        let icon = cursor_icon(cursor)?;
+        let Some(replacement) = self.theme.cursors.get(icon.name()) else {
+            return Some(icon.into());
+        };
+        if let CursorReplacement::Named(name) = replacement {
+            return Some(named_cursor(name).unwrap_or(icon).into());
+        }
+
+        let mut hasher = DefaultHasher::new();
+        replacement.hash(&mut hasher);
+        let image = self.images.entry(hasher.finish()).or_insert_with(|| {
+            decode(replacement)
+                .map(|source| evl.create_custom_cursor(source))
+                .inspect_err(|error| log::warn!("Verso shows the {} cursor: {error}", icon.name()))
+                .ok()
+        });
+        Some(match image {
+            Some(image) => image.clone().into(),
+            None => icon.into(),
+        })
+    }
+}
+
+/// A cursor of the system theme by CSS name, `None` if there's no such cursor
+fn named_cursor(name: &str) -> Option<CursorIcon> {
+    CursorIcon::from_str(name)
+        .inspect_err(|_| log::warn!("Verso has no cursor named {name}"))
+        .ok()
+}
+
+/// Decode the image of a replacement into the source of a window cursor
+fn decode(replacement: &CursorReplacement) -> Result<CustomCursorSource, String> {
+    let (data, hotspot) = match replacement {
+        CursorReplacement::Named(name) => return Err(format!("{name} is not an image")),
+        CursorReplacement::Image { data, hotspot } => (data.clone(), *hotspot),
+        CursorReplacement::ImageFile { path, hotspot } => (
+            std::fs::read(path)
+                .map_err(|error| format!("failed to read {}: {error}", path.display()))?,
+            *hotspot,
+        ),
+    };
+    let image = image::load_from_memory(&data)
+        .map_err(|error| format!("failed to decode the cursor image: {error}"))?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(format!("the cursor image is too large, {width}x{height}"));
+    };
+    CustomCursor::from_rgba(image.into_raw(), width, height, hotspot.0, hotspot.1)
+        .map_err(|error| format!("invalid cursor image: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor as IoCursor;
+
+    use image::{ImageFormat, RgbaImage};
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbaImage::new(width, height)
+            .write_to(&mut IoCursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_decode_cursor_image() {
+        let image = |data, hotspot| CursorReplacement::Image { data, hotspot };
+        assert!(decode(&image(png(32, 32), (4, 2))).is_ok());
+        // The hotspot must be in the image.
+        assert!(decode(&image(png(32, 32), (40, 2))).is_err());
+        assert!(decode(&image(b"not an image".to_vec(), (0, 0))).is_err());
+        assert!(decode(&CursorReplacement::Named("pointer".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_named_cursor() {
+        assert_eq!(named_cursor("pointer"), Some(CursorIcon::Pointer));
+        assert_eq!(named_cursor("ns-resize"), Some(CursorIcon::NsResize));
+        assert_eq!(named_cursor("sparkles"), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! GPU Device Loss
+//!
+//! Detects the GPU driver resetting under Verso, a TDR on Windows, once per
+//! composited frame. Contexts with `GL_KHR_robustness` report resets through
+//! their reset status. Without it a reset shows up as GL errors: the
+//! `GL_CONTEXT_LOST` error, or errors [`CONSECUTIVE_ERROR_LIMIT`] frames in a
+//! row.
+//!
+//! On a loss a context created to be lost on reset is replaced along with the
+//! renderer, see [`crate::renderer_restart`]. Otherwise WebRender's GPU caches
+//! are dropped and everything is re-uploaded on a surface recreated if needed, like after a GPU
+//! switch. The restart is counted by the recovery policies, which can escalate
+//! to a browser restart, and the controller gets
+//! [`ToControllerMessage::OnDeviceLost`](versoview_messages::ToControllerMessage::OnDeviceLost).
+
+use gleam::gl;
+use versoview_messages::DeviceLostCause;
+
+/// Frames in a row with GL errors after which the device is taken for lost
+pub const CONSECUTIVE_ERROR_LIMIT: u32 = 5;
+
+/// `GL_CONTEXT_LOST`, OpenGL 4.5, OpenGL ES 3.2 and `GL_KHR_robustness`
+const CONTEXT_LOST: gl::GLenum = 0x0507;
+/// `GL_GUILTY_CONTEXT_RESET`
+const GUILTY_CONTEXT_RESET: gl::GLenum = 0x8253;
+/// `GL_INNOCENT_CONTEXT_RESET`
+const INNOCENT_CONTEXT_RESET: gl::GLenum = 0x8254;
+
+/// Watches the GL context of the compositor for a device loss
+#[derive(Debug, Default)]
+pub struct DeviceLostWatchdog {
+    consecutive_errors: u32,
+}
+
+impl DeviceLostWatchdog {
+    /// Check the GL context after a frame, `Some` if the device was lost
+    ///
+    /// `robust` tells whether the context reports resets, `glGetGraphicsResetStatus`
+    /// may not be loaded otherwise.
+    pub fn check(&mut self, gl: &dyn gl::Gl, robust: bool) -> Option<DeviceLostCause> {
+        let reset_status = if robust {
+            gl.get_graphics_reset_status()
+        } else {
+            gl::NO_ERROR
+        };
+        self.observe(reset_status, gl.get_error())
+    }
+
+    /// Whether the device was lost, from the reset status and the error of a frame
+    pub fn observe(
+        &mut self,
+        reset_status: gl::GLenum,
+        error: gl::GLenum,
+    ) -> Option<DeviceLostCause> {
+        let cause = match (reset_status, error) {
+            (GUILTY_CONTEXT_RESET, _) => Some(DeviceLostCause::GuiltyReset),
+            (INNOCENT_CONTEXT_RESET, _) => Some(DeviceLostCause::InnocentReset),
+            (gl::NO_ERROR, CONTEXT_LOST) => Some(DeviceLostCause::UnknownReset),
+            (gl::NO_ERROR, gl::NO_ERROR) => {
+                self.consecutive_errors = 0;
+                None
+            }
+            (gl::NO_ERROR, _) => {
+                self.consecutive_errors += 1;
+                (self.consecutive_errors >= CONSECUTIVE_ERROR_LIMIT)
+                    .then_some(DeviceLostCause::ConsecutiveErrors)
+            }
+            _ => Some(DeviceLostCause::UnknownReset),
+        };
+        if cause.is_some() {
+            log::error!("GPU device lost: {cause:?}");
+            self.consecutive_errors = 0;
+        }
+        cause
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe() {
+        let mut watchdog = DeviceLostWatchdog::default();
+        assert_eq!(watchdog.observe(gl::NO_ERROR, gl::NO_ERROR), None);
+        assert_eq!(
+            watchdog.observe(GUILTY_CONTEXT_RESET, gl::NO_ERROR),
+            Some(DeviceLostCause::GuiltyReset)
+        );
+        assert_eq!(
+            watchdog.observe(gl::NO_ERROR, CONTEXT_LOST),
+            Some(DeviceLostCause::UnknownReset)
+        );
+
+        // Errors interrupted by a clean frame don't add up.
+        for _ in 0..CONSECUTIVE_ERROR_LIMIT - 1 {
+            assert_eq!(watchdog.observe(gl::NO_ERROR, gl::INVALID_OPERATION), None);
+        }
+        assert_eq!(watchdog.observe(gl::NO_ERROR, gl::NO_ERROR), None);
+        for _ in 0..CONSECUTIVE_ERROR_LIMIT - 1 {
+            assert_eq!(watchdog.observe(gl::NO_ERROR, gl::INVALID_OPERATION), None);
+        }
+        assert_eq!(
+            watchdog.observe(gl::NO_ERROR, gl::OUT_OF_MEMORY),
+            Some(DeviceLostCause::ConsecutiveErrors)
+        );
+        assert_eq!(watchdog.observe(gl::NO_ERROR, gl::OUT_OF_MEMORY), None);
+    }
+}
@@ -0,0 +1,171 @@
+//! Display List Transport
+//!
+//! Audit of how display lists get from script to WebRender. Script sends the
+//! items, the cache and the spatial tree of a display list as three byte
+//! messages, each received into its own buffer: that is the only copy on the
+//! compositor side. The buffers are then moved into the `DisplayListPayload`,
+//! the `BuiltDisplayList` and the transaction, which the transport checks by
+//! comparing the address of the items buffer before and after, so a copy
+//! sneaking in shows up as more than one copy per buffer.
+//!
+//! Mapping the payload from `IpcSharedMemory` wouldn't go below one copy:
+//! `DisplayListPayload` owns its buffers, so a mapped buffer would still be
+//! copied into one, and the sender would copy into the shared memory first.
+//! The byte channel is also what Servo's script thread sends on, which the
+//! embedder doesn't control.
+
+use std::time::Duration;
+
+use ipc_channel::ipc::IpcBytesReceiver;
+
+/// Display list transport of one composited frame, or of all frames
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransportCounts {
+    /// Display lists handed to WebRender
+    pub display_lists: u64,
+    /// Buffers received from script
+    pub buffers: u64,
+    /// Bytes received from script
+    pub bytes: u64,
+    /// Copies of buffers made by the compositor, one per buffer when nothing extra is copied
+    pub copies: u64,
+    /// Bytes copied by the compositor
+    pub bytes_copied: u64,
+}
+
+impl TransportCounts {
+    /// Copies made per received buffer
+    pub fn copies_per_buffer(&self) -> f64 {
+        if self.buffers == 0 {
+            0.0
+        } else {
+            self.copies as f64 / self.buffers as f64
+        }
+    }
+
+    fn add(&mut self, other: &TransportCounts) {
+        self.display_lists += other.display_lists;
+        self.buffers += other.buffers;
+        self.bytes += other.bytes;
+        self.copies += other.copies;
+        self.bytes_copied += other.bytes_copied;
+    }
+}
+
+/// Display list transport statistics
+#[derive(Clone, Debug, Default)]
+pub struct DisplayListTransportStats {
+    /// Frames composited
+    pub frames: u64,
+    /// Counts of all frames
+    pub total: TransportCounts,
+    /// Counts of the last composited frame that received a display list
+    pub last_frame: TransportCounts,
+    /// Largest display list received, in bytes
+    pub largest_display_list: u64,
+    /// Total time spent receiving display lists
+    pub receive_time: Duration,
+}
+
+/// Receives display list buffers and counts the bytes and copies per frame
+#[derive(Debug, Default)]
+pub struct DisplayListTransport {
+    frame: TransportCounts,
+    display_list_bytes: u64,
+    stats: DisplayListTransportStats,
+}
+
+impl DisplayListTransport {
+    /// Receive the next buffer of a display list, which copies it out of the channel once
+    pub fn receive(&mut self, receiver: &IpcBytesReceiver) -> Result<Vec<u8>, String> {
+        let start = std::time::Instant::now();
+        let buffer = receiver.recv().map_err(|error| error.to_string())?;
+        self.stats.receive_time += start.elapsed();
+        self.received(buffer.len());
+        Ok(buffer)
+    }
+
+    fn received(&mut self, len: usize) {
+        let len = len as u64;
+        self.frame.buffers += 1;
+        self.frame.bytes += len;
+        self.copied(len);
+        self.display_list_bytes += len;
+    }
+
+    /// Count a copy of `len` bytes of a display list
+    pub fn copied(&mut self, len: u64) {
+        self.frame.copies += 1;
+        self.frame.bytes_copied += len;
+    }
+
+    /// A display list was built from the received buffers
+    ///
+    /// `received_items` and `built_items` are the addresses and lengths of the items buffer as
+    /// received and as held by the built display list, which differ if it was copied.
+    pub fn built(&mut self, received_items: (*const u8, usize), built_items: &[u8]) {
+        self.frame.display_lists += 1;
+        if received_items != (built_items.as_ptr(), built_items.len()) {
+            log::warn!(
+                "Display list items were copied on their way to WebRender ({} bytes)",
+                built_items.len()
+            );
+            self.copied(built_items.len() as u64);
+        }
+        self.stats.largest_display_list =
+            self.stats.largest_display_list.max(self.display_list_bytes);
+        self.display_list_bytes = 0;
+    }
+
+    /// A frame was composited, the counts since the last one become its counts
+    pub fn frame_composited(&mut self) {
+        self.stats.frames += 1;
+        if self.frame == TransportCounts::default() {
+            return;
+        }
+        log::trace!(
+            "Display lists of this frame: {} lists, {} bytes, {} copies",
+            self.frame.display_lists,
+            self.frame.bytes,
+            self.frame.copies
+        );
+        self.stats.total.add(&self.frame);
+        self.stats.last_frame = std::mem::take(&mut self.frame);
+    }
+
+    /// Get the statistics of the frames composited so far
+    pub fn stats(&self) -> &DisplayListTransportStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_per_frame() {
+        let mut transport = DisplayListTransport::default();
+        let items = vec![0u8; 1024];
+        for len in [items.len(), 16, 32] {
+            transport.received(len);
+        }
+        transport.built((items.as_ptr(), items.len()), &items);
+        transport.frame_composited();
+        transport.frame_composited();
+
+        let stats = transport.stats();
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.largest_display_list, 1072);
+        assert_eq!(stats.last_frame, stats.total);
+        assert_eq!(stats.total.display_lists, 1);
+        assert_eq!(stats.total.copies_per_buffer(), 1.0);
+
+        let copy = items.clone();
+        transport.received(items.len());
+        transport.built((items.as_ptr(), items.len()), &copy);
+        transport.frame_composited();
+        assert_eq!(transport.stats().last_frame.copies, 2);
+        assert_eq!(transport.stats().total.bytes_copied, 1072 + 2048);
+    }
+}
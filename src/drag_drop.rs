@@ -0,0 +1,443 @@
+//! Drag and Drop
+//!
+//! Drags links, images and selected text from one webview to another, or onto
+//! the browser panel. Servo doesn't start drags itself, so Verso watches the
+//! pointer instead: a listener injected in every page notes what is under the
+//! pointer when the button goes down, and once the pointer moved far enough
+//! with the button held, that becomes the [`DragData`] of the drag. The drag is
+//! kept by the compositor, which draws a label of the dragged data under the
+//! pointer and outlines the webview it is over, wherever the drag started.
+//!
+//! Pages get `dragenter`, `dragover`, `dragleave` and `drop` events carrying a
+//! `DataTransfer` with the data. A drop no page handled inserts the text into
+//! the text field under the pointer, and a link or image dropped on the panel
+//! opens in a new tab.
+//!
+//! Drags only start from links, images and text that was selected before the
+//! press, and not when the page prevented the default action of the press or
+//! marked the element `draggable="false"`, as maps and sliders do. Pages can't
+//! start drags with `draggable` or `dragstart`, nor change the data or the drop
+//! effect, and drags don't leave the window: leaving it cancels the drag. Pages
+//! get no pointer moves while a drag is in progress. Embedders can turn drags
+//! off with `ConfigFromController::drag_and_drop`.
+
+use base::id::WebViewId;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use webrender_api::units::DevicePoint;
+use winit::window::WindowId;
+
+/// Distance the pointer moves with the button held before a drag starts, in device pixels
+const DRAG_THRESHOLD: f32 = 6.0;
+/// Longest label drawn under the pointer, in characters
+const MAX_LABEL_CHARS: usize = 48;
+
+/// Notes what is under the pointer when a button goes down, run when the head is parsed
+pub const DRAG_SOURCE_LISTENER_SCRIPT: &str = r#"
+(() => {
+    if (window.__versoDragSource !== undefined) {
+        return;
+    }
+    window.__versoDragSource = null;
+    addEventListener('mousedown', (event) => {
+        const target = event.target instanceof Element ? event.target : null;
+        if (target && target.closest('[draggable="false"]')) {
+            window.__versoDragSource = null;
+            return;
+        }
+        const link = target && target.closest('a[href]');
+        const image = target && target.closest('img[src]');
+        let text = null;
+        const selection = getSelection();
+        if (selection && !selection.isCollapsed) {
+            for (let i = 0; i < selection.rangeCount && text === null; i++) {
+                for (const rect of selection.getRangeAt(i).getClientRects()) {
+                    if (event.clientX >= rect.left && event.clientX <= rect.right
+                        && event.clientY >= rect.top && event.clientY <= rect.bottom) {
+                        text = selection.toString();
+                        break;
+                    }
+                }
+            }
+        }
+        window.__versoDragSource = link || image || text ? {
+            event,
+            data: {
+                link: link ? link.href : null,
+                title: link ? link.textContent.trim() : image ? image.alt : null,
+                image: image ? image.currentSrc || image.src : null,
+                text: text ?? (link ? link.textContent.trim() : null),
+            },
+        } : null;
+    }, true);
+})()
+"#;
+
+/// Returns and forgets what was under the pointer when the button went down, as JSON
+///
+/// Nothing is dragged if a handler of the page prevented the default action of the press,
+/// which runs after the capturing listener noted the source.
+pub const DRAG_SOURCE_SCRIPT: &str = r#"
+(() => {
+    const source = window.__versoDragSource ?? null;
+    window.__versoDragSource = null;
+    return JSON.stringify(source && !source.event.defaultPrevented ? source.data : null);
+})()
+"#;
+
+/// Data carried by a drag, the flavors of its `DataTransfer`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DragData {
+    /// Link dragged
+    pub link: Option<Url>,
+    /// Text of the link, or alternative text of the image
+    pub title: Option<String>,
+    /// Image dragged
+    pub image: Option<Url>,
+    /// Text dragged
+    pub text: Option<String>,
+}
+
+impl DragData {
+    /// Parse the answer of [`DRAG_SOURCE_SCRIPT`], `None` if there's nothing to drag
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str::<Option<Self>>(json)
+            .ok()
+            .flatten()
+            .filter(|data| data.link.is_some() || data.image.is_some() || data.text.is_some())
+    }
+
+    /// URL of the link, or of the image if it isn't a link
+    pub fn url(&self) -> Option<&Url> {
+        self.link.as_ref().or(self.image.as_ref())
+    }
+
+    /// Flavors of the data, as `DataTransfer` types and values
+    pub fn items(&self) -> Vec<(&'static str, String)> {
+        let mut items = Vec::new();
+        if let Some(url) = self.url() {
+            items.push(("text/uri-list", url.to_string()));
+        }
+        let html = match (&self.link, &self.image) {
+            (Some(link), _) => Some(format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(link.as_str()),
+                escape_html(self.title.as_deref().unwrap_or(link.as_str()))
+            )),
+            (None, Some(image)) => Some(format!(
+                "<img src=\"{}\" alt=\"{}\">",
+                escape_html(image.as_str()),
+                escape_html(self.title.as_deref().unwrap_or_default())
+            )),
+            (None, None) => None,
+        };
+        if let Some(html) = html {
+            items.push(("text/html", html));
+        }
+        if let Some(text) = self.text.clone().or_else(|| self.url().map(Url::to_string)) {
+            items.push(("text/plain", text));
+        }
+        items
+    }
+
+    /// Label drawn under the pointer
+    pub fn label(&self) -> String {
+        let label = self
+            .title
+            .as_deref()
+            .filter(|title| !title.is_empty())
+            .or(self.text.as_deref())
+            .map(str::to_string)
+            .or_else(|| self.url().map(Url::to_string))
+            .unwrap_or_default();
+        let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+        if label.chars().count() > MAX_LABEL_CHARS {
+            let mut label: String = label.chars().take(MAX_LABEL_CHARS - 1).collect();
+            label.push('…');
+            label
+        } else {
+            label
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Script dispatching a drag event of `kind` with `data` at `point`, in CSS pixels of the page
+///
+/// A `drop` that isn't canceled inserts the text into the text field it is over.
+pub fn drag_event_script(kind: &str, data: &DragData, point: (f32, f32)) -> String {
+    let items = serde_json::to_string(&data.items()).unwrap_or_else(|_| "[]".to_string());
+    let kind = serde_json::to_string(kind).unwrap();
+    format!(
+        r#"
+(() => {{
+    const items = {items};
+    const kind = {kind};
+    const x = {x};
+    const y = {y};
+    const getData = (type) => (items.find(([item]) => item === type) || [null, ''])[1];
+    let dataTransfer;
+    if (typeof DataTransfer === 'function') {{
+        dataTransfer = new DataTransfer();
+        for (const [type, value] of items) {{
+            dataTransfer.setData(type, value);
+        }}
+    }} else {{
+        dataTransfer = {{
+            types: items.map(([type]) => type),
+            getData,
+            dropEffect: 'copy',
+            effectAllowed: 'copyLink',
+        }};
+    }}
+    const init = {{ bubbles: true, cancelable: kind !== 'dragleave', clientX: x, clientY: y }};
+    const target = document.elementFromPoint(x, y) || document.body || document.documentElement;
+    let event;
+    try {{
+        event = new DragEvent(kind, {{ ...init, dataTransfer }});
+    }} catch (error) {{
+        event = new MouseEvent(kind, init);
+        Object.defineProperty(event, 'dataTransfer', {{ value: dataTransfer }});
+    }}
+    const handled = !target.dispatchEvent(event);
+    if (kind === 'drop' && !handled
+        && (target instanceof HTMLTextAreaElement || target instanceof HTMLInputElement)
+        && !target.readOnly && !target.disabled) {{
+        const end = target.value.length;
+        target.setRangeText(getData('text/plain'), target.selectionStart ?? end,
+            target.selectionEnd ?? end, 'end');
+        target.dispatchEvent(new Event('input', {{ bubbles: true }}));
+    }}
+    return handled;
+}})()
+"#,
+        x = point.0,
+        y = point.1,
+    )
+}
+
+/// A drag in progress
+#[derive(Clone, Debug)]
+pub struct Drag {
+    /// Window the drag is in
+    pub window: WindowId,
+    /// Webview the drag started from
+    pub source: WebViewId,
+    /// What is dragged
+    pub data: DragData,
+    /// Where the pointer is
+    pub point: DevicePoint,
+    /// Webview the pointer is over
+    pub target: Option<WebViewId>,
+}
+
+/// What a pointer move means for the drag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DragMove {
+    /// No drag, the move goes to the page
+    None,
+    /// The pointer moved far enough to drag from the webview, ask it what is dragged
+    Start(WebViewId),
+    /// The webview was asked what is dragged, the move goes to the page until it answers
+    Waiting(WebViewId),
+    /// The drag goes on
+    Over,
+}
+
+#[derive(Debug, Default)]
+enum DragState {
+    #[default]
+    Idle,
+    Pressed {
+        window: WindowId,
+        source: WebViewId,
+        start: DevicePoint,
+        /// Whether the webview was asked what is dragged
+        asked: bool,
+    },
+    Dragging(Drag),
+}
+
+/// The drag of the compositor, there's at most one at a time
+#[derive(Debug, Default)]
+pub struct DragSession {
+    state: DragState,
+}
+
+impl DragSession {
+    /// Record that the button went down over the webview at `point`
+    pub fn press(&mut self, window: WindowId, source: WebViewId, point: DevicePoint) {
+        self.state = DragState::Pressed {
+            window,
+            source,
+            start: point,
+            asked: false,
+        };
+    }
+
+    /// Record that the pointer moved to `point` in the window
+    pub fn moved(&mut self, window: WindowId, point: DevicePoint) -> DragMove {
+        match &mut self.state {
+            DragState::Pressed {
+                window: pressed,
+                source,
+                start,
+                asked,
+            } if *pressed == window => {
+                if *asked {
+                    DragMove::Waiting(*source)
+                } else if (point - *start).length() >= DRAG_THRESHOLD {
+                    *asked = true;
+                    DragMove::Start(*source)
+                } else {
+                    DragMove::None
+                }
+            }
+            DragState::Dragging(drag) if drag.window == window => {
+                drag.point = point;
+                DragMove::Over
+            }
+            _ => DragMove::None,
+        }
+    }
+
+    /// Start dragging `data` from the pressed webview, or give up on the press if there's nothing to drag
+    pub fn start(&mut self, data: Option<DragData>, point: DevicePoint) -> bool {
+        let (DragState::Pressed { window, source, .. }, Some(data)) = (&self.state, data) else {
+            self.state = DragState::Idle;
+            return false;
+        };
+        self.state = DragState::Dragging(Drag {
+            window: *window,
+            source: *source,
+            data,
+            point,
+            target: None,
+        });
+        true
+    }
+
+    /// Record the webview the pointer is over, returns the webview it left, if it changed
+    pub fn set_target(&mut self, target: Option<WebViewId>) -> Option<Option<WebViewId>> {
+        match &mut self.state {
+            DragState::Dragging(drag) if drag.target != target => {
+                Some(std::mem::replace(&mut drag.target, target))
+            }
+            _ => None,
+        }
+    }
+
+    /// Record that the button went up, returns the drag to drop if there was one
+    pub fn release(&mut self) -> Option<Drag> {
+        match std::mem::take(&mut self.state) {
+            DragState::Dragging(drag) => Some(drag),
+            _ => None,
+        }
+    }
+
+    /// Cancel the press or drag in the window, returns the drag if there was one
+    pub fn cancel(&mut self, window: WindowId) -> Option<Drag> {
+        match &self.state {
+            DragState::Pressed {
+                window: pressed, ..
+            } if *pressed == window => self.release(),
+            DragState::Dragging(drag) if drag.window == window => self.release(),
+            _ => None,
+        }
+    }
+
+    /// The drag in progress in the window
+    pub fn dragging(&self, window: WindowId) -> Option<&Drag> {
+        match &self.state {
+            DragState::Dragging(drag) if drag.window == window => Some(drag),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    #[test]
+    fn test_drag_data() {
+        let data = DragData::from_json(
+            r#"{"link":"https://example.com/a?b&c","title":"An   example","image":null,"text":null}"#,
+        )
+        .unwrap();
+        assert_eq!(data.label(), "An example");
+        assert_eq!(
+            data.items(),
+            vec![
+                ("text/uri-list", "https://example.com/a?b&c".to_string()),
+                (
+                    "text/html",
+                    "<a href=\"https://example.com/a?b&amp;c\">An   example</a>".to_string()
+                ),
+                ("text/plain", "https://example.com/a?b&c".to_string()),
+            ]
+        );
+        assert_eq!(DragData::from_json("null"), None);
+        assert_eq!(
+            DragData::from_json(r#"{"link":null,"title":null,"image":null,"text":null}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_drag_session() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let window = WindowId::from(1);
+        let source = WebViewId::new();
+        let target = WebViewId::new();
+        let mut session = DragSession::default();
+
+        session.press(window, source, DevicePoint::new(10., 10.));
+        assert_eq!(
+            session.moved(window, DevicePoint::new(12., 11.)),
+            DragMove::None
+        );
+        assert_eq!(
+            session.moved(window, DevicePoint::new(30., 10.)),
+            DragMove::Start(source)
+        );
+        // The page is asked once, moves wait for its answer.
+        assert_eq!(
+            session.moved(window, DevicePoint::new(35., 10.)),
+            DragMove::Waiting(source)
+        );
+        // Nothing under the pointer to drag, the press is a text selection.
+        assert!(!session.start(None, DevicePoint::new(30., 10.)));
+        assert_eq!(
+            session.moved(window, DevicePoint::new(40., 10.)),
+            DragMove::None
+        );
+
+        session.press(window, source, DevicePoint::new(10., 10.));
+        let data = DragData {
+            text: Some("text".to_string()),
+            ..Default::default()
+        };
+        assert!(session.start(Some(data.clone()), DevicePoint::new(30., 10.)));
+        assert_eq!(session.set_target(Some(source)), Some(None));
+        assert_eq!(session.set_target(Some(source)), None);
+        assert_eq!(
+            session.moved(window, DevicePoint::new(300., 10.)),
+            DragMove::Over
+        );
+        assert_eq!(session.set_target(Some(target)), Some(Some(source)));
+
+        let drag = session.release().unwrap();
+        assert_eq!(drag.data, data);
+        assert_eq!(drag.target, Some(target));
+        assert_eq!(drag.point, DevicePoint::new(300., 10.));
+        assert!(session.dragging(window).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Emoji Fonts
+//!
+//! Makes sure emoji render in color instead of as tofu on systems without an
+//! emoji font, which is common on embedded Linux. The configured font is
+//! registered with fontconfig as an application font and aliased to the
+//! families Servo falls back to for emoji, so any page text missing an emoji
+//! glyph picks it up. Without one, the system's emoji font is used, if any.
+//!
+//! macOS and Windows always ship a color emoji font that Servo falls back to,
+//! so registration is Linux only there.
+//!
+//! Color glyphs (CBDT/CBLC bitmaps, COLR layers, sbix) are enabled per font:
+//! the compositor checks every font Servo adds with [`is_color_font`], system
+//! fonts on another thread since their files are read whole, and rasterizes the
+//! color ones with embedded bitmaps.
+
+use std::path::{Path, PathBuf};
+
+use versoview_messages::EmojiSettings;
+
+/// Families Servo and fontconfig look up when falling back for emoji
+#[cfg_attr(not(linux), allow(dead_code))]
+const EMOJI_FALLBACK_FAMILIES: [&str; 3] = ["Noto Color Emoji", "Twemoji", "emoji"];
+
+/// The emoji font file to use, `None` if none is configured or it doesn't exist
+pub fn emoji_font_path(settings: &EmojiSettings) -> Option<PathBuf> {
+    settings.font.clone().filter(|path| path.is_file())
+}
+
+/// Register the configured emoji font so Servo falls back to it, returns the registered family
+pub fn register_emoji_font(settings: &EmojiSettings) -> Option<String> {
+    let Some(path) = emoji_font_path(settings) else {
+        if settings.font.is_some() {
+            log::error!("Emoji font {:?} not found", settings.font);
+        } else {
+            log::debug!("No emoji font configured, using the system emoji font");
+        }
+        return None;
+    };
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(error) => {
+            log::error!("Failed to read emoji font {}: {error}", path.display());
+            return None;
+        }
+    };
+    let Some(family) = family_name(&data) else {
+        log::error!("{} is not a valid font", path.display());
+        return None;
+    };
+    if !is_color_font(&data, 0) {
+        log::warn!(
+            "Emoji font {} has no color glyphs, emoji will render in monochrome",
+            path.display()
+        );
+    }
+
+    register(&path, &family).map(|()| {
+        log::info!("Registered emoji font '{family}' from {}", path.display());
+        family
+    })
+}
+
+/// Whether a font has color glyph tables
+pub fn is_color_font(data: &[u8], index: u32) -> bool {
+    ttf_parser::Face::parse(data, index).is_ok_and(|face| {
+        let tables = face.tables();
+        tables.cbdt.is_some() || tables.colr.is_some() || tables.sbix.is_some()
+    })
+}
+
+fn family_name(data: &[u8]) -> Option<String> {
+    let face = ttf_parser::Face::parse(data, 0).ok()?;
+    face.names()
+        .into_iter()
+        .filter(|name| {
+            name.name_id == ttf_parser::name_id::TYPOGRAPHIC_FAMILY
+                || name.name_id == ttf_parser::name_id::FAMILY
+        })
+        .find_map(|name| name.to_string())
+}
+
+/// fontconfig rules making the emoji fallback families prefer `family`
+#[cfg_attr(not(linux), allow(dead_code))]
+fn fallback_aliases(family: &str) -> String {
+    let family = family
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let aliases: String = EMOJI_FALLBACK_FAMILIES
+        .iter()
+        .map(|fallback| {
+            format!(
+                "<alias binding=\"same\"><family>{fallback}</family><prefer><family>{family}</family></prefer></alias>"
+            )
+        })
+        .collect();
+    format!("<?xml version=\"1.0\"?><fontconfig>{aliases}</fontconfig>")
+}
+
+#[cfg(linux)]
+fn register(path: &Path, family: &str) -> Option<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    use fontconfig_sys::{FcConfigAppFontAddFile, FcConfigGetCurrent, FcConfigParseAndLoad};
+
+    let font = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let aliases_path =
+        std::env::temp_dir().join(format!("verso-emoji-{}.conf", std::process::id()));
+    if let Err(error) = std::fs::write(&aliases_path, fallback_aliases(family)) {
+        log::error!("Failed to write emoji font aliases: {error}");
+        return None;
+    }
+    let aliases = CString::new(aliases_path.as_os_str().as_bytes()).ok()?;
+
+    // SAFETY: both paths are valid C strings and the current config is owned by fontconfig.
+    let registered = unsafe {
+        let config = FcConfigGetCurrent();
+        FcConfigAppFontAddFile(config, font.as_ptr() as *const _) != 0
+            && FcConfigParseAndLoad(config, aliases.as_ptr() as *const _, 1) != 0
+    };
+    let _ = std::fs::remove_file(&aliases_path);
+    if !registered {
+        log::error!("fontconfig refused emoji font {}", path.display());
+    }
+    registered.then_some(())
+}
+
+#[cfg(not(linux))]
+fn register(_path: &Path, _family: &str) -> Option<()> {
+    log::debug!("Emoji font registration is only needed on Linux, using the system emoji font");
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_font_path() {
+        let settings = EmojiSettings {
+            font: Some(PathBuf::from("/nonexistent/emoji.ttf")),
+            ..Default::default()
+        };
+        assert_eq!(emoji_font_path(&settings), None);
+        assert_eq!(emoji_font_path(&EmojiSettings::default()), None);
+        assert!(!is_color_font(b"not a font", 0));
+    }
+
+    #[test]
+    fn test_fallback_aliases() {
+        let aliases = fallback_aliases("Emoji & Symbols");
+        assert!(aliases.contains(
+            "<family>Noto Color Emoji</family><prefer><family>Emoji &amp; Symbols</family>"
+        ));
+        assert_eq!(
+            aliases.matches("<alias").count(),
+            EMOJI_FALLBACK_FAMILIES.len()
+        );
+    }
+}
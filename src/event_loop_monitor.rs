@@ -0,0 +1,273 @@
+//! Event Loop Monitor
+//!
+//! Finds which subsystem blocks interactivity. Verso handles window events,
+//! controller and internal messages, and the messages Servo sends to the
+//! compositor and the embedder on one thread, each winit callback being one
+//! iteration of its event loop. The monitor times every message by type,
+//! samples how many messages were waiting in the compositor and embedder
+//! channels when they are drained, and flags the iterations longer than the
+//! stall threshold, logging the type of the message that took the longest.
+//!
+//! Message types are the variant names of the messages. Compositor and embedder
+//! messages are handled within a winit callback, so their time also counts for
+//! the window event or wake up that drained them. The depth of winit's own
+//! queue isn't visible to Verso, only the channels it drains itself are
+//! sampled.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Write},
+    time::{Duration, Instant, SystemTime},
+};
+
+use versoview_messages::{
+    EventLoopStall, EventLoopStats, EventQueue, MessageTypeStats, QueueDepthStats,
+};
+
+/// Stalls kept for [`EventLoopStats::recent_stalls`]
+const MAX_RECENT_STALLS: usize = 16;
+/// Longest message type name kept
+const MAX_MESSAGE_TYPE_LEN: usize = 64;
+
+/// Type of a message, the variant name its `Debug` output starts with
+pub fn message_type(message: &impl fmt::Debug) -> String {
+    struct VariantName(String);
+
+    impl Write for VariantName {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = s
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(s.len());
+            self.0.push_str(&s[..end]);
+            // Stop formatting the fields, they can be large.
+            if end < s.len() || self.0.len() >= MAX_MESSAGE_TYPE_LEN {
+                Err(fmt::Error)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    let mut name = VariantName(String::new());
+    let _ = write!(name, "{message:?}");
+    name.0.truncate(MAX_MESSAGE_TYPE_LEN);
+    name.0
+}
+
+#[derive(Debug, Default)]
+struct MessageStats {
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+#[derive(Debug, Default)]
+struct QueueStats {
+    samples: u64,
+    total: u64,
+    last: u32,
+    max: u32,
+}
+
+/// Message that took the longest in the current iteration
+#[derive(Debug)]
+struct SlowestMessage {
+    queue: EventQueue,
+    message: String,
+    duration: Duration,
+}
+
+/// Times the messages of the event loop and detects stalls
+#[derive(Debug, Default)]
+pub struct EventLoopMonitor {
+    stall_threshold: Option<Duration>,
+    iterations: u64,
+    iteration_start: Option<Instant>,
+    slowest: Option<SlowestMessage>,
+    messages: HashMap<EventQueue, HashMap<String, MessageStats>>,
+    queues: HashMap<EventQueue, QueueStats>,
+    stalls: u64,
+    recent_stalls: VecDeque<EventLoopStall>,
+}
+
+impl EventLoopMonitor {
+    /// Create a monitor flagging iterations longer than `stall_threshold_ms`, none if `None`
+    pub fn new(stall_threshold_ms: Option<u64>) -> Self {
+        Self {
+            stall_threshold: stall_threshold_ms.map(Duration::from_millis),
+            ..Default::default()
+        }
+    }
+
+    /// Start timing an iteration, does nothing if one is already being timed
+    pub fn begin_iteration(&mut self) {
+        if self.iteration_start.is_none() {
+            self.iteration_start = Some(Instant::now());
+        }
+    }
+
+    /// Record that `depth` messages were waiting in `queue` when it was drained
+    pub fn queue_drained(&mut self, queue: EventQueue, depth: usize) {
+        let depth = depth.min(u32::MAX as usize) as u32;
+        let stats = self.queues.entry(queue).or_default();
+        stats.samples += 1;
+        stats.total += depth as u64;
+        stats.last = depth;
+        stats.max = stats.max.max(depth);
+    }
+
+    /// Record that handling a `message` from `queue` took `elapsed`
+    pub fn message_handled(&mut self, queue: EventQueue, message: &str, elapsed: Duration) {
+        let messages = self.messages.entry(queue).or_default();
+        let stats = match messages.get_mut(message) {
+            Some(stats) => stats,
+            None => messages.entry(message.to_string()).or_default(),
+        };
+        stats.count += 1;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+
+        if self.iteration_start.is_some()
+            && self
+                .slowest
+                .as_ref()
+                .is_none_or(|slowest| elapsed > slowest.duration)
+        {
+            self.slowest = Some(SlowestMessage {
+                queue,
+                message: message.to_string(),
+                duration: elapsed,
+            });
+        }
+    }
+
+    /// Finish timing the iteration, which handled the winit `message`, returns the stall if it was one
+    pub fn end_iteration(&mut self, message: &str) -> Option<&EventLoopStall> {
+        let start = self.iteration_start.take()?;
+        let elapsed = start.elapsed();
+        self.iterations += 1;
+        let slowest = self.slowest.take();
+        self.message_handled(EventQueue::Winit, message, elapsed);
+
+        if self
+            .stall_threshold
+            .is_none_or(|threshold| elapsed <= threshold)
+        {
+            return None;
+        }
+        let slowest = slowest.unwrap_or(SlowestMessage {
+            queue: EventQueue::Winit,
+            message: message.to_string(),
+            duration: elapsed,
+        });
+        log::warn!(
+            "Event loop stalled for {elapsed:?} handling {message}, slowest was {:?} message {} taking {:?}",
+            slowest.queue,
+            slowest.message,
+            slowest.duration
+        );
+
+        self.stalls += 1;
+        if self.recent_stalls.len() == MAX_RECENT_STALLS {
+            self.recent_stalls.pop_front();
+        }
+        self.recent_stalls.push_back(EventLoopStall {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            duration_ms: elapsed.as_millis() as u64,
+            queue: slowest.queue,
+            message: slowest.message,
+            message_duration_ms: slowest.duration.as_millis() as u64,
+        });
+        self.recent_stalls.back()
+    }
+
+    /// Statistics of all iterations so far
+    pub fn stats(&self) -> EventLoopStats {
+        let mut messages: Vec<_> = self
+            .messages
+            .iter()
+            .flat_map(|(queue, messages)| {
+                messages.iter().map(|(message, stats)| MessageTypeStats {
+                    queue: *queue,
+                    message: message.clone(),
+                    count: stats.count,
+                    total_us: stats.total.as_micros() as u64,
+                    max_us: stats.max.as_micros() as u64,
+                })
+            })
+            .collect();
+        messages.sort_by(|a, b| b.total_us.cmp(&a.total_us));
+        let mut queues: Vec<_> = self
+            .queues
+            .iter()
+            .map(|(queue, stats)| QueueDepthStats {
+                queue: *queue,
+                last: stats.last,
+                max: stats.max,
+                average: stats.total as f32 / stats.samples.max(1) as f32,
+            })
+            .collect();
+        queues.sort_by_key(|queue| queue.queue as u8);
+
+        EventLoopStats {
+            iterations: self.iterations,
+            stalls: self.stalls,
+            recent_stalls: self.recent_stalls.iter().cloned().collect(),
+            messages,
+            queues,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    enum Message {
+        Resize(u32, u32),
+        Redraw,
+        Scroll { delta: f32 },
+    }
+
+    #[test]
+    fn test_message_type() {
+        assert_eq!(message_type(&Message::Resize(800, 600)), "Resize");
+        assert_eq!(message_type(&Message::Redraw), "Redraw");
+        assert_eq!(message_type(&Message::Scroll { delta: 1.0 }), "Scroll");
+    }
+
+    #[test]
+    fn test_stall_names_slowest_message() {
+        let mut monitor = EventLoopMonitor::new(Some(1));
+        monitor.begin_iteration();
+        monitor.queue_drained(EventQueue::Compositor, 3);
+        monitor.message_handled(
+            EventQueue::Compositor,
+            "NewWebRenderFrameReady",
+            Duration::from_millis(1),
+        );
+        monitor.message_handled(
+            EventQueue::Compositor,
+            "GenerateImageKey",
+            Duration::from_millis(5),
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        let stall = monitor.end_iteration("RedrawRequested").unwrap();
+        assert_eq!(stall.queue, EventQueue::Compositor);
+        assert_eq!(stall.message, "GenerateImageKey");
+        assert_eq!(stall.message_duration_ms, 5);
+
+        monitor.begin_iteration();
+        assert!(monitor.end_iteration("Wake").is_none());
+
+        let stats = monitor.stats();
+        assert_eq!(stats.iterations, 2);
+        assert_eq!(stats.stalls, 1);
+        assert_eq!(stats.queues[0].max, 3);
+        assert_eq!(stats.messages[0].message, "RedrawRequested");
+    }
+}
@@ -1,8 +1,10 @@
 use base::id::PipelineId;
+use ipc_channel::ipc::IpcSharedMemory;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use ipc_channel::ipc::IpcSharedMemory;
-use webrender_api::{FontInstanceFlags, FontInstanceKey, FontKey, ImageData, ImageDescriptor, ImageKey};
+use webrender_api::{
+    FontInstanceFlags, FontInstanceKey, FontKey, ImageData, ImageDescriptor, ImageKey,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ExtendedCompositorMsg {
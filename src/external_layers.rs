@@ -0,0 +1,325 @@
+//! External Layers
+//!
+//! Textures of the embedder composited with the webviews of a window, for a
+//! native video player or map renderer drawing into its own GPU texture. The
+//! embedder hands over an [`ExternalTextureSource`] giving out a texture of the
+//! compositor's GL context, and places it with an [`ExternalLayer`]: layers
+//! with a negative z-order are drawn under the webviews, the others over them,
+//! lower z-orders first. WebRender locks the source whenever it draws a frame
+//! with the layer and samples the texture as it is, without a copy, so the
+//! compositor has to be told when the texture has new contents.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use compositing_traits::{
+    WebrenderExternalImageApi, WebrenderExternalImageRegistry, WebrenderImageHandlerType,
+    WebrenderImageSource,
+};
+use euclid::default::Size2D;
+use gleam::gl;
+use webrender::Transaction;
+use webrender_api::{
+    DirtyRect, ExternalImageData, ExternalImageId, ExternalImageType, ImageBufferKind, ImageData,
+    ImageDescriptor, ImageDescriptorFlags, ImageFormat, ImageKey,
+    units::{DeviceIntSize, DeviceRect},
+};
+use winit::window::WindowId;
+
+/// A texture of the embedder drawn by an external layer
+pub trait ExternalTextureSource: Send {
+    /// The texture to draw and its size, the texture must be on the compositor's GL context
+    fn lock(&mut self) -> (gl::GLuint, DeviceIntSize);
+
+    /// WebRender is done drawing the texture locked last
+    fn unlock(&mut self) {}
+}
+
+/// Id of an external layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ExternalLayerId(u64);
+
+/// Where an external layer is drawn in its window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExternalLayer {
+    /// Rect in the window, in device pixels
+    pub rect: DeviceRect,
+    /// Drawing order, negative under the webviews and the others over them
+    pub z_order: i32,
+}
+
+/// Sources of the external layers, by external image id
+type Sources = Arc<Mutex<HashMap<u64, Box<dyn ExternalTextureSource>>>>;
+
+/// WebRender's handler for the textures of external layers
+///
+/// Register it with [`WebrenderImageHandlerType::Media`], which Verso has no
+/// other handler for, and give a clone to [`ExternalLayers::new`].
+#[derive(Clone, Default)]
+pub struct ExternalLayerImages {
+    sources: Sources,
+}
+
+impl WebrenderExternalImageApi for ExternalLayerImages {
+    fn lock(&mut self, id: u64) -> (WebrenderImageSource, Size2D<i32>) {
+        match self.sources.lock().unwrap().get_mut(&id) {
+            Some(source) => {
+                let (texture, size) = source.lock();
+                (
+                    WebrenderImageSource::TextureHandle(texture),
+                    size.to_untyped(),
+                )
+            }
+            None => {
+                // The layer was removed after WebRender built the frame.
+                log::warn!("WebRender composited the removed external layer {id}");
+                (WebrenderImageSource::TextureHandle(0), Size2D::zero())
+            }
+        }
+    }
+
+    fn unlock(&mut self, id: u64) {
+        if let Some(source) = self.sources.lock().unwrap().get_mut(&id) {
+            source.unlock();
+        }
+    }
+}
+
+/// A layer and the WebRender image of its texture
+#[derive(Debug)]
+struct Layer {
+    window_id: WindowId,
+    layer: ExternalLayer,
+    image_key: ImageKey,
+    external_image_id: ExternalImageId,
+    texture_size: DeviceIntSize,
+}
+
+/// The external layers of the windows
+pub struct ExternalLayers {
+    images: ExternalLayerImages,
+    registry: Arc<Mutex<WebrenderExternalImageRegistry>>,
+    layers: HashMap<ExternalLayerId, Layer>,
+}
+
+impl ExternalLayers {
+    /// Keep the layers' sources in `images`, with external image ids of `registry`
+    pub fn new(
+        images: ExternalLayerImages,
+        registry: Arc<Mutex<WebrenderExternalImageRegistry>>,
+    ) -> Self {
+        Self {
+            images,
+            registry,
+            layers: HashMap::new(),
+        }
+    }
+
+    /// Add a layer of the window drawing the source as the image `image_key`
+    pub fn add(
+        &mut self,
+        transaction: &mut Transaction,
+        image_key: ImageKey,
+        window_id: WindowId,
+        source: Box<dyn ExternalTextureSource>,
+        texture_size: DeviceIntSize,
+        layer: ExternalLayer,
+    ) -> ExternalLayerId {
+        let external_image_id = self
+            .registry
+            .lock()
+            .unwrap()
+            .next_id(WebrenderImageHandlerType::Media);
+        self.images
+            .sources
+            .lock()
+            .unwrap()
+            .insert(external_image_id.0, source);
+        transaction.add_image(
+            image_key,
+            image_descriptor(texture_size),
+            image_data(external_image_id),
+            None,
+        );
+        let id = ExternalLayerId(external_image_id.0);
+        self.layers.insert(
+            id,
+            Layer {
+                window_id,
+                layer,
+                image_key,
+                external_image_id,
+                texture_size,
+            },
+        );
+        id
+    }
+
+    /// Move or reorder a layer, returns its window or `None` if there's no such layer
+    pub fn set(&mut self, id: ExternalLayerId, layer: ExternalLayer) -> Option<WindowId> {
+        let entry = self.layers.get_mut(&id)?;
+        entry.layer = layer;
+        Some(entry.window_id)
+    }
+
+    /// Have WebRender draw the new contents or size of a layer's texture, returns its window
+    pub fn texture_changed(
+        &mut self,
+        transaction: &mut Transaction,
+        id: ExternalLayerId,
+        texture_size: DeviceIntSize,
+    ) -> Option<WindowId> {
+        let entry = self.layers.get_mut(&id)?;
+        entry.texture_size = texture_size;
+        transaction.update_image(
+            entry.image_key,
+            image_descriptor(texture_size),
+            image_data(entry.external_image_id),
+            &DirtyRect::All,
+        );
+        Some(entry.window_id)
+    }
+
+    /// Remove a layer, returns its window
+    pub fn remove(
+        &mut self,
+        transaction: &mut Transaction,
+        id: ExternalLayerId,
+    ) -> Option<WindowId> {
+        let entry = self.layers.remove(&id)?;
+        transaction.delete_image(entry.image_key);
+        self.images
+            .sources
+            .lock()
+            .unwrap()
+            .remove(&entry.external_image_id.0);
+        self.registry
+            .lock()
+            .unwrap()
+            .remove(&entry.external_image_id);
+        Some(entry.window_id)
+    }
+
+    /// Remove the layers of a closed window
+    pub fn remove_window(&mut self, transaction: &mut Transaction, window_id: WindowId) {
+        let ids: Vec<_> = self
+            .layers
+            .iter()
+            .filter(|(_, entry)| entry.window_id == window_id)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.remove(transaction, id);
+        }
+    }
+
+    /// Add the images of every layer to a renderer restarted after a GPU reset
+    pub fn replay(&self, transaction: &mut Transaction) {
+        for entry in self.layers.values() {
+            transaction.add_image(
+                entry.image_key,
+                image_descriptor(entry.texture_size),
+                image_data(entry.external_image_id),
+                None,
+            );
+        }
+    }
+
+    /// Layers of the window under the webviews or over them, in drawing order
+    pub fn layers(&self, window_id: WindowId, below: bool) -> Vec<(ExternalLayer, ImageKey)> {
+        let mut layers: Vec<_> = self
+            .layers
+            .values()
+            .filter(|entry| entry.window_id == window_id && (entry.layer.z_order < 0) == below)
+            .map(|entry| (entry.layer, entry.image_key))
+            .collect();
+        layers.sort_by_key(|(layer, _)| layer.z_order);
+        layers
+    }
+}
+
+/// Descriptor of the WebRender image of a layer's texture
+fn image_descriptor(size: DeviceIntSize) -> ImageDescriptor {
+    ImageDescriptor::new(
+        size.width,
+        size.height,
+        ImageFormat::RGBA8,
+        ImageDescriptorFlags::empty(),
+    )
+}
+
+/// Image data pointing WebRender at a layer's texture
+fn image_data(id: ExternalImageId) -> ImageData {
+    ImageData::External(ExternalImageData {
+        id,
+        channel_index: 0,
+        image_type: ExternalImageType::TextureHandle(ImageBufferKind::Texture2D),
+        normalized_uvs: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use compositing_traits::WebrenderExternalImageHandlers;
+    use webrender_api::IdNamespace;
+
+    use super::*;
+
+    struct Texture(gl::GLuint);
+
+    impl ExternalTextureSource for Texture {
+        fn lock(&mut self) -> (gl::GLuint, DeviceIntSize) {
+            (self.0, DeviceIntSize::new(64, 32))
+        }
+    }
+
+    fn layer(z_order: i32) -> ExternalLayer {
+        ExternalLayer {
+            rect: DeviceRect::zero(),
+            z_order,
+        }
+    }
+
+    #[test]
+    fn test_external_layers() {
+        let (_, registry) = WebrenderExternalImageHandlers::new();
+        let mut images = ExternalLayerImages::default();
+        let mut layers = ExternalLayers::new(images.clone(), registry);
+        let mut transaction = Transaction::new();
+        let window_id = WindowId::from(1);
+        let size = DeviceIntSize::new(64, 32);
+        let mut add = |key, texture, z_order| {
+            layers.add(
+                &mut transaction,
+                ImageKey::new(IdNamespace(1), key),
+                window_id,
+                Box::new(Texture(texture)),
+                size,
+                layer(z_order),
+            )
+        };
+        let video = add(1, 10, -1);
+        let map = add(2, 20, 2);
+        let overlay = add(3, 30, 1);
+
+        let keys = |layers: Vec<(ExternalLayer, ImageKey)>| -> Vec<u32> {
+            layers.into_iter().map(|(_, key)| key.1).collect()
+        };
+        assert_eq!(keys(layers.layers(window_id, true)), [1]);
+        assert_eq!(keys(layers.layers(window_id, false)), [3, 2]);
+        assert!(layers.layers(WindowId::from(2), false).is_empty());
+
+        let (source, size) = images.lock(map.0);
+        assert!(matches!(source, WebrenderImageSource::TextureHandle(20)));
+        assert_eq!(size, Size2D::new(64, 32));
+
+        assert_eq!(layers.set(overlay, layer(3)), Some(window_id));
+        assert_eq!(keys(layers.layers(window_id, false)), [2, 3]);
+        assert_eq!(layers.remove(&mut transaction, video), Some(window_id));
+        assert_eq!(layers.remove(&mut transaction, video), None);
+        layers.remove_window(&mut transaction, window_id);
+        assert!(layers.layers(window_id, false).is_empty());
+    }
+}
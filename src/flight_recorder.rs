@@ -0,0 +1,393 @@
+//! Flight Recorder
+//!
+//! Answers "what happened right before it broke" from the field: the last
+//! seconds of input events, compositor messages and scaled down frames are kept
+//! in memory and written to disk on demand, when a pipeline panics, or when a
+//! composite takes longer than the jank threshold. Automatic dumps happen at
+//! most once per recorded duration, so a janky page doesn't fill the disk.
+//!
+//! Frames are scaled down on the GPU, by blitting the back buffer WebRender just
+//! rendered into a small framebuffer, and only that is read back. Only the
+//! window being composited is captured. Events are recorded with their `Debug`
+//! representation. A dump is a directory holding an `events.json` timeline and
+//! the frames as PNGs, both named by their offset to the dump in milliseconds,
+//! and is written by a background thread.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use gleam::gl;
+use serde::Serialize;
+use versoview_messages::FlightRecorderSettings;
+
+/// Most events kept, the oldest are dropped first even if they are recent
+pub const MAX_EVENTS: usize = 10_000;
+
+/// Where a recorded event comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum RecordedKind {
+    /// A window event from the OS
+    Input,
+    /// A message handled by the compositor
+    Compositor,
+    /// Something Verso noticed, like a panic or a janky composite
+    Marker,
+}
+
+#[derive(Clone, Debug)]
+struct RecordedEvent {
+    at: Instant,
+    kind: RecordedKind,
+    description: String,
+}
+
+#[derive(Clone, Debug)]
+struct FrameCapture {
+    at: Instant,
+    width: u32,
+    height: u32,
+    /// Rows bottom up, as read from GL
+    rgba: Vec<u8>,
+}
+
+/// An event in `events.json`
+#[derive(Debug, Serialize)]
+struct DumpedEvent {
+    /// Milliseconds before the dump
+    offset_ms: u64,
+    kind: RecordedKind,
+    description: String,
+}
+
+/// Small framebuffer frames are scaled down into
+#[derive(Debug)]
+struct CaptureTarget {
+    framebuffer: gl::GLuint,
+    renderbuffer: gl::GLuint,
+    width: u32,
+    height: u32,
+}
+
+/// Ring buffer of the recent input events, compositor messages and frames
+#[derive(Debug, Default)]
+pub struct FlightRecorder {
+    settings: FlightRecorderSettings,
+    events: VecDeque<RecordedEvent>,
+    frames: VecDeque<FrameCapture>,
+    last_capture: Option<Instant>,
+    last_auto_dump: Option<Instant>,
+    target: Option<CaptureTarget>,
+}
+
+impl FlightRecorder {
+    /// Create a recorder, it records nothing unless enabled in `settings`
+    pub fn new(settings: FlightRecorderSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    /// Whether anything is recorded
+    pub fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    fn duration(&self) -> Duration {
+        Duration::from_secs(self.settings.duration_secs)
+    }
+
+    /// Record an event, `describe` is only called when recording
+    pub fn record(&mut self, kind: RecordedKind, describe: impl FnOnce() -> String) {
+        if !self.settings.enabled {
+            return;
+        }
+        let now = Instant::now();
+        self.forget_before(now);
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(RecordedEvent {
+            at: now,
+            kind,
+            description: describe(),
+        });
+    }
+
+    fn forget_before(&mut self, now: Instant) {
+        let Some(cutoff) = now.checked_sub(self.duration()) else {
+            return;
+        };
+        while self.events.front().is_some_and(|event| event.at < cutoff) {
+            self.events.pop_front();
+        }
+        while self.frames.front().is_some_and(|frame| frame.at < cutoff) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Capture the frame just rendered to the back buffer, if the capture interval is over
+    pub fn capture_frame(&mut self, gl: &dyn gl::Gl, viewport_width: u32, viewport_height: u32) {
+        let now = Instant::now();
+        let interval = Duration::from_millis(self.settings.capture_interval_ms);
+        if !self.settings.enabled
+            || viewport_width == 0
+            || viewport_height == 0
+            || self
+                .last_capture
+                .is_some_and(|last_capture| now.duration_since(last_capture) < interval)
+        {
+            return;
+        }
+        self.last_capture = Some(now);
+
+        let (width, height) =
+            capture_size(viewport_width, viewport_height, self.settings.capture_width);
+        let target = match self.target.take() {
+            Some(target) if target.width == width && target.height == height => target,
+            Some(target) => {
+                gl.delete_framebuffers(&[target.framebuffer]);
+                gl.delete_renderbuffers(&[target.renderbuffer]);
+                CaptureTarget::new(gl, width, height)
+            }
+            None => CaptureTarget::new(gl, width, height),
+        };
+
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, target.framebuffer);
+        gl.blit_framebuffer(
+            0,
+            0,
+            viewport_width as i32,
+            viewport_height as i32,
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::COLOR_BUFFER_BIT,
+            gl::LINEAR,
+        );
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, target.framebuffer);
+        let rgba = gl.read_pixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+        );
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        self.target = Some(target);
+
+        self.forget_before(now);
+        self.frames.push_back(FrameCapture {
+            at: now,
+            width,
+            height,
+            rgba,
+        });
+    }
+
+    /// Note how long a composite took, dumping if it janked
+    pub fn composite_finished(&mut self, composite_time: Duration) {
+        let Some(threshold) = self.settings.jank_threshold_ms else {
+            return;
+        };
+        if !self.settings.enabled || composite_time < Duration::from_millis(threshold) {
+            return;
+        }
+        self.record(RecordedKind::Marker, || {
+            format!("Composite took {composite_time:?}")
+        });
+        self.auto_dump("jank");
+    }
+
+    /// Note a pipeline panic, dumping if the settings ask for it
+    pub fn panicked(&mut self, description: impl FnOnce() -> String) {
+        self.record(RecordedKind::Marker, description);
+        if self.settings.enabled && self.settings.dump_on_crash {
+            self.auto_dump("crash");
+        }
+    }
+
+    fn auto_dump(&mut self, reason: &str) {
+        let now = Instant::now();
+        if self
+            .last_auto_dump
+            .is_some_and(|last_dump| now.duration_since(last_dump) < self.duration())
+        {
+            return;
+        }
+        self.last_auto_dump = Some(now);
+        match self.dump(reason) {
+            Ok(directory) => log::warn!(
+                "Verso flight recorder dumped the last {:?} to {} ({reason})",
+                self.duration(),
+                directory.display()
+            ),
+            Err(error) => log::error!("Verso flight recorder failed to dump: {error}"),
+        }
+    }
+
+    /// Write the recording to a new directory in the background, returns the directory
+    pub fn dump(&mut self, reason: &str) -> Result<PathBuf, String> {
+        if !self.settings.enabled {
+            return Err("the flight recorder is disabled".to_string());
+        }
+        let now = Instant::now();
+        self.forget_before(now);
+        let timestamp_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let directory = self
+            .settings
+            .dump_directory
+            .clone()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(format!("verso-flight-{timestamp_ms}-{reason}"));
+        std::fs::create_dir_all(&directory)
+            .map_err(|error| format!("failed to create {}: {error}", directory.display()))?;
+
+        let events = self.events.iter().cloned().collect();
+        let frames = self.frames.iter().cloned().collect();
+        let writer_directory = directory.clone();
+        std::thread::Builder::new()
+            .name("FlightRecorderDump".to_string())
+            .spawn(move || {
+                if let Err(error) = write_dump(&writer_directory, now, events, frames) {
+                    log::error!("Verso flight recorder failed to write its dump: {error}");
+                }
+            })
+            .map_err(|error| format!("failed to spawn the dump writer: {error}"))?;
+        Ok(directory)
+    }
+}
+
+impl CaptureTarget {
+    fn new(gl: &dyn gl::Gl, width: u32, height: u32) -> Self {
+        let framebuffer = gl.gen_framebuffers(1)[0];
+        let renderbuffer = gl.gen_renderbuffers(1)[0];
+        gl.bind_renderbuffer(gl::RENDERBUFFER, renderbuffer);
+        gl.renderbuffer_storage(gl::RENDERBUFFER, gl::RGBA8, width as i32, height as i32);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl.framebuffer_renderbuffer(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            renderbuffer,
+        );
+        gl.bind_renderbuffer(gl::RENDERBUFFER, 0);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        Self {
+            framebuffer,
+            renderbuffer,
+            width,
+            height,
+        }
+    }
+}
+
+/// Size of a capture `capture_width` wide with the aspect ratio of the viewport, never upscaled
+fn capture_size(viewport_width: u32, viewport_height: u32, capture_width: u32) -> (u32, u32) {
+    let width = capture_width.clamp(1, viewport_width);
+    let height = (viewport_height as u64 * width as u64 / viewport_width as u64).max(1) as u32;
+    (width, height)
+}
+
+fn write_dump(
+    directory: &Path,
+    dumped_at: Instant,
+    events: Vec<RecordedEvent>,
+    frames: Vec<FrameCapture>,
+) -> Result<(), String> {
+    let offset_ms = |at: Instant| dumped_at.saturating_duration_since(at).as_millis() as u64;
+    let events: Vec<_> = events
+        .into_iter()
+        .map(|event| DumpedEvent {
+            offset_ms: offset_ms(event.at),
+            kind: event.kind,
+            description: event.description,
+        })
+        .collect();
+    let events = serde_json::to_string_pretty(&events).map_err(|error| error.to_string())?;
+    std::fs::write(directory.join("events.json"), events)
+        .map_err(|error| format!("failed to write events.json: {error}"))?;
+
+    for frame in frames {
+        let Some(mut image) = image::RgbaImage::from_raw(frame.width, frame.height, frame.rgba)
+        else {
+            continue;
+        };
+        image::imageops::flip_vertical_in_place(&mut image);
+        let path = directory.join(format!("frame-{}ms.png", offset_ms(frame.at)));
+        image
+            .save(&path)
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder() -> FlightRecorder {
+        FlightRecorder::new(FlightRecorderSettings {
+            enabled: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_record() {
+        let mut disabled = FlightRecorder::default();
+        disabled.record(RecordedKind::Input, || unreachable!());
+        assert!(disabled.dump("manual").is_err());
+
+        let mut recorder = recorder();
+        for index in 0..=MAX_EVENTS {
+            recorder.record(RecordedKind::Compositor, || index.to_string());
+        }
+        assert_eq!(recorder.events.len(), MAX_EVENTS);
+        assert_eq!(recorder.events.front().unwrap().description, "1");
+
+        recorder.forget_before(Instant::now() + Duration::from_secs(60));
+        assert!(recorder.events.is_empty());
+    }
+
+    #[test]
+    fn test_capture_size() {
+        assert_eq!(capture_size(1920, 1080, 320), (320, 180));
+        assert_eq!(capture_size(200, 100, 320), (200, 100));
+        assert_eq!(capture_size(4000, 1, 320), (320, 1));
+    }
+
+    #[test]
+    fn test_write_dump() {
+        let directory =
+            std::env::temp_dir().join(format!("verso-flight-recorder-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let now = Instant::now();
+        let at = now - Duration::from_millis(500);
+        let events = vec![RecordedEvent {
+            at,
+            kind: RecordedKind::Input,
+            description: "MouseInput".to_string(),
+        }];
+        let frames = vec![FrameCapture {
+            at,
+            width: 2,
+            height: 1,
+            rgba: vec![255; 8],
+        }];
+        write_dump(&directory, now, events, frames).unwrap();
+
+        let events = std::fs::read_to_string(directory.join("events.json")).unwrap();
+        assert!(events.contains("\"offset_ms\": 500"));
+        assert!(directory.join("frame-500ms.png").exists());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -5,6 +5,11 @@
 
 use std::time::{Duration, Instant};
 
+use dpi::PhysicalSize;
+use winit::monitor::MonitorHandle;
+
+use versoview_messages::PresentMode;
+
 /// Frame pacing configuration
 #[derive(Clone, Debug)]
 pub struct FramePacingConfig {
@@ -29,6 +34,17 @@ impl Default for FramePacingConfig {
     }
 }
 
+impl FramePacingConfig {
+    /// Adjust pacing for the presentation mode of the rendering context
+    ///
+    /// Without a blocking swap, pacing is the only thing keeping composites at the
+    /// refresh rate, so catching up by skipping the wait is turned off.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.adaptive_vsync = present_mode == PresentMode::Fifo;
+        self
+    }
+}
+
 /// Frame timing and pacing state
 #[derive(Debug)]
 pub struct FramePacing {
@@ -119,8 +135,9 @@ impl FramePacing {
         }
 
         // Check for frame drops
-        let drop_threshold =
-            self.target_frame_duration.mul_f32(self.config.frame_drop_threshold);
+        let drop_threshold = self
+            .target_frame_duration
+            .mul_f32(self.config.frame_drop_threshold);
         if frame_time > drop_threshold {
             self.frames_dropped += 1;
             self.behind_schedule = true;
@@ -157,6 +174,17 @@ impl FramePacing {
         }
     }
 
+    /// Restart frame timing from now, call after the system resumes from sleep
+    ///
+    /// Otherwise the first frame after resume would be timed against the last one
+    /// before suspend and counted as dropped, putting the pacer behind schedule.
+    pub fn resync(&mut self) {
+        self.last_frame_time = Instant::now();
+        self.frame_time_history.clear();
+        self.avg_frame_time = self.target_frame_duration;
+        self.behind_schedule = false;
+    }
+
     /// Reset statistics (useful when display changes)
     pub fn reset_stats(&mut self) {
         self.frame_count = 0;
@@ -227,6 +255,16 @@ impl Default for VsyncMode {
     }
 }
 
+impl From<PresentMode> for VsyncMode {
+    fn from(present_mode: PresentMode) -> Self {
+        match present_mode {
+            PresentMode::Fifo => VsyncMode::On,
+            PresentMode::Mailbox => VsyncMode::Mailbox,
+            PresentMode::Immediate => VsyncMode::Off,
+        }
+    }
+}
+
 /// Helper to detect display refresh rate
 pub fn detect_refresh_rate(monitor_refresh_millihertz: Option<u32>) -> f64 {
     monitor_refresh_millihertz
@@ -234,6 +272,33 @@ pub fn detect_refresh_rate(monitor_refresh_millihertz: Option<u32>) -> f64 {
         .unwrap_or(60.0)
 }
 
+/// Refresh rate of a monitor in millihertz, for [`detect_refresh_rate`]
+///
+/// Platforms that don't report the rate of the current mode, like X11 without RandR 1.2 or
+/// Wayland before the compositor sent the output's mode, fall back to the fastest mode at the
+/// monitor's resolution.
+pub fn monitor_refresh_rate(monitor: &MonitorHandle) -> Option<u32> {
+    monitor.refresh_rate_millihertz().or_else(|| {
+        fastest_mode_refresh_rate(
+            monitor
+                .video_modes()
+                .map(|mode| (mode.size(), mode.refresh_rate_millihertz())),
+            monitor.size(),
+        )
+    })
+}
+
+/// Fastest refresh rate of the video modes at `size`
+fn fastest_mode_refresh_rate(
+    modes: impl Iterator<Item = (PhysicalSize<u32>, u32)>,
+    size: PhysicalSize<u32>,
+) -> Option<u32> {
+    modes
+        .filter(|&(mode_size, millihertz)| mode_size == size && millihertz > 0)
+        .map(|(_, millihertz)| millihertz)
+        .max()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,6 +323,16 @@ mod tests {
         assert!(diff < Duration::from_micros(100));
     }
 
+    #[test]
+    fn test_resync_after_sleep() {
+        let mut pacing = FramePacing::default();
+        pacing.last_frame_time -= Duration::from_secs(600);
+        pacing.resync();
+        pacing.on_frame_presented();
+        assert_eq!(pacing.stats().frames_dropped, 0);
+        assert!(!pacing.stats().behind_schedule);
+    }
+
     #[test]
     fn test_refresh_rate_change() {
         let mut pacing = FramePacing::default();
@@ -299,10 +374,38 @@ mod tests {
         assert!(stats.current_fps > 0.0);
     }
 
+    #[test]
+    fn test_present_mode_pacing() {
+        let config = FramePacingConfig::default().with_present_mode(PresentMode::Immediate);
+        assert!(!config.adaptive_vsync);
+        let config = FramePacingConfig::default().with_present_mode(PresentMode::Fifo);
+        assert!(config.adaptive_vsync);
+        assert_eq!(VsyncMode::from(PresentMode::Mailbox), VsyncMode::Mailbox);
+    }
+
     #[test]
     fn test_detect_refresh_rate() {
         assert_eq!(detect_refresh_rate(Some(60000)), 60.0);
         assert_eq!(detect_refresh_rate(Some(144000)), 144.0);
         assert_eq!(detect_refresh_rate(None), 60.0); // Default fallback
     }
+
+    #[test]
+    fn test_fastest_mode_refresh_rate() {
+        let size = PhysicalSize::new(2560, 1440);
+        let modes = [
+            (PhysicalSize::new(1920, 1080), 240000),
+            (size, 60000),
+            (size, 143856),
+            (size, 0),
+        ];
+        assert_eq!(
+            fastest_mode_refresh_rate(modes.into_iter(), size),
+            Some(143856)
+        );
+        assert_eq!(
+            fastest_mode_refresh_rate(modes.into_iter(), PhysicalSize::new(800, 600)),
+            None
+        );
+    }
 }
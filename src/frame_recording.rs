@@ -0,0 +1,270 @@
+//! Frame Recording
+//!
+//! Records the composited frames of the window, for reproduction videos of
+//! rendering bugs. Frames are taken right after they are rendered, at most
+//! [`RecordingSettings::frames_per_second`] a second, and written as a PNG
+//! sequence, appended to one raw RGBA file, or piped as raw RGBA to an encoder
+//! such as `ffmpeg`. Nothing is recorded while nothing is composited, so a
+//! recording of a still page has few frames and an encoder should be told
+//! the frame rate is variable, or use the timestamps in the file names.
+//!
+//! Raw and encoded recordings keep the size of their first frame, frames of
+//! another size, after a window resize, are skipped.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use image::RgbaImage;
+use versoview_messages::{RecordingFormat, RecordingReport, RecordingSettings};
+
+/// Where the frames go
+#[derive(Debug)]
+enum Sink {
+    /// A PNG file per frame
+    Images,
+    /// One raw RGBA file
+    Raw(BufWriter<File>),
+    /// The standard input of an encoder
+    Encoder(Child),
+}
+
+/// A recording of the composited frames in progress
+#[derive(Debug)]
+pub struct FrameRecorder {
+    settings: RecordingSettings,
+    directory: PathBuf,
+    started: Instant,
+    next_frame: Instant,
+    sink: Option<Sink>,
+    size: Option<(u32, u32)>,
+    frames: u64,
+    skipped_frames: u64,
+    error: Option<String>,
+}
+
+impl FrameRecorder {
+    /// Start recording, creating the directory of the frames
+    pub fn start(settings: RecordingSettings, now: Instant) -> Result<Self, String> {
+        let directory = settings.directory.clone().unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("verso-recording-{}", std::process::id()))
+        });
+        fs::create_dir_all(&directory).map_err(|error| {
+            format!(
+                "the directory {} can't be created: {error}",
+                directory.display()
+            )
+        })?;
+        Ok(Self {
+            settings,
+            directory,
+            started: now,
+            next_frame: now,
+            sink: None,
+            size: None,
+            frames: 0,
+            skipped_frames: 0,
+            error: None,
+        })
+    }
+
+    /// Whether the frame composited at `now` should be recorded
+    pub fn frame_due(&self, now: Instant) -> bool {
+        self.error.is_none() && now >= self.next_frame
+    }
+
+    /// Record a frame, top row first
+    pub fn record(&mut self, frame: RgbaImage, now: Instant) {
+        let size = frame.dimensions();
+        let fixed_size =
+            self.settings.format == RecordingFormat::Raw || self.settings.encoder.is_some();
+        if fixed_size && self.size.is_some_and(|first| first != size) {
+            self.skipped_frames += 1;
+            return;
+        }
+        self.next_frame = now + Duration::from_secs(1) / self.settings.frames_per_second.max(1);
+        if let Err(error) = self.write(frame, now) {
+            log::warn!("Verso stops recording frames: {error}");
+            self.error = Some(error);
+        }
+    }
+
+    /// Stop recording, waiting for the encoder to finish
+    pub fn stop(mut self) -> Result<RecordingReport, String> {
+        match self.sink.take() {
+            Some(Sink::Raw(mut file)) => {
+                if let Err(error) = file.flush() {
+                    self.error
+                        .get_or_insert(format!("the frames can't be written: {error}"));
+                }
+            }
+            Some(Sink::Encoder(mut encoder)) => {
+                drop(encoder.stdin.take());
+                match encoder.wait() {
+                    Ok(status) if status.success() => {}
+                    Ok(status) => {
+                        self.error
+                            .get_or_insert(format!("the encoder exited with {status}"));
+                    }
+                    Err(error) => {
+                        self.error
+                            .get_or_insert(format!("the encoder can't be waited on: {error}"));
+                    }
+                }
+            }
+            Some(Sink::Images) | None => {}
+        }
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let (width, height) = self.size.unwrap_or_default();
+        Ok(RecordingReport {
+            frames: self.frames,
+            skipped_frames: self.skipped_frames,
+            width,
+            height,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            directory: self.directory,
+        })
+    }
+
+    fn write(&mut self, frame: RgbaImage, now: Instant) -> Result<(), String> {
+        let (width, height) = frame.dimensions();
+        if self.sink.is_none() {
+            self.sink = Some(self.open(width, height)?);
+            self.size = Some((width, height));
+        }
+        self.frames += 1;
+        match self.sink.as_mut() {
+            Some(Sink::Images) => {
+                let path = self.directory.join(format!(
+                    "frame-{:06}-{}ms.png",
+                    self.frames,
+                    now.duration_since(self.started).as_millis()
+                ));
+                frame
+                    .save(&path)
+                    .map_err(|error| format!("{} can't be written: {error}", path.display()))
+            }
+            Some(Sink::Raw(file)) => file
+                .write_all(frame.as_raw())
+                .map_err(|error| format!("the frames can't be written: {error}")),
+            Some(Sink::Encoder(encoder)) => encoder
+                .stdin
+                .as_mut()
+                .ok_or("the encoder has no standard input")?
+                .write_all(frame.as_raw())
+                .map_err(|error| format!("the encoder stopped reading frames: {error}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Open the sink once the size of the frames is known
+    fn open(&self, width: u32, height: u32) -> Result<Sink, String> {
+        if let Some(encoder) = &self.settings.encoder {
+            let command = encoder_command(
+                encoder,
+                width,
+                height,
+                self.settings.frames_per_second,
+                &self.directory,
+            );
+            let (program, args) = command
+                .split_first()
+                .ok_or("the encoder command is empty")?;
+            let child = Command::new(program)
+                .args(args)
+                .current_dir(&self.directory)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|error| format!("the encoder {program} can't be started: {error}"))?;
+            return Ok(Sink::Encoder(child));
+        }
+        match self.settings.format {
+            RecordingFormat::Png => Ok(Sink::Images),
+            RecordingFormat::Raw => {
+                let path = self.directory.join(format!("frames-{width}x{height}.rgba"));
+                let file = File::create(&path)
+                    .map_err(|error| format!("{} can't be created: {error}", path.display()))?;
+                Ok(Sink::Raw(BufWriter::new(file)))
+            }
+        }
+    }
+}
+
+/// Encoder command with `{width}`, `{height}`, `{fps}` and `{directory}` replaced
+pub fn encoder_command(
+    encoder: &[String],
+    width: u32,
+    height: u32,
+    frames_per_second: u32,
+    directory: &std::path::Path,
+) -> Vec<String> {
+    encoder
+        .iter()
+        .map(|arg| {
+            arg.replace("{width}", &width.to_string())
+                .replace("{height}", &height.to_string())
+                .replace("{fps}", &frames_per_second.to_string())
+                .replace("{directory}", &directory.to_string_lossy())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_recording() {
+        let directory =
+            std::env::temp_dir().join(format!("verso-recording-test-{}", std::process::id()));
+        let start = Instant::now();
+        let mut recorder = FrameRecorder::start(
+            RecordingSettings {
+                directory: Some(directory.clone()),
+                frames_per_second: 10,
+                format: RecordingFormat::Raw,
+                encoder: None,
+            },
+            start,
+        )
+        .unwrap();
+
+        assert!(recorder.frame_due(start));
+        recorder.record(RgbaImage::new(4, 2), start);
+        assert!(!recorder.frame_due(start + Duration::from_millis(50)));
+        let later = start + Duration::from_millis(100);
+        assert!(recorder.frame_due(later));
+        recorder.record(RgbaImage::new(2, 2), later);
+        recorder.record(RgbaImage::new(4, 2), later);
+
+        let report = recorder.stop().unwrap();
+        assert_eq!((report.frames, report.skipped_frames), (2, 1));
+        assert_eq!((report.width, report.height), (4, 2));
+        let raw = fs::read(directory.join("frames-4x2.rgba")).unwrap();
+        assert_eq!(raw.len(), 2 * 4 * 2 * 4);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_encoder_command() {
+        let encoder = [
+            "ffmpeg",
+            "-s",
+            "{width}x{height}",
+            "-r",
+            "{fps}",
+            "{directory}/out.mp4",
+        ]
+        .map(String::from);
+        assert_eq!(
+            encoder_command(&encoder, 1280, 720, 30, std::path::Path::new("/tmp/rec")),
+            ["ffmpeg", "-s", "1280x720", "-r", "30", "/tmp/rec/out.mp4"]
+        );
+    }
+}
@@ -0,0 +1,492 @@
+//! Gesture Commands
+//!
+//! Maps the gestures Verso recognizes to the commands they run, through a table
+//! the controller or a JSON file sets, so each deployment decides what a swipe
+//! or a key chord does. Unless [`GestureBindings::defaults`] is turned off,
+//! Verso's own bindings apply to the gestures the table doesn't bind: pinching
+//! zooms, `CmdOrCtrl+KeyT` opens a tab, `CmdOrCtrl+KeyW` closes it and
+//! `CmdOrCtrl+KeyL` focuses the URL bar. Binding a gesture to
+//! [`GestureCommand::Nothing`] turns its default off.
+//!
+//! Touch gestures are recognized from the touchscreen events of the window:
+//! three fingers moving together, a finger entering from an edge, and a finger
+//! held still. Every touch sequence makes at most one gesture, and touches
+//! aren't forwarded to pages. Pinches and rotations are the touchpad gestures
+//! winit reports, which not every platform has. A pinch bound to
+//! [`GestureCommand::Zoom`] zooms continuously, any other command runs once per
+//! pinch. Key chords match the physical keys, whatever the keyboard layout, and
+//! only with exactly their modifiers held.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use keyboard_types::{Code, Modifiers};
+use versoview_messages::{
+    Gesture, GestureBinding, GestureBindings, GestureCommand, RotationDirection, SwipeDirection,
+    WindowEdge,
+};
+use webrender_api::units::{DevicePoint, DeviceSize, DeviceVector2D};
+use winit::event::TouchPhase;
+
+use crate::keyboard::CMD_OR_CONTROL;
+
+/// How far fingers travel to swipe, in logical pixels
+const SWIPE_DISTANCE: f32 = 80.0;
+/// How close to an edge of the window an edge swipe starts, in logical pixels
+const EDGE_MARGIN: f32 = 24.0;
+/// How long a finger is held still for a long press
+const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+/// How far a held finger may drift and still long press, in logical pixels
+const LONG_PRESS_SLOP: f32 = 10.0;
+/// How far a rotation turns before it runs its command, in degrees
+const ROTATION_ANGLE: f32 = 30.0;
+/// How much a pinch scales before it runs a command other than zooming
+const PINCH_SCALE: f64 = 0.25;
+
+/// Verso's own bindings, see [`GestureBindings::defaults`]
+fn default_bindings() -> Vec<GestureBinding> {
+    [
+        (Gesture::Pinch, GestureCommand::Zoom),
+        (
+            Gesture::KeyChord("CmdOrCtrl+KeyT".to_string()),
+            GestureCommand::NewTab,
+        ),
+        (
+            Gesture::KeyChord("CmdOrCtrl+KeyW".to_string()),
+            GestureCommand::CloseTab,
+        ),
+        (
+            Gesture::KeyChord("CmdOrCtrl+KeyL".to_string()),
+            GestureCommand::FocusUrlBar,
+        ),
+        (
+            Gesture::KeyChord("CmdOrCtrl+Shift+KeyM".to_string()),
+            GestureCommand::ToggleTouchEmulation,
+        ),
+    ]
+    .into_iter()
+    .map(|(gesture, command)| GestureBinding { gesture, command })
+    .collect()
+}
+
+/// Modifiers and key of a [`Gesture::KeyChord`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    modifiers: Modifiers,
+    code: Code,
+}
+
+impl KeyChord {
+    /// Parse a chord like `CmdOrCtrl+Shift+KeyT`, `None` if it isn't one
+    pub fn parse(chord: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let code = Code::from_str(parts.pop()?)
+            .ok()
+            .filter(|code| *code != Code::Unidentified)?;
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CONTROL,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                "meta" | "cmd" | "command" | "super" => Modifiers::META,
+                "cmdorctrl" => CMD_OR_CONTROL,
+                _ => return None,
+            };
+        }
+        Some(Self { modifiers, code })
+    }
+}
+
+/// A finger on the touchscreen
+#[derive(Clone, Copy, Debug)]
+struct TrackedTouch {
+    start: DevicePoint,
+    current: DevicePoint,
+    since: Instant,
+    /// Whether it stayed within [`LONG_PRESS_SLOP`] of where it started
+    still: bool,
+}
+
+/// Recognizes the gestures of a window and looks up the commands bound to them
+#[derive(Debug, Default)]
+pub struct GestureMapper {
+    commands: HashMap<Gesture, GestureCommand>,
+    chords: Vec<(KeyChord, GestureCommand)>,
+    touches: HashMap<u64, TrackedTouch>,
+    /// Most fingers down at once since the first one of the sequence
+    max_touches: usize,
+    /// Whether the touch sequence already made a gesture
+    recognized: bool,
+    pinch_scale: f64,
+    pinch_fired: bool,
+    rotation: f32,
+    rotation_fired: bool,
+    /// Names of the custom commands to send the controller
+    custom: Vec<String>,
+}
+
+impl GestureMapper {
+    /// Create a mapper with the table of `bindings`
+    pub fn new(bindings: &GestureBindings) -> Self {
+        let mut mapper = Self::default();
+        mapper.set_bindings(bindings);
+        mapper
+    }
+
+    /// Replace the table with `bindings`
+    pub fn set_bindings(&mut self, bindings: &GestureBindings) {
+        self.commands.clear();
+        self.chords.clear();
+        let defaults = if bindings.defaults {
+            default_bindings()
+        } else {
+            Vec::new()
+        };
+        for binding in defaults.iter().chain(&bindings.bindings) {
+            match &binding.gesture {
+                Gesture::KeyChord(chord) => match KeyChord::parse(chord) {
+                    Some(chord) => {
+                        self.chords.retain(|(bound, _)| *bound != chord);
+                        self.chords.push((chord, binding.command.clone()));
+                    }
+                    None => log::warn!("Verso ignores the binding of invalid key chord {chord}"),
+                },
+                gesture => {
+                    self.commands
+                        .insert(gesture.clone(), binding.command.clone());
+                }
+            }
+        }
+    }
+
+    /// Command bound to `gesture`, `None` if it does nothing
+    pub fn command(&self, gesture: &Gesture) -> Option<GestureCommand> {
+        self.commands
+            .get(gesture)
+            .filter(|command| **command != GestureCommand::Nothing)
+            .cloned()
+    }
+
+    /// Command bound to pressing the key of `code` with exactly `modifiers`
+    pub fn key_chord(&self, modifiers: Modifiers, code: Code) -> Option<GestureCommand> {
+        self.chords
+            .iter()
+            .find(|(chord, _)| chord.modifiers == modifiers && chord.code == code)
+            .map(|(_, command)| command.clone())
+            .filter(|command| *command != GestureCommand::Nothing)
+    }
+
+    /// Track a touchscreen event, returns the command of the gesture it completes
+    ///
+    /// `viewport` is the size of the window and `scale` its device pixels per logical pixel.
+    pub fn touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        point: DevicePoint,
+        viewport: DeviceSize,
+        scale: f32,
+        now: Instant,
+    ) -> Option<GestureCommand> {
+        match phase {
+            TouchPhase::Started => {
+                if self.touches.is_empty() {
+                    self.max_touches = 0;
+                    self.recognized = false;
+                }
+                self.touches.insert(
+                    id,
+                    TrackedTouch {
+                        start: point,
+                        current: point,
+                        since: now,
+                        still: true,
+                    },
+                );
+                self.max_touches = self.max_touches.max(self.touches.len());
+                None
+            }
+            TouchPhase::Moved => {
+                let touch = self.touches.get_mut(&id)?;
+                touch.current = point;
+                touch.still &= (point - touch.start).length() <= LONG_PRESS_SLOP * scale;
+                if self.recognized {
+                    return None;
+                }
+                let gesture = self.swipe(viewport, scale)?;
+                self.recognized = true;
+                self.command(&gesture)
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&id);
+                None
+            }
+        }
+    }
+
+    /// Swipe the fingers down make, if they travelled far enough
+    fn swipe(&self, viewport: DeviceSize, scale: f32) -> Option<Gesture> {
+        let distance = SWIPE_DISTANCE * scale;
+        match (self.max_touches, self.touches.len()) {
+            (3, 3) => {
+                let travel = self
+                    .touches
+                    .values()
+                    .fold(DeviceVector2D::zero(), |travel, touch| {
+                        travel + (touch.current - touch.start)
+                    })
+                    / 3.0;
+                (travel.length() >= distance)
+                    .then(|| Gesture::ThreeFingerSwipe(swipe_direction(travel)))
+            }
+            (1, 1) => {
+                let touch = self.touches.values().next()?;
+                let margin = EDGE_MARGIN * scale;
+                let travel = touch.current - touch.start;
+                let edge = if touch.start.x <= margin && travel.x >= distance {
+                    WindowEdge::Left
+                } else if touch.start.x >= viewport.width - margin && -travel.x >= distance {
+                    WindowEdge::Right
+                } else if touch.start.y <= margin && travel.y >= distance {
+                    WindowEdge::Top
+                } else if touch.start.y >= viewport.height - margin && -travel.y >= distance {
+                    WindowEdge::Bottom
+                } else {
+                    return None;
+                };
+                Some(Gesture::EdgeSwipe(edge))
+            }
+            _ => None,
+        }
+    }
+
+    /// The finger that would long press, if long pressing does something
+    fn long_press_candidate(&self) -> Option<&TrackedTouch> {
+        if self.recognized || self.max_touches != 1 || self.command(&Gesture::LongPress).is_none() {
+            return None;
+        }
+        self.touches.values().next().filter(|touch| touch.still)
+    }
+
+    /// When a finger held down long presses
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.long_press_candidate()
+            .map(|touch| touch.since + LONG_PRESS_DURATION)
+    }
+
+    /// Returns the command of a long press if a finger was held long enough
+    pub fn poll(&mut self, now: Instant) -> Option<GestureCommand> {
+        let touch = self.long_press_candidate()?;
+        if now < touch.since + LONG_PRESS_DURATION {
+            return None;
+        }
+        self.recognized = true;
+        self.command(&Gesture::LongPress)
+    }
+
+    /// Track a touchpad pinch scaling by `delta`, returns the command it runs
+    pub fn pinch(&mut self, delta: f64, phase: TouchPhase) -> Option<GestureCommand> {
+        if phase == TouchPhase::Started {
+            self.pinch_scale = 0.0;
+            self.pinch_fired = false;
+        }
+        let command = self.command(&Gesture::Pinch)?;
+        if command == GestureCommand::Zoom {
+            return Some(command);
+        }
+        self.pinch_scale += delta;
+        if self.pinch_fired || self.pinch_scale.abs() < PINCH_SCALE {
+            return None;
+        }
+        self.pinch_fired = true;
+        Some(command)
+    }
+
+    /// Track a touchpad rotation by `delta` degrees, counterclockwise if positive, returns the command it runs
+    pub fn rotation(&mut self, delta: f32, phase: TouchPhase) -> Option<GestureCommand> {
+        if phase == TouchPhase::Started {
+            self.rotation = 0.0;
+            self.rotation_fired = false;
+        }
+        self.rotation += delta;
+        if self.rotation_fired || self.rotation.abs() < ROTATION_ANGLE {
+            return None;
+        }
+        self.rotation_fired = true;
+        let direction = if self.rotation > 0.0 {
+            RotationDirection::CounterClockwise
+        } else {
+            RotationDirection::Clockwise
+        };
+        self.command(&Gesture::Rotate(direction))
+    }
+
+    /// Queue a custom command for the controller
+    pub fn queue_custom(&mut self, name: String) {
+        self.custom.push(name);
+    }
+
+    /// Take the custom commands queued since the last call
+    pub fn take_custom(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.custom)
+    }
+}
+
+fn swipe_direction(travel: DeviceVector2D) -> SwipeDirection {
+    if travel.x.abs() >= travel.y.abs() {
+        if travel.x < 0.0 {
+            SwipeDirection::Left
+        } else {
+            SwipeDirection::Right
+        }
+    } else if travel.y < 0.0 {
+        SwipeDirection::Up
+    } else {
+        SwipeDirection::Down
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(gesture: Gesture, command: GestureCommand) -> GestureBinding {
+        GestureBinding { gesture, command }
+    }
+
+    #[test]
+    fn test_bindings_override_defaults() {
+        let chord = KeyChord::parse("CmdOrCtrl+Shift+KeyT").unwrap();
+        assert_eq!(chord.modifiers, CMD_OR_CONTROL | Modifiers::SHIFT);
+        assert_eq!(chord.code, Code::KeyT);
+        assert!(KeyChord::parse("Hyper+KeyT").is_none());
+        assert!(KeyChord::parse("Ctrl+NotAKey").is_none());
+
+        let mapper = GestureMapper::new(&GestureBindings {
+            bindings: vec![
+                binding(
+                    Gesture::KeyChord("CmdOrCtrl+KeyW".to_string()),
+                    GestureCommand::Nothing,
+                ),
+                binding(
+                    Gesture::KeyChord("Alt+ArrowLeft".to_string()),
+                    GestureCommand::Back,
+                ),
+                binding(Gesture::LongPress, GestureCommand::Reload),
+            ],
+            defaults: true,
+        });
+        assert_eq!(
+            mapper.key_chord(CMD_OR_CONTROL, Code::KeyT),
+            Some(GestureCommand::NewTab)
+        );
+        assert_eq!(mapper.key_chord(CMD_OR_CONTROL, Code::KeyW), None);
+        assert_eq!(
+            mapper.key_chord(Modifiers::ALT, Code::ArrowLeft),
+            Some(GestureCommand::Back)
+        );
+        assert_eq!(mapper.key_chord(Modifiers::empty(), Code::ArrowLeft), None);
+        assert_eq!(mapper.command(&Gesture::Pinch), Some(GestureCommand::Zoom));
+
+        let mapper = GestureMapper::new(&GestureBindings {
+            bindings: Vec::new(),
+            defaults: false,
+        });
+        assert_eq!(mapper.key_chord(CMD_OR_CONTROL, Code::KeyT), None);
+        assert_eq!(mapper.command(&Gesture::Pinch), None);
+    }
+
+    #[test]
+    fn test_touch_gestures() {
+        let mut mapper = GestureMapper::new(&GestureBindings {
+            bindings: vec![
+                binding(
+                    Gesture::ThreeFingerSwipe(SwipeDirection::Left),
+                    GestureCommand::Forward,
+                ),
+                binding(Gesture::EdgeSwipe(WindowEdge::Left), GestureCommand::Back),
+                binding(
+                    Gesture::LongPress,
+                    GestureCommand::Custom("menu".to_string()),
+                ),
+            ],
+            defaults: false,
+        });
+        let viewport = DeviceSize::new(800.0, 600.0);
+        let start = Instant::now();
+        let mut touch =
+            |id, phase, x, y| mapper.touch(id, phase, DevicePoint::new(x, y), viewport, 1.0, start);
+
+        // Three fingers swiping left.
+        for id in 0..3 {
+            assert_eq!(
+                touch(id, TouchPhase::Started, 400.0, 100.0 + id as f32 * 50.0),
+                None
+            );
+        }
+        assert_eq!(touch(0, TouchPhase::Moved, 100.0, 100.0), None);
+        assert_eq!(touch(1, TouchPhase::Moved, 100.0, 150.0), None);
+        assert_eq!(
+            touch(2, TouchPhase::Moved, 100.0, 200.0),
+            Some(GestureCommand::Forward)
+        );
+        for id in 0..3 {
+            touch(id, TouchPhase::Ended, 100.0, 100.0);
+        }
+
+        // A finger entering from the left edge, then one swiping in the middle.
+        touch(0, TouchPhase::Started, 5.0, 300.0);
+        assert_eq!(
+            touch(0, TouchPhase::Moved, 120.0, 300.0),
+            Some(GestureCommand::Back)
+        );
+        touch(0, TouchPhase::Ended, 120.0, 300.0);
+        touch(0, TouchPhase::Started, 400.0, 300.0);
+        assert_eq!(touch(0, TouchPhase::Moved, 520.0, 300.0), None);
+        touch(0, TouchPhase::Ended, 520.0, 300.0);
+
+        // A finger held still.
+        touch(0, TouchPhase::Started, 400.0, 300.0);
+        touch(0, TouchPhase::Moved, 404.0, 302.0);
+        let deadline = mapper.next_deadline().unwrap();
+        assert_eq!(deadline, start + LONG_PRESS_DURATION);
+        assert_eq!(mapper.poll(start), None);
+        assert_eq!(
+            mapper.poll(deadline),
+            Some(GestureCommand::Custom("menu".to_string()))
+        );
+        assert_eq!(mapper.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_touchpad_gestures() {
+        let mut mapper = GestureMapper::new(&GestureBindings {
+            bindings: vec![
+                binding(
+                    Gesture::Rotate(RotationDirection::Clockwise),
+                    GestureCommand::ResetZoom,
+                ),
+                binding(Gesture::Pinch, GestureCommand::ToggleHud),
+            ],
+            defaults: true,
+        });
+        assert_eq!(mapper.rotation(-20.0, TouchPhase::Started), None);
+        assert_eq!(
+            mapper.rotation(-20.0, TouchPhase::Moved),
+            Some(GestureCommand::ResetZoom)
+        );
+        assert_eq!(mapper.rotation(-20.0, TouchPhase::Moved), None);
+        assert_eq!(mapper.rotation(40.0, TouchPhase::Started), None);
+
+        assert_eq!(mapper.pinch(0.2, TouchPhase::Started), None);
+        assert_eq!(
+            mapper.pinch(0.1, TouchPhase::Moved),
+            Some(GestureCommand::ToggleHud)
+        );
+        assert_eq!(mapper.pinch(0.1, TouchPhase::Moved), None);
+    }
+}
@@ -0,0 +1,351 @@
+//! HTTP/3 for Embedder-Side Fetches
+//!
+//! Lets the requests Verso makes itself (favicons, upgraded mixed content and
+//! color managed images) use HTTP/3 over QUIC. An origin is only tried over
+//! HTTP/3 once it advertised it with an `Alt-Svc` header, or right away when
+//! discovery is off. The attempt races a TCP connection started after a short
+//! delay, happy eyeballs style, so a network dropping UDP costs at most that
+//! delay. An origin whose HTTP/3 attempt fails is marked broken, with a backoff
+//! doubling on every failure, and origins behind known broken middleboxes can
+//! be excluded outright.
+//!
+//! This is not HTTP/3 for web content: Servo's network stack loads pages and
+//! their subresources over TCP, it has no QUIC support, so navigations keep
+//! using HTTP/2 and HTTP/1.1 and their timings don't report HTTP/3. Protocol usage of Verso's requests is counted
+//! in [`ProtocolStats`] instead.
+//!
+//! QUIC needs the `http3` feature, which builds on reqwest's unstable HTTP/3
+//! support and so also needs `RUSTFLAGS="--cfg reqwest_unstable"`. Without it
+//! every request goes over TCP.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use http::{HeaderMap, Method, Version};
+use reqwest::{Client, Response};
+use url::Url;
+use versoview_messages::Http3Settings;
+
+/// How long an origin stays broken after its first failure, doubled on every further failure
+const BROKEN_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Longest time an origin stays broken
+const MAX_BROKEN_BACKOFF: Duration = Duration::from_secs(48 * 60 * 60);
+
+static SHARED: OnceLock<Http3Transport> = OnceLock::new();
+
+/// Requests served by each protocol
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProtocolStats {
+    /// Responses received over HTTP/3
+    pub http3: u64,
+    /// Responses received over HTTP/2
+    pub http2: u64,
+    /// Responses received over HTTP/1.x
+    pub http1: u64,
+    /// HTTP/3 attempts that failed
+    pub http3_failures: u64,
+    /// HTTP/3 attempts the TCP connection won
+    pub tcp_fallbacks: u64,
+}
+
+/// What an `Alt-Svc` header says about HTTP/3
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AltSvc {
+    /// HTTP/3 is available for this long
+    Http3(Duration),
+    /// Forget every alternative service of the origin
+    Clear,
+}
+
+/// Parse an `Alt-Svc` header value, `None` if it doesn't mention HTTP/3
+pub fn parse_alt_svc(value: &str) -> Option<AltSvc> {
+    if value.trim().eq_ignore_ascii_case("clear") {
+        return Some(AltSvc::Clear);
+    }
+    value.split(',').find_map(|alternative| {
+        let mut parameters = alternative.split(';').map(str::trim);
+        let (protocol, _authority) = parameters.next()?.split_once('=')?;
+        if protocol != "h3" {
+            return None;
+        }
+        // Alternatives are fresh for 24 hours without a `ma` parameter.
+        let max_age = parameters
+            .filter_map(|parameter| parameter.strip_prefix("ma="))
+            .find_map(|max_age| max_age.parse().ok())
+            .unwrap_or(24 * 60 * 60);
+        Some(AltSvc::Http3(Duration::from_secs(max_age)))
+    })
+}
+
+struct Broken {
+    until: Instant,
+    failures: u32,
+}
+
+/// Decides per origin whether to try HTTP/3
+pub struct Http3Policy {
+    settings: Http3Settings,
+    advertised: HashMap<String, Instant>,
+    broken: HashMap<String, Broken>,
+    stats: ProtocolStats,
+}
+
+impl Http3Policy {
+    /// Create a policy following `settings`
+    pub fn new(settings: Http3Settings) -> Self {
+        Self {
+            settings,
+            advertised: HashMap::new(),
+            broken: HashMap::new(),
+            stats: ProtocolStats::default(),
+        }
+    }
+
+    /// Whether a request to `url` should try HTTP/3 first
+    pub fn should_try_http3(&self, url: &Url, now: Instant) -> bool {
+        if !self.settings.enabled || url.scheme() != "https" {
+            return false;
+        }
+        let origin = url.origin().ascii_serialization();
+        if self.settings.disabled_origins.contains(&origin)
+            || self
+                .broken
+                .get(&origin)
+                .is_some_and(|broken| broken.until > now)
+        {
+            return false;
+        }
+        !self.settings.alt_svc_discovery
+            || self
+                .advertised
+                .get(&origin)
+                .is_some_and(|expiry| *expiry > now)
+    }
+
+    /// Record a response, with the `Alt-Svc` header it carried
+    pub fn on_response(
+        &mut self,
+        url: &Url,
+        version: Version,
+        alt_svc: Option<&str>,
+        now: Instant,
+    ) {
+        let origin = url.origin().ascii_serialization();
+        match version {
+            Version::HTTP_3 => {
+                self.stats.http3 += 1;
+                self.broken.remove(&origin);
+            }
+            Version::HTTP_2 => self.stats.http2 += 1,
+            _ => self.stats.http1 += 1,
+        }
+        match alt_svc.and_then(parse_alt_svc) {
+            Some(AltSvc::Http3(max_age)) => {
+                self.advertised.insert(origin, now + max_age);
+            }
+            Some(AltSvc::Clear) => {
+                self.advertised.remove(&origin);
+            }
+            None => {}
+        }
+    }
+
+    /// Record a failed HTTP/3 attempt, the origin is skipped for a while
+    pub fn on_http3_failure(&mut self, url: &Url, now: Instant) {
+        self.stats.http3_failures += 1;
+        let broken = self
+            .broken
+            .entry(url.origin().ascii_serialization())
+            .or_insert(Broken {
+                until: now,
+                failures: 0,
+            });
+        let backoff = BROKEN_BACKOFF.saturating_mul(1 << broken.failures.min(16));
+        broken.until = now + backoff.min(MAX_BROKEN_BACKOFF);
+        broken.failures += 1;
+    }
+
+    /// Record an HTTP/3 attempt that lost the race to TCP
+    pub fn on_tcp_fallback(&mut self) {
+        self.stats.tcp_fallbacks += 1;
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> ProtocolStats {
+        self.stats
+    }
+}
+
+/// Sends Verso's requests over HTTP/3 when the policy allows, falling back to TCP
+pub struct Http3Transport {
+    policy: Mutex<Http3Policy>,
+    fallback_delay: Duration,
+    quic: Option<Client>,
+}
+
+impl Http3Transport {
+    /// Set up the transport shared by the whole process, only the first call has an effect
+    pub fn init(settings: Http3Settings) {
+        let _ = SHARED.set(Self::new(settings));
+    }
+
+    /// The transport shared by the whole process, HTTP/3 is disabled until [`Self::init`]
+    pub fn shared() -> &'static Self {
+        SHARED.get_or_init(|| Self::new(Http3Settings::default()))
+    }
+
+    fn new(settings: Http3Settings) -> Self {
+        let quic = settings.enabled.then(quic_client).flatten();
+        Self {
+            fallback_delay: Duration::from_millis(settings.fallback_delay_ms),
+            policy: Mutex::new(Http3Policy::new(settings)),
+            quic,
+        }
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> ProtocolStats {
+        self.policy.lock().unwrap().stats()
+    }
+
+    /// Send a request, over HTTP/3 if the origin supports it, else with the `tcp` client
+    pub async fn send(
+        &self,
+        tcp: &Client,
+        method: Method,
+        url: &Url,
+        headers: HeaderMap,
+    ) -> reqwest::Result<Response> {
+        let tcp_request = tcp
+            .request(method.clone(), url.as_str())
+            .headers(headers.clone());
+        let response = match &self.quic {
+            Some(quic)
+                if self
+                    .policy
+                    .lock()
+                    .unwrap()
+                    .should_try_http3(url, Instant::now()) =>
+            {
+                let quic_request = quic
+                    .request(method, url.as_str())
+                    .headers(headers)
+                    .version(Version::HTTP_3);
+                self.race(quic_request, tcp_request, url).await?
+            }
+            _ => tcp_request.send().await?,
+        };
+
+        let alt_svc = response
+            .headers()
+            .get(http::header::ALT_SVC)
+            .and_then(|value| value.to_str().ok());
+        self.policy
+            .lock()
+            .unwrap()
+            .on_response(url, response.version(), alt_svc, Instant::now());
+        Ok(response)
+    }
+
+    /// Start HTTP/3 right away and TCP after the fallback delay, the first response wins
+    async fn race(
+        &self,
+        quic_request: reqwest::RequestBuilder,
+        tcp_request: reqwest::RequestBuilder,
+        url: &Url,
+    ) -> reqwest::Result<Response> {
+        let quic = quic_request.send();
+        let tcp = async {
+            tokio::time::sleep(self.fallback_delay).await;
+            tcp_request.send().await
+        };
+        tokio::pin!(quic, tcp);
+        tokio::select! {
+            response = &mut quic => match response {
+                Ok(response) => Ok(response),
+                Err(error) => {
+                    log::debug!("HTTP/3 to {url} failed, falling back to TCP: {error}");
+                    self.policy.lock().unwrap().on_http3_failure(url, Instant::now());
+                    tcp.await
+                }
+            },
+            response = &mut tcp => {
+                self.policy.lock().unwrap().on_tcp_fallback();
+                response
+            }
+        }
+    }
+}
+
+#[cfg(feature = "http3")]
+fn quic_client() -> Option<Client> {
+    Client::builder()
+        .http3_prior_knowledge()
+        .build()
+        .inspect_err(|error| {
+            log::error!("HTTP/3 disabled, failed to create a QUIC client: {error}")
+        })
+        .ok()
+}
+
+#[cfg(not(feature = "http3"))]
+fn quic_client() -> Option<Client> {
+    log::warn!("HTTP/3 is enabled but Verso was built without the http3 feature, using TCP only");
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_alt_svc() {
+        assert_eq!(
+            parse_alt_svc(r#"h3=":443"; ma=3600, h3-29=":443"; ma=3600"#),
+            Some(AltSvc::Http3(Duration::from_secs(3600)))
+        );
+        assert_eq!(
+            parse_alt_svc(r#"h2="alt.example:443", h3=":8443""#),
+            Some(AltSvc::Http3(Duration::from_secs(24 * 60 * 60)))
+        );
+        assert_eq!(parse_alt_svc("clear"), Some(AltSvc::Clear));
+        assert_eq!(parse_alt_svc(r#"h2=":443""#), None);
+    }
+
+    #[test]
+    fn test_discovery_and_broken_backoff() {
+        let now = Instant::now();
+        let mut policy = Http3Policy::new(Http3Settings {
+            enabled: true,
+            disabled_origins: vec!["https://middlebox.example".to_string()],
+            ..Default::default()
+        });
+        let site = url("https://site.example/favicon.ico");
+        assert!(!policy.should_try_http3(&site, now));
+
+        policy.on_response(&site, Version::HTTP_2, Some(r#"h3=":443"; ma=3600"#), now);
+        assert!(policy.should_try_http3(&site, now));
+        assert!(!policy.should_try_http3(&site, now + Duration::from_secs(3601)));
+
+        policy.on_http3_failure(&site, now);
+        assert!(!policy.should_try_http3(&site, now + Duration::from_secs(1)));
+        assert!(policy.should_try_http3(&site, now + BROKEN_BACKOFF));
+        policy.on_http3_failure(&site, now);
+        assert!(!policy.should_try_http3(&site, now + BROKEN_BACKOFF));
+
+        let middlebox = url("https://middlebox.example/");
+        policy.on_response(&middlebox, Version::HTTP_11, Some(r#"h3=":443""#), now);
+        assert!(!policy.should_try_http3(&middlebox, now));
+
+        let stats = policy.stats();
+        assert_eq!((stats.http2, stats.http1, stats.http3_failures), (1, 1, 2));
+    }
+}
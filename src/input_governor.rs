@@ -0,0 +1,370 @@
+//! Input Rate Governor
+//!
+//! Gaming mice can report motion at up to 8kHz. Forwarding every sample to the
+//! compositor floods its queue with hit tests nobody will see. The governor
+//! resamples mouse move and wheel streams to at most one event per frame
+//! interval, while keeping every raw sample around for consumers that want full
+//! resolution pointer data. Wheel events keep the phase of the last one coalesced,
+//! and those starting or ending a scroll are never held back.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+use dpi::PhysicalPosition;
+use embedder_traits::TouchEventType;
+pub use versoview_messages::RawPointerSample;
+use webrender_api::units::{DevicePoint, LayoutVector2D};
+
+/// Input governor configuration
+#[derive(Clone, Debug)]
+pub struct InputGovernorConfig {
+    /// Enable resampling (disable to forward every event)
+    pub enabled: bool,
+    /// Maximum rate at which events are forwarded, in Hz
+    pub max_rate_hz: f64,
+    /// Number of raw pointer samples kept for raw-update consumers
+    pub raw_history: usize,
+}
+
+impl Default for InputGovernorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_rate_hz: 240.0,
+            raw_history: 1024,
+        }
+    }
+}
+
+/// Input governor statistics
+#[derive(Clone, Debug, Default)]
+pub struct InputGovernorStats {
+    /// Mouse move events received from the OS
+    pub moves_received: u64,
+    /// Mouse move events forwarded to the compositor
+    pub moves_forwarded: u64,
+    /// Wheel events received from the OS
+    pub wheels_received: u64,
+    /// Wheel events forwarded to the compositor
+    pub wheels_forwarded: u64,
+}
+
+impl InputGovernorStats {
+    /// Fraction of move events dropped by resampling
+    pub fn move_drop_ratio(&self) -> f64 {
+        if self.moves_received == 0 {
+            0.0
+        } else {
+            1.0 - self.moves_forwarded as f64 / self.moves_received as f64
+        }
+    }
+}
+
+/// Wheel deltas waiting to be forwarded
+#[derive(Clone, Copy, Debug)]
+struct PendingWheel {
+    delta: LayoutVector2D,
+    count: u32,
+    phase: TouchEventType,
+}
+
+/// Resamples high-frequency pointer input to the frame rate
+#[derive(Debug)]
+pub struct InputGovernor {
+    config: InputGovernorConfig,
+    interval: Duration,
+    last_move_forwarded: Option<Instant>,
+    pending_move: Option<DevicePoint>,
+    last_wheel_forwarded: Option<Instant>,
+    pending_wheel: Option<PendingWheel>,
+    raw_samples: VecDeque<RawPointerSample>,
+    stats: InputGovernorStats,
+}
+
+impl InputGovernor {
+    /// Create a new governor
+    pub fn new(config: InputGovernorConfig) -> Self {
+        let interval = Duration::from_secs_f64(1.0 / config.max_rate_hz.max(1.0));
+        Self {
+            raw_samples: VecDeque::with_capacity(config.raw_history),
+            config,
+            interval,
+            last_move_forwarded: None,
+            pending_move: None,
+            last_wheel_forwarded: None,
+            pending_wheel: None,
+            stats: InputGovernorStats::default(),
+        }
+    }
+
+    /// Record a mouse move, returning the position to forward now, if any
+    pub fn on_mouse_move(&mut self, point: DevicePoint, now: Instant) -> Option<DevicePoint> {
+        self.stats.moves_received += 1;
+        if self.raw_samples.len() == self.config.raw_history {
+            self.raw_samples.pop_front();
+        }
+        self.raw_samples.push_back(RawPointerSample {
+            position: PhysicalPosition::new(point.x as f64, point.y as f64),
+            timestamp_us: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_micros() as u64),
+        });
+
+        if !self.config.enabled || self.interval_elapsed(self.last_move_forwarded, now) {
+            self.pending_move = None;
+            self.last_move_forwarded = Some(now);
+            self.stats.moves_forwarded += 1;
+            return Some(point);
+        }
+        self.pending_move = Some(point);
+        None
+    }
+
+    /// Forward the mouse move held back, whether or not its interval has elapsed
+    ///
+    /// Call before a button event so the page sees the button at the last position.
+    pub fn take_pending_move(&mut self, now: Instant) -> Option<DevicePoint> {
+        let point = self.pending_move.take()?;
+        self.last_move_forwarded = Some(now);
+        self.stats.moves_forwarded += 1;
+        Some(point)
+    }
+
+    /// Record a wheel delta, returning the averaged delta to forward now with its phase, if any
+    ///
+    /// Deltas are averaged rather than summed, matching how the compositor
+    /// combines scroll events within a frame.
+    pub fn on_wheel(
+        &mut self,
+        delta: LayoutVector2D,
+        phase: TouchEventType,
+        now: Instant,
+    ) -> Option<(LayoutVector2D, TouchEventType)> {
+        self.stats.wheels_received += 1;
+        let pending = self.pending_wheel.get_or_insert(PendingWheel {
+            delta: LayoutVector2D::zero(),
+            count: 0,
+            phase,
+        });
+        pending.delta += delta;
+        pending.count += 1;
+        pending.phase = phase;
+
+        if !self.config.enabled
+            || phase != TouchEventType::Move
+            || self.interval_elapsed(self.last_wheel_forwarded, now)
+        {
+            self.last_wheel_forwarded = Some(now);
+            return self.take_wheel();
+        }
+        None
+    }
+
+    /// Forward whatever was held back once its interval has elapsed
+    ///
+    /// Call at [`Self::next_deadline`] so the final position of a burst is never lost.
+    pub fn flush(
+        &mut self,
+        now: Instant,
+    ) -> (
+        Option<DevicePoint>,
+        Option<(LayoutVector2D, TouchEventType)>,
+    ) {
+        let mut pending_move = None;
+        if self.pending_move.is_some() && self.interval_elapsed(self.last_move_forwarded, now) {
+            pending_move = self.pending_move.take();
+            self.last_move_forwarded = Some(now);
+            self.stats.moves_forwarded += 1;
+        }
+        let mut pending_wheel = None;
+        if self.pending_wheel.is_some() && self.interval_elapsed(self.last_wheel_forwarded, now) {
+            self.last_wheel_forwarded = Some(now);
+            pending_wheel = self.take_wheel();
+        }
+        (pending_move, pending_wheel)
+    }
+
+    /// Whether input is being held back for the next flush
+    pub fn has_pending(&self) -> bool {
+        self.pending_move.is_some() || self.pending_wheel.is_some()
+    }
+
+    /// When held back input should be flushed, if there is any
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let move_deadline = self
+            .pending_move
+            .and(self.last_move_forwarded)
+            .map(|last| last + self.interval);
+        let wheel_deadline = self
+            .pending_wheel
+            .and(self.last_wheel_forwarded)
+            .map(|last| last + self.interval);
+        move_deadline.into_iter().chain(wheel_deadline).min()
+    }
+
+    /// Drain the full resolution pointer samples recorded since the last call
+    pub fn take_raw_samples(&mut self) -> Vec<RawPointerSample> {
+        self.raw_samples.drain(..).collect()
+    }
+
+    /// Get governor statistics
+    pub fn stats(&self) -> &InputGovernorStats {
+        &self.stats
+    }
+
+    fn interval_elapsed(&self, last: Option<Instant>, now: Instant) -> bool {
+        last.is_none_or(|last| now.duration_since(last) >= self.interval)
+    }
+
+    fn take_wheel(&mut self) -> Option<(LayoutVector2D, TouchEventType)> {
+        let pending = self.pending_wheel.take()?;
+        self.stats.wheels_forwarded += 1;
+        Some((pending.delta / pending.count as f32, pending.phase))
+    }
+}
+
+impl Default for InputGovernor {
+    fn default() -> Self {
+        Self::new(InputGovernorConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_is_resampled() {
+        let mut governor = InputGovernor::default();
+        let start = Instant::now();
+
+        // 8 samples within 1ms, as an 8kHz mouse would report them.
+        let forwarded: Vec<_> = (0..8)
+            .filter_map(|i| {
+                governor.on_mouse_move(
+                    DevicePoint::new(i as f32, 0.0),
+                    start + Duration::from_micros(125 * i),
+                )
+            })
+            .collect();
+
+        assert_eq!(forwarded.len(), 1);
+        assert!(governor.has_pending());
+        assert_eq!(governor.next_deadline(), Some(start + governor.interval));
+        assert_eq!(
+            governor.flush(start + Duration::from_millis(1)),
+            (None, None)
+        );
+
+        let (point, wheel) = governor.flush(start + Duration::from_millis(5));
+        assert_eq!(point, Some(DevicePoint::new(7.0, 0.0)));
+        assert_eq!(wheel, None);
+        assert_eq!(governor.stats().moves_received, 8);
+        assert_eq!(governor.stats().moves_forwarded, 2);
+    }
+
+    #[test]
+    fn test_raw_samples_kept() {
+        let mut governor = InputGovernor::default();
+        let start = Instant::now();
+        for i in 0..8 {
+            governor.on_mouse_move(DevicePoint::new(i as f32, 0.0), start);
+        }
+        let raw = governor.take_raw_samples();
+        assert_eq!(raw.len(), 8);
+        assert_eq!(raw[7].position, PhysicalPosition::new(7.0, 0.0));
+        assert!(governor.take_raw_samples().is_empty());
+    }
+
+    #[test]
+    fn test_raw_history_bounded() {
+        let config = InputGovernorConfig {
+            raw_history: 4,
+            ..Default::default()
+        };
+        let mut governor = InputGovernor::new(config);
+        let start = Instant::now();
+        for i in 0..10 {
+            governor.on_mouse_move(DevicePoint::new(i as f32, 0.0), start);
+        }
+        let raw = governor.take_raw_samples();
+        assert_eq!(raw.len(), 4);
+        assert_eq!(raw[0].position, PhysicalPosition::new(6.0, 0.0));
+    }
+
+    #[test]
+    fn test_wheel_is_averaged() {
+        let mut governor = InputGovernor::default();
+        let start = Instant::now();
+        assert!(
+            governor
+                .on_wheel(LayoutVector2D::new(0.0, 10.0), TouchEventType::Move, start)
+                .is_some()
+        );
+        assert!(
+            governor
+                .on_wheel(LayoutVector2D::new(0.0, 10.0), TouchEventType::Move, start)
+                .is_none()
+        );
+        assert!(
+            governor
+                .on_wheel(LayoutVector2D::new(0.0, 30.0), TouchEventType::Move, start)
+                .is_none()
+        );
+
+        let (_, wheel) = governor.flush(start + Duration::from_millis(5));
+        assert_eq!(
+            wheel,
+            Some((LayoutVector2D::new(0.0, 20.0), TouchEventType::Move))
+        );
+    }
+
+    #[test]
+    fn test_wheel_end_is_not_held_back() {
+        let mut governor = InputGovernor::default();
+        let start = Instant::now();
+        governor.on_wheel(LayoutVector2D::new(0.0, 10.0), TouchEventType::Down, start);
+        assert!(
+            governor
+                .on_wheel(LayoutVector2D::new(0.0, 10.0), TouchEventType::Move, start)
+                .is_none()
+        );
+        assert_eq!(
+            governor.on_wheel(LayoutVector2D::new(0.0, 30.0), TouchEventType::Up, start),
+            Some((LayoutVector2D::new(0.0, 20.0), TouchEventType::Up))
+        );
+        assert!(!governor.has_pending());
+    }
+
+    #[test]
+    fn test_pending_move_taken_before_interval() {
+        let mut governor = InputGovernor::default();
+        let start = Instant::now();
+        governor.on_mouse_move(DevicePoint::new(0.0, 0.0), start);
+        governor.on_mouse_move(DevicePoint::new(5.0, 0.0), start);
+        assert_eq!(
+            governor.take_pending_move(start),
+            Some(DevicePoint::new(5.0, 0.0))
+        );
+        assert!(governor.take_pending_move(start).is_none());
+        assert_eq!(governor.stats().moves_forwarded, 2);
+    }
+
+    #[test]
+    fn test_disabled_forwards_everything() {
+        let config = InputGovernorConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let mut governor = InputGovernor::new(config);
+        let start = Instant::now();
+        for i in 0..8 {
+            assert!(
+                governor
+                    .on_mouse_move(DevicePoint::new(i as f32, 0.0), start)
+                    .is_some()
+            );
+        }
+        assert!(!governor.has_pending());
+    }
+}
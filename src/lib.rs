@@ -5,16 +5,104 @@
 
 #![deny(missing_docs)]
 
+/// Interpretation of text typed in the address bar.
+pub mod address_input;
+/// Per-subsystem heap allocation counters.
+pub mod allocation_profiling;
+/// Compositor-driven playback of animated images.
+pub mod animated_images;
+/// Audit log of blocking decisions.
+pub mod audit_log;
+/// Conversion of images to the output color space.
+pub mod color_management;
 /// Verso's compositor component to handle webrender.
 pub mod compositor;
+/// Text drawn by the compositor without a webview.
+pub mod compositor_text;
 /// Utilities to read options and preferences.
 pub mod config;
+/// Cursors the embedder puts in place of the pages' ones.
+pub mod cursor_theme;
+/// Detection of GPU driver resets.
+pub mod device_lost;
+/// Byte and copy accounting of display lists received from script.
+pub mod display_list_transport;
+/// Dragging links, images and text between webviews.
+pub mod drag_drop;
+/// Emoji font registration and color glyph detection.
+pub mod emoji;
 /// Error and result types.
 pub mod errors;
+/// Event loop message timing and stall detection.
+pub mod event_loop_monitor;
+/// Textures of the embedder composited with the webviews.
+pub mod external_layers;
+/// Flight recorder of recent input, compositor messages and frames.
+pub mod flight_recorder;
+/// Frame pacing aligned with the display refresh rate.
+pub mod frame_pacing;
+/// Recording of the composited frames.
+pub mod frame_recording;
+/// Mapping of gestures and key chords to commands.
+pub mod gestures;
+/// HTTP/3 for embedder-side fetches, the requests Verso makes itself.
+pub mod http3;
+/// Resampling of high-frequency mouse input.
+pub mod input_governor;
 /// Utilities to handle keyboard inputs and states.
 pub mod keyboard;
+/// Memory pressure detection and response.
+pub mod memory_pressure;
+/// Per-webview network routing through proxies and DNS resolvers.
+pub mod network_context;
+/// Pinch zoom of webviews and its animated reset.
+pub mod pinch_zoom;
+/// Kiosk playlist rotation.
+pub mod playlist;
+/// Waiting for pages to be loaded and their rendering settled.
+pub mod readiness;
+/// Automatic recovery of crashed and hung webviews.
+pub mod recovery;
+/// Comparing hardware rendered frames with the software renderer.
+pub mod render_validation;
+/// WebRender renderer option overrides.
+pub mod renderer_options;
+/// Restarting the renderer after the GL context was lost.
+pub mod renderer_restart;
 /// Verso's rendering context.
 pub mod rendering;
+/// Request prioritization by rendering impact.
+pub mod request_priority;
+/// Safe mode after repeated startup crashes.
+pub mod safe_mode;
+/// Screenshots of the composited frames.
+pub mod screenshot;
+/// Script engine policy per profile and origin.
+pub mod script_policy;
+/// Scroll event coalescing.
+pub mod scroll_coalescing;
+/// Scroll event sequencing and merging with script scrolls.
+pub mod scroll_sequence;
+/// Startup session manifests.
+pub mod session;
+/// Compositing into textures shared with the controller.
+pub mod shared_texture;
+/// Shutdown sequencing with a forced-teardown deadline.
+pub mod shutdown;
+/// Per-origin security policy settings.
+pub mod site_settings;
+/// Animated mouse wheel and keyboard scrolling.
+pub mod smooth_scroll;
+/// Long-session soak testing.
+pub mod soak;
+/// Frame rate, dropped frames and scroll coalescing shown in the HUD.
+pub mod stats_hud;
+/// System suspend/resume handling.
+pub mod suspend;
+/// Rasterization of SVG icons and images.
+pub mod svg_raster;
+/// Text anti-aliasing and hinting policy.
+pub mod text_rendering;
 /// Utilities to handle touch inputs and states.
 pub mod touch;
 /// Main entry types and functions.
@@ -33,6 +121,8 @@ pub use winit;
 pub mod bookmark;
 /// Download manager
 pub mod download;
+/// Extended Compositor Messages for PipelineId association
+pub mod extended_compositor_msg;
 /// Storage manager, handles all the storage operations,
 /// such as reading and writing bookmarks, preferences, etc.
 pub(crate) mod storage;
@@ -40,8 +130,6 @@ pub(crate) mod storage;
 pub mod tab;
 /// Utilities
 pub(crate) mod utils;
-/// Extended Compositor Messages for PipelineId association
-pub mod extended_compositor_msg;
 
 /// WebGL support infrastructure.
 /// This module is only available when the `webgl` feature is enabled.
@@ -1,9 +1,11 @@
 // Prevent console window from appearing on Windows
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use versoview::event_loop_monitor::message_type;
 use versoview::verso::EventLoopProxyMessage;
 use versoview::{Result, Verso};
 use winit::application::ApplicationHandler;
+use winit::event::StartCause;
 use winit::event_loop::{self, DeviceEvents};
 use winit::event_loop::{EventLoop, EventLoopProxy};
 
@@ -14,10 +16,28 @@ struct App {
 
 impl ApplicationHandler<EventLoopProxyMessage> for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(v) = self.verso.as_mut() {
+            v.resume();
+            return;
+        }
         self.verso = Some(Verso::new(event_loop, self.proxy.clone()));
         self.verso.as_mut().unwrap().init();
     }
 
+    fn suspended(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(v) = self.verso.as_mut() {
+            v.suspend();
+        }
+    }
+
+    fn new_events(&mut self, event_loop: &event_loop::ActiveEventLoop, cause: StartCause) {
+        if let (Some(v), StartCause::ResumeTimeReached { .. }) = (self.verso.as_mut(), cause) {
+            v.begin_event_loop_iteration();
+            v.handle_servo_messages(event_loop);
+            v.end_event_loop_iteration("ResumeTimeReached");
+        }
+    }
+
     fn exiting(&mut self, _event_loop: &event_loop::ActiveEventLoop) {
         if let Some(v) = self.verso.as_mut() {
             v.before_shutdown();
@@ -31,7 +51,10 @@ impl ApplicationHandler<EventLoopProxyMessage> for App {
         event: winit::event::WindowEvent,
     ) {
         if let Some(v) = self.verso.as_mut() {
+            let message = message_type(&event);
+            v.begin_event_loop_iteration();
             v.handle_window_event(event_loop, window_id, event);
+            v.end_event_loop_iteration(&message);
         }
     }
 
@@ -41,6 +64,12 @@ impl ApplicationHandler<EventLoopProxyMessage> for App {
         event: EventLoopProxyMessage,
     ) {
         if let Some(v) = self.verso.as_mut() {
+            let message = match &event {
+                EventLoopProxyMessage::Wake => "Wake".to_string(),
+                EventLoopProxyMessage::IpcMessage(message) => message_type(message),
+                EventLoopProxyMessage::VersoInternalMessage(message) => message_type(message),
+            };
+            v.begin_event_loop_iteration();
             match event {
                 EventLoopProxyMessage::Wake => {
                     v.request_redraw(event_loop);
@@ -52,6 +81,7 @@ impl ApplicationHandler<EventLoopProxyMessage> for App {
                     v.handle_verso_internal_message(message);
                 }
             }
+            v.end_event_loop_iteration(&message);
         }
     }
 }
@@ -66,6 +96,19 @@ async fn main() -> Result<()> {
     let mut app = App { verso: None, proxy };
     event_loop.run_app(&mut app)?;
 
+    if let Some(restart) = app.verso.as_ref().and_then(Verso::restart) {
+        restart.perform();
+    }
+
+    if app
+        .verso
+        .as_ref()
+        .and_then(Verso::soak_report)
+        .is_some_and(|report| !report.passed())
+    {
+        std::process::exit(1);
+    }
+
     Ok(())
 }
 
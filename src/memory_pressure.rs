@@ -223,13 +223,13 @@ mod tests {
     #[test]
     fn test_cache_reduction_factors() {
         let mut monitor = MemoryPressureMonitor::default();
-        
+
         monitor.current_level = MemoryPressureLevel::Normal;
         assert_eq!(monitor.cache_reduction_factor(), 1.0);
-        
+
         monitor.current_level = MemoryPressureLevel::Warning;
         assert_eq!(monitor.cache_reduction_factor(), 0.5);
-        
+
         monitor.current_level = MemoryPressureLevel::Critical;
         assert_eq!(monitor.cache_reduction_factor(), 0.25);
     }
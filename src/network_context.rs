@@ -0,0 +1,246 @@
+//! Network Contexts
+//!
+//! Routes the traffic of a webview through a named network context with its own
+//! proxy and DNS resolver, so webviews side by side can exit through different
+//! tunnels, like container tabs.
+//!
+//! Servo has a single network stack, so routed requests are taken over in the
+//! web resource interception path and loaded by the context's HTTP client,
+//! which never follows redirects so Servo sees and routes every hop. The
+//! interception path doesn't carry request bodies, so requests with a body
+//! (form posts, `fetch` uploads) can't be routed and are cancelled rather than
+//! leaked through the default route. WebSockets don't go through interception
+//! and still use the default route. Cookies stay in Servo's shared cookie jar.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use base::id::WebViewId;
+use embedder_traits::{WebResourceRequest, WebResourceResponse, WebResourceResponseMsg};
+use hickory_resolver::{
+    TokioAsyncResolver,
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+};
+use ipc_channel::ipc::IpcSender;
+use reqwest::{
+    Client,
+    dns::{Name, Resolve, Resolving},
+};
+use versoview_messages::NetworkContext;
+
+/// Methods whose requests carry no body, the only ones that can be routed
+const ROUTABLE_METHODS: [http::Method; 3] =
+    [http::Method::GET, http::Method::HEAD, http::Method::OPTIONS];
+
+/// Resolves host names with the DNS servers of a context
+struct DnsResolver(Arc<TokioAsyncResolver>);
+
+impl DnsResolver {
+    fn new(servers: &[IpAddr]) -> Self {
+        let servers = NameServerConfigGroup::from_ips_clear(servers, 53, true);
+        Self(Arc::new(TokioAsyncResolver::tokio(
+            ResolverConfig::from_parts(None, Vec::new(), servers),
+            ResolverOpts::default(),
+        )))
+    }
+}
+
+impl Resolve for DnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            let addresses: Vec<_> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            Ok(Box::new(addresses.into_iter()) as Box<dyn Iterator<Item = SocketAddr> + Send>)
+        })
+    }
+}
+
+/// Create the HTTP client of a context
+pub fn build_client(context: &NetworkContext) -> Result<Client, String> {
+    let mut builder = Client::builder().redirect(reqwest::redirect::Policy::none());
+    if let Some(proxy) = &context.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|error| format!("invalid proxy {proxy}: {error}"))?;
+        builder = builder.proxy(proxy);
+    } else {
+        builder = builder.no_proxy();
+    }
+    if !context.dns_servers.is_empty() {
+        builder = builder.dns_resolver(Arc::new(DnsResolver::new(&context.dns_servers)));
+    }
+    builder
+        .build()
+        .map_err(|error| format!("failed to create the HTTP client: {error}"))
+}
+
+/// Network contexts of the webviews of a window
+#[derive(Clone, Debug, Default)]
+pub struct NetworkContextStore {
+    clients: Arc<HashMap<String, Client>>,
+    default: Option<String>,
+    assignments: HashMap<WebViewId, Option<String>>,
+}
+
+impl NetworkContextStore {
+    /// Create a store with a client per context, webviews use `default` until assigned
+    pub fn new(contexts: &HashMap<String, NetworkContext>, default: Option<String>) -> Self {
+        let clients = contexts
+            .iter()
+            .filter_map(|(name, context)| match build_client(context) {
+                Ok(client) => Some((name.clone(), client)),
+                Err(error) => {
+                    log::error!("Network context '{name}' disabled: {error}");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            clients: Arc::new(clients),
+            default,
+            assignments: HashMap::new(),
+        }
+    }
+
+    /// Route a webview through a context, or the default route with `None`
+    pub fn assign(&mut self, webview_id: WebViewId, context: Option<String>) -> Result<(), String> {
+        if let Some(name) = context
+            .as_ref()
+            .filter(|name| !self.clients.contains_key(*name))
+        {
+            return Err(format!("unknown network context '{name}'"));
+        }
+        self.assignments.insert(webview_id, context);
+        Ok(())
+    }
+
+    /// Forget a closed webview
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.assignments.remove(&webview_id);
+    }
+
+    /// Name of the context a webview is routed through
+    pub fn context_of(&self, webview_id: WebViewId) -> Option<&str> {
+        self.assignments
+            .get(&webview_id)
+            .unwrap_or(&self.default)
+            .as_deref()
+    }
+
+    /// Client of the context a webview is routed through, `None` for the default route
+    pub fn client_for(&self, webview_id: WebViewId) -> Option<(&str, Client)> {
+        let name = self.context_of(webview_id)?;
+        let client = self.clients.get(name)?;
+        Some((name, client.clone()))
+    }
+}
+
+/// Whether a request can be loaded by a context's client
+pub fn is_routable(request: &WebResourceRequest) -> bool {
+    matches!(request.url.scheme(), "http" | "https") && ROUTABLE_METHODS.contains(&request.method)
+}
+
+/// Load a request with a context's client and hand the response to Servo,
+/// with `content_security_policy` added to the page's own policies
+pub async fn fetch_routed(
+    client: Client,
+    request: WebResourceRequest,
+    content_security_policy: Option<http::HeaderValue>,
+    sender: IpcSender<WebResourceResponseMsg>,
+) {
+    let mut headers = request.headers;
+    // The body is handed to Servo as is, so it must not be content-encoded.
+    headers.remove(http::header::ACCEPT_ENCODING);
+    let url = request.url;
+    let mut response = match client
+        .request(request.method, url.as_str())
+        .headers(headers)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            log::warn!("Verso failed to load {url} through its network context: {error}");
+            let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+            return;
+        }
+    };
+    let mut response_headers = response.headers().clone();
+    if let Some(policy) = content_security_policy {
+        // Every policy a document is served with is enforced, so the page can't relax this one.
+        response_headers.append(http::header::CONTENT_SECURITY_POLICY, policy);
+    }
+    if sender
+        .send(WebResourceResponseMsg::Start(
+            WebResourceResponse::new(url.clone())
+                .headers(response_headers)
+                .status_code(response.status()),
+        ))
+        .is_err()
+    {
+        return;
+    }
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                if sender
+                    .send(WebResourceResponseMsg::SendBodyData(chunk.to_vec()))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Ok(None) => {
+                let _ = sender.send(WebResourceResponseMsg::FinishLoad);
+                return;
+            }
+            Err(error) => {
+                log::warn!("Verso failed to read {url} through its network context: {error}");
+                let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    use super::*;
+
+    #[test]
+    fn test_build_client() {
+        assert!(
+            build_client(&NetworkContext {
+                proxy: Some("socks5h://127.0.0.1:9050".to_string()),
+                dns_servers: Vec::new(),
+            })
+            .is_ok()
+        );
+        assert!(
+            build_client(&NetworkContext {
+                proxy: Some("not a proxy".to_string()),
+                dns_servers: Vec::new(),
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_unknown_context() {
+        let contexts = HashMap::from([("work".to_string(), NetworkContext::default())]);
+        let mut store = NetworkContextStore::new(&contexts, None);
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        assert!(store.client_for(webview_id).is_none());
+        assert!(store.assign(webview_id, Some("home".to_string())).is_err());
+        store.assign(webview_id, Some("work".to_string())).unwrap();
+        assert_eq!(store.context_of(webview_id), Some("work"));
+        store.remove(webview_id);
+        assert_eq!(store.context_of(webview_id), None);
+    }
+}
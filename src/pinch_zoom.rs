@@ -0,0 +1,155 @@
+//! Pinch Zoom
+//!
+//! Magnifies a webview without laying its page out again, for touch screens
+//! and touchpads. Every webview has its own magnification, clamped between the
+//! limits of the configuration, and the compositor scales the webview around
+//! the center of its rect, clipped to the rect. Resetting animates the
+//! magnification back to 1 over [`RESET_DURATION`].
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use base::id::WebViewId;
+
+/// How long a reset takes to animate back to 1
+pub const RESET_DURATION: Duration = Duration::from_millis(250);
+
+/// A reset animating back to 1
+#[derive(Debug)]
+struct Reset {
+    from: f32,
+    start: Instant,
+}
+
+/// Pinch zoom of the webviews
+#[derive(Debug)]
+pub struct PinchZoom {
+    min: f32,
+    max: f32,
+    zooms: HashMap<WebViewId, f32>,
+    resets: HashMap<WebViewId, Reset>,
+}
+
+impl PinchZoom {
+    /// Clamp magnifications between `min` and `max`, a `min` that isn't positive is 1
+    pub fn new(min: f32, max: f32) -> Self {
+        let min = if min.is_finite() && min > 0.0 {
+            min
+        } else {
+            1.0
+        };
+        Self {
+            min,
+            max: if max.is_nan() { min } else { max.max(min) },
+            zooms: HashMap::new(),
+            resets: HashMap::new(),
+        }
+    }
+
+    /// Magnification of the webview, 1 unless it was pinched
+    pub fn zoom(&self, webview_id: WebViewId) -> f32 {
+        self.zooms.get(&webview_id).copied().unwrap_or(1.0)
+    }
+
+    /// Multiply the magnification of the webview, stopping a reset, and return the clamped result
+    pub fn magnify(&mut self, webview_id: WebViewId, magnification: f32) -> f32 {
+        self.resets.remove(&webview_id);
+        let zoom = self.zoom(webview_id) * magnification;
+        let zoom = if zoom.is_nan() {
+            self.zoom(webview_id)
+        } else {
+            zoom.clamp(self.min, self.max)
+        };
+        self.set(webview_id, zoom);
+        zoom
+    }
+
+    /// Start animating the magnification of the webview back to 1, `false` if it is already 1
+    pub fn reset(&mut self, webview_id: WebViewId, now: Instant) -> bool {
+        let from = self.zoom(webview_id);
+        if from == 1.0 {
+            return false;
+        }
+        self.resets.insert(webview_id, Reset { from, start: now });
+        true
+    }
+
+    /// Advance the resets, `true` if a magnification changed
+    pub fn tick(&mut self, now: Instant) -> bool {
+        if self.resets.is_empty() {
+            return false;
+        }
+        let mut finished = Vec::new();
+        for (webview_id, reset) in &self.resets {
+            let progress = (now.saturating_duration_since(reset.start).as_secs_f32()
+                / RESET_DURATION.as_secs_f32())
+            .min(1.0);
+            // Ease out, fast at first and settling on 1.
+            let eased = 1.0 - (1.0 - progress).powi(3);
+            let zoom = reset.from + (1.0 - reset.from) * eased;
+            if zoom == 1.0 {
+                self.zooms.remove(webview_id);
+            } else {
+                self.zooms.insert(*webview_id, zoom);
+            }
+            if progress >= 1.0 {
+                finished.push(*webview_id);
+            }
+        }
+        for webview_id in finished {
+            self.resets.remove(&webview_id);
+        }
+        true
+    }
+
+    /// Whether a reset is animating
+    pub fn animating(&self) -> bool {
+        !self.resets.is_empty()
+    }
+
+    /// Forget the webview, call when it is closed
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.zooms.remove(&webview_id);
+        self.resets.remove(&webview_id);
+    }
+
+    fn set(&mut self, webview_id: WebViewId, zoom: f32) {
+        if zoom == 1.0 {
+            self.zooms.remove(&webview_id);
+        } else {
+            self.zooms.insert(webview_id, zoom);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    use super::*;
+
+    #[test]
+    fn test_pinch_zoom() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let mut pinch_zoom = PinchZoom::new(1.0, 4.0);
+
+        assert_eq!(pinch_zoom.magnify(webview_id, 0.5), 1.0);
+        assert_eq!(pinch_zoom.magnify(webview_id, 3.0), 3.0);
+        assert_eq!(pinch_zoom.magnify(webview_id, 2.0), 4.0);
+
+        let start = Instant::now();
+        assert!(pinch_zoom.reset(webview_id, start));
+        assert!(pinch_zoom.tick(start + RESET_DURATION / 2));
+        let halfway = pinch_zoom.zoom(webview_id);
+        assert!(halfway > 1.0 && halfway < 2.0, "{halfway}");
+        assert!(pinch_zoom.animating());
+
+        assert!(pinch_zoom.tick(start + RESET_DURATION));
+        assert_eq!(pinch_zoom.zoom(webview_id), 1.0);
+        assert!(!pinch_zoom.animating());
+        assert!(!pinch_zoom.reset(webview_id, start));
+    }
+}
@@ -0,0 +1,665 @@
+//! Kiosk Playlist
+//!
+//! Rotates a signage window through the pages of a [`Playlist`]. The next page
+//! is loaded in a hidden webview while the current one is shown, and swapped
+//! in once the current page's time is up and the next one finished loading,
+//! either at once or with a compositor cross-fade. Pages that don't load within
+//! the load timeout, or whose webview crashes, are skipped; if every page
+//! failed in a row, the playlist waits for a page's duration before trying
+//! again. Outside the daily schedules the idle page is shown instead.
+//!
+//! Schedules are in the local time of the system and checked every half
+//! minute. A page that fails after loading, like a server error page, isn't
+//! detected since Servo reports it as loaded. Playlist webviews are tabs of the
+//! window without entries in the tab bar, and aren't exported to sessions.
+
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use base::id::WebViewId;
+use chrono::{Datelike, Timelike};
+use url::Url;
+use versoview_messages::{
+    DailySchedule, Playlist, PlaylistCommand, PlaylistStatus, PlaylistTransition,
+};
+
+/// How often schedules are checked
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Time between two frames of a cross-fade
+const FADE_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Local time of the week schedules are checked against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    /// Day of the week, 0 for Monday to 6 for Sunday
+    pub weekday: u8,
+    /// Minutes since midnight
+    pub minute: u16,
+}
+
+impl LocalTime {
+    /// Local time of the system now
+    pub fn now() -> Self {
+        let now = chrono::Local::now();
+        Self {
+            weekday: now.weekday().num_days_from_monday() as u8,
+            minute: (now.hour() * 60 + now.minute()) as u16,
+        }
+    }
+}
+
+/// What the window has to do for its playlist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaylistAction {
+    /// Open a hidden webview loading the URL, and register it with [`PlaylistPlayer::preloading`]
+    Preload(Url),
+    /// Show the webview, fading it in over the previous one if a duration is given
+    Show(WebViewId, Option<Duration>),
+    /// Close a webview the playlist is done with
+    Close(WebViewId),
+}
+
+/// A daily schedule, in minutes since midnight
+#[derive(Debug)]
+struct Schedule {
+    days: Vec<u8>,
+    start: u16,
+    end: u16,
+}
+
+impl Schedule {
+    fn parse(schedule: &DailySchedule) -> Option<Self> {
+        Some(Self {
+            days: schedule.days.clone(),
+            start: parse_time(&schedule.start)?,
+            end: parse_time(&schedule.end)?,
+        })
+    }
+
+    fn contains(&self, time: LocalTime) -> bool {
+        let day = self.days.is_empty() || self.days.contains(&time.weekday);
+        let within = if self.start <= self.end {
+            self.start <= time.minute && time.minute < self.end
+        } else {
+            time.minute >= self.start || time.minute < self.end
+        };
+        day && within
+    }
+}
+
+/// Parse a `HH:MM` time to minutes since midnight
+fn parse_time(time: &str) -> Option<u16> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let hours: u16 = hours.parse().ok()?;
+    let minutes: u16 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// A page of the playlist, or the idle page
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Slot {
+    Item(usize),
+    Idle,
+}
+
+/// The page shown
+#[derive(Debug)]
+struct Shown {
+    slot: Slot,
+    webview_id: WebViewId,
+    since: Instant,
+}
+
+/// The page loading in a hidden webview
+#[derive(Debug)]
+struct Preload {
+    slot: Slot,
+    webview_id: Option<WebViewId>,
+    started: Instant,
+    loaded: bool,
+    failed: bool,
+}
+
+/// A cross-fade in progress
+#[derive(Debug)]
+struct Fade {
+    from: WebViewId,
+    to: WebViewId,
+    start: Instant,
+    duration: Duration,
+}
+
+/// Plays a playlist in a window
+#[derive(Debug)]
+pub struct PlaylistPlayer {
+    playlist: Playlist,
+    schedules: Vec<Schedule>,
+    shown: Option<Shown>,
+    preload: Option<Preload>,
+    fade: Option<Fade>,
+    /// Item shown after the current one
+    next: usize,
+    paused: bool,
+    /// Switch as soon as the next page is loaded
+    skip: bool,
+    /// Items that failed in a row
+    failures: usize,
+    retry_at: Option<Instant>,
+    /// Webviews closed by the playlist, their window stays open
+    retired: HashSet<WebViewId>,
+    skipped: u64,
+    scheduled: bool,
+}
+
+impl PlaylistPlayer {
+    /// Create a player starting with the first page of `playlist`
+    pub fn new(playlist: Playlist) -> Self {
+        let mut player = Self {
+            playlist: Playlist::default(),
+            schedules: Vec::new(),
+            shown: None,
+            preload: None,
+            fade: None,
+            next: 0,
+            paused: false,
+            skip: false,
+            failures: 0,
+            retry_at: None,
+            retired: HashSet::new(),
+            skipped: 0,
+            scheduled: true,
+        };
+        player.set_playlist(playlist);
+        player
+    }
+
+    fn set_playlist(&mut self, playlist: Playlist) {
+        self.schedules = playlist
+            .schedules
+            .iter()
+            .filter_map(|schedule| {
+                let parsed = Schedule::parse(schedule);
+                if parsed.is_none() {
+                    log::error!("Ignoring playlist schedule with invalid times: {schedule:?}");
+                }
+                parsed
+            })
+            .collect();
+        self.playlist = playlist;
+        self.next = 0;
+        self.failures = 0;
+        self.retry_at = None;
+        self.skip = true;
+    }
+
+    /// Apply a command from the controller
+    pub fn command(&mut self, command: PlaylistCommand) -> Vec<PlaylistAction> {
+        let mut actions = Vec::new();
+        match command {
+            PlaylistCommand::Set(playlist) => {
+                self.cancel_preload(&mut actions);
+                self.set_playlist(playlist);
+            }
+            PlaylistCommand::Next => self.skip = true,
+            PlaylistCommand::Previous => {
+                let len = self.playlist.items.len();
+                let shown = self.shown.as_ref().map(|shown| shown.slot);
+                if let Some(Slot::Item(index)) = shown.filter(|_| len > 0) {
+                    self.cancel_preload(&mut actions);
+                    self.next = (index + len - 1) % len;
+                    self.retry_at = None;
+                    self.skip = true;
+                }
+            }
+            PlaylistCommand::Pause => self.paused = true,
+            PlaylistCommand::Resume => {
+                self.paused = false;
+                if let Some(shown) = self.shown.as_mut() {
+                    shown.since = Instant::now();
+                }
+            }
+        }
+        actions
+    }
+
+    /// Register the webview opened for the last [`PlaylistAction::Preload`]
+    pub fn preloading(&mut self, webview_id: WebViewId) {
+        if let Some(preload) = self.preload.as_mut() {
+            preload.webview_id = Some(webview_id);
+        }
+    }
+
+    /// Record that a webview finished loading
+    pub fn loaded(&mut self, webview_id: WebViewId) {
+        if let Some(preload) = self
+            .preload
+            .as_mut()
+            .filter(|preload| preload.webview_id == Some(webview_id))
+        {
+            preload.loaded = true;
+        }
+    }
+
+    /// Record that a webview crashed, it is skipped
+    pub fn failed(&mut self, webview_id: WebViewId) {
+        if let Some(preload) = self
+            .preload
+            .as_mut()
+            .filter(|preload| preload.webview_id == Some(webview_id))
+        {
+            preload.failed = true;
+        } else if self
+            .shown
+            .as_ref()
+            .is_some_and(|shown| shown.webview_id == webview_id)
+        {
+            self.skip = true;
+        }
+    }
+
+    /// Whether the webview is the hidden one the next page loads in
+    pub fn is_preloading(&self, webview_id: WebViewId) -> bool {
+        self.preload
+            .as_ref()
+            .is_some_and(|preload| preload.webview_id == Some(webview_id))
+    }
+
+    /// Whether the webview belongs to the playlist
+    pub fn owns(&self, webview_id: WebViewId) -> bool {
+        self.is_preloading(webview_id)
+            || self
+                .shown
+                .as_ref()
+                .is_some_and(|shown| shown.webview_id == webview_id)
+            || self
+                .fade
+                .as_ref()
+                .is_some_and(|fade| fade.from == webview_id)
+            || self.retired.contains(&webview_id)
+    }
+
+    /// Whether the playlist closed the webview, forgetting it
+    pub fn take_retired(&mut self, webview_id: WebViewId) -> bool {
+        self.retired.remove(&webview_id)
+    }
+
+    /// Webview fading out under the shown one
+    pub fn fading_out(&self) -> Option<WebViewId> {
+        self.fade.as_ref().map(|fade| fade.from)
+    }
+
+    /// Opacity to paint the webview with
+    pub fn opacity(&self, webview_id: WebViewId, now: Instant) -> f32 {
+        match &self.fade {
+            Some(fade) if fade.to == webview_id => {
+                (now.saturating_duration_since(fade.start).as_secs_f32()
+                    / fade.duration.as_secs_f32())
+                .min(1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// When the player needs to be polled next
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.fade.is_some() {
+            return Some(Instant::now() + FADE_FRAME_INTERVAL);
+        }
+        let page_end = self
+            .shown
+            .as_ref()
+            .filter(|_| !self.paused)
+            .and_then(|shown| match shown.slot {
+                Slot::Item(index) => Some(shown.since + self.duration(index)),
+                Slot::Idle => None,
+            });
+        let load_timeout = self
+            .preload
+            .as_ref()
+            .filter(|preload| !preload.loaded)
+            .map(|preload| preload.started + self.load_timeout());
+        let schedule_check =
+            (!self.schedules.is_empty()).then(|| Instant::now() + SCHEDULE_CHECK_INTERVAL);
+        [page_end, load_timeout, self.retry_at, schedule_check]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// What the playlist shows
+    pub fn status(&self) -> PlaylistStatus {
+        let slot = self.shown.as_ref().map(|shown| shown.slot);
+        PlaylistStatus {
+            current: match slot {
+                Some(Slot::Item(index)) => Some(index),
+                _ => None,
+            },
+            url: slot.and_then(|slot| self.url(slot)),
+            paused: self.paused,
+            scheduled: self.scheduled,
+            skipped: self.skipped,
+        }
+    }
+
+    /// Advance the playlist, returns what the window has to do
+    pub fn poll(&mut self, now: Instant, time: LocalTime) -> Vec<PlaylistAction> {
+        let mut actions = Vec::new();
+
+        if let Some(fade) = self.fade.take_if(|fade| now >= fade.start + fade.duration) {
+            self.retire(fade.from, &mut actions);
+        }
+
+        self.scheduled = self.schedules.is_empty()
+            || self
+                .schedules
+                .iter()
+                .any(|schedule| schedule.contains(time));
+        let target = self.target();
+        if self
+            .preload
+            .as_ref()
+            .is_some_and(|preload| preload.slot != target)
+        {
+            self.cancel_preload(&mut actions);
+        }
+
+        let load_timeout = self.load_timeout();
+        if let Some(preload) = self.preload.take_if(|preload| {
+            preload.failed || (!preload.loaded && now >= preload.started + load_timeout)
+        }) {
+            self.preload_failed(preload, now, &mut actions);
+        }
+
+        let target = self.target();
+        if self.preload.is_none()
+            && self.retry_at.is_none_or(|retry_at| now >= retry_at)
+            && self.needs(target)
+        {
+            self.retry_at = None;
+            if let Some(url) = self.url(target) {
+                actions.push(PlaylistAction::Preload(url));
+                self.preload = Some(Preload {
+                    slot: target,
+                    webview_id: None,
+                    started: now,
+                    loaded: false,
+                    failed: false,
+                });
+            }
+        }
+
+        if self.fade.is_none()
+            && self
+                .preload
+                .as_ref()
+                .is_some_and(|preload| preload.loaded && preload.webview_id.is_some())
+            && self.switch_due(target, now)
+        {
+            let preload = self.preload.take().unwrap();
+            self.show(preload, now, &mut actions);
+        }
+
+        actions
+    }
+
+    /// Page that should be shown next
+    fn target(&self) -> Slot {
+        if self.scheduled && !self.playlist.items.is_empty() {
+            Slot::Item(self.next.min(self.playlist.items.len() - 1))
+        } else {
+            Slot::Idle
+        }
+    }
+
+    /// Whether `target` has to be loaded
+    fn needs(&self, target: Slot) -> bool {
+        match (self.shown.as_ref().map(|shown| shown.slot), target) {
+            (None, _) => true,
+            (Some(Slot::Idle), Slot::Idle) => self.skip,
+            // A single page stays shown, unless it crashed or is replaced.
+            (Some(Slot::Item(shown)), Slot::Item(target)) if shown == target => self.skip,
+            _ => true,
+        }
+    }
+
+    /// Whether the shown page has to make way for the loaded one
+    fn switch_due(&self, target: Slot, now: Instant) -> bool {
+        match &self.shown {
+            Some(Shown {
+                slot: Slot::Item(index),
+                since,
+                ..
+            }) if !self.skip && target != Slot::Idle => {
+                !self.paused && now >= *since + self.duration(*index)
+            }
+            _ => true,
+        }
+    }
+
+    fn show(&mut self, preload: Preload, now: Instant, actions: &mut Vec<PlaylistAction>) {
+        let webview_id = preload.webview_id.unwrap();
+        let transition = match preload.slot {
+            Slot::Item(index) => {
+                self.next = (index + 1) % self.playlist.items.len();
+                self.failures = 0;
+                self.playlist.items[index].transition
+            }
+            Slot::Idle => PlaylistTransition::Cut,
+        };
+        let fade = match transition {
+            PlaylistTransition::CrossFade { duration_ms } if duration_ms > 0 => {
+                Some(Duration::from_millis(duration_ms))
+            }
+            _ => None,
+        };
+        actions.push(PlaylistAction::Show(webview_id, fade));
+        if let Some(previous) = self.shown.take() {
+            match fade {
+                Some(duration) => {
+                    self.fade = Some(Fade {
+                        from: previous.webview_id,
+                        to: webview_id,
+                        start: now,
+                        duration,
+                    })
+                }
+                None => self.retire(previous.webview_id, actions),
+            }
+        }
+        self.shown = Some(Shown {
+            slot: preload.slot,
+            webview_id,
+            since: now,
+        });
+        self.skip = false;
+    }
+
+    fn preload_failed(
+        &mut self,
+        preload: Preload,
+        now: Instant,
+        actions: &mut Vec<PlaylistAction>,
+    ) {
+        let url = self.url(preload.slot);
+        log::warn!("Playlist skips {url:?}, it failed to load");
+        if let Some(webview_id) = preload.webview_id {
+            self.retire(webview_id, actions);
+        }
+        self.skipped += 1;
+        match preload.slot {
+            Slot::Item(index) => {
+                let len = self.playlist.items.len();
+                self.next = (index + 1) % len;
+                self.failures += 1;
+                if self.failures >= len {
+                    log::warn!("Every page of the playlist failed, retrying later");
+                    self.failures = 0;
+                    self.retry_at = Some(now + self.duration(index));
+                }
+            }
+            Slot::Idle => self.retry_at = Some(now + SCHEDULE_CHECK_INTERVAL),
+        }
+    }
+
+    fn cancel_preload(&mut self, actions: &mut Vec<PlaylistAction>) {
+        if let Some(webview_id) = self.preload.take().and_then(|preload| preload.webview_id) {
+            self.retire(webview_id, actions);
+        }
+    }
+
+    fn retire(&mut self, webview_id: WebViewId, actions: &mut Vec<PlaylistAction>) {
+        self.retired.insert(webview_id);
+        actions.push(PlaylistAction::Close(webview_id));
+    }
+
+    fn url(&self, slot: Slot) -> Option<Url> {
+        match slot {
+            Slot::Item(index) => self.playlist.items.get(index).map(|item| item.url.clone()),
+            Slot::Idle => Some(
+                self.playlist
+                    .idle_url
+                    .clone()
+                    .unwrap_or_else(|| Url::parse("about:blank").unwrap()),
+            ),
+        }
+    }
+
+    fn duration(&self, index: usize) -> Duration {
+        let seconds = self
+            .playlist
+            .items
+            .get(index)
+            .map_or(0, |item| item.duration_secs);
+        Duration::from_secs(seconds.max(1))
+    }
+
+    fn load_timeout(&self) -> Duration {
+        Duration::from_secs(self.playlist.load_timeout_secs.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+    use versoview_messages::PlaylistItem;
+
+    const NOON: LocalTime = LocalTime {
+        weekday: 0,
+        minute: 12 * 60,
+    };
+
+    fn item(url: &str, duration_secs: u64, transition: PlaylistTransition) -> PlaylistItem {
+        PlaylistItem {
+            url: Url::parse(url).unwrap(),
+            duration_secs,
+            transition,
+        }
+    }
+
+    /// Poll and open a webview for the preload requested, if any
+    fn poll(player: &mut PlaylistPlayer, now: Instant) -> (Vec<PlaylistAction>, Option<WebViewId>) {
+        let actions = player.poll(now, NOON);
+        let preloaded = actions
+            .iter()
+            .any(|action| matches!(action, PlaylistAction::Preload(_)))
+            .then(|| {
+                let webview_id = WebViewId::new();
+                player.preloading(webview_id);
+                webview_id
+            });
+        (actions, preloaded)
+    }
+
+    #[test]
+    fn test_rotation_with_cross_fade() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let fade = PlaylistTransition::CrossFade { duration_ms: 500 };
+        let mut player = PlaylistPlayer::new(Playlist {
+            items: vec![
+                item("https://a.example/", 10, PlaylistTransition::Cut),
+                item("https://b.example/", 10, fade),
+            ],
+            ..Default::default()
+        });
+        let start = Instant::now();
+
+        let (_, a) = poll(&mut player, start);
+        let a = a.unwrap();
+        player.loaded(a);
+        let (actions, b) = poll(&mut player, start);
+        assert_eq!(actions, vec![PlaylistAction::Show(a, None)]);
+        // Shown right away, b preloads on the next poll.
+        let (_, b2) = poll(&mut player, start);
+        let b = b.or(b2).unwrap();
+        player.loaded(b);
+        assert!(
+            poll(&mut player, start + Duration::from_secs(5))
+                .0
+                .is_empty()
+        );
+
+        let switch = start + Duration::from_secs(10);
+        let (actions, _) = poll(&mut player, switch);
+        assert_eq!(
+            actions[0],
+            PlaylistAction::Show(b, Some(Duration::from_millis(500)))
+        );
+        assert_eq!(player.fading_out(), Some(a));
+        assert_eq!(player.opacity(b, switch + Duration::from_millis(250)), 0.5);
+
+        let (actions, _) = poll(&mut player, switch + Duration::from_secs(1));
+        assert!(actions.contains(&PlaylistAction::Close(a)));
+        assert!(player.take_retired(a));
+        assert_eq!(player.status().current, Some(1));
+    }
+
+    #[test]
+    fn test_failures_are_skipped_and_schedule() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let mut player = PlaylistPlayer::new(Playlist {
+            items: vec![
+                item("https://broken.example/", 10, PlaylistTransition::Cut),
+                item("https://ok.example/", 10, PlaylistTransition::Cut),
+            ],
+            schedules: vec![DailySchedule {
+                days: vec![],
+                start: "08:00".to_string(),
+                end: "20:00".to_string(),
+            }],
+            load_timeout_secs: 5,
+            ..Default::default()
+        });
+        let start = Instant::now();
+
+        let (_, broken) = poll(&mut player, start);
+        let broken = broken.unwrap();
+        // The broken page times out and the next one is loaded instead.
+        let (actions, ok) = poll(&mut player, start + Duration::from_secs(6));
+        assert!(actions.contains(&PlaylistAction::Close(broken)));
+        assert!(actions.contains(&PlaylistAction::Preload(
+            Url::parse("https://ok.example/").unwrap()
+        )));
+        player.loaded(ok.unwrap());
+        poll(&mut player, start + Duration::from_secs(6));
+        assert_eq!(player.status().current, Some(1));
+        assert_eq!(player.status().skipped, 1);
+
+        let night = LocalTime {
+            weekday: 0,
+            minute: 22 * 60,
+        };
+        let actions = player.poll(start + Duration::from_secs(7), night);
+        assert!(actions.contains(&PlaylistAction::Preload(Url::parse("about:blank").unwrap())));
+        assert!(!player.status().scheduled);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("08:30"), Some(510));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("noon"), None);
+    }
+}
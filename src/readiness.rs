@@ -0,0 +1,342 @@
+//! Page Readiness
+//!
+//! Tells the embedder when a webview's page is really done, for screenshots,
+//! tests and kiosks that shouldn't show a page before it settled. A page is
+//! stable once every condition of its [`StabilityOptions`] holds at the same
+//! probe:
+//!
+//! - Network idle: the document finished loading and no resource finished
+//!   loading for [`StabilityOptions::network_idle_ms`]. Servo doesn't tell the
+//!   embedder when a request finishes, so this is read from the page's resource
+//!   timings, and a request still waiting for its response isn't seen.
+//! - Fonts loaded: `document.fonts` reports every font face loaded.
+//! - No pending frames: WebRender has no frame pending, no animation or
+//!   animation frame callback runs, and the constellation confirmed every
+//!   pipeline painted its latest epoch, the check the `-x` flag waits for before
+//!   exiting.
+//! - Identical composites: the last composites of the window rendered the same
+//!   pixels. While a page is waited on, the compositor composites the window at
+//!   every probe and hashes each frame, so the whole window is compared, not
+//!   just the webview.
+//! - First contentful paint: the page painted text or an image, from its
+//!   `first-contentful-paint` paint timing.
+//!
+//! Besides the controller's `WaitUntilStable` message, a wait starts when a tab
+//! finishes loading if the `page_settled` option of the config is set,
+//! and the `-x` flag waits on the first page loaded before exiting.
+//!
+//! The page is probed every [`PROBE_INTERVAL`], and gives up with the conditions
+//! that didn't hold after [`StabilityOptions::timeout_ms`].
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use base::id::WebViewId;
+use constellation_traits::EmbedderToConstellationMessage;
+use crossbeam_channel::Sender;
+use embedder_traits::WebDriverJSValue;
+use serde::Deserialize;
+use versoview_messages::{StabilityOptions, StabilityReport};
+
+use crate::webview::execute_script_with_timeout;
+
+/// Time between two probes of a page waited on
+pub const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longest time a probe waits for the page to answer
+const PROBE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Returns the load, network, font and paint state of the page as JSON
+const PAGE_STATE_SCRIPT: &str = r#"
+(() => {
+    let last = 0;
+    for (const entry of performance.getEntriesByType("resource")) {
+        last = Math.max(last, entry.responseEnd);
+    }
+    const navigation = performance.getEntriesByType("navigation")[0];
+    if (navigation) {
+        last = Math.max(last, navigation.loadEventEnd || navigation.responseEnd);
+    }
+    return JSON.stringify({
+        loaded: document.readyState === "complete",
+        idle_ms: performance.now() - last,
+        fonts_loaded: !document.fonts || document.fonts.status === "loaded",
+        contentful_paint: performance.getEntriesByName("first-contentful-paint").length > 0,
+    });
+})()
+"#;
+
+/// Load, network, font and paint state of a page
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct PageState {
+    /// Whether the document finished loading
+    pub loaded: bool,
+    /// Milliseconds since a resource last finished loading
+    pub idle_ms: f64,
+    /// Whether every font face of the document loaded
+    pub fonts_loaded: bool,
+    /// Whether the page had its first contentful paint
+    pub contentful_paint: bool,
+}
+
+/// Ask the webview's page for its state, `None` if it didn't answer in time
+pub fn page_state(
+    sender: &Sender<EmbedderToConstellationMessage>,
+    webview_id: WebViewId,
+) -> Option<PageState> {
+    match execute_script_with_timeout(sender, &webview_id, PAGE_STATE_SCRIPT, PROBE_TIMEOUT) {
+        Some(Ok(WebDriverJSValue::String(state))) => serde_json::from_str(&state)
+            .inspect_err(|error| log::warn!("Verso failed to parse the page state: {error}"))
+            .ok(),
+        _ => None,
+    }
+}
+
+/// Hashes of the composited frames, kept while a page is waited on
+#[derive(Debug, Default)]
+pub struct FrameStability {
+    /// Whether composites are hashed
+    pub tracking: bool,
+    last_hash: Option<u64>,
+    identical: u32,
+    composites: u32,
+}
+
+impl FrameStability {
+    /// Record the pixels of a composite
+    pub fn record(&mut self, pixels: &[u8]) {
+        let mut hasher = DefaultHasher::new();
+        pixels.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.last_hash == Some(hash) {
+            self.identical += 1;
+        } else {
+            self.last_hash = Some(hash);
+            self.identical = 1;
+        }
+        self.composites = self.composites.wrapping_add(1);
+    }
+
+    /// Consecutive composites with the same pixels as the last one, itself included
+    pub fn identical_composites(&self) -> u32 {
+        self.identical
+    }
+
+    /// Composites recorded so far
+    pub fn composites(&self) -> u32 {
+        self.composites
+    }
+}
+
+/// Who is told when a page waited on is stable
+#[derive(Debug, Clone, PartialEq)]
+pub enum StabilityWaiter {
+    /// The controller, answering its `WaitUntilStable` message
+    Controller(uuid::Uuid),
+    /// The controller, with an event for a page a tab loaded at this URL
+    Embedder(Option<url::Url>),
+    /// Verso itself, exiting once the page is stable as the `-x` flag asks
+    Exit,
+}
+
+/// A page waited on until it is stable
+#[derive(Debug)]
+pub struct StabilityWait {
+    /// Webview of the page
+    pub webview_id: WebViewId,
+    options: StabilityOptions,
+    started: Instant,
+    next_probe: Instant,
+    /// [`FrameStability::composites`] when the wait started
+    first_composite: u32,
+}
+
+impl StabilityWait {
+    /// Start waiting on the webview's page
+    pub fn new(
+        webview_id: WebViewId,
+        options: StabilityOptions,
+        frames: &FrameStability,
+        now: Instant,
+    ) -> Self {
+        Self {
+            webview_id,
+            options,
+            started: now,
+            next_probe: now,
+            first_composite: frames.composites(),
+        }
+    }
+
+    /// When the page is probed next, or given up on
+    pub fn next_deadline(&self) -> Instant {
+        self.next_probe
+    }
+
+    /// Whether the page should be probed
+    pub fn probe_due(&self, now: Instant) -> bool {
+        now >= self.next_probe
+    }
+
+    /// Check the conditions with the state just probed, `None` if the page is neither stable nor given up on
+    pub fn check(
+        &mut self,
+        now: Instant,
+        page: Option<PageState>,
+        frames_settled: bool,
+        frames: &FrameStability,
+    ) -> Option<Result<StabilityReport, String>> {
+        self.next_probe = now + PROBE_INTERVAL;
+        let composites = frames.composites().wrapping_sub(self.first_composite);
+        let unmet = self.unmet(page, frames_settled, frames, composites);
+        let elapsed = now.duration_since(self.started);
+        if unmet.is_empty() {
+            return Some(Ok(StabilityReport {
+                elapsed_ms: elapsed.as_millis() as u64,
+                composites,
+            }));
+        }
+        if elapsed >= Duration::from_millis(self.options.timeout_ms) {
+            return Some(Err(format!(
+                "the page wasn't stable after {} ms, waiting for {}",
+                self.options.timeout_ms,
+                unmet.join(", ")
+            )));
+        }
+        None
+    }
+
+    /// Conditions that don't hold yet
+    fn unmet(
+        &self,
+        page: Option<PageState>,
+        frames_settled: bool,
+        frames: &FrameStability,
+        composites: u32,
+    ) -> Vec<&'static str> {
+        let mut unmet = Vec::new();
+        match page {
+            None => unmet.push("the page to respond"),
+            Some(page) => {
+                if !page.loaded || page.idle_ms < self.options.network_idle_ms as f64 {
+                    unmet.push("network idle");
+                }
+                if self.options.fonts_loaded && !page.fonts_loaded {
+                    unmet.push("fonts");
+                }
+                if self.options.first_contentful_paint && !page.contentful_paint {
+                    unmet.push("first contentful paint");
+                }
+            }
+        }
+        if self.options.no_pending_frames && !frames_settled {
+            unmet.push("pending frames");
+        }
+        let wanted = self.options.identical_composites;
+        if wanted > 0 && frames.identical_composites().min(composites) < wanted {
+            unmet.push("identical composites");
+        }
+        unmet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    fn page(loaded: bool, idle_ms: f64, fonts_loaded: bool) -> Option<PageState> {
+        Some(PageState {
+            loaded,
+            idle_ms,
+            fonts_loaded,
+            contentful_paint: true,
+        })
+    }
+
+    #[test]
+    fn test_stability_wait() {
+        PipelineNamespace::install(PipelineNamespaceId(3));
+        let webview_id = WebViewId::new();
+        let start = Instant::now();
+        let mut frames = FrameStability::default();
+        frames.record(&[1, 2, 3]);
+        let mut wait = StabilityWait::new(webview_id, StabilityOptions::default(), &frames, start);
+        assert!(wait.probe_due(start));
+
+        // Composites before the wait don't count.
+        frames.record(&[1, 2, 3]);
+        assert_eq!(
+            wait.check(start, page(true, 800.0, true), true, &frames),
+            None
+        );
+        assert!(!wait.probe_due(start));
+        assert_eq!(wait.next_deadline(), start + PROBE_INTERVAL);
+
+        frames.record(&[1, 2, 3]);
+        let now = start + PROBE_INTERVAL;
+        assert_eq!(
+            wait.check(now, page(true, 100.0, true), true, &frames),
+            None
+        );
+        assert_eq!(
+            wait.check(now, page(true, 800.0, false), true, &frames),
+            None
+        );
+        assert_eq!(
+            wait.check(now, page(true, 800.0, true), false, &frames),
+            None
+        );
+        assert_eq!(
+            wait.check(now, page(true, 800.0, true), true, &frames),
+            Some(Ok(StabilityReport {
+                elapsed_ms: 100,
+                composites: 2,
+            }))
+        );
+
+        // The first contentful paint is only waited for when asked.
+        let mut wait = StabilityWait::new(
+            webview_id,
+            StabilityOptions {
+                first_contentful_paint: true,
+                identical_composites: 0,
+                ..Default::default()
+            },
+            &frames,
+            start,
+        );
+        let blank = Some(PageState {
+            contentful_paint: false,
+            ..page(true, 800.0, true).unwrap()
+        });
+        assert_eq!(wait.check(start, blank, true, &frames), None);
+        assert!(
+            wait.check(start, page(true, 800.0, true), true, &frames)
+                .is_some_and(|result| result.is_ok())
+        );
+
+        frames.record(&[4, 5, 6]);
+        let mut wait = StabilityWait::new(
+            webview_id,
+            StabilityOptions {
+                timeout_ms: 1000,
+                ..Default::default()
+            },
+            &frames,
+            start,
+        );
+        let result = wait.check(start + Duration::from_secs(1), None, false, &frames);
+        assert_eq!(
+            result,
+            Some(Err(
+                "the page wasn't stable after 1000 ms, waiting for the page to respond, \
+                      pending frames, identical composites"
+                    .to_string()
+            ))
+        );
+    }
+}
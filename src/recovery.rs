@@ -0,0 +1,444 @@
+//! Recovery Policies
+//!
+//! Keeps unattended devices like signage showing their content: crashed
+//! pipelines, webviews that stop answering a liveness probe and renderer
+//! restarts are counted per webview, and the [`RecoveryPolicy`] rules decide
+//! whether to reload the webview, recreate it or restart the whole browser.
+//! Repeated failures escalate to the stronger actions, and every action waits
+//! an exponentially growing backoff so a page that crashes on load doesn't
+//! spin. Every scheduled action is logged and reported to the controller.
+//!
+//! The probe runs a trivial script in the shown webview of each window without
+//! waiting for it, so a hung page never blocks the event loop. Webviews in
+//! background tabs are throttled and not probed. A standalone Verso restarts
+//! itself with the same arguments once it shut down, carrying the restart count
+//! over in [`RESTART_COUNT_ENV`] so the backoff keeps growing across restarts.
+//! A Verso launched by a controller can't reconnect to it and exits with
+//! [`RECOVERY_RESTART_EXIT_CODE`] for the controller to start a new one.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant, SystemTime},
+};
+
+use base::id::WebViewId;
+use constellation_traits::EmbedderToConstellationMessage;
+use crossbeam_channel::Sender;
+use embedder_traits::{WebDriverCommandMsg, WebDriverJSResult, WebDriverScriptCommand};
+use ipc_channel::ipc::{self, IpcReceiver, TryRecvError};
+use url::Url;
+use versoview_messages::{
+    RECOVERY_RESTART_EXIT_CODE, RecoveryAction, RecoveryEvent, RecoveryPolicy, RecoveryTrigger,
+};
+
+use crate::verso::send_to_constellation;
+
+/// Environment variable carrying the number of recovery restarts to the restarted process
+pub const RESTART_COUNT_ENV: &str = "VERSO_RECOVERY_RESTARTS";
+
+/// Script answering the liveness probe
+const PROBE_SCRIPT: &str = "true";
+
+/// Failures and actions of a webview within the failure window
+#[derive(Debug, Default)]
+struct FailureHistory {
+    failures: VecDeque<(Instant, RecoveryTrigger)>,
+    actions: VecDeque<Instant>,
+}
+
+impl FailureHistory {
+    fn forget_before(&mut self, cutoff: Instant) {
+        while self.failures.front().is_some_and(|(at, _)| *at < cutoff) {
+            self.failures.pop_front();
+        }
+        while self.actions.front().is_some_and(|at| *at < cutoff) {
+            self.actions.pop_front();
+        }
+    }
+
+    fn count(&self, trigger: RecoveryTrigger) -> u32 {
+        self.failures
+            .iter()
+            .filter(|(_, failure)| *failure == trigger)
+            .count() as u32
+    }
+}
+
+/// A liveness probe waiting for its answer
+#[derive(Debug)]
+struct Probe {
+    sent: Instant,
+    receiver: IpcReceiver<WebDriverJSResult>,
+}
+
+/// How Verso restarts once it shut down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Restart {
+    /// Start Verso again with the same arguments
+    Relaunch,
+    /// Exit with [`RECOVERY_RESTART_EXIT_CODE`] for the controller to start a new Verso
+    ExitForController,
+}
+
+impl Restart {
+    /// Restart or exit the process
+    pub fn perform(self) -> ! {
+        if self == Restart::Relaunch {
+            let restarts = restart_count() + 1;
+            let spawned = std::env::current_exe().and_then(|executable| {
+                std::process::Command::new(executable)
+                    .args(std::env::args_os().skip(1))
+                    .env(RESTART_COUNT_ENV, restarts.to_string())
+                    .spawn()
+            });
+            match spawned {
+                Ok(_) => {
+                    log::warn!("Verso restarted itself ({restarts} recovery restarts)");
+                    std::process::exit(0);
+                }
+                Err(error) => log::error!("Verso failed to restart itself: {error}"),
+            }
+        }
+        std::process::exit(RECOVERY_RESTART_EXIT_CODE);
+    }
+}
+
+/// Recovery restarts that happened before this process, from [`RESTART_COUNT_ENV`]
+pub fn restart_count() -> u32 {
+    std::env::var(RESTART_COUNT_ENV)
+        .ok()
+        .and_then(|restarts| restarts.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Counts the failures of the webviews of a window and schedules their recovery
+#[derive(Debug, Default)]
+pub struct RecoveryEngine {
+    policy: RecoveryPolicy,
+    /// Restarts before this process, raising the first backoffs
+    restarts: u32,
+    history: HashMap<WebViewId, FailureHistory>,
+    pending: HashMap<WebViewId, (RecoveryAction, Instant)>,
+    probes: HashMap<WebViewId, Probe>,
+    next_probe: Option<Instant>,
+    /// Webviews closed to be recreated, their window stays open
+    replaced: HashSet<WebViewId>,
+    events: Vec<RecoveryEvent>,
+}
+
+impl RecoveryEngine {
+    /// Create an engine following `policy`, `restarts` are the recovery restarts so far
+    pub fn new(policy: RecoveryPolicy, restarts: u32) -> Self {
+        Self {
+            policy,
+            restarts,
+            ..Default::default()
+        }
+    }
+
+    /// Whether webviews are watched
+    pub fn is_enabled(&self) -> bool {
+        self.policy.enabled
+    }
+
+    /// Count a failure of a webview, scheduling the action of the strongest matching rule
+    pub fn record_failure(
+        &mut self,
+        webview_id: WebViewId,
+        trigger: RecoveryTrigger,
+        url: Option<Url>,
+        now: Instant,
+    ) -> Option<RecoveryAction> {
+        if !self.policy.enabled {
+            return None;
+        }
+        let window = Duration::from_secs(self.policy.failure_window_secs);
+        let history = self.history.entry(webview_id).or_default();
+        if let Some(cutoff) = now.checked_sub(window) {
+            history.forget_before(cutoff);
+        }
+        history.failures.push_back((now, trigger));
+        let failures = history.count(trigger);
+
+        let Some(action) = self
+            .policy
+            .rules
+            .iter()
+            .filter(|rule| rule.trigger == trigger && rule.failures <= failures)
+            .max_by_key(|rule| rule.failures)
+            .map(|rule| rule.action)
+        else {
+            log::warn!("Verso webview {webview_id:?} failed with {trigger:?} ({failures} times)");
+            return None;
+        };
+
+        let (action, due) = match self.pending.get(&webview_id) {
+            // An action is already waiting out its backoff, escalate it if needed.
+            Some((pending, due)) => ((*pending).max(action), *due),
+            None => {
+                let exponent = (history.actions.len() as u32 + self.restarts).min(16);
+                let delay = Duration::from_millis(
+                    self.policy
+                        .initial_backoff_ms
+                        .saturating_mul(1 << exponent)
+                        .min(self.policy.max_backoff_ms),
+                );
+                history.actions.push_back(now);
+                (action, now + delay)
+            }
+        };
+        self.pending.insert(webview_id, (action, due));
+        self.probes.remove(&webview_id);
+
+        let event = RecoveryEvent {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            webview: format!("{webview_id:?}"),
+            url,
+            trigger,
+            failures,
+            action,
+            delay_ms: due.saturating_duration_since(now).as_millis() as u64,
+        };
+        log::warn!("Verso recovery: {event:?}");
+        self.events.push(event);
+        Some(action)
+    }
+
+    /// Take the recovery events scheduled since the last call
+    pub fn take_events(&mut self) -> Vec<RecoveryEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Take the actions whose backoff is over
+    pub fn due_actions(&mut self, now: Instant) -> Vec<(WebViewId, RecoveryAction)> {
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, due))| *due <= now)
+            .map(|(webview_id, (action, _))| (*webview_id, *action))
+            .collect();
+        for (webview_id, _) in &due {
+            self.pending.remove(webview_id);
+        }
+        due
+    }
+
+    /// Check the answers to the probes, returns the webviews that didn't answer in time
+    ///
+    /// Probes `shown` again when the probe interval is over.
+    pub fn check_probes(
+        &mut self,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        shown: impl IntoIterator<Item = WebViewId>,
+        now: Instant,
+    ) -> Vec<WebViewId> {
+        if !self.policy.enabled {
+            return Vec::new();
+        }
+        let timeout = Duration::from_millis(self.policy.probe_timeout_ms);
+        let mut unresponsive = Vec::new();
+        self.probes
+            .retain(|webview_id, probe| match probe.receiver.try_recv() {
+                Err(TryRecvError::Empty) if now.duration_since(probe.sent) < timeout => true,
+                Err(TryRecvError::Empty) => {
+                    unresponsive.push(*webview_id);
+                    false
+                }
+                // Answered, or the webview went away.
+                _ => false,
+            });
+
+        if self.next_probe.is_some_and(|next_probe| now < next_probe) {
+            return unresponsive;
+        }
+        self.next_probe = Some(now + Duration::from_millis(self.policy.probe_interval_ms));
+        for webview_id in shown {
+            if self.probes.contains_key(&webview_id)
+                || self.pending.contains_key(&webview_id)
+                || unresponsive.contains(&webview_id)
+            {
+                continue;
+            }
+            let Ok((result_sender, receiver)) = ipc::channel() else {
+                continue;
+            };
+            send_to_constellation(
+                sender,
+                EmbedderToConstellationMessage::WebDriverCommand(
+                    WebDriverCommandMsg::ScriptCommand(
+                        webview_id.0,
+                        WebDriverScriptCommand::ExecuteScript(
+                            PROBE_SCRIPT.to_string(),
+                            result_sender,
+                        ),
+                    ),
+                ),
+            );
+            self.probes.insert(
+                webview_id,
+                Probe {
+                    sent: now,
+                    receiver,
+                },
+            );
+        }
+        unresponsive
+    }
+
+    /// When the engine next needs to run, for a probe or an action
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if !self.policy.enabled {
+            return None;
+        }
+        let timeout = Duration::from_millis(self.policy.probe_timeout_ms);
+        self.pending
+            .values()
+            .map(|(_, due)| *due)
+            .chain(self.probes.values().map(|probe| probe.sent + timeout))
+            .chain(self.next_probe)
+            .min()
+    }
+
+    /// A webview was recreated as `new`, its failures carry over
+    pub fn replaced(&mut self, old: WebViewId, new: WebViewId) {
+        if let Some(history) = self.history.remove(&old) {
+            self.history.insert(new, history);
+        }
+        self.pending.remove(&old);
+        self.probes.remove(&old);
+        self.replaced.insert(old);
+    }
+
+    /// Whether a closed webview was closed to be recreated
+    pub fn take_replaced(&mut self, webview_id: WebViewId) -> bool {
+        self.replaced.remove(&webview_id)
+    }
+
+    /// Forget a closed webview
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.history.remove(&webview_id);
+        self.pending.remove(&webview_id);
+        self.probes.remove(&webview_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    use super::*;
+
+    fn engine(restarts: u32) -> RecoveryEngine {
+        RecoveryEngine::new(
+            RecoveryPolicy {
+                enabled: true,
+                ..Default::default()
+            },
+            restarts,
+        )
+    }
+
+    #[test]
+    fn test_escalation() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let mut engine = engine(0);
+        let now = Instant::now();
+
+        let mut actions = Vec::new();
+        for failure in 0..5 {
+            let at = now + Duration::from_secs(120 * failure);
+            actions.push(engine.record_failure(webview_id, RecoveryTrigger::Crash, None, at));
+            engine.due_actions(at + Duration::from_secs(60));
+        }
+        assert_eq!(
+            actions,
+            vec![
+                Some(RecoveryAction::Reload),
+                Some(RecoveryAction::Reload),
+                Some(RecoveryAction::RecreateWebView),
+                Some(RecoveryAction::RecreateWebView),
+                Some(RecoveryAction::RestartBrowser),
+            ]
+        );
+        assert_eq!(engine.take_events().len(), 5);
+
+        // Failures older than the window are forgotten.
+        let later = now + Duration::from_secs(3600);
+        assert_eq!(
+            engine.record_failure(webview_id, RecoveryTrigger::Crash, None, later),
+            Some(RecoveryAction::Reload)
+        );
+        // Renderer restarts need three failures before anything is done.
+        assert_eq!(
+            engine.record_failure(webview_id, RecoveryTrigger::RendererRestart, None, later),
+            None
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let mut engine = engine(0);
+        let now = Instant::now();
+
+        engine.record_failure(webview_id, RecoveryTrigger::Unresponsive, None, now);
+        assert!(engine.due_actions(now).is_empty());
+        assert_eq!(
+            engine.due_actions(now + Duration::from_secs(1)),
+            vec![(webview_id, RecoveryAction::Reload)]
+        );
+
+        // The second action waits twice as long, and a failure while waiting escalates it.
+        let now = now + Duration::from_secs(2);
+        engine.record_failure(webview_id, RecoveryTrigger::Crash, None, now);
+        engine.record_failure(webview_id, RecoveryTrigger::Unresponsive, None, now);
+        let events = engine.take_events();
+        assert_eq!(events[0].delay_ms, 2000);
+        assert_eq!(events[1].action, RecoveryAction::RecreateWebView);
+        assert!(engine.due_actions(now + Duration::from_secs(1)).is_empty());
+        assert_eq!(
+            engine.due_actions(now + Duration::from_secs(2)),
+            vec![(webview_id, RecoveryAction::RecreateWebView)]
+        );
+
+        // Earlier restarts raise the backoff, up to the maximum.
+        let mut engine = engine(20);
+        engine.record_failure(webview_id, RecoveryTrigger::Crash, None, now);
+        assert_eq!(engine.take_events()[0].delay_ms, 60_000);
+    }
+
+    #[test]
+    fn test_replaced() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let old = WebViewId::new();
+        let new = WebViewId::new();
+        let mut engine = engine(0);
+        let now = Instant::now();
+
+        engine.record_failure(old, RecoveryTrigger::Unresponsive, None, now);
+        engine.replaced(old, new);
+        assert!(engine.take_replaced(old));
+        assert!(!engine.take_replaced(old));
+        assert!(engine.due_actions(now + Duration::from_secs(60)).is_empty());
+        assert_eq!(
+            engine.record_failure(new, RecoveryTrigger::Unresponsive, None, now),
+            Some(RecoveryAction::RecreateWebView)
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let mut engine = RecoveryEngine::default();
+        let now = Instant::now();
+        assert_eq!(
+            engine.record_failure(WebViewId::new(), RecoveryTrigger::Crash, None, now),
+            None
+        );
+        assert!(engine.next_deadline().is_none());
+    }
+}
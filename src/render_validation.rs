@@ -0,0 +1,378 @@
+//! Render Validation
+//!
+//! Turns the software renderer into an oracle for rendering bugs reported from
+//! the field. A comparison reads back the frame WebRender just rendered on the
+//! GPU, saves a WebRender capture of that frame, replays the capture in a second
+//! WebRender instance running on swgl, WebRender's software implementation of
+//! GL, and diffs both frames pixel by pixel. Where they diverge by more than the
+//! tolerance, the GPU driver is the likely culprit.
+//!
+//! Replaying needs the `render-validation` feature, which builds swgl and
+//! WebRender's replay support, without it comparisons fail. The software
+//! renderer is created for every comparison and renders on the CPU, so a
+//! comparison takes a while and blocks the compositor: it is meant for
+//! diagnosis, not for every frame. Only the window being composited is compared.
+//! Anti-aliased edges and gradients may differ by a few levels between the
+//! renderers, which the tolerance absorbs.
+//!
+//! Divergent comparisons keep their output directory, holding the WebRender
+//! capture in `capture`, which wrench can replay too, the frames as
+//! `hardware.png` and `software.png`, and `difference.png` with the divergent
+//! pixels in red over a dimmed hardware frame. Matching comparisons delete it.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use gleam::gl;
+use versoview_messages::{DivergentRegion, RenderValidationReport, RenderValidationSettings};
+use webrender::Renderer;
+use webrender_api::{CaptureBits, RenderApi};
+
+/// How long the renderer gets to write its part of a capture
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the software renderer gets to build the replayed frame
+#[cfg(feature = "render-validation")]
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How two frames differ
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameDiff {
+    /// Pixels with a color channel differing by more than the tolerance
+    pub divergent_pixels: u64,
+    /// Largest difference of a color channel
+    pub max_difference: u8,
+    /// Smallest region holding every divergent pixel, from the top left
+    pub divergent_region: Option<DivergentRegion>,
+}
+
+/// Compares frames rendered by the GPU with the software renderer
+#[derive(Debug, Default)]
+pub struct RenderValidator {
+    settings: RenderValidationSettings,
+    due: bool,
+}
+
+impl RenderValidator {
+    /// Create a validator, it only compares frames on its own if enabled in `settings`
+    pub fn new(settings: RenderValidationSettings) -> Self {
+        Self {
+            settings,
+            due: false,
+        }
+    }
+
+    /// Note that a page finished loading, its first frame is compared if the settings ask for it
+    pub fn page_loaded(&mut self) {
+        self.due |= self.settings.validate_on_load;
+    }
+
+    /// Whether the frame being composited should be compared, only once per page load
+    pub fn take_due(&mut self) -> bool {
+        std::mem::take(&mut self.due)
+    }
+
+    /// Compare the frame WebRender just rendered into the current framebuffer with the software renderer
+    pub fn validate(
+        &self,
+        webrender: &mut Renderer,
+        webrender_api: &RenderApi,
+        gl: &dyn gl::Gl,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderValidationReport, String> {
+        if width == 0 || height == 0 {
+            return Err("the viewport is empty".to_string());
+        }
+        let hardware = read_frame(gl, width, height);
+
+        let timestamp_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let directory = self
+            .settings
+            .output_directory
+            .clone()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(format!("verso-render-validation-{timestamp_ms}"));
+        let capture = directory.join("capture");
+        std::fs::create_dir_all(&capture)
+            .map_err(|error| format!("failed to create {}: {error}", capture.display()))?;
+        let software = save_capture(webrender, webrender_api, &capture)
+            .and_then(|()| render_capture(&capture, width, height));
+        let software = match software {
+            Ok(software) => software,
+            Err(error) => {
+                let _ = std::fs::remove_dir_all(&directory);
+                return Err(error);
+            }
+        };
+
+        let diff = diff_frames(&hardware, &software, width, height, self.settings.tolerance);
+        let output_directory = if diff.divergent_pixels == 0 {
+            let _ = std::fs::remove_dir_all(&directory);
+            None
+        } else {
+            if let Err(error) = write_frames(
+                &directory,
+                width,
+                height,
+                hardware,
+                software,
+                self.settings.tolerance,
+            ) {
+                log::error!("Verso failed to write the frames of a render validation: {error}");
+            }
+            Some(directory)
+        };
+        Ok(RenderValidationReport {
+            width,
+            height,
+            hardware_renderer: gl.get_string(gl::RENDERER),
+            divergent_pixels: diff.divergent_pixels,
+            max_difference: diff.max_difference,
+            divergent_region: diff.divergent_region,
+            output_directory,
+        })
+    }
+}
+
+/// Read the RGBA pixels of the default framebuffer, rows bottom up
+pub fn read_frame(gl: &dyn gl::Gl, width: u32, height: u32) -> Vec<u8> {
+    gl.bind_framebuffer(gl::READ_FRAMEBUFFER, 0);
+    gl.read_pixels(
+        0,
+        0,
+        width as i32,
+        height as i32,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+    )
+}
+
+/// Save a capture of the current frame to `capture`, waiting for the renderer to write its part
+fn save_capture(
+    webrender: &mut Renderer,
+    webrender_api: &RenderApi,
+    capture: &Path,
+) -> Result<(), String> {
+    webrender_api.save_capture(
+        capture.to_path_buf(),
+        CaptureBits::SCENE | CaptureBits::FRAME | CaptureBits::EXTERNAL_RESOURCES,
+    );
+    // The render backend saves the scene and the frame, then asks the renderer to save the
+    // textures and external images, which it does when it next updates.
+    let start = Instant::now();
+    while !capture.join("renderer.ron").exists() {
+        if start.elapsed() > CAPTURE_TIMEOUT {
+            return Err("timed out saving the WebRender capture".to_string());
+        }
+        webrender.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    Ok(())
+}
+
+/// Replay `capture` in a software renderer, returns the RGBA pixels of the frame, rows bottom up
+#[cfg(feature = "render-validation")]
+fn render_capture(capture: &Path, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    use std::rc::Rc;
+
+    use webrender::{WebRenderOptions, create_webrender_instance};
+    use webrender_api::{ColorF, units::DeviceIntSize};
+
+    /// Tells the replay that the software renderer has a frame to render
+    struct ReplayNotifier(crossbeam_channel::Sender<()>);
+
+    impl webrender_api::RenderNotifier for ReplayNotifier {
+        fn clone(&self) -> Box<dyn webrender_api::RenderNotifier> {
+            Box::new(ReplayNotifier(self.0.clone()))
+        }
+
+        fn wake_up(&self, _composite_needed: bool) {}
+
+        fn new_frame_ready(
+            &self,
+            _document_id: webrender_api::DocumentId,
+            _scrolled: bool,
+            _composite_needed: bool,
+            _frame_publish_id: webrender_api::FramePublishId,
+        ) {
+            let _ = self.0.send(());
+        }
+    }
+
+    let context = swgl::Context::create();
+    context.make_current();
+    context.init_default_framebuffer(0, 0, width as i32, height as i32, 0, std::ptr::null_mut());
+    let gl: Rc<dyn gl::Gl> = Rc::new(context);
+    let (frame_sender, frame_receiver) = crossbeam_channel::unbounded();
+    let result = create_webrender_instance(
+        gl.clone(),
+        Box::new(ReplayNotifier(frame_sender)),
+        WebRenderOptions {
+            // Like the hardware renderer, see `Verso::new`.
+            clear_color: ColorF::new(0., 0., 0., 0.),
+            ..Default::default()
+        },
+        None,
+    )
+    .map_err(|error| format!("failed to create the software renderer: {error:?}"))
+    .and_then(|(mut renderer, api_sender)| {
+        let api = api_sender.create_api();
+        let frame = if api.load_capture(capture.to_path_buf(), None).is_empty() {
+            Err("the capture holds no document".to_string())
+        } else {
+            frame_receiver
+                .recv_timeout(REPLAY_TIMEOUT)
+                .map_err(|_| "timed out replaying the capture".to_string())
+                .and_then(|()| {
+                    renderer.update();
+                    renderer
+                        .render(DeviceIntSize::new(width as i32, height as i32), 0)
+                        .map_err(|errors| format!("the software renderer failed: {errors:?}"))
+                })
+                .map(|_| read_frame(&*gl, width, height))
+        };
+        api.shut_down(true);
+        renderer.deinit();
+        frame
+    });
+    drop(gl);
+    context.destroy();
+    result
+}
+
+#[cfg(not(feature = "render-validation"))]
+fn render_capture(_capture: &Path, _width: u32, _height: u32) -> Result<Vec<u8>, String> {
+    Err("Verso was built without the render-validation feature".to_string())
+}
+
+/// Compare two RGBA frames with rows bottom up, channels differing by at most `tolerance` match
+pub fn diff_frames(
+    hardware: &[u8],
+    software: &[u8],
+    width: u32,
+    height: u32,
+    tolerance: u8,
+) -> FrameDiff {
+    let mut diff = FrameDiff::default();
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    let pixels = hardware.chunks_exact(4).zip(software.chunks_exact(4));
+    for (index, (a, b)) in pixels.take(width as usize * height as usize).enumerate() {
+        let difference = pixel_difference(a, b);
+        diff.max_difference = diff.max_difference.max(difference);
+        if difference <= tolerance {
+            continue;
+        }
+        diff.divergent_pixels += 1;
+        let x = index as u32 % width;
+        let y = height - 1 - index as u32 / width;
+        left = left.min(x);
+        right = right.max(x);
+        top = top.min(y);
+        bottom = bottom.max(y);
+    }
+    if diff.divergent_pixels > 0 {
+        diff.divergent_region = Some(DivergentRegion {
+            x: left,
+            y: top,
+            width: right - left + 1,
+            height: bottom - top + 1,
+        });
+    }
+    diff
+}
+
+fn pixel_difference(a: &[u8], b: &[u8]) -> u8 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or_default()
+}
+
+/// Write both frames and their difference as PNGs to `directory`
+fn write_frames(
+    directory: &Path,
+    width: u32,
+    height: u32,
+    hardware: Vec<u8>,
+    software: Vec<u8>,
+    tolerance: u8,
+) -> Result<(), String> {
+    let difference: Vec<u8> = hardware
+        .chunks_exact(4)
+        .zip(software.chunks_exact(4))
+        .flat_map(|(a, b)| {
+            if pixel_difference(a, b) > tolerance {
+                [255, 0, 0, 255]
+            } else {
+                [a[0] / 4, a[1] / 4, a[2] / 4, 255]
+            }
+        })
+        .collect();
+    for (name, rgba) in [
+        ("hardware.png", hardware),
+        ("software.png", software),
+        ("difference.png", difference),
+    ] {
+        let Some(mut image) = image::RgbaImage::from_raw(width, height, rgba) else {
+            return Err(format!("{name} doesn't have {width}x{height} pixels"));
+        };
+        image::imageops::flip_vertical_in_place(&mut image);
+        let path: PathBuf = directory.join(name);
+        image
+            .save(&path)
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_frames() {
+        // 3x2 frame, rows bottom up.
+        let hardware = vec![10u8; 3 * 2 * 4];
+        let mut software = hardware.clone();
+        // Bottom row, middle pixel: within the tolerance.
+        software[4] = 12;
+        // Top row, last pixel: divergent.
+        software[5 * 4 + 2] = 200;
+
+        let diff = diff_frames(&hardware, &software, 3, 2, 2);
+        assert_eq!(diff.divergent_pixels, 1);
+        assert_eq!(diff.max_difference, 190);
+        assert_eq!(
+            diff.divergent_region,
+            Some(DivergentRegion {
+                x: 2,
+                y: 0,
+                width: 1,
+                height: 1,
+            })
+        );
+
+        let diff = diff_frames(&hardware, &hardware, 3, 2, 0);
+        assert_eq!(diff, FrameDiff::default());
+    }
+
+    #[test]
+    fn test_validate_on_load() {
+        let mut validator = RenderValidator::default();
+        validator.page_loaded();
+        assert!(!validator.take_due());
+
+        let mut validator = RenderValidator::new(RenderValidationSettings {
+            validate_on_load: true,
+            ..Default::default()
+        });
+        validator.page_loaded();
+        assert!(validator.take_due());
+        assert!(!validator.take_due());
+    }
+}
@@ -0,0 +1,431 @@
+//! WebRender Renderer Options
+//!
+//! This module exposes the WebRender `RendererOptions` that matter most for
+//! deployment tuning, so they can be overridden from the CLI or the controller
+//! without patching Verso. Every override is validated and the effective values
+//! are logged once at startup. The few options WebRender can change while it
+//! runs are also parsed into [`RuntimeRendererOption`] for the compositor.
+//!
+//! Servo registers no blob image handler with WebRender, so there are no blob
+//! images to prioritize around the viewport or to report in the telemetry. The
+//! blob worker options only size the pool WebRender rasterizes glyphs on.
+//!
+//! WebRender has no switch to turn picture caching off, it always caches
+//! pictures in tiles. `picture_caching=false` approximates it with a single
+//! tile as large as the texture limit, so any change redraws the whole
+//! picture like an uncached one would, but the tile is still kept between
+//! frames that change nothing.
+
+use std::sync::Arc;
+
+use webrender::{CompositorConfig, TextureCacheConfig, WebRenderOptions};
+use webrender_api::units::DeviceIntSize;
+
+/// Lower and upper bounds accepted for `batch_lookback_count`
+const BATCH_LOOKBACK_RANGE: (usize, usize) = (1, 64);
+/// Lower and upper bounds accepted for picture cache tile dimensions
+const PICTURE_TILE_RANGE: (i32, i32) = (64, 4096);
+/// Lower and upper bounds accepted for texture cache page dimensions
+const TEXTURE_CACHE_RANGE: (i32, i32) = (512, 16384);
+/// Upper bound on the WebRender worker count
+const MAX_BLOB_WORKERS: usize = 32;
+/// Upper bound on the automatically chosen WebRender worker count
+const MAX_AUTO_BLOB_WORKERS: usize = 8;
+/// Lower and upper bounds accepted for the tiles large images are split into
+const IMAGE_TILE_RANGE: (u16, u16) = (64, 4096);
+
+/// Renderer option overrides
+///
+/// `None` keeps the WebRender (or Servo preference) default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RendererOptionsConfig {
+    /// Enable subpixel text anti-aliasing
+    pub enable_subpixel_aa: Option<bool>,
+    /// How many previous batches to look at when merging primitives
+    pub batch_lookback_count: Option<usize>,
+    /// Size of the picture cache tiles, in device pixels
+    pub picture_tile_size: Option<DeviceIntSize>,
+    /// Approximate turning picture caching off with tiles as large as the texture limit,
+    /// WebRender has no switch for it
+    pub disable_picture_caching: bool,
+    /// Size of the color texture cache pages, in device pixels
+    pub color_texture_cache_size: Option<i32>,
+    /// Size of the glyph texture cache pages, in device pixels
+    pub glyph_texture_cache_size: Option<i32>,
+    /// Maximum size of internal render target textures
+    pub max_internal_texture_size: Option<i32>,
+    /// Number of WebRender worker threads, which rasterize glyphs as Servo uses no blob images
+    pub blob_worker_threads: Option<usize>,
+    /// Size of the tiles images larger than it are split into, in device pixels
+    pub image_tile_size: Option<u16>,
+    /// Redraw only the region that changed and present it as the damage of the frame,
+    /// where the surface reports its buffer age
+    pub partial_present: bool,
+}
+
+impl RendererOptionsConfig {
+    /// Apply a `name=value` override, as given on the command line or by the controller
+    pub fn apply_override(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match name.trim().replace('-', "_").as_str() {
+            "enable_subpixel_aa" | "subpixel_aa" => {
+                self.enable_subpixel_aa = Some(parse_bool(value)?);
+            }
+            "batch_lookback_count" => {
+                self.batch_lookback_count = Some(parse_number(value)?);
+            }
+            "picture_tile_size" => {
+                self.picture_tile_size = Some(parse_size(value)?);
+            }
+            "picture_caching" => {
+                self.disable_picture_caching = !parse_bool(value)?;
+            }
+            "color_texture_cache_size" => {
+                self.color_texture_cache_size = Some(parse_number(value)?);
+            }
+            "glyph_texture_cache_size" => {
+                self.glyph_texture_cache_size = Some(parse_number(value)?);
+            }
+            "max_internal_texture_size" => {
+                self.max_internal_texture_size = Some(parse_number(value)?);
+            }
+            "blob_worker_threads" | "workers" => {
+                self.blob_worker_threads = if value.eq_ignore_ascii_case("auto") {
+                    Some(recommended_worker_count())
+                } else {
+                    Some(parse_number(value)?)
+                };
+            }
+            "image_tile_size" | "blob_tile_size" => {
+                self.image_tile_size = Some(parse_number(value)?);
+            }
+            "partial_present" => {
+                self.partial_present = parse_bool(value)?;
+            }
+            other => return Err(format!("unknown renderer option '{other}'")),
+        }
+        Ok(())
+    }
+
+    /// Clamp out-of-range values, returning a warning for every adjustment made
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Some(count) = self.batch_lookback_count {
+            let clamped = count.clamp(BATCH_LOOKBACK_RANGE.0, BATCH_LOOKBACK_RANGE.1);
+            if clamped != count {
+                warnings.push(format!(
+                    "batch_lookback_count {count} out of range, using {clamped}"
+                ));
+                self.batch_lookback_count = Some(clamped);
+            }
+        }
+
+        if let Some(size) = self.picture_tile_size {
+            let clamped = DeviceIntSize::new(
+                size.width.clamp(PICTURE_TILE_RANGE.0, PICTURE_TILE_RANGE.1),
+                size.height
+                    .clamp(PICTURE_TILE_RANGE.0, PICTURE_TILE_RANGE.1),
+            );
+            if clamped != size {
+                warnings.push(format!(
+                    "picture_tile_size {}x{} out of range, using {}x{}",
+                    size.width, size.height, clamped.width, clamped.height
+                ));
+                self.picture_tile_size = Some(clamped);
+            }
+        }
+        if self.disable_picture_caching && self.picture_tile_size.is_some() {
+            warnings.push("picture_tile_size is ignored when picture caching is off".to_string());
+        }
+
+        for (name, value) in [
+            (
+                "color_texture_cache_size",
+                &mut self.color_texture_cache_size,
+            ),
+            (
+                "glyph_texture_cache_size",
+                &mut self.glyph_texture_cache_size,
+            ),
+            (
+                "max_internal_texture_size",
+                &mut self.max_internal_texture_size,
+            ),
+        ] {
+            if let Some(size) = *value {
+                let clamped = size.clamp(TEXTURE_CACHE_RANGE.0, TEXTURE_CACHE_RANGE.1);
+                if clamped != size {
+                    warnings.push(format!("{name} {size} out of range, using {clamped}"));
+                    *value = Some(clamped);
+                }
+            }
+        }
+
+        if let Some(threads) = self.blob_worker_threads {
+            let clamped = threads.clamp(1, MAX_BLOB_WORKERS);
+            if clamped != threads {
+                warnings.push(format!(
+                    "blob_worker_threads {threads} out of range, using {clamped}"
+                ));
+                self.blob_worker_threads = Some(clamped);
+            }
+        }
+
+        if let Some(size) = self.image_tile_size {
+            let clamped = size.clamp(IMAGE_TILE_RANGE.0, IMAGE_TILE_RANGE.1);
+            if clamped != size {
+                warnings.push(format!(
+                    "image_tile_size {size} out of range, using {clamped}"
+                ));
+                self.image_tile_size = Some(clamped);
+            }
+        }
+
+        warnings
+    }
+
+    /// Write the overrides into WebRender's options
+    pub fn apply_to(&self, options: &mut WebRenderOptions) {
+        if let Some(enable_subpixel_aa) = self.enable_subpixel_aa {
+            options.enable_subpixel_aa = enable_subpixel_aa;
+        }
+        if let Some(count) = self.batch_lookback_count {
+            options.batch_lookback_count = count;
+        }
+        if self.disable_picture_caching {
+            // The closest WebRender gets to no picture caching: one tile covering the picture.
+            let max = self
+                .max_internal_texture_size
+                .unwrap_or(PICTURE_TILE_RANGE.1);
+            options.picture_tile_size = Some(DeviceIntSize::new(max, max));
+        } else if let Some(size) = self.picture_tile_size {
+            options.picture_tile_size = Some(size);
+        }
+        if self.color_texture_cache_size.is_some() || self.glyph_texture_cache_size.is_some() {
+            let mut texture_cache = TextureCacheConfig::DEFAULT;
+            if let Some(size) = self.color_texture_cache_size {
+                texture_cache.color8_linear_texture_size = size;
+                texture_cache.color8_nearest_texture_size = size;
+            }
+            if let Some(size) = self.glyph_texture_cache_size {
+                texture_cache.color8_glyph_texture_size = size;
+                texture_cache.alpha8_glyph_texture_size = size;
+            }
+            options.texture_cache_config = texture_cache;
+        }
+        if let Some(size) = self.max_internal_texture_size {
+            options.max_internal_texture_size = Some(size);
+        }
+        if let Some(threads) = self.blob_worker_threads {
+            match rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|index| format!("WRWorker#{index}"))
+                .build()
+            {
+                Ok(pool) => options.workers = Some(Arc::new(pool)),
+                Err(e) => log::error!("Failed to create WebRender worker pool: {e}"),
+            }
+        }
+        if self.partial_present {
+            // One rect, the union of the damage, is all swap_buffers_with_damage needs.
+            options.compositor_config = CompositorConfig::Draw {
+                max_partial_present_rects: 1,
+                draw_previous_partial_present_regions: false,
+                partial_present: None,
+            };
+        }
+    }
+
+    /// Log the effective renderer options after overrides have been applied
+    pub fn log_effective(options: &WebRenderOptions) {
+        log::info!(
+            "WebRender options: subpixel_aa={} batch_lookback={} picture_tile_size={:?} \
+             max_internal_texture_size={:?} color_cache={} glyph_cache={} workers={}",
+            options.enable_subpixel_aa,
+            options.batch_lookback_count,
+            options.picture_tile_size,
+            options.max_internal_texture_size,
+            options.texture_cache_config.color8_linear_texture_size,
+            options.texture_cache_config.color8_glyph_texture_size,
+            options
+                .workers
+                .as_ref()
+                .map(|pool| pool.current_num_threads().to_string())
+                .unwrap_or_else(|| "default".to_string()),
+        );
+    }
+}
+
+/// A renderer option WebRender can change while it runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuntimeRendererOption {
+    /// Size of the picture cache tiles, in device pixels
+    PictureTileSize(DeviceIntSize),
+    /// How many previous batches to look at when merging primitives
+    BatchLookbackCount(usize),
+    /// Rasterize blob images and glyphs on the worker threads
+    Multithreading(bool),
+    /// Size of the tiles images added from now on are split into, in device pixels
+    ImageTileSize(u16),
+}
+
+impl RuntimeRendererOption {
+    /// Parse a `name=value` override, clamping the value like
+    /// [`RendererOptionsConfig::validate`] does
+    pub fn parse(name: &str, value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        match name.trim().replace('-', "_").as_str() {
+            "picture_tile_size" => {
+                let size = parse_size(value)?;
+                Ok(Self::PictureTileSize(DeviceIntSize::new(
+                    size.width.clamp(PICTURE_TILE_RANGE.0, PICTURE_TILE_RANGE.1),
+                    size.height
+                        .clamp(PICTURE_TILE_RANGE.0, PICTURE_TILE_RANGE.1),
+                )))
+            }
+            "batch_lookback_count" => Ok(Self::BatchLookbackCount(
+                parse_number::<usize>(value)?.clamp(BATCH_LOOKBACK_RANGE.0, BATCH_LOOKBACK_RANGE.1),
+            )),
+            "multithreading" => Ok(Self::Multithreading(parse_bool(value)?)),
+            "image_tile_size" | "blob_tile_size" => Ok(Self::ImageTileSize(
+                parse_number::<u16>(value)?.clamp(IMAGE_TILE_RANGE.0, IMAGE_TILE_RANGE.1),
+            )),
+            other => {
+                let mut config = RendererOptionsConfig::default();
+                config.apply_override(other, value)?;
+                Err(format!(
+                    "renderer option '{other}' can only be set at startup"
+                ))
+            }
+        }
+    }
+}
+
+/// Pick a WebRender worker pool size for this machine
+///
+/// Leaves half of the cores to layout, script and the compositor.
+pub fn recommended_worker_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| (cores.get() / 2).clamp(1, MAX_AUTO_BLOB_WORKERS))
+        .unwrap_or(1)
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "on" | "yes" => Ok(true),
+        "0" | "false" | "off" | "no" => Ok(false),
+        _ => Err(format!("expected a boolean, got '{value}'")),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a number, got '{value}'"))
+}
+
+fn parse_size(value: &str) -> Result<DeviceIntSize, String> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{value}'"))?;
+    Ok(DeviceIntSize::new(
+        parse_number(width)?,
+        parse_number(height)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_override() {
+        let mut config = RendererOptionsConfig::default();
+        config.apply_override("subpixel-aa", "off").unwrap();
+        config.apply_override("batch_lookback_count", "20").unwrap();
+        config
+            .apply_override("picture_tile_size", "512x256")
+            .unwrap();
+        config.apply_override("workers", "4").unwrap();
+        config.apply_override("partial-present", "on").unwrap();
+        config.apply_override("blob-tile-size", "1024").unwrap();
+        assert_eq!(config.image_tile_size, Some(1024));
+        assert!(config.partial_present);
+        assert_eq!(config.blob_worker_threads, Some(4));
+        config.apply_override("workers", "auto").unwrap();
+        assert_eq!(config.blob_worker_threads, Some(recommended_worker_count()));
+
+        assert_eq!(config.enable_subpixel_aa, Some(false));
+        assert_eq!(config.batch_lookback_count, Some(20));
+        assert_eq!(config.picture_tile_size, Some(DeviceIntSize::new(512, 256)));
+    }
+
+    #[test]
+    fn test_invalid_override() {
+        let mut config = RendererOptionsConfig::default();
+        assert!(config.apply_override("not_an_option", "1").is_err());
+        assert!(config.apply_override("picture_tile_size", "512").is_err());
+        assert!(config.apply_override("subpixel_aa", "maybe").is_err());
+        assert_eq!(config, RendererOptionsConfig::default());
+    }
+
+    #[test]
+    fn test_validate_clamps() {
+        let mut config = RendererOptionsConfig {
+            batch_lookback_count: Some(0),
+            picture_tile_size: Some(DeviceIntSize::new(16, 8192)),
+            blob_worker_threads: Some(1000),
+            image_tile_size: Some(8),
+            ..Default::default()
+        };
+        let warnings = config.validate();
+
+        assert_eq!(warnings.len(), 4);
+        assert_eq!(config.image_tile_size, Some(64));
+        assert_eq!(config.batch_lookback_count, Some(1));
+        assert_eq!(config.picture_tile_size, Some(DeviceIntSize::new(64, 4096)));
+        assert_eq!(config.blob_worker_threads, Some(MAX_BLOB_WORKERS));
+    }
+
+    #[test]
+    fn test_runtime_option() {
+        assert_eq!(
+            RuntimeRendererOption::parse("picture-tile-size", "8192x512"),
+            Ok(RuntimeRendererOption::PictureTileSize(DeviceIntSize::new(
+                4096, 512
+            )))
+        );
+        assert_eq!(
+            RuntimeRendererOption::parse("multithreading", "off"),
+            Ok(RuntimeRendererOption::Multithreading(false))
+        );
+        assert_eq!(
+            RuntimeRendererOption::parse("blob_tile_size", "256"),
+            Ok(RuntimeRendererOption::ImageTileSize(256))
+        );
+        assert!(
+            RuntimeRendererOption::parse("workers", "4")
+                .unwrap_err()
+                .contains("startup")
+        );
+        assert!(
+            RuntimeRendererOption::parse("not_an_option", "1")
+                .unwrap_err()
+                .contains("unknown")
+        );
+    }
+
+    #[test]
+    fn test_validate_default_is_clean() {
+        let mut config = RendererOptionsConfig::default();
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_recommended_worker_count() {
+        let count = recommended_worker_count();
+        assert!((1..=MAX_AUTO_BLOB_WORKERS).contains(&count));
+    }
+}
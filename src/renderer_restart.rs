@@ -0,0 +1,279 @@
+//! Renderer Restart
+//!
+//! A GL context created to be lost on a GPU reset, as asked for with
+//! `GlContextSettings::lose_context_on_reset`, is dead once the driver reset,
+//! and so is the WebRender renderer drawing with it. The compositor then
+//! creates a new context and a new renderer, and gives the renderer everything
+//! the old one had. Fonts, images and display lists all come from script
+//! through the compositor, so while restarts are possible the compositor keeps
+//! what is still alive in [`RetainedResources`]: font and image data are shared
+//! with WebRender, the latest display list of each pipeline is a copy.
+//!
+//! The same restart moves rendering to another GPU when the window's display
+//! is now driven by one, since a context never leaves the GPU it was created on.
+//!
+//! The WebRender API the constellation got at startup, which only WebGPU uses,
+//! stays on the old renderer.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use compositing_traits::{
+    WebrenderExternalImageApi, WebrenderExternalImageRegistry, WebrenderImageHandlerType,
+    WebrenderImageSource,
+};
+use gleam::gl;
+use ipc_channel::ipc::IpcSharedMemory;
+use webrender::{
+    ExternalImage, ExternalImageHandler, ExternalImageSource, RenderApiSender, Renderer,
+    RendererError, Transaction,
+};
+use webrender_api::units::TexelRect;
+use webrender_api::{
+    BuiltDisplayList, BuiltDisplayListDescriptor, DisplayListPayload, Epoch, ExternalImageId,
+    FontInstanceKey, FontKey, ImageData, ImageDescriptor, ImageKey, NativeFontHandle, PipelineId,
+    TileSize,
+};
+
+use crate::compositor::TransactionTrait;
+
+/// Creates a WebRender renderer drawing with a GL context, with the options Verso started with
+pub type CreateRenderer =
+    Box<dyn Fn(Rc<dyn gl::Gl>) -> Result<(Renderer, RenderApiSender), RendererError>>;
+
+/// Data of a font WebRender was given
+enum FontData {
+    Raw(Arc<IpcSharedMemory>, u32),
+    Native(NativeFontHandle),
+}
+
+/// Latest display list of a pipeline
+struct RetainedDisplayList {
+    epoch: Epoch,
+    descriptor: BuiltDisplayListDescriptor,
+    payload: DisplayListPayload,
+}
+
+/// Fonts, images and display lists a new renderer has to be given again
+#[derive(Default)]
+pub struct RetainedResources {
+    fonts: HashMap<FontKey, FontData>,
+    images: HashMap<ImageKey, (ImageDescriptor, ImageData)>,
+    display_lists: HashMap<PipelineId, RetainedDisplayList>,
+}
+
+impl RetainedResources {
+    /// Keep a font added from its data
+    pub fn add_raw_font(&mut self, key: FontKey, data: Arc<IpcSharedMemory>, index: u32) {
+        self.fonts.insert(key, FontData::Raw(data, index));
+    }
+
+    /// Keep a font added from a file of the system
+    pub fn add_native_font(&mut self, key: FontKey, handle: NativeFontHandle) {
+        self.fonts.insert(key, FontData::Native(handle));
+    }
+
+    /// Keep an image added or updated, replacing its previous data
+    pub fn set_image(&mut self, key: ImageKey, descriptor: ImageDescriptor, data: &ImageData) {
+        self.images.insert(key, (descriptor, data.clone()));
+    }
+
+    /// Keep a copy of the latest display list of a pipeline
+    pub fn set_display_list(
+        &mut self,
+        pipeline_id: PipelineId,
+        epoch: Epoch,
+        descriptor: BuiltDisplayListDescriptor,
+        payload: &DisplayListPayload,
+    ) {
+        let payload = DisplayListPayload {
+            items_data: payload.items_data.clone(),
+            cache_data: payload.cache_data.clone(),
+            spatial_tree: payload.spatial_tree.clone(),
+        };
+        self.display_lists.insert(
+            pipeline_id,
+            RetainedDisplayList {
+                epoch,
+                descriptor,
+                payload,
+            },
+        );
+    }
+
+    /// Forget the display list of a removed pipeline
+    pub fn remove_pipeline(&mut self, pipeline_id: PipelineId) {
+        self.display_lists.remove(&pipeline_id);
+    }
+
+    /// Add the fonts and images to a new renderer, tiling the images like they were
+    pub fn replay_resources(
+        &self,
+        transaction: &mut Transaction,
+        tiling: impl Fn(&ImageDescriptor) -> Option<TileSize>,
+    ) {
+        for (key, font) in &self.fonts {
+            match font {
+                FontData::Raw(data, index) => {
+                    transaction.add_raw_font(*key, (***data).into(), *index)
+                }
+                FontData::Native(handle) => transaction.add_native_font(*key, handle.clone()),
+            }
+        }
+        for (key, (descriptor, data)) in &self.images {
+            transaction.add_image(*key, *descriptor, data.clone(), tiling(descriptor));
+        }
+    }
+
+    /// The latest display list of every pipeline, to set on a new renderer
+    pub fn display_lists(
+        &self,
+    ) -> impl Iterator<Item = (PipelineId, Epoch, BuiltDisplayList)> + '_ {
+        self.display_lists
+            .iter()
+            .map(|(pipeline_id, display_list)| {
+                let payload = DisplayListPayload {
+                    items_data: display_list.payload.items_data.clone(),
+                    cache_data: display_list.payload.cache_data.clone(),
+                    spatial_tree: display_list.payload.spatial_tree.clone(),
+                };
+                (
+                    *pipeline_id,
+                    display_list.epoch,
+                    BuiltDisplayList::from_data(payload, display_list.descriptor),
+                )
+            })
+    }
+}
+
+/// Resources deleted from WebRender are forgotten
+impl TransactionTrait for RetainedResources {
+    fn delete_font(&mut self, key: FontKey) {
+        self.fonts.remove(&key);
+    }
+
+    fn delete_font_instance(&mut self, _key: FontInstanceKey) {}
+
+    fn delete_image(&mut self, key: ImageKey) {
+        self.images.remove(&key);
+    }
+}
+
+/// External image handlers of a restarted renderer, looking up the images in the registry of
+/// the first one
+///
+/// Servo's `WebrenderExternalImageHandlers` comes with a registry of its own, but a restarted
+/// renderer has to find the images of the ids the constellation and the compositor already
+/// handed out.
+pub struct ExternalImageHandlers {
+    registry: Arc<Mutex<WebrenderExternalImageRegistry>>,
+    webgl: Option<Box<dyn WebrenderExternalImageApi>>,
+    media: Option<Box<dyn WebrenderExternalImageApi>>,
+    webgpu: Option<Box<dyn WebrenderExternalImageApi>>,
+}
+
+impl ExternalImageHandlers {
+    /// Handle the external images of `registry`
+    pub fn new(registry: Arc<Mutex<WebrenderExternalImageRegistry>>) -> Self {
+        Self {
+            registry,
+            webgl: None,
+            media: None,
+            webgpu: None,
+        }
+    }
+
+    /// Handle the external images of a type
+    pub fn set_handler(
+        &mut self,
+        handler: Box<dyn WebrenderExternalImageApi>,
+        handler_type: WebrenderImageHandlerType,
+    ) {
+        match handler_type {
+            WebrenderImageHandlerType::WebGL => self.webgl = Some(handler),
+            WebrenderImageHandlerType::Media => self.media = Some(handler),
+            WebrenderImageHandlerType::WebGPU => self.webgpu = Some(handler),
+        }
+    }
+}
+
+impl ExternalImageHandler for ExternalImageHandlers {
+    fn lock(&mut self, key: ExternalImageId, _channel_index: u8) -> ExternalImage {
+        let registry = self.registry.lock().unwrap();
+        // WebGL and WebGPU textures are upside down, like the GL framebuffers they are.
+        let (handler, flipped) = match registry
+            .get(&key)
+            .expect("Tried to get unknown external image")
+        {
+            WebrenderImageHandlerType::WebGL => (&mut self.webgl, true),
+            WebrenderImageHandlerType::Media => (&mut self.media, false),
+            WebrenderImageHandlerType::WebGPU => (&mut self.webgpu, true),
+        };
+        let (source, size) = handler
+            .as_mut()
+            .expect("No handler for the external image")
+            .lock(key.0);
+        let uv = if flipped {
+            TexelRect::new(0.0, size.height as f32, size.width as f32, 0.0)
+        } else {
+            TexelRect::new(0.0, 0.0, size.width as f32, size.height as f32)
+        };
+        let source = match source {
+            WebrenderImageSource::TextureHandle(texture) => {
+                ExternalImageSource::NativeTexture(texture)
+            }
+            WebrenderImageSource::Raw(data) => ExternalImageSource::RawData(data),
+        };
+        ExternalImage { uv, source }
+    }
+
+    fn unlock(&mut self, key: ExternalImageId, _channel_index: u8) {
+        let registry = self.registry.lock().unwrap();
+        let handler = match registry.get(&key) {
+            Some(WebrenderImageHandlerType::WebGL) => &mut self.webgl,
+            Some(WebrenderImageHandlerType::Media) => &mut self.media,
+            Some(WebrenderImageHandlerType::WebGPU) => &mut self.webgpu,
+            None => return,
+        };
+        if let Some(handler) = handler {
+            handler.unlock(key.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use webrender_api::{ImageDescriptorFlags, ImageFormat};
+
+    use super::*;
+
+    #[test]
+    fn test_retained_resources() {
+        let mut resources = RetainedResources::default();
+        let image_key = ImageKey::new(1, 0);
+        let descriptor =
+            ImageDescriptor::new(2, 2, ImageFormat::BGRA8, ImageDescriptorFlags::empty());
+        resources.set_image(image_key, descriptor, &ImageData::new(vec![0; 16]));
+        resources.set_image(image_key, descriptor, &ImageData::new(vec![255; 16]));
+        assert_eq!(resources.images.len(), 1);
+
+        let pipeline_id = PipelineId(1, 1);
+        let payload = DisplayListPayload {
+            items_data: vec![1, 2, 3],
+            cache_data: Vec::new(),
+            spatial_tree: Vec::new(),
+        };
+        resources.set_display_list(pipeline_id, Epoch(1), Default::default(), &payload);
+        resources.set_display_list(pipeline_id, Epoch(2), Default::default(), &payload);
+        let display_lists: Vec<_> = resources.display_lists().collect();
+        assert_eq!(display_lists.len(), 1);
+        assert_eq!(display_lists[0].1, Epoch(2));
+
+        // Resources deleted with a removed pipeline are forgotten.
+        resources.delete_image(image_key);
+        resources.remove_pipeline(pipeline_id);
+        assert!(resources.images.is_empty());
+        assert_eq!(resources.display_lists().count(), 0);
+    }
+}
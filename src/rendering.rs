@@ -5,9 +5,14 @@ use std::{cell::Cell, ffi::CString};
 use dpi::PhysicalSize;
 use euclid::Size2D;
 use gleam::gl;
+#[cfg(target_os = "linux")]
 use glutin::{
-    config::{Config, GetGlConfig, GlConfig},
-    context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Version},
+    api::egl::{device::Device, display::Display as EglDisplay},
+    config::ConfigSurfaceTypes,
+};
+use glutin::{
+    config::{ColorBufferType, Config, ConfigTemplateBuilder, GetGlConfig, GlConfig},
+    context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Robustness, Version},
     display::GetGlDisplay,
     prelude::{GlContext, GlDisplay, NotCurrentGlContext, PossiblyCurrentGlContext},
     surface::{
@@ -16,23 +21,287 @@ use glutin::{
 };
 use glutin_winit::GlWindow;
 use raw_window_handle::HasWindowHandle;
-use webrender_api::units::DevicePixel;
+use versoview_messages::{
+    GlApi, GlContextInfo, GlContextSettings, GlPlatform, GlProfile, GlResetStrategy,
+    OutputColorDepth, PresentMode,
+};
+use webrender_api::units::{DeviceIntRect, DevicePixel};
 use winit::window::Window;
 
+/// `GL_CONTEXT_FLAGS`, OpenGL 3.0 and OpenGL ES 3.2
+const CONTEXT_FLAGS: gl::GLenum = 0x821E;
+/// `GL_CONTEXT_FLAG_ROBUST_ACCESS_BIT`
+const CONTEXT_FLAG_ROBUST_ACCESS_BIT: gl::GLint = 0x4;
+/// `GL_CONTEXT_PROFILE_MASK`, OpenGL 3.2
+const CONTEXT_PROFILE_MASK: gl::GLenum = 0x9126;
+/// `GL_CONTEXT_CORE_PROFILE_BIT`
+const CONTEXT_CORE_PROFILE_BIT: gl::GLint = 0x1;
+/// `GL_CONTEXT_COMPATIBILITY_PROFILE_BIT`
+const CONTEXT_COMPATIBILITY_PROFILE_BIT: gl::GLint = 0x2;
+/// `GL_RESET_NOTIFICATION_STRATEGY`, OpenGL 4.5, OpenGL ES 3.2 and the robustness extensions
+const RESET_NOTIFICATION_STRATEGY: gl::GLenum = 0x8256;
+/// `GL_LOSE_CONTEXT_ON_RESET`
+const LOSE_CONTEXT_ON_RESET: gl::GLint = 0x8252;
+/// `GL_NO_RESET_NOTIFICATION`
+const NO_RESET_NOTIFICATION: gl::GLint = 0x8261;
+/// Extension of Mesa's software (llvmpipe) EGL device
+#[cfg(target_os = "linux")]
+const MESA_DEVICE_SOFTWARE: &str = "EGL_MESA_device_software";
+
+/// Parse a presentation mode from the command line
+pub fn parse_present_mode(present_mode: &str) -> Option<PresentMode> {
+    match present_mode.to_lowercase().as_str() {
+        "fifo" | "vsync" => Some(PresentMode::Fifo),
+        "mailbox" | "latest" => Some(PresentMode::Mailbox),
+        "immediate" | "tearing" => Some(PresentMode::Immediate),
+        _ => None,
+    }
+}
+
+/// Swap interval giving `present_mode`, `None` if GL can't present that way.
+fn swap_interval(present_mode: PresentMode) -> Option<SwapInterval> {
+    match present_mode {
+        PresentMode::Fifo => Some(SwapInterval::Wait(NonZeroU32::new(1).unwrap())),
+        PresentMode::Immediate => Some(SwapInterval::DontWait),
+        // GL has no mailbox swap chain, not blocking on swap would tear like immediate.
+        PresentMode::Mailbox => None,
+    }
+}
+
+/// The GPU a GL context renders on, as reported by the driver.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GpuIdentity {
+    /// `GL_VENDOR` string
+    pub vendor: String,
+    /// `GL_RENDERER` string
+    pub renderer: String,
+}
+
+impl GpuIdentity {
+    /// Ask the driver which GPU `gl` currently renders on.
+    pub fn query(gl: &dyn gl::Gl) -> Self {
+        Self {
+            vendor: gl.get_string(gl::VENDOR),
+            renderer: gl.get_string(gl::RENDERER),
+        }
+    }
+}
+
+impl std::fmt::Display for GpuIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.renderer, self.vendor)
+    }
+}
+
+/// Ask the driver for the attributes of the current context.
+fn query_context_info(
+    gl: &dyn gl::Gl,
+    api: GlApi,
+    platform_version: String,
+    config: &Config,
+) -> GlContextInfo {
+    let version_string = gl.get_string(gl::VERSION);
+    let version = parse_gl_version(&version_string);
+    let flags = get_integer(gl, CONTEXT_FLAGS).unwrap_or(0);
+    let profile = match api {
+        GlApi::OpenGl if version >= (3, 2) => {
+            get_integer(gl, CONTEXT_PROFILE_MASK).and_then(|mask| {
+                if mask & CONTEXT_CORE_PROFILE_BIT != 0 {
+                    Some(GlProfile::Core)
+                } else if mask & CONTEXT_COMPATIBILITY_PROFILE_BIT != 0 {
+                    Some(GlProfile::Compatibility)
+                } else {
+                    None
+                }
+            })
+        }
+        _ => None,
+    };
+    let reset_strategy = match get_integer(gl, RESET_NOTIFICATION_STRATEGY) {
+        Some(LOSE_CONTEXT_ON_RESET) => Some(GlResetStrategy::LoseContextOnReset),
+        Some(NO_RESET_NOTIFICATION) => Some(GlResetStrategy::NoResetNotification),
+        _ => None,
+    };
+    GlContextInfo {
+        api,
+        version,
+        version_string,
+        shading_language_version: gl.get_string(gl::SHADING_LANGUAGE_VERSION),
+        profile,
+        robust_access: flags & CONTEXT_FLAG_ROBUST_ACCESS_BIT != 0,
+        reset_strategy,
+        platform: parse_platform(&platform_version),
+        platform_version,
+        vendor: gl.get_string(gl::VENDOR),
+        renderer: gl.get_string(gl::RENDERER),
+        samples: config.num_samples(),
+        color_bits: color_bits(config),
+        float_pixels: config.float_pixels(),
+        alpha_bits: config.alpha_size(),
+        transparent: config.supports_transparency().unwrap_or(false),
+    }
+}
+
+/// Get an integer state, `None` if the context doesn't know `name`.
+fn get_integer(gl: &dyn gl::Gl, name: gl::GLenum) -> Option<gl::GLint> {
+    // Clear earlier errors, so only this query's error is seen.
+    for _ in 0..8 {
+        if gl.get_error() == gl::NO_ERROR {
+            break;
+        }
+    }
+    let mut value = [0];
+    unsafe { gl.get_integer_v(name, &mut value) };
+    (gl.get_error() == gl::NO_ERROR).then_some(value[0])
+}
+
+/// Major and minor version of a `GL_VERSION` string, `(0, 0)` if it can't be read.
+///
+/// Desktop strings start with the version, e.g. `4.6 (Core Profile) Mesa 24.0`, OpenGL ES
+/// ones with a prefix, e.g. `OpenGL ES 3.2 Mesa 24.0`.
+fn parse_gl_version(version: &str) -> (u8, u8) {
+    let version = version.trim_start_matches("OpenGL ES-CM ");
+    let version = version.trim_start_matches("OpenGL ES-CL ");
+    let version = version.trim_start_matches("OpenGL ES ");
+    let number = version.split_whitespace().next().unwrap_or_default();
+    let mut parts = number.split('.').map(|part| part.parse::<u8>().ok());
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(major), Some(minor)) => (major, minor),
+        _ => (0, 0),
+    }
+}
+
+/// Platform interface of a glutin display version string, e.g. `EGL 1.5`.
+fn parse_platform(platform_version: &str) -> Option<GlPlatform> {
+    let platform = platform_version.split_whitespace().next()?;
+    match platform {
+        "EGL" => Some(GlPlatform::Egl),
+        "GLX" => Some(GlPlatform::Glx),
+        "WGL" => Some(GlPlatform::Wgl),
+        "Apple" | "CGL" => Some(GlPlatform::Cgl),
+        _ => None,
+    }
+}
+
+/// A framebuffer a headless [`RenderingContext`] renders into instead of a window surface.
+pub struct OffscreenFramebuffer {
+    framebuffer: gl::GLuint,
+    color: gl::GLuint,
+    depth_stencil: gl::GLuint,
+    size: Cell<PhysicalSize<u32>>,
+}
+
+impl OffscreenFramebuffer {
+    /// Create a framebuffer with RGBA color, depth and stencil buffers of `size`, and bind it.
+    pub fn create(gl: &dyn gl::Gl, size: PhysicalSize<u32>) -> Self {
+        let renderbuffers = gl.gen_renderbuffers(2);
+        let framebuffer = Self {
+            framebuffer: gl.gen_framebuffers(1)[0],
+            color: renderbuffers[0],
+            depth_stencil: renderbuffers[1],
+            size: Cell::new(size),
+        };
+        framebuffer.allocate(gl, size);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, framebuffer.framebuffer);
+        gl.framebuffer_renderbuffer(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            framebuffer.color,
+        );
+        for attachment in [gl::DEPTH_ATTACHMENT, gl::STENCIL_ATTACHMENT] {
+            gl.framebuffer_renderbuffer(
+                gl::FRAMEBUFFER,
+                attachment,
+                gl::RENDERBUFFER,
+                framebuffer.depth_stencil,
+            );
+        }
+        if gl.check_frame_buffer_status(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            log::error!("The offscreen framebuffer is incomplete");
+        }
+        framebuffer.bind(gl);
+        framebuffer
+    }
+
+    /// Bind the framebuffer for drawing and reading, with a viewport covering it.
+    pub fn bind(&self, gl: &dyn gl::Gl) {
+        let size = self.size.get();
+        gl.bind_framebuffer(gl::FRAMEBUFFER, self.framebuffer);
+        gl.viewport(0, 0, size.width as i32, size.height as i32);
+    }
+
+    /// Reallocate the buffers at `size`, the content is lost.
+    pub fn resize(&self, gl: &dyn gl::Gl, size: PhysicalSize<u32>) {
+        self.size.set(size);
+        self.allocate(gl, size);
+        self.bind(gl);
+    }
+
+    /// Read the pixels of the last frame, top row first.
+    pub fn read_pixels(&self, gl: &dyn gl::Gl) -> Option<image::RgbaImage> {
+        let size = self.size.get();
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, self.framebuffer);
+        let pixels = gl.read_pixels(
+            0,
+            0,
+            size.width as i32,
+            size.height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+        );
+        let mut image = image::RgbaImage::from_raw(size.width, size.height, pixels)?;
+        // GL rows are bottom up.
+        image::imageops::flip_vertical_in_place(&mut image);
+        Some(image)
+    }
+
+    /// Delete the framebuffer and its buffers.
+    pub fn delete(self, gl: &dyn gl::Gl) {
+        gl.delete_framebuffers(&[self.framebuffer]);
+        gl.delete_renderbuffers(&[self.color, self.depth_stencil]);
+    }
+
+    fn allocate(&self, gl: &dyn gl::Gl, size: PhysicalSize<u32>) {
+        for (renderbuffer, format) in [
+            (self.color, gl::RGBA8),
+            (self.depth_stencil, gl::DEPTH24_STENCIL8),
+        ] {
+            gl.bind_renderbuffer(gl::RENDERBUFFER, renderbuffer);
+            gl.renderbuffer_storage(
+                gl::RENDERBUFFER,
+                format,
+                size.width as i32,
+                size.height as i32,
+            );
+        }
+        gl.bind_renderbuffer(gl::RENDERBUFFER, 0);
+    }
+}
+
 /// A Verso rendering context, which holds all of the information needed
 /// to render Servo's layout, and bridges WebRender and glutin.
 pub struct RenderingContext {
     context: PossiblyCurrentContext,
     size: Cell<PhysicalSize<u32>>,
     pub(crate) gl: Rc<dyn gl::Gl>,
+    present_mode: Cell<PresentMode>,
+    context_info: GlContextInfo,
+    settings: GlContextSettings,
+    /// Rendered into instead of a window surface, if the context is headless
+    offscreen: Option<OffscreenFramebuffer>,
 }
 
 impl RenderingContext {
     /// Create a rendering context instance.
+    ///
+    /// A robust context is asked for first if `settings` want one. Without it, creation fails
+    /// if robustness is required and falls back to a regular context otherwise.
     pub fn create(
         window: &Window,
         gl_config: &Config,
         size: PhysicalSize<u32>,
+        settings: GlContextSettings,
     ) -> Result<(Self, Surface<WindowSurface>), Box<dyn std::error::Error>> {
         // XXX This will panic on Android, but we care about Desktop for now.
         let raw_window_handle = window.window_handle().ok().map(|handle| handle.as_raw());
@@ -51,7 +320,7 @@ impl RenderingContext {
         let legacy_context_attributes = ContextAttributesBuilder::new()
             .with_context_api(ContextApi::OpenGl(Some(Version::new(2, 1))))
             .build(raw_window_handle);
-        let not_current_gl_context = unsafe {
+        let create_regular_context = || unsafe {
             gl_display
                 .create_context(gl_config, &context_attributes)
                 .unwrap_or_else(|_| {
@@ -64,6 +333,43 @@ impl RenderingContext {
                         })
                 })
         };
+        let not_current_gl_context = if settings.require_robustness
+            || settings.lose_context_on_reset
+        {
+            let robustness = if settings.lose_context_on_reset {
+                Robustness::RobustLoseContextOnReset
+            } else {
+                Robustness::RobustNoResetNotification
+            };
+            let robust_context_attributes = ContextAttributesBuilder::new()
+                .with_robustness(robustness)
+                .build(raw_window_handle);
+            let robust_fallback_context_attributes = ContextAttributesBuilder::new()
+                .with_robustness(robustness)
+                .with_context_api(ContextApi::Gles(None))
+                .build(raw_window_handle);
+            let robust_context = unsafe {
+                gl_display
+                    .create_context(gl_config, &robust_context_attributes)
+                    .or_else(|_| {
+                        gl_display.create_context(gl_config, &robust_fallback_context_attributes)
+                    })
+            };
+            match robust_context {
+                Ok(context) => context,
+                Err(error) if settings.require_robustness => {
+                    return Err(format!("Failed to create a robust GL context: {error}").into());
+                }
+                Err(error) => {
+                    log::warn!(
+                        "Failed to create a robust GL context, falling back to a regular one: {error}"
+                    );
+                    create_regular_context()
+                }
+            }
+        } else {
+            create_regular_context()
+        };
 
         // Create surface
         let attrs = window
@@ -86,6 +392,10 @@ impl RenderingContext {
             log::error!("Error setting vsync: {res:?}");
         }
 
+        let api = match context.context_api() {
+            ContextApi::OpenGl(_) => GlApi::OpenGl,
+            ContextApi::Gles(_) => GlApi::Gles,
+        };
         let gl = match context.context_api() {
             ContextApi::OpenGl(_) => unsafe {
                 gleam::gl::GlFns::load_with(|symbol| {
@@ -108,16 +418,152 @@ impl RenderingContext {
             gl.get_string(gl::SHADING_LANGUAGE_VERSION)
         );
 
+        let context_info =
+            query_context_info(gl.as_ref(), api, gl_display.version_string(), gl_config);
+        log::info!("GL context: {context_info:?}");
+        if settings.require_robustness && !context_info.robust_access {
+            return Err("The GL context has no robust buffer access".into());
+        }
+
         Ok((
             Self {
                 size: Cell::new(size),
                 context,
                 gl,
+                present_mode: Cell::new(PresentMode::Fifo),
+                context_info,
+                settings,
+                offscreen: None,
             },
             surface,
         ))
     }
 
+    /// Create a rendering context without a window, rendering into an [`OffscreenFramebuffer`].
+    ///
+    /// For server-side rendering and CI, where no window can be created. The context is a
+    /// surfaceless OpenGL ES one on an EGL device, a GPU if there is one and Mesa's llvmpipe
+    /// otherwise.
+    #[cfg(target_os = "linux")]
+    pub fn create_headless(
+        size: PhysicalSize<u32>,
+        settings: GlContextSettings,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let devices: Vec<_> = Device::query_devices()?.collect();
+        let device = devices
+            .iter()
+            .find(|device| !device.extensions().contains(MESA_DEVICE_SOFTWARE))
+            .or_else(|| devices.first())
+            .ok_or("no EGL device")?;
+        let display = unsafe { EglDisplay::with_device(device, None)? };
+        let template = ConfigTemplateBuilder::new()
+            .with_surface_type(ConfigSurfaceTypes::empty())
+            .build();
+        let config = unsafe { display.find_configs(template)? }
+            .next()
+            .ok_or("no GL config on the EGL device")?;
+        let robustness = if settings.lose_context_on_reset {
+            Robustness::RobustLoseContextOnReset
+        } else if settings.require_robustness {
+            Robustness::RobustNoResetNotification
+        } else {
+            Robustness::NotRobust
+        };
+        let attributes = ContextAttributesBuilder::new()
+            .with_robustness(robustness)
+            .with_context_api(ContextApi::Gles(None))
+            .build(None);
+        let context = match unsafe { display.create_context(&config, &attributes) } {
+            Ok(context) => context,
+            Err(error) if settings.require_robustness => {
+                return Err(format!("Failed to create a robust GL context: {error}").into());
+            }
+            Err(error) => {
+                log::warn!(
+                    "Failed to create a robust GL context, falling back to a regular one: {error}"
+                );
+                let attributes = ContextAttributesBuilder::new()
+                    .with_context_api(ContextApi::Gles(None))
+                    .build(None);
+                unsafe { display.create_context(&config, &attributes)? }
+            }
+        }
+        .make_current_surfaceless()?;
+        let gl = unsafe {
+            gleam::gl::GlesFns::load_with(|symbol| {
+                let symbol = CString::new(symbol).unwrap();
+                display.get_proc_address(symbol.as_c_str()) as *const _
+            })
+        };
+        log::info!("Headless GL context on {}", gl.get_string(gl::RENDERER));
+
+        let context_info =
+            query_context_info(gl.as_ref(), GlApi::Gles, display.version_string(), &config);
+        log::info!("GL context: {context_info:?}");
+        if settings.require_robustness && !context_info.robust_access {
+            return Err("The GL context has no robust buffer access".into());
+        }
+
+        let offscreen = OffscreenFramebuffer::create(gl.as_ref(), size);
+        Ok(Self {
+            context: PossiblyCurrentContext::Egl(context),
+            size: Cell::new(size),
+            gl,
+            present_mode: Cell::new(PresentMode::Fifo),
+            context_info,
+            settings,
+            offscreen: Some(offscreen),
+        })
+    }
+
+    /// Create a context like this one in place of it once it was lost, with a surface for
+    /// `window`, see [`crate::renderer_restart`].
+    pub fn recreate(
+        &self,
+        window: &Window,
+    ) -> Result<(Self, Surface<WindowSurface>), Box<dyn std::error::Error>> {
+        if self.is_headless() {
+            return Err("Headless GL contexts aren't recreated".into());
+        }
+        let config = self.context.config();
+        let (context, surface) = Self::create(window, &config, self.size.get(), self.settings)?;
+        context.set_present_mode(&surface, self.present_mode.get());
+        Ok((context, surface))
+    }
+
+    /// Whether the context renders into an [`OffscreenFramebuffer`] instead of a window.
+    pub fn is_headless(&self) -> bool {
+        self.offscreen.is_some()
+    }
+
+    /// Make a headless context current with its offscreen framebuffer bound.
+    pub fn make_offscreen_current(&self) -> Result<(), crate::errors::Error> {
+        let Some(offscreen) = &self.offscreen else {
+            return Ok(());
+        };
+        #[cfg(target_os = "linux")]
+        if let PossiblyCurrentContext::Egl(context) = &self.context {
+            context.make_current_surfaceless()?;
+        }
+        offscreen.bind(self.gl.as_ref());
+        Ok(())
+    }
+
+    /// Resize the offscreen framebuffer of a headless context.
+    pub fn resize_offscreen(&self, size: PhysicalSize<u32>) {
+        if let Some(offscreen) = &self.offscreen {
+            self.size.set(size);
+            offscreen.resize(self.gl.as_ref(), size);
+        }
+    }
+
+    /// Read the last frame of a headless context, `None` if the context has a window.
+    pub fn read_offscreen_pixels(&self) -> Option<image::RgbaImage> {
+        self.offscreen
+            .as_ref()
+            .and_then(|offscreen| offscreen.read_pixels(self.gl.as_ref()))
+    }
+
     /// Create a surface based on provided window.
     pub fn create_surface(
         &self,
@@ -130,6 +576,52 @@ impl RenderingContext {
         unsafe { Ok(config.display().create_window_surface(&config, &attrs)?) }
     }
 
+    /// Set the presentation mode of a surface, returns the mode that is actually in effect.
+    ///
+    /// Falls back to [`PresentMode::Fifo`] for [`PresentMode::Mailbox`], which GL can't do,
+    /// and if the platform refuses a non-blocking swap interval.
+    pub fn set_present_mode(
+        &self,
+        surface: &Surface<impl SurfaceTypeTrait>,
+        present_mode: PresentMode,
+    ) -> PresentMode {
+        let fifo = SwapInterval::Wait(NonZeroU32::new(1).unwrap());
+        let Some(interval) = swap_interval(present_mode) else {
+            log::warn!("Present mode {present_mode:?} isn't supported with GL, using Fifo");
+            if let Err(e) = surface.set_swap_interval(&self.context, fifo) {
+                log::error!("Error setting vsync: {e:?}");
+            }
+            self.present_mode.set(PresentMode::Fifo);
+            return PresentMode::Fifo;
+        };
+        let effective = match surface.set_swap_interval(&self.context, interval) {
+            Ok(()) => present_mode,
+            Err(e) if present_mode != PresentMode::Fifo => {
+                log::warn!("Present mode {present_mode:?} isn't supported ({e:?}), using Fifo");
+                if let Err(e) = surface.set_swap_interval(&self.context, fifo) {
+                    log::error!("Error setting vsync: {e:?}");
+                }
+                PresentMode::Fifo
+            }
+            Err(e) => {
+                log::error!("Error setting vsync: {e:?}");
+                PresentMode::Fifo
+            }
+        };
+        self.present_mode.set(effective);
+        effective
+    }
+
+    /// Apply the current presentation mode to a newly created surface.
+    pub fn apply_present_mode(&self, surface: &Surface<impl SurfaceTypeTrait>) {
+        self.set_present_mode(surface, self.present_mode.get());
+    }
+
+    /// Get the presentation mode in effect.
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode.get()
+    }
+
     /// Make GL context current.
     pub fn make_gl_context_current(
         &self,
@@ -164,6 +656,86 @@ impl RenderingContext {
         Ok(())
     }
 
+    /// Present the surface with `damage`, the region that changed since the last frame, in
+    /// device pixels from the top left corner.
+    ///
+    /// Uses `EGL_KHR_swap_buffers_with_damage` so the system compositor and the display only
+    /// update the damage, and falls back to [`Self::present`] where it isn't available.
+    pub fn present_with_damage(
+        &self,
+        surface: &Surface<impl SurfaceTypeTrait>,
+        damage: &[DeviceIntRect],
+    ) -> Result<(), crate::errors::Error> {
+        #[cfg(target_os = "linux")]
+        if let (Surface::Egl(egl_surface), PossiblyCurrentContext::Egl(context)) =
+            (surface, &self.context)
+        {
+            self.context.make_current(surface)?;
+            // EGL rects start from the bottom left corner.
+            let height = self.size.get().height as i32;
+            let rects: Vec<_> = damage
+                .iter()
+                .filter(|rect| !rect.is_empty())
+                .map(|rect| {
+                    glutin::surface::Rect::new(
+                        rect.min.x,
+                        height - rect.max.y,
+                        rect.width(),
+                        rect.height(),
+                    )
+                })
+                .collect();
+            // An empty list damages the whole surface.
+            if egl_surface
+                .swap_buffers_with_damage(context, &rects)
+                .is_ok()
+            {
+                return Ok(());
+            }
+        }
+        let _ = damage;
+        self.present(surface)
+    }
+
+    /// Age of the back buffer of the surface in frames, 0 if its content is undefined or the
+    /// age is unknown, so WebRender knows which regions it has to redraw.
+    pub fn buffer_age(&self, surface: &Surface<impl SurfaceTypeTrait>) -> usize {
+        surface.buffer_age() as usize
+    }
+
+    /// Get the attributes this context was created with.
+    pub fn context_info(&self) -> &GlContextInfo {
+        &self.context_info
+    }
+
+    /// Get the GPU this context currently renders on.
+    pub fn gpu_identity(&self) -> GpuIdentity {
+        GpuIdentity::query(self.gl.as_ref())
+    }
+
+    /// Ask which GPU a context created now for `window` would render on.
+    ///
+    /// A context keeps reporting the GPU it was created on, so a throwaway one is created for
+    /// the window and made current without a surface. No context is current afterwards.
+    pub fn probe_gpu_identity(
+        &self,
+        window: &Window,
+    ) -> Result<GpuIdentity, Box<dyn std::error::Error>> {
+        if self.is_headless() {
+            return Err("Headless GL contexts don't move between GPUs".into());
+        }
+        let config = self.context.config();
+        let raw_window_handle = window.window_handle().ok().map(|handle| handle.as_raw());
+        let attributes = ContextAttributesBuilder::new()
+            .with_context_api(self.context.context_api())
+            .build(raw_window_handle);
+        let probe = unsafe { config.display().create_context(&config, &attributes)? }
+            .make_current_surfaceless()?;
+        let gpu_identity = GpuIdentity::query(self.gl.as_ref());
+        probe.make_not_current()?;
+        Ok(gpu_identity)
+    }
+
     /// Get the current size of this [`RenderingContext`].
     pub fn size(&self) -> PhysicalSize<u32> {
         self.size.get()
@@ -189,6 +761,11 @@ impl RenderingContext {
     pub fn gl_rc(&self) -> Rc<dyn gl::Gl> {
         self.gl.clone()
     }
+
+    /// Get the glutin context, to reach the platform's GL interface.
+    pub(crate) fn context(&self) -> &PossiblyCurrentContext {
+        &self.context
+    }
 }
 
 /// WebGL rendering support implementation for RenderingContext
@@ -199,25 +776,126 @@ impl crate::webgl_support::WebGLRenderingSupport for RenderingContext {
     }
 }
 
-/// Find the config with the maximum number of samples, so our triangle will be
-/// smooth.
-pub fn gl_config_picker(configs: Box<dyn Iterator<Item = Config> + '_>) -> Config {
-    configs
-        .reduce(|accum, config| {
-            let transparency_check = config.supports_transparency().unwrap_or(false)
-                & !accum.supports_transparency().unwrap_or(false);
+/// Template of the window configs, letting through the configs deeper color depths need.
+/// Transparent windows need a config the OS compositor blends with per-pixel alpha.
+pub fn gl_config_template(
+    color_depth: OutputColorDepth,
+    transparent: bool,
+) -> ConfigTemplateBuilder {
+    let template = ConfigTemplateBuilder::new().with_transparency(transparent);
+    match color_depth {
+        OutputColorDepth::Standard => template.with_alpha_size(8),
+        // 10-bit configs only have 2 bits of alpha.
+        OutputColorDepth::Auto | OutputColorDepth::Rgb10 => template.with_alpha_size(2),
+        OutputColorDepth::Float16 => template.with_alpha_size(2).with_float_pixels(true),
+    }
+}
 
-            if transparency_check || config.num_samples() > accum.num_samples() {
-                config
-            } else {
-                accum
-            }
-        })
-        .unwrap()
+/// Find the config closest to the color depth, then with the maximum number of
+/// samples, so our triangle will be smooth.
+pub fn gl_config_picker(
+    color_depth: OutputColorDepth,
+) -> impl FnOnce(Box<dyn Iterator<Item = Config> + '_>) -> Config {
+    move |configs| {
+        configs
+            .reduce(|accum, config| {
+                let rank = |config: &Config| {
+                    color_depth_rank(color_depth, color_bits(config), config.float_pixels())
+                };
+                if rank(&config) != rank(&accum) {
+                    return if rank(&config) > rank(&accum) {
+                        config
+                    } else {
+                        accum
+                    };
+                }
+
+                let transparency_check = config.supports_transparency().unwrap_or(false)
+                    & !accum.supports_transparency().unwrap_or(false);
+
+                if transparency_check || config.num_samples() > accum.num_samples() {
+                    config
+                } else {
+                    accum
+                }
+            })
+            .unwrap()
+    }
+}
+
+/// Bits of the red channel of a config.
+fn color_bits(config: &Config) -> u8 {
+    match config.color_buffer_type() {
+        Some(ColorBufferType::Rgb { r_size, .. }) => r_size,
+        Some(ColorBufferType::Luminance(size)) => size,
+        None => 0,
+    }
+}
+
+/// How well a config matches the color depth, higher is better. Deeper configs
+/// than 8 bits are only preferred when the color depth asks for them.
+fn color_depth_rank(color_depth: OutputColorDepth, color_bits: u8, float_pixels: bool) -> u8 {
+    match (color_depth, color_bits, float_pixels) {
+        (OutputColorDepth::Float16, 16, true) => 3,
+        (_, 8, false) => 1,
+        (OutputColorDepth::Standard, _, _) => 0,
+        (_, 10, false) => 2,
+        _ => 0,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     // Integration tests would require a GL context which is not available in unit tests.
     // See tests/ directory for integration tests.
+    use super::*;
+
+    #[test]
+    fn test_present_mode_from_str() {
+        assert_eq!(parse_present_mode("vsync"), Some(PresentMode::Fifo));
+        assert_eq!(parse_present_mode("Mailbox"), Some(PresentMode::Mailbox));
+        assert_eq!(parse_present_mode("tearing"), Some(PresentMode::Immediate));
+        assert_eq!(parse_present_mode("triple"), None);
+        assert_eq!(PresentMode::default(), PresentMode::Fifo);
+    }
+
+    #[test]
+    fn test_mailbox_has_no_swap_interval() {
+        assert!(swap_interval(PresentMode::Mailbox).is_none());
+        assert!(matches!(
+            swap_interval(PresentMode::Immediate),
+            Some(SwapInterval::DontWait)
+        ));
+    }
+
+    #[test]
+    fn test_parse_gl_version() {
+        assert_eq!(parse_gl_version("4.6 (Core Profile) Mesa 24.0.5"), (4, 6));
+        assert_eq!(parse_gl_version("3.3.0 NVIDIA 550.54"), (3, 3));
+        assert_eq!(parse_gl_version("OpenGL ES 3.2 Mesa 24.0.5"), (3, 2));
+        assert_eq!(
+            parse_gl_version("OpenGL ES 3.0 (ANGLE 2.1.0 git hash: 1234)"),
+            (3, 0)
+        );
+        assert_eq!(parse_gl_version("garbage"), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_platform() {
+        assert_eq!(parse_platform("EGL 1.5"), Some(GlPlatform::Egl));
+        assert_eq!(parse_platform("GLX 1.4"), Some(GlPlatform::Glx));
+        assert_eq!(parse_platform("WGL"), Some(GlPlatform::Wgl));
+        assert_eq!(parse_platform("Apple CGL"), Some(GlPlatform::Cgl));
+        assert_eq!(parse_platform(""), None);
+    }
+
+    #[test]
+    fn test_color_depth_rank() {
+        use OutputColorDepth::*;
+        assert!(color_depth_rank(Standard, 8, false) > color_depth_rank(Standard, 10, false));
+        assert!(color_depth_rank(Auto, 10, false) > color_depth_rank(Auto, 8, false));
+        assert!(color_depth_rank(Rgb10, 8, false) > color_depth_rank(Rgb10, 16, true));
+        assert!(color_depth_rank(Float16, 16, true) > color_depth_rank(Float16, 10, false));
+        assert!(color_depth_rank(Float16, 10, false) > color_depth_rank(Float16, 8, false));
+    }
 }
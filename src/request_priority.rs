@@ -0,0 +1,444 @@
+//! Request Priority
+//!
+//! Orders the requests of a page by how much rendering waits on them:
+//! render-blocking documents, style sheets, fonts and scripts first, then
+//! images in or near the viewport, then images below the fold, then prefetches.
+//!
+//! Servo schedules its fetches itself, so the prioritizer works in the web
+//! resource interception path. Servo doesn't tell the embedder when a request
+//! it loads finishes, so nothing is held for a guessed completion time: the
+//! requests of a burst are let through together, most urgent first, and only
+//! images wait, until their page answered where its images are. The page is
+//! asked without blocking the event loop, and asked again after the viewport
+//! moves (scrolling, keyboard navigation, touch or a resize), so held images
+//! move up or down as the viewport does. Held requests are all let through
+//! once their page finished loading, or after [`MAX_DELAY`] at the latest.
+//!
+//! When the page finishes loading, the time its first viewport took to load is
+//! measured from its resource timings and logged, to compare with the
+//! prioritizer turned off.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use base::id::WebViewId;
+use embedder_traits::{WebResourceRequest, WebResourceResponseMsg};
+use ipc_channel::ipc::IpcSender;
+use url::Url;
+
+/// Longest time an image waits for its page to say where its images are
+pub const MAX_DELAY: Duration = Duration::from_millis(500);
+
+/// Shortest time between two viewport queries of a page
+const VIEWPORT_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returns the URLs of the images within half a screen of the viewport as a JSON array
+pub const VIEWPORT_IMAGES_SCRIPT: &str = r#"
+(() => {
+    const margin = innerHeight / 2;
+    const urls = [];
+    for (const image of document.images) {
+        const rect = image.getBoundingClientRect();
+        if (rect.bottom >= -margin && rect.top <= innerHeight + margin
+            && rect.right >= 0 && rect.left <= innerWidth) {
+            urls.push(image.currentSrc || image.src);
+        }
+    }
+    return JSON.stringify(urls);
+})()
+"#;
+
+/// Returns when the style sheets, fonts and viewport images of the page finished loading, in milliseconds
+pub const FIRST_VIEWPORT_COMPLETE_SCRIPT: &str = r#"
+(() => {
+    const margin = innerHeight / 2;
+    const viewport = new Set();
+    for (const image of document.images) {
+        const rect = image.getBoundingClientRect();
+        if (rect.bottom >= -margin && rect.top <= innerHeight + margin) {
+            viewport.add(image.currentSrc || image.src);
+        }
+    }
+    let complete = 0;
+    for (const entry of performance.getEntriesByType("resource")) {
+        if (["css", "link", "font"].includes(entry.initiatorType) || viewport.has(entry.name)) {
+            complete = Math.max(complete, entry.responseEnd);
+        }
+    }
+    return Math.round(complete);
+})()
+"#;
+
+/// How much rendering waits on a request, most urgent first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    /// Documents, style sheets, fonts and scripts
+    RenderBlocking,
+    /// Images near the viewport, and data fetched by scripts
+    Viewport,
+    /// Images and media away from the viewport
+    BelowFold,
+    /// Speculative loads
+    Prefetch,
+}
+
+/// Priority of a request, `viewport_images` are the image URLs near the viewport
+pub fn classify(request: &WebResourceRequest, viewport_images: &HashSet<Url>) -> RequestPriority {
+    let header = |name: &str| {
+        request
+            .headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    if [header("sec-purpose"), header("purpose")]
+        .into_iter()
+        .flatten()
+        .any(|purpose| purpose.contains("prefetch"))
+    {
+        return RequestPriority::Prefetch;
+    }
+    if request.is_for_main_frame {
+        return RequestPriority::RenderBlocking;
+    }
+    match header("sec-fetch-dest") {
+        Some("document" | "iframe" | "frame" | "style" | "font" | "script") => {
+            RequestPriority::RenderBlocking
+        }
+        Some("image") if viewport_images.contains(&request.url) => RequestPriority::Viewport,
+        Some("image" | "video" | "audio" | "track") => RequestPriority::BelowFold,
+        _ => RequestPriority::Viewport,
+    }
+}
+
+/// Requests let through by the prioritizer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PriorityStats {
+    /// Requests let through, by [`RequestPriority`]
+    pub released: [u64; 4],
+    /// Requests held before being let through
+    pub held: u64,
+    /// Total time requests were held
+    pub total_delay: Duration,
+    /// Pages whose first viewport was measured
+    pub pages: u64,
+    /// Total time those pages took to load their first viewport
+    pub total_first_viewport: Duration,
+}
+
+struct Pending {
+    webview_id: WebViewId,
+    request: WebResourceRequest,
+    sender: IpcSender<WebResourceResponseMsg>,
+    priority: RequestPriority,
+    queued_at: Instant,
+}
+
+#[derive(Default)]
+struct PageState {
+    viewport_images: HashSet<Url>,
+    viewport_stale: bool,
+    /// A viewport query was sent and not answered yet
+    viewport_querying: bool,
+    viewport_queried: Option<Instant>,
+}
+
+impl PageState {
+    /// Whether the images of the page must wait for where its viewport is
+    fn awaits_viewport(&self) -> bool {
+        self.viewport_stale || self.viewport_querying
+    }
+}
+
+fn is_image(priority: RequestPriority) -> bool {
+    matches!(
+        priority,
+        RequestPriority::Viewport | RequestPriority::BelowFold
+    )
+}
+
+/// Holds the requests of the webviews of a window until their turn
+#[derive(Default)]
+pub struct RequestScheduler {
+    enabled: bool,
+    pending: Vec<Pending>,
+    pages: HashMap<WebViewId, PageState>,
+    stats: PriorityStats,
+}
+
+impl RequestScheduler {
+    /// Create a scheduler, a disabled one lets every request through
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    /// Whether requests are prioritized
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Let the request through now or hold it until its turn
+    pub fn submit(
+        &mut self,
+        webview_id: WebViewId,
+        request: WebResourceRequest,
+        sender: IpcSender<WebResourceResponseMsg>,
+        now: Instant,
+    ) {
+        let page = self.pages.entry(webview_id).or_default();
+        let priority = classify(&request, &page.viewport_images);
+        if priority == RequestPriority::BelowFold {
+            page.viewport_stale = true;
+        }
+        let pending = Pending {
+            webview_id,
+            request,
+            sender,
+            priority,
+            queued_at: now,
+        };
+        // Let through on the next release, after the more urgent requests of the same burst.
+        self.pending.push(pending);
+    }
+
+    fn may_release(&self, pending: &Pending, now: Instant) -> bool {
+        if !self.enabled || now >= pending.queued_at + MAX_DELAY {
+            return true;
+        }
+        !is_image(pending.priority)
+            || self
+                .pages
+                .get(&pending.webview_id)
+                .is_none_or(|page| !page.awaits_viewport())
+    }
+
+    fn release(&mut self, pending: Pending, now: Instant) {
+        self.stats.released[pending.priority as usize] += 1;
+        if now > pending.queued_at {
+            self.stats.held += 1;
+            self.stats.total_delay += now - pending.queued_at;
+        }
+        let _ = pending.sender.send(WebResourceResponseMsg::DoNotIntercept);
+    }
+
+    /// Let through the held requests whose turn came, most urgent first
+    pub fn release_due(&mut self, now: Instant) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.pending.sort_by_key(|pending| pending.priority);
+        let mut index = 0;
+        while index < self.pending.len() {
+            if self.may_release(&self.pending[index], now) {
+                let pending = self.pending.remove(index);
+                self.release(pending, now);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// When a held request's turn comes at the latest, answers of the pages wake the event loop
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending
+            .iter()
+            .map(|pending| pending.queued_at + MAX_DELAY)
+            .min()
+    }
+
+    /// Webviews with images held whose viewport should be asked again
+    pub fn stale_viewports(&self, now: Instant) -> Vec<WebViewId> {
+        self.pages
+            .iter()
+            .filter(|(_, page)| {
+                page.viewport_stale
+                    && !page.viewport_querying
+                    && page
+                        .viewport_queried
+                        .is_none_or(|queried| now >= queried + VIEWPORT_REFRESH_INTERVAL)
+            })
+            .map(|(webview_id, _)| *webview_id)
+            .filter(|webview_id| {
+                self.pending
+                    .iter()
+                    .any(|pending| pending.webview_id == *webview_id && is_image(pending.priority))
+            })
+            .collect()
+    }
+
+    /// The page was asked where its images are
+    pub fn viewport_query_sent(&mut self, webview_id: WebViewId, now: Instant) {
+        let page = self.pages.entry(webview_id).or_default();
+        page.viewport_stale = false;
+        page.viewport_querying = true;
+        page.viewport_queried = Some(now);
+    }
+
+    /// Update the images near the viewport of a webview, moving its held images up or down
+    pub fn set_viewport_images(&mut self, webview_id: WebViewId, images: HashSet<Url>) {
+        for pending in self
+            .pending
+            .iter_mut()
+            .filter(|pending| pending.webview_id == webview_id)
+        {
+            if is_image(pending.priority) {
+                pending.priority = classify(&pending.request, &images);
+            }
+        }
+        let page = self.pages.entry(webview_id).or_default();
+        page.viewport_images = images;
+        page.viewport_querying = false;
+    }
+
+    /// The page couldn't answer, its images are let through in the order they came
+    pub fn viewport_query_failed(&mut self, webview_id: WebViewId) {
+        if let Some(page) = self.pages.get_mut(&webview_id) {
+            page.viewport_querying = false;
+        }
+    }
+
+    /// The viewports moved, held images are reclassified on the next release
+    pub fn viewport_changed(&mut self) {
+        for page in self.pages.values_mut() {
+            page.viewport_stale = true;
+        }
+    }
+
+    /// A webview navigated, forget the viewport of its previous page
+    pub fn page_started(&mut self, webview_id: WebViewId) {
+        let page = self.pages.entry(webview_id).or_default();
+        page.viewport_images.clear();
+        page.viewport_querying = false;
+        page.viewport_queried = None;
+    }
+
+    /// A webview's page finished loading, let its held requests through
+    pub fn page_loaded(&mut self, webview_id: WebViewId, now: Instant) {
+        let (ready, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| pending.webview_id == webview_id);
+        self.pending = pending;
+        for pending in ready {
+            self.release(pending, now);
+        }
+    }
+
+    /// Forget a closed webview, letting its held requests through for Servo to drop
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.page_loaded(webview_id, Instant::now());
+        self.pages.remove(&webview_id);
+    }
+
+    /// Record how long a page took to load its first viewport
+    pub fn record_first_viewport(&mut self, duration: Duration) {
+        self.stats.pages += 1;
+        self.stats.total_first_viewport += duration;
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> PriorityStats {
+        self.stats
+    }
+}
+
+/// Parse the answer of [`VIEWPORT_IMAGES_SCRIPT`]
+pub fn parse_viewport_images(report: &str) -> HashSet<Url> {
+    serde_json::from_str::<Vec<String>>(report)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|url| Url::parse(url).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    use super::*;
+
+    fn request(url: &str, destination: &str) -> WebResourceRequest {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("sec-fetch-dest", destination.parse().unwrap());
+        WebResourceRequest {
+            method: http::Method::GET,
+            headers,
+            url: Url::parse(url).unwrap(),
+            is_for_main_frame: false,
+            is_redirect: false,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let viewport = HashSet::from([Url::parse("https://a.example/hero.png").unwrap()]);
+        let priority = |url, destination| classify(&request(url, destination), &viewport);
+        assert_eq!(
+            priority("https://a.example/site.css", "style"),
+            RequestPriority::RenderBlocking
+        );
+        assert_eq!(
+            priority("https://a.example/hero.png", "image"),
+            RequestPriority::Viewport
+        );
+        assert_eq!(
+            priority("https://a.example/footer.png", "image"),
+            RequestPriority::BelowFold
+        );
+
+        let mut prefetch = request("https://a.example/next.html", "document");
+        prefetch
+            .headers
+            .insert("sec-purpose", "prefetch".parse().unwrap());
+        assert_eq!(classify(&prefetch, &viewport), RequestPriority::Prefetch);
+
+        assert_eq!(
+            parse_viewport_images(r#"["https://a.example/hero.png", ""]"#),
+            viewport
+        );
+    }
+
+    #[test]
+    fn test_images_wait_for_viewport() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let mut scheduler = RequestScheduler::new(true);
+        let now = Instant::now();
+        let (image_sender, image_receiver) = ipc_channel::ipc::channel().unwrap();
+        scheduler.submit(
+            webview_id,
+            request("https://a.example/hero.png", "image"),
+            image_sender,
+            now,
+        );
+        let (style_sender, style_receiver) = ipc_channel::ipc::channel().unwrap();
+        scheduler.submit(
+            webview_id,
+            request("https://a.example/site.css", "style"),
+            style_sender,
+            now,
+        );
+        assert_eq!(scheduler.stale_viewports(now), vec![webview_id]);
+        scheduler.viewport_query_sent(webview_id, now);
+
+        scheduler.release_due(now);
+        assert!(style_receiver.try_recv().is_ok());
+        assert!(image_receiver.try_recv().is_err());
+        assert_eq!(scheduler.next_deadline(), Some(now + MAX_DELAY));
+
+        scheduler.set_viewport_images(
+            webview_id,
+            HashSet::from([Url::parse("https://a.example/hero.png").unwrap()]),
+        );
+        scheduler.release_due(now);
+        assert!(image_receiver.try_recv().is_ok());
+        assert_eq!(scheduler.next_deadline(), None);
+        assert_eq!(
+            scheduler.stats().released[RequestPriority::Viewport as usize],
+            1
+        );
+    }
+}
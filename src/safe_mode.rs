@@ -0,0 +1,245 @@
+//! Safe Mode
+//!
+//! Keeps versoview usable when something in its startup keeps crashing it. A
+//! marker file is written to the config directory when versoview starts and
+//! removed once the first frame is composited, so finding it at startup means
+//! the previous startup never got that far. After
+//! [`SafeModeSettings::crash_threshold`] such startups in a row, the next one
+//! runs in safe mode: WebGL 2 and WebGPU, hardware media decoding, shader
+//! precompilation, renderer option overrides and user scripts are turned off.
+//! Safe mode lasts until a startup succeeds, then the following one is normal
+//! again.
+//!
+//! The marker also records which subsystem was starting, so the report can
+//! name the one implicated. Only the subsystems versoview starts itself before
+//! its first frame can be: the media backend and the renderer compiling its
+//! shaders. WebGL contexts are created by pages and user scripts run in pages,
+//! so a crash while loading the first page implicates nothing. Verso keeps no
+//! shader program cache on disk, turning the shader cache off only skips the
+//! precompilation. A startup that was killed, or that lost power, counts as a
+//! crash too.
+
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use versoview_messages::{SafeModeReport, SafeModeSettings, SafeModeSubsystem};
+
+use crate::{config::Config, renderer_options::RendererOptionsConfig, storage::Storage};
+
+/// Name of the startup marker in the config directory
+const MARKER_FILE: &str = "startup.json";
+
+/// Everything safe mode disables
+const DISABLED_SUBSYSTEMS: [SafeModeSubsystem; 4] = [
+    SafeModeSubsystem::WebGl,
+    SafeModeSubsystem::HardwareMediaDecode,
+    SafeModeSubsystem::ShaderCache,
+    SafeModeSubsystem::ConfigOverrides,
+];
+
+/// Content of the startup marker
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+struct StartupMarker {
+    /// Startups in a row that didn't finish, including the current one
+    attempts: u32,
+    /// Subsystem being started
+    stage: Option<SafeModeSubsystem>,
+}
+
+/// Startup crash tracking and safe mode of this run
+#[derive(Debug, Default)]
+pub struct SafeMode {
+    marker_path: Option<PathBuf>,
+    marker: StartupMarker,
+    report: Option<SafeModeReport>,
+    started: bool,
+}
+
+impl SafeMode {
+    /// Start tracking this startup, with the marker in Verso's config directory
+    pub fn begin(settings: &SafeModeSettings) -> Self {
+        let marker_path = Storage::config_dir().map(|dir| dir.join(MARKER_FILE));
+        Self::begin_with_marker(settings, marker_path)
+    }
+
+    /// Start tracking this startup with the marker at `marker_path`, and decide whether it runs in safe mode
+    pub fn begin_with_marker(settings: &SafeModeSettings, marker_path: Option<PathBuf>) -> Self {
+        if !settings.enabled && !settings.force {
+            return Self::default();
+        }
+
+        let previous = marker_path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|marker| serde_json::from_slice::<StartupMarker>(&marker).ok())
+            .unwrap_or_default();
+        let crashed = settings.enabled && previous.attempts >= settings.crash_threshold.max(1);
+        let report = (crashed || settings.force).then(|| SafeModeReport {
+            crashed_startups: previous.attempts,
+            disabled: DISABLED_SUBSYSTEMS.to_vec(),
+            implicated: previous.stage,
+            forced: !crashed,
+        });
+        if let Some(report) = &report {
+            log::warn!(
+                "Starting in safe mode after {} crashed startups, implicated: {:?}",
+                report.crashed_startups,
+                report.implicated
+            );
+        }
+
+        let safe_mode = Self {
+            marker_path,
+            marker: StartupMarker {
+                attempts: previous.attempts + 1,
+                stage: None,
+            },
+            report,
+            started: false,
+        };
+        safe_mode.write_marker();
+        safe_mode
+    }
+
+    /// Whether this run is in safe mode
+    pub fn is_active(&self) -> bool {
+        self.report.is_some()
+    }
+
+    /// Safe mode report of this run, `None` if it isn't in safe mode
+    pub fn report(&self) -> Option<&SafeModeReport> {
+        self.report.as_ref()
+    }
+
+    /// Whether the startup is still in progress
+    pub fn is_starting(&self) -> bool {
+        !self.started
+    }
+
+    /// Remove the renderer option overrides and user scripts from `config` in safe mode
+    pub fn apply_to_config(&self, config: &mut Config) {
+        if self.is_active() {
+            config.renderer_options = RendererOptionsConfig::default();
+            config.user_scripts.clear();
+        }
+    }
+
+    /// Turn off WebGL 2, WebGPU, hardware media decoding and shader precompilation in safe mode, call after [`Config::init`]
+    pub fn apply_to_preferences(&self) {
+        if !self.is_active() {
+            return;
+        }
+        let mut preferences = servo_config::prefs::get().clone();
+        preferences.dom_webgl2_enabled = false;
+        preferences.dom_webgpu_enabled = false;
+        preferences.media_glvideo_enabled = false;
+        preferences.gfx_precache_shaders = false;
+        servo_config::prefs::set(preferences);
+    }
+
+    /// Record that `subsystem` is starting, or that none is if `None`
+    pub fn starting(&mut self, subsystem: Option<SafeModeSubsystem>) {
+        if self.started || self.marker.stage == subsystem {
+            return;
+        }
+        self.marker.stage = subsystem;
+        self.write_marker();
+    }
+
+    /// Record that the startup finished, returns the report to send the embedder if this run is in safe mode
+    pub fn startup_succeeded(&mut self) -> Option<&SafeModeReport> {
+        if self.started {
+            return None;
+        }
+        self.finish_startup();
+        if let Some(report) = &self.report {
+            log::warn!(
+                "Safe mode startup succeeded, implicated subsystem: {:?}",
+                report.implicated
+            );
+        }
+        self.report.as_ref()
+    }
+
+    /// Record that versoview exits cleanly, a startup cut short by the user isn't a crash
+    pub fn exiting(&mut self) {
+        if !self.started {
+            self.finish_startup();
+        }
+    }
+
+    fn finish_startup(&mut self) {
+        self.started = true;
+        if let Some(path) = &self.marker_path {
+            if let Err(e) = fs::remove_file(path) {
+                log::error!("Failed to remove startup marker {}: {e}", path.display());
+            }
+        }
+    }
+
+    fn write_marker(&self) {
+        let Some(path) = &self.marker_path else {
+            return;
+        };
+        let result = serde_json::to_vec(&self.marker)
+            .map_err(|e| e.to_string())
+            .and_then(|marker| fs::write(path, marker).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::error!("Failed to write startup marker {}: {e}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_mode_after_crashed_startups() {
+        let path =
+            std::env::temp_dir().join(format!("verso-safe-mode-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let settings = SafeModeSettings::default();
+        let begin = || SafeMode::begin_with_marker(&settings, Some(path.clone()));
+
+        // Two startups crash, the first while compiling shaders and the second while starting media.
+        let mut safe_mode = begin();
+        assert!(!safe_mode.is_active());
+        safe_mode.starting(Some(SafeModeSubsystem::ShaderCache));
+        let mut safe_mode = begin();
+        assert!(!safe_mode.is_active());
+        safe_mode.starting(Some(SafeModeSubsystem::HardwareMediaDecode));
+
+        let mut safe_mode = begin();
+        let report = safe_mode.report().unwrap();
+        assert_eq!(report.crashed_startups, 2);
+        assert_eq!(
+            report.implicated,
+            Some(SafeModeSubsystem::HardwareMediaDecode)
+        );
+        assert!(!report.forced);
+        assert!(safe_mode.startup_succeeded().is_some());
+        assert!(safe_mode.startup_succeeded().is_none());
+        assert!(!path.exists());
+
+        assert!(!begin().is_active());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_forced_and_disabled_safe_mode() {
+        let forced = SafeModeSettings {
+            force: true,
+            ..Default::default()
+        };
+        let report = SafeMode::begin_with_marker(&forced, None).report.unwrap();
+        assert!(report.forced);
+        assert_eq!(report.implicated, None);
+
+        let disabled = SafeModeSettings {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(!SafeMode::begin_with_marker(&disabled, None).is_active());
+    }
+}
@@ -0,0 +1,236 @@
+//! Screenshots
+//!
+//! Captures the next composited frame of a webview for the controller. A
+//! capture is queued on the compositor with the webview's rect and taken from
+//! the frame right after it is rendered, cropped to the rect and encoded.
+//!
+//! A full page capture first resizes the webview to the height of its page.
+//! The compositor then renders into an offscreen framebuffer as large as the
+//! resized webview instead of the window, once the webview sent a display list
+//! at its new size, and the webview is resized back after the capture. Pages
+//! taller than the largest framebuffer the GPU supports are cut there. The
+//! window shows the top of the resized scene until then.
+
+use std::io::Cursor;
+
+use base::id::WebViewId;
+use image::{ImageFormat, RgbaImage};
+use versoview_messages::{Screenshot, ScreenshotFormat, ScreenshotOptions};
+use webrender_api::units::{DevicePoint, DeviceRect, DeviceSize};
+
+/// Height of the page in CSS pixels, as a string
+pub const PAGE_HEIGHT_SCRIPT: &str = "String(Math.max(document.documentElement.scrollHeight, document.body ? document.body.scrollHeight : 0))";
+
+/// A capture waiting for its frame
+#[derive(Debug)]
+pub struct ScreenshotRequest {
+    /// Id of the controller's request
+    pub id: uuid::Uuid,
+    /// Webview captured
+    pub webview_id: WebViewId,
+    /// Rect of the webview in the frame, resized to the page for a full page capture
+    pub rect: DeviceRect,
+    /// Whether to capture the full page and how to encode the capture
+    pub options: ScreenshotOptions,
+    /// Rect the webview is resized back to after a full page capture
+    pub restore: Option<DeviceRect>,
+    /// Whether the webview sent a display list since the capture was queued
+    painted: bool,
+}
+
+impl ScreenshotRequest {
+    /// Capture the webview at `rect` in the next frame
+    pub fn new(id: uuid::Uuid, webview_id: WebViewId, rect: DeviceRect) -> Self {
+        Self {
+            id,
+            webview_id,
+            rect,
+            options: ScreenshotOptions::default(),
+            restore: None,
+            painted: false,
+        }
+    }
+
+    /// Capture the whole page, with the webview resized from `restore` to `rect`
+    pub fn full_page(mut self, rect: DeviceRect, restore: DeviceRect) -> Self {
+        self.rect = rect;
+        self.restore = Some(restore);
+        self.options.full_page = true;
+        self
+    }
+
+    /// Encode the capture as `format`
+    pub fn format(mut self, format: ScreenshotFormat) -> Self {
+        self.options.format = format;
+        self
+    }
+}
+
+/// Captures waiting for their frame and those taken
+#[derive(Debug, Default)]
+pub struct ScreenshotQueue {
+    pending: Vec<ScreenshotRequest>,
+    finished: Vec<(ScreenshotRequest, Result<Screenshot, String>)>,
+}
+
+impl ScreenshotQueue {
+    /// Queue a capture
+    pub fn push(&mut self, request: ScreenshotRequest) {
+        self.pending.push(request);
+    }
+
+    /// Record that the webview sent a display list
+    pub fn painted(&mut self, webview_id: WebViewId) {
+        for request in &mut self.pending {
+            if request.webview_id == webview_id {
+                request.painted = true;
+            }
+        }
+    }
+
+    /// Size of the scene while a full page capture waits, covering the resized webviews
+    pub fn full_page_size(&self) -> Option<DeviceSize> {
+        self.pending
+            .iter()
+            .filter(|request| request.options.full_page)
+            .map(|request| request.rect.max.to_vector().to_size())
+            .reduce(|size, other| size.max(other))
+    }
+
+    /// Whether a full page capture has its webview painted at the new size
+    pub fn full_page_due(&self) -> bool {
+        self.pending
+            .iter()
+            .any(|request| request.options.full_page && request.painted)
+    }
+
+    /// Take the captures the frame just rendered answers, the full page ones or the others
+    pub fn take_due(&mut self, full_page: bool) -> Vec<ScreenshotRequest> {
+        let (due, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|request| {
+                request.options.full_page == full_page && (request.painted || !full_page)
+            });
+        self.pending = pending;
+        due
+    }
+
+    /// Record the capture taken for a request
+    pub fn finish(&mut self, request: ScreenshotRequest, result: Result<Screenshot, String>) {
+        self.finished.push((request, result));
+    }
+
+    /// Take the captures taken since the last call
+    pub fn take_finished(&mut self) -> Vec<(ScreenshotRequest, Result<Screenshot, String>)> {
+        std::mem::take(&mut self.finished)
+    }
+}
+
+/// Rect of a webview at `rect` resized to a page `page_height` CSS pixels tall
+///
+/// The rect is cut so it fits in a framebuffer of `max_size` pixels.
+pub fn full_page_rect(
+    rect: DeviceRect,
+    page_height: f32,
+    device_pixels_per_css_pixel: f32,
+    max_size: i32,
+) -> DeviceRect {
+    let max_size = max_size as f32;
+    let height = (page_height * device_pixels_per_css_pixel)
+        .ceil()
+        .max(rect.height())
+        .min(max_size - rect.min.y);
+    let width = rect.width().min(max_size - rect.min.x);
+    DeviceRect::from_origin_and_size(rect.min, DeviceSize::new(width, height))
+}
+
+/// Crop the webview at `rect` from a frame and encode it
+pub fn capture(
+    frame: &RgbaImage,
+    rect: DeviceRect,
+    format: ScreenshotFormat,
+) -> Result<Screenshot, String> {
+    let frame_rect =
+        DeviceRect::from_size(DeviceSize::new(frame.width() as f32, frame.height() as f32));
+    let rect = rect
+        .round_out()
+        .intersection(&frame_rect)
+        .filter(|rect| !rect.is_empty())
+        .ok_or("the webview isn't in the frame")?;
+    let DevicePoint { x, y, .. } = rect.min;
+    let (width, height) = (rect.width() as u32, rect.height() as u32);
+    let image = image::imageops::crop_imm(frame, x as u32, y as u32, width, height).to_image();
+    let data = match format {
+        ScreenshotFormat::Png => {
+            let mut png = Cursor::new(Vec::new());
+            image
+                .write_to(&mut png, ImageFormat::Png)
+                .map_err(|error| format!("the capture can't be encoded: {error}"))?;
+            png.into_inner()
+        }
+        ScreenshotFormat::Rgba => image.into_raw(),
+    };
+    Ok(Screenshot {
+        width,
+        height,
+        format,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let mut frame = RgbaImage::new(8, 6);
+        frame.put_pixel(3, 2, image::Rgba([255, 0, 0, 255]));
+        let rect = DeviceRect::new(DevicePoint::new(2.5, 2.0), DevicePoint::new(20.0, 4.0));
+
+        let screenshot = capture(&frame, rect, ScreenshotFormat::Rgba).unwrap();
+        assert_eq!((screenshot.width, screenshot.height), (6, 2));
+        assert_eq!(&screenshot.data[4..8], &[255, 0, 0, 255]);
+
+        let png = capture(&frame, rect, ScreenshotFormat::Png).unwrap();
+        let decoded = image::load_from_memory(&png.data).unwrap().to_rgba8();
+        assert_eq!(decoded.into_raw(), screenshot.data);
+
+        let outside = DeviceRect::new(DevicePoint::new(10.0, 0.0), DevicePoint::new(20.0, 4.0));
+        assert!(capture(&frame, outside, ScreenshotFormat::Png).is_err());
+    }
+
+    #[test]
+    fn test_queue() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let rect = DeviceRect::new(DevicePoint::new(0.0, 80.0), DevicePoint::new(800.0, 600.0));
+        let page = full_page_rect(rect, 2000.0, 2.0, 4096);
+        assert_eq!(
+            page,
+            DeviceRect::new(DevicePoint::new(0.0, 80.0), DevicePoint::new(800.0, 4080.0))
+        );
+
+        let mut queue = ScreenshotQueue::default();
+        queue.push(ScreenshotRequest::new(
+            uuid::Uuid::new_v4(),
+            webview_id,
+            rect,
+        ));
+        queue.push(
+            ScreenshotRequest::new(uuid::Uuid::new_v4(), webview_id, rect).full_page(page, rect),
+        );
+        assert_eq!(queue.full_page_size(), Some(DeviceSize::new(800.0, 4080.0)));
+        assert!(!queue.full_page_due());
+        assert!(queue.take_due(true).is_empty());
+        assert_eq!(queue.take_due(false).len(), 1);
+
+        queue.painted(webview_id);
+        assert!(queue.full_page_due());
+        let due = queue.take_due(true);
+        assert_eq!(due[0].restore, Some(rect));
+        assert_eq!(queue.full_page_size(), None);
+    }
+}
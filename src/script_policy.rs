@@ -0,0 +1,247 @@
+//! Script Engine Policy
+//!
+//! Limits on what scripts may do, for deployments that trade speed for a
+//! smaller attack surface: WebAssembly, the JIT tiers of SpiderMonkey and the
+//! number of workers a page runs.
+//!
+//! The profile's JIT tiers and WebAssembly switch are applied through Servo's
+//! preferences, which every content process reads when its pipelines are
+//! created, so they hold for all pages, workers included. JIT tiers can't
+//! differ between origins since a content process has one engine for all of
+//! them.
+//!
+//! Servo has no preference for the worker count or per-origin WebAssembly, so
+//! those limits are advisory: a document start user script, built from the site
+//! settings loaded at startup, hides `WebAssembly` and wraps `Worker`. Policies
+//! changed afterwards are sent to the page without waiting when its head is
+//! parsed, and can only tighten what the startup script enforces. A page can
+//! get around the script, e.g. through a fresh frame's globals, and it doesn't
+//! run in workers, so the limits are a guard against accidental use rather than
+//! a security boundary.
+
+use embedder_traits::user_content_manager::UserScript;
+use servo_config::prefs::Preferences;
+use url::Url;
+use versoview_messages::{JitPolicy, ScriptPolicy, SitePolicy};
+
+use crate::site_settings::SiteSettings;
+
+/// Enforces a policy in a page, `POLICY` is an expression evaluating to the policy or `null`
+///
+/// Applying it again updates the worker limit, WebAssembly stays removed once it is.
+const ENFORCE_SCRIPT_POLICY_SCRIPT: &str = r#"
+(() => {
+    const policy = POLICY;
+    if (!policy) {
+        return;
+    }
+    if (!policy.wasm) {
+        delete globalThis.WebAssembly;
+    }
+    if (window.__versoWorkerLimit) {
+        window.__versoWorkerLimit.max = policy.maxWorkers;
+        return;
+    }
+    if (policy.maxWorkers === null || !window.Worker) {
+        return;
+    }
+    const limit = { max: policy.maxWorkers, live: 0 };
+    Object.defineProperty(window, "__versoWorkerLimit", { value: limit });
+    const NativeWorker = window.Worker;
+    window.Worker = class Worker extends NativeWorker {
+        #live = true;
+        constructor(...args) {
+            if (limit.max !== null && limit.live >= limit.max) {
+                throw new DOMException(
+                    `At most ${limit.max} workers may run at once`,
+                    "QuotaExceededError"
+                );
+            }
+            super(...args);
+            limit.live += 1;
+        }
+        terminate() {
+            if (this.#live) {
+                this.#live = false;
+                limit.live -= 1;
+            }
+            super.terminate();
+        }
+    };
+})();
+"#;
+
+/// Set the Servo preferences enforcing the profile's policy
+pub fn apply_preferences(policy: &ScriptPolicy, preferences: &mut Preferences) {
+    let baseline = policy.jit != JitPolicy::InterpreterOnly;
+    let optimizing = policy.jit == JitPolicy::Full;
+
+    // WebAssembly has no interpreter, it needs one of its compilers.
+    preferences.js_wasm_enabled = policy.wasm_enabled && baseline;
+    preferences.js_asmjs_enabled = optimizing;
+    preferences.js_baseline_interpreter_enabled = baseline;
+    preferences.js_baseline_jit_enabled = baseline;
+    preferences.js_ion_enabled = optimizing;
+    preferences.js_native_regex_enabled = baseline;
+    preferences.js_wasm_baseline_enabled = policy.wasm_enabled && baseline;
+    preferences.js_wasm_ion_enabled = policy.wasm_enabled && optimizing;
+}
+
+/// Parse a JIT policy as given on the command line
+pub fn parse_jit_policy(jit: &str) -> Option<JitPolicy> {
+    match jit.trim().to_ascii_lowercase().as_str() {
+        "full" => Some(JitPolicy::Full),
+        "baseline" => Some(JitPolicy::BaselineOnly),
+        "interpreter" => Some(JitPolicy::InterpreterOnly),
+        _ => None,
+    }
+}
+
+/// Policy in effect for pages of `url`, the profile's tightened by the origin's
+pub fn effective_policy(
+    profile: &ScriptPolicy,
+    site_settings: &SiteSettings,
+    url: &Url,
+) -> ScriptPolicy {
+    tighten(profile, &site_settings.policy_for(url))
+}
+
+fn tighten(profile: &ScriptPolicy, site: &SitePolicy) -> ScriptPolicy {
+    ScriptPolicy {
+        wasm_enabled: profile.wasm_enabled && site.wasm_enabled != Some(false),
+        jit: profile.jit,
+        max_workers: match (profile.max_workers, site.max_workers) {
+            (Some(profile), Some(site)) => Some(profile.min(site)),
+            (profile, site) => profile.or(site),
+        },
+    }
+}
+
+/// Document start script enforcing the policy of every origin, `None` if nothing needs enforcing
+pub fn user_script(profile: &ScriptPolicy, site_settings: &SiteSettings) -> Option<UserScript> {
+    let default = tighten(profile, &site_settings.default_policy);
+    let overrides: serde_json::Map<_, _> = site_settings
+        .overrides
+        .iter()
+        .map(|(origin, site)| (origin.clone(), policy_json(&tighten(profile, site))))
+        .collect();
+    if overrides.values().all(serde_json::Value::is_null) && !needs_script(&default) {
+        return None;
+    }
+
+    let expression = format!(
+        "(overrides => Object.hasOwn(overrides, location.origin) ? overrides[location.origin] : {1})({0})",
+        serde_json::Value::Object(overrides),
+        policy_json(&default)
+    );
+    Some(UserScript {
+        script: ENFORCE_SCRIPT_POLICY_SCRIPT.replace("POLICY", &expression),
+        source_file: None,
+    })
+}
+
+/// Script enforcing `policy` in a page that already started loading, `None` if nothing needs enforcing
+pub fn page_script(policy: &ScriptPolicy) -> Option<String> {
+    needs_script(policy)
+        .then(|| ENFORCE_SCRIPT_POLICY_SCRIPT.replace("POLICY", &policy_json(policy).to_string()))
+}
+
+/// Whether the page has to enforce part of the policy, WebAssembly off for the profile is already enforced by the engine
+fn needs_script(policy: &ScriptPolicy) -> bool {
+    !policy.wasm_enabled || policy.max_workers.is_some()
+}
+
+fn policy_json(policy: &ScriptPolicy) -> serde_json::Value {
+    if !needs_script(policy) {
+        return serde_json::Value::Null;
+    }
+    serde_json::json!({
+        "wasm": policy.wasm_enabled,
+        "maxWorkers": policy.max_workers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_site_policy_only_tightens() {
+        let profile = ScriptPolicy {
+            wasm_enabled: false,
+            jit: JitPolicy::BaselineOnly,
+            max_workers: Some(4),
+        };
+        let mut site_settings = SiteSettings::default();
+        site_settings.set_override(
+            "https://kiosk.example",
+            Some(SitePolicy {
+                wasm_enabled: Some(true),
+                max_workers: Some(1),
+                ..Default::default()
+            }),
+        );
+
+        let policy = effective_policy(
+            &profile,
+            &site_settings,
+            &Url::parse("https://kiosk.example/app").unwrap(),
+        );
+        assert_eq!(
+            policy,
+            ScriptPolicy {
+                wasm_enabled: false,
+                jit: JitPolicy::BaselineOnly,
+                max_workers: Some(1),
+            }
+        );
+        assert_eq!(
+            effective_policy(
+                &ScriptPolicy::default(),
+                &site_settings,
+                &Url::parse("https://other.example/").unwrap()
+            ),
+            ScriptPolicy::default()
+        );
+    }
+
+    #[test]
+    fn test_scripts() {
+        let site_settings = SiteSettings::default();
+        assert!(user_script(&ScriptPolicy::default(), &site_settings).is_none());
+        assert!(page_script(&ScriptPolicy::default()).is_none());
+
+        let profile = ScriptPolicy {
+            max_workers: Some(2),
+            ..Default::default()
+        };
+        let script = user_script(&profile, &site_settings).unwrap().script;
+        assert!(script.contains(": {"));
+        assert!(script.contains(")({})"));
+        assert!(script.contains(r#""maxWorkers":2"#));
+        assert!(!script.contains("POLICY"));
+    }
+
+    #[test]
+    fn test_interpreter_only_preferences() {
+        assert_eq!(
+            parse_jit_policy("Interpreter"),
+            Some(JitPolicy::InterpreterOnly)
+        );
+        assert_eq!(parse_jit_policy("ion"), None);
+
+        let mut preferences = Preferences::default();
+        apply_preferences(
+            &ScriptPolicy {
+                jit: JitPolicy::InterpreterOnly,
+                ..Default::default()
+            },
+            &mut preferences,
+        );
+        assert!(!preferences.js_wasm_enabled);
+        assert!(!preferences.js_baseline_jit_enabled);
+        assert!(!preferences.js_ion_enabled);
+        assert!(!preferences.js_wasm_baseline_enabled);
+        assert!(!preferences.js_native_regex_enabled);
+    }
+}
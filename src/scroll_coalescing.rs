@@ -45,8 +45,8 @@ impl CoalescedScrollEvent {
     /// Returns true if coalescing was successful, false if events should be separate
     pub fn try_coalesce(&mut self, delta: Vector2D<f32>, cursor: DeviceIntPoint) -> bool {
         // Don't coalesce if cursor moved significantly (different scroll target)
-        let cursor_distance = ((self.cursor.x - cursor.x).pow(2)
-            + (self.cursor.y - cursor.y).pow(2)) as f32;
+        let cursor_distance =
+            ((self.cursor.x - cursor.x).pow(2) + (self.cursor.y - cursor.y).pow(2)) as f32;
         if cursor_distance > 100.0 {
             // 10px threshold
             return false;
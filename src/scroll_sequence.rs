@@ -0,0 +1,219 @@
+//! Scroll Sequencing
+//!
+//! Orders the scrolls and zooms of the compositor so user input and script
+//! scrolls are merged the same way every time. Every scroll or zoom event the
+//! compositor queues gets the next [`ScrollSequenceId`]. A batch of coalesced
+//! events keeps the id of its last event, and the id is the generation of the
+//! scroll offsets WebRender samples, so a frame tells which input it shows.
+//!
+//! A scroll the compositor applies for the user stays unacknowledged until the
+//! script thread of its pipeline has the new offset. Servo doesn't answer the
+//! `SetScrollStates` message carrying it, so a scroll is acknowledged once its
+//! pipeline sends a display list, or after [`ACK_TIMEOUT`]. A script scroll
+//! (`scrollTo` and friends) arriving while scrolls of its node are
+//! unacknowledged was computed from an offset without them: they are reapplied
+//! on top of the script's offset, until acknowledged, instead of the script
+//! jumping the page back during a fling.
+//!
+//! A display list already on its way when the offsets were sent acknowledges
+//! them too early, and the next script scroll of the node isn't merged.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use base::id::PipelineId;
+use webrender_api::ExternalScrollId;
+use webrender_api::units::LayoutVector2D;
+
+/// Time after which the script thread is taken to have the offset of a scroll
+pub const ACK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Position of a scroll or zoom event in the order the compositor received them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScrollSequenceId(pub u64);
+
+/// A user scroll the script thread may not have seen
+#[derive(Clone, Copy, Debug)]
+struct UnacknowledgedScroll {
+    sequence: ScrollSequenceId,
+    /// Change of the scroll node's offset
+    delta: LayoutVector2D,
+    /// When the offset was last sent to the script thread
+    sent: Instant,
+}
+
+/// Hands out sequence ids and merges script scrolls with unacknowledged user scrolls
+#[derive(Debug, Default)]
+pub struct ScrollSequencer {
+    last: ScrollSequenceId,
+    sampled: ScrollSequenceId,
+    unacknowledged: HashMap<(PipelineId, ExternalScrollId), Vec<UnacknowledgedScroll>>,
+}
+
+impl ScrollSequencer {
+    /// Id of the next scroll or zoom event
+    pub fn next(&mut self) -> ScrollSequenceId {
+        self.last.0 += 1;
+        self.last
+    }
+
+    /// Last scroll or zoom applied to WebRender
+    pub fn sampled(&self) -> ScrollSequenceId {
+        self.sampled
+    }
+
+    /// Record that the events up to `sequence` were applied to WebRender
+    pub fn set_sampled(&mut self, sequence: ScrollSequenceId) {
+        self.sampled = self.sampled.max(sequence);
+    }
+
+    /// Record a user scroll of the node, sent to the script thread at `now`
+    pub fn record_user_scroll(
+        &mut self,
+        pipeline_id: PipelineId,
+        scroll_id: ExternalScrollId,
+        sequence: ScrollSequenceId,
+        delta: LayoutVector2D,
+        now: Instant,
+    ) {
+        self.expire(now);
+        if delta == LayoutVector2D::zero() {
+            return;
+        }
+        self.unacknowledged
+            .entry((pipeline_id, scroll_id))
+            .or_default()
+            .push(UnacknowledgedScroll {
+                sequence,
+                delta,
+                sent: now,
+            });
+    }
+
+    /// Acknowledge the scrolls of the pipeline, call when it sends a display list
+    pub fn acknowledge(&mut self, pipeline_id: PipelineId) {
+        self.unacknowledged
+            .retain(|(scrolled_pipeline, _), _| *scrolled_pipeline != pipeline_id);
+    }
+
+    /// Merge a script scroll of the node to `offset` with the user scrolls the script didn't see
+    ///
+    /// Returns the offset to apply and the id of the script scroll. The merged offset must be
+    /// sent to the script thread when it differs from `offset`, and the scrolls stay
+    /// unacknowledged until the script thread has it.
+    pub fn merge_script_scroll(
+        &mut self,
+        pipeline_id: PipelineId,
+        scroll_id: ExternalScrollId,
+        offset: LayoutVector2D,
+        now: Instant,
+    ) -> (LayoutVector2D, ScrollSequenceId) {
+        self.expire(now);
+        let sequence = self.next();
+        let Some(scrolls) = self.unacknowledged.get_mut(&(pipeline_id, scroll_id)) else {
+            return (offset, sequence);
+        };
+        let mut merged = offset;
+        for scroll in scrolls.iter_mut() {
+            merged += scroll.delta;
+            scroll.sent = now;
+        }
+        log::trace!(
+            "Merged script scroll {sequence:?} with user scrolls {:?}",
+            scrolls
+                .iter()
+                .map(|scroll| scroll.sequence)
+                .collect::<Vec<_>>()
+        );
+        (merged, sequence)
+    }
+
+    /// Acknowledge the scrolls sent longer than [`ACK_TIMEOUT`] ago
+    fn expire(&mut self, now: Instant) {
+        self.unacknowledged.retain(|_, scrolls| {
+            scrolls.retain(|scroll| now.duration_since(scroll.sent) < ACK_TIMEOUT);
+            !scrolls.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+    use webrender_api::PipelineId as WebRenderPipelineId;
+
+    #[test]
+    fn test_merge_script_scroll() {
+        PipelineNamespace::install(PipelineNamespaceId(4));
+        let pipeline_id = PipelineId::new();
+        let scroll_id = ExternalScrollId(1, WebRenderPipelineId(0, 1));
+        let start = Instant::now();
+        let mut sequencer = ScrollSequencer::default();
+
+        let first = sequencer.next();
+        sequencer.record_user_scroll(
+            pipeline_id,
+            scroll_id,
+            first,
+            LayoutVector2D::new(0.0, 40.0),
+            start,
+        );
+        let second = sequencer.next();
+        assert!(second > first);
+        sequencer.record_user_scroll(
+            pipeline_id,
+            scroll_id,
+            second,
+            LayoutVector2D::new(0.0, 20.0),
+            start,
+        );
+        sequencer.set_sampled(second);
+        sequencer.set_sampled(first);
+        assert_eq!(sequencer.sampled(), second);
+
+        // The script scrolls from the offset it had before the user scrolls, twice before it
+        // gets the merged offset.
+        let (merged, sequence) = sequencer.merge_script_scroll(
+            pipeline_id,
+            scroll_id,
+            LayoutVector2D::new(0.0, 100.0),
+            start,
+        );
+        assert_eq!(merged, LayoutVector2D::new(0.0, 160.0));
+        assert!(sequence > second);
+        let (merged, _) = sequencer.merge_script_scroll(
+            pipeline_id,
+            scroll_id,
+            LayoutVector2D::new(0.0, 101.0),
+            start + ACK_TIMEOUT / 2,
+        );
+        assert_eq!(merged, LayoutVector2D::new(0.0, 161.0));
+
+        // Acknowledged by timeout, counted from the merged offset.
+        let (merged, _) = sequencer.merge_script_scroll(
+            pipeline_id,
+            scroll_id,
+            LayoutVector2D::new(0.0, 162.0),
+            start + ACK_TIMEOUT * 3 / 2,
+        );
+        assert_eq!(merged, LayoutVector2D::new(0.0, 162.0));
+
+        // Acknowledged by a display list.
+        let now = start + ACK_TIMEOUT * 2;
+        let third = sequencer.next();
+        sequencer.record_user_scroll(
+            pipeline_id,
+            scroll_id,
+            third,
+            LayoutVector2D::new(0.0, 10.0),
+            now,
+        );
+        sequencer.acknowledge(pipeline_id);
+        let (merged, _) =
+            sequencer.merge_script_scroll(pipeline_id, scroll_id, LayoutVector2D::zero(), now);
+        assert_eq!(merged, LayoutVector2D::zero());
+    }
+}
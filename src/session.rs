@@ -0,0 +1,131 @@
+//! Session Manifest
+//!
+//! Builds the whole layout at startup from a [`SessionManifest`]: windows with
+//! their position, size and zoom, and the webviews each window opens as its
+//! tab group, with their network context and content settings. Kiosk windows
+//! are fullscreen without panel or decorations. The current layout can be
+//! exported back to the same format, from the controller or to a file on exit.
+//!
+//! Webviews are opened in order and, like tabs opened by hand, the last one is
+//! shown. Exported webviews carry the URL they show now, their history isn't
+//! kept.
+
+use std::path::Path;
+
+use versoview_messages::{SessionManifest, SessionWindow};
+use winit::window::{Fullscreen, WindowAttributes};
+
+/// Read a session manifest from a JSON file
+pub fn load(path: &Path) -> Result<SessionManifest, String> {
+    let manifest = std::fs::read_to_string(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    parse(&manifest).map_err(|error| format!("invalid session {}: {error}", path.display()))
+}
+
+/// Parse a session manifest, windows without webviews are an error
+pub fn parse(manifest: &str) -> Result<SessionManifest, String> {
+    let manifest: SessionManifest =
+        serde_json::from_str(manifest).map_err(|error| error.to_string())?;
+    if let Some(index) = manifest
+        .windows
+        .iter()
+        .position(|window| window.webviews.is_empty())
+    {
+        return Err(format!("window {index} has no webviews"));
+    }
+    Ok(manifest)
+}
+
+/// Write a session manifest to a JSON file
+pub fn save(path: &Path, manifest: &SessionManifest) -> Result<(), String> {
+    let manifest = serde_json::to_string_pretty(manifest).map_err(|error| error.to_string())?;
+    std::fs::write(path, manifest)
+        .map_err(|error| format!("failed to write {}: {error}", path.display()))
+}
+
+/// Attributes of a session window, on top of the attributes of the default window
+pub fn window_attributes(base: &WindowAttributes, window: &SessionWindow) -> WindowAttributes {
+    let mut attributes = base.clone().with_maximized(window.maximized);
+    if let Some(position) = window.position {
+        attributes = attributes.with_position(position);
+    }
+    if let Some(size) = window.size {
+        attributes = attributes.with_inner_size(size);
+    }
+    if window.fullscreen || window.kiosk {
+        attributes = attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    } else {
+        attributes = attributes.with_fullscreen(None);
+    }
+    if window.kiosk {
+        attributes = attributes.with_decorations(false);
+    }
+    attributes
+}
+
+/// Whether a session window shows the control panel
+pub fn has_panel(window: &SessionWindow) -> bool {
+    window.panel && !window.kiosk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "windows": [
+            {
+                "kiosk": true,
+                "zoom": 1.5,
+                "webviews": [
+                    { "url": "https://kiosk.example/dashboard" },
+                    {
+                        "url": "https://kiosk.example/admin",
+                        "network_context": "vpn",
+                        "content_settings": { "javascript_enabled": false, "author_styles_enabled": true }
+                    }
+                ]
+            },
+            {
+                "position": { "x": 1920, "y": 0 },
+                "size": { "width": 1280, "height": 720 },
+                "webviews": [{ "url": "https://example.com/" }]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse() {
+        let manifest = parse(MANIFEST).unwrap();
+        assert_eq!(manifest.windows.len(), 2);
+        let kiosk = &manifest.windows[0];
+        assert!(!has_panel(kiosk));
+        assert_eq!(kiosk.zoom, Some(1.5));
+        assert_eq!(kiosk.webviews[1].network_context.as_deref(), Some("vpn"));
+        assert!(
+            !kiosk.webviews[1]
+                .content_settings
+                .unwrap()
+                .javascript_enabled
+        );
+        assert!(has_panel(&manifest.windows[1]));
+        assert_eq!(manifest.windows[1].size.map(|size| size.width), Some(1280));
+
+        let exported = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(parse(&exported).unwrap(), manifest);
+
+        assert!(parse(r#"{ "windows": [{ "webviews": [] }] }"#).is_err());
+    }
+
+    #[test]
+    fn test_kiosk_attributes() {
+        let manifest = parse(MANIFEST).unwrap();
+        let base = WindowAttributes::default();
+        let kiosk = window_attributes(&base, &manifest.windows[0]);
+        assert!(kiosk.fullscreen.is_some());
+        assert!(!kiosk.decorations);
+        let windowed = window_attributes(&base, &manifest.windows[1]);
+        assert!(windowed.fullscreen.is_none());
+        assert!(windowed.position.is_some());
+    }
+}
@@ -0,0 +1,738 @@
+//! Shared Textures
+//!
+//! With [`SharedTextureSettings`], the frames of a window are composited into a
+//! few textures exported to the controller instead of the window surface, so a
+//! host like a game engine or a Qt or Flutter app can draw them without copies.
+//! The textures' memory comes from the platform's API for sharing GPU memory
+//! between processes:
+//!
+//! - Linux: GL textures exported as DMA-BUF with `EGL_MESA_image_dma_buf_export`
+//! - macOS: IOSurfaces bound to rectangle textures with `CGLTexImageIOSurface2D`
+//! - Windows: D3D11 textures of ANGLE pbuffers, shared with
+//!   `EGL_ANGLE_surface_d3d_texture_2d_share_handle`
+//!
+//! Each frame goes into a texture the controller doesn't hold, and the
+//! controller holds it until it gives it back. When it holds all of them, frames
+//! wait. The textures are created again at the new size when the window is
+//! resized, which changes their handles.
+
+use std::time::SystemTime;
+
+use dpi::PhysicalSize;
+use euclid::Size2D;
+use gleam::gl;
+use versoview_messages::{SharedTextureFrame, SharedTextureSettings};
+use webrender_api::units::DevicePixel;
+use winit::window::WindowId;
+
+use crate::rendering::RenderingContext;
+
+/// Most textures a window composites into
+const MAX_BUFFERS: u32 = 8;
+
+/// A texture frames are composited into and its framebuffer
+struct SharedBuffer {
+    texture: gl::GLuint,
+    framebuffer: gl::GLuint,
+    export: platform::Export,
+}
+
+/// Textures the frames of a window are composited into, handed to the controller in turn
+pub struct SharedTextures {
+    window_id: WindowId,
+    count: u32,
+    size: Size2D<u32, DevicePixel>,
+    buffers: Vec<SharedBuffer>,
+    /// Whether the controller holds the texture of each buffer
+    held: Vec<bool>,
+    /// Depth and stencil buffer shared by the framebuffers, 0 if there are none
+    depth_stencil: gl::GLuint,
+    /// Buffer composited into last
+    last: usize,
+    frames_composited: u64,
+    frames: Vec<SharedTextureFrame>,
+}
+
+impl SharedTextures {
+    /// Composite the frames of the window into textures as set by `settings`, created for the
+    /// first frame
+    pub fn new(window_id: WindowId, settings: SharedTextureSettings) -> Self {
+        Self {
+            window_id,
+            count: settings.buffers.clamp(1, MAX_BUFFERS),
+            size: Size2D::zero(),
+            buffers: Vec::new(),
+            held: Vec::new(),
+            depth_stencil: 0,
+            last: 0,
+            frames_composited: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// The window whose frames are composited into the textures
+    pub fn window_id(&self) -> WindowId {
+        self.window_id
+    }
+
+    /// Bind the framebuffer of a texture the controller doesn't hold, returns its buffer or
+    /// `None` if it holds them all.
+    ///
+    /// The textures are created again if `size` changed.
+    pub fn begin_frame(
+        &mut self,
+        context: &RenderingContext,
+        size: Size2D<u32, DevicePixel>,
+    ) -> Result<Option<u32>, String> {
+        let gl = context.gl();
+        if size != self.size || self.buffers.is_empty() {
+            self.delete(gl.as_ref());
+            self.allocate(context, size)?;
+        }
+        let Some(buffer) = next_free_buffer(&self.held, self.last) else {
+            return Ok(None);
+        };
+        gl.bind_framebuffer(gl::FRAMEBUFFER, self.buffers[buffer].framebuffer);
+        gl.viewport(0, 0, size.width as i32, size.height as i32);
+        Ok(Some(buffer as u32))
+    }
+
+    /// Finish the frame composited into `buffer` for the controller, which holds the texture
+    /// until it's released.
+    pub fn end_frame(&mut self, gl: &dyn gl::Gl, buffer: u32) {
+        // The controller reads the texture with a context of its own, which can't wait on ours.
+        gl.finish();
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        let Some(shared) = self.buffers.get(buffer as usize) else {
+            return;
+        };
+        self.held[buffer as usize] = true;
+        self.last = buffer as usize;
+        self.frames_composited += 1;
+        self.frames.push(SharedTextureFrame {
+            buffer,
+            frame: self.frames_composited,
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            size: PhysicalSize::new(self.size.width, self.size.height),
+            handle: shared.export.handle(),
+        });
+    }
+
+    /// Composite into the texture of `buffer` again, the controller is done with it.
+    pub fn release(&mut self, buffer: u32) {
+        match self.held.get_mut(buffer as usize) {
+            Some(held) => *held = false,
+            None => log::warn!("There's no shared texture {buffer} to release"),
+        }
+    }
+
+    /// Take the frames composited since the last call.
+    pub fn take_frames(&mut self) -> Vec<SharedTextureFrame> {
+        std::mem::take(&mut self.frames)
+    }
+
+    /// Forget the textures of a lost GL context, they're created again for the next frame.
+    pub fn forget(&mut self) {
+        self.buffers.clear();
+        self.held.clear();
+        self.depth_stencil = 0;
+    }
+
+    /// Delete the textures and their framebuffers.
+    pub fn delete(&mut self, gl: &dyn gl::Gl) {
+        for SharedBuffer {
+            texture,
+            framebuffer,
+            export,
+        } in self.buffers.drain(..)
+        {
+            // The texture is detached from the shared memory first.
+            drop(export);
+            gl.delete_framebuffers(&[framebuffer]);
+            gl.delete_textures(&[texture]);
+        }
+        self.held.clear();
+        if self.depth_stencil != 0 {
+            gl.delete_renderbuffers(&[self.depth_stencil]);
+            self.depth_stencil = 0;
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        context: &RenderingContext,
+        size: Size2D<u32, DevicePixel>,
+    ) -> Result<(), String> {
+        let gl = context.gl();
+        self.size = size;
+        self.depth_stencil = gl.gen_renderbuffers(1)[0];
+        gl.bind_renderbuffer(gl::RENDERBUFFER, self.depth_stencil);
+        gl.renderbuffer_storage(
+            gl::RENDERBUFFER,
+            gl::DEPTH24_STENCIL8,
+            size.width as i32,
+            size.height as i32,
+        );
+        gl.bind_renderbuffer(gl::RENDERBUFFER, 0);
+
+        for _ in 0..self.count {
+            let texture = gl.gen_textures(1)[0];
+            gl.bind_texture(platform::TEXTURE_TARGET, texture);
+            gl.tex_parameter_i(
+                platform::TEXTURE_TARGET,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR as i32,
+            );
+            gl.tex_parameter_i(
+                platform::TEXTURE_TARGET,
+                gl::TEXTURE_MAG_FILTER,
+                gl::LINEAR as i32,
+            );
+            let export = platform::Export::new(context, texture, size);
+            gl.bind_texture(platform::TEXTURE_TARGET, 0);
+            let export = match export {
+                Ok(export) => export,
+                Err(error) => {
+                    gl.delete_textures(&[texture]);
+                    self.delete(gl.as_ref());
+                    return Err(error);
+                }
+            };
+
+            let framebuffer = gl.gen_framebuffers(1)[0];
+            gl.bind_framebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl.framebuffer_texture_2d(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                platform::TEXTURE_TARGET,
+                texture,
+                0,
+            );
+            for attachment in [gl::DEPTH_ATTACHMENT, gl::STENCIL_ATTACHMENT] {
+                gl.framebuffer_renderbuffer(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::RENDERBUFFER,
+                    self.depth_stencil,
+                );
+            }
+            let complete =
+                gl.check_frame_buffer_status(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE;
+            gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+            self.buffers.push(SharedBuffer {
+                texture,
+                framebuffer,
+                export,
+            });
+            self.held.push(false);
+            if !complete {
+                self.delete(gl.as_ref());
+                return Err("The framebuffer of a shared texture is incomplete".into());
+            }
+        }
+        // The first frame goes into the first buffer.
+        self.last = self.buffers.len() - 1;
+        log::info!(
+            "Compositing into {} shared textures of {}x{}",
+            self.buffers.len(),
+            size.width,
+            size.height
+        );
+        Ok(())
+    }
+}
+
+/// The buffer after `last` whose texture isn't held, round robin
+fn next_free_buffer(held: &[bool], last: usize) -> Option<usize> {
+    (1..=held.len())
+        .map(|offset| (last + offset) % held.len())
+        .find(|&buffer| !held[buffer])
+}
+
+/// Load an EGL function, an error if the driver doesn't have it
+#[cfg(any(linux, target_os = "windows"))]
+fn egl_function<F>(display: &glutin::display::Display, name: &std::ffi::CStr) -> Result<F, String> {
+    use glutin::prelude::GlDisplay;
+
+    let function = display.get_proc_address(name);
+    if function.is_null() {
+        return Err(format!("{name:?} isn't available"));
+    }
+    Ok(unsafe { std::mem::transmute_copy(&function) })
+}
+
+/// DMA-BUF export of GL textures through EGL
+#[cfg(linux)]
+mod platform {
+    use std::ffi::c_void;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    use euclid::Size2D;
+    use gleam::gl;
+    use glutin::context::{AsRawContext, RawContext};
+    use glutin::display::{AsRawDisplay, GetGlDisplay, RawDisplay};
+    use versoview_messages::{DmaBufPlane, SharedTextureHandle};
+    use webrender_api::units::DevicePixel;
+
+    use super::egl_function;
+    use crate::rendering::RenderingContext;
+
+    /// `EGL_GL_TEXTURE_2D_KHR`
+    const EGL_GL_TEXTURE_2D: u32 = 0x30B1;
+    /// `EGL_NONE`
+    const EGL_NONE: i32 = 0x3038;
+    /// Most memory planes of a DMA-BUF
+    const MAX_PLANES: usize = 4;
+
+    type CreateImage = unsafe extern "system" fn(
+        *const c_void,
+        *const c_void,
+        u32,
+        *mut c_void,
+        *const i32,
+    ) -> *const c_void;
+    type DestroyImage = unsafe extern "system" fn(*const c_void, *const c_void) -> u32;
+    type ExportQuery = unsafe extern "system" fn(
+        *const c_void,
+        *const c_void,
+        *mut i32,
+        *mut i32,
+        *mut u64,
+    ) -> u32;
+    type ExportImage = unsafe extern "system" fn(
+        *const c_void,
+        *const c_void,
+        *mut i32,
+        *mut i32,
+        *mut i32,
+    ) -> u32;
+
+    pub const TEXTURE_TARGET: gl::GLenum = gl::TEXTURE_2D;
+
+    /// The DMA-BUF of a texture
+    pub struct Export {
+        fourcc: u32,
+        modifier: u64,
+        /// File descriptor, offset and stride of each plane
+        planes: Vec<(OwnedFd, u32, u32)>,
+    }
+
+    impl Export {
+        /// Allocate the texture bound to [`TEXTURE_TARGET`] and export it
+        pub fn new(
+            context: &RenderingContext,
+            texture: gl::GLuint,
+            size: Size2D<u32, DevicePixel>,
+        ) -> Result<Self, String> {
+            context.gl().tex_image_2d(
+                TEXTURE_TARGET,
+                0,
+                gl::RGBA as i32,
+                size.width as i32,
+                size.height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                None,
+            );
+            let display = context.context().display();
+            let RawDisplay::Egl(egl_display) = display.raw_display() else {
+                return Err("DMA-BUF textures need an EGL display".into());
+            };
+            let RawContext::Egl(egl_context) = context.context().raw_context() else {
+                return Err("DMA-BUF textures need an EGL context".into());
+            };
+            let create_image: CreateImage = egl_function(&display, c"eglCreateImageKHR")?;
+            let destroy_image: DestroyImage = egl_function(&display, c"eglDestroyImageKHR")?;
+            let export_query: ExportQuery =
+                egl_function(&display, c"eglExportDMABUFImageQueryMESA")?;
+            let export_image: ExportImage = egl_function(&display, c"eglExportDMABUFImageMESA")?;
+
+            let attributes = [EGL_NONE];
+            let image = unsafe {
+                create_image(
+                    egl_display,
+                    egl_context,
+                    EGL_GL_TEXTURE_2D,
+                    texture as usize as *mut c_void,
+                    attributes.as_ptr(),
+                )
+            };
+            if image.is_null() {
+                return Err("eglCreateImageKHR failed".into());
+            }
+            let mut fourcc = 0;
+            let mut plane_count = 0;
+            let mut modifiers = [0; MAX_PLANES];
+            let mut fds = [-1; MAX_PLANES];
+            let mut strides = [0; MAX_PLANES];
+            let mut offsets = [0; MAX_PLANES];
+            let exported = unsafe {
+                export_query(
+                    egl_display,
+                    image,
+                    &mut fourcc,
+                    &mut plane_count,
+                    modifiers.as_mut_ptr(),
+                ) != 0
+                    && (1..=MAX_PLANES as i32).contains(&plane_count)
+                    && export_image(
+                        egl_display,
+                        image,
+                        fds.as_mut_ptr(),
+                        strides.as_mut_ptr(),
+                        offsets.as_mut_ptr(),
+                    ) != 0
+            };
+            // The DMA-BUF keeps the memory alive without the image.
+            unsafe { destroy_image(egl_display, image) };
+            if !exported {
+                return Err("eglExportDMABUFImageMESA failed".into());
+            }
+            let planes = (0..plane_count as usize)
+                .map(|plane| {
+                    let fd = unsafe { OwnedFd::from_raw_fd(fds[plane]) };
+                    (fd, offsets[plane] as u32, strides[plane] as u32)
+                })
+                .collect();
+            Ok(Self {
+                fourcc: fourcc as u32,
+                modifier: modifiers[0],
+                planes,
+            })
+        }
+
+        /// The handle the controller gets
+        pub fn handle(&self) -> SharedTextureHandle {
+            SharedTextureHandle::DmaBuf {
+                pid: std::process::id(),
+                fourcc: self.fourcc,
+                modifier: self.modifier,
+                planes: self
+                    .planes
+                    .iter()
+                    .map(|(fd, offset, stride)| DmaBufPlane {
+                        fd: fd.as_raw_fd(),
+                        offset: *offset,
+                        stride: *stride,
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+/// IOSurfaces bound to GL textures
+#[cfg(macos)]
+mod platform {
+    use std::ffi::c_void;
+
+    use euclid::Size2D;
+    use gleam::gl;
+    use versoview_messages::SharedTextureHandle;
+    use webrender_api::units::DevicePixel;
+
+    use crate::rendering::RenderingContext;
+
+    type CFTypeRef = *const c_void;
+
+    /// `kCFNumberSInt32Type`
+    const CF_NUMBER_SINT32_TYPE: isize = 3;
+    /// `'BGRA'`
+    const PIXEL_FORMAT_BGRA: i32 = 0x42475241;
+    /// `GL_TEXTURE_RECTANGLE`, the only target IOSurfaces bind to
+    const GL_TEXTURE_RECTANGLE: gl::GLenum = 0x84F5;
+    /// `GL_UNSIGNED_INT_8_8_8_8_REV`
+    const GL_UNSIGNED_INT_8_8_8_8_REV: gl::GLenum = 0x8367;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        static kCFTypeDictionaryKeyCallBacks: c_void;
+        static kCFTypeDictionaryValueCallBacks: c_void;
+        static kCFBooleanTrue: CFTypeRef;
+        fn CFDictionaryCreateMutable(
+            allocator: CFTypeRef,
+            capacity: isize,
+            key_callbacks: *const c_void,
+            value_callbacks: *const c_void,
+        ) -> CFTypeRef;
+        fn CFDictionarySetValue(dictionary: CFTypeRef, key: CFTypeRef, value: CFTypeRef);
+        fn CFNumberCreate(
+            allocator: CFTypeRef,
+            number_type: isize,
+            value: *const c_void,
+        ) -> CFTypeRef;
+        fn CFRelease(object: CFTypeRef);
+    }
+
+    #[link(name = "IOSurface", kind = "framework")]
+    unsafe extern "C" {
+        static kIOSurfaceWidth: CFTypeRef;
+        static kIOSurfaceHeight: CFTypeRef;
+        static kIOSurfaceBytesPerElement: CFTypeRef;
+        static kIOSurfacePixelFormat: CFTypeRef;
+        static kIOSurfaceIsGlobal: CFTypeRef;
+        fn IOSurfaceCreate(properties: CFTypeRef) -> CFTypeRef;
+        fn IOSurfaceGetID(surface: CFTypeRef) -> u32;
+    }
+
+    #[link(name = "OpenGL", kind = "framework")]
+    unsafe extern "C" {
+        fn CGLGetCurrentContext() -> *mut c_void;
+        fn CGLTexImageIOSurface2D(
+            context: *mut c_void,
+            target: gl::GLenum,
+            internal_format: gl::GLenum,
+            width: i32,
+            height: i32,
+            format: gl::GLenum,
+            data_type: gl::GLenum,
+            surface: CFTypeRef,
+            plane: u32,
+        ) -> i32;
+    }
+
+    pub const TEXTURE_TARGET: gl::GLenum = GL_TEXTURE_RECTANGLE;
+
+    /// The IOSurface of a texture
+    pub struct Export {
+        surface: CFTypeRef,
+    }
+
+    impl Export {
+        /// Create an IOSurface and bind it to the texture bound to [`TEXTURE_TARGET`]
+        pub fn new(
+            _context: &RenderingContext,
+            _texture: gl::GLuint,
+            size: Size2D<u32, DevicePixel>,
+        ) -> Result<Self, String> {
+            let (width, height) = (size.width as i32, size.height as i32);
+            let surface = unsafe {
+                let properties = CFDictionaryCreateMutable(
+                    std::ptr::null(),
+                    0,
+                    &kCFTypeDictionaryKeyCallBacks,
+                    &kCFTypeDictionaryValueCallBacks,
+                );
+                for (key, value) in [
+                    (kIOSurfaceWidth, width),
+                    (kIOSurfaceHeight, height),
+                    (kIOSurfaceBytesPerElement, 4),
+                    (kIOSurfacePixelFormat, PIXEL_FORMAT_BGRA),
+                ] {
+                    let number = CFNumberCreate(
+                        std::ptr::null(),
+                        CF_NUMBER_SINT32_TYPE,
+                        &value as *const i32 as *const c_void,
+                    );
+                    CFDictionarySetValue(properties, key, number);
+                    CFRelease(number);
+                }
+                // Other processes can only look up global surfaces by their id.
+                CFDictionarySetValue(properties, kIOSurfaceIsGlobal, kCFBooleanTrue);
+                let surface = IOSurfaceCreate(properties);
+                CFRelease(properties);
+                surface
+            };
+            if surface.is_null() {
+                return Err("IOSurfaceCreate failed".into());
+            }
+            let export = Self { surface };
+            let error = unsafe {
+                CGLTexImageIOSurface2D(
+                    CGLGetCurrentContext(),
+                    TEXTURE_TARGET,
+                    gl::RGBA,
+                    width,
+                    height,
+                    gl::BGRA,
+                    GL_UNSIGNED_INT_8_8_8_8_REV,
+                    export.surface,
+                    0,
+                )
+            };
+            if error != 0 {
+                return Err(format!("CGLTexImageIOSurface2D failed ({error})"));
+            }
+            Ok(export)
+        }
+
+        /// The handle the controller gets
+        pub fn handle(&self) -> SharedTextureHandle {
+            SharedTextureHandle::IoSurface(unsafe { IOSurfaceGetID(self.surface) })
+        }
+    }
+
+    impl Drop for Export {
+        fn drop(&mut self) {
+            unsafe { CFRelease(self.surface) };
+        }
+    }
+}
+
+/// ANGLE pbuffers of shared D3D11 textures bound to GL textures
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ffi::c_void;
+
+    use euclid::Size2D;
+    use gleam::gl;
+    use glutin::config::{AsRawConfig, GetGlConfig, RawConfig};
+    use glutin::display::{AsRawDisplay, GetGlDisplay, RawDisplay};
+    use versoview_messages::SharedTextureHandle;
+    use webrender_api::units::DevicePixel;
+
+    use super::egl_function;
+    use crate::rendering::RenderingContext;
+
+    const EGL_NONE: i32 = 0x3038;
+    const EGL_HEIGHT: i32 = 0x3056;
+    const EGL_WIDTH: i32 = 0x3057;
+    const EGL_TEXTURE_RGBA: i32 = 0x305E;
+    const EGL_TEXTURE_2D: i32 = 0x305F;
+    const EGL_TEXTURE_FORMAT: i32 = 0x3080;
+    const EGL_TEXTURE_TARGET: i32 = 0x3081;
+    const EGL_BACK_BUFFER: i32 = 0x3084;
+    const EGL_D3D_TEXTURE_2D_SHARE_HANDLE_ANGLE: i32 = 0x3200;
+
+    type CreatePbufferSurface =
+        unsafe extern "system" fn(*const c_void, *const c_void, *const i32) -> *const c_void;
+    type QuerySurfacePointer =
+        unsafe extern "system" fn(*const c_void, *const c_void, i32, *mut *mut c_void) -> u32;
+    type TexImage = unsafe extern "system" fn(*const c_void, *const c_void, i32) -> u32;
+    type DestroySurface = unsafe extern "system" fn(*const c_void, *const c_void) -> u32;
+
+    pub const TEXTURE_TARGET: gl::GLenum = gl::TEXTURE_2D;
+
+    /// The pbuffer of a texture and the share handle of its D3D11 texture
+    pub struct Export {
+        display: *const c_void,
+        surface: *const c_void,
+        share_handle: u64,
+        release_tex_image: TexImage,
+        destroy_surface: DestroySurface,
+    }
+
+    impl Export {
+        /// Create a pbuffer and bind it to the texture bound to [`TEXTURE_TARGET`]
+        pub fn new(
+            context: &RenderingContext,
+            _texture: gl::GLuint,
+            size: Size2D<u32, DevicePixel>,
+        ) -> Result<Self, String> {
+            let display = context.context().display();
+            let RawDisplay::Egl(egl_display) = display.raw_display() else {
+                return Err("Shared D3D11 textures need ANGLE's EGL display".into());
+            };
+            let RawConfig::Egl(egl_config) = context.context().config().raw_config() else {
+                return Err("Shared D3D11 textures need an EGL config".into());
+            };
+            let create_pbuffer_surface: CreatePbufferSurface =
+                egl_function(&display, c"eglCreatePbufferSurface")?;
+            let query_surface_pointer: QuerySurfacePointer =
+                egl_function(&display, c"eglQuerySurfacePointerANGLE")?;
+            let bind_tex_image: TexImage = egl_function(&display, c"eglBindTexImage")?;
+            let release_tex_image: TexImage = egl_function(&display, c"eglReleaseTexImage")?;
+            let destroy_surface: DestroySurface = egl_function(&display, c"eglDestroySurface")?;
+
+            let attributes = [
+                EGL_WIDTH,
+                size.width as i32,
+                EGL_HEIGHT,
+                size.height as i32,
+                EGL_TEXTURE_FORMAT,
+                EGL_TEXTURE_RGBA,
+                EGL_TEXTURE_TARGET,
+                EGL_TEXTURE_2D,
+                EGL_NONE,
+            ];
+            let surface =
+                unsafe { create_pbuffer_surface(egl_display, egl_config, attributes.as_ptr()) };
+            if surface.is_null() {
+                return Err("eglCreatePbufferSurface failed".into());
+            }
+            let mut share_handle = std::ptr::null_mut();
+            let queried = unsafe {
+                query_surface_pointer(
+                    egl_display,
+                    surface,
+                    EGL_D3D_TEXTURE_2D_SHARE_HANDLE_ANGLE,
+                    &mut share_handle,
+                ) != 0
+            };
+            if !queried || unsafe { bind_tex_image(egl_display, surface, EGL_BACK_BUFFER) } == 0 {
+                unsafe { destroy_surface(egl_display, surface) };
+                return Err("The pbuffer has no shared D3D11 texture".into());
+            }
+            Ok(Self {
+                display: egl_display,
+                surface,
+                share_handle: share_handle as u64,
+                release_tex_image,
+                destroy_surface,
+            })
+        }
+
+        /// The handle the controller gets
+        pub fn handle(&self) -> SharedTextureHandle {
+            SharedTextureHandle::Dxgi(self.share_handle)
+        }
+    }
+
+    impl Drop for Export {
+        fn drop(&mut self) {
+            unsafe {
+                (self.release_tex_image)(self.display, self.surface, EGL_BACK_BUFFER);
+                (self.destroy_surface)(self.display, self.surface);
+            }
+        }
+    }
+}
+
+/// No sharing API elsewhere
+#[cfg(not(any(linux, macos, target_os = "windows")))]
+mod platform {
+    use euclid::Size2D;
+    use gleam::gl;
+    use versoview_messages::SharedTextureHandle;
+    use webrender_api::units::DevicePixel;
+
+    use crate::rendering::RenderingContext;
+
+    pub const TEXTURE_TARGET: gl::GLenum = gl::TEXTURE_2D;
+
+    /// Never created
+    pub enum Export {}
+
+    impl Export {
+        pub fn new(
+            _context: &RenderingContext,
+            _texture: gl::GLuint,
+            _size: Size2D<u32, DevicePixel>,
+        ) -> Result<Self, String> {
+            Err("Shared textures aren't supported on this platform".into())
+        }
+
+        pub fn handle(&self) -> SharedTextureHandle {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_free_buffer() {
+        assert_eq!(next_free_buffer(&[false, false, false], 2), Some(0));
+        assert_eq!(next_free_buffer(&[true, false, false], 0), Some(1));
+        // Held textures are skipped, the last one is reused if it's the only one free.
+        assert_eq!(next_free_buffer(&[false, true, true], 0), Some(0));
+        assert_eq!(next_free_buffer(&[true, true, false], 0), Some(2));
+        assert_eq!(next_free_buffer(&[true, true, true], 1), None);
+        assert_eq!(next_free_buffer(&[], 0), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Shutdown Coordination
+//!
+//! Sequences a Verso shutdown through [`ShutdownPhase`]s: pages get their
+//! `beforeunload`, downloads and storage are flushed, then the constellation and
+//! finally WebRender are torn down. Every phase is reported to the controller
+//! once it listens to the progress. If the sequence doesn't finish before the
+//! deadline, the main loop tears the compositor down without waiting any longer.
+
+use std::time::{Duration, Instant};
+
+use ipc_channel::ipc::IpcSender;
+use versoview_messages::{ShutdownPhase, ShutdownProgress, ToControllerMessage};
+
+/// Shutdown timing configuration
+#[derive(Clone, Debug)]
+pub struct ShutdownConfig {
+    /// Time all pages together get to run their `beforeunload` handlers
+    pub beforeunload_timeout: Duration,
+    /// Time after which the teardown is forced
+    pub deadline: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            beforeunload_timeout: Duration::from_secs(1),
+            deadline: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Tracks the shutdown sequence and reports its progress
+#[derive(Debug)]
+pub struct ShutdownCoordinator {
+    config: ShutdownConfig,
+    started: Option<Instant>,
+    phase: Option<ShutdownPhase>,
+    /// Whether the progress is sent to the controller
+    pub report_progress: bool,
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator, nothing happens until [`Self::enter`] is called
+    pub fn new(config: ShutdownConfig) -> Self {
+        Self {
+            config,
+            started: None,
+            phase: None,
+            report_progress: false,
+        }
+    }
+
+    /// Whether a shutdown is in progress
+    pub fn is_started(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Phase the shutdown is in
+    pub fn phase(&self) -> Option<ShutdownPhase> {
+        self.phase
+    }
+
+    /// Deadline for the `beforeunload` phase
+    pub fn beforeunload_deadline(&self) -> Instant {
+        self.started.unwrap_or_else(Instant::now) + self.config.beforeunload_timeout
+    }
+
+    /// Time after which the main loop forces the teardown, if a shutdown is in progress
+    pub fn deadline(&self) -> Option<Instant> {
+        self.started.map(|started| started + self.config.deadline)
+    }
+
+    /// Whether the shutdown is still going past its deadline
+    pub fn is_overdue(&self, now: Instant) -> bool {
+        self.phase != Some(ShutdownPhase::Finished)
+            && self.deadline().is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Move to `phase`, the first call starts the shutdown
+    ///
+    /// Phases only move forward, entering an earlier or the current phase again is ignored.
+    pub fn enter(
+        &mut self,
+        phase: ShutdownPhase,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
+    ) {
+        if self.phase.is_some_and(|current| current >= phase) {
+            return;
+        }
+        let now = Instant::now();
+        let started = *self.started.get_or_insert(now);
+        self.phase = Some(phase);
+
+        let elapsed = now.duration_since(started);
+        let progress = ShutdownProgress {
+            phase,
+            elapsed_ms: elapsed.as_millis() as u64,
+            remaining_ms: self.config.deadline.saturating_sub(elapsed).as_millis() as u64,
+        };
+        log::debug!("Shutdown: {progress:?}");
+        if !self.report_progress {
+            return;
+        }
+        if let Some(to_controller_sender) = to_controller_sender {
+            if let Err(error) =
+                to_controller_sender.send(ToControllerMessage::OnShutdownProgress(progress))
+            {
+                log::warn!("Verso failed to send shutdown progress to controller: {error}");
+            }
+        }
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new(ShutdownConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_only_move_forward() {
+        let mut coordinator = ShutdownCoordinator::new(ShutdownConfig {
+            deadline: Duration::from_secs(3600),
+            ..Default::default()
+        });
+        assert!(!coordinator.is_started());
+
+        coordinator.enter(ShutdownPhase::FlushStorage, &None);
+        assert!(coordinator.is_started());
+        coordinator.enter(ShutdownPhase::BeforeUnload, &None);
+        assert_eq!(coordinator.phase(), Some(ShutdownPhase::FlushStorage));
+
+        coordinator.enter(ShutdownPhase::WebRender, &None);
+        assert_eq!(coordinator.phase(), Some(ShutdownPhase::WebRender));
+    }
+
+    #[test]
+    fn test_overdue_until_finished() {
+        let mut coordinator = ShutdownCoordinator::new(ShutdownConfig {
+            deadline: Duration::from_secs(1),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(!coordinator.is_overdue(now + Duration::from_secs(2)));
+
+        coordinator.enter(ShutdownPhase::BeforeUnload, &None);
+        assert!(!coordinator.is_overdue(now));
+        assert!(coordinator.is_overdue(now + Duration::from_secs(2)));
+
+        coordinator.enter(ShutdownPhase::Finished, &None);
+        assert!(!coordinator.is_overdue(now + Duration::from_secs(2)));
+    }
+}
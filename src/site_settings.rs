@@ -0,0 +1,227 @@
+//! Site Settings
+//!
+//! Per-origin security policy overrides, persisted next to the bookmarks. The
+//! policies decide how mixed content is handled, whether subresource integrity
+//! failures are reported and how scripts are limited, so operators can loosen
+//! or tighten them for the content they deploy without touching the rest of the
+//! web.
+
+use std::{collections::HashMap, fs::File, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use versoview_messages::{MixedContentPolicy, SitePolicy};
+
+/// Collects subresources carrying an `integrity` attribute that failed their check.
+///
+/// Servo doesn't tell the embedder about integrity failures, so the page records
+/// them in a global that [`TAKE_INTEGRITY_FAILURES_SCRIPT`] drains once loaded.
+/// An element failing to load is only counted when its resource was fetched with
+/// a successful status, so network errors and error statuses aren't reported.
+pub const INTEGRITY_FAILURE_LISTENER_SCRIPT: &str = r#"
+(() => {
+    if (window.__versoIntegrityFailures) {
+        return;
+    }
+    window.__versoIntegrityFailures = [];
+    const fetched = (url) => performance.getEntriesByName(url, "resource").some((entry) =>
+        entry.responseStatus === undefined
+            ? entry.responseEnd > 0 && entry.transferSize + entry.decodedBodySize > 0
+            : entry.responseStatus >= 200 && entry.responseStatus < 300);
+    window.addEventListener("error", (event) => {
+        const element = event.target;
+        if (!element || !element.hasAttribute || !element.hasAttribute("integrity")) {
+            return;
+        }
+        const url = element.src || element.href;
+        if (url && fetched(url)) {
+            window.__versoIntegrityFailures.push(url);
+        }
+    }, true);
+})();
+"#;
+
+/// Returns the failures recorded by [`INTEGRITY_FAILURE_LISTENER_SCRIPT`] as a JSON array and clears them
+pub const TAKE_INTEGRITY_FAILURES_SCRIPT: &str = r#"
+(() => {
+    const failures = window.__versoIntegrityFailures || [];
+    window.__versoIntegrityFailures = [];
+    return JSON.stringify(failures);
+})()
+"#;
+
+/// Security policies of every origin, falling back to a default
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SiteSettings {
+    /// Policy used for origins without an override
+    #[serde(default)]
+    pub default_policy: SitePolicy,
+    /// Overrides keyed by serialized origin, e.g. `https://example.com`
+    #[serde(default)]
+    pub overrides: HashMap<String, SitePolicy>,
+}
+
+impl SiteSettings {
+    /// Policy that applies to pages loaded from `url`
+    pub fn policy_for(&self, url: &Url) -> SitePolicy {
+        self.overrides
+            .get(&url.origin().ascii_serialization())
+            .copied()
+            .unwrap_or(self.default_policy)
+    }
+
+    /// Override the policy of `origin`, or remove the override with `None`
+    pub fn set_override(&mut self, origin: &str, policy: Option<SitePolicy>) {
+        let origin = Url::parse(origin)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_else(|_| origin.to_string());
+        match policy {
+            Some(policy) => {
+                self.overrides.insert(origin, policy);
+            }
+            None => {
+                self.overrides.remove(&origin);
+            }
+        }
+    }
+
+    /// How a subresource request of `document` should be handled
+    pub fn mixed_content_action(&self, document: &Url, resource: &Url) -> MixedContentAction {
+        if !is_mixed_content(document, resource) {
+            return MixedContentAction::Allow;
+        }
+        match self.policy_for(document).mixed_content {
+            MixedContentPolicy::Block => MixedContentAction::Block,
+            MixedContentPolicy::Upgrade => MixedContentAction::Upgrade(upgrade(resource)),
+            MixedContentPolicy::Allow => MixedContentAction::Allow,
+        }
+    }
+}
+
+/// Outcome of [`SiteSettings::mixed_content_action`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MixedContentAction {
+    /// Load the resource as requested
+    Allow,
+    /// Cancel the load
+    Block,
+    /// Load the resource from this secure URL instead
+    Upgrade(Url),
+}
+
+/// Whether a secure page is loading `resource` over an insecure connection
+fn is_mixed_content(document: &Url, resource: &Url) -> bool {
+    let secure_document = matches!(document.scheme(), "https" | "wss");
+    let insecure_resource = matches!(resource.scheme(), "http" | "ws");
+    // Loopback is potentially trustworthy, same as in browsers.
+    let loopback = matches!(
+        resource.host_str(),
+        Some("localhost" | "127.0.0.1" | "[::1]")
+    );
+    secure_document && insecure_resource && !loopback
+}
+
+/// Swap an insecure scheme for its secure counterpart
+fn upgrade(resource: &Url) -> Url {
+    let mut upgraded = resource.clone();
+    let scheme = if resource.scheme() == "ws" {
+        "wss"
+    } else {
+        "https"
+    };
+    let _ = upgraded.set_scheme(scheme);
+    if upgraded.port() == Some(80) {
+        let _ = upgraded.set_port(None);
+    }
+    upgraded
+}
+
+pub(crate) struct SiteSettingsStorage {
+    config_dir_path: PathBuf,
+}
+
+impl SiteSettingsStorage {
+    /// Create a new `SiteSettingsStorage`.
+    pub fn new(config_dir_path: PathBuf) -> Self {
+        Self { config_dir_path }
+    }
+
+    fn site_settings_file_path(&self) -> PathBuf {
+        self.config_dir_path.join("site_settings.json")
+    }
+
+    /// Load site settings from disk.
+    pub fn load_from_file(&self) -> Result<SiteSettings, std::io::Error> {
+        let file = File::open(self.site_settings_file_path())?;
+        let site_settings: SiteSettings = serde_json::from_reader(file)?;
+        Ok(site_settings)
+    }
+
+    /// Save site settings to disk.
+    pub fn save_to_file(&self, site_settings: &SiteSettings) -> Result<(), std::io::Error> {
+        let file = File::create(self.site_settings_file_path())?;
+        serde_json::to_writer(file, site_settings)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_mixed_content_default_blocks() {
+        let settings = SiteSettings::default();
+        let document = url("https://kiosk.example/");
+        assert_eq!(
+            settings.mixed_content_action(&document, &url("http://cdn.example/app.js")),
+            MixedContentAction::Block
+        );
+        assert_eq!(
+            settings.mixed_content_action(&document, &url("https://cdn.example/app.js")),
+            MixedContentAction::Allow
+        );
+        assert_eq!(
+            settings.mixed_content_action(&document, &url("http://localhost:8080/api")),
+            MixedContentAction::Allow
+        );
+        assert_eq!(
+            settings.mixed_content_action(&url("http://kiosk.example/"), &url("http://a/")),
+            MixedContentAction::Allow
+        );
+    }
+
+    #[test]
+    fn test_origin_override_upgrades() {
+        let mut settings = SiteSettings::default();
+        settings.set_override(
+            "https://kiosk.example/some/page",
+            Some(SitePolicy {
+                mixed_content: MixedContentPolicy::Upgrade,
+                ..Default::default()
+            }),
+        );
+
+        assert_eq!(
+            settings.mixed_content_action(
+                &url("https://kiosk.example/index.html"),
+                &url("http://cdn.example:80/image.png")
+            ),
+            MixedContentAction::Upgrade(url("https://cdn.example/image.png"))
+        );
+        assert_eq!(
+            settings.mixed_content_action(
+                &url("https://other.example/"),
+                &url("http://cdn.example/image.png")
+            ),
+            MixedContentAction::Block
+        );
+
+        settings.set_override("https://kiosk.example", None);
+        assert!(settings.overrides.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Smooth Scroll
+//!
+//! Animates the scrolls of mouse wheel notches and scrolling keys. Instead of
+//! scrolling by the whole delta at once, the compositor scrolls by the part of
+//! the delta the easing curve reached at every frame, so the offset WebRender
+//! samples glides to where the scroll ends. A scroll during the animation adds
+//! what was left of the previous one to its delta and starts over, so holding a
+//! key or spinning the wheel keeps scrolling without stopping at every notch.
+
+use std::time::{Duration, Instant};
+
+use versoview_messages::{ScrollEasing, SmoothScrolling};
+use webrender_api::units::{DeviceIntPoint, LayoutVector2D};
+
+/// A scroll being animated
+#[derive(Debug)]
+struct Animation {
+    /// Where the scroll hit tests the node to scroll
+    cursor: DeviceIntPoint,
+    delta: LayoutVector2D,
+    /// Part of the delta already scrolled
+    scrolled: LayoutVector2D,
+    start: Instant,
+}
+
+/// The smooth scroll of the window being composited
+#[derive(Debug)]
+pub struct SmoothScroll {
+    settings: SmoothScrolling,
+    animation: Option<Animation>,
+}
+
+impl SmoothScroll {
+    /// Animate scrolls as the settings say
+    pub fn new(settings: SmoothScrolling) -> Self {
+        Self {
+            settings,
+            animation: None,
+        }
+    }
+
+    /// Whether scrolls are animated
+    pub fn enabled(&self) -> bool {
+        self.settings.enabled && self.settings.duration_ms > 0
+    }
+
+    /// Start animating a scroll by `delta` at `cursor`, `false` if scrolls aren't animated
+    pub fn scroll(&mut self, cursor: DeviceIntPoint, delta: LayoutVector2D, now: Instant) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+        let left = self
+            .animation
+            .take()
+            .map_or(LayoutVector2D::zero(), |animation| {
+                animation.delta - animation.scrolled
+            });
+        self.animation = Some(Animation {
+            cursor,
+            delta: left + delta,
+            scrolled: LayoutVector2D::zero(),
+            start: now,
+        });
+        true
+    }
+
+    /// Advance the animation, returns where to scroll and by how much since the last frame
+    pub fn tick(&mut self, now: Instant) -> Option<(DeviceIntPoint, LayoutVector2D)> {
+        let animation = self.animation.as_mut()?;
+        let duration = Duration::from_millis(self.settings.duration_ms);
+        let progress = (now.saturating_duration_since(animation.start).as_secs_f32()
+            / duration.as_secs_f32())
+        .min(1.0);
+        let reached = if progress >= 1.0 {
+            animation.delta
+        } else {
+            animation.delta * ease(self.settings.easing, progress)
+        };
+        let step = reached - animation.scrolled;
+        animation.scrolled = reached;
+        let cursor = animation.cursor;
+        if progress >= 1.0 {
+            self.animation = None;
+        }
+        Some((cursor, step))
+    }
+
+    /// Whether a scroll is animating
+    pub fn animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Stop the scroll being animated where it is
+    pub fn stop(&mut self) {
+        self.animation = None;
+    }
+}
+
+/// Part of the delta scrolled at `progress`, between 0 and 1, of the animation
+fn ease(easing: ScrollEasing, progress: f32) -> f32 {
+    match easing {
+        ScrollEasing::Linear => progress,
+        ScrollEasing::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, progress),
+        ScrollEasing::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, progress),
+        ScrollEasing::CubicBezier(x1, y1, x2, y2) => {
+            cubic_bezier(x1.clamp(0.0, 1.0), y1, x2.clamp(0.0, 1.0), y2, progress)
+        }
+    }
+}
+
+/// Value of a CSS `cubic-bezier()` timing function at `x`
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let bezier = |p1: f32, p2: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+    };
+    // With the x coordinates between 0 and 1 the curve's x only grows, so bisect for the t at x.
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..24 {
+        let t = (low + high) / 2.0;
+        if bezier(x1, x2, t) < x {
+            low = t;
+        } else {
+            high = t;
+        }
+    }
+    bezier(y1, y2, (low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing() {
+        for easing in [
+            ScrollEasing::Linear,
+            ScrollEasing::EaseOut,
+            ScrollEasing::EaseInOut,
+            ScrollEasing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        ] {
+            assert!(ease(easing, 0.0).abs() < 1e-3, "{easing:?}");
+            assert!((ease(easing, 1.0) - 1.0).abs() < 1e-3, "{easing:?}");
+        }
+        assert!((ease(ScrollEasing::EaseInOut, 0.5) - 0.5).abs() < 1e-3);
+        assert!(ease(ScrollEasing::EaseOut, 0.5) > 0.5);
+    }
+
+    #[test]
+    fn test_smooth_scroll() {
+        let cursor = DeviceIntPoint::new(10, 10);
+        let start = Instant::now();
+        let half = Duration::from_millis(75);
+        let mut smooth_scroll = SmoothScroll::new(SmoothScrolling {
+            easing: ScrollEasing::Linear,
+            ..Default::default()
+        });
+        assert!(smooth_scroll.scroll(cursor, LayoutVector2D::new(0.0, -100.0), start));
+        assert_eq!(
+            smooth_scroll.tick(start + half),
+            Some((cursor, LayoutVector2D::new(0.0, -50.0)))
+        );
+
+        // A scroll during the animation carries on with what was left.
+        let now = start + half;
+        assert!(smooth_scroll.scroll(cursor, LayoutVector2D::new(0.0, -100.0), now));
+        assert_eq!(
+            smooth_scroll.tick(now + half),
+            Some((cursor, LayoutVector2D::new(0.0, -75.0)))
+        );
+        assert_eq!(
+            smooth_scroll.tick(now + half * 2),
+            Some((cursor, LayoutVector2D::new(0.0, -75.0)))
+        );
+        assert!(!smooth_scroll.animating());
+        assert_eq!(smooth_scroll.tick(now + half * 3), None);
+
+        let mut smooth_scroll = SmoothScroll::new(SmoothScrolling {
+            enabled: false,
+            ..Default::default()
+        });
+        assert!(!smooth_scroll.scroll(cursor, LayoutVector2D::new(0.0, -100.0), start));
+    }
+}
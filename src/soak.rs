@@ -0,0 +1,396 @@
+//! Soak Testing
+//!
+//! `verso soak --hours N --urls a,b,c` keeps a browser busy for hours, cycling
+//! navigation, scrolling and webview creation/destruction, and samples memory
+//! along the way. At the end it prints a report and fails when the resident set
+//! size or the number of WebRender keys tracked for live pipelines grew beyond
+//! the configured thresholds, which points at a leak.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+/// Soak test configuration
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoakConfig {
+    /// How long to run
+    pub duration: Duration,
+    /// Pages to cycle through
+    pub urls: Vec<Url>,
+    /// Time between two actions
+    pub step_interval: Duration,
+    /// Time between two memory samples
+    pub sample_interval: Duration,
+    /// Samples taken before this are ignored, while caches fill up
+    pub warmup: Duration,
+    /// Largest accepted RSS growth after warmup, in bytes
+    pub max_rss_growth: u64,
+    /// Largest accepted growth of tracked WebRender keys after warmup
+    pub max_tracked_key_growth: u64,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(60 * 60),
+            urls: Vec::new(),
+            step_interval: Duration::from_secs(5),
+            sample_interval: Duration::from_secs(60),
+            warmup: Duration::from_secs(10 * 60),
+            max_rss_growth: 256 * 1024 * 1024,
+            max_tracked_key_growth: 2000,
+        }
+    }
+}
+
+impl SoakConfig {
+    /// Parse the `--urls` argument, a comma separated list or `@path` to a file with one URL per line
+    pub fn parse_urls(value: &str) -> Result<Vec<Url>, String> {
+        let list = match value.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read URL list {path}: {e}"))?,
+            None => value.replace(',', "\n"),
+        };
+        let urls = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| Url::parse(line).map_err(|e| format!("invalid URL '{line}': {e}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        if urls.is_empty() {
+            return Err("no URLs to cycle through".to_string());
+        }
+        Ok(urls)
+    }
+}
+
+/// Something the soak test does to the browser
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SoakAction {
+    /// Load a page in the current webview
+    Navigate(Url),
+    /// Scroll the current page down by a screen
+    Scroll,
+    /// Open a new webview with a page
+    CreateWebView(Url),
+    /// Close the most recently opened webview
+    CloseWebView,
+}
+
+/// A memory sample
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoakSample {
+    /// Time since the soak test started
+    pub elapsed: Duration,
+    /// Resident set size in bytes, if the platform reports it
+    pub rss: Option<u64>,
+    /// WebRender keys tracked for live pipelines
+    pub tracked_keys: u64,
+}
+
+/// Outcome of a soak test
+#[derive(Clone, Debug)]
+pub struct SoakReport {
+    /// Every sample taken
+    pub samples: Vec<SoakSample>,
+    /// Actions performed
+    pub actions: u64,
+    /// RSS growth after warmup, in bytes
+    pub rss_growth: Option<i64>,
+    /// Tracked key growth after warmup
+    pub tracked_key_growth: i64,
+    /// Thresholds that were exceeded
+    pub failures: Vec<String>,
+}
+
+impl SoakReport {
+    /// Whether every threshold held
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Soak test {}: {} actions, {} samples",
+            if self.passed() { "passed" } else { "FAILED" },
+            self.actions,
+            self.samples.len()
+        )?;
+        match self.rss_growth {
+            Some(growth) => writeln!(f, "  RSS growth: {} KiB", growth / 1024)?,
+            None => writeln!(f, "  RSS growth: unavailable")?,
+        }
+        writeln!(f, "  Tracked key growth: {}", self.tracked_key_growth)?;
+        for failure in &self.failures {
+            writeln!(f, "  {failure}")?;
+        }
+        writeln!(f, "  elapsed_s,rss_kib,tracked_keys")?;
+        for sample in &self.samples {
+            writeln!(
+                f,
+                "  {},{},{}",
+                sample.elapsed.as_secs(),
+                sample
+                    .rss
+                    .map(|rss| (rss / 1024).to_string())
+                    .unwrap_or_default(),
+                sample.tracked_keys
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Drives a soak test and collects its samples
+#[derive(Debug)]
+pub struct SoakRunner {
+    config: SoakConfig,
+    started: Instant,
+    next_step: Instant,
+    next_sample: Instant,
+    step: usize,
+    open_webviews: usize,
+    actions: u64,
+    samples: Vec<SoakSample>,
+}
+
+impl SoakRunner {
+    /// Start a soak test now
+    pub fn new(config: SoakConfig) -> Self {
+        Self::new_at(config, Instant::now())
+    }
+
+    fn new_at(config: SoakConfig, now: Instant) -> Self {
+        Self {
+            started: now,
+            next_step: now + config.step_interval,
+            next_sample: now,
+            config,
+            step: 0,
+            open_webviews: 0,
+            actions: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Whether the configured duration has elapsed
+    pub fn is_finished(&self, now: Instant) -> bool {
+        now.duration_since(self.started) >= self.config.duration
+    }
+
+    /// When the runner next needs to be polled
+    ///
+    /// Without URLs there are no steps, only samples.
+    pub fn next_deadline(&self) -> Instant {
+        let deadline = self.next_sample.min(self.started + self.config.duration);
+        if self.config.urls.is_empty() {
+            deadline
+        } else {
+            deadline.min(self.next_step)
+        }
+    }
+
+    /// Whether a memory sample is due
+    pub fn sample_due(&self, now: Instant) -> bool {
+        now >= self.next_sample
+    }
+
+    /// Record a memory sample
+    pub fn record_sample(&mut self, now: Instant, rss: Option<u64>, tracked_keys: u64) {
+        let sample = SoakSample {
+            elapsed: now.duration_since(self.started),
+            rss,
+            tracked_keys,
+        };
+        log::info!("Soak sample: {sample:?}");
+        self.samples.push(sample);
+        self.next_sample = now + self.config.sample_interval;
+    }
+
+    /// The next action to perform, if one is due
+    pub fn next_action(&mut self, now: Instant) -> Option<SoakAction> {
+        if now < self.next_step || self.config.urls.is_empty() {
+            return None;
+        }
+        self.next_step = now + self.config.step_interval;
+        let url = self.config.urls[self.step % self.config.urls.len()].clone();
+        let action = match self.step % 5 {
+            0 => SoakAction::Navigate(url),
+            1 | 2 => SoakAction::Scroll,
+            3 => {
+                self.open_webviews += 1;
+                SoakAction::CreateWebView(url)
+            }
+            _ if self.open_webviews > 0 => {
+                self.open_webviews -= 1;
+                SoakAction::CloseWebView
+            }
+            _ => SoakAction::Scroll,
+        };
+        self.step += 1;
+        self.actions += 1;
+        Some(action)
+    }
+
+    /// Compare the samples against the thresholds
+    ///
+    /// Growth is measured from the first sample after warmup to the smallest of
+    /// the last three samples, so a transient spike at the end doesn't fail the run.
+    pub fn report(&self) -> SoakReport {
+        let after_warmup: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.elapsed >= self.config.warmup)
+            .collect();
+        let (baseline, tail) = match after_warmup.split_first() {
+            Some((baseline, rest)) if !rest.is_empty() => {
+                (*baseline, &rest[rest.len().saturating_sub(3)..])
+            }
+            _ => {
+                return SoakReport {
+                    samples: self.samples.clone(),
+                    actions: self.actions,
+                    rss_growth: None,
+                    tracked_key_growth: 0,
+                    failures: vec!["not enough samples after warmup".to_string()],
+                };
+            }
+        };
+
+        let rss_growth = baseline.rss.and_then(|baseline_rss| {
+            tail.iter()
+                .filter_map(|sample| sample.rss)
+                .min()
+                .map(|rss| rss as i64 - baseline_rss as i64)
+        });
+        let tracked_key_growth = tail
+            .iter()
+            .map(|sample| sample.tracked_keys)
+            .min()
+            .unwrap_or(baseline.tracked_keys) as i64
+            - baseline.tracked_keys as i64;
+
+        let mut failures = Vec::new();
+        if let Some(growth) =
+            rss_growth.filter(|growth| *growth > self.config.max_rss_growth as i64)
+        {
+            failures.push(format!(
+                "RSS grew by {} KiB, more than the {} KiB allowed",
+                growth / 1024,
+                self.config.max_rss_growth / 1024
+            ));
+        }
+        if tracked_key_growth > self.config.max_tracked_key_growth as i64 {
+            failures.push(format!(
+                "Tracked WebRender keys grew by {tracked_key_growth}, more than the {} allowed",
+                self.config.max_tracked_key_growth
+            ));
+        }
+
+        SoakReport {
+            samples: self.samples.clone(),
+            actions: self.actions,
+            rss_growth,
+            tracked_key_growth,
+            failures,
+        }
+    }
+}
+
+/// Resident set size of this process in bytes
+pub fn resident_set_size() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kib: u64 = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()?;
+        Some(kib * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SoakConfig {
+        SoakConfig {
+            urls: SoakConfig::parse_urls("https://a.example,https://b.example").unwrap(),
+            warmup: Duration::from_secs(60),
+            max_rss_growth: 1000,
+            max_tracked_key_growth: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_urls() {
+        assert_eq!(config().urls.len(), 2);
+        assert!(SoakConfig::parse_urls("not a url").is_err());
+        assert!(SoakConfig::parse_urls(" , ").is_err());
+    }
+
+    #[test]
+    fn test_no_urls_deadline() {
+        let start = Instant::now();
+        let mut runner = SoakRunner::new_at(SoakConfig::default(), start);
+        let later = start + Duration::from_secs(600);
+        runner.record_sample(later, None, 0);
+        assert_eq!(runner.next_action(later), None);
+        // The step that never runs doesn't keep the deadline in the past.
+        assert!(runner.next_deadline() > later);
+    }
+
+    #[test]
+    fn test_action_cycle() {
+        let start = Instant::now();
+        let mut runner = SoakRunner::new_at(config(), start);
+        assert_eq!(runner.next_action(start), None);
+
+        let actions: Vec<_> = (1..=5)
+            .filter_map(|i| runner.next_action(start + Duration::from_secs(5 * i)))
+            .collect();
+        assert_eq!(actions.len(), 5);
+        assert!(matches!(actions[0], SoakAction::Navigate(_)));
+        assert_eq!(actions[1], SoakAction::Scroll);
+        assert!(matches!(actions[3], SoakAction::CreateWebView(_)));
+        assert_eq!(actions[4], SoakAction::CloseWebView);
+    }
+
+    #[test]
+    fn test_report_detects_growth() {
+        let start = Instant::now();
+        let mut runner = SoakRunner::new_at(config(), start);
+        for minute in 0..6u64 {
+            runner.record_sample(
+                start + Duration::from_secs(60 * minute),
+                Some(10_000 + minute * 1000),
+                100 + minute,
+            );
+        }
+        let report = runner.report();
+        assert_eq!(report.rss_growth, Some(2000));
+        assert_eq!(report.tracked_key_growth, 2);
+        assert_eq!(report.failures.len(), 1);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_report_needs_samples() {
+        let runner = SoakRunner::new(config());
+        assert!(!runner.report().passed());
+    }
+}
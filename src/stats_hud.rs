@@ -0,0 +1,93 @@
+//! Stats HUD
+//!
+//! Lines the compositor draws in its HUD to show how well it keeps up: the
+//! frame rate against the target, frame times, dropped frames from
+//! [`FramePacing`](crate::frame_pacing::FramePacing), and how many scroll
+//! events were merged into each scroll the compositor performed. The lines are
+//! rebuilt every [`REFRESH_INTERVAL`] while the HUD shows them, which keeps
+//! the window compositing at that rate even when nothing else changes.
+
+use std::time::Duration;
+
+use crate::frame_pacing::FramePacingStats;
+
+/// How often the stats are redrawn while shown
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Scroll events received and the scrolls they were merged into
+#[derive(Debug, Default)]
+pub struct ScrollCoalescing {
+    events: u64,
+    scrolls: u64,
+}
+
+impl ScrollCoalescing {
+    /// Record a batch of scroll events merged into one scroll, nothing if it's empty
+    pub fn record(&mut self, events: usize) {
+        if events > 0 {
+            self.events += events as u64;
+            self.scrolls += 1;
+        }
+    }
+
+    /// Scroll events per scroll performed, 1 before any scroll
+    pub fn ratio(&self) -> f64 {
+        if self.scrolls == 0 {
+            1.0
+        } else {
+            self.events as f64 / self.scrolls as f64
+        }
+    }
+}
+
+/// Lines of the stats HUD
+pub fn lines(pacing: &FramePacingStats, scroll: &ScrollCoalescing) -> Vec<String> {
+    vec![
+        format!("FPS: {:.1} / {:.0}", pacing.current_fps, pacing.target_fps),
+        format!(
+            "Frame time: {:.1} ms, target {:.1} ms",
+            pacing.avg_frame_time.as_secs_f64() * 1000.0,
+            pacing.target_frame_time.as_secs_f64() * 1000.0
+        ),
+        format!(
+            "Dropped: {} of {} ({:.1}%)",
+            pacing.frames_dropped,
+            pacing.frame_count,
+            pacing.drop_percentage()
+        ),
+        format!("Scroll coalescing: {:.1} events/scroll", scroll.ratio()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let mut scroll = ScrollCoalescing::default();
+        assert_eq!(scroll.ratio(), 1.0);
+        scroll.record(3);
+        scroll.record(0);
+        scroll.record(2);
+
+        let pacing = FramePacingStats {
+            frame_count: 200,
+            frames_dropped: 5,
+            avg_frame_time: Duration::from_micros(20_000),
+            target_frame_time: Duration::from_micros(16_667),
+            current_fps: 50.0,
+            target_fps: 60.0,
+            behind_schedule: false,
+        };
+        assert_eq!(
+            lines(&pacing, &scroll),
+            vec![
+                "FPS: 50.0 / 60",
+                "Frame time: 20.0 ms, target 16.7 ms",
+                "Dropped: 5 of 200 (2.5%)",
+                "Scroll coalescing: 2.5 events/scroll",
+            ]
+        );
+    }
+}
@@ -1,29 +1,36 @@
 use directories::ProjectDirs;
 use std::{fs::create_dir_all, path::PathBuf};
 
-use crate::bookmark::BookmarkStorage;
+use crate::{bookmark::BookmarkStorage, site_settings::SiteSettingsStorage};
 
 #[derive(Default)]
 pub(crate) struct Storage {
     bookmark_storage: Option<BookmarkStorage>,
+    site_settings_storage: Option<SiteSettingsStorage>,
 }
 
 impl Storage {
     pub fn new() -> Self {
-        let project_dir = ProjectDirs::from("org", "versotile", "verso");
-
-        let config_dir_path = Self::get_and_create_config_dir_path(project_dir);
+        let config_dir_path = Self::config_dir();
         if config_dir_path.is_none() {
             return Self::default();
         }
 
-        let bookmark_storage = BookmarkStorage::new(config_dir_path.unwrap());
+        let config_dir_path = config_dir_path.unwrap();
+        let bookmark_storage = BookmarkStorage::new(config_dir_path.clone());
+        let site_settings_storage = SiteSettingsStorage::new(config_dir_path);
 
         Self {
             bookmark_storage: Some(bookmark_storage),
+            site_settings_storage: Some(site_settings_storage),
         }
     }
 
+    /// Verso's config directory, created if it doesn't exist
+    pub(crate) fn config_dir() -> Option<PathBuf> {
+        Self::get_and_create_config_dir_path(ProjectDirs::from("org", "versotile", "verso"))
+    }
+
     fn get_and_create_config_dir_path(project_dir: Option<ProjectDirs>) -> Option<PathBuf> {
         if project_dir.is_none() {
             log::error!("Project directory not found");
@@ -46,4 +53,8 @@ impl Storage {
     pub(crate) fn bookmark_storage(&self) -> Option<&BookmarkStorage> {
         self.bookmark_storage.as_ref()
     }
+
+    pub(crate) fn site_settings_storage(&self) -> Option<&SiteSettingsStorage> {
+        self.site_settings_storage.as_ref()
+    }
 }
@@ -0,0 +1,166 @@
+//! Suspend/Resume Handling
+//!
+//! Keeps Verso usable across system sleep. Mobile platforms report suspend and
+//! resume through winit, and embedders can forward OS power notifications with
+//! `ToVersoMessage::Suspend`/`Resume`. Desktop sleeps that nobody reported are
+//! caught by [`SleepDetector`], which reads two monotonic clocks: one stops
+//! while the system sleeps and the other doesn't, so a gap between the two
+//! means we slept. They are `CLOCK_MONOTONIC` and `CLOCK_BOOTTIME` on Linux,
+//! `CLOCK_UPTIME_RAW` and `CLOCK_MONOTONIC_RAW` on Apple platforms, and the
+//! unbiased and regular interrupt time on Windows.
+
+use std::time::Duration;
+
+/// Pauses playing media and tells the page it is frozen, see the Page Lifecycle API
+pub const SUSPEND_SCRIPT: &str = r#"
+(() => {
+    for (const media of document.querySelectorAll("audio, video")) {
+        if (!media.paused) {
+            media.dataset.versoSuspended = "";
+            media.pause();
+        }
+    }
+    document.dispatchEvent(new Event("freeze"));
+})();
+"#;
+
+/// Tells the page it is resumed and restarts the media paused by [`SUSPEND_SCRIPT`]
+pub const RESUME_SCRIPT: &str = r#"
+(() => {
+    document.dispatchEvent(new Event("resume"));
+    for (const media of document.querySelectorAll("[data-verso-suspended]")) {
+        delete media.dataset.versoSuspended;
+        media.play().catch(() => {});
+    }
+})();
+"#;
+
+/// Time pages get to run [`SUSPEND_SCRIPT`] or [`RESUME_SCRIPT`]
+pub const PAGE_LIFECYCLE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Readings of two monotonic clocks, one that stops while the system sleeps and one that doesn't
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ClockReading {
+    /// Time the system was awake
+    awake: Duration,
+    /// Time including sleep
+    total: Duration,
+}
+
+impl ClockReading {
+    /// Read both clocks, `None` where the platform has no clock counting sleep
+    #[cfg(any(target_os = "linux", target_os = "android", apple))]
+    fn now() -> Option<Self> {
+        #[cfg(apple)]
+        let (awake, total) = (libc::CLOCK_UPTIME_RAW, libc::CLOCK_MONOTONIC_RAW);
+        #[cfg(not(apple))]
+        let (awake, total) = (libc::CLOCK_MONOTONIC, libc::CLOCK_BOOTTIME);
+
+        let read = |clock| {
+            let mut time = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            // SAFETY: `time` is a valid timespec to write to.
+            (unsafe { libc::clock_gettime(clock, &mut time) } == 0)
+                .then(|| Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+        };
+        Some(Self {
+            awake: read(awake)?,
+            total: read(total)?,
+        })
+    }
+
+    /// Read both clocks, `None` where the platform has no clock counting sleep
+    #[cfg(windows)]
+    fn now() -> Option<Self> {
+        use windows_sys::Win32::System::WindowsProgramming::{
+            QueryInterruptTime, QueryUnbiasedInterruptTime,
+        };
+
+        let (mut awake, mut total) = (0u64, 0u64);
+        // SAFETY: both pointers are valid u64s to write to.
+        let read = unsafe {
+            QueryInterruptTime(&mut total);
+            QueryUnbiasedInterruptTime(&mut awake)
+        };
+        // Both clocks count in 100 ns units.
+        (read != 0).then(|| Self {
+            awake: Duration::from_nanos(awake * 100),
+            total: Duration::from_nanos(total * 100),
+        })
+    }
+
+    /// Read both clocks, `None` where the platform has no clock counting sleep
+    #[cfg(not(any(target_os = "linux", target_os = "android", apple, windows)))]
+    fn now() -> Option<Self> {
+        None
+    }
+}
+
+/// Detects system sleep by comparing a monotonic clock that counts sleep to one that doesn't
+///
+/// Unlike the wall clock, neither is moved by NTP or the user setting the time.
+#[derive(Debug)]
+pub struct SleepDetector {
+    threshold: Duration,
+    last: Option<ClockReading>,
+}
+
+impl SleepDetector {
+    /// Create a detector reporting gaps longer than `threshold`
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last: ClockReading::now(),
+        }
+    }
+
+    /// How long the system slept since the last check, if it did
+    pub fn check(&mut self) -> Option<Duration> {
+        self.check_at(ClockReading::now()?)
+    }
+
+    fn check_at(&mut self, reading: ClockReading) -> Option<Duration> {
+        let last = self.last.replace(reading)?;
+        let awake = reading.awake.saturating_sub(last.awake);
+        let total = reading.total.saturating_sub(last.total);
+        total
+            .checked_sub(awake)
+            .filter(|slept| *slept > self.threshold)
+    }
+}
+
+impl Default for SleepDetector {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleep_detection() {
+        let start = ClockReading {
+            awake: Duration::from_secs(100),
+            total: Duration::from_secs(200),
+        };
+        let mut detector = SleepDetector {
+            threshold: Duration::from_secs(5),
+            last: Some(start),
+        };
+
+        let second = Duration::from_secs(1);
+        let reading = |awake, slept| ClockReading {
+            awake: start.awake + awake,
+            total: start.total + awake + slept,
+        };
+        assert_eq!(detector.check_at(reading(second, Duration::ZERO)), None);
+
+        let slept = Duration::from_secs(600);
+        assert_eq!(detector.check_at(reading(2 * second, slept)), Some(slept));
+        assert_eq!(detector.check_at(reading(3 * second, slept)), None);
+    }
+}
@@ -0,0 +1,194 @@
+//! SVG Rasterization
+//!
+//! Rasterizes SVG icons and images with resvg, at the device pixel size they
+//! are shown at. Used for favicons, internal `verso://` pages, and by embedders
+//! through `ToVersoMessage::RasterizeSvg`. Rasterized images are cached as PNGs
+//! by content and size, so an icon shown in many tabs is only rasterized once.
+
+use std::sync::{Arc, LazyLock, Mutex};
+
+use base64::Engine;
+use resvg::{tiny_skia, usvg};
+use url::Url;
+
+use crate::utils::content_cache::{ContentCache, content_key};
+
+/// Size favicons are shown at, in CSS pixels
+pub const FAVICON_SIZE: u32 = 16;
+
+/// Largest width or height rasterized, in device pixels
+const MAX_DIMENSION: u32 = 4096;
+
+/// Upper bound of the rasterized image cache, in bytes
+const CACHE_CAPACITY: usize = 16 * 1024 * 1024;
+
+static SHARED: LazyLock<SvgRasterizer> = LazyLock::new(SvgRasterizer::default);
+
+/// Size to rasterize at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterSize {
+    /// Width in CSS pixels
+    pub width: u32,
+    /// Height in CSS pixels
+    pub height: u32,
+    /// Device pixels per CSS pixel
+    pub scale_factor: f64,
+}
+
+impl RasterSize {
+    /// Create a size of `width` x `height` CSS pixels
+    pub fn new(width: u32, height: u32, scale_factor: f64) -> Self {
+        Self {
+            width,
+            height,
+            scale_factor,
+        }
+    }
+
+    /// Parse the size from a query like `size=32&scale=2` or `width=64&height=32`
+    ///
+    /// The scale factor defaults to 1. Returns `None` without a width and height.
+    pub fn from_query(query: &str) -> Option<Self> {
+        let (mut width, mut height, mut scale_factor) = (None, None, 1.0);
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match &*key {
+                "size" => {
+                    width = value.parse().ok();
+                    height = width;
+                }
+                "width" => width = value.parse().ok(),
+                "height" => height = value.parse().ok(),
+                "scale" => scale_factor = value.parse().ok().unwrap_or(scale_factor),
+                _ => {}
+            }
+        }
+        Some(Self::new(width?, height?, scale_factor))
+    }
+
+    /// Size in device pixels, at least 1x1 and at most [`MAX_DIMENSION`]
+    fn physical(&self) -> (u32, u32) {
+        let scale_factor = if self.scale_factor.is_finite() && self.scale_factor > 0.0 {
+            self.scale_factor
+        } else {
+            1.0
+        };
+        let scale =
+            |length: u32| ((length as f64 * scale_factor).round() as u32).clamp(1, MAX_DIMENSION);
+        (scale(self.width), scale(self.height))
+    }
+}
+
+/// Rasterizes SVGs to PNGs, with a cache
+pub struct SvgRasterizer {
+    /// Rasterized images by content hash and size
+    cache: Mutex<ContentCache<Arc<Vec<u8>>>>,
+}
+
+impl Default for SvgRasterizer {
+    fn default() -> Self {
+        Self {
+            cache: Mutex::new(ContentCache::new(CACHE_CAPACITY)),
+        }
+    }
+}
+
+impl SvgRasterizer {
+    /// The rasterizer shared by the whole process
+    pub fn shared() -> &'static Self {
+        &SHARED
+    }
+
+    /// Rasterize an SVG document to a PNG of `size`
+    ///
+    /// The image is scaled to fit and centered, keeping its aspect ratio.
+    pub fn rasterize(&self, svg: &[u8], size: RasterSize) -> Result<Arc<Vec<u8>>, String> {
+        let (width, height) = size.physical();
+        let key = content_key(svg, (width, height));
+        if let Some(png) = self.cache.lock().unwrap().get(key) {
+            return Ok(png);
+        }
+
+        let png = Arc::new(rasterize_uncached(svg, width, height)?);
+        self.cache.lock().unwrap().insert(key, png.clone());
+        Ok(png)
+    }
+}
+
+fn rasterize_uncached(svg: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let tree = usvg::Tree::from_data(svg, &usvg::Options::default())
+        .map_err(|error| format!("invalid SVG: {error}"))?;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("can't allocate a {width}x{height} image"))?;
+
+    let tree_size = tree.size();
+    let scale = (width as f32 / tree_size.width()).min(height as f32 / tree_size.height());
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (width as f32 - tree_size.width() * scale) / 2.0,
+        (height as f32 - tree_size.height() * scale) / 2.0,
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    pixmap
+        .encode_png()
+        .map_err(|error| format!("failed to encode PNG: {error}"))
+}
+
+/// Whether a URL points to an SVG image
+pub fn is_svg_url(url: &Url) -> bool {
+    url.path().to_ascii_lowercase().ends_with(".svg")
+}
+
+/// A `data:` URL of a PNG, to hand rasterized images to web content
+pub fn png_data_url(png: &[u8]) -> String {
+    format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+        <rect width="10" height="10" fill="red"/>
+    </svg>"#;
+
+    #[test]
+    fn test_rasterize_at_device_size() {
+        let rasterizer = SvgRasterizer::default();
+        let png = rasterizer
+            .rasterize(SQUARE, RasterSize::new(FAVICON_SIZE, FAVICON_SIZE, 2.0))
+            .unwrap();
+        let image = image::load_from_memory(&png).unwrap().into_rgba8();
+        assert_eq!(image.dimensions(), (32, 32));
+        assert_eq!(image.get_pixel(16, 16).0, [255, 0, 0, 255]);
+
+        let cached = rasterizer
+            .rasterize(SQUARE, RasterSize::new(FAVICON_SIZE, FAVICON_SIZE, 2.0))
+            .unwrap();
+        assert!(Arc::ptr_eq(&png, &cached));
+        assert!(
+            rasterizer
+                .rasterize(b"<svg", RasterSize::new(16, 16, 1.0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_size_from_query() {
+        assert_eq!(
+            RasterSize::from_query("size=32&scale=1.5"),
+            Some(RasterSize::new(32, 32, 1.5))
+        );
+        assert_eq!(
+            RasterSize::from_query("width=64&height=16"),
+            Some(RasterSize::new(64, 16, 1.0))
+        );
+        assert_eq!(RasterSize::from_query("scale=2"), None);
+        assert_eq!(
+            RasterSize::new(100_000, 0, 1.0).physical(),
+            (MAX_DIMENSION, 1)
+        );
+    }
+}
@@ -109,6 +109,8 @@ pub struct TabManager {
     active_tab_id: Option<WebViewId>,
     /// Tab webview id -> Tab webview
     tab_map: HashMap<WebViewId, Tab>,
+    /// Tab webview ids in the order the tabs were opened
+    tab_order: Vec<WebViewId>,
     /// Prompt webview id -> Parent tab webview id
     prompt_tab_map: HashMap<WebViewId, WebViewId>,
 }
@@ -119,6 +121,7 @@ impl TabManager {
         Self {
             active_tab_id: None,
             tab_map: HashMap::new(),
+            tab_order: Vec::new(),
             prompt_tab_map: HashMap::new(),
         }
     }
@@ -146,9 +149,9 @@ impl TabManager {
             None
         }
     }
-    /// Get all tab id.
+    /// Get all tab id, in the order the tabs were opened.
     pub fn tab_ids(&self) -> Vec<WebViewId> {
-        self.tab_map.keys().cloned().collect()
+        self.tab_order.clone()
     }
     /// Activate the tab by tab id.
     pub fn activate_tab(&mut self, tab_id: WebViewId) -> Option<&Tab> {
@@ -169,6 +172,7 @@ impl TabManager {
         let id = webview.webview_id;
         let tab = Tab::new(webview);
         self.tab_map.insert(id, tab);
+        self.tab_order.push(id);
         if active {
             self.active_tab_id = Some(id);
         }
@@ -176,7 +180,10 @@ impl TabManager {
     /// Close a tab.
     pub fn close_tab(&mut self, id: WebViewId) -> Result<Tab, TabManagerErr> {
         match self.tab_map.remove(&id) {
-            Some(tab) => Ok(tab),
+            Some(tab) => {
+                self.tab_order.retain(|tab_id| *tab_id != id);
+                Ok(tab)
+            }
             None => Err(TabManagerErr::WebViewIdNotFound),
         }
     }
@@ -261,6 +268,10 @@ impl TabManager {
             None
         }
     }
+    /// Get the ID of the tab showing a prompt dialog.
+    pub fn tab_id_by_prompt_id(&self, prompt_id: WebViewId) -> Option<WebViewId> {
+        self.prompt_tab_map.get(&prompt_id).copied()
+    }
     /// Check if there is a prompt dialog by prompt webview ID.
     pub fn has_prompt(&self, prompt_id: WebViewId) -> bool {
         self.prompt_tab_map.contains_key(&prompt_id)
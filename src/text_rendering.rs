@@ -0,0 +1,209 @@
+//! Text Rendering Policy
+//!
+//! Turns the configured [`TextRendering`] into the WebRender font instance
+//! options, so subpixel anti-aliasing, hinting, LCD filtering and gamma are the
+//! same for every font instance, whichever glyph rasterizer backend (FreeType,
+//! DirectWrite or Core Text) is in use. Settings a backend doesn't support are
+//! ignored on that platform: hinting and LCD filters only apply to FreeType,
+//! gamma only to DirectWrite.
+//!
+//! Servo still lays text out with unhinted metrics, so changing the policy only
+//! needs the glyphs to be rasterized again, never a relayout.
+
+use versoview_messages::{LcdFilter, TextHinting, TextRendering};
+use webrender_api::{
+    FontInstanceFlags, FontInstanceOptions, FontInstancePlatformOptions, FontRenderMode,
+};
+
+/// Applies a [`TextRendering`] configuration to font instances
+#[derive(Clone, Copy, Debug)]
+pub struct TextRenderingPolicy {
+    config: TextRendering,
+    subpixel_aa_available: bool,
+}
+
+impl TextRenderingPolicy {
+    /// Create a policy, `subpixel_aa_available` is whether the WebRender renderer allows subpixel AA
+    pub fn new(config: TextRendering, subpixel_aa_available: bool) -> Self {
+        let policy = Self {
+            config,
+            subpixel_aa_available,
+        };
+        policy.warn_unavailable();
+        policy
+    }
+
+    /// The configuration in use
+    pub fn config(&self) -> TextRendering {
+        self.config
+    }
+
+    /// Change the configuration, returns whether anything changed
+    pub fn set_config(&mut self, config: TextRendering) -> bool {
+        if self.config == config {
+            return false;
+        }
+        self.config = config;
+        self.warn_unavailable();
+        true
+    }
+
+    fn warn_unavailable(&self) {
+        if self.config.subpixel_aa == Some(true) && !self.subpixel_aa_available {
+            log::warn!(
+                "Subpixel text anti-aliasing is disabled in the renderer, text will be grayscale anti-aliased"
+            );
+        }
+    }
+
+    /// Options for a new font instance, combining Servo's `flags` with the policy
+    pub fn font_instance_options(&self, flags: FontInstanceFlags) -> FontInstanceOptions {
+        let render_mode = match self.config.subpixel_aa {
+            Some(false) => FontRenderMode::Alpha,
+            _ => FontRenderMode::Subpixel,
+        };
+        FontInstanceOptions {
+            flags,
+            render_mode,
+            ..Default::default()
+        }
+    }
+
+    /// Backend specific options, `None` to keep the backend defaults
+    pub fn platform_options(&self) -> Option<FontInstancePlatformOptions> {
+        platform_options(&self.config)
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn platform_options(config: &TextRendering) -> Option<FontInstancePlatformOptions> {
+    let gamma = config.gamma?;
+    Some(FontInstancePlatformOptions {
+        gamma: (gamma * 100.0).round().clamp(100.0, 300.0) as u16,
+        ..Default::default()
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn platform_options(_config: &TextRendering) -> Option<FontInstancePlatformOptions> {
+    None
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn platform_options(config: &TextRendering) -> Option<FontInstancePlatformOptions> {
+    use webrender_api::{FontHinting, FontLCDFilter};
+
+    if config.hinting.is_none() && config.lcd_filter.is_none() {
+        return None;
+    }
+    let defaults = FontInstancePlatformOptions::default();
+    let subpixel_aa = config.subpixel_aa != Some(false);
+    Some(FontInstancePlatformOptions {
+        hinting: match config.hinting {
+            None => defaults.hinting,
+            Some(TextHinting::None) => FontHinting::None,
+            Some(TextHinting::Light) => FontHinting::Light,
+            Some(TextHinting::Normal) => FontHinting::Normal,
+            Some(TextHinting::Full) if subpixel_aa => FontHinting::LCD,
+            Some(TextHinting::Full) => FontHinting::Mono,
+        },
+        lcd_filter: match config.lcd_filter {
+            None => defaults.lcd_filter,
+            Some(LcdFilter::None) => FontLCDFilter::None,
+            Some(LcdFilter::Default) => FontLCDFilter::Default,
+            Some(LcdFilter::Light) => FontLCDFilter::Light,
+            Some(LcdFilter::Legacy) => FontLCDFilter::Legacy,
+        },
+    })
+}
+
+/// Apply a `name=value` override, as given on the command line
+pub fn apply_override(config: &mut TextRendering, name: &str, value: &str) -> Result<(), String> {
+    let value = value.trim().to_ascii_lowercase();
+    match name.trim().replace('_', "-").as_str() {
+        "subpixel-aa" => {
+            config.subpixel_aa = Some(match value.as_str() {
+                "on" | "true" | "1" => true,
+                "off" | "false" | "0" => false,
+                _ => return Err(format!("expected on or off, got '{value}'")),
+            });
+        }
+        "hinting" => {
+            config.hinting = Some(match value.as_str() {
+                "none" => TextHinting::None,
+                "light" => TextHinting::Light,
+                "normal" => TextHinting::Normal,
+                "full" => TextHinting::Full,
+                _ => {
+                    return Err(format!(
+                        "expected none, light, normal or full, got '{value}'"
+                    ));
+                }
+            });
+        }
+        "lcd-filter" => {
+            config.lcd_filter = Some(match value.as_str() {
+                "none" => LcdFilter::None,
+                "default" => LcdFilter::Default,
+                "light" => LcdFilter::Light,
+                "legacy" => LcdFilter::Legacy,
+                _ => {
+                    return Err(format!(
+                        "expected none, default, light or legacy, got '{value}'"
+                    ));
+                }
+            });
+        }
+        "gamma" => {
+            let gamma = value
+                .parse::<f32>()
+                .map_err(|e| format!("invalid gamma '{value}': {e}"))?;
+            if !(1.0..=3.0).contains(&gamma) {
+                return Err(format!("gamma {gamma} out of range, expected 1.0 to 3.0"));
+            }
+            config.gamma = Some(gamma);
+        }
+        other => return Err(format!("unknown text rendering option '{other}'")),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_override() {
+        let mut config = TextRendering::default();
+        apply_override(&mut config, "subpixel_aa", "off").unwrap();
+        apply_override(&mut config, "hinting", "Light").unwrap();
+        apply_override(&mut config, "gamma", "1.8").unwrap();
+        assert_eq!(config.subpixel_aa, Some(false));
+        assert_eq!(config.hinting, Some(TextHinting::Light));
+        assert_eq!(config.gamma, Some(1.8));
+
+        assert!(apply_override(&mut config, "gamma", "9").is_err());
+        assert!(apply_override(&mut config, "lcd-filter", "strong").is_err());
+        assert!(apply_override(&mut config, "kerning", "on").is_err());
+    }
+
+    #[test]
+    fn test_font_instance_options() {
+        let mut policy = TextRenderingPolicy::new(TextRendering::default(), true);
+        let options = policy.font_instance_options(FontInstanceFlags::SYNTHETIC_BOLD);
+        assert_eq!(options.render_mode, FontRenderMode::Subpixel);
+        assert!(options.flags.contains(FontInstanceFlags::SYNTHETIC_BOLD));
+
+        assert!(policy.set_config(TextRendering {
+            subpixel_aa: Some(false),
+            ..Default::default()
+        }));
+        assert!(!policy.set_config(policy.config()));
+        assert_eq!(
+            policy
+                .font_instance_options(FontInstanceFlags::empty())
+                .render_mode,
+            FontRenderMode::Alpha
+        );
+    }
+}
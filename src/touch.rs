@@ -1,8 +1,10 @@
+use base::id::WebViewId;
 use embedder_traits::TouchEventResult;
 use embedder_traits::TouchId;
+use embedder_traits::{InputEvent, MouseButton, MouseButtonAction, TouchEvent, TouchEventType};
 use euclid::{Point2D, Scale, Vector2D};
 use log::warn;
-use webrender_api::units::DevicePixel;
+use webrender_api::units::{DevicePixel, DevicePoint};
 
 use self::TouchState::*;
 
@@ -241,3 +243,168 @@ impl TouchHandler {
         (distance, center)
     }
 }
+
+/// Touches emulated with the mouse, to try touch UIs on a desktop.
+///
+/// Pressing the left button puts a finger down, moving the mouse while it is held moves the
+/// finger and releasing the button lifts it. Every press is a new touch, with its own id, and
+/// the whole sequence goes to the webview the finger went down on. The ids are negative so
+/// they never collide with the touches of a touchscreen.
+#[derive(Debug)]
+pub struct MouseToTouch {
+    enabled: bool,
+    /// Webview, id and position of the touch of the held button
+    active: Option<(WebViewId, TouchId, DevicePoint)>,
+    next_id: i32,
+}
+
+impl MouseToTouch {
+    /// Create the emulation, on or off.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            active: None,
+            next_id: -1,
+        }
+    }
+
+    /// Whether mouse input is turned into touch events.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn the emulation on or off, returns the cancel event of the touch in progress if it is
+    /// turned off while the button is held.
+    pub fn set_enabled(&mut self, enabled: bool) -> Option<(WebViewId, TouchEvent)> {
+        self.enabled = enabled;
+        if enabled {
+            return None;
+        }
+        self.active.take().map(|(webview_id, id, point)| {
+            (
+                webview_id,
+                TouchEvent::new(TouchEventType::Cancel, id, point),
+            )
+        })
+    }
+
+    /// The touch events replacing a mouse event, `None` to handle the event as it is.
+    ///
+    /// Moves with no button held and clicks are dropped, since a touchscreen has no hover and
+    /// the touch handler clicks on its own when a finger is lifted.
+    pub fn convert(
+        &mut self,
+        webview_id: WebViewId,
+        event: &InputEvent,
+    ) -> Option<Vec<(WebViewId, TouchEvent)>> {
+        if !self.enabled {
+            return None;
+        }
+        match event {
+            InputEvent::MouseButton(event) if event.button == MouseButton::Left => {
+                Some(match event.action {
+                    MouseButtonAction::Down if self.active.is_none() => {
+                        let id = TouchId(self.next_id);
+                        self.next_id = self.next_id.checked_sub(1).unwrap_or(-1);
+                        self.active = Some((webview_id, id, event.point));
+                        vec![(
+                            webview_id,
+                            TouchEvent::new(TouchEventType::Down, id, event.point),
+                        )]
+                    }
+                    MouseButtonAction::Up => match self.active.take() {
+                        Some((webview_id, id, _)) => vec![(
+                            webview_id,
+                            TouchEvent::new(TouchEventType::Up, id, event.point),
+                        )],
+                        None => Vec::new(),
+                    },
+                    _ => Vec::new(),
+                })
+            }
+            InputEvent::MouseMove(event) => Some(match &mut self.active {
+                Some((webview_id, id, point)) if *point != event.point => {
+                    *point = event.point;
+                    vec![(
+                        *webview_id,
+                        TouchEvent::new(TouchEventType::Move, *id, event.point),
+                    )]
+                }
+                _ => Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+    use embedder_traits::{MouseButtonEvent, MouseMoveEvent};
+
+    use super::*;
+
+    fn button(action: MouseButtonAction, x: f32) -> InputEvent {
+        InputEvent::MouseButton(MouseButtonEvent {
+            button: MouseButton::Left,
+            action,
+            point: DevicePoint::new(x, 0.0),
+        })
+    }
+
+    fn touches(events: Option<Vec<(WebViewId, TouchEvent)>>) -> Vec<(TouchEventType, i32)> {
+        events
+            .unwrap()
+            .into_iter()
+            .map(|(_, event)| (event.event_type, event.id.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_mouse_to_touch() {
+        PipelineNamespace::install(PipelineNamespaceId(4));
+        let webview_id = WebViewId::new();
+        let other_webview_id = WebViewId::new();
+        let hover = InputEvent::MouseMove(MouseMoveEvent {
+            point: DevicePoint::new(3.0, 0.0),
+        });
+
+        let mut emulation = MouseToTouch::new(false);
+        assert!(emulation.convert(webview_id, &hover).is_none());
+
+        emulation.set_enabled(true);
+        assert!(touches(emulation.convert(webview_id, &hover)).is_empty());
+        assert_eq!(
+            touches(emulation.convert(webview_id, &button(MouseButtonAction::Down, 1.0))),
+            [(TouchEventType::Down, -1)]
+        );
+        // The touch stays with its webview when the mouse leaves it.
+        let moved = emulation.convert(other_webview_id, &hover).unwrap();
+        assert_eq!(moved[0].0, webview_id);
+        assert_eq!(moved[0].1.id, TouchId(-1));
+        assert_eq!(
+            touches(emulation.convert(webview_id, &button(MouseButtonAction::Up, 3.0))),
+            [(TouchEventType::Up, -1)]
+        );
+        assert!(
+            touches(emulation.convert(webview_id, &button(MouseButtonAction::Click, 3.0)))
+                .is_empty()
+        );
+
+        // A new press is a new touch, cancelled when the emulation is turned off.
+        assert_eq!(
+            touches(emulation.convert(webview_id, &button(MouseButtonAction::Down, 1.0))),
+            [(TouchEventType::Down, -2)]
+        );
+        let (_, cancel) = emulation.set_enabled(false).unwrap();
+        assert_eq!(
+            (cancel.event_type, cancel.id),
+            (TouchEventType::Cancel, TouchId(-2))
+        );
+        assert!(
+            emulation
+                .convert(webview_id, &button(MouseButtonAction::Up, 1.0))
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Byte-bounded cache of derived content, keyed by a hash of its source
+//!
+//! Shared by the image conversions that are worth doing only once per image:
+//! color management and SVG rasterization. Entries are evicted oldest first.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+/// Bytes every entry counts for besides its content, so entries without content
+/// (images used as is) still fill the cache
+pub(crate) const ENTRY_OVERHEAD: usize = 256;
+
+/// Hash identifying content, and whatever else the cached result depends on
+pub(crate) fn content_key(content: &[u8], extra: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    extra.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Bytes a cached value holds
+pub(crate) trait CachedBytes {
+    /// Bytes held, not counting [`ENTRY_OVERHEAD`]
+    fn cached_bytes(&self) -> usize;
+}
+
+impl CachedBytes for Arc<Vec<u8>> {
+    fn cached_bytes(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: CachedBytes> CachedBytes for Option<T> {
+    fn cached_bytes(&self) -> usize {
+        self.as_ref().map_or(0, T::cached_bytes)
+    }
+}
+
+/// Values by content key, holding at most `capacity` bytes
+pub(crate) struct ContentCache<V> {
+    capacity: usize,
+    entries: HashMap<u64, V>,
+    order: VecDeque<u64>,
+    size: usize,
+}
+
+impl<V: CachedBytes + Clone> ContentCache<V> {
+    /// Create an empty cache holding at most `capacity` bytes
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            size: 0,
+        }
+    }
+
+    /// Value of the key, if it is still cached
+    pub(crate) fn get(&self, key: u64) -> Option<V> {
+        self.entries.get(&key).cloned()
+    }
+
+    /// Add a value, evicting the oldest ones to make room, values larger than the cache are dropped
+    pub(crate) fn insert(&mut self, key: u64, value: V) {
+        let size = ENTRY_OVERHEAD + value.cached_bytes();
+        if size > self.capacity || self.entries.contains_key(&key) {
+            return;
+        }
+        while self.size + size > self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.size -= ENTRY_OVERHEAD + evicted.cached_bytes();
+            }
+        }
+        self.entries.insert(key, value);
+        self.order.push_back(key);
+        self.size += size;
+    }
+
+    /// Bytes counted against the capacity
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eviction_counts_empty_entries() {
+        let capacity = 4 * ENTRY_OVERHEAD;
+        let mut cache = ContentCache::new(capacity);
+        let half = Some(Arc::new(vec![0; capacity / 2]));
+        cache.insert(1, half.clone());
+        cache.insert(2, None);
+        cache.insert(3, half);
+        assert!(cache.get(1).is_none());
+        assert_eq!(cache.get(2), Some(None));
+        assert!(cache.get(3).is_some());
+        assert_eq!(cache.size(), 2 * ENTRY_OVERHEAD + capacity / 2);
+
+        for key in 4..100 {
+            cache.insert(key, None);
+        }
+        assert!(cache.size() <= capacity);
+        assert!(cache.get(3).is_none());
+    }
+}
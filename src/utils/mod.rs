@@ -1 +1,2 @@
+pub(crate) mod content_cache;
 pub(crate) mod content_disposition_parser;
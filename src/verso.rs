@@ -1,7 +1,9 @@
 use std::{
     collections::HashMap,
     fmt::Debug,
+    rc::Rc,
     sync::{Arc, atomic::Ordering},
+    time::Instant,
 };
 
 use arboard::Clipboard;
@@ -18,11 +20,13 @@ use constellation_traits::EmbedderToConstellationMessage;
 use crossbeam_channel::{Receiver, Sender, unbounded};
 use devtools;
 use embedder_traits::{
-    AllowOrDeny, EmbedderMsg, EmbedderProxy, EventLoopWaker, PromptResponse, WebResourceResponse,
-    WebResourceResponseMsg, user_content_manager::UserContentManager,
+    AllowOrDeny, Cursor, EmbedderMsg, EmbedderProxy, EventLoopWaker, LoadStatus, PromptResponse,
+    WebDriverJSValue, WebResourceResponse, WebResourceResponseMsg,
+    user_content_manager::UserContentManager,
 };
 use euclid::Scale;
 use fonts::SystemFontService;
+use gleam::gl;
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use layout_thread_2020;
@@ -34,24 +38,63 @@ use serde::{Deserialize, Serialize};
 use servo_config::{opts, pref};
 use servo_url::ServoUrl;
 use style;
-use versoview_messages::{PositionType, SizeType, ToControllerMessage, ToVersoMessage};
+use versoview_messages::{
+    AuditEntry, EventLoopStats, EventQueue, GlContextInfo, HitTestResult, PageSettled,
+    PlaylistCommand, PlaylistStatus, PositionType, SafeModeReport, SafeModeSubsystem,
+    ScreenshotFormat, ScreenshotOptions, SessionManifest, SessionWindow, ShutdownPhase, SizeType,
+    TabRect, ToControllerMessage, ToVersoMessage,
+};
 use webgpu;
-use webrender::{ShaderPrecacheFlags, WebRenderOptions, create_webrender_instance};
-use webrender_api::*;
+use webrender::{RendererError, ShaderPrecacheFlags, WebRenderOptions, create_webrender_instance};
+use webrender_api::{
+    units::{DeviceIntSize, DevicePoint},
+    *,
+};
 use winit::{
+    dpi::LogicalPosition,
     event::WindowEvent,
     event_loop::{ActiveEventLoop, ControlFlow, EventLoopProxy},
     window::WindowId,
 };
 
 use crate::{
+    address_input,
+    animated_images::DecodedAnimation,
+    audit_log,
     bookmark::{BookmarkId, BookmarkManager},
+    color_management::ColorManager,
     compositor::{IOCompositor, InitialCompositorState, ShutdownState},
     config::{Config, parse_cli_args},
+    cursor_theme::ThemedCursors,
     download::{DownloadId, DownloadItem, UpdateDownloadState},
+    emoji,
+    event_loop_monitor::message_type,
+    external_layers::{ExternalLayer, ExternalLayerId, ExternalLayerImages, ExternalTextureSource},
+    flight_recorder::RecordedKind,
+    gestures::GestureMapper,
+    http3::Http3Transport,
+    network_context::NetworkContextStore,
+    playlist::PlaylistPlayer,
+    readiness::{self, StabilityWait, StabilityWaiter},
+    recovery::{self, RecoveryEngine, Restart},
+    renderer_options::{RendererOptionsConfig, RuntimeRendererOption},
+    renderer_restart::{CreateRenderer, ExternalImageHandlers},
+    request_priority::{RequestScheduler, parse_viewport_images},
+    safe_mode::SafeMode,
+    screenshot::{PAGE_HEIGHT_SCRIPT, ScreenshotRequest},
+    script_policy, session,
+    shutdown::ShutdownCoordinator,
+    site_settings::SiteSettings,
+    soak::{SoakAction, SoakReport, SoakRunner, resident_set_size},
     storage::Storage,
-    webview::execute_script,
-    window::Window,
+    suspend::{PAGE_LIFECYCLE_TIMEOUT, RESUME_SCRIPT, SUSPEND_SCRIPT, SleepDetector},
+    svg_raster::{RasterSize, SvgRasterizer},
+    text_rendering::TextRenderingPolicy,
+    webview::{
+        clock_override, connections, content_settings::ContentSettingsStore, coverage,
+        execute_script, execute_script_async, execute_script_deferred, tiling,
+    },
+    window::{Window, cursor_icon},
 };
 
 /// Main entry point of Verso browser.
@@ -71,7 +114,28 @@ pub struct Verso {
     config: Config,
     storage: Storage,
     bookmark_manager: BookmarkManager,
+    site_settings: SiteSettings,
+    /// Soak test in progress, see [`crate::soak`]
+    soak: Option<SoakRunner>,
+    /// Outcome of the finished soak test
+    soak_report: Option<SoakReport>,
+    /// Pages waited on until they're stable, see [`crate::readiness`]
+    stability_waits: Vec<(StabilityWaiter, StabilityWait)>,
+    /// Whether to exit once the first page loaded is stable, as the `-x` flag asks
+    exit_when_settled: bool,
+    /// How to restart once shut down, when a recovery policy asked for it
+    restart: Option<Restart>,
+    /// Startup crash tracking, see [`crate::safe_mode`]
+    safe_mode: SafeMode,
+    shutdown: ShutdownCoordinator,
+    /// Whether the system told us it is going to sleep
+    suspended: bool,
+    sleep_detector: SleepDetector,
+    color_manager: Option<Arc<ColorManager>>,
+    network_contexts: NetworkContextStore,
     downloads: HashMap<DownloadId, DownloadItem>,
+    /// Window cursors with the embedder's replacements, see [`crate::cursor_theme`]
+    cursors: ThemedCursors,
 }
 
 /// Message for Verso internal communication
@@ -91,6 +155,24 @@ pub enum VersoInternalMsg {
     BookmarkRemove(BookmarkId),
     /// Rename a bookmark in the bookmark manager.
     BookmarkRename(BookmarkId, String),
+    /// Send the audit log to the frontend audit page.
+    UpdateAuditPage(IpcSender<PromptResponse>),
+    /// Send the GL context attributes to the frontend GL diagnostics page.
+    UpdateGlContextPage(IpcSender<PromptResponse>),
+    /// A page answered where its images are, as a JSON array of URLs, `None` if it couldn't.
+    ViewportImages(WebViewId, Option<String>),
+    /// A page handed over the CSP violations of its document, as a JSON array.
+    CspViolations(WebViewId, url::Url, String),
+    /// The frames of an animated image a page loaded were decoded.
+    AnimatedImage(WebViewId, Box<DecodedAnimation>),
+    /// A page answered which of its images are near its viewport, as a JSON array of URLs.
+    AnimatedImagesInView(WebViewId, Option<String>),
+    /// A page answered how many milliseconds its first viewport took to complete.
+    FirstViewportComplete(WebViewId, f64),
+    /// A page handed over the resources of its document that failed their integrity check, as a JSON array.
+    IntegrityFailures(WebViewId, url::Url, String),
+    /// A page answered its height for the full page capture of this id, in CSS pixels.
+    PageHeight(uuid::Uuid, WebViewId, ScreenshotFormat, Result<f32, String>),
 }
 
 impl Debug for VersoInternalMsg {
@@ -103,6 +185,15 @@ impl Debug for VersoInternalMsg {
             VersoInternalMsg::UpdateBookmarkManager(_) => write!(f, "UpdateBookmarkManager"),
             VersoInternalMsg::BookmarkRemove(_) => write!(f, "BookmarkRemove"),
             VersoInternalMsg::BookmarkRename(_, _) => write!(f, "BookmarkRename"),
+            VersoInternalMsg::UpdateAuditPage(_) => write!(f, "UpdateAuditPage"),
+            VersoInternalMsg::UpdateGlContextPage(_) => write!(f, "UpdateGlContextPage"),
+            VersoInternalMsg::ViewportImages(_, _) => write!(f, "ViewportImages"),
+            VersoInternalMsg::CspViolations(_, _, _) => write!(f, "CspViolations"),
+            VersoInternalMsg::AnimatedImage(_, _) => write!(f, "AnimatedImage"),
+            VersoInternalMsg::AnimatedImagesInView(_, _) => write!(f, "AnimatedImagesInView"),
+            VersoInternalMsg::FirstViewportComplete(_, _) => write!(f, "FirstViewportComplete"),
+            VersoInternalMsg::IntegrityFailures(_, _, _) => write!(f, "IntegrityFailures"),
+            VersoInternalMsg::PageHeight(_, _, _, _) => write!(f, "PageHeight"),
         }
     }
 }
@@ -125,22 +216,70 @@ impl Verso {
     /// - Constellation: Enabled
     /// - Image Cache: Enabled
     pub fn new(evl: &ActiveEventLoop, proxy: EventLoopProxy<EventLoopProxyMessage>) -> Self {
-        let (config, to_controller_sender) = try_connect_ipc_and_get_config(&proxy);
+        let (mut config, to_controller_sender) = try_connect_ipc_and_get_config(&proxy);
+        let mut safe_mode = SafeMode::begin(&config.safe_mode);
+        safe_mode.apply_to_config(&mut config);
         let (verso_internal_sender, verso_internal_receiver) = ipc_channel::ipc::channel().unwrap();
 
         // Initialize configurations and Verso window
         let protocols = config.create_protocols();
         let initial_url = config.url.clone();
-        let with_panel = config.with_panel;
-        let window_settings = config.window_attributes.clone();
         let user_scripts = config.user_scripts.clone();
-        let zoom_level = config.zoom_level;
+        // The first window of a session replaces the initial window, the others are opened after.
+        let session_windows = config
+            .session
+            .as_ref()
+            .map(|session| session.windows.clone())
+            .unwrap_or_default();
+        let first_session_window = session_windows.first();
+        let with_panel = first_session_window.map_or(config.with_panel, session::has_panel);
+        let window_settings = match first_session_window {
+            Some(session_window) => {
+                session::window_attributes(&config.window_attributes, session_window)
+            }
+            None => config.window_attributes.clone(),
+        };
+        let zoom_level = first_session_window
+            .and_then(|session_window| session_window.zoom)
+            .or(config.zoom_level);
 
         config.init();
+        safe_mode.apply_to_preferences();
+        emoji::register_emoji_font(&config.emoji);
+        Http3Transport::init(config.http3.clone());
         // Reserving a namespace to create WebViewId.
         PipelineNamespace::install(PipelineNamespaceId(0));
-        let (mut window, rendering_context) =
-            Window::new(evl, window_settings, verso_internal_sender.clone());
+        let (mut window, rendering_context) = Window::new(
+            evl,
+            window_settings,
+            verso_internal_sender.clone(),
+            config.gl_context,
+        );
+        let present_mode = rendering_context.set_present_mode(&window.surface, config.present_mode);
+        log::info!("Presenting frames with {present_mode:?} mode");
+        window.content_settings = ContentSettingsStore::new(config.content_settings);
+        window.script_policy = config.script_policy;
+        window.drag_and_drop = config.drag_and_drop;
+        let network_contexts = NetworkContextStore::new(
+            &config.network_contexts,
+            config.default_network_context.clone(),
+        );
+        window.network_contexts = network_contexts.clone();
+        window.request_scheduler = RequestScheduler::new(config.prioritize_requests);
+        window.recovery = RecoveryEngine::new(config.recovery.clone(), recovery::restart_count());
+        window.gestures = GestureMapper::new(&config.gestures);
+        window.address_input = config.address_input.clone();
+        log::info!("Script policy: {:?}", config.script_policy);
+        let color_manager = ColorManager::new(&config.color_management).unwrap_or_else(|e| {
+            log::error!("Color management disabled: {e}");
+            None
+        });
+        window.color_manager = color_manager.clone();
+        if let Some(session_window) = first_session_window {
+            window.kiosk = session_window.kiosk;
+            window.session_webviews = session_window.webviews.iter().cloned().collect();
+        }
+        window.zoom_level = zoom_level;
         let event_loop_waker = Box::new(Waker(proxy.clone()));
         let opts = opts::get();
 
@@ -156,6 +295,7 @@ impl Verso {
         // This will create a thread to initialize a global static of servo media.
         // The thread will be closed once the static is initialized.
         // TODO: This is used by content process. Spawn it there once if we have multiprocess mode.
+        safe_mode.starting(Some(SafeModeSubsystem::HardwareMediaDecode));
         servo_media::ServoMedia::init::<servo_media_dummy::DummyBackend>();
 
         // Get GL bindings
@@ -184,38 +324,20 @@ impl Verso {
         };
 
         // Create Webrender threads
+        safe_mode.starting(Some(SafeModeSubsystem::ShaderCache));
         let (mut webrender, webrender_api_sender) = {
-            let mut debug_flags = DebugFlags::empty();
-            debug_flags.set(DebugFlags::PROFILER_DBG, opts.debug.webrender_stats);
-
             let render_notifier = Box::new(RenderNotifier::new(compositor_proxy.clone()));
-            let clear_color = ColorF::new(0., 0., 0., 0.);
+            let webrender_options = webrender_options(&config.renderer_options);
+            RendererOptionsConfig::log_effective(&webrender_options);
             create_webrender_instance(
                 webrender_gl.clone(),
                 render_notifier,
-                WebRenderOptions {
-                    // We force the use of optimized shaders here because rendering is broken
-                    // on Android emulators with unoptimized shaders. This is due to a known
-                    // issue in the emulator's OpenGL emulation layer.
-                    // See: https://github.com/servo/servo/issues/31726
-                    use_optimized_shaders: true,
-                    resource_override_path: opts.shaders_dir.clone(),
-                    debug_flags,
-                    precache_flags: if pref!(gfx_precache_shaders) {
-                        ShaderPrecacheFlags::FULL_COMPILE
-                    } else {
-                        ShaderPrecacheFlags::empty()
-                    },
-                    enable_aa: pref!(gfx_text_antialiasing_enabled),
-                    enable_subpixel_aa: pref!(gfx_subpixel_text_antialiasing_enabled),
-                    allow_texture_swizzling: pref!(gfx_texture_swizzling_enabled),
-                    clear_color,
-                    ..Default::default()
-                },
+                webrender_options,
                 None,
             )
             .expect("Unable to initialize webrender!")
         };
+        safe_mode.starting(None);
         let webrender_api = webrender_api_sender.create_api();
         let webrender_document = webrender_api
             .add_document_with_id(window.size().to_i32(), u64::from(window.id()) as u32);
@@ -256,8 +378,45 @@ impl Verso {
             WebrenderImageHandlerType::WebGPU,
         );
 
+        // Set webrender external image handler for the textures of external layers
+        let external_layer_images = ExternalLayerImages::default();
+        external_image_handlers.set_handler(
+            Box::new(external_layer_images.clone()),
+            WebrenderImageHandlerType::Media,
+        );
+        let external_image_registry = external_images.clone();
+
         webrender.set_external_image_handler(external_image_handlers);
 
+        // A renderer lost with its GL context is created again with the same options and
+        // handlers, looking up the external images of the constellation's registry.
+        let create_renderer = config.gl_context.lose_context_on_reset.then(|| {
+            let compositor_proxy = compositor_proxy.clone();
+            let renderer_options = config.renderer_options.clone();
+            let external_images = external_images.clone();
+            let external_layer_images = external_layer_images.clone();
+            Box::new(move |gl: Rc<dyn gl::Gl>| -> Result<_, RendererError> {
+                let render_notifier = Box::new(RenderNotifier::new(compositor_proxy.clone()));
+                let (mut webrender, webrender_api_sender) = create_webrender_instance(
+                    gl,
+                    render_notifier,
+                    webrender_options(&renderer_options),
+                    None,
+                )?;
+                let mut handlers = ExternalImageHandlers::new(external_images.clone());
+                handlers.set_handler(
+                    Box::new(webgpu::WGPUExternalImages::default()),
+                    WebrenderImageHandlerType::WebGPU,
+                );
+                handlers.set_handler(
+                    Box::new(external_layer_images.clone()),
+                    WebrenderImageHandlerType::Media,
+                );
+                webrender.set_external_image_handler(Box::new(handlers));
+                Ok((webrender, webrender_api_sender))
+            }) as CreateRenderer
+        });
+
         // Create bluetooth thread
         let bluetooth_thread: IpcSender<BluetoothRequest> =
             BluetoothThreadFactory::new(embedder_proxy.clone());
@@ -288,7 +447,26 @@ impl Verso {
             public_resource_threads.clone(),
         );
 
+        // Site settings are needed before the constellation starts, to build the script policy.
+        let storage = Storage::new();
+        let site_settings = storage
+            .site_settings_storage()
+            .and_then(|site_settings_storage| site_settings_storage.load_from_file().ok())
+            .unwrap_or_default();
+
         let mut user_content_manager = UserContentManager::new();
+        if let Some(script) = script_policy::user_script(&config.script_policy, &site_settings) {
+            user_content_manager.add_script(script);
+        }
+        if let Some(script) = config
+            .clock_override
+            .as_ref()
+            .map(clock_override::user_script)
+        {
+            user_content_manager.add_script(script);
+        }
+        user_content_manager.add_script(connections::user_script());
+        user_content_manager.add_script(audit_log::user_script());
         for script in user_scripts {
             user_content_manager.add_script(script);
         }
@@ -347,19 +525,44 @@ impl Verso {
                 webrender_api,
                 rendering_context,
                 webrender_gl,
+                text_rendering: TextRenderingPolicy::new(
+                    config.text_rendering,
+                    config
+                        .renderer_options
+                        .enable_subpixel_aa
+                        .unwrap_or(pref!(gfx_subpixel_text_antialiasing_enabled)),
+                ),
+                color_glyphs: config.emoji.color_glyphs,
+                flight_recorder: config.flight_recorder.clone(),
+                stall_threshold_ms: config.stall_threshold_ms,
+                render_validation: config.render_validation.clone(),
+                min_pinch_zoom: config.min_pinch_zoom,
+                max_pinch_zoom: config.max_pinch_zoom,
+                smooth_scrolling: config.smooth_scrolling,
+                partial_present: config.renderer_options.partial_present,
+                image_tile_size: config.renderer_options.image_tile_size,
+                external_layer_images,
+                external_image_registry,
+                create_renderer,
+                shared_texture: config.shared_texture,
             },
-            opts.wait_for_stable_image,
             opts.debug.convert_mouse_to_touch,
         );
+        compositor.update_refresh_rate(&window);
 
         if let Some(zoom_level) = zoom_level {
             compositor.on_zoom_window_event(zoom_level, &window);
         }
 
+        window.playlist = config.playlist.clone().map(PlaylistPlayer::new);
         if with_panel {
             window.create_panel(&constellation_sender, initial_url);
-        } else {
+        } else if window.playlist.is_some() {
+            // The playlist opens its first page when it is driven.
+        } else if window.session_webviews.is_empty() {
             window.create_tab(&constellation_sender, initial_url.into());
+        } else {
+            window.open_session_webviews(&constellation_sender);
         }
 
         let mut windows = HashMap::new();
@@ -381,7 +584,7 @@ impl Verso {
         );
 
         // Create Verso instance
-        let verso = Verso {
+        let mut verso = Verso {
             windows,
             compositor: Some(compositor),
             constellation_sender,
@@ -389,17 +592,252 @@ impl Verso {
             embedder_receiver,
             _js_engine_setup: js_engine_setup,
             clipboard: Clipboard::new().ok(),
+            soak: config.soak.clone().map(SoakRunner::new),
+            soak_report: None,
+            stability_waits: Vec::new(),
+            exit_when_settled: opts.wait_for_stable_image,
+            restart: None,
+            safe_mode,
+            shutdown: ShutdownCoordinator::default(),
+            suspended: false,
+            sleep_detector: SleepDetector::default(),
+            color_manager,
+            network_contexts,
+            cursors: ThemedCursors::new(config.cursor_theme.clone()),
             config,
             bookmark_manager: BookmarkManager::new(),
+            site_settings,
             downloads: HashMap::new(),
             verso_internal_sender,
-            storage: Storage::new(),
+            storage,
         };
 
+        for session_window in session_windows.iter().skip(1) {
+            verso.open_session_window(evl, session_window);
+        }
+
         verso.setup_logging();
         verso
     }
 
+    /// Open a window of the startup session next to the initial one.
+    fn open_session_window(&mut self, evl: &ActiveEventLoop, session_window: &SessionWindow) {
+        let Some(compositor) = self.compositor.as_mut() else {
+            return;
+        };
+        let mut window = Window::new_with_compositor(
+            evl,
+            session::window_attributes(&self.config.window_attributes, session_window),
+            compositor,
+            self.verso_internal_sender.clone(),
+        );
+        window.content_settings = ContentSettingsStore::new(self.config.content_settings);
+        window.color_manager = self.color_manager.clone();
+        window.script_policy = self.config.script_policy;
+        window.drag_and_drop = self.config.drag_and_drop;
+        window.network_contexts = self.network_contexts.clone();
+        window.request_scheduler = RequestScheduler::new(self.config.prioritize_requests);
+        window.recovery =
+            RecoveryEngine::new(self.config.recovery.clone(), recovery::restart_count());
+        window.gestures = GestureMapper::new(&self.config.gestures);
+        window.address_input = self.config.address_input.clone();
+        window.kiosk = session_window.kiosk;
+        window.zoom_level = session_window.zoom.or(self.config.zoom_level);
+        window.session_webviews = session_window.webviews.iter().cloned().collect();
+        if let Some(zoom_level) = window.zoom_level {
+            compositor.on_zoom_window_event(zoom_level, &window);
+        }
+        if session::has_panel(session_window) {
+            window.create_panel(&self.constellation_sender, self.config.url.clone());
+        } else {
+            window.open_session_webviews(&self.constellation_sender);
+        }
+        let webrender_document = compositor.window_document(window.id());
+        self.windows
+            .insert(window.id(), (window, webrender_document));
+    }
+
+    /// Start timing an event loop iteration, finish it with [`Verso::end_event_loop_iteration`].
+    pub fn begin_event_loop_iteration(&mut self) {
+        if let Some(compositor) = self.compositor.as_mut() {
+            compositor.event_loop_monitor.begin_iteration();
+        }
+    }
+
+    /// Finish timing the event loop iteration that handled `message`, see [`message_type`].
+    pub fn end_event_loop_iteration(&mut self, message: &str) {
+        let Some(compositor) = self.compositor.as_mut() else {
+            return;
+        };
+        if let Some(stall) = compositor.event_loop_monitor.end_iteration(message) {
+            compositor.flight_recorder.record(RecordedKind::Marker, || {
+                format!(
+                    "Event loop stalled for {}ms, {:?} message {} took {}ms",
+                    stall.duration_ms, stall.queue, stall.message, stall.message_duration_ms
+                )
+            });
+        }
+    }
+
+    /// Time spent per message type and stalls of the event loop, `None` once the compositor is gone.
+    pub fn event_loop_stats(&self) -> Option<EventLoopStats> {
+        self.compositor
+            .as_ref()
+            .map(|compositor| compositor.event_loop_monitor.stats())
+    }
+
+    /// Apply a playlist command to the window playing a playlist, or to the first window.
+    ///
+    /// Setting a playlist on a window without one starts playing it there.
+    pub fn control_playlist(&mut self, command: PlaylistCommand) {
+        let Some(compositor) = self.compositor.as_mut() else {
+            return;
+        };
+        let window_id = self
+            .windows
+            .iter()
+            .find(|(_, (window, _))| window.playlist.is_some())
+            .or_else(|| self.windows.iter().next())
+            .map(|(window_id, _)| *window_id);
+        let Some((window, _)) = window_id.and_then(|window_id| self.windows.get_mut(&window_id))
+        else {
+            return;
+        };
+        let actions = match (window.playlist.as_mut(), command) {
+            (Some(playlist), command) => playlist.command(command),
+            (None, PlaylistCommand::Set(playlist)) => {
+                window.playlist = Some(PlaylistPlayer::new(playlist));
+                Vec::new()
+            }
+            (None, command) => {
+                log::warn!("Verso ignores {command:?}, no window plays a playlist");
+                return;
+            }
+        };
+        window.apply_playlist_actions(compositor, actions);
+        window.drive_playlist(compositor);
+    }
+
+    /// Show the tab at `rect` of the window showing it, or back in its content area.
+    pub fn set_tab_rect(&mut self, tab_id: WebViewId, rect: Option<TabRect>) {
+        let Some(compositor) = self.compositor.as_mut() else {
+            return;
+        };
+        let Some((window, _)) = self
+            .windows
+            .values_mut()
+            .find(|(window, _)| window.tab_manager.tab(tab_id).is_some())
+        else {
+            log::warn!("Verso ignores the rect of {tab_id:?}, no window has such a tab");
+            return;
+        };
+        window.set_tab_rect(compositor, tab_id, rect.as_ref().map(tiling::device_rect));
+    }
+
+    /// Zoom the page of the tab in the window showing it.
+    pub fn set_page_zoom(&mut self, tab_id: WebViewId, factor: f32) {
+        let Some(compositor) = self.compositor.as_mut() else {
+            return;
+        };
+        let Some((window, _)) = self
+            .windows
+            .values()
+            .find(|(window, _)| window.tab_manager.tab(tab_id).is_some())
+        else {
+            log::warn!("Verso ignores the page zoom of {tab_id:?}, no window has such a tab");
+            return;
+        };
+        compositor.set_page_zoom(window, tab_id, factor);
+    }
+
+    /// What the tab shows at a point of the window showing it, `None` if it shows nothing there.
+    pub fn hit_test(&self, tab_id: WebViewId, point: DevicePoint) -> Option<HitTestResult> {
+        let compositor = self.compositor.as_ref()?;
+        self.windows
+            .values()
+            .find(|(window, _)| window.tab_manager.tab(tab_id).is_some())?;
+        let result = compositor.hit_test(tab_id, point)?;
+        Some(HitTestResult {
+            main_frame: compositor.is_webview_root_pipeline(tab_id, result.pipeline_id),
+            point_in_viewport: LogicalPosition::new(
+                result.point_in_viewport.x,
+                result.point_in_viewport.y,
+            ),
+            point_relative_to_item: LogicalPosition::new(
+                result.point_relative_to_item.x,
+                result.point_relative_to_item.y,
+            ),
+            cursor: cursor_icon(result.cursor.unwrap_or(Cursor::Default))
+                .map(|cursor| cursor.name().to_string()),
+        })
+    }
+
+    /// Composite a texture of the embedder in the window, `None` if there's no such window.
+    pub fn add_external_layer(
+        &mut self,
+        window_id: WindowId,
+        source: Box<dyn ExternalTextureSource>,
+        texture_size: DeviceIntSize,
+        layer: ExternalLayer,
+    ) -> Option<ExternalLayerId> {
+        let compositor = self.compositor.as_mut()?;
+        let (window, _) = self.windows.get(&window_id)?;
+        Some(compositor.add_external_layer(window, source, texture_size, layer))
+    }
+
+    /// Move or reorder an external layer.
+    pub fn set_external_layer(&mut self, id: ExternalLayerId, layer: ExternalLayer) {
+        if let Some(compositor) = self.compositor.as_mut() {
+            compositor.set_external_layer(id, layer, &self.windows);
+        }
+    }
+
+    /// Draw the new contents or size of an external layer's texture.
+    pub fn external_texture_changed(&mut self, id: ExternalLayerId, texture_size: DeviceIntSize) {
+        if let Some(compositor) = self.compositor.as_mut() {
+            compositor.external_texture_changed(id, texture_size);
+        }
+    }
+
+    /// Stop compositing an external layer.
+    pub fn remove_external_layer(&mut self, id: ExternalLayerId) {
+        if let Some(compositor) = self.compositor.as_mut() {
+            compositor.remove_external_layer(id, &self.windows);
+        }
+    }
+
+    /// What the playlist shows, `None` if no window plays one.
+    pub fn playlist_status(&self) -> Option<PlaylistStatus> {
+        self.windows
+            .values()
+            .find_map(|(window, _)| window.playlist.as_ref())
+            .map(PlaylistPlayer::status)
+    }
+
+    /// How this run started in safe mode, `None` if it didn't.
+    pub fn safe_mode_report(&self) -> Option<&SafeModeReport> {
+        self.safe_mode.report()
+    }
+
+    /// Attributes of the GL context Verso renders with, `None` once the compositor is gone.
+    pub fn gl_context_info(&self) -> Option<GlContextInfo> {
+        self.compositor
+            .as_ref()
+            .map(|compositor| compositor.rendering_context.context_info().clone())
+    }
+
+    /// The open windows and their webviews as a session manifest, in the order they were opened.
+    pub fn export_session(&self) -> SessionManifest {
+        let mut windows: Vec<_> = self.windows.values().map(|(window, _)| window).collect();
+        windows.sort_by_key(|window| u64::from(window.id()));
+        SessionManifest {
+            windows: windows
+                .into_iter()
+                .map(|window| window.export_session())
+                .collect(),
+        }
+    }
+
     /// Initialize Verso instance.
     ///
     /// This function is called when the Verso instance is created.
@@ -413,14 +851,136 @@ impl Verso {
         }
     }
 
-    /// Task to be done before shutting down.
+    /// Start the shutdown sequence, does nothing if it already started.
     ///
-    /// This function is called when the Verso instance is shutting down.
-    pub fn before_shutdown(&mut self) {
+    /// Pages get their `beforeunload` event, downloads are stopped and storage is flushed
+    /// before the constellation is asked to exit. The rest happens as the compositor
+    /// reports its shutdown progress in [`Self::handle_servo_messages`].
+    pub fn begin_shutdown(&mut self) {
+        if self.shutdown.is_started() {
+            return;
+        }
+        if self.config.http3.enabled {
+            log::info!(
+                "Protocols used by Verso's requests: {:?}",
+                Http3Transport::shared().stats()
+            );
+        }
+        if let Some(path) = &self.config.export_session {
+            match session::save(path, &self.export_session()) {
+                Ok(()) => log::info!("Session saved to {}", path.display()),
+                Err(error) => log::error!("Verso failed to save the session: {error}"),
+            }
+        }
+
+        self.shutdown
+            .enter(ShutdownPhase::BeforeUnload, &self.to_controller_sender);
+        self.run_in_all_tabs(
+            "window.dispatchEvent(new Event('beforeunload', { cancelable: true }))",
+            self.shutdown.beforeunload_deadline(),
+        );
+
+        self.shutdown
+            .enter(ShutdownPhase::FlushDownloads, &self.to_controller_sender);
+        for download in self.downloads.values_mut() {
+            if !download.stopped {
+                log::info!("Shutdown: stopping unfinished download {:?}", download.id());
+                download.abort();
+            }
+        }
+
+        self.shutdown
+            .enter(ShutdownPhase::FlushStorage, &self.to_controller_sender);
+        self.flush_storage();
+
+        self.shutdown
+            .enter(ShutdownPhase::Constellation, &self.to_controller_sender);
+        if let Some(compositor) = &mut self.compositor {
+            compositor.maybe_start_shutting_down();
+        }
+    }
+
+    /// Prepare for the system going to sleep.
+    ///
+    /// Pages are frozen and their media paused, storage is flushed in case we never wake
+    /// up, and GPU resources that commonly don't survive a sleep are released.
+    pub fn suspend(&mut self) {
+        if self.suspended {
+            return;
+        }
+        log::info!("Verso is suspending");
+        self.suspended = true;
+
+        self.run_in_all_tabs(SUSPEND_SCRIPT, Instant::now() + PAGE_LIFECYCLE_TIMEOUT);
+        self.flush_storage();
+        if let Some(compositor) = &mut self.compositor {
+            compositor.on_system_suspend();
+        }
+    }
+
+    /// Recover from the system waking up.
+    ///
+    /// Also called when [`SleepDetector`] notices a sleep nobody told us about. The GL
+    /// context of every window is revalidated and the windows repainted, then pages are
+    /// resumed.
+    pub fn resume(&mut self) {
+        log::info!("Verso is resuming");
+        self.suspended = false;
+        // Restart sleep detection from now, the resume itself isn't another sleep.
+        self.sleep_detector = SleepDetector::default();
+
+        if let Some(compositor) = &mut self.compositor {
+            for (window, _) in self.windows.values_mut() {
+                compositor.swap_current_window(window);
+                compositor.check_gpu_switch(window);
+                compositor.on_system_resume(window);
+            }
+        }
+        self.run_in_all_tabs(RESUME_SCRIPT, Instant::now() + PAGE_LIFECYCLE_TIMEOUT);
+    }
+
+    /// Run a script in every tab at once and wait for them to finish, giving up on the tabs
+    /// still running it once `deadline` passed.
+    fn run_in_all_tabs(&self, js: &str, deadline: Instant) {
+        let answers: Vec<_> = self
+            .windows
+            .values()
+            .flat_map(|(window, _)| window.tab_manager.tab_ids())
+            .filter_map(|webview_id| {
+                execute_script_deferred(&self.constellation_sender, &webview_id, js)
+                    .map(|answer| (webview_id, answer))
+            })
+            .collect();
+        for (webview_id, answer) in answers {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if answer.try_recv_timeout(timeout).is_err() {
+                log::warn!("Timed out running a script in {webview_id:?}");
+            }
+        }
+    }
+
+    /// Write bookmarks and site settings to disk.
+    fn flush_storage(&mut self) {
         // Save bookmarks to disk
         if let Some(bookmark_storage) = self.storage.bookmark_storage() {
             let _ = bookmark_storage.save_to_file(self.bookmark_manager.bookmarks());
         }
+
+        // Save site settings to disk
+        if let Some(site_settings_storage) = self.storage.site_settings_storage() {
+            let _ = site_settings_storage.save_to_file(&self.site_settings);
+        }
+    }
+
+    /// Task to be done before shutting down.
+    ///
+    /// This function is called when the Verso instance is shutting down.
+    pub fn before_shutdown(&mut self) {
+        self.safe_mode.exiting();
+        // The shutdown sequence already flushed the storage.
+        if !self.shutdown.is_started() {
+            self.flush_storage();
+        }
     }
 
     /// Handle Winit window events. The strategy to handle event are different between platforms
@@ -483,9 +1043,18 @@ impl Verso {
                 }
             }
             // self.windows.remove(&window_id);
-            compositor.maybe_start_shutting_down();
+            self.begin_shutdown();
         } else {
             window.handle_winit_window_event(&self.constellation_sender, compositor, &event);
+            if let Some(frame) = compositor.take_presented_frame() {
+                if let Some(to_controller_sender) = &self.to_controller_sender {
+                    if let Err(error) =
+                        to_controller_sender.send(ToControllerMessage::OnFramePresented(frame))
+                    {
+                        log::error!("Verso failed to send OnFramePresented to controller: {error}");
+                    }
+                }
+            }
             return window.resizing;
         }
 
@@ -498,9 +1067,14 @@ impl Verso {
             log::error!("Verso shouldn't be handling messages after compositor has shut down");
             return;
         }
+        if let Some(slept) = self.sleep_detector.check() {
+            log::info!("Verso detected the system slept for {slept:?}");
+            self.resume();
+        }
         let compositor = self.compositor.as_mut().unwrap();
 
         let mut shutdown = false;
+        let mut settled = false;
 
         // Handle Compositor's messages first
         log::trace!("Verso is handling Compositor messages");
@@ -515,14 +1089,39 @@ impl Verso {
             while let Ok(msg) = self.embedder_receiver.try_recv() {
                 messages.push(msg);
             }
+            compositor
+                .event_loop_monitor
+                .queue_drained(EventQueue::Embedder, messages.len());
         }
 
         match compositor.shutdown_state {
             ShutdownState::NotShuttingDown => {
                 for msg in messages {
+                    let message = message_type(&msg);
+                    let handling = Instant::now();
                     if let Some(webview_id) = Self::get_embedder_message_webview_id(&msg) {
-                        for (window, document) in self.windows.values_mut() {
+                        for (window, _) in self.windows.values_mut() {
                             if window.has_webview(*webview_id) {
+                                if let EmbedderMsg::NotifyLoadStatusChanged(
+                                    webview_id,
+                                    LoadStatus::Complete,
+                                ) = &msg
+                                {
+                                    if window.tab_manager.tab(*webview_id).is_some() {
+                                        Self::start_page_settled_waits(
+                                            &mut self.stability_waits,
+                                            &mut self.exit_when_settled,
+                                            &self.config,
+                                            compositor,
+                                            *webview_id,
+                                            window.document_url(*webview_id),
+                                        );
+                                    }
+                                }
+                                if let EmbedderMsg::SetCursor(_, cursor) = msg {
+                                    window.set_cursor(self.cursors.cursor(evl, cursor));
+                                    break;
+                                }
                                 if window.handle_servo_message(
                                     *webview_id,
                                     msg,
@@ -531,6 +1130,7 @@ impl Verso {
                                     self.clipboard.as_mut(),
                                     compositor,
                                     &mut self.bookmark_manager,
+                                    &self.site_settings,
                                 ) {
                                     let mut window = Window::new_with_compositor(
                                         evl,
@@ -538,11 +1138,26 @@ impl Verso {
                                         compositor,
                                         self.verso_internal_sender.clone(),
                                     );
+                                    window.content_settings =
+                                        ContentSettingsStore::new(self.config.content_settings);
+                                    window.color_manager = self.color_manager.clone();
+                                    window.script_policy = self.config.script_policy;
+                                    window.drag_and_drop = self.config.drag_and_drop;
+                                    window.network_contexts = self.network_contexts.clone();
+                                    window.request_scheduler =
+                                        RequestScheduler::new(self.config.prioritize_requests);
+                                    window.recovery = RecoveryEngine::new(
+                                        self.config.recovery.clone(),
+                                        recovery::restart_count(),
+                                    );
+                                    window.gestures = GestureMapper::new(&self.config.gestures);
+                                    window.address_input = self.config.address_input.clone();
                                     window.create_panel(
                                         &self.constellation_sender,
                                         self.config.url.clone(),
                                     );
-                                    let webrender_document = *document;
+                                    let webrender_document =
+                                        compositor.window_document(window.id());
                                     self.windows
                                         .insert(window.id(), (window, webrender_document));
                                 }
@@ -576,6 +1191,11 @@ impl Verso {
                             }
                         }
                     }
+                    compositor.event_loop_monitor.message_handled(
+                        EventQueue::Embedder,
+                        &message,
+                        handling.elapsed(),
+                    );
                 }
             }
             ShutdownState::FinishedShuttingDown => {
@@ -585,33 +1205,495 @@ impl Verso {
         }
 
         if compositor.shutdown_state != ShutdownState::FinishedShuttingDown {
+            if compositor.shutdown_state == ShutdownState::NotShuttingDown {
+                if let Some(event) = compositor.take_device_lost() {
+                    if compositor.can_restart_renderer() {
+                        if let Err(error) = compositor.restart_renderer(&mut self.windows) {
+                            log::error!("Verso failed to restart the renderer: {error}");
+                            self.restart = Some(if self.to_controller_sender.is_some() {
+                                Restart::ExitForController
+                            } else {
+                                Restart::Relaunch
+                            });
+                        }
+                    }
+                    for (window, _) in self.windows.values_mut() {
+                        compositor.swap_current_window(window);
+                        compositor.on_system_resume(window);
+                        window.renderer_restarted();
+                    }
+                    if let Some(to_controller_sender) = &self.to_controller_sender {
+                        if let Err(error) =
+                            to_controller_sender.send(ToControllerMessage::OnDeviceLost(event))
+                        {
+                            log::error!("Verso failed to send OnDeviceLost to controller: {error}")
+                        }
+                    }
+                }
+                if compositor.take_gpu_switch().is_some() {
+                    if let Err(error) = compositor.move_to_gpu(&mut self.windows) {
+                        log::error!("Verso failed to move the renderer to another GPU: {error}");
+                        self.restart = Some(if self.to_controller_sender.is_some() {
+                            Restart::ExitForController
+                        } else {
+                            Restart::Relaunch
+                        });
+                    }
+                    for (window, _) in self.windows.values_mut() {
+                        compositor.swap_current_window(window);
+                        compositor.on_system_resume(window);
+                        window.renderer_restarted();
+                    }
+                }
+                for (window, _) in self.windows.values_mut() {
+                    window.flush_pending_input(&self.constellation_sender, compositor);
+                    window.release_requests(&self.constellation_sender);
+                    window.query_animated_images_in_view(&self.constellation_sender, compositor);
+                    window.drive_playlist(compositor);
+                    window.drive_gestures(compositor, &self.to_controller_sender);
+                    window.report_raw_pointer_samples(&self.to_controller_sender);
+                    if window.recover(compositor, &self.to_controller_sender)
+                        && self.restart.is_none()
+                    {
+                        log::warn!("Verso restarts to recover its webviews");
+                        self.restart = Some(if self.to_controller_sender.is_some() {
+                            Restart::ExitForController
+                        } else {
+                            Restart::Relaunch
+                        });
+                    }
+                }
+                if let Some(soak) = self.soak.as_mut() {
+                    Self::drive_soak(
+                        soak,
+                        &mut self.soak_report,
+                        &mut self.windows,
+                        compositor,
+                        &self.constellation_sender,
+                    );
+                }
+                settled = Self::drive_stability_waits(
+                    &mut self.stability_waits,
+                    compositor,
+                    &self.constellation_sender,
+                    &self.to_controller_sender,
+                );
+            }
             // Update compositor
             compositor.perform_updates(&mut self.windows);
+            Self::deliver_screenshots(compositor, &mut self.windows, &self.to_controller_sender);
+            for metric in compositor.take_paint_metrics() {
+                if let Some(to_controller_sender) = &self.to_controller_sender {
+                    if let Err(error) =
+                        to_controller_sender.send(ToControllerMessage::OnPaintMetric(metric))
+                    {
+                        log::error!("Verso failed to send OnPaintMetric to controller: {error}");
+                    }
+                }
+            }
+            for frame in compositor.take_shared_texture_frames() {
+                if let Some(to_controller_sender) = &self.to_controller_sender {
+                    if let Err(error) =
+                        to_controller_sender.send(ToControllerMessage::OnSharedTextureFrame(frame))
+                    {
+                        log::error!(
+                            "Verso failed to send OnSharedTextureFrame to controller: {error}"
+                        );
+                    }
+                }
+            }
+            if self.safe_mode.is_starting() && compositor.has_presented_frame() {
+                Self::startup_succeeded(&mut self.safe_mode, &self.to_controller_sender);
+            }
         } else {
             shutdown = true;
         }
 
         // Check if Verso need to start shutting down.
-        if self.windows.is_empty() {
-            self.compositor
-                .as_mut()
-                .map(IOCompositor::maybe_start_shutting_down);
+        if self.windows.is_empty()
+            || self.soak_report.is_some()
+            || self.restart.is_some()
+            || settled
+        {
+            self.begin_shutdown();
+        }
+        if !shutdown && self.shutdown.is_overdue(Instant::now()) {
+            log::error!("Verso didn't shut down before the deadline, forcing the teardown");
+            shutdown = true;
         }
 
         // Check compositor status and set control flow.
         if shutdown {
             // If Compositor has shut down, deinit and remove it.
+            self.shutdown
+                .enter(ShutdownPhase::WebRender, &self.to_controller_sender);
             if let Some(mut compositor) = self.compositor.take() {
                 IOCompositor::deinit(&mut compositor)
             }
+            self.shutdown
+                .enter(ShutdownPhase::Finished, &self.to_controller_sender);
             evl.exit();
         } else if self.is_animating() {
             evl.set_control_flow(ControlFlow::Poll);
+        } else if let Some(deadline) = self
+            .windows
+            .values()
+            .flat_map(|(window, _)| {
+                [
+                    window.pending_input_deadline(),
+                    window.request_scheduler.next_deadline(),
+                    window.next_animated_images_query(),
+                    window.recovery.next_deadline(),
+                    window
+                        .playlist
+                        .as_ref()
+                        .and_then(PlaylistPlayer::next_deadline),
+                    window.gestures.next_deadline(),
+                ]
+            })
+            .flatten()
+            .chain(self.soak.as_ref().map(SoakRunner::next_deadline))
+            .chain(self.shutdown.deadline())
+            .chain(
+                self.stability_waits
+                    .iter()
+                    .map(|(_, wait)| wait.next_deadline()),
+            )
+            .chain(
+                self.compositor
+                    .as_ref()
+                    .and_then(IOCompositor::next_pinch_zoom_frame),
+            )
+            .chain(
+                self.compositor
+                    .as_ref()
+                    .and_then(IOCompositor::next_smooth_scroll_frame),
+            )
+            .chain(
+                self.compositor
+                    .as_ref()
+                    .and_then(IOCompositor::next_stats_hud_refresh),
+            )
+            .chain(
+                self.compositor
+                    .as_ref()
+                    .and_then(IOCompositor::next_animated_image_frame),
+            )
+            .min()
+        {
+            // Wake up to deliver the tail of a resampled mouse burst, to let held requests
+            // through, to probe webviews or recover them, for the next soak test step, to probe
+            // pages waited on until they're stable, for the next frame of a pinch zoom reset, a
+            // smooth scroll or an animated image, to ask pages where their animated images are,
+            // or to draw the stats in the HUD again.
+            evl.set_control_flow(ControlFlow::WaitUntil(deadline));
         } else {
             evl.set_control_flow(ControlFlow::Wait);
         }
     }
 
+    /// Clear the startup marker once the first frame is composited, and tell the controller if this run is in safe mode.
+    fn startup_succeeded(
+        safe_mode: &mut SafeMode,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
+    ) {
+        let Some(report) = safe_mode.startup_succeeded() else {
+            return;
+        };
+        if let Some(to_controller_sender) = to_controller_sender {
+            if let Err(error) =
+                to_controller_sender.send(ToControllerMessage::OnSafeMode(report.clone()))
+            {
+                log::error!("Verso failed to send OnSafeMode to controller: {error}")
+            }
+        }
+    }
+
+    /// Wait on the page a tab finished loading, for the controller's page settled events and for the `-x` flag.
+    fn start_page_settled_waits(
+        stability_waits: &mut Vec<(StabilityWaiter, StabilityWait)>,
+        exit_when_settled: &mut bool,
+        config: &Config,
+        compositor: &mut IOCompositor,
+        webview_id: WebViewId,
+        url: Option<url::Url>,
+    ) {
+        let now = Instant::now();
+        if let Some(options) = &config.page_settled {
+            // A new page replaces the one the tab was still waiting on.
+            stability_waits.retain(|(waiter, wait)| {
+                !matches!(waiter, StabilityWaiter::Embedder(_)) || wait.webview_id != webview_id
+            });
+            let wait = StabilityWait::new(
+                webview_id,
+                options.clone(),
+                &compositor.frame_stability,
+                now,
+            );
+            stability_waits.push((StabilityWaiter::Embedder(url), wait));
+        }
+        if std::mem::take(exit_when_settled) {
+            let options = config.page_settled.clone().unwrap_or_default();
+            let wait = StabilityWait::new(webview_id, options, &compositor.frame_stability, now);
+            stability_waits.push((StabilityWaiter::Exit, wait));
+        }
+        compositor.frame_stability.tracking = !stability_waits.is_empty();
+    }
+
+    /// Probe the pages waited on that are due, and tell the controller about those that got stable or were given up on.
+    ///
+    /// Returns whether Verso should exit because the page the `-x` flag waited on settled.
+    fn drive_stability_waits(
+        stability_waits: &mut Vec<(StabilityWaiter, StabilityWait)>,
+        compositor: &mut IOCompositor,
+        constellation_sender: &Sender<EmbedderToConstellationMessage>,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
+    ) -> bool {
+        let now = Instant::now();
+        let mut exit = false;
+        stability_waits.retain_mut(|(waiter, wait)| {
+            if !wait.probe_due(now) {
+                return true;
+            }
+            let page = readiness::page_state(constellation_sender, wait.webview_id);
+            let frames_settled = compositor.frames_settled();
+            let Some(result) = wait.check(now, page, frames_settled, &compositor.frame_stability)
+            else {
+                compositor.composite_for_stability();
+                return true;
+            };
+            let message = match waiter {
+                StabilityWaiter::Controller(id) => {
+                    ToControllerMessage::WaitUntilStableResponse(*id, result)
+                }
+                StabilityWaiter::Embedder(url) => ToControllerMessage::OnPageSettled(PageSettled {
+                    webview: format!("{:?}", wait.webview_id),
+                    url: url.take(),
+                    result,
+                }),
+                StabilityWaiter::Exit => {
+                    if let Err(error) = result {
+                        log::warn!("Verso exits anyway: {error}");
+                    }
+                    println!(
+                        "Shutting down the Constellation after generating an output file or exit flag specified"
+                    );
+                    exit = true;
+                    return false;
+                }
+            };
+            if let Some(sender) = to_controller_sender {
+                if let Err(error) = sender.send(message) {
+                    log::error!("Verso failed to tell the controller a page is stable: {error}");
+                }
+            }
+            false
+        });
+        compositor.frame_stability.tracking = !stability_waits.is_empty();
+        exit
+    }
+
+    /// Answer the controller with the screenshots taken, resizing back the webviews captured in full.
+    fn deliver_screenshots(
+        compositor: &mut IOCompositor,
+        windows: &mut HashMap<WindowId, (Window, DocumentId)>,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
+    ) {
+        for (request, result) in compositor.screenshots.take_finished() {
+            if let Some(rect) = request.restore {
+                if let Some((window, _)) = windows
+                    .values_mut()
+                    .find(|(window, _)| window.tab_manager.tab(request.webview_id).is_some())
+                {
+                    window.tab_manager.set_size(request.webview_id, rect);
+                    compositor.on_resize_webview_event(request.webview_id, rect);
+                    compositor.send_root_pipeline_display_list(window);
+                }
+            }
+            if let Some(sender) = to_controller_sender {
+                if let Err(error) = sender.send(ToControllerMessage::CaptureScreenshotResponse(
+                    request.id, result,
+                )) {
+                    log::error!(
+                        "Verso failed to send CaptureScreenshotResponse to controller: {error}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Queue a capture of the current webview of the first window, answered once it is composited.
+    fn capture_screenshot(
+        &mut self,
+        id: uuid::Uuid,
+        options: ScreenshotOptions,
+    ) -> Result<(), String> {
+        let compositor = self
+            .compositor
+            .as_mut()
+            .ok_or("the compositor isn't running")?;
+        let (window, _) = self
+            .windows
+            .values_mut()
+            .next()
+            .ok_or("there is no window")?;
+        let tab = window
+            .tab_manager
+            .current_tab()
+            .ok_or("there is no webview to capture")?;
+        let (webview_id, rect) = (tab.id(), tab.webview().rect);
+        if !options.full_page {
+            compositor.queue_screenshot(
+                ScreenshotRequest::new(id, webview_id, rect).format(options.format),
+            );
+            return Ok(());
+        }
+
+        // The capture is queued once the page answered its height.
+        let answer =
+            execute_script_deferred(&self.constellation_sender, &webview_id, PAGE_HEIGHT_SCRIPT)
+                .ok_or("the page height can't be read")?;
+        let verso_internal_sender = window.verso_internal_sender.clone();
+        let format = options.format;
+        ROUTER.add_typed_route(
+            answer,
+            Box::new(move |result| {
+                let page_height = match result {
+                    Ok(Ok(WebDriverJSValue::String(height))) => height
+                        .parse::<f32>()
+                        .map_err(|error| format!("the page height {height} is invalid: {error}")),
+                    result => Err(format!("the page height can't be read: {result:?}")),
+                };
+                let _ = verso_internal_sender.send(VersoInternalMsg::PageHeight(
+                    id,
+                    webview_id,
+                    format,
+                    page_height,
+                ));
+            }),
+        );
+        Ok(())
+    }
+
+    /// Queue a full page capture of the webview once its page answered its height.
+    fn capture_full_page(
+        &mut self,
+        id: uuid::Uuid,
+        webview_id: WebViewId,
+        format: ScreenshotFormat,
+        page_height: Result<f32, String>,
+    ) -> Result<(), String> {
+        let page_height = page_height?;
+        let compositor = self
+            .compositor
+            .as_mut()
+            .ok_or("the compositor isn't running")?;
+        let (window, _) = self
+            .windows
+            .values_mut()
+            .find(|(window, _)| window.has_webview(webview_id))
+            .ok_or("the webview to capture was closed")?;
+        let rect = window
+            .tab_manager
+            .tab(webview_id)
+            .ok_or("the webview to capture was closed")?
+            .webview()
+            .rect;
+        let request = ScreenshotRequest::new(id, webview_id, rect).format(format);
+        let full_page = compositor.full_page_rect(rect, page_height);
+        window.tab_manager.set_size(webview_id, full_page);
+        compositor.on_resize_webview_event(webview_id, full_page);
+        compositor.queue_screenshot(request.full_page(full_page, rect));
+        compositor.send_root_pipeline_display_list(window);
+        Ok(())
+    }
+
+    /// Answer the controller's capture request with an error.
+    fn send_screenshot_error(&self, id: uuid::Uuid, error: String) {
+        if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+            ToControllerMessage::CaptureScreenshotResponse(id, Err(error)),
+        ) {
+            log::error!("Verso failed to send CaptureScreenshotResponse to controller: {error}")
+        }
+    }
+
+    /// Run the soak test actions and memory samples that are due, and finish it once its time is up.
+    fn drive_soak(
+        soak: &mut SoakRunner,
+        soak_report: &mut Option<SoakReport>,
+        windows: &mut HashMap<WindowId, (Window, DocumentId)>,
+        compositor: &mut IOCompositor,
+        constellation_sender: &Sender<EmbedderToConstellationMessage>,
+    ) {
+        if soak_report.is_some() {
+            return;
+        }
+        let now = Instant::now();
+        if soak.sample_due(now) {
+            soak.record_sample(
+                now,
+                resident_set_size(),
+                compositor.tracked_resource_count() as u64,
+            );
+        }
+        if soak.is_finished(now) {
+            let report = soak.report();
+            log::info!("{report}");
+            soak_report.replace(report);
+            return;
+        }
+
+        let Some(action) = soak.next_action(now) else {
+            return;
+        };
+        let Some((window, _)) = windows.values_mut().next() else {
+            return;
+        };
+        log::debug!("Soak action: {action:?}");
+        match action {
+            SoakAction::Navigate(url) => {
+                if let Some(webview_id) = window.tab_manager.current_tab_id() {
+                    send_to_constellation(
+                        constellation_sender,
+                        EmbedderToConstellationMessage::LoadUrl(
+                            webview_id,
+                            ServoUrl::from_url(url),
+                        ),
+                    );
+                }
+            }
+            SoakAction::Scroll => {
+                if let Some(webview_id) = window.tab_manager.current_tab_id() {
+                    execute_script_async(
+                        constellation_sender,
+                        &webview_id,
+                        "window.scrollBy(0, window.innerHeight)",
+                    );
+                }
+            }
+            SoakAction::CreateWebView(url) => {
+                window.create_tab(constellation_sender, ServoUrl::from_url(url));
+            }
+            SoakAction::CloseWebView => {
+                if window.tab_manager.count() > 1 {
+                    if let Some(tab_id) = window.tab_manager.tab_ids().last().copied() {
+                        window.close_tab(compositor, tab_id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Outcome of the soak test, once it finished.
+    pub fn soak_report(&self) -> Option<&SoakReport> {
+        self.soak_report.as_ref()
+    }
+
+    /// How to restart once shut down, if a recovery policy asked for it.
+    pub fn restart(&self) -> Option<Restart> {
+        self.restart
+    }
+
     /// Handle message from the Verso internal channel
     pub fn handle_verso_internal_message(&mut self, message: VersoInternalMsg) {
         match message {
@@ -676,6 +1758,99 @@ impl Verso {
                     log::error!("Failed to rename bookmarks");
                 }
             }
+            VersoInternalMsg::UpdateAuditPage(sender) => {
+                if let Ok(audit_json) = serde_json::to_string(&self.audit_entries()) {
+                    let _ = sender.send(PromptResponse::Ok(audit_json));
+                } else {
+                    log::error!("Failed to serialize the audit log");
+                    let _ = sender.send(PromptResponse::Cancel);
+                }
+            }
+            VersoInternalMsg::UpdateGlContextPage(sender) => {
+                match serde_json::to_string(&self.gl_context_info()) {
+                    Ok(info_json) => {
+                        let _ = sender.send(PromptResponse::Ok(info_json));
+                    }
+                    Err(error) => {
+                        log::error!("Failed to serialize the GL context attributes: {error}");
+                        let _ = sender.send(PromptResponse::Cancel);
+                    }
+                }
+            }
+            VersoInternalMsg::ViewportImages(webview_id, images) => {
+                if let Some((window, _)) = self
+                    .windows
+                    .values_mut()
+                    .find(|(window, _)| window.has_webview(webview_id))
+                {
+                    window.set_viewport_images(webview_id, images);
+                }
+            }
+            VersoInternalMsg::CspViolations(webview_id, document_url, report) => {
+                if let Some((window, _)) = self
+                    .windows
+                    .values_mut()
+                    .find(|(window, _)| window.has_webview(webview_id))
+                {
+                    window
+                        .audit_log
+                        .add_csp_violations(webview_id, document_url, &report);
+                }
+            }
+            VersoInternalMsg::AnimatedImage(webview_id, animation) => {
+                let Some(compositor) = &mut self.compositor else {
+                    return;
+                };
+                compositor.add_animated_image(webview_id, *animation);
+                if let Some((window, _)) = self
+                    .windows
+                    .values_mut()
+                    .find(|(window, _)| window.has_webview(webview_id))
+                {
+                    window.animated_images_stale = true;
+                }
+            }
+            VersoInternalMsg::AnimatedImagesInView(webview_id, images) => {
+                if let (Some(compositor), Some(images)) = (&mut self.compositor, images) {
+                    compositor
+                        .set_animated_images_in_view(webview_id, &parse_viewport_images(&images));
+                }
+                if let Some((window, _)) = self
+                    .windows
+                    .values_mut()
+                    .find(|(window, _)| window.has_webview(webview_id))
+                {
+                    window.animated_images_querying = false;
+                }
+            }
+            VersoInternalMsg::FirstViewportComplete(webview_id, milliseconds) => {
+                if let Some((window, _)) = self
+                    .windows
+                    .values_mut()
+                    .find(|(window, _)| window.has_webview(webview_id))
+                {
+                    window.record_first_viewport(webview_id, milliseconds);
+                }
+            }
+            VersoInternalMsg::IntegrityFailures(webview_id, document_url, failures) => {
+                if let Some((window, _)) = self
+                    .windows
+                    .values_mut()
+                    .find(|(window, _)| window.has_webview(webview_id))
+                {
+                    window.report_integrity_failures(
+                        webview_id,
+                        document_url,
+                        &failures,
+                        &self.to_controller_sender,
+                    );
+                }
+            }
+            VersoInternalMsg::PageHeight(id, webview_id, format, page_height) => {
+                if let Err(error) = self.capture_full_page(id, webview_id, format, page_height) {
+                    self.send_screenshot_error(id, error);
+                }
+            }
         }
     }
 
@@ -738,9 +1913,13 @@ impl Verso {
     pub fn handle_incoming_webview_message(&mut self, message: ToVersoMessage) {
         match message {
             ToVersoMessage::Exit => {
-                if let Some(compositor) = &mut self.compositor {
-                    compositor.maybe_start_shutting_down();
-                }
+                self.begin_shutdown();
+            }
+            ToVersoMessage::Suspend => {
+                self.suspend();
+            }
+            ToVersoMessage::Resume => {
+                self.resume();
             }
             ToVersoMessage::ListenToOnCloseRequested => {
                 if let Some(window) = self.first_window_mut() {
@@ -772,6 +1951,9 @@ impl Verso {
                 }
             }
             ToVersoMessage::OnNavigationStartingResponse(id, allow) => {
+                for (window, _) in self.windows.values_mut() {
+                    window.audit_log.navigation_answered(&id, allow);
+                }
                 send_to_constellation(
                     &self.constellation_sender,
                     EmbedderToConstellationMessage::AllowNavigationResponse(
@@ -964,6 +2146,309 @@ impl Verso {
                     }
                 }
             }
+            ToVersoMessage::SetNetworkContext(context) => {
+                if let Some(webview_id) = self.first_webview_id() {
+                    if let Some(window) = self.first_window_mut() {
+                        if let Err(error) = window.network_contexts.assign(webview_id, context) {
+                            log::error!("Verso failed to set the network context: {error}");
+                        }
+                    }
+                }
+            }
+            ToVersoMessage::SetContentSettings(settings) => {
+                self.config.content_settings = settings;
+                for (window, _) in self.windows.values_mut() {
+                    window.content_settings.set_all(settings);
+                }
+            }
+            ToVersoMessage::SetLaunchParams(params) => {
+                if let Some(webview_id) = self.first_webview_id() {
+                    if let Some(window) = self.first_window_mut() {
+                        window.launch_params.set(webview_id, params);
+                    }
+                }
+            }
+            ToVersoMessage::GetAuditLog(id) => {
+                let entries = self.audit_entries();
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::GetAuditLogResponse(id, entries))
+                {
+                    log::error!("Verso failed to send GetAuditLogResponse to controller: {error}")
+                }
+            }
+            ToVersoMessage::ExportSession(id) => {
+                let session = self.export_session();
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::ExportSessionResponse(id, session))
+                {
+                    log::error!("Verso failed to send ExportSessionResponse to controller: {error}")
+                }
+            }
+            ToVersoMessage::GetGlContextInfo(id) => {
+                if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+                    ToControllerMessage::GetGlContextInfoResponse(id, self.gl_context_info()),
+                ) {
+                    log::error!(
+                        "Verso failed to send GetGlContextInfoResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::GetEventLoopStats(id) => {
+                if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+                    ToControllerMessage::GetEventLoopStatsResponse(id, self.event_loop_stats()),
+                ) {
+                    log::error!(
+                        "Verso failed to send GetEventLoopStatsResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::ControlPlaylist(command) => {
+                self.control_playlist(command);
+            }
+            ToVersoMessage::GetPlaylistStatus(id) => {
+                if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+                    ToControllerMessage::GetPlaylistStatusResponse(id, self.playlist_status()),
+                ) {
+                    log::error!(
+                        "Verso failed to send GetPlaylistStatusResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::GetSafeModeReport(id) => {
+                if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+                    ToControllerMessage::GetSafeModeReportResponse(
+                        id,
+                        self.safe_mode_report().cloned(),
+                    ),
+                ) {
+                    log::error!(
+                        "Verso failed to send GetSafeModeReportResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::DumpFlightRecorder(id) => {
+                let dump = match self.compositor.as_mut() {
+                    Some(compositor) => compositor.flight_recorder.dump("controller"),
+                    None => Err("the compositor isn't running".to_string()),
+                };
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::DumpFlightRecorderResponse(id, dump))
+                {
+                    log::error!(
+                        "Verso failed to send DumpFlightRecorderResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::ValidateRendering(id) => {
+                let report = match self.compositor.as_mut() {
+                    Some(compositor) => match self.windows.get(&compositor.current_window) {
+                        Some((window, _)) => compositor.validate_rendering(window),
+                        None => Err("no window is being composited".to_string()),
+                    },
+                    None => Err("the compositor isn't running".to_string()),
+                };
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::ValidateRenderingResponse(id, report))
+                {
+                    log::error!(
+                        "Verso failed to send ValidateRenderingResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::WaitUntilStable(id, options) => {
+                let error = match (self.first_webview_id(), self.compositor.as_mut()) {
+                    (Some(webview_id), Some(compositor)) => {
+                        compositor.frame_stability.tracking = true;
+                        let wait = StabilityWait::new(
+                            webview_id,
+                            options,
+                            &compositor.frame_stability,
+                            Instant::now(),
+                        );
+                        self.stability_waits
+                            .push((StabilityWaiter::Controller(id), wait));
+                        return;
+                    }
+                    (None, _) => "there is no webview to wait on",
+                    (_, None) => "the compositor isn't running",
+                };
+                if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+                    ToControllerMessage::WaitUntilStableResponse(id, Err(error.to_string())),
+                ) {
+                    log::error!(
+                        "Verso failed to send WaitUntilStableResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::SetGestureBindings(bindings) => {
+                for (window, _) in self.windows.values_mut() {
+                    window.gestures.set_bindings(&bindings);
+                }
+                self.config.gestures = bindings;
+            }
+            ToVersoMessage::ListenToFramePresented => {
+                if let Some(compositor) = self.compositor.as_mut() {
+                    compositor.report_presented_frames = true;
+                }
+            }
+            ToVersoMessage::ListenToRawPointerSamples => {
+                if let Some(window) = self.first_window_mut() {
+                    window.event_listeners.on_raw_pointer_samples = true;
+                }
+            }
+            ToVersoMessage::ListenToShutdownProgress => {
+                self.shutdown.report_progress = true;
+            }
+            ToVersoMessage::SetCursorTheme(theme) => {
+                self.cursors.set_theme(theme.clone());
+                self.config.cursor_theme = theme;
+            }
+            ToVersoMessage::SetAddressInput(settings) => {
+                for (window, _) in self.windows.values_mut() {
+                    window.address_input = settings.clone();
+                }
+                self.config.address_input = settings;
+            }
+            ToVersoMessage::InterpretAddressInput(id, input) => {
+                let target = address_input::interpret(&input, &self.config.address_input);
+                if let Err(error) = self.to_controller_sender.as_ref().unwrap().send(
+                    ToControllerMessage::InterpretAddressInputResponse(id, target),
+                ) {
+                    log::error!(
+                        "Verso failed to send InterpretAddressInputResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::SetTabRect(webview, rect) => match self.tab_id(&webview) {
+                Some(tab_id) => self.set_tab_rect(tab_id, rect),
+                None => log::warn!("Verso ignores the rect of {webview}, there's no such tab"),
+            },
+            ToVersoMessage::SetPageZoom(webview, factor) => match self.tab_id(&webview) {
+                Some(tab_id) => self.set_page_zoom(tab_id, factor),
+                None => log::warn!("Verso ignores the page zoom of {webview}, there's no such tab"),
+            },
+            ToVersoMessage::ToggleStatsHud => {
+                if let Some(compositor) = self.compositor.as_mut() {
+                    if let Some((window, _)) = self.windows.get(&compositor.current_window) {
+                        compositor.toggle_stats_hud(window);
+                    }
+                }
+            }
+            ToVersoMessage::SetMouseToTouch(enabled) => {
+                if let Some(compositor) = self.compositor.as_mut() {
+                    compositor.set_mouse_to_touch(enabled);
+                }
+            }
+            ToVersoMessage::ToggleWebRenderDebug(option) => {
+                if let Some(compositor) = self.compositor.as_mut() {
+                    compositor.toggle_webrender_debug(option);
+                }
+            }
+            ToVersoMessage::CaptureWebRenderFrame(directory) => {
+                if let Some(compositor) = self.compositor.as_mut() {
+                    compositor.capture_webrender_frame(directory);
+                }
+            }
+            ToVersoMessage::ReleaseSharedTexture(buffer) => {
+                if let Some(compositor) = self.compositor.as_mut() {
+                    compositor.release_shared_texture(buffer);
+                    // A frame may be waiting for the texture.
+                    for (window, _) in self.windows.values() {
+                        window.window.request_redraw();
+                    }
+                }
+            }
+            ToVersoMessage::SetRendererOption(name, value) => {
+                match RuntimeRendererOption::parse(&name, &value) {
+                    Ok(option) => {
+                        if let Some(compositor) = self.compositor.as_mut() {
+                            compositor.set_renderer_option(option);
+                        }
+                    }
+                    Err(e) => log::warn!("Ignoring renderer option {name}={value}: {e}"),
+                }
+            }
+            ToVersoMessage::ResetPinchZoom(index) => {
+                let tab_id = self
+                    .windows
+                    .values()
+                    .next()
+                    .and_then(|(window, _)| window.tab_manager.tab_ids().get(index).copied());
+                match (self.compositor.as_mut(), tab_id) {
+                    (Some(compositor), Some(tab_id)) => compositor.reset_pinch_zoom(tab_id),
+                    (_, None) => log::warn!(
+                        "Verso ignores the pinch zoom reset of tab {index}, the window has no such tab"
+                    ),
+                    (None, _) => {}
+                }
+            }
+            ToVersoMessage::CaptureScreenshot(id, options) => {
+                if let Err(error) = self.capture_screenshot(id, options) {
+                    self.send_screenshot_error(id, error);
+                }
+            }
+            ToVersoMessage::StartRecording(settings) => {
+                let started = match self.compositor.as_mut() {
+                    Some(compositor) => compositor.start_recording(settings),
+                    None => Err("the compositor isn't running".to_string()),
+                };
+                if let Err(error) = started {
+                    log::error!("Verso failed to start recording frames: {error}");
+                }
+            }
+            ToVersoMessage::StopRecording(id) => {
+                let report = match self.compositor.as_mut() {
+                    Some(compositor) => compositor.stop_recording(),
+                    None => Err("the compositor isn't running".to_string()),
+                };
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::StopRecordingResponse(id, report))
+                {
+                    log::error!("Verso failed to send StopRecordingResponse to controller: {error}")
+                }
+            }
+            ToVersoMessage::HitTest(id, webview, point) => {
+                let point = DevicePoint::new(point.x as f32, point.y as f32);
+                let result = self
+                    .tab_id(&webview)
+                    .and_then(|tab_id| self.hit_test(tab_id, point));
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::HitTestResponse(id, result))
+                {
+                    log::error!("Verso failed to send HitTestResponse to controller: {error}")
+                }
+            }
+            ToVersoMessage::ListenToSecurityPolicyReports => {
+                if let Some(window) = self.first_window_mut() {
+                    window.event_listeners.on_security_policy_report = true;
+                }
+            }
+            ToVersoMessage::SetSitePolicy(origin, policy) => {
+                self.site_settings.set_override(&origin, policy);
+                if let Some(site_settings_storage) = self.storage.site_settings_storage() {
+                    if let Err(error) = site_settings_storage.save_to_file(&self.site_settings) {
+                        log::error!("Verso failed to save site settings: {error}");
+                    }
+                }
+            }
             ToVersoMessage::GetCurrentUrl(id) => {
                 if let Some(window) = self.first_window() {
                     let tab = window.tab_manager.current_tab().unwrap();
@@ -980,6 +2465,115 @@ impl Verso {
                     }
                 }
             }
+            ToVersoMessage::SetTextRendering(text_rendering) => {
+                if let Some(compositor) = &mut self.compositor {
+                    compositor.set_text_rendering(text_rendering);
+                }
+            }
+            ToVersoMessage::RasterizeSvg(id, request) => {
+                let scale_factor = request.scale_factor.unwrap_or_else(|| {
+                    self.first_window()
+                        .map_or(1.0, |window| window.window.scale_factor())
+                });
+                let size = RasterSize::new(request.width, request.height, scale_factor);
+                let to_controller_sender = self.to_controller_sender.clone().unwrap();
+                // Large SVGs can take a while, keep the event loop responsive.
+                std::thread::spawn(move || {
+                    let png = SvgRasterizer::shared()
+                        .rasterize(&request.svg, size)
+                        .map(|png| png.to_vec());
+                    if let Err(error) = to_controller_sender
+                        .send(ToControllerMessage::RasterizeSvgResponse(id, png))
+                    {
+                        log::error!(
+                            "Verso failed to send RasterizeSvgResponse to controller: {error}"
+                        )
+                    }
+                });
+            }
+            ToVersoMessage::GetScriptPolicy(id, url) => {
+                let policy = script_policy::effective_policy(
+                    &self.config.script_policy,
+                    &self.site_settings,
+                    &url,
+                );
+                if let Err(error) = self
+                    .to_controller_sender
+                    .as_ref()
+                    .unwrap()
+                    .send(ToControllerMessage::GetScriptPolicyResponse(id, policy))
+                {
+                    log::error!(
+                        "Verso failed to send GetScriptPolicyResponse to controller: {error}"
+                    )
+                }
+            }
+            ToVersoMessage::GetConnections(id) => {
+                let to_controller_sender = self.to_controller_sender.clone().unwrap();
+                let respond = move |connections| {
+                    if let Err(error) = to_controller_sender
+                        .send(ToControllerMessage::GetConnectionsResponse(id, connections))
+                    {
+                        log::error!(
+                            "Verso failed to send GetConnectionsResponse to controller: {error}"
+                        )
+                    }
+                };
+                match self.first_webview_id() {
+                    Some(webview_id) => {
+                        connections::connections(&self.constellation_sender, webview_id, respond)
+                    }
+                    None => respond(Vec::new()),
+                }
+            }
+            ToVersoMessage::SetCoverage(collecting) => {
+                if let Some(webview_id) = self.first_webview_id() {
+                    coverage::set_collecting(&self.constellation_sender, webview_id, collecting);
+                    if let Some(window) = self.first_window_mut() {
+                        if collecting {
+                            window.coverage.insert(webview_id);
+                        } else {
+                            window.coverage.remove(&webview_id);
+                        }
+                    }
+                }
+            }
+            ToVersoMessage::GetCoverage(id) => {
+                let to_controller_sender = self.to_controller_sender.clone().unwrap();
+                let respond = move |report| {
+                    if let Err(error) = to_controller_sender
+                        .send(ToControllerMessage::GetCoverageResponse(id, report))
+                    {
+                        log::error!(
+                            "Verso failed to send GetCoverageResponse to controller: {error}"
+                        )
+                    }
+                };
+                match self.first_webview_id() {
+                    Some(webview_id) => {
+                        coverage::coverage(&self.constellation_sender, webview_id, respond)
+                    }
+                    None => respond(None),
+                }
+            }
+            ToVersoMessage::CloseConnection(connection_id) => {
+                if let Some(webview_id) = self.first_webview_id() {
+                    connections::close_connection(
+                        &self.constellation_sender,
+                        webview_id,
+                        connection_id,
+                        move |closed| {
+                            if closed {
+                                log::info!(
+                                    "Verso closed connection {connection_id} of {webview_id:?}"
+                                );
+                            } else {
+                                log::warn!("Verso found no connection {connection_id} to close");
+                            }
+                        },
+                    );
+                }
+            }
             _ => {}
         }
     }
@@ -992,6 +2586,30 @@ impl Verso {
         self.windows.values_mut().next().map(|(window, _)| window)
     }
 
+    /// Blocking decisions of every window, oldest first
+    ///
+    /// The pages are asked for their latest CSP violations, they are in the log from the next read.
+    fn audit_entries(&self) -> Vec<AuditEntry> {
+        for (window, _) in self.windows.values() {
+            window.request_all_csp_violations(&self.constellation_sender);
+        }
+        let mut entries: Vec<_> = self
+            .windows
+            .values()
+            .flat_map(|(window, _)| window.audit_log.entries().cloned())
+            .collect();
+        entries.sort_by_key(|entry| entry.timestamp_ms);
+        entries
+    }
+
+    /// The tab identified like [`AuditEntry::webview`], in any window.
+    fn tab_id(&self, webview: &str) -> Option<WebViewId> {
+        self.windows
+            .values()
+            .flat_map(|(window, _)| window.tab_manager.tab_ids())
+            .find(|tab_id| format!("{tab_id:?}") == webview)
+    }
+
     fn first_webview_id(&self) -> Option<WebViewId> {
         self.windows
             .values()
@@ -1126,6 +2744,37 @@ impl webrender::api::RenderNotifier for RenderNotifier {
     }
 }
 
+/// Options of the WebRender renderer, with the overrides of the controller
+fn webrender_options(renderer_options: &RendererOptionsConfig) -> WebRenderOptions {
+    let opts = opts::get();
+    let mut debug_flags = DebugFlags::empty();
+    debug_flags.set(DebugFlags::PROFILER_DBG, opts.debug.webrender_stats);
+
+    // Clear to transparent so transparent windows keep the alpha of the pages.
+    let clear_color = ColorF::new(0., 0., 0., 0.);
+    let mut webrender_options = WebRenderOptions {
+        // We force the use of optimized shaders here because rendering is broken
+        // on Android emulators with unoptimized shaders. This is due to a known
+        // issue in the emulator's OpenGL emulation layer.
+        // See: https://github.com/servo/servo/issues/31726
+        use_optimized_shaders: true,
+        resource_override_path: opts.shaders_dir.clone(),
+        debug_flags,
+        precache_flags: if pref!(gfx_precache_shaders) {
+            ShaderPrecacheFlags::FULL_COMPILE
+        } else {
+            ShaderPrecacheFlags::empty()
+        },
+        enable_aa: pref!(gfx_text_antialiasing_enabled),
+        enable_subpixel_aa: pref!(gfx_subpixel_text_antialiasing_enabled),
+        allow_texture_swizzling: pref!(gfx_texture_swizzling_enabled),
+        clear_color,
+        ..Default::default()
+    };
+    renderer_options.apply_to(&mut webrender_options);
+    webrender_options
+}
+
 // A logger that logs to two downstream loggers.
 // This should probably be in the log crate.
 struct BothLogger<Log1, Log2>(Log1, Log2);
@@ -387,7 +387,8 @@ pub fn init_webgl(config: &WebGLConfig, gl: &dyn gl::Gl) -> WebGLInitResult {
         log::warn!("WebGL 2.0 requested but not available, falling back to WebGL 1.0");
     }
 
-    if let Some(entry) = is_gpu_blocked(&vendor, &renderer, &default_gpu_blocklist(), actual_version)
+    if let Some(entry) =
+        is_gpu_blocked(&vendor, &renderer, &default_gpu_blocklist(), actual_version)
     {
         log::warn!("WebGL blocked on this GPU: {}", entry.reason);
         return WebGLInitResult::Failed {
@@ -620,11 +621,11 @@ mod tests {
             assert_eq!(parse_gl_major_version("4.6.0 NVIDIA 535.154.05"), Some(4));
             assert_eq!(parse_gl_major_version("3.3 Mesa 23.0.4"), Some(3));
             assert_eq!(parse_gl_major_version("2.1 Mesa 23.0.4"), Some(2));
-            
+
             // OpenGL ES version strings
             assert_eq!(parse_gl_major_version("OpenGL ES 3.2 Mesa 23.0.4"), Some(3));
             assert_eq!(parse_gl_major_version("OpenGL ES 2.0 Mesa 23.0.4"), Some(2));
-            
+
             // Edge cases
             assert_eq!(parse_gl_major_version(""), None);
             assert_eq!(parse_gl_major_version("no version here"), None);
@@ -0,0 +1,243 @@
+//! Clock and locale overrides
+//!
+//! Lets tests and demo kiosks pin the time zone, locale and clocks pages see
+//! without changing the host. SpiderMonkey takes its time zone and default
+//! locale from the process and Servo's time source can't be shifted, so a
+//! document start user script replaces `Date`, the `Intl` constructors, the
+//! `toLocale*String` methods and `navigator.language` with versions using the
+//! override, and shifts `Date.now()` and `performance.now()`. It runs before
+//! any script of the page, in frames too.
+//!
+//! User scripts are installed when the constellation starts, so the override
+//! is set for the whole profile and can't change while Verso runs. Workers
+//! still see the host's clock and zone, as do event and animation frame
+//! timestamps. The `Accept-Language` header isn't changed. An invalid time
+//! zone or locale is ignored with a console warning.
+
+use embedder_traits::user_content_manager::UserScript;
+use versoview_messages::ClockOverride;
+
+/// Replaces the clock, time zone and locale APIs with ones using OVERRIDE
+const CLOCK_OVERRIDE_SCRIPT: &str = r#"
+(() => {
+    if (window.__versoClockOverride) {
+        return;
+    }
+    Object.defineProperty(window, "__versoClockOverride", { value: true });
+    const config = OVERRIDE;
+    const NativeDate = Date;
+    const nativeNow = NativeDate.now;
+    const nativeParse = NativeDate.parse;
+    const proto = NativeDate.prototype;
+    const native = {};
+    for (const name of Object.getOwnPropertyNames(proto)) {
+        native[name] = proto[name];
+    }
+    const NativeDateTimeFormat = Intl.DateTimeFormat;
+
+    let timeZone = config.time_zone;
+    if (timeZone) {
+        try {
+            new NativeDateTimeFormat("en-US", { timeZone });
+        } catch (error) {
+            console.warn(`Verso ignores the invalid time zone override ${timeZone}`);
+            timeZone = null;
+        }
+    }
+    let locale = config.locale;
+    if (locale) {
+        try {
+            locale = Intl.getCanonicalLocales(locale)[0];
+        } catch (error) {
+            console.warn(`Verso ignores the invalid locale override ${locale}`);
+            locale = null;
+        }
+    }
+
+    // Clocks
+    const skew = (config.start_time_ms === null ? 0 : config.start_time_ms - nativeNow())
+        + config.clock_skew_ms;
+    const now = () => nativeNow() + skew;
+    if (config.performance_skew_ms !== 0) {
+        const nativePerformanceNow = performance.now.bind(performance);
+        performance.now = () => nativePerformanceNow() + config.performance_skew_ms;
+    }
+
+    // Time zone, in minutes UTC is ahead of local time like getTimezoneOffset
+    const parts = timeZone && new NativeDateTimeFormat("en-US", {
+        timeZone,
+        hourCycle: "h23",
+        year: "numeric",
+        month: "numeric",
+        day: "numeric",
+        hour: "numeric",
+        minute: "numeric",
+        second: "numeric",
+    });
+    const offsetAt = (time) => {
+        const values = {};
+        for (const part of parts.formatToParts(time)) {
+            values[part.type] = Number(part.value);
+        }
+        const local = NativeDate.UTC(values.year, values.month - 1, values.day,
+            values.hour, values.minute, values.second);
+        return Math.round((Math.floor(time / 1000) * 1000 - local) / 60000);
+    };
+    // UTC time of a local time given as if it were UTC
+    const fromLocal = (local) => {
+        if (Number.isNaN(local)) {
+            return NaN;
+        }
+        const time = local + offsetAt(local) * 60000;
+        return local + offsetAt(time) * 60000;
+    };
+    const toLocal = (time) => new NativeDate(time - offsetAt(time) * 60000);
+    const hasZone = /(Z|[+-]\d\d:?\d\d|GMT|UTC)\s*(\(.*\))?\s*$|^\d{4}(-\d\d){0,2}$/i;
+    const parse = (text) => {
+        const time = nativeParse(text);
+        if (!timeZone || Number.isNaN(time) || hasZone.test(String(text).trim())) {
+            return time;
+        }
+        // Parsed as a host local time, reinterpret its fields in the time zone.
+        const host = new NativeDate(time);
+        return fromLocal(NativeDate.UTC(host.getFullYear(), host.getMonth(), host.getDate(),
+            host.getHours(), host.getMinutes(), host.getSeconds(), host.getMilliseconds()));
+    };
+
+    function VersoDate(...args) {
+        if (!new.target) {
+            return new VersoDate().toString();
+        }
+        let time;
+        if (args.length === 0) {
+            time = now();
+        } else if (args.length === 1) {
+            const value = args[0];
+            time = value instanceof NativeDate ? native.getTime.call(value)
+                : typeof value === "string" ? parse(value) : value;
+        } else if (timeZone) {
+            time = fromLocal(NativeDate.UTC(...args));
+        } else {
+            time = native.getTime.call(new NativeDate(...args));
+        }
+        return Reflect.construct(NativeDate, [time], new.target);
+    }
+    VersoDate.prototype = proto;
+    Object.defineProperty(proto, "constructor", { value: VersoDate, writable: true, configurable: true });
+    VersoDate.now = now;
+    VersoDate.parse = parse;
+    VersoDate.UTC = NativeDate.UTC;
+    Object.defineProperty(window, "Date", { value: VersoDate, writable: true, configurable: true });
+
+    if (timeZone) {
+        proto.getTimezoneOffset = function () {
+            const time = native.getTime.call(this);
+            return Number.isNaN(time) ? NaN : offsetAt(time);
+        };
+        for (const field of ["FullYear", "Month", "Date", "Day", "Hours", "Minutes", "Seconds", "Milliseconds"]) {
+            const utcGetter = native[`getUTC${field}`];
+            proto[`get${field}`] = function () {
+                const time = native.getTime.call(this);
+                return Number.isNaN(time) ? NaN : utcGetter.call(toLocal(time));
+            };
+            if (field === "Day") {
+                continue;
+            }
+            const utcSetter = native[`setUTC${field}`];
+            proto[`set${field}`] = function (...args) {
+                const time = native.getTime.call(this);
+                const local = Number.isNaN(time) ? new NativeDate(NaN) : toLocal(time);
+                utcSetter.apply(local, args);
+                return native.setTime.call(this, fromLocal(native.getTime.call(local)));
+            };
+        }
+        const days = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+        const months = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+        const pad = (value, length = 2) => String(value).padStart(length, "0");
+        const zoneName = new NativeDateTimeFormat("en-US", { timeZone, timeZoneName: "long" });
+        const dateString = (local) => `${days[local.getUTCDay()]} ${months[local.getUTCMonth()]} `
+            + `${pad(local.getUTCDate())} ${pad(local.getUTCFullYear(), 4)}`;
+        const timeString = (time, local) => {
+            const offset = -offsetAt(time);
+            const sign = offset < 0 ? "-" : "+";
+            const name = zoneName.formatToParts(time).find((part) => part.type === "timeZoneName");
+            return `${pad(local.getUTCHours())}:${pad(local.getUTCMinutes())}:${pad(local.getUTCSeconds())} `
+                + `GMT${sign}${pad(Math.floor(Math.abs(offset) / 60))}${pad(Math.abs(offset) % 60)}`
+                + (name ? ` (${name.value})` : "");
+        };
+        const invalid = (format) => function () {
+            const time = native.getTime.call(this);
+            return Number.isNaN(time) ? "Invalid Date" : format(time, toLocal(time));
+        };
+        proto.toString = invalid((time, local) => `${dateString(local)} ${timeString(time, local)}`);
+        proto.toDateString = invalid((time, local) => dateString(local));
+        proto.toTimeString = invalid((time, local) => timeString(time, local));
+    }
+
+    // Default locale and time zone of Intl and toLocale*String
+    const locales = (value) => (value === undefined && locale ? locale : value);
+    const zoned = (options) => (timeZone && (options === undefined || options === null
+        || options.timeZone === undefined) ? { ...options, timeZone } : options);
+    const wrap = (Native, withZone) => {
+        const Wrapped = function (value, options) {
+            const args = [locales(value), withZone ? zoned(options) : options];
+            return new.target ? Reflect.construct(Native, args, new.target) : Native(...args);
+        };
+        Wrapped.prototype = Native.prototype;
+        Wrapped.supportedLocalesOf = Native.supportedLocalesOf;
+        Object.defineProperty(Native.prototype, "constructor", { value: Wrapped, writable: true, configurable: true });
+        return Wrapped;
+    };
+    for (const name of ["DateTimeFormat", "NumberFormat", "Collator", "PluralRules", "RelativeTimeFormat", "ListFormat"]) {
+        if (Intl[name]) {
+            Object.defineProperty(Intl, name, { value: wrap(Intl[name], name === "DateTimeFormat"), writable: true, configurable: true });
+        }
+    }
+    for (const name of ["toLocaleString", "toLocaleDateString", "toLocaleTimeString"]) {
+        proto[name] = function (value, options) {
+            return native[name].call(this, locales(value), zoned(options));
+        };
+    }
+    if (locale) {
+        const numberToLocaleString = Number.prototype.toLocaleString;
+        Number.prototype.toLocaleString = function (value, options) {
+            return numberToLocaleString.call(this, locales(value), options);
+        };
+        const localeCompare = String.prototype.localeCompare;
+        String.prototype.localeCompare = function (that, value, options) {
+            return localeCompare.call(this, that, locales(value), options);
+        };
+        Object.defineProperty(navigator, "language", { get: () => locale });
+        Object.defineProperty(navigator, "languages", { get: () => Object.freeze([locale]) });
+    }
+})();
+"#;
+
+/// Document start script applying the override in every page and frame
+pub fn user_script(clock_override: &ClockOverride) -> UserScript {
+    let config = serde_json::to_string(clock_override).unwrap_or_else(|_| "{}".to_string());
+    UserScript {
+        script: CLOCK_OVERRIDE_SCRIPT.replace("OVERRIDE", &config),
+        source_file: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_script() {
+        let script = user_script(&ClockOverride {
+            time_zone: Some("Pacific/Auckland".to_string()),
+            locale: Some("de-CH".to_string()),
+            start_time_ms: Some(1_700_000_000_000),
+            ..Default::default()
+        })
+        .script;
+        assert!(script.contains(
+            r#"const config = {"time_zone":"Pacific/Auckland","locale":"de-CH","start_time_ms":1700000000000,"clock_skew_ms":0,"performance_skew_ms":0.0};"#
+        ));
+        assert!(!script.contains("OVERRIDE"));
+    }
+}
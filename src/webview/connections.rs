@@ -0,0 +1,208 @@
+//! Live connection monitor
+//!
+//! Reports the WebSocket and EventSource connections of a webview, with their
+//! state and traffic, and lets the embedder close them. Servo doesn't tell the
+//! embedder about either, so every page gets a document start script wrapping
+//! both constructors and recording each connection in a page global. Queries
+//! drain closed connections after reporting them once.
+//!
+//! Bytes are counted from message payloads as seen by the page, without
+//! framing or headers. EventSource events with a custom type aren't counted.
+//!
+//! Verso has no Chrome DevTools Protocol endpoint, its devtools server speaks
+//! Firefox's protocol, so the connections are only reported through the
+//! embedder API for now. [`ConnectionInfo`] carries what CDP's `Network`
+//! domain reports for WebSockets and EventSources.
+
+use base::id::WebViewId;
+use constellation_traits::EmbedderToConstellationMessage;
+use crossbeam_channel::Sender;
+use embedder_traits::{WebDriverJSValue, user_content_manager::UserScript};
+use ipc_channel::router::ROUTER;
+use versoview_messages::ConnectionInfo;
+
+use super::execute_script_deferred;
+
+/// Wraps `WebSocket` and `EventSource` to record every connection in `window.__versoConnections`
+pub const CONNECTION_MONITOR_SCRIPT: &str = r#"
+(() => {
+    if (window.__versoConnections) {
+        return;
+    }
+    const connections = new Map();
+    Object.defineProperty(window, "__versoConnections", { value: connections });
+    let nextId = 1;
+    const encoder = new TextEncoder();
+    const size = (data) => {
+        if (typeof data === "string") {
+            return encoder.encode(data).length;
+        }
+        if (data instanceof Blob) {
+            return data.size;
+        }
+        return data && data.byteLength !== undefined ? data.byteLength : 0;
+    };
+    const track = (kind, socket) => {
+        const record = {
+            id: nextId++,
+            kind,
+            socket,
+            bytes_sent: 0,
+            bytes_received: 0,
+            messages_sent: 0,
+            messages_received: 0,
+        };
+        connections.set(record.id, record);
+        socket.addEventListener("message", (event) => {
+            record.messages_received += 1;
+            record.bytes_received += size(event.data);
+        });
+        return record;
+    };
+    if (window.WebSocket) {
+        window.WebSocket = class WebSocket extends window.WebSocket {
+            #record;
+            constructor(...args) {
+                super(...args);
+                this.#record = track("WebSocket", this);
+            }
+            send(data) {
+                super.send(data);
+                this.#record.messages_sent += 1;
+                this.#record.bytes_sent += size(data);
+            }
+        };
+    }
+    if (window.EventSource) {
+        window.EventSource = class EventSource extends window.EventSource {
+            constructor(...args) {
+                super(...args);
+                track("EventSource", this);
+            }
+        };
+    }
+})();
+"#;
+
+/// Returns the connections recorded by [`CONNECTION_MONITOR_SCRIPT`] as a JSON array, forgetting closed ones
+const TAKE_CONNECTIONS_SCRIPT: &str = r#"
+(() => {
+    const connections = window.__versoConnections;
+    if (!connections) {
+        return "[]";
+    }
+    const report = [];
+    for (const [id, record] of connections) {
+        const closed = record.kind === "EventSource" ? 2 : 3;
+        const readyState = record.socket.readyState;
+        const state = readyState >= closed
+            ? "Closed"
+            : ["Connecting", "Open", "Closing"][readyState];
+        if (state === "Closed") {
+            connections.delete(id);
+        }
+        report.push({
+            id,
+            kind: record.kind,
+            url: record.socket.url,
+            state,
+            bytes_sent: record.bytes_sent,
+            bytes_received: record.bytes_received,
+            messages_sent: record.messages_sent,
+            messages_received: record.messages_received,
+        });
+    }
+    return JSON.stringify(report);
+})()
+"#;
+
+/// The user script installing the monitor in every page
+pub fn user_script() -> UserScript {
+    UserScript {
+        script: CONNECTION_MONITOR_SCRIPT.to_string(),
+        source_file: None,
+    }
+}
+
+/// Live connections of a webview, and the ones closed since the last query, handed to
+/// `on_report` once the page answered
+pub fn connections(
+    sender: &Sender<EmbedderToConstellationMessage>,
+    webview_id: WebViewId,
+    on_report: impl FnOnce(Vec<ConnectionInfo>) + Send + 'static,
+) {
+    let Some(answer) = execute_script_deferred(sender, &webview_id, TAKE_CONNECTIONS_SCRIPT) else {
+        on_report(Vec::new());
+        return;
+    };
+    let mut on_report = Some(on_report);
+    ROUTER.add_typed_route(
+        answer,
+        Box::new(move |result| {
+            let connections = match result {
+                Ok(Ok(WebDriverJSValue::String(report))) => parse_report(&report),
+                _ => Vec::new(),
+            };
+            if let Some(on_report) = on_report.take() {
+                on_report(connections);
+            }
+        }),
+    );
+}
+
+/// Close a connection of a webview, `on_closed` is told whether it was found
+pub fn close_connection(
+    sender: &Sender<EmbedderToConstellationMessage>,
+    webview_id: WebViewId,
+    id: u64,
+    on_closed: impl FnOnce(bool) + Send + 'static,
+) {
+    let script = format!(
+        "(() => {{ const record = window.__versoConnections && window.__versoConnections.get({id}); if (record) {{ record.socket.close(); }} return !!record; }})()"
+    );
+    let Some(answer) = execute_script_deferred(sender, &webview_id, script) else {
+        on_closed(false);
+        return;
+    };
+    let mut on_closed = Some(on_closed);
+    ROUTER.add_typed_route(
+        answer,
+        Box::new(move |result| {
+            if let Some(on_closed) = on_closed.take() {
+                on_closed(matches!(result, Ok(Ok(WebDriverJSValue::Boolean(true)))));
+            }
+        }),
+    );
+}
+
+fn parse_report(report: &str) -> Vec<ConnectionInfo> {
+    serde_json::from_str(report).unwrap_or_else(|error| {
+        log::warn!("Verso failed to parse the connection report: {error}");
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use versoview_messages::{ConnectionKind, ConnectionState};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_report() {
+        let connections = parse_report(
+            r#"[{"id":1,"kind":"WebSocket","url":"wss://kiosk.example/live","state":"Open",
+                "bytes_sent":5,"bytes_received":1024,"messages_sent":1,"messages_received":8},
+               {"id":2,"kind":"EventSource","url":"https://kiosk.example/events","state":"Closed",
+                "bytes_sent":0,"bytes_received":12,"messages_sent":0,"messages_received":3}]"#,
+        );
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].kind, ConnectionKind::WebSocket);
+        assert_eq!(connections[0].state, ConnectionState::Open);
+        assert_eq!(connections[0].bytes_received, 1024);
+        assert_eq!(connections[1].kind, ConnectionKind::EventSource);
+        assert_eq!(connections[1].state, ConnectionState::Closed);
+
+        assert!(parse_report("not json").is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! Per-webview content settings
+//!
+//! Lets content-review tools and constrained devices turn off JavaScript and/or
+//! author CSS for a single webview. Settings are looked up on every navigation,
+//! so a change takes effect from the next page load.
+//!
+//! Servo has no per-webview switch for either, so both are enforced through
+//! Content Security Policy: document loads are taken over in the web resource
+//! interception path and handed to Servo with an extra
+//! `Content-Security-Policy` header, which Servo enforces before any inline
+//! script runs or any style applies. External scripts and workers are also
+//! cancelled in the interception path. Navigations with a body (form posts)
+//! can't be taken over and load without the policy.
+
+use std::collections::HashMap;
+
+use base::id::WebViewId;
+use embedder_traits::WebResourceRequest;
+use http::HeaderValue;
+use reqwest::Client;
+use versoview_messages::ContentSettings;
+
+use crate::network_context;
+
+/// File extensions treated as scripts when the request has no fetch destination
+const SCRIPT_EXTENSIONS: [&str; 2] = [".js", ".mjs"];
+
+/// Fetch destinations that execute script
+const SCRIPT_DESTINATIONS: [&str; 6] = [
+    "script",
+    "worker",
+    "sharedworker",
+    "serviceworker",
+    "audioworklet",
+    "paintworklet",
+];
+
+/// Fetch destinations that load a document
+const DOCUMENT_DESTINATIONS: [&str; 3] = ["document", "iframe", "frame"];
+
+/// Content settings of every webview, falling back to a default
+#[derive(Debug)]
+pub struct ContentSettingsStore {
+    default: ContentSettings,
+    overrides: HashMap<WebViewId, ContentSettings>,
+    /// Loads the documents the policy is added to, without following redirects
+    /// so Servo sees and checks every hop
+    client: Client,
+}
+
+impl Default for ContentSettingsStore {
+    fn default() -> Self {
+        Self::new(ContentSettings::default())
+    }
+}
+
+impl ContentSettingsStore {
+    /// Create a store where webviews without an override use `default`
+    pub fn new(default: ContentSettings) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+            client: Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Settings of the webview
+    pub fn get(&self, webview_id: WebViewId) -> ContentSettings {
+        self.overrides
+            .get(&webview_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Settings the webview was given, `None` if it uses the default
+    pub fn override_of(&self, webview_id: WebViewId) -> Option<ContentSettings> {
+        self.overrides.get(&webview_id).copied()
+    }
+
+    /// Override the settings of the webview
+    pub fn set(&mut self, webview_id: WebViewId, settings: ContentSettings) {
+        if settings == self.default {
+            self.overrides.remove(&webview_id);
+        } else {
+            self.overrides.insert(webview_id, settings);
+        }
+    }
+
+    /// Apply the settings to every webview, including ones opened later
+    pub fn set_all(&mut self, settings: ContentSettings) {
+        self.default = settings;
+        self.overrides.clear();
+    }
+
+    /// Forget the webview, call when it is closed
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.overrides.remove(&webview_id);
+    }
+
+    /// Whether the request should be cancelled under the webview's settings
+    pub fn should_block(&self, webview_id: WebViewId, request: &WebResourceRequest) -> bool {
+        !self.get(webview_id).javascript_enabled
+            && !request.is_for_main_frame
+            && is_script_request(
+                request
+                    .headers
+                    .get("sec-fetch-dest")
+                    .and_then(|value| value.to_str().ok()),
+                request.url.path(),
+            )
+    }
+
+    /// Policy to add to the document the request loads, `None` if it isn't a
+    /// document load or the webview's settings don't restrict anything
+    pub fn content_security_policy(
+        &self,
+        webview_id: WebViewId,
+        request: &WebResourceRequest,
+    ) -> Option<HeaderValue> {
+        let is_document = request.is_for_main_frame
+            || request
+                .headers
+                .get("sec-fetch-dest")
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|destination| DOCUMENT_DESTINATIONS.contains(&destination));
+        if !is_document || !network_context::is_routable(request) {
+            return None;
+        }
+        policy(self.get(webview_id)).map(HeaderValue::from_static)
+    }
+
+    /// Client to load the documents the policy is added to
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+}
+
+/// Content Security Policy enforcing the settings
+fn policy(settings: ContentSettings) -> Option<&'static str> {
+    match (settings.javascript_enabled, settings.author_styles_enabled) {
+        (true, true) => None,
+        (false, true) => Some("script-src 'none'; worker-src 'none'"),
+        (true, false) => Some("style-src 'none'"),
+        (false, false) => Some("script-src 'none'; worker-src 'none'; style-src 'none'"),
+    }
+}
+
+/// Whether a request fetches something that will be executed as script
+fn is_script_request(destination: Option<&str>, path: &str) -> bool {
+    match destination {
+        Some(destination) => SCRIPT_DESTINATIONS.contains(&destination),
+        None => {
+            let path = path.to_ascii_lowercase();
+            SCRIPT_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_script_request() {
+        assert!(is_script_request(Some("script"), "/app"));
+        assert!(is_script_request(Some("worker"), "/worker"));
+        assert!(!is_script_request(Some("style"), "/app.js"));
+        assert!(is_script_request(None, "/static/App.JS"));
+        assert!(is_script_request(None, "/module.mjs"));
+        assert!(!is_script_request(None, "/index.html"));
+    }
+
+    #[test]
+    fn test_policy() {
+        assert_eq!(policy(ContentSettings::default()), None);
+        let settings = ContentSettings {
+            javascript_enabled: false,
+            author_styles_enabled: false,
+        };
+        assert_eq!(
+            policy(settings),
+            Some("script-src 'none'; worker-src 'none'; style-src 'none'")
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! CSS and JavaScript coverage
+//!
+//! Reports which style rules of a page were used, and how many of their bytes
+//! never were, for trimming the content shipped to devices. Servo has no
+//! coverage instrumentation, so a webview collecting coverage gets a script,
+//! sent without waiting when collection starts and when each page's head is
+//! parsed, that samples the page's style rules: each rule whose selector
+//! matches an element when the document changes or finishes loading counts as
+//! used. Pages of webviews that don't collect run nothing. Collection restarts
+//! with every page the webview loads, and stopping it forgets what was sampled.
+//!
+//! Rules are only sampled while collecting, so a rule matching only briefly,
+//! like a `:hover` rule, counts as used only if it matched at a sample. Rules in
+//! `@media` and `@supports` blocks that don't apply at the time don't match.
+//! Rule sizes are those of their serialized text, not of the original source.
+//! Scripts are listed with their size only: SpiderMonkey's code coverage isn't
+//! exposed to embedders, so their used and unused bytes are 0 rather than a
+//! guess. External sizes come from resource timing, and are 0 if it has no
+//! entry for the resource.
+//!
+//! Verso has no Chrome DevTools Protocol endpoint, its devtools server speaks
+//! Firefox's protocol, so coverage is only reported through the embedder API.
+//! [`CoverageReport`] carries what CDP's `CSS.takeCoverageDelta` and
+//! `Profiler.takePreciseCoverage` report, per resource instead of per range.
+
+use base::id::WebViewId;
+use constellation_traits::EmbedderToConstellationMessage;
+use crossbeam_channel::Sender;
+use embedder_traits::WebDriverJSValue;
+use ipc_channel::router::ROUTER;
+use versoview_messages::{CoverageKind, CoverageReport};
+
+use super::{execute_script_async, execute_script_deferred};
+
+/// Defines `window.__versoCoverage` to sample and report coverage, then starts sampling
+const START_COVERAGE_SCRIPT: &str = r#"
+(() => {
+    if (window.__versoCoverage) {
+        window.__versoCoverage.start();
+        return;
+    }
+    let usedRules = new WeakSet();
+    let observer = null;
+    let timer = null;
+    const pseudoElements = /::?(before|after|first-line|first-letter|marker|placeholder|selection|backdrop|file-selector-button)\b(\([^)]*\))?/gi;
+    const applies = (rule) => {
+        if (typeof CSSMediaRule !== "undefined" && rule instanceof CSSMediaRule) {
+            return matchMedia(rule.media.mediaText).matches;
+        }
+        if (typeof CSSSupportsRule !== "undefined" && rule instanceof CSSSupportsRule) {
+            return CSS.supports(rule.conditionText);
+        }
+        return true;
+    };
+    const isGroup = (rule) =>
+        typeof CSSGroupingRule !== "undefined" && rule instanceof CSSGroupingRule;
+    const walk = (rules) => {
+        for (const rule of rules) {
+            if (rule instanceof CSSStyleRule) {
+                if (usedRules.has(rule)) {
+                    continue;
+                }
+                const selector = rule.selectorText.replace(pseudoElements, "") || "*";
+                try {
+                    if (document.querySelector(selector)) {
+                        usedRules.add(rule);
+                    }
+                } catch (error) {
+                    // Selectors the engine can't query count as used.
+                    usedRules.add(rule);
+                }
+            } else if (isGroup(rule) && applies(rule)) {
+                walk(rule.cssRules);
+            }
+        }
+    };
+    const sample = () => {
+        for (const sheet of document.styleSheets) {
+            try {
+                walk(sheet.cssRules);
+            } catch (error) {}
+        }
+    };
+
+    const encoder = new TextEncoder();
+    const bytes = (text) => encoder.encode(text).length;
+    const transferred = (url) => {
+        const entry = performance.getEntriesByName(url, "resource")[0];
+        return entry ? entry.decodedBodySize || 0 : 0;
+    };
+    const javaScript = /^((text|application)\/(x-)?(java|ecma)script|text\/jscript|module)$/;
+    const report = () => {
+        sample();
+        const resources = [];
+        for (const sheet of document.styleSheets) {
+            const resource = {
+                kind: "Stylesheet",
+                url: sheet.href,
+                total_bytes: 0,
+                used_bytes: 0,
+                rules: 0,
+                used_rules: 0,
+                unused_selectors: [],
+                opaque: false,
+            };
+            const count = (rules) => {
+                for (const rule of rules) {
+                    const size = bytes(rule.cssText);
+                    if (rule instanceof CSSStyleRule) {
+                        resource.rules += 1;
+                        resource.total_bytes += size;
+                        if (usedRules.has(rule)) {
+                            resource.used_rules += 1;
+                            resource.used_bytes += size;
+                        } else if (resource.unused_selectors.length < 100) {
+                            resource.unused_selectors.push(rule.selectorText);
+                        }
+                    } else if (isGroup(rule)) {
+                        count(rule.cssRules);
+                    } else {
+                        resource.total_bytes += size;
+                        resource.used_bytes += size;
+                    }
+                }
+            };
+            try {
+                count(sheet.cssRules);
+            } catch (error) {
+                resource.opaque = true;
+                resource.total_bytes = sheet.href ? transferred(sheet.href) : 0;
+                resource.used_bytes = resource.total_bytes;
+            }
+            resources.push(resource);
+        }
+        for (const script of document.scripts) {
+            const type = (script.type || "text/javascript").trim().toLowerCase();
+            if (!javaScript.test(type)) {
+                continue;
+            }
+            const external = script.hasAttribute("src");
+            resources.push({
+                kind: "Script",
+                url: external ? script.src : null,
+                total_bytes: external ? transferred(script.src) : bytes(script.text),
+                used_bytes: 0,
+                rules: 0,
+                used_rules: 0,
+                unused_selectors: [],
+                opaque: false,
+            });
+        }
+        return JSON.stringify({ url: location.href, resources });
+    };
+
+    const start = () => {
+        if (observer) {
+            return;
+        }
+        sample();
+        observer = new MutationObserver(() => {
+            if (timer === null) {
+                timer = setTimeout(() => {
+                    timer = null;
+                    sample();
+                }, 500);
+            }
+        });
+        observer.observe(document, { subtree: true, childList: true, attributes: true });
+        addEventListener("load", sample);
+    };
+    const stop = () => {
+        if (observer) {
+            observer.disconnect();
+            observer = null;
+        }
+        removeEventListener("load", sample);
+        usedRules = new WeakSet();
+    };
+    Object.defineProperty(window, "__versoCoverage", { value: { start, stop, report } });
+    start();
+})();
+"#;
+
+/// Stops sampling the style rules of the page, forgetting which were used
+const STOP_COVERAGE_SCRIPT: &str = "window.__versoCoverage && window.__versoCoverage.stop()";
+
+/// Returns the coverage of the page as JSON
+const COVERAGE_REPORT_SCRIPT: &str =
+    "window.__versoCoverage ? window.__versoCoverage.report() : null";
+
+/// Start or stop collecting the coverage of the webview's page, without waiting for the page
+pub fn set_collecting(
+    sender: &Sender<EmbedderToConstellationMessage>,
+    webview_id: WebViewId,
+    collecting: bool,
+) {
+    let script = if collecting {
+        START_COVERAGE_SCRIPT
+    } else {
+        STOP_COVERAGE_SCRIPT
+    };
+    execute_script_async(sender, &webview_id, script);
+}
+
+/// Coverage of the webview's page handed to `on_report` once the page answered, `None` if
+/// the page couldn't report it
+pub fn coverage(
+    sender: &Sender<EmbedderToConstellationMessage>,
+    webview_id: WebViewId,
+    on_report: impl FnOnce(Option<CoverageReport>) + Send + 'static,
+) {
+    let Some(answer) = execute_script_deferred(sender, &webview_id, COVERAGE_REPORT_SCRIPT) else {
+        on_report(None);
+        return;
+    };
+    let mut on_report = Some(on_report);
+    ROUTER.add_typed_route(
+        answer,
+        Box::new(move |result| {
+            let report = match result {
+                Ok(Ok(WebDriverJSValue::String(report))) => parse_report(&report),
+                _ => None,
+            };
+            if let Some(on_report) = on_report.take() {
+                on_report(report);
+            }
+        }),
+    );
+}
+
+fn parse_report(report: &str) -> Option<CoverageReport> {
+    let mut report: CoverageReport = serde_json::from_str(report)
+        .inspect_err(|error| log::warn!("Verso failed to parse the coverage report: {error}"))
+        .ok()?;
+    for resource in &mut report.resources {
+        if resource.kind == CoverageKind::Stylesheet {
+            resource.unused_bytes = resource.total_bytes.saturating_sub(resource.used_bytes);
+        }
+    }
+    report.total_bytes = report
+        .resources
+        .iter()
+        .map(|resource| resource.total_bytes)
+        .sum();
+    report.unused_bytes = report
+        .resources
+        .iter()
+        .map(|resource| resource.unused_bytes)
+        .sum();
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report() {
+        let report = parse_report(
+            r#"{"url":"https://kiosk.example/","resources":[
+                {"kind":"Stylesheet","url":"https://kiosk.example/site.css","total_bytes":1200,
+                 "used_bytes":300,"rules":12,"used_rules":3,"unused_selectors":[".promo"],
+                 "executed":true,"opaque":false},
+                {"kind":"Script","url":null,"total_bytes":80,"used_bytes":0,"rules":0,
+                 "used_rules":0,"unused_selectors":[],"opaque":false},
+                {"kind":"Script","url":"https://kiosk.example/legacy.js","total_bytes":500,
+                 "used_bytes":0,"rules":0,"used_rules":0,"unused_selectors":[],
+                 "opaque":false}]}"#,
+        )
+        .unwrap();
+        assert_eq!(report.resources.len(), 3);
+        assert_eq!(report.resources[0].kind, CoverageKind::Stylesheet);
+        assert_eq!(report.resources[0].unused_bytes, 900);
+        assert_eq!(report.resources[2].unused_bytes, 0);
+        assert_eq!(report.total_bytes, 1780);
+        assert_eq!(report.unused_bytes, 900);
+
+        assert!(parse_report("not json").is_none());
+    }
+}
@@ -0,0 +1,153 @@
+//! Per-webview launch parameters
+//!
+//! Lets packaged apps hand device IDs and configuration to their pages without
+//! putting them in the URL: the embedder gives a webview key/value parameters,
+//! and its pages read them from `window.verso.params`. Only `app://` pages and
+//! pages of the origins the embedder allowed get the object, other pages the
+//! webview navigates to see nothing.
+//!
+//! The object is defined by a script run when the document head is parsed, so
+//! scripts running before that can wait for the `versoparams` event on
+//! `window`. It is frozen and can't be redefined by the page, and it is only
+//! defined in the top-level document, never in frames. The script checks the
+//! origin of the document it runs in again, so a navigation racing it can't
+//! hand the parameters to another origin.
+
+use std::collections::HashMap;
+
+use base::id::WebViewId;
+use url::Url;
+use versoview_messages::LaunchParams;
+
+/// Scheme of packaged app pages, always allowed to read their parameters
+pub const APP_SCHEME: &str = "app";
+
+/// Defines `window.verso.params` as PARAMS and announces it, if the document still is at ALLOWED
+const DEFINE_PARAMS_SCRIPT: &str = r#"
+(() => {
+    if (!(ALLOWED)) {
+        return;
+    }
+    const verso = window.verso || {};
+    Object.defineProperty(verso, "params", {
+        value: Object.freeze(PARAMS),
+        enumerable: true,
+    });
+    if (!window.verso) {
+        Object.defineProperty(window, "verso", { value: verso });
+    }
+    window.dispatchEvent(new Event("versoparams"));
+})();
+"#;
+
+/// Launch parameters of the webviews that have some
+#[derive(Debug, Default)]
+pub struct LaunchParamsStore {
+    params: HashMap<WebViewId, LaunchParams>,
+}
+
+impl LaunchParamsStore {
+    /// Parameters of the webview, `None` if it has none
+    pub fn get(&self, webview_id: WebViewId) -> Option<&LaunchParams> {
+        self.params.get(&webview_id)
+    }
+
+    /// Set the parameters of the webview, replacing the previous ones
+    pub fn set(&mut self, webview_id: WebViewId, params: LaunchParams) {
+        if params == LaunchParams::default() {
+            self.params.remove(&webview_id);
+        } else {
+            self.params.insert(webview_id, params);
+        }
+    }
+
+    /// Forget the webview, call when it is closed
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.params.remove(&webview_id);
+    }
+
+    /// Script defining the parameters in the webview's document at `url`, `None` if it may not see them
+    pub fn page_script(&self, webview_id: WebViewId, url: &Url) -> Option<String> {
+        let params = self.get(webview_id)?;
+        if !is_allowed(params, url) {
+            log::debug!("Launch parameters of {webview_id:?} aren't exposed to {url}");
+            return None;
+        }
+        let params = serde_json::to_string(&params.params).ok()?;
+        Some(
+            DEFINE_PARAMS_SCRIPT
+                .replace("ALLOWED", &origin_check(url)?)
+                .replace("PARAMS", &params),
+        )
+    }
+}
+
+/// Expression checking the document running a script still is at the origin of `url`
+fn origin_check(url: &Url) -> Option<String> {
+    if url.scheme() == APP_SCHEME {
+        return Some(format!(r#"location.protocol === "{APP_SCHEME}:""#));
+    }
+    let origin = serde_json::to_string(&url.origin().ascii_serialization()).ok()?;
+    Some(format!("location.origin === {origin}"))
+}
+
+/// Whether a document at `url` may read the parameters
+fn is_allowed(params: &LaunchParams, url: &Url) -> bool {
+    if url.scheme() == APP_SCHEME {
+        return true;
+    }
+    let origin = url.origin();
+    origin.is_tuple()
+        && params
+            .allowed_origins
+            .iter()
+            .filter_map(|allowed| Url::parse(allowed).ok())
+            .any(|allowed| allowed.origin() == origin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+
+    fn params() -> LaunchParams {
+        LaunchParams {
+            params: [("deviceId".to_string(), "kiosk-42".to_string())].into(),
+            allowed_origins: vec!["https://kiosk.example".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_allowed_origins() {
+        let params = params();
+        let allowed = |url: &str| is_allowed(&params, &Url::parse(url).unwrap());
+        assert!(allowed("app://launcher/index.html"));
+        assert!(allowed("https://kiosk.example/dashboard?x=1"));
+        assert!(!allowed("http://kiosk.example/"));
+        assert!(!allowed("https://kiosk.example:8443/"));
+        assert!(!allowed("https://evil.example/"));
+        assert!(!allowed("data:text/html,hello"));
+    }
+
+    #[test]
+    fn test_page_script() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let webview_id = WebViewId::new();
+        let url = Url::parse("https://kiosk.example/").unwrap();
+        let mut store = LaunchParamsStore::default();
+        assert!(store.page_script(webview_id, &url).is_none());
+
+        store.set(webview_id, params());
+        let script = store.page_script(webview_id, &url).unwrap();
+        assert!(script.contains(r#"Object.freeze({"deviceId":"kiosk-42"})"#));
+        assert!(script.contains(r#"location.origin === "https://kiosk.example""#));
+        assert!(
+            store
+                .page_script(webview_id, &Url::parse("https://other.example/").unwrap())
+                .is_none()
+        );
+
+        store.set(webview_id, LaunchParams::default());
+        assert!(store.get(webview_id).is_none());
+    }
+}
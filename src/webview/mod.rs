@@ -1,11 +1,26 @@
 mod webview;
 /// WebView
-pub use webview::{Panel, WebView, execute_script};
+pub use webview::{
+    Panel, WebView, execute_script, execute_script_async, execute_script_deferred,
+    execute_script_with_timeout,
+};
+/// Clock and locale overrides
+pub mod clock_override;
+/// Live connections
+pub mod connections;
+/// Content Settings
+pub mod content_settings;
 /// Context Menu
 pub mod context_menu;
+/// CSS and JavaScript coverage
+pub mod coverage;
 /// Browsing history menu
 pub mod history_menu;
+/// Launch Parameters
+pub mod launch_params;
 /// Prompt Dialog
 pub mod prompt;
+/// Tiled tabs
+pub mod tiling;
 /// WebView Menu
 pub mod webview_menu;
@@ -0,0 +1,102 @@
+//! Tiled tabs
+//!
+//! Lets a window show several of its tabs at once, each at its own rect, for
+//! split views and dashboards. A tab given a rect is painted there on top of
+//! the panel and kept unthrottled whether it is the current tab or not; the
+//! current tab is painted last, in its rect if it has one or in the content
+//! area otherwise. Every tab keeps its own pipeline, so scrolling and hit
+//! testing already go to the tab under the pointer.
+//!
+//! Rects are in device pixels from the top left corner of the window and are
+//! kept as they are when the window is resized, the embedder lays the tabs
+//! out again.
+
+use base::id::WebViewId;
+use versoview_messages::TabRect;
+use webrender_api::units::{DevicePoint, DeviceRect, DeviceSize};
+
+/// Tabs shown at their own rect, in the order they are painted
+#[derive(Debug, Default)]
+pub struct TileLayout {
+    tiles: Vec<(WebViewId, DeviceRect)>,
+}
+
+impl TileLayout {
+    /// Rect of the tab, `None` if it isn't tiled
+    pub fn rect(&self, webview_id: WebViewId) -> Option<DeviceRect> {
+        self.tiles
+            .iter()
+            .find(|(tiled, _)| *tiled == webview_id)
+            .map(|(_, rect)| *rect)
+    }
+
+    /// Show the tab at `rect` on top of the other tiles, or stop tiling it with `None`
+    pub fn set(&mut self, webview_id: WebViewId, rect: Option<DeviceRect>) {
+        self.remove(webview_id);
+        if let Some(rect) = rect {
+            self.tiles.push((webview_id, rect));
+        }
+    }
+
+    /// Stop tiling the tab, call when it is closed
+    pub fn remove(&mut self, webview_id: WebViewId) {
+        self.tiles.retain(|(tiled, _)| *tiled != webview_id);
+    }
+
+    /// Tiled tabs, bottom first
+    pub fn webview_ids(&self) -> impl Iterator<Item = WebViewId> + '_ {
+        self.tiles.iter().map(|(webview_id, _)| *webview_id)
+    }
+}
+
+/// Device rect of a rect from the controller
+pub fn device_rect(rect: &TabRect) -> DeviceRect {
+    DeviceRect::from_origin_and_size(
+        DevicePoint::new(rect.position.x as f32, rect.position.y as f32),
+        DeviceSize::new(rect.size.width as f32, rect.size.height as f32),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use base::id::{PipelineNamespace, PipelineNamespaceId};
+    use dpi::{PhysicalPosition, PhysicalSize};
+
+    use super::*;
+
+    #[test]
+    fn test_tile_layout() {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let left = WebViewId::new();
+        let right = WebViewId::new();
+        let left_rect = device_rect(&TabRect {
+            position: PhysicalPosition::new(0, 80),
+            size: PhysicalSize::new(640, 720),
+        });
+        let right_rect = DeviceRect::new(
+            DevicePoint::new(640.0, 80.0),
+            DevicePoint::new(1280.0, 800.0),
+        );
+        let mut layout = TileLayout::default();
+
+        layout.set(left, Some(left_rect));
+        layout.set(right, Some(right_rect));
+        assert_eq!(
+            layout.rect(left),
+            Some(DeviceRect::new(
+                DevicePoint::new(0.0, 80.0),
+                DevicePoint::new(640.0, 800.0)
+            ))
+        );
+        assert_eq!(layout.webview_ids().collect::<Vec<_>>(), vec![left, right]);
+
+        // Setting a rect again brings the tab to the top.
+        layout.set(left, Some(left_rect));
+        assert_eq!(layout.webview_ids().collect::<Vec<_>>(), vec![right, left]);
+
+        layout.set(right, None);
+        layout.remove(left);
+        assert_eq!(layout.rect(right), None);
+        assert_eq!(layout.webview_ids().count(), 0);
+    }
+}
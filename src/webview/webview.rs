@@ -1,4 +1,8 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use arboard::Clipboard;
 use base::id::WebViewId;
@@ -6,27 +10,51 @@ use constellation_traits::{EmbedderToConstellationMessage, TraversalDirection};
 use crossbeam_channel::Sender;
 use embedder_traits::{
     AlertResponse, AllowOrDeny, ConfirmResponse, ContextMenuResult, EmbedderMsg, LoadStatus,
-    PromptResponse, SimpleDialog, ViewportDetails, WebDriverCommandMsg, WebDriverJSResult,
-    WebDriverScriptCommand,
+    PromptResponse, SimpleDialog, TouchEventType, ViewportDetails, WebDriverCommandMsg,
+    WebDriverJSResult, WebDriverJSValue, WebDriverScriptCommand, WebResourceRequest,
+    WebResourceResponse, WebResourceResponseMsg,
 };
 use euclid::Scale;
-use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use ipc_channel::router::ROUTER;
+use keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers};
 use servo_url::ServoUrl;
 use url::Url;
-use versoview_messages::ToControllerMessage;
-use webrender_api::units::{DevicePoint, DeviceRect};
+use versoview_messages::{
+    AddressTarget, AuditCategory, RecoveryTrigger, SecurityPolicyReport, SecurityPolicyViolation,
+    ToControllerMessage,
+};
+use webrender_api::{
+    ScrollLocation,
+    units::{DevicePoint, DeviceRect, LayoutVector2D},
+};
 
 use crate::{
+    address_input,
+    animated_images::{decode_animation, is_animated_image_request},
+    audit_log::TAKE_CSP_VIOLATIONS_SCRIPT,
     bookmark::{BookmarkId, BookmarkManager},
+    color_management::ColorManager,
     compositor::IOCompositor,
     download::{DownloadId, check_should_download, download_body},
+    drag_drop::DRAG_SOURCE_LISTENER_SCRIPT,
+    http3::Http3Transport,
+    network_context::{self, fetch_routed},
+    request_priority::FIRST_VIEWPORT_COMPLETE_SCRIPT,
+    script_policy,
+    site_settings::{
+        INTEGRITY_FAILURE_LISTENER_SCRIPT, MixedContentAction, SiteSettings,
+        TAKE_INTEGRITY_FAILURES_SCRIPT,
+    },
+    svg_raster::{FAVICON_SIZE, RasterSize, SvgRasterizer, is_svg_url, png_data_url},
     tab::{Tab, TabActivateRequest, TabCloseRequest, TabCreateResponse},
     verso::{VersoInternalMsg, send_to_constellation},
     webview::{
+        coverage,
         history_menu::{HistoryMenuUIResponse, OpenHistoryMenuRequest},
         prompt::{HttpBasicAuthInputResult, PromptDialog, PromptInputResult, PromptSender},
     },
-    window::Window,
+    window::{LINE_HEIGHT, Window},
 };
 
 #[cfg(linux)]
@@ -68,7 +96,7 @@ impl WebView {
 /// - Refresh the page: `window.prompt('REFRESH')`
 /// - Minimize the window: `window.prompt('MINIMIZE')`
 /// - Maximize the window: `window.prompt('MAXIMIZE')`
-/// - Navigate to a specific URL: `window.prompt('NAVIGATE_TO:${url}')`
+/// - Navigate to what the user typed in the address bar: `window.prompt('NAVIGATE_TO:${input}')`, see [`crate::address_input`]
 pub struct Panel {
     /// The panel's webview
     pub(crate) webview: WebView,
@@ -86,6 +114,7 @@ impl Window {
         to_controller_sender: &Option<ipc::IpcSender<ToControllerMessage>>,
         clipboard: Option<&mut Clipboard>,
         compositor: &mut IOCompositor,
+        site_settings: &SiteSettings,
     ) {
         log::trace!("Verso WebView {webview_id:?} is handling Embedder message: {message:?}",);
         match message {
@@ -108,12 +137,70 @@ impl Window {
                 );
             }
             EmbedderMsg::NotifyLoadStatusChanged(_webview_id, status) => match status {
+                LoadStatus::HeadParsed => {
+                    if let Some(script) = self
+                        .document_url(webview_id)
+                        .and_then(|url| self.launch_params.page_script(webview_id, &url))
+                    {
+                        execute_script_async(sender, &webview_id, script);
+                    }
+                    // Catches up with site policies changed after the user script was built.
+                    if let Some(url) = self.document_url(webview_id) {
+                        let policy = script_policy::effective_policy(
+                            &self.script_policy,
+                            site_settings,
+                            &url,
+                        );
+                        log::debug!("Script policy of {url}: {policy:?}");
+                        if let Some(script) = script_policy::page_script(&policy) {
+                            execute_script_async(sender, &webview_id, script);
+                        }
+                    }
+                    if self.drag_and_drop {
+                        execute_script_async(sender, &webview_id, DRAG_SOURCE_LISTENER_SCRIPT);
+                    }
+                    if self.coverage.contains(&webview_id) {
+                        coverage::set_collecting(sender, webview_id, true);
+                    }
+                    if self
+                        .document_url(webview_id)
+                        .is_some_and(|url| site_settings.policy_for(&url).report_integrity_failures)
+                    {
+                        execute_script_async(
+                            sender,
+                            &webview_id,
+                            INTEGRITY_FAILURE_LISTENER_SCRIPT,
+                        );
+                    }
+                }
+                LoadStatus::Started => {
+                    self.request_scheduler.page_started(webview_id);
+                }
                 LoadStatus::Complete => {
+                    self.request_scheduler
+                        .page_loaded(webview_id, Instant::now());
+                    self.measure_first_viewport(webview_id, sender);
+                    if let Some(url) = self.document_url(webview_id) {
+                        self.request_csp_violations(sender, webview_id, url);
+                    }
+                    self.request_integrity_failures(webview_id, sender, site_settings);
                     self.window.request_redraw();
-                    send_to_constellation(
-                        sender,
-                        EmbedderToConstellationMessage::FocusWebView(webview_id),
-                    );
+                    compositor.render_validation.page_loaded();
+                    // A hidden playlist page is focused once it is shown.
+                    let preloading = self
+                        .playlist
+                        .as_mut()
+                        .map(|playlist| {
+                            playlist.loaded(webview_id);
+                            playlist.is_preloading(webview_id)
+                        })
+                        .unwrap_or_default();
+                    if !preloading {
+                        send_to_constellation(
+                            sender,
+                            EmbedderToConstellationMessage::FocusWebView(webview_id),
+                        );
+                    }
                 }
                 _ => {
                     log::trace!(
@@ -143,9 +230,10 @@ impl Window {
             EmbedderMsg::AllowNavigationRequest(_webview_id, id, url) => {
                 if let Some(to_controller_sender) = to_controller_sender {
                     if self.event_listeners.on_navigation_starting {
+                        let serialized_id = bincode::serialize(&id).unwrap();
                         if let Err(error) =
                             to_controller_sender.send(ToControllerMessage::OnNavigationStarting(
-                                bincode::serialize(&id).unwrap(),
+                                serialized_id.clone(),
                                 url.clone().into_url(),
                             ))
                         {
@@ -153,6 +241,11 @@ impl Window {
                                 "Verso failed to send AllowNavigationRequest to controller: {error}"
                             )
                         } else {
+                            self.audit_log.navigation_requested(
+                                serialized_id,
+                                webview_id,
+                                url.clone().into_url(),
+                            );
                             // We will handle a ToVersoMessage::OnNavigationStartingResponse
                             // and send EmbedderToConstellationMessage::AllowNavigationResponse there if the call succeed
                             return;
@@ -187,6 +280,136 @@ impl Window {
                 }
             }
             EmbedderMsg::WebResourceRequested(_webview_id, request, sender) => {
+                if self.content_settings.should_block(webview_id, &request) {
+                    log::debug!(
+                        "Verso WebView {webview_id:?} blocked script {} because JavaScript is disabled",
+                        request.url
+                    );
+                    let document_url = self.document_url(webview_id);
+                    self.audit_log.record(
+                        webview_id,
+                        AuditCategory::ContentBlocked,
+                        document_url,
+                        request.url,
+                        "JavaScript is disabled for this webview",
+                    );
+                    let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+                    return;
+                }
+                if let Some(document_url) = self
+                    .document_url(webview_id)
+                    .filter(|_| !request.is_for_main_frame)
+                {
+                    match site_settings.mixed_content_action(&document_url, &request.url) {
+                        MixedContentAction::Allow => {}
+                        MixedContentAction::Block => {
+                            self.report_security_policy(
+                                webview_id,
+                                to_controller_sender,
+                                SecurityPolicyReport {
+                                    violation: SecurityPolicyViolation::MixedContentBlocked,
+                                    document_url,
+                                    resource_url: request.url,
+                                },
+                            );
+                            let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+                            return;
+                        }
+                        MixedContentAction::Upgrade(upgraded_url) => {
+                            self.report_security_policy(
+                                webview_id,
+                                to_controller_sender,
+                                SecurityPolicyReport {
+                                    violation: SecurityPolicyViolation::MixedContentUpgraded,
+                                    document_url,
+                                    resource_url: request.url,
+                                },
+                            );
+                            // Servo follows the redirect itself, with the request's cookies,
+                            // headers and body.
+                            let _ = sender
+                                .send(WebResourceResponseMsg::Start(upgrade_redirect(
+                                    request.url,
+                                    &upgraded_url,
+                                )))
+                                .and_then(|_| sender.send(WebResourceResponseMsg::FinishLoad));
+                            return;
+                        }
+                    }
+                }
+                let content_security_policy = self
+                    .content_settings
+                    .content_security_policy(webview_id, &request);
+                if let Some((context, client)) = self.network_contexts.client_for(webview_id) {
+                    if !network_context::is_routable(&request) {
+                        log::warn!(
+                            "Verso WebView {webview_id:?} cancelled {} {} because requests with a body can't go through network context '{context}'",
+                            request.method,
+                            request.url
+                        );
+                        let document_url = self.document_url(webview_id);
+                        self.audit_log.record(
+                            webview_id,
+                            AuditCategory::NetworkContext,
+                            document_url,
+                            request.url,
+                            format!(
+                                "{} requests with a body can't go through network context '{context}'",
+                                request.method
+                            ),
+                        );
+                        let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+                        return;
+                    }
+                    tokio::spawn(async move {
+                        fetch_routed(client, request, content_security_policy, sender).await;
+                    });
+                    return;
+                }
+                if content_security_policy.is_some() {
+                    let client = self.content_settings.client();
+                    tokio::spawn(async move {
+                        fetch_routed(client, request, content_security_policy, sender).await;
+                    });
+                    return;
+                }
+                // Servo only uploads the first frame of an image, the compositor plays the others.
+                if is_animated_image_request(&request)
+                    && self.event_listeners.on_web_resource_requested.is_none()
+                {
+                    let client = self.reqwest_client.clone();
+                    let verso_internal_sender = self.verso_internal_sender.clone();
+                    tokio::spawn(async move {
+                        fetch_animated_image(
+                            client,
+                            request,
+                            sender,
+                            webview_id,
+                            verso_internal_sender,
+                        )
+                        .await;
+                    });
+                    return;
+                }
+                if let Some(color_manager) = self
+                    .color_manager
+                    .clone()
+                    .filter(|color_manager| color_manager.handles(&request))
+                {
+                    let client = self.reqwest_client.clone();
+                    tokio::spawn(async move {
+                        fetch_color_managed(client, color_manager, request, sender).await;
+                    });
+                    return;
+                }
+                // Requests the controller intercepts are its to schedule.
+                if self.request_scheduler.is_enabled()
+                    && self.event_listeners.on_web_resource_requested.is_none()
+                {
+                    self.request_scheduler
+                        .submit(webview_id, request, sender, Instant::now());
+                    return;
+                }
                 if let Some(to_controller_sender) = to_controller_sender {
                     if let Some(request_map) = &mut self.event_listeners.on_web_resource_requested {
                         let id = uuid::Uuid::new_v4();
@@ -355,6 +578,8 @@ impl Window {
                 }
             }
             EmbedderMsg::PromptPermission(_webview_id, feature, prompt_sender) => {
+                self.audit_log
+                    .permission_requested(webview_id, format!("{feature:?}"));
                 if let Some(tab) = self.tab_manager.tab(webview_id) {
                     let message = format!(
                         "This website would like to request permission for {:?}.",
@@ -440,6 +665,44 @@ impl Window {
             EmbedderMsg::ShowNotification(_webview_id, notification) => {
                 self.show_notification(&notification);
             }
+            EmbedderMsg::NewFavicon(_webview_id, url) => {
+                if let Some(panel) = self.panel.as_ref() {
+                    let panel_id = panel.webview.webview_id;
+                    let size = RasterSize::new(FAVICON_SIZE, FAVICON_SIZE, self.scale_factor());
+                    let client = self.reqwest_client.clone();
+                    let sender = sender.clone();
+                    tokio::spawn(async move {
+                        let Some(icon) = favicon_src(client, url.into_url(), size).await else {
+                            return;
+                        };
+                        let script = format!(
+                            "window.navbar.setTabFavicon({}, {})",
+                            serde_json::to_string(&webview_id).unwrap(),
+                            serde_json::to_string(&icon).unwrap()
+                        );
+                        let _ = tokio::task::spawn_blocking(move || {
+                            execute_script(&sender, &panel_id, script)
+                        })
+                        .await;
+                    });
+                }
+            }
+            EmbedderMsg::Panic(_, reason, backtrace) => {
+                log::error!("Verso WebView {webview_id:?} panicked: {reason}");
+                if let Some(backtrace) = backtrace {
+                    log::debug!("{backtrace}");
+                }
+                compositor
+                    .flight_recorder
+                    .panicked(|| format!("WebView {webview_id:?} panicked: {reason}"));
+                self.record_failure(webview_id, RecoveryTrigger::Crash);
+                if let Some(playlist) = self.playlist.as_mut() {
+                    playlist.failed(webview_id);
+                }
+            }
+            EmbedderMsg::Keyboard(_, event) => {
+                self.scroll_with_keyboard(compositor, webview_id, &event);
+            }
             e => {
                 log::trace!("Verso WebView isn't supporting this message yet: {e:?}")
             }
@@ -482,7 +745,11 @@ impl Window {
                         EmbedderToConstellationMessage::FocusWebView(panel_id),
                     );
 
-                    self.create_tab(&sender, self.panel.as_ref().unwrap().initial_url.clone());
+                    if self.session_webviews.is_empty() {
+                        self.create_tab(&sender, self.panel.as_ref().unwrap().initial_url.clone());
+                    } else {
+                        self.open_session_webviews(&sender);
+                    }
                 } else {
                     log::trace!("Verso Panel ignores NotifyLoadStatusChanged status: {status:?}");
                 }
@@ -657,17 +924,12 @@ impl Window {
                         if let Some(tab) = self.tab_manager.current_tab() {
                             let id = tab.id();
                             if message.starts_with("NAVIGATE_TO:") {
-                                let unparsed_url = message.strip_prefix("NAVIGATE_TO:").unwrap();
-                                let url = match Url::parse(unparsed_url) {
-                                    Ok(url_parsed) => url_parsed,
-                                    Err(e) => {
-                                        if e == url::ParseError::RelativeUrlWithoutBase {
-                                            Url::parse(&format!("https://{}", unparsed_url))
-                                                .unwrap()
-                                        } else {
-                                            panic!("Verso Panel failed to parse URL: {}", e);
-                                        }
-                                    }
+                                let input = message.strip_prefix("NAVIGATE_TO:").unwrap();
+                                let Some(AddressTarget { url, .. }) =
+                                    address_input::interpret(input, &self.address_input)
+                                else {
+                                    log::warn!("Verso Panel can't navigate to {input:?}");
+                                    return false;
                                 };
 
                                 let client = self.reqwest_client.clone();
@@ -910,6 +1172,14 @@ impl Window {
                                     AllowOrDeny::Deny
                                 }
                             };
+                            if let Some(tab_id) = self.tab_manager.tab_id_by_prompt_id(webview_id) {
+                                let document_url = self.document_url(tab_id);
+                                self.audit_log.permission_answered(
+                                    tab_id,
+                                    matches!(result, AllowOrDeny::Allow),
+                                    document_url,
+                                );
+                            }
                             let _ = sender.send(result);
                         }
                         PromptSender::HttpBasicAuthSender(sender) => {
@@ -970,6 +1240,31 @@ impl Window {
                     ));
             }
             return;
+        } else if message == "AUDIT_LOG_GET" {
+            // Pages could read what was blocked in other tabs, only internal pages may.
+            if self
+                .document_url(tab.id())
+                .is_some_and(|url| url.scheme() == "verso")
+            {
+                let _ = self
+                    .verso_internal_sender
+                    .send(VersoInternalMsg::UpdateAuditPage(response_sender));
+            } else {
+                let _ = response_sender.send(PromptResponse::Cancel);
+            }
+            return;
+        } else if message == "GL_CONTEXT_GET" {
+            if self
+                .document_url(tab.id())
+                .is_some_and(|url| url.scheme() == "verso")
+            {
+                let _ = self
+                    .verso_internal_sender
+                    .send(VersoInternalMsg::UpdateGlContextPage(response_sender));
+            } else {
+                let _ = response_sender.send(PromptResponse::Cancel);
+            }
+            return;
         } else if message.starts_with("BOOKMARK_LIST_GET") {
             let _ = self
                 .verso_internal_sender
@@ -1025,6 +1320,425 @@ impl Window {
             return;
         }
     }
+
+    /// URL of the document currently shown in the webview
+    pub(crate) fn document_url(&self, webview_id: WebViewId) -> Option<Url> {
+        self.tab_manager
+            .history(webview_id)
+            .and_then(|history| history.list.get(history.current_idx))
+            .map(|url| url.clone().into_url())
+    }
+
+    /// Scroll the webview with a scrolling key its page didn't handle
+    fn scroll_with_keyboard(
+        &self,
+        compositor: &mut IOCompositor,
+        webview_id: WebViewId,
+        event: &KeyboardEvent,
+    ) {
+        if event.state != KeyState::Down
+            || event
+                .modifiers
+                .intersects(Modifiers::CONTROL | Modifiers::ALT | Modifiers::META)
+        {
+            return;
+        }
+        let Some(tab) = self.tab_manager.tab(webview_id) else {
+            return;
+        };
+        let rect = tab.webview().rect;
+        let cursor = rect.center().to_i32();
+        // A page keeps a bit of the previous one in view.
+        let page = rect.height() * 0.875;
+        let shift = event.modifiers.contains(Modifiers::SHIFT);
+        let delta = match &event.key {
+            Key::ArrowUp => LayoutVector2D::new(0.0, LINE_HEIGHT),
+            Key::ArrowDown => LayoutVector2D::new(0.0, -LINE_HEIGHT),
+            Key::ArrowLeft => LayoutVector2D::new(LINE_HEIGHT, 0.0),
+            Key::ArrowRight => LayoutVector2D::new(-LINE_HEIGHT, 0.0),
+            Key::PageUp => LayoutVector2D::new(0.0, page),
+            Key::PageDown => LayoutVector2D::new(0.0, -page),
+            Key::Character(c) if c == " " && shift => LayoutVector2D::new(0.0, page),
+            Key::Character(c) if c == " " => LayoutVector2D::new(0.0, -page),
+            Key::Home | Key::End => {
+                let location = if event.key == Key::Home {
+                    ScrollLocation::Start
+                } else {
+                    ScrollLocation::End
+                };
+                compositor.on_scroll_event(location, cursor, TouchEventType::Move);
+                return;
+            }
+            _ => return,
+        };
+        compositor.on_smooth_scroll_event(delta, cursor);
+    }
+
+    /// Ask the page how long it took to load its style sheets, fonts and images in view, the
+    /// answer comes back as a [`VersoInternalMsg::FirstViewportComplete`].
+    fn measure_first_viewport(
+        &self,
+        webview_id: WebViewId,
+        sender: &Sender<EmbedderToConstellationMessage>,
+    ) {
+        if !self.request_scheduler.is_enabled() && !log::log_enabled!(log::Level::Debug) {
+            return;
+        }
+        let Some(answer) =
+            execute_script_deferred(sender, &webview_id, FIRST_VIEWPORT_COMPLETE_SCRIPT)
+        else {
+            return;
+        };
+        let verso_internal_sender = self.verso_internal_sender.clone();
+        ROUTER.add_typed_route(
+            answer,
+            Box::new(move |result| {
+                if let Ok(Ok(WebDriverJSValue::Number(milliseconds))) = result {
+                    let _ = verso_internal_sender.send(VersoInternalMsg::FirstViewportComplete(
+                        webview_id,
+                        milliseconds,
+                    ));
+                }
+            }),
+        );
+    }
+
+    /// Record and log how long a page took to complete its first viewport
+    pub(crate) fn record_first_viewport(&mut self, webview_id: WebViewId, milliseconds: f64) {
+        let duration = Duration::from_secs_f64(milliseconds.max(0.0) / 1000.0);
+        self.request_scheduler.record_first_viewport(duration);
+        let stats = self.request_scheduler.stats();
+        log::info!(
+            "First viewport of {} complete after {} ms, request prioritization {} (average {} ms over {} pages, {} requests held)",
+            self.document_url(webview_id)
+                .map_or_else(String::new, |url| url.to_string()),
+            duration.as_millis(),
+            if self.request_scheduler.is_enabled() {
+                "on"
+            } else {
+                "off"
+            },
+            (stats.total_first_viewport / stats.pages as u32).as_millis(),
+            stats.pages,
+            stats.held,
+        );
+    }
+
+    /// Ask every tab for its CSP violations, internal pages excepted
+    pub(crate) fn request_all_csp_violations(
+        &self,
+        sender: &Sender<EmbedderToConstellationMessage>,
+    ) {
+        for tab_id in self.tab_manager.tab_ids() {
+            if let Some(url) = self
+                .document_url(tab_id)
+                .filter(|url| url.scheme() != "verso")
+            {
+                self.request_csp_violations(sender, tab_id, url);
+            }
+        }
+    }
+
+    /// Ask a page for the CSP violations it recorded since the last call, the answer comes
+    /// back as a [`VersoInternalMsg::CspViolations`].
+    fn request_csp_violations(
+        &self,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        webview_id: WebViewId,
+        document_url: Url,
+    ) {
+        let Some(answer) = execute_script_deferred(sender, &webview_id, TAKE_CSP_VIOLATIONS_SCRIPT)
+        else {
+            return;
+        };
+        let verso_internal_sender = self.verso_internal_sender.clone();
+        ROUTER.add_typed_route(
+            answer,
+            Box::new(move |result| {
+                if let Ok(Ok(WebDriverJSValue::String(report))) = result {
+                    let _ = verso_internal_sender.send(VersoInternalMsg::CspViolations(
+                        webview_id,
+                        document_url.clone(),
+                        report,
+                    ));
+                }
+            }),
+        );
+    }
+
+    /// Log a security policy report, record it in the audit log and forward it to the controller if it's listening
+    fn report_security_policy(
+        &mut self,
+        webview_id: WebViewId,
+        to_controller_sender: &Option<ipc::IpcSender<ToControllerMessage>>,
+        report: SecurityPolicyReport,
+    ) {
+        log::warn!(
+            "Verso security policy: {:?} for {} on {}",
+            report.violation,
+            report.resource_url,
+            report.document_url
+        );
+        let (category, reason) = match report.violation {
+            SecurityPolicyViolation::MixedContentBlocked => (
+                AuditCategory::MixedContent,
+                "insecure subresource of a secure page was blocked",
+            ),
+            SecurityPolicyViolation::MixedContentUpgraded => (
+                AuditCategory::MixedContent,
+                "insecure subresource of a secure page was loaded over HTTPS instead",
+            ),
+            SecurityPolicyViolation::IntegrityCheckFailed => (
+                AuditCategory::Integrity,
+                "didn't match its integrity attribute, or failed to load",
+            ),
+        };
+        self.audit_log.record(
+            webview_id,
+            category,
+            Some(report.document_url.clone()),
+            report.resource_url.as_str(),
+            reason,
+        );
+        if !self.event_listeners.on_security_policy_report {
+            return;
+        }
+        if let Some(to_controller_sender) = to_controller_sender {
+            if let Err(error) =
+                to_controller_sender.send(ToControllerMessage::OnSecurityPolicyReport(report))
+            {
+                log::error!("Verso failed to send SecurityPolicyReport to controller: {error}");
+            }
+        }
+    }
+
+    /// Ask the page for the integrity failures it recorded since it started loading, the answer
+    /// comes back as a [`VersoInternalMsg::IntegrityFailures`].
+    fn request_integrity_failures(
+        &self,
+        webview_id: WebViewId,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        site_settings: &SiteSettings,
+    ) {
+        let Some(document_url) = self.document_url(webview_id) else {
+            return;
+        };
+        if !site_settings
+            .policy_for(&document_url)
+            .report_integrity_failures
+        {
+            return;
+        }
+        let Some(answer) =
+            execute_script_deferred(sender, &webview_id, TAKE_INTEGRITY_FAILURES_SCRIPT)
+        else {
+            return;
+        };
+        let verso_internal_sender = self.verso_internal_sender.clone();
+        ROUTER.add_typed_route(
+            answer,
+            Box::new(move |result| {
+                if let Ok(Ok(WebDriverJSValue::String(failures))) = result {
+                    let _ = verso_internal_sender.send(VersoInternalMsg::IntegrityFailures(
+                        webview_id,
+                        document_url.clone(),
+                        failures,
+                    ));
+                }
+            }),
+        );
+    }
+
+    /// Report the integrity failures a page handed over, as a JSON array of resource URLs
+    pub(crate) fn report_integrity_failures(
+        &mut self,
+        webview_id: WebViewId,
+        document_url: Url,
+        failures: &str,
+        to_controller_sender: &Option<ipc::IpcSender<ToControllerMessage>>,
+    ) {
+        let failures: Vec<String> = serde_json::from_str(failures).unwrap_or_default();
+        for failure in failures {
+            if let Ok(resource_url) = document_url.join(&failure) {
+                self.report_security_policy(
+                    webview_id,
+                    to_controller_sender,
+                    SecurityPolicyReport {
+                        violation: SecurityPolicyViolation::IntegrityCheckFailed,
+                        document_url: document_url.clone(),
+                        resource_url,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// A redirect of an insecure request to its HTTPS upgrade
+fn upgrade_redirect(url: Url, upgraded_url: &Url) -> WebResourceResponse {
+    let mut headers = http::HeaderMap::new();
+    if let Ok(location) = http::HeaderValue::from_str(upgraded_url.as_str()) {
+        headers.insert(http::header::LOCATION, location);
+    }
+    WebResourceResponse::new(url)
+        .headers(headers)
+        .status_code(http::StatusCode::TEMPORARY_REDIRECT)
+}
+
+/// Fetch an image for Servo, converted to the output color space if it carries a color profile
+async fn fetch_color_managed(
+    client: reqwest::Client,
+    color_manager: Arc<ColorManager>,
+    request: WebResourceRequest,
+    sender: IpcSender<WebResourceResponseMsg>,
+) {
+    let mut headers = request.headers;
+    // The body is handed to Servo as is, so it must not be content-encoded.
+    headers.remove(http::header::ACCEPT_ENCODING);
+    let url = request.url;
+    let response = match Http3Transport::shared()
+        .send(&client, http::Method::GET, &url, headers)
+        .await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            log::warn!("Verso failed to load image {url}: {error}");
+            let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+            return;
+        }
+    };
+    let status = response.status();
+    let mut headers = response.headers().clone();
+    let body = match response.bytes().await {
+        Ok(body) => body.to_vec(),
+        Err(error) => {
+            log::warn!("Verso failed to read image {url}: {error}");
+            let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+            return;
+        }
+    };
+
+    let (body, converted) = if status.is_success() {
+        // Decoding, converting and encoding would hold up the other tasks of this worker.
+        match tokio::task::spawn_blocking(move || {
+            let converted = color_manager.convert(&body);
+            (body, converted)
+        })
+        .await
+        {
+            Ok(result) => result,
+            Err(error) => {
+                log::warn!("Verso failed to convert image {url}: {error}");
+                let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+                return;
+            }
+        }
+    } else {
+        (body, None)
+    };
+    let body = match converted {
+        Some(converted) => {
+            log::trace!("Verso converted image {url} to the output color space");
+            headers.remove(http::header::CONTENT_LENGTH);
+            headers.insert(
+                http::header::CONTENT_TYPE,
+                http::HeaderValue::from_static("image/png"),
+            );
+            converted.to_vec()
+        }
+        None => body,
+    };
+    let _ = sender
+        .send(WebResourceResponseMsg::Start(
+            WebResourceResponse::new(url)
+                .headers(headers)
+                .status_code(status),
+        ))
+        .and_then(|_| sender.send(WebResourceResponseMsg::SendBodyData(body)))
+        .and_then(|_| sender.send(WebResourceResponseMsg::FinishLoad));
+}
+
+/// Fetch an image for Servo, and hand its frames to the compositor if it is animated
+async fn fetch_animated_image(
+    client: reqwest::Client,
+    request: WebResourceRequest,
+    sender: IpcSender<WebResourceResponseMsg>,
+    webview_id: WebViewId,
+    verso_internal_sender: IpcSender<VersoInternalMsg>,
+) {
+    let mut headers = request.headers;
+    // The body is handed to Servo as is, so it must not be content-encoded.
+    headers.remove(http::header::ACCEPT_ENCODING);
+    let url = request.url;
+    let response = match Http3Transport::shared()
+        .send(&client, http::Method::GET, &url, headers)
+        .await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            log::warn!("Verso failed to load image {url}: {error}");
+            let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+            return;
+        }
+    };
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(error) => {
+            log::warn!("Verso failed to read image {url}: {error}");
+            let _ = sender.send(WebResourceResponseMsg::CancelLoad);
+            return;
+        }
+    };
+    let _ = sender
+        .send(WebResourceResponseMsg::Start(
+            WebResourceResponse::new(url.clone())
+                .headers(headers)
+                .status_code(status),
+        ))
+        .and_then(|_| sender.send(WebResourceResponseMsg::SendBodyData(body.to_vec())))
+        .and_then(|_| sender.send(WebResourceResponseMsg::FinishLoad));
+    if !status.is_success() {
+        return;
+    }
+
+    // Decoding every frame would hold up the other tasks of this worker.
+    if let Ok(Some(animation)) =
+        tokio::task::spawn_blocking(move || decode_animation(url, &body)).await
+    {
+        let _ = verso_internal_sender.send(VersoInternalMsg::AnimatedImage(
+            webview_id,
+            Box::new(animation),
+        ));
+    }
+}
+
+/// The image source the panel shows for a favicon
+///
+/// SVG favicons are rasterized at the panel's device pixel size, other formats are
+/// loaded by the panel itself.
+async fn favicon_src(client: reqwest::Client, url: Url, size: RasterSize) -> Option<String> {
+    if !is_svg_url(&url) {
+        return Some(url.to_string());
+    }
+    let svg = match Http3Transport::shared()
+        .send(&client, http::Method::GET, &url, http::HeaderMap::new())
+        .await
+    {
+        Ok(response) => response.bytes().await.ok()?,
+        Err(error) => {
+            log::warn!("Verso failed to load favicon {url}: {error}");
+            return None;
+        }
+    };
+    match SvgRasterizer::shared().rasterize(&svg, size) {
+        Ok(png) => Some(png_data_url(&png)),
+        Err(error) => {
+            log::warn!("Verso failed to rasterize favicon {url}: {error}");
+            None
+        }
+    }
 }
 
 /// Blocking execute a script on this webview
@@ -1043,3 +1757,58 @@ pub fn execute_script(
     );
     result_receiver.recv().unwrap()
 }
+
+/// Execute a script on this webview without waiting for its result
+pub fn execute_script_async(
+    constellation_sender: &Sender<EmbedderToConstellationMessage>,
+    webview: &WebViewId,
+    js: impl ToString,
+) {
+    let Ok((result_sender, result_receiver)) = ipc::channel::<WebDriverJSResult>() else {
+        return;
+    };
+    // Keep the result receiver alive until the script answers, then drop the answer.
+    ROUTER.add_typed_route(result_receiver, Box::new(|_| {}));
+    send_to_constellation(
+        constellation_sender,
+        EmbedderToConstellationMessage::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
+            webview.0,
+            WebDriverScriptCommand::ExecuteScript(js.to_string(), result_sender),
+        )),
+    );
+}
+
+/// Execute a script on this webview, returns the receiver its result arrives on to poll later
+pub fn execute_script_deferred(
+    constellation_sender: &Sender<EmbedderToConstellationMessage>,
+    webview: &WebViewId,
+    js: impl ToString,
+) -> Option<IpcReceiver<WebDriverJSResult>> {
+    let (result_sender, result_receiver) = ipc::channel::<WebDriverJSResult>().ok()?;
+    send_to_constellation(
+        constellation_sender,
+        EmbedderToConstellationMessage::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
+            webview.0,
+            WebDriverScriptCommand::ExecuteScript(js.to_string(), result_sender),
+        )),
+    );
+    Some(result_receiver)
+}
+
+/// Execute a script on this webview, giving up if it doesn't finish before `timeout`
+pub fn execute_script_with_timeout(
+    constellation_sender: &Sender<EmbedderToConstellationMessage>,
+    webview: &WebViewId,
+    js: impl ToString,
+    timeout: Duration,
+) -> Option<WebDriverJSResult> {
+    let (result_sender, result_receiver) = ipc::channel::<WebDriverJSResult>().ok()?;
+    send_to_constellation(
+        constellation_sender,
+        EmbedderToConstellationMessage::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
+            webview.0,
+            WebDriverScriptCommand::ExecuteScript(js.to_string(), result_sender),
+        )),
+    );
+    result_receiver.try_recv_timeout(timeout).ok()
+}
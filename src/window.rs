@@ -1,23 +1,30 @@
-use std::{cell::Cell, collections::HashMap};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::Instant,
+};
 
 use base::id::WebViewId;
-use constellation_traits::EmbedderToConstellationMessage;
+use constellation_traits::{EmbedderToConstellationMessage, TraversalDirection};
 use crossbeam_channel::Sender;
 use embedder_traits::{
     AlertResponse, AllowOrDeny, ConfirmResponse, Cursor, EmbedderMsg, ImeEvent, InputEvent,
     MouseButton, MouseButtonAction, MouseButtonEvent, MouseMoveEvent, Notification, PromptResponse,
-    TouchEventType, ViewportDetails, WebDriverJSValue, WebResourceResponseMsg, WheelMode,
+    TouchEventType, ViewportDetails, WebDriverJSResult, WebDriverJSValue, WebResourceResponseMsg,
+    WheelMode,
 };
 use euclid::{Point2D, Scale, Size2D};
 use glutin::{
-    config::{ConfigTemplateBuilder, GlConfig},
+    config::GlConfig,
     surface::{Surface, WindowSurface},
 };
 use glutin_winit::DisplayBuilder;
-use ipc_channel::ipc::IpcSender;
-use keyboard_types::{
-    Code, CompositionEvent, CompositionState, KeyState, KeyboardEvent, Modifiers,
+use ipc_channel::{
+    ipc::{IpcReceiver, IpcSender, TryRecvError},
+    router::ROUTER,
 };
+use keyboard_types::{CompositionEvent, CompositionState, KeyState, KeyboardEvent};
 #[cfg(any(target_os = "macos", target_os = "windows"))]
 use muda::{MenuEvent, MenuEventReceiver};
 #[cfg(linux)]
@@ -26,7 +33,10 @@ use notify_rust::Image;
 use raw_window_handle::HasWindowHandle;
 use reqwest::Client;
 use servo_url::ServoUrl;
-use versoview_messages::ToControllerMessage;
+use versoview_messages::{
+    AddressInputSettings, GestureCommand, GlContextSettings, OutputColorDepth, RecoveryAction,
+    RecoveryTrigger, ScriptPolicy, SessionWebView, SessionWindow, ToControllerMessage,
+};
 use webrender_api::{
     ScrollLocation,
     units::{DeviceIntPoint, DevicePoint, DeviceRect, DeviceSize, LayoutVector2D},
@@ -38,17 +48,35 @@ use winit::{
     event::{ElementState, Ime, TouchPhase, WindowEvent},
     event_loop::ActiveEventLoop,
     keyboard::ModifiersState,
-    window::{CursorIcon, Window as WinitWindow, WindowAttributes, WindowId},
+    window::{
+        Cursor as WindowCursor, CursorIcon, Window as WinitWindow, WindowAttributes, WindowId,
+    },
 };
 
 use crate::{
+    animated_images::VIEW_REFRESH_INTERVAL,
+    audit_log::AuditLog,
     bookmark::BookmarkManager,
+    color_management::ColorManager,
     compositor::IOCompositor,
+    drag_drop::{DRAG_SOURCE_SCRIPT, DragData, DragMove, drag_event_script},
+    flight_recorder::RecordedKind,
+    gestures::GestureMapper,
+    input_governor::{InputGovernor, RawPointerSample},
     keyboard::keyboard_event_from_winit,
-    rendering::{RenderingContext, gl_config_picker},
+    network_context::NetworkContextStore,
+    playlist::{LocalTime, PlaylistAction, PlaylistPlayer},
+    recovery::RecoveryEngine,
+    rendering::{RenderingContext, gl_config_picker, gl_config_template},
+    request_priority::{RequestScheduler, VIEWPORT_IMAGES_SCRIPT, parse_viewport_images},
+    site_settings::SiteSettings,
     tab::TabManager,
     verso::{VersoInternalMsg, send_to_constellation},
-    webview::{Panel, WebView, execute_script, prompt::PromptSender, webview_menu::WebViewMenu},
+    webview::{
+        Panel, WebView, content_settings::ContentSettingsStore, execute_script,
+        execute_script_async, execute_script_deferred, launch_params::LaunchParamsStore,
+        prompt::PromptSender, tiling::TileLayout, webview_menu::WebViewMenu,
+    },
 };
 
 use arboard::Clipboard;
@@ -57,6 +85,11 @@ const PANEL_HEIGHT: f64 = 50.0;
 const TAB_HEIGHT: f64 = 30.0;
 const BOOKMARK_HEIGHT: f64 = 30.0;
 const PANEL_PADDING: f64 = 4.0;
+/// Zoom factor of each zoom step a gesture makes
+const GESTURE_ZOOM_STEP: f32 = 1.1;
+/// Pixels scrolled by a line of a mouse wheel or an arrow key
+// FIXME: Should be configurable (from browser setting?) and vary by zoom level.
+pub(crate) const LINE_HEIGHT: f32 = 38.0;
 
 #[derive(Default)]
 pub(crate) struct EventListeners {
@@ -67,11 +100,15 @@ pub(crate) struct EventListeners {
         Option<HashMap<uuid::Uuid, (url::Url, IpcSender<WebResourceResponseMsg>)>>,
     /// This is `true` if the controller wants to get and handle WindowEvent::CloseRequested
     pub(crate) on_close_requested: bool,
+    /// This is `true` if the controller wants to get notified on security policy reports
+    pub(crate) on_security_policy_report: bool,
+    /// This is `true` if the controller wants to get every mouse position the OS reported
+    pub(crate) on_raw_pointer_samples: bool,
 }
 
 #[derive(Debug, Default)]
 struct CursorState {
-    current_cursor: CursorIcon,
+    current_cursor: WindowCursor,
     #[cfg(any(linux, target_os = "windows"))]
     cursor_resizing: bool,
 }
@@ -109,6 +146,52 @@ pub struct Window {
     pub(crate) reqwest_client: Client,
     /// The sender for the Verso internal channel
     pub(crate) verso_internal_sender: IpcSender<VersoInternalMsg>,
+    /// Resamples high-frequency mouse input to the frame rate
+    input_governor: InputGovernor,
+    /// JavaScript and author style switches of the webviews in this window
+    pub(crate) content_settings: ContentSettingsStore,
+    /// Parameters the embedder exposes to the pages of the webviews in this window
+    pub(crate) launch_params: LaunchParamsStore,
+    /// Converts images to the output color space, if color management is enabled
+    pub(crate) color_manager: Option<Arc<ColorManager>>,
+    /// Script engine limits of the profile
+    pub(crate) script_policy: ScriptPolicy,
+    /// Network contexts the webviews in this window are routed through
+    pub(crate) network_contexts: NetworkContextStore,
+    /// Holds page requests until those rendering waits on more have been let through
+    pub(crate) request_scheduler: RequestScheduler,
+    /// Whether the current tab moved since it was asked where its animated images are
+    pub(crate) animated_images_stale: bool,
+    /// Whether the current tab is being asked where its animated images are
+    pub(crate) animated_images_querying: bool,
+    /// When the current tab was last asked where its animated images are
+    animated_images_queried: Option<Instant>,
+    /// Blocking decisions made for the webviews in this window
+    pub(crate) audit_log: AuditLog,
+    /// Opened from a session as a kiosk window
+    pub(crate) kiosk: bool,
+    /// Zoom level the window was opened with
+    pub(crate) zoom_level: Option<f32>,
+    /// Session webviews to open once the panel is loaded
+    pub(crate) session_webviews: VecDeque<SessionWebView>,
+    /// Counts webview failures and schedules their recovery
+    pub(crate) recovery: RecoveryEngine,
+    /// Kiosk playlist the window rotates through
+    pub(crate) playlist: Option<PlaylistPlayer>,
+    /// Webviews collecting the coverage of their pages
+    pub(crate) coverage: HashSet<WebViewId>,
+    /// Gestures and key chords the window recognizes, and their commands
+    pub(crate) gestures: GestureMapper,
+    /// Search template, keywords and rewrites of the address bar
+    pub(crate) address_input: AddressInputSettings,
+    /// Tabs shown at their own rect of the window
+    pub(crate) tiles: TileLayout,
+    /// Whether the window is minimized or covered by other windows, as far as the system tells
+    pub(crate) occluded: bool,
+    /// Drag links, images and selected text out of the pages
+    pub(crate) drag_and_drop: bool,
+    /// Answer of the page a drag may start from to what is under the pointer
+    drag_source_answer: Option<IpcReceiver<WebDriverJSResult>>,
 }
 
 impl Window {
@@ -117,19 +200,46 @@ impl Window {
         evl: &ActiveEventLoop,
         window_attributes: WindowAttributes,
         verso_internal_sender: IpcSender<VersoInternalMsg>,
+        gl_context: GlContextSettings,
     ) -> (Self, RenderingContext) {
-        let template = ConfigTemplateBuilder::new()
-            .with_alpha_size(8)
-            .with_transparency(cfg!(macos));
-
-        let (window, gl_config) = DisplayBuilder::new()
-            .with_window_attributes(Some(window_attributes))
-            .build(evl, template, gl_config_picker)
+        let color_depth = gl_context.color_depth;
+        let transparent = window_attributes.transparent;
+        let build = |color_depth, transparent| {
+            DisplayBuilder::new()
+                .with_window_attributes(Some(window_attributes.clone()))
+                .build(
+                    evl,
+                    gl_config_template(color_depth, transparent),
+                    gl_config_picker(color_depth),
+                )
+        };
+        let (window, gl_config) = build(color_depth, transparent)
+            .or_else(|error| {
+                // Displays without floating point window configs fail the template.
+                if color_depth != OutputColorDepth::Float16 {
+                    return Err(error);
+                }
+                log::warn!("No 16-bit floating point window config, trying 10 bits: {error}");
+                build(OutputColorDepth::Rgb10, transparent)
+            })
+            .or_else(|error| {
+                // Displays without an alpha visual can still show the window opaque.
+                if !transparent {
+                    return Err(error);
+                }
+                log::warn!("No window config with per-pixel alpha, the window is opaque: {error}");
+                build(color_depth, false)
+            })
             .expect("Failed to create window and gl config");
 
         let window = window.ok_or("Failed to create window").unwrap();
 
-        log::debug!("Picked a config with {} samples", gl_config.num_samples());
+        log::debug!(
+            "Picked a config with {} samples, {:?} color and {} bits of alpha",
+            gl_config.num_samples(),
+            gl_config.color_buffer_type(),
+            gl_config.alpha_size()
+        );
 
         #[cfg(macos)]
         unsafe {
@@ -142,7 +252,7 @@ impl Window {
             }
         }
         let (rendering_context, surface) =
-            RenderingContext::create(&window, &gl_config, window.inner_size())
+            RenderingContext::create(&window, &gl_config, window.inner_size(), gl_context)
                 .expect("Failed to create rendering context");
         log::trace!("Created rendering context for window {:?}", window);
 
@@ -164,6 +274,29 @@ impl Window {
                 show_bookmark: false,
                 reqwest_client: Client::new(),
                 verso_internal_sender,
+                input_governor: InputGovernor::default(),
+                content_settings: ContentSettingsStore::default(),
+                launch_params: LaunchParamsStore::default(),
+                color_manager: None,
+                script_policy: ScriptPolicy::default(),
+                network_contexts: NetworkContextStore::default(),
+                request_scheduler: RequestScheduler::default(),
+                animated_images_stale: false,
+                animated_images_querying: false,
+                animated_images_queried: None,
+                audit_log: AuditLog::default(),
+                kiosk: false,
+                zoom_level: None,
+                session_webviews: VecDeque::new(),
+                recovery: RecoveryEngine::default(),
+                playlist: None,
+                coverage: HashSet::new(),
+                gestures: GestureMapper::default(),
+                address_input: AddressInputSettings::default(),
+                tiles: TileLayout::default(),
+                occluded: false,
+                drag_and_drop: true,
+                drag_source_answer: None,
             },
             rendering_context,
         )
@@ -194,6 +327,7 @@ impl Window {
             .rendering_context
             .create_surface(&window)
             .unwrap();
+        compositor.rendering_context.apply_present_mode(&surface);
 
         let mut window = Self {
             window,
@@ -213,7 +347,31 @@ impl Window {
             show_bookmark: false,
             reqwest_client: Client::new(),
             verso_internal_sender,
+            input_governor: InputGovernor::default(),
+            content_settings: ContentSettingsStore::default(),
+            launch_params: LaunchParamsStore::default(),
+            color_manager: None,
+            script_policy: ScriptPolicy::default(),
+            network_contexts: NetworkContextStore::default(),
+            request_scheduler: RequestScheduler::default(),
+            animated_images_stale: false,
+            animated_images_querying: false,
+            animated_images_queried: None,
+            audit_log: AuditLog::default(),
+            kiosk: false,
+            zoom_level: None,
+            session_webviews: VecDeque::new(),
+            recovery: RecoveryEngine::default(),
+            playlist: None,
+            coverage: HashSet::new(),
+            gestures: GestureMapper::default(),
+            address_input: AddressInputSettings::default(),
+            tiles: TileLayout::default(),
+            occluded: false,
+            drag_and_drop: true,
+            drag_source_answer: None,
         };
+        compositor.add_window_document(&window);
         compositor.swap_current_window(&mut window);
         window
     }
@@ -276,7 +434,7 @@ impl Window {
         &mut self,
         constellation_sender: &Sender<EmbedderToConstellationMessage>,
         initial_url: ServoUrl,
-    ) {
+    ) -> WebViewId {
         let webview_id = WebViewId::new();
         let size = self.size().to_f32();
         let rect = DeviceRect::from_size(size);
@@ -311,6 +469,64 @@ impl Window {
             EmbedderToConstellationMessage::NewWebView(initial_url, webview_id, viewport_details),
         );
         log::debug!("Verso Window {:?} adds webview {}", self.id(), webview_id);
+        webview_id
+    }
+
+    /// Open the queued session webviews as tabs, with their network context and content settings
+    pub fn open_session_webviews(
+        &mut self,
+        constellation_sender: &Sender<EmbedderToConstellationMessage>,
+    ) {
+        while let Some(session_webview) = self.session_webviews.pop_front() {
+            let webview_id = self.create_tab(constellation_sender, session_webview.url.into());
+            if let Some(settings) = session_webview.content_settings {
+                self.content_settings.set(webview_id, settings);
+            }
+            if let Some(params) = session_webview.launch_params {
+                self.launch_params.set(webview_id, params);
+            }
+            if let Some(context) = session_webview.network_context {
+                if let Err(error) = self.network_contexts.assign(webview_id, Some(context)) {
+                    log::error!("Verso failed to route session webview {webview_id}: {error}");
+                }
+            }
+        }
+    }
+
+    /// Describe the window and its tabs as a session window
+    pub fn export_session(&self) -> SessionWindow {
+        let fullscreen = self.window.fullscreen().is_some();
+        SessionWindow {
+            position: self.window.outer_position().ok(),
+            size: Some(self.window.inner_size()),
+            maximized: self.window.is_maximized(),
+            fullscreen: fullscreen && !self.kiosk,
+            panel: self.panel.is_some(),
+            kiosk: self.kiosk,
+            zoom: self.zoom_level,
+            webviews: self
+                .tab_manager
+                .tab_ids()
+                .into_iter()
+                .filter(|webview_id| {
+                    !self
+                        .playlist
+                        .as_ref()
+                        .is_some_and(|playlist| playlist.owns(*webview_id))
+                })
+                .filter_map(|webview_id| {
+                    Some(SessionWebView {
+                        url: self.document_url(webview_id)?,
+                        network_context: self
+                            .network_contexts
+                            .context_of(webview_id)
+                            .map(str::to_string),
+                        content_settings: self.content_settings.override_of(webview_id),
+                        launch_params: self.launch_params.get(webview_id).cloned(),
+                    })
+                })
+                .collect(),
+        }
     }
 
     /// Close a tab
@@ -352,6 +568,7 @@ impl Window {
         let size = self.size().to_f32();
         let rect = DeviceRect::from_size(size);
         let content_size = self.get_content_size(rect, show_tab, self.show_bookmark);
+        let content_size = self.tiles.rect(tab_id).unwrap_or(content_size);
         let (tab_id, prompt_id) = self.tab_manager.set_size(tab_id, content_size);
 
         if let Some(prompt_id) = prompt_id {
@@ -363,7 +580,10 @@ impl Window {
             let old_tab_id = self.tab_manager.current_tab_id();
             if self.tab_manager.activate_tab(tab_id).is_some() {
                 // throttle the old tab to avoid unnecessary animation caclulations
-                if let Some(old_tab_id) = old_tab_id {
+                // unless it stays visible in its tile
+                if let Some(old_tab_id) =
+                    old_tab_id.filter(|old_tab_id| self.tiles.rect(*old_tab_id).is_none())
+                {
                     let _ = compositor.constellation_chan.send(
                         EmbedderToConstellationMessage::SetWebViewThrottled(old_tab_id, true),
                     );
@@ -396,6 +616,45 @@ impl Window {
         }
     }
 
+    /// Show a tab at `rect` of the window next to the other tiled tabs, or back in the content area
+    pub fn set_tab_rect(
+        &mut self,
+        compositor: &mut IOCompositor,
+        tab_id: WebViewId,
+        rect: Option<DeviceRect>,
+    ) {
+        if self.tab_manager.tab(tab_id).is_none() {
+            log::warn!("Verso can't tile {tab_id:?}, it isn't a tab of this window");
+            return;
+        }
+        self.tiles.set(tab_id, rect);
+
+        let show_tab = self.tab_manager.count() > 1;
+        let content_size = self.get_content_size(
+            DeviceRect::from_size(self.size().to_f32()),
+            show_tab,
+            self.show_bookmark,
+        );
+        let rect = rect.unwrap_or(content_size);
+        let (tab_id, prompt_id) = self.tab_manager.set_size(tab_id, rect);
+        if let Some(prompt_id) = prompt_id {
+            compositor.on_resize_webview_event(prompt_id, rect);
+        }
+        if let Some(tab_id) = tab_id {
+            compositor.on_resize_webview_event(tab_id, rect);
+
+            // Tiled tabs are painted, the compositor keeps them animating unless they're occluded.
+            let visible = self.tiles.rect(tab_id).is_some()
+                || self.tab_manager.current_tab_id() == Some(tab_id);
+            if !visible {
+                let _ = compositor.constellation_chan.send(
+                    EmbedderToConstellationMessage::SetWebViewThrottled(tab_id, true),
+                );
+            }
+        }
+        compositor.send_root_pipeline_display_list(self);
+    }
+
     /// Handle Winit window event and return a boolean to indicate if the compositor should repaint immediately.
     pub fn handle_winit_window_event(
         &mut self,
@@ -403,40 +662,82 @@ impl Window {
         compositor: &mut IOCompositor,
         event: &winit::event::WindowEvent,
     ) {
+        if compositor.flight_recorder.is_enabled() && event != &WindowEvent::RedrawRequested {
+            compositor
+                .flight_recorder
+                .record(RecordedKind::Input, || format!("{event:?}"));
+        }
         match event {
             WindowEvent::RedrawRequested => {
-                if compositor.ready_to_present {
+                if compositor.ready_to_present && compositor.presenting_window == Some(self.id()) {
                     self.window.pre_present_notify();
-                    if let Err(err) = compositor.rendering_context.present(&self.surface) {
+                    let presented = match compositor.take_present_damage() {
+                        Some(damage) => compositor
+                            .rendering_context
+                            .present_with_damage(&self.surface, &damage),
+                        None => compositor.rendering_context.present(&self.surface),
+                    };
+                    if let Err(err) = presented {
                         log::warn!("Failed to present surface: {:?}", err);
                     }
+                    compositor.on_frame_presented(self.id());
                     compositor.ready_to_present = false;
                 }
             }
             WindowEvent::Focused(focused) => {
                 if *focused {
                     compositor.swap_current_window(self);
+                    compositor.check_gpu_switch(self);
                 }
             }
             WindowEvent::Resized(size) => {
                 if self.window.has_focus() {
                     self.resizing = true;
                 }
+                self.viewport_changed();
                 let size = Size2D::new(size.width, size.height);
                 compositor.resize(size.to_f32(), self);
             }
+            WindowEvent::Occluded(occluded) => {
+                // Throttle the webviews while nothing of the window can be seen.
+                self.occluded = *occluded;
+                compositor.send_root_pipeline_display_list(self);
+            }
+            WindowEvent::Moved(_) => {
+                // The window may be on another monitor.
+                compositor.update_refresh_rate(self);
+            }
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 compositor.on_scale_factor_event(*scale_factor as f32, self);
+                compositor.update_refresh_rate(self);
+                // Moving to another display may move rendering to another GPU.
+                compositor.check_gpu_switch(self);
             }
             WindowEvent::CursorEntered { .. } => {
                 compositor.swap_current_window(self);
             }
             WindowEvent::CursorLeft { .. } => {
                 self.mouse_position.set(None);
+                // Drags don't leave the window.
+                if let Some(drag) = compositor.drag.cancel(self.id()) {
+                    if let Some(target) = drag.target {
+                        self.dispatch_drag_event(
+                            sender,
+                            target,
+                            "dragleave",
+                            &drag.data,
+                            drag.point,
+                        );
+                    }
+                    compositor.send_root_pipeline_display_list(self);
+                }
             }
             WindowEvent::CursorMoved { position, .. } => {
                 let point: DevicePoint = DevicePoint::new(position.x as f32, position.y as f32);
                 self.mouse_position.set(Some(*position));
+                if self.drag_pointer_moved(compositor, sender, point) {
+                    return;
+                }
                 let webview_id = match self.focused_webview_id {
                     Some(webview_id) => webview_id,
                     None => {
@@ -445,12 +746,15 @@ impl Window {
                     }
                 };
 
-                forward_input_event(
-                    compositor,
-                    webview_id,
-                    sender,
-                    InputEvent::MouseMove(MouseMoveEvent { point }),
-                );
+                // The rest of the burst is forwarded by `flush_pending_input`.
+                if let Some(point) = self.input_governor.on_mouse_move(point, Instant::now()) {
+                    forward_input_event(
+                        compositor,
+                        webview_id,
+                        sender,
+                        InputEvent::MouseMove(MouseMoveEvent { point }),
+                    );
+                }
 
                 // handle Windows and Linux non-decoration window resize cursor
                 #[cfg(any(linux, target_os = "windows"))]
@@ -491,6 +795,19 @@ impl Window {
 
                 /* handle mouse events */
 
+                // The page has to see the pointer where the button is pressed.
+                if let (Some(point), Some(webview_id)) = (
+                    self.input_governor.take_pending_move(Instant::now()),
+                    self.focused_webview_id,
+                ) {
+                    forward_input_event(
+                        compositor,
+                        webview_id,
+                        sender,
+                        InputEvent::MouseMove(MouseMoveEvent { point }),
+                    );
+                }
+
                 let button: MouseButton = match button {
                     winit::event::MouseButton::Left => MouseButton::Left,
                     winit::event::MouseButton::Right => MouseButton::Right,
@@ -519,11 +836,31 @@ impl Window {
                     }
                 };
 
+                if button == MouseButton::Left && *state == ElementState::Released {
+                    if let Some(source) = self.drop_dragged(compositor, sender, point) {
+                        // The page the drag started from gets the button up, but no click.
+                        forward_input_event(
+                            compositor,
+                            source,
+                            sender,
+                            InputEvent::MouseButton(event),
+                        );
+                        return;
+                    }
+                }
+
                 let Some(webview_id) = &compositor.webview_id_from_point(point) else {
                     log::trace!("No webview at point, skipping MouseInput event.");
                     return;
                 };
 
+                if self.drag_and_drop
+                    && button == MouseButton::Left
+                    && *state == ElementState::Pressed
+                {
+                    compositor.drag.press(self.id(), *webview_id, point);
+                }
+
                 forward_input_event(
                     compositor,
                     *webview_id,
@@ -546,8 +883,36 @@ impl Window {
                     );
                 }
             }
-            WindowEvent::PinchGesture { delta, .. } => {
-                compositor.on_zoom_window_event(1.0 + *delta as f32, self);
+            WindowEvent::PinchGesture { delta, phase, .. } => {
+                match self.gestures.pinch(*delta, *phase) {
+                    Some(GestureCommand::Zoom) => {
+                        compositor.on_zoom_window_event(1.0 + *delta as f32, self);
+                    }
+                    Some(command) => self.run_gesture_command(compositor, command),
+                    None => {}
+                }
+            }
+            WindowEvent::RotationGesture { delta, phase, .. } => {
+                if let Some(command) = self.gestures.rotation(*delta, *phase) {
+                    self.run_gesture_command(compositor, command);
+                }
+            }
+            WindowEvent::Touch(touch) => {
+                if touch.phase == TouchPhase::Moved {
+                    self.viewport_changed();
+                }
+                let size = self.window.inner_size();
+                let command = self.gestures.touch(
+                    touch.id,
+                    touch.phase,
+                    DevicePoint::new(touch.location.x as f32, touch.location.y as f32),
+                    DeviceSize::new(size.width as f32, size.height as f32),
+                    self.window.scale_factor() as f32,
+                    Instant::now(),
+                );
+                if let Some(command) = command {
+                    self.run_gesture_command(compositor, command);
+                }
             }
             WindowEvent::MouseWheel { delta, phase, .. } => {
                 let point = match self.mouse_position.get() {
@@ -558,10 +923,7 @@ impl Window {
                     }
                 };
 
-                // FIXME: Pixels per line, should be configurable (from browser setting?) and vary by zoom level.
-                const LINE_HEIGHT: f32 = 38.0;
-
-                let (mut x, mut y, _mode) = match delta {
+                let (mut x, mut y, mode) = match delta {
                     winit::event::MouseScrollDelta::LineDelta(x, y) => {
                         (*x as f64, (*y * LINE_HEIGHT) as f64, WheelMode::DeltaLine)
                     }
@@ -585,12 +947,25 @@ impl Window {
                     TouchPhase::Ended => TouchEventType::Up,
                     TouchPhase::Cancelled => TouchEventType::Cancel,
                 };
+                self.viewport_changed();
+                let point = DeviceIntPoint::new(point.x as i32, point.y as i32);
 
-                compositor.on_scroll_event(
-                    ScrollLocation::Delta(LayoutVector2D::new(x as f32, y as f32)),
-                    DeviceIntPoint::new(point.x as i32, point.y as i32),
+                // Wheel notches are animated, touchpads already scroll in small steps.
+                if mode == WheelMode::DeltaLine && phase == TouchEventType::Move {
+                    compositor
+                        .on_smooth_scroll_event(LayoutVector2D::new(x as f32, y as f32), point);
+                    return;
+                }
+
+                let Some((delta, phase)) = self.input_governor.on_wheel(
+                    LayoutVector2D::new(x as f32, y as f32),
                     phase,
-                );
+                    Instant::now(),
+                ) else {
+                    return;
+                };
+
+                compositor.on_scroll_event(ScrollLocation::Delta(delta), point, phase);
             }
             WindowEvent::ModifiersChanged(modifier) => self.modifiers_state.set(modifier.state()),
             WindowEvent::Ime(event) => {
@@ -679,66 +1054,597 @@ impl Window {
                 if self.handle_keyboard_shortcut(compositor, &event) {
                     return;
                 }
+                // Arrow keys, space and page keys scroll the page.
+                if event.state == KeyState::Down {
+                    self.viewport_changed();
+                }
                 forward_input_event(compositor, webview_id, sender, InputEvent::Keyboard(event));
             }
             e => log::trace!("Verso Window isn't supporting this window event yet: {e:?}"),
         }
     }
 
-    /// Handle Window keyboard shortcut
+    /// Forward mouse input held back by the input governor during the last frame interval.
+    pub(crate) fn flush_pending_input(
+        &mut self,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        compositor: &mut IOCompositor,
+    ) {
+        if !self.input_governor.has_pending() {
+            return;
+        }
+        let (point, wheel) = self.input_governor.flush(Instant::now());
+        if let (Some(point), Some(webview_id)) = (point, self.focused_webview_id) {
+            forward_input_event(
+                compositor,
+                webview_id,
+                sender,
+                InputEvent::MouseMove(MouseMoveEvent { point }),
+            );
+        }
+        if let (Some((delta, phase)), Some(position)) = (wheel, self.mouse_position.get()) {
+            compositor.on_scroll_event(
+                ScrollLocation::Delta(delta),
+                DeviceIntPoint::new(position.x as i32, position.y as i32),
+                phase,
+            );
+        }
+    }
+
+    /// Let through the held page requests whose turn came.
     ///
-    /// - Returns `true` if the event is handled, then we should skip sending it to constellation
-    fn handle_keyboard_shortcut(
+    /// Pages that scrolled since their images were classified are asked where their images are,
+    /// the answer comes back as a [`VersoInternalMsg::ViewportImages`].
+    pub(crate) fn release_requests(&mut self, sender: &Sender<EmbedderToConstellationMessage>) {
+        if !self.request_scheduler.is_enabled() {
+            return;
+        }
+        let now = Instant::now();
+        for webview_id in self.request_scheduler.stale_viewports(now) {
+            let Some(answer) = execute_script_deferred(sender, &webview_id, VIEWPORT_IMAGES_SCRIPT)
+            else {
+                continue;
+            };
+            self.request_scheduler.viewport_query_sent(webview_id, now);
+            let verso_internal_sender = self.verso_internal_sender.clone();
+            ROUTER.add_typed_route(
+                answer,
+                Box::new(move |result| {
+                    let images = match result {
+                        Ok(Ok(WebDriverJSValue::String(images))) => Some(images),
+                        _ => None,
+                    };
+                    let _ = verso_internal_sender
+                        .send(VersoInternalMsg::ViewportImages(webview_id, images));
+                }),
+            );
+        }
+        self.request_scheduler.release_due(now);
+    }
+
+    /// The viewport of the pages moved, the images near it have to be asked again.
+    fn viewport_changed(&mut self) {
+        self.request_scheduler.viewport_changed();
+        self.animated_images_stale = true;
+    }
+
+    /// Ask the current tab where its images are if it moved, to pause the animated images the
+    /// compositor plays away from its viewport.
+    ///
+    /// The answer comes back as a [`VersoInternalMsg::AnimatedImagesInView`].
+    pub(crate) fn query_animated_images_in_view(
+        &mut self,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        compositor: &IOCompositor,
+    ) {
+        let now = Instant::now();
+        if self
+            .next_animated_images_query()
+            .is_none_or(|query| now < query)
+        {
+            return;
+        }
+        self.animated_images_stale = false;
+        let Some(webview_id) = self
+            .tab_manager
+            .current_tab_id()
+            .filter(|webview_id| compositor.has_animated_images(*webview_id))
+        else {
+            return;
+        };
+        let Some(answer) = execute_script_deferred(sender, &webview_id, VIEWPORT_IMAGES_SCRIPT)
+        else {
+            return;
+        };
+        self.animated_images_querying = true;
+        self.animated_images_queried = Some(now);
+        let verso_internal_sender = self.verso_internal_sender.clone();
+        ROUTER.add_typed_route(
+            answer,
+            Box::new(move |result| {
+                let images = match result {
+                    Ok(Ok(WebDriverJSValue::String(images))) => Some(images),
+                    _ => None,
+                };
+                let _ = verso_internal_sender
+                    .send(VersoInternalMsg::AnimatedImagesInView(webview_id, images));
+            }),
+        );
+    }
+
+    /// When the current tab is next asked where its animated images are, if it moved
+    pub(crate) fn next_animated_images_query(&self) -> Option<Instant> {
+        (self.animated_images_stale && !self.animated_images_querying).then(|| {
+            self.animated_images_queried
+                .map_or_else(Instant::now, |queried| queried + VIEW_REFRESH_INTERVAL)
+        })
+    }
+
+    /// A page answered where its images are, `None` if it couldn't
+    pub(crate) fn set_viewport_images(&mut self, webview_id: WebViewId, images: Option<String>) {
+        match images {
+            Some(images) => self
+                .request_scheduler
+                .set_viewport_images(webview_id, parse_viewport_images(&images)),
+            None => self.request_scheduler.viewport_query_failed(webview_id),
+        }
+        self.request_scheduler.release_due(Instant::now());
+    }
+
+    /// Count a failure of a webview towards its recovery
+    pub(crate) fn record_failure(&mut self, webview_id: WebViewId, trigger: RecoveryTrigger) {
+        let url = self.document_url(webview_id);
+        self.recovery
+            .record_failure(webview_id, trigger, url, Instant::now());
+    }
+
+    /// Count a renderer restart against every tab, since all of them were rendered by it
+    pub(crate) fn renderer_restarted(&mut self) {
+        for tab_id in self.tab_manager.tab_ids() {
+            self.record_failure(tab_id, RecoveryTrigger::RendererRestart);
+        }
+    }
+
+    /// Probe the shown tab and take the recovery actions whose backoff is over.
+    ///
+    /// Returns `true` if the browser has to restart.
+    pub(crate) fn recover(
         &mut self,
         compositor: &mut IOCompositor,
-        event: &KeyboardEvent,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
     ) -> bool {
-        let is_macos = cfg!(target_os = "macos");
-        let control_or_meta = if is_macos {
-            Modifiers::META
+        if !self.recovery.is_enabled() {
+            return false;
+        }
+        let now = Instant::now();
+        let sender = compositor.constellation_chan.clone();
+        let shown = self.tab_manager.current_tab_id();
+        for webview_id in self.recovery.check_probes(&sender, shown, now) {
+            log::warn!("Verso WebView {webview_id:?} didn't answer the liveness probe");
+            self.record_failure(webview_id, RecoveryTrigger::Unresponsive);
+        }
+        if let Some(to_controller_sender) = to_controller_sender {
+            for event in self.recovery.take_events() {
+                if let Err(error) =
+                    to_controller_sender.send(ToControllerMessage::OnRecovery(event))
+                {
+                    log::warn!("Verso failed to send recovery event to controller: {error}");
+                }
+            }
         } else {
-            Modifiers::CONTROL
-        };
+            self.recovery.take_events();
+        }
 
-        if event.state == KeyState::Down {
-            // TODO: New Window, Close Browser
-            match (event.modifiers, event.code) {
-                (modifiers, Code::KeyT) if modifiers == control_or_meta => {
-                    (*self).create_tab(
-                        &compositor.constellation_chan,
-                        ServoUrl::parse("https://example.com").unwrap(),
-                    );
-                    return true;
+        let mut restart = false;
+        for (webview_id, action) in self.recovery.due_actions(now) {
+            if self.tab_manager.tab(webview_id).is_none() {
+                continue;
+            }
+            log::warn!("Verso recovers WebView {webview_id:?} with {action:?}");
+            match action {
+                RecoveryAction::Reload => {
+                    let message = match self.document_url(webview_id) {
+                        Some(url) => EmbedderToConstellationMessage::LoadUrl(
+                            webview_id,
+                            ServoUrl::from_url(url),
+                        ),
+                        None => EmbedderToConstellationMessage::Reload(webview_id),
+                    };
+                    send_to_constellation(&sender, message);
                 }
-                (modifiers, Code::KeyW) if modifiers == control_or_meta => {
-                    if let Some(tab_id) = self.tab_manager.current_tab_id() {
-                        (*self).close_tab(compositor, tab_id);
+                RecoveryAction::RecreateWebView => {
+                    let Some(url) = self.document_url(webview_id) else {
+                        continue;
+                    };
+                    let settings = self.content_settings.override_of(webview_id);
+                    let params = self.launch_params.get(webview_id).cloned();
+                    let context = self
+                        .network_contexts
+                        .context_of(webview_id)
+                        .map(str::to_string);
+                    let new_id = self.create_tab(&sender, ServoUrl::from_url(url));
+                    if let Some(settings) = settings {
+                        self.content_settings.set(new_id, settings);
+                    }
+                    if let Some(params) = params {
+                        self.launch_params.set(new_id, params);
                     }
-                    return true;
+                    if let Err(error) = self.network_contexts.assign(new_id, context) {
+                        log::error!("Verso failed to route recreated WebView {new_id:?}: {error}");
+                    }
+                    self.recovery.replaced(webview_id, new_id);
+                    self.close_tab(compositor, webview_id);
                 }
-                (modifiers, Code::KeyL) if modifiers == control_or_meta => {
-                    // focus on navigation input
-                    if let Some(panel) = &self.panel {
-                        let webview_id = &panel.webview.webview_id;
+                RecoveryAction::RestartBrowser => restart = true,
+            }
+        }
+        restart
+    }
 
-                        let _ = compositor.constellation_chan.send(
-                            EmbedderToConstellationMessage::FocusWebView(webview_id.clone()),
-                        );
+    /// Advance the playlist of the window and take the actions it returns.
+    pub(crate) fn drive_playlist(&mut self, compositor: &mut IOCompositor) {
+        let Some(playlist) = self.playlist.as_mut() else {
+            return;
+        };
+        let actions = playlist.poll(Instant::now(), LocalTime::now());
+        let fading = playlist.fading_out().is_some();
+        self.apply_playlist_actions(compositor, actions);
+        if fading {
+            // Repaint with the new opacities of the cross-fade.
+            compositor.send_root_pipeline_display_list(self);
+        }
+    }
 
-                        let _ = execute_script(
-                            &compositor.constellation_chan,
-                            webview_id,
-                            "window.navbar.focusUrlInput()".to_string(),
-                        );
+    /// Take the actions returned by the playlist of the window.
+    pub(crate) fn apply_playlist_actions(
+        &mut self,
+        compositor: &mut IOCompositor,
+        actions: Vec<PlaylistAction>,
+    ) {
+        for action in actions {
+            match action {
+                PlaylistAction::Preload(url) => {
+                    let webview_id = self
+                        .create_hidden_tab(&compositor.constellation_chan, ServoUrl::from_url(url));
+                    if let Some(playlist) = self.playlist.as_mut() {
+                        playlist.preloading(webview_id);
                     }
-                    return true;
                 }
+                PlaylistAction::Show(webview_id, _) => {
+                    self.show_playlist_webview(compositor, webview_id)
+                }
+                PlaylistAction::Close(webview_id) => send_to_constellation(
+                    &compositor.constellation_chan,
+                    EmbedderToConstellationMessage::CloseWebView(webview_id),
+                ),
+            }
+        }
+    }
+
+    /// Create a webview loading the URL without showing it or adding it to the tab bar
+    fn create_hidden_tab(
+        &mut self,
+        constellation_sender: &Sender<EmbedderToConstellationMessage>,
+        initial_url: ServoUrl,
+    ) -> WebViewId {
+        let webview_id = WebViewId::new();
+        let rect = DeviceRect::from_size(self.size().to_f32());
+        let content_size = self.get_content_size(rect, false, self.show_bookmark);
 
-                _ => (),
+        let hidpi_scale_factor = Scale::new(self.scale_factor() as f32);
+        let viewport_details = ViewportDetails {
+            size: content_size.size().to_f32() / hidpi_scale_factor,
+            hidpi_scale_factor,
+        };
+        let mut webview = WebView::new(webview_id, viewport_details);
+        webview.set_size(content_size);
+        self.tab_manager.append_tab(webview, false);
+
+        send_to_constellation(
+            constellation_sender,
+            EmbedderToConstellationMessage::NewWebView(initial_url, webview_id, viewport_details),
+        );
+        log::debug!(
+            "Verso Window {:?} preloads webview {}",
+            self.id(),
+            webview_id
+        );
+        webview_id
+    }
+
+    /// Show a playlist webview in place of the current tab
+    fn show_playlist_webview(&mut self, compositor: &mut IOCompositor, webview_id: WebViewId) {
+        let rect = DeviceRect::from_size(self.size().to_f32());
+        let content_size = self.get_content_size(rect, false, self.show_bookmark);
+        self.tab_manager.set_size(webview_id, content_size);
+        compositor.on_resize_webview_event(webview_id, content_size);
+        if self.tab_manager.activate_tab(webview_id).is_none() {
+            return;
+        }
+        let _ = compositor.constellation_chan.send(
+            EmbedderToConstellationMessage::SetWebViewThrottled(webview_id, false),
+        );
+        self.focused_webview_id = Some(webview_id);
+        let _ = compositor
+            .constellation_chan
+            .send(EmbedderToConstellationMessage::FocusWebView(webview_id));
+        compositor.send_root_pipeline_display_list(self);
+    }
+
+    /// Opacity to paint the webview with, below 1 while it fades in.
+    pub fn webview_opacity(&self, webview_id: WebViewId) -> f32 {
+        self.playlist
+            .as_ref()
+            .map_or(1.0, |playlist| playlist.opacity(webview_id, Instant::now()))
+    }
+
+    /// Start a drag or move the one in progress, returns `true` if the pointer is dragging.
+    fn drag_pointer_moved(
+        &mut self,
+        compositor: &mut IOCompositor,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        point: DevicePoint,
+    ) -> bool {
+        match compositor.drag.moved(self.id(), point) {
+            DragMove::None => return false,
+            DragMove::Start(source) => {
+                // The pointer keeps going to the page until it tells what is dragged.
+                self.drag_source_answer =
+                    execute_script_deferred(sender, &source, DRAG_SOURCE_SCRIPT);
+                if self.drag_source_answer.is_none() {
+                    compositor.drag.start(None, point);
+                }
+                return false;
+            }
+            DragMove::Waiting(source) => {
+                let data = match self.drag_source_answer.as_ref().map(IpcReceiver::try_recv) {
+                    Some(Err(TryRecvError::Empty)) => return false,
+                    Some(Ok(Ok(WebDriverJSValue::String(data)))) => DragData::from_json(&data),
+                    _ => None,
+                };
+                self.drag_source_answer = None;
+                if !compositor.drag.start(data, point) {
+                    return false;
+                }
+                log::debug!("Verso Window {:?} drags from {source:?}", self.id());
             }
+            DragMove::Over => {}
         }
 
-        false
+        let Some(data) = compositor
+            .drag
+            .dragging(self.id())
+            .map(|drag| drag.data.clone())
+        else {
+            return false;
+        };
+        let target = compositor.webview_id_from_point(point);
+        if let Some(left) = compositor.drag.set_target(target) {
+            if let Some(left) = left {
+                self.dispatch_drag_event(sender, left, "dragleave", &data, point);
+            }
+            if let Some(target) = target {
+                self.dispatch_drag_event(sender, target, "dragenter", &data, point);
+            }
+        }
+        if let Some(target) = target {
+            self.dispatch_drag_event(sender, target, "dragover", &data, point);
+        }
+        // Move the drag label and the outline of the webview it is over.
+        compositor.send_root_pipeline_display_list(self);
+        true
+    }
+
+    /// Drop what is dragged where the pointer is, returns the webview the drag started from if there was a drag.
+    ///
+    /// A link or image dropped on the panel opens in a new tab.
+    fn drop_dragged(
+        &mut self,
+        compositor: &mut IOCompositor,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        point: DevicePoint,
+    ) -> Option<WebViewId> {
+        let drag = compositor.drag.release()?;
+        let panel_id = self.panel.as_ref().map(|panel| panel.webview.webview_id);
+        match compositor.webview_id_from_point(point) {
+            Some(target) if Some(target) == panel_id => {
+                if let Some(url) = drag.data.url() {
+                    self.create_tab(sender, ServoUrl::from_url(url.clone()));
+                }
+            }
+            Some(target) => self.dispatch_drag_event(sender, target, "drop", &drag.data, point),
+            None => {}
+        }
+        compositor.send_root_pipeline_display_list(self);
+        Some(drag.source)
+    }
+
+    /// Dispatch a drag event to a webview at `point`, without waiting for the page. The panel gets none.
+    fn dispatch_drag_event(
+        &self,
+        sender: &Sender<EmbedderToConstellationMessage>,
+        webview_id: WebViewId,
+        kind: &str,
+        data: &DragData,
+        point: DevicePoint,
+    ) {
+        let Some(webview) = self.tab_manager.tab(webview_id).map(|tab| tab.webview()) else {
+            return;
+        };
+        let client = (point - webview.rect.min) / self.scale_factor() as f32;
+        execute_script_async(
+            sender,
+            &webview_id,
+            drag_event_script(kind, data, (client.x, client.y)),
+        );
+    }
+
+    /// When mouse input held back by the input governor needs to be flushed.
+    pub(crate) fn pending_input_deadline(&self) -> Option<Instant> {
+        self.input_governor.next_deadline()
+    }
+
+    /// Take the full resolution pointer samples recorded since the last call.
+    ///
+    /// Mouse moves forwarded to web content are resampled to the frame rate, use this
+    /// to get every position the OS reported.
+    pub fn take_raw_pointer_samples(&mut self) -> Vec<RawPointerSample> {
+        self.input_governor.take_raw_samples()
+    }
+
+    /// Send the pointer samples recorded since the last call to the controller, if it listens.
+    pub(crate) fn report_raw_pointer_samples(
+        &mut self,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
+    ) {
+        let Some(to_controller_sender) = to_controller_sender else {
+            return;
+        };
+        if !self.event_listeners.on_raw_pointer_samples {
+            return;
+        }
+        let samples = self.take_raw_pointer_samples();
+        if samples.is_empty() {
+            return;
+        }
+        if let Err(error) =
+            to_controller_sender.send(ToControllerMessage::OnRawPointerSamples(samples))
+        {
+            log::error!("Verso failed to send OnRawPointerSamples to controller: {error}");
+        }
+    }
+
+    /// Handle Window keyboard shortcut
+    ///
+    /// - Returns `true` if the event is handled, then we should skip sending it to constellation
+    fn handle_keyboard_shortcut(
+        &mut self,
+        compositor: &mut IOCompositor,
+        event: &KeyboardEvent,
+    ) -> bool {
+        if event.state != KeyState::Down {
+            return false;
+        }
+        match self.gestures.key_chord(event.modifiers, event.code) {
+            Some(command) => {
+                self.run_gesture_command(compositor, command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run the command bound to a gesture or key chord
+    pub(crate) fn run_gesture_command(
+        &mut self,
+        compositor: &mut IOCompositor,
+        command: GestureCommand,
+    ) {
+        log::debug!("Verso runs gesture command {command:?}");
+        let sender = compositor.constellation_chan.clone();
+        let current_tab_id = self.tab_manager.current_tab_id();
+        match command {
+            GestureCommand::Back | GestureCommand::Forward => {
+                let direction = if command == GestureCommand::Back {
+                    TraversalDirection::Back(1)
+                } else {
+                    TraversalDirection::Forward(1)
+                };
+                if let Some(webview_id) = current_tab_id {
+                    send_to_constellation(
+                        &sender,
+                        EmbedderToConstellationMessage::TraverseHistory(webview_id, direction),
+                    );
+                }
+            }
+            GestureCommand::Reload => {
+                if let Some(webview_id) = current_tab_id {
+                    send_to_constellation(
+                        &sender,
+                        EmbedderToConstellationMessage::Reload(webview_id),
+                    );
+                }
+            }
+            GestureCommand::Navigate(url) => {
+                if let Some(webview_id) = current_tab_id {
+                    send_to_constellation(
+                        &sender,
+                        EmbedderToConstellationMessage::LoadUrl(
+                            webview_id,
+                            ServoUrl::from_url(url),
+                        ),
+                    );
+                }
+            }
+            GestureCommand::Zoom | GestureCommand::ZoomIn => {
+                compositor.on_zoom_window_event(GESTURE_ZOOM_STEP, self);
+            }
+            GestureCommand::ZoomOut => {
+                compositor.on_zoom_window_event(1.0 / GESTURE_ZOOM_STEP, self);
+            }
+            GestureCommand::ResetZoom => {
+                if let Some(webview_id) = self.tab_manager.current_tab_id() {
+                    compositor.reset_pinch_zoom(webview_id);
+                }
+                compositor.on_zoom_reset_window_event(self);
+            }
+            GestureCommand::NewTab => {
+                self.create_tab(&sender, ServoUrl::parse("https://example.com").unwrap());
+            }
+            GestureCommand::CloseTab => {
+                if let Some(tab_id) = current_tab_id {
+                    self.close_tab(compositor, tab_id);
+                }
+            }
+            GestureCommand::FocusUrlBar => {
+                // focus on navigation input
+                if let Some(panel) = &self.panel {
+                    let webview_id = &panel.webview.webview_id;
+
+                    let _ = sender.send(EmbedderToConstellationMessage::FocusWebView(
+                        webview_id.clone(),
+                    ));
+
+                    let _ = execute_script(
+                        &sender,
+                        webview_id,
+                        "window.navbar.focusUrlInput()".to_string(),
+                    );
+                }
+            }
+            GestureCommand::ToggleHud => compositor.toggle_hud(self),
+            GestureCommand::ToggleStatsHud => compositor.toggle_stats_hud(self),
+            GestureCommand::ToggleTouchEmulation => {
+                compositor.set_mouse_to_touch(!compositor.mouse_to_touch())
+            }
+            GestureCommand::CaptureWebRenderFrame => {
+                compositor.capture_webrender_frame(None);
+            }
+            GestureCommand::Custom(name) => self.gestures.queue_custom(name),
+            GestureCommand::Nothing => {}
+        }
+    }
+
+    /// Run the command of a finger held long enough, and send the controller the custom commands run.
+    pub(crate) fn drive_gestures(
+        &mut self,
+        compositor: &mut IOCompositor,
+        to_controller_sender: &Option<IpcSender<ToControllerMessage>>,
+    ) {
+        if let Some(command) = self.gestures.poll(Instant::now()) {
+            self.run_gesture_command(compositor, command);
+        }
+        let commands = self.gestures.take_custom();
+        let Some(to_controller_sender) = to_controller_sender else {
+            return;
+        };
+        for command in commands {
+            if let Err(error) =
+                to_controller_sender.send(ToControllerMessage::OnGestureCommand(command))
+            {
+                log::error!("Verso failed to send a gesture command to the controller: {error}");
+            }
+        }
     }
 
     /// Handle servo messages. Return true if it requests a new window
@@ -751,12 +1657,8 @@ impl Window {
         clipboard: Option<&mut Clipboard>,
         compositor: &mut IOCompositor,
         bookmark_manager: &mut BookmarkManager,
+        site_settings: &SiteSettings,
     ) -> bool {
-        if let EmbedderMsg::SetCursor(_, cursor) = message {
-            self.set_cursor_icon(cursor);
-            return false;
-        }
-
         // Handle message in Verso Panel
         if let Some(panel) = &self.panel {
             if panel.webview.webview_id == webview_id {
@@ -793,6 +1695,7 @@ impl Window {
             to_controller_sender,
             clipboard,
             compositor,
+            site_settings,
         );
         false
     }
@@ -888,7 +1791,23 @@ impl Window {
             }
             (self.panel.take().map(|panel| panel.webview), false)
         } else if let Ok(tab) = self.tab_manager.close_tab(id) {
-            let close_window = self.tab_manager.count() == 0 || self.panel.is_none();
+            self.content_settings.remove(id);
+            self.launch_params.remove(id);
+            self.tiles.remove(id);
+            self.network_contexts.remove(id);
+            self.request_scheduler.remove(id);
+            self.audit_log.remove(id);
+            self.coverage.remove(&id);
+            // A webview closed to be recreated leaves its replacement in the window.
+            let replaced = self.recovery.take_replaced(id);
+            self.recovery.remove(id);
+            // So does a page the playlist is done with.
+            let retired = self
+                .playlist
+                .as_mut()
+                .is_some_and(|playlist| playlist.take_retired(id));
+            let close_window =
+                !replaced && !retired && (self.tab_manager.count() == 0 || self.panel.is_none());
             if self.focused_webview_id == Some(id) {
                 self.focused_webview_id = None;
             }
@@ -905,6 +1824,29 @@ impl Window {
             order.push(&panel.webview);
         }
 
+        // A playlist page fading out is painted under the one fading in.
+        if let Some(tab) = self
+            .playlist
+            .as_ref()
+            .and_then(PlaylistPlayer::fading_out)
+            .and_then(|webview_id| self.tab_manager.tab(webview_id))
+        {
+            order.push(tab.webview());
+        }
+
+        let current_tab_id = self.tab_manager.current_tab_id();
+        for tab in self
+            .tiles
+            .webview_ids()
+            .filter(|webview_id| Some(*webview_id) != current_tab_id)
+            .filter_map(|webview_id| self.tab_manager.tab(webview_id))
+        {
+            order.push(tab.webview());
+            if let Some(prompt) = tab.prompt() {
+                order.push(prompt.webview());
+            }
+        }
+
         if let Some(tab) = self.tab_manager.current_tab() {
             order.push(tab.webview());
         }
@@ -920,50 +1862,14 @@ impl Window {
         order
     }
 
-    /// Set cursor icon of the window.
-    pub fn set_cursor_icon(&mut self, cursor: Cursor) {
-        let winit_cursor = match cursor {
-            Cursor::Default => CursorIcon::Default,
-            Cursor::Pointer => CursorIcon::Pointer,
-            Cursor::ContextMenu => CursorIcon::ContextMenu,
-            Cursor::Help => CursorIcon::Help,
-            Cursor::Progress => CursorIcon::Progress,
-            Cursor::Wait => CursorIcon::Wait,
-            Cursor::Cell => CursorIcon::Cell,
-            Cursor::Crosshair => CursorIcon::Crosshair,
-            Cursor::Text => CursorIcon::Text,
-            Cursor::VerticalText => CursorIcon::VerticalText,
-            Cursor::Alias => CursorIcon::Alias,
-            Cursor::Copy => CursorIcon::Copy,
-            Cursor::Move => CursorIcon::Move,
-            Cursor::NoDrop => CursorIcon::NoDrop,
-            Cursor::NotAllowed => CursorIcon::NotAllowed,
-            Cursor::Grab => CursorIcon::Grab,
-            Cursor::Grabbing => CursorIcon::Grabbing,
-            Cursor::EResize => CursorIcon::EResize,
-            Cursor::NResize => CursorIcon::NResize,
-            Cursor::NeResize => CursorIcon::NeResize,
-            Cursor::NwResize => CursorIcon::NwResize,
-            Cursor::SResize => CursorIcon::SResize,
-            Cursor::SeResize => CursorIcon::SeResize,
-            Cursor::SwResize => CursorIcon::SwResize,
-            Cursor::WResize => CursorIcon::WResize,
-            Cursor::EwResize => CursorIcon::EwResize,
-            Cursor::NsResize => CursorIcon::NsResize,
-            Cursor::NeswResize => CursorIcon::NeswResize,
-            Cursor::NwseResize => CursorIcon::NwseResize,
-            Cursor::ColResize => CursorIcon::ColResize,
-            Cursor::RowResize => CursorIcon::RowResize,
-            Cursor::AllScroll => CursorIcon::AllScroll,
-            Cursor::ZoomIn => CursorIcon::ZoomIn,
-            Cursor::ZoomOut => CursorIcon::ZoomOut,
-            Cursor::None => {
-                self.window.set_cursor_visible(false);
-                return;
-            }
+    /// Set cursor of the window, `None` hides it.
+    pub fn set_cursor(&mut self, cursor: Option<WindowCursor>) {
+        let Some(cursor) = cursor else {
+            self.window.set_cursor_visible(false);
+            return;
         };
-        self.cursor_state.current_cursor = winit_cursor;
-        self.window.set_cursor(winit_cursor);
+        self.cursor_state.current_cursor = cursor.clone();
+        self.window.set_cursor(cursor);
         self.window.set_cursor_visible(true);
     }
 
@@ -1150,7 +2056,8 @@ impl Window {
             self.window.set_cursor(cursor);
         } else if self.cursor_state.cursor_resizing {
             self.cursor_state.cursor_resizing = false;
-            self.window.set_cursor(self.cursor_state.current_cursor);
+            self.window
+                .set_cursor(self.cursor_state.current_cursor.clone());
         }
     }
 }
@@ -1183,6 +2090,47 @@ pub unsafe fn decorate_window(view: *mut AnyObject, _position: LogicalPosition<f
     );
 }
 
+/// The winit cursor of a page's cursor, `None` if the page hides it.
+pub(crate) fn cursor_icon(cursor: Cursor) -> Option<CursorIcon> {
+    match cursor {
+        Cursor::Default => Some(CursorIcon::Default),
+        Cursor::Pointer => Some(CursorIcon::Pointer),
+        Cursor::ContextMenu => Some(CursorIcon::ContextMenu),
+        Cursor::Help => Some(CursorIcon::Help),
+        Cursor::Progress => Some(CursorIcon::Progress),
+        Cursor::Wait => Some(CursorIcon::Wait),
+        Cursor::Cell => Some(CursorIcon::Cell),
+        Cursor::Crosshair => Some(CursorIcon::Crosshair),
+        Cursor::Text => Some(CursorIcon::Text),
+        Cursor::VerticalText => Some(CursorIcon::VerticalText),
+        Cursor::Alias => Some(CursorIcon::Alias),
+        Cursor::Copy => Some(CursorIcon::Copy),
+        Cursor::Move => Some(CursorIcon::Move),
+        Cursor::NoDrop => Some(CursorIcon::NoDrop),
+        Cursor::NotAllowed => Some(CursorIcon::NotAllowed),
+        Cursor::Grab => Some(CursorIcon::Grab),
+        Cursor::Grabbing => Some(CursorIcon::Grabbing),
+        Cursor::EResize => Some(CursorIcon::EResize),
+        Cursor::NResize => Some(CursorIcon::NResize),
+        Cursor::NeResize => Some(CursorIcon::NeResize),
+        Cursor::NwResize => Some(CursorIcon::NwResize),
+        Cursor::SResize => Some(CursorIcon::SResize),
+        Cursor::SeResize => Some(CursorIcon::SeResize),
+        Cursor::SwResize => Some(CursorIcon::SwResize),
+        Cursor::WResize => Some(CursorIcon::WResize),
+        Cursor::EwResize => Some(CursorIcon::EwResize),
+        Cursor::NsResize => Some(CursorIcon::NsResize),
+        Cursor::NeswResize => Some(CursorIcon::NeswResize),
+        Cursor::NwseResize => Some(CursorIcon::NwseResize),
+        Cursor::ColResize => Some(CursorIcon::ColResize),
+        Cursor::RowResize => Some(CursorIcon::RowResize),
+        Cursor::AllScroll => Some(CursorIcon::AllScroll),
+        Cursor::ZoomIn => Some(CursorIcon::ZoomIn),
+        Cursor::ZoomOut => Some(CursorIcon::ZoomOut),
+        Cursor::None => None,
+    }
+}
+
 /// Forward input event to compositor or constellation.
 fn forward_input_event(
     compositor: &mut IOCompositor,
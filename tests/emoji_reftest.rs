@@ -0,0 +1,84 @@
+//! Emoji fallback reftest, driving the versoview binary built with it through the controller.
+//!
+//! It opens windows so it is ignored by default, run it on a machine with a display with
+//! `cargo test --test emoji_reftest -- --ignored`.
+
+use std::path::{Path, PathBuf};
+
+use dpi::PhysicalSize;
+use url::Url;
+use verso::{
+    EmojiSettings, Screenshot, ScreenshotFormat, ScreenshotOptions, StabilityOptions, VersoBuilder,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Show `url` in a new versoview and capture it once the page settled
+fn render(url: Url, emoji: EmojiSettings) -> Screenshot {
+    let controller = VersoBuilder::new()
+        .with_panel(false)
+        .inner_size(PhysicalSize::new(400, 200))
+        .resources_directory(Path::new(env!("CARGO_MANIFEST_DIR")).join("resources"))
+        .emoji(emoji)
+        .build(env!("CARGO_BIN_EXE_versoview"), url);
+    controller
+        .wait_until_stable(StabilityOptions::default())
+        .expect("versoview didn't answer")
+        .expect("the page didn't settle");
+    let screenshot = controller
+        .capture_screenshot(ScreenshotOptions {
+            full_page: false,
+            format: ScreenshotFormat::Rgba,
+        })
+        .expect("versoview exited")
+        .expect("the page wasn't captured");
+    let _ = controller.exit();
+    screenshot
+}
+
+/// Emoji missing from the page's font fall back to the configured emoji font, see
+/// `tests/fixtures/emoji_fallback.html`
+#[test]
+#[ignore = "opens windows, and needs a color emoji font in VERSO_TEST_EMOJI_FONT"]
+fn emoji_fallback_reftest() {
+    let font = PathBuf::from(
+        std::env::var_os("VERSO_TEST_EMOJI_FONT").expect("VERSO_TEST_EMOJI_FONT isn't set"),
+    );
+    let emoji = EmojiSettings {
+        font: Some(font.clone()),
+        ..Default::default()
+    };
+
+    // The reference loads the font directly.
+    let reference = std::fs::read_to_string(fixture("emoji_fallback-ref.html"))
+        .unwrap()
+        .replace(
+            "EMOJI_FONT_URL",
+            Url::from_file_path(&font).unwrap().as_str(),
+        );
+    let reference_path = std::env::temp_dir().join(format!(
+        "verso-emoji-fallback-ref-{}.html",
+        std::process::id()
+    ));
+    std::fs::write(&reference_path, reference).unwrap();
+
+    let test = render(
+        Url::from_file_path(fixture("emoji_fallback.html")).unwrap(),
+        emoji.clone(),
+    );
+    let reference = render(Url::from_file_path(&reference_path).unwrap(), emoji);
+    let _ = std::fs::remove_file(&reference_path);
+
+    assert_eq!(
+        (test.width, test.height),
+        (reference.width, reference.height)
+    );
+    assert!(
+        test.data == reference.data,
+        "emoji_fallback.html doesn't match its reference"
+    );
+}
@@ -1,6 +1,12 @@
 use dpi::{Position, Size};
 use std::path::{Path, PathBuf};
-use versoview_messages::{ConfigFromController, ProfilerSettings, UserScript};
+use versoview_messages::{
+    AddressInputSettings, ColorManagement, ConfigFromController, ContentSettings, CursorTheme,
+    EmojiSettings, FlightRecorderSettings, GestureBindings, GlContextSettings, Http3Settings,
+    NetworkContext, Playlist, PresentMode, ProfilerSettings, RecoveryPolicy,
+    RenderValidationSettings, SafeModeSettings, ScriptPolicy, SessionManifest,
+    SharedTextureSettings, SmoothScrolling, StabilityOptions, TextRendering, UserScript,
+};
 
 use crate::VersoviewController;
 
@@ -63,6 +69,11 @@ impl VersoBuilder {
     }
 
     /// Sets whether the window will be initially transparent or not.
+    ///
+    /// A transparent window is blended by the OS compositor with per-pixel alpha, so
+    /// the parts of the pages without a background show what's behind it. Displays
+    /// without an alpha visual fall back to an opaque window, see
+    /// [`GlContextInfo::transparent`](crate::GlContextInfo::transparent).
     pub fn transparent(mut self, transparent: bool) -> Self {
         self.0.transparent = transparent;
         self
@@ -122,12 +133,192 @@ impl VersoBuilder {
         self
     }
 
+    /// Sets the smallest and largest pinch zoom of a webview, see [`VersoviewController::reset_pinch_zoom`].
+    pub fn pinch_zoom_limits(mut self, min: f32, max: f32) -> Self {
+        self.0.min_pinch_zoom = min;
+        self.0.max_pinch_zoom = max;
+        self
+    }
+
     /// Sets the resource directory path.
     pub fn resources_directory(mut self, path: impl Into<PathBuf>) -> Self {
         self.0.resources_directory = Some(path.into());
         self
     }
 
+    /// Overrides a WebRender renderer option, e.g. `("batch_lookback_count", "10")`.
+    pub fn renderer_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.renderer_options.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets how frames are presented, e.g. [`PresentMode::Immediate`] for the lowest latency.
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.0.present_mode = present_mode;
+        self
+    }
+
+    /// Sets whether scripts provided by pages run.
+    pub fn javascript_enabled(mut self, enabled: bool) -> Self {
+        self.0.content_settings.javascript_enabled = enabled;
+        self
+    }
+
+    /// Sets whether styles provided by pages are applied.
+    pub fn author_styles_enabled(mut self, enabled: bool) -> Self {
+        self.0.content_settings.author_styles_enabled = enabled;
+        self
+    }
+
+    /// Sets both content switches at once, see [`ContentSettings`].
+    pub fn content_settings(mut self, settings: ContentSettings) -> Self {
+        self.0.content_settings = settings;
+        self
+    }
+
+    /// Converts images with an embedded color profile to sRGB, or to the display's
+    /// ICC profile at `output_profile`, see [`ColorManagement`].
+    pub fn color_management(mut self, output_profile: Option<PathBuf>) -> Self {
+        self.0.color_management = ColorManagement {
+            enabled: true,
+            output_profile,
+        };
+        self
+    }
+
+    /// Sets how text is anti-aliased and hinted, see [`TextRendering`].
+    pub fn text_rendering(mut self, text_rendering: TextRendering) -> Self {
+        self.0.text_rendering = text_rendering;
+        self
+    }
+
+    /// Sets the emoji font and whether emoji render in color, see [`EmojiSettings`].
+    pub fn emoji(mut self, emoji: EmojiSettings) -> Self {
+        self.0.emoji = emoji;
+        self
+    }
+
+    /// Sets the script engine limits of the profile, see [`ScriptPolicy`].
+    pub fn script_policy(mut self, script_policy: ScriptPolicy) -> Self {
+        self.0.script_policy = script_policy;
+        self
+    }
+
+    /// Sets whether the requests Verso makes itself use HTTP/3, see [`Http3Settings`].
+    pub fn http3(mut self, http3: Http3Settings) -> Self {
+        self.0.http3 = http3;
+        self
+    }
+
+    /// Adds a network context webviews can be routed through, see [`NetworkContext`].
+    pub fn network_context(mut self, name: impl Into<String>, context: NetworkContext) -> Self {
+        self.0.network_contexts.insert(name.into(), context);
+        self
+    }
+
+    /// Sets the network context new webviews are routed through.
+    pub fn default_network_context(mut self, name: impl Into<String>) -> Self {
+        self.0.default_network_context = Some(name.into());
+        self
+    }
+
+    /// Sets whether page requests are ordered by how much rendering waits on them.
+    pub fn prioritize_requests(mut self, prioritize_requests: bool) -> Self {
+        self.0.prioritize_requests = prioritize_requests;
+        self
+    }
+
+    /// Sets how crashed and hung webviews are recovered, see [`VersoviewController::on_recovery`].
+    pub fn recovery(mut self, recovery: RecoveryPolicy) -> Self {
+        self.0.recovery = recovery;
+        self
+    }
+
+    /// Sets what the flight recorder keeps and when it dumps, see [`VersoviewController::dump_flight_recorder`].
+    pub fn flight_recorder(mut self, flight_recorder: FlightRecorderSettings) -> Self {
+        self.0.flight_recorder = flight_recorder;
+        self
+    }
+
+    /// Sets when frames are compared with the software renderer, see [`VersoviewController::validate_rendering`].
+    pub fn render_validation(mut self, render_validation: RenderValidationSettings) -> Self {
+        self.0.render_validation = render_validation;
+        self
+    }
+
+    /// Sets the commands bound to gestures and key chords, see [`VersoviewController::on_gesture_command`].
+    pub fn gestures(mut self, gestures: GestureBindings) -> Self {
+        self.0.gestures = gestures;
+        self
+    }
+
+    /// Sets how address bar input is turned into a URL, see [`VersoviewController::interpret_address_input`].
+    pub fn address_input(mut self, address_input: AddressInputSettings) -> Self {
+        self.0.address_input = address_input;
+        self
+    }
+
+    /// Sets the cursors shown in place of the ones pages ask for, by CSS cursor name, see [`VersoviewController::set_cursor_theme`].
+    pub fn cursor_theme(mut self, cursor_theme: CursorTheme) -> Self {
+        self.0.cursor_theme = cursor_theme;
+        self
+    }
+
+    /// Sets how mouse wheel and keyboard scrolls are animated, on by default.
+    pub fn smooth_scrolling(mut self, smooth_scrolling: SmoothScrolling) -> Self {
+        self.0.smooth_scrolling = smooth_scrolling;
+        self
+    }
+
+    /// Sets when the pages tabs load count as settled, see [`VersoviewController::on_page_settled`].
+    pub fn page_settled(mut self, options: StabilityOptions) -> Self {
+        self.0.page_settled = Some(options);
+        self
+    }
+
+    /// Sets whether links, images and selected text can be dragged out of pages, on by default.
+    pub fn drag_and_drop(mut self, drag_and_drop: bool) -> Self {
+        self.0.drag_and_drop = drag_and_drop;
+        self
+    }
+
+    /// Sets the first window's frames to be composited into textures shared with the controller
+    /// instead of presented, see [`VersoviewController::on_shared_texture_frame`].
+    pub fn shared_texture(mut self, settings: SharedTextureSettings) -> Self {
+        self.0.shared_texture = Some(settings);
+        self
+    }
+
+    /// Sets the requirements on the GL context and the color depth of the window surface, see [`VersoviewController::get_gl_context_info`].
+    pub fn gl_context(mut self, gl_context: GlContextSettings) -> Self {
+        self.0.gl_context = gl_context;
+        self
+    }
+
+    /// Sets when versoview starts in safe mode, see [`VersoviewController::on_safe_mode`].
+    pub fn safe_mode(mut self, safe_mode: SafeModeSettings) -> Self {
+        self.0.safe_mode = safe_mode;
+        self
+    }
+
+    /// Sets how long an event loop iteration may take before it's logged as a stall, `None` to never log, see [`VersoviewController::get_event_loop_stats`].
+    pub fn stall_threshold_ms(mut self, stall_threshold_ms: Option<u64>) -> Self {
+        self.0.stall_threshold_ms = stall_threshold_ms;
+        self
+    }
+
+    /// Sets a playlist of pages for the window to rotate through, see [`VersoviewController::control_playlist`].
+    pub fn playlist(mut self, playlist: Playlist) -> Self {
+        self.0.playlist = Some(playlist);
+        self
+    }
+
+    /// Sets the windows and webviews to open at startup, instead of the initial window.
+    pub fn session(mut self, session: SessionManifest) -> Self {
+        self.0.session = Some(session);
+        self
+    }
+
     /// Builds the [`VersoviewController`] with the configured settings.
     pub fn build(
         self,
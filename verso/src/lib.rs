@@ -9,12 +9,32 @@ use ipc_channel::{
 use log::error;
 use std::{
     collections::HashMap,
-    path::Path,
+    path::{Path, PathBuf},
     process::Command,
     sync::{Arc, Mutex, mpsc::Sender as MpscSender},
+    time::Duration,
 };
 pub use versoview_messages::{
-    ConfigFromController as VersoviewSettings, Icon, ProfilerSettings, UserScript,
+    AddressInputKind, AddressInputSettings, AddressRewrite, AddressTarget, AuditCategory,
+    AuditEntry, ClockOverride, ColorManagement, ConfigFromController as VersoviewSettings,
+    ConnectionInfo, ConnectionKind, ConnectionState, ContentSettings, CoverageKind, CoverageReport,
+    CursorReplacement, CursorTheme, DailySchedule, DeviceLostCause, DeviceLostEvent,
+    DivergentRegion, DmaBufPlane, EmojiSettings, EventLoopStall, EventLoopStats, EventQueue,
+    FlightRecorderSettings, FramePresented, Gesture, GestureBinding, GestureBindings,
+    GestureCommand, GlApi, GlContextInfo, GlContextSettings, GlPlatform, GlProfile,
+    GlResetStrategy, HitTestResult, Http3Settings, Icon, JitPolicy, LaunchParams, LcdFilter,
+    MessageTypeStats, MixedContentPolicy, NetworkContext, OutputColorDepth, PageSettled,
+    PaintMetric, PaintMetricKind, Playlist, PlaylistCommand, PlaylistItem, PlaylistStatus,
+    PlaylistTransition, PresentMode, PresentedWebView, ProfilerSettings, QueueDepthStats,
+    RECOVERY_RESTART_EXIT_CODE, RawPointerSample, RecordingFormat, RecordingReport,
+    RecordingSettings, RecoveryAction, RecoveryEvent, RecoveryPolicy, RecoveryRule,
+    RecoveryTrigger, RenderValidationReport, RenderValidationSettings, ResourceCoverage,
+    RotationDirection, SafeModeReport, SafeModeSettings, SafeModeSubsystem, Screenshot,
+    ScreenshotFormat, ScreenshotOptions, ScriptPolicy, ScrollEasing, SecurityPolicyReport,
+    SecurityPolicyViolation, SessionManifest, SessionWebView, SessionWindow, SharedTextureFrame,
+    SharedTextureHandle, SharedTextureSettings, ShutdownPhase, ShutdownProgress, SitePolicy,
+    SmoothScrolling, StabilityOptions, StabilityReport, SvgRasterRequest, SwipeDirection, TabRect,
+    TextHinting, TextRendering, UserScript, WebRenderDebugOption, WindowEdge,
 };
 use versoview_messages::{
     PositionType, SizeType, ToControllerMessage, ToVersoMessage, WebResourceRequestResponse,
@@ -24,12 +44,26 @@ type ResponseFunction = Box<dyn FnOnce(Option<http::Response<Vec<u8>>>) + Send>;
 type Listener<T> = Arc<Mutex<Option<T>>>;
 type ResponseListener<T> = Arc<Mutex<HashMap<uuid::Uuid, T>>>;
 
+/// Time versoview gets past [`StabilityOptions::timeout_ms`] to answer a stability wait
+const STABILITY_ANSWER_GRACE: Duration = Duration::from_secs(5);
+
 #[derive(Default)]
 struct EventListeners {
     on_close_requested: Listener<Box<dyn Fn() + Send + 'static>>,
     on_navigation_starting: Listener<Box<dyn Fn(url::Url) -> bool + Send + 'static>>,
     on_web_resource_requested:
         Listener<Box<dyn Fn(http::Request<Vec<u8>>, ResponseFunction) + Send + 'static>>,
+    on_security_policy_report: Listener<Box<dyn Fn(SecurityPolicyReport) + Send + 'static>>,
+    on_shutdown_progress: Listener<Box<dyn Fn(ShutdownProgress) + Send + 'static>>,
+    on_recovery: Listener<Box<dyn Fn(RecoveryEvent) + Send + 'static>>,
+    on_safe_mode: Listener<Box<dyn Fn(SafeModeReport) + Send + 'static>>,
+    on_gesture_command: Listener<Box<dyn Fn(String) + Send + 'static>>,
+    on_device_lost: Listener<Box<dyn Fn(DeviceLostEvent) + Send + 'static>>,
+    on_frame_presented: Listener<Box<dyn Fn(FramePresented) + Send + 'static>>,
+    on_raw_pointer_samples: Listener<Box<dyn Fn(Vec<RawPointerSample>) + Send + 'static>>,
+    on_page_settled: Listener<Box<dyn Fn(PageSettled) + Send + 'static>>,
+    on_paint_metric: Listener<Box<dyn Fn(PaintMetric) + Send + 'static>>,
+    on_shared_texture_frame: Listener<Box<dyn Fn(SharedTextureFrame) + Send + 'static>>,
     size_response: ResponseListener<MpscSender<PhysicalSize<u32>>>,
     position_response: ResponseListener<MpscSender<Option<PhysicalPosition<i32>>>>,
     maximized_response: ResponseListener<MpscSender<bool>>,
@@ -38,6 +72,24 @@ struct EventListeners {
     visible_response: ResponseListener<MpscSender<bool>>,
     scale_factor_response: ResponseListener<MpscSender<f64>>,
     get_url_response: ResponseListener<MpscSender<url::Url>>,
+    rasterize_svg_response: ResponseListener<MpscSender<Result<Vec<u8>, String>>>,
+    script_policy_response: ResponseListener<MpscSender<ScriptPolicy>>,
+    connections_response: ResponseListener<MpscSender<Vec<ConnectionInfo>>>,
+    audit_log_response: ResponseListener<MpscSender<Vec<AuditEntry>>>,
+    export_session_response: ResponseListener<MpscSender<SessionManifest>>,
+    dump_flight_recorder_response: ResponseListener<MpscSender<Result<PathBuf, String>>>,
+    gl_context_info_response: ResponseListener<MpscSender<Option<GlContextInfo>>>,
+    safe_mode_report_response: ResponseListener<MpscSender<Option<SafeModeReport>>>,
+    event_loop_stats_response: ResponseListener<MpscSender<Option<EventLoopStats>>>,
+    playlist_status_response: ResponseListener<MpscSender<Option<PlaylistStatus>>>,
+    coverage_response: ResponseListener<MpscSender<Option<CoverageReport>>>,
+    render_validation_response:
+        ResponseListener<MpscSender<Result<RenderValidationReport, String>>>,
+    stability_response: ResponseListener<MpscSender<Result<StabilityReport, String>>>,
+    address_input_response: ResponseListener<MpscSender<Option<AddressTarget>>>,
+    screenshot_response: ResponseListener<MpscSender<Result<Screenshot, String>>>,
+    recording_response: ResponseListener<MpscSender<Result<RecordingReport, String>>>,
+    hit_test_response: ResponseListener<MpscSender<Option<HitTestResult>>>,
 }
 
 /// A VersoView controller
@@ -78,6 +130,17 @@ impl VersoviewController {
         let on_close_requested = event_listeners.on_close_requested.clone();
         let on_navigation_starting = event_listeners.on_navigation_starting.clone();
         let on_web_resource_requested = event_listeners.on_web_resource_requested.clone();
+        let on_security_policy_report = event_listeners.on_security_policy_report.clone();
+        let on_shutdown_progress = event_listeners.on_shutdown_progress.clone();
+        let on_recovery = event_listeners.on_recovery.clone();
+        let on_safe_mode = event_listeners.on_safe_mode.clone();
+        let on_gesture_command = event_listeners.on_gesture_command.clone();
+        let on_device_lost = event_listeners.on_device_lost.clone();
+        let on_frame_presented = event_listeners.on_frame_presented.clone();
+        let on_raw_pointer_samples = event_listeners.on_raw_pointer_samples.clone();
+        let on_page_settled = event_listeners.on_page_settled.clone();
+        let on_paint_metric = event_listeners.on_paint_metric.clone();
+        let on_shared_texture_frame = event_listeners.on_shared_texture_frame.clone();
         let size_response = event_listeners.size_response.clone();
         let position_response = event_listeners.position_response.clone();
         let minimized_response = event_listeners.minimized_response.clone();
@@ -86,6 +149,23 @@ impl VersoviewController {
         let visible_response = event_listeners.visible_response.clone();
         let scale_factor_response = event_listeners.scale_factor_response.clone();
         let get_url_response = event_listeners.get_url_response.clone();
+        let rasterize_svg_response = event_listeners.rasterize_svg_response.clone();
+        let script_policy_response = event_listeners.script_policy_response.clone();
+        let connections_response = event_listeners.connections_response.clone();
+        let audit_log_response = event_listeners.audit_log_response.clone();
+        let export_session_response = event_listeners.export_session_response.clone();
+        let dump_flight_recorder_response = event_listeners.dump_flight_recorder_response.clone();
+        let gl_context_info_response = event_listeners.gl_context_info_response.clone();
+        let safe_mode_report_response = event_listeners.safe_mode_report_response.clone();
+        let event_loop_stats_response = event_listeners.event_loop_stats_response.clone();
+        let playlist_status_response = event_listeners.playlist_status_response.clone();
+        let coverage_response = event_listeners.coverage_response.clone();
+        let render_validation_response = event_listeners.render_validation_response.clone();
+        let stability_response = event_listeners.stability_response.clone();
+        let address_input_response = event_listeners.address_input_response.clone();
+        let screenshot_response = event_listeners.screenshot_response.clone();
+        let recording_response = event_listeners.recording_response.clone();
+        let hit_test_response = event_listeners.hit_test_response.clone();
         let to_verso_sender = sender.clone();
         ROUTER.add_typed_route(
             receiver,
@@ -123,6 +203,61 @@ impl VersoviewController {
                             );
                         }
                     }
+                    ToControllerMessage::OnSecurityPolicyReport(report) => {
+                        if let Some(ref callback) = *on_security_policy_report.lock().unwrap() {
+                            callback(report);
+                        }
+                    }
+                    ToControllerMessage::OnShutdownProgress(progress) => {
+                        if let Some(ref callback) = *on_shutdown_progress.lock().unwrap() {
+                            callback(progress);
+                        }
+                    }
+                    ToControllerMessage::OnRecovery(event) => {
+                        if let Some(ref callback) = *on_recovery.lock().unwrap() {
+                            callback(event);
+                        }
+                    }
+                    ToControllerMessage::OnSafeMode(report) => {
+                        if let Some(ref callback) = *on_safe_mode.lock().unwrap() {
+                            callback(report);
+                        }
+                    }
+                    ToControllerMessage::OnGestureCommand(command) => {
+                        if let Some(ref callback) = *on_gesture_command.lock().unwrap() {
+                            callback(command);
+                        }
+                    }
+                    ToControllerMessage::OnDeviceLost(event) => {
+                        if let Some(ref callback) = *on_device_lost.lock().unwrap() {
+                            callback(event);
+                        }
+                    }
+                    ToControllerMessage::OnFramePresented(frame) => {
+                        if let Some(ref callback) = *on_frame_presented.lock().unwrap() {
+                            callback(frame);
+                        }
+                    }
+                    ToControllerMessage::OnRawPointerSamples(samples) => {
+                        if let Some(ref callback) = *on_raw_pointer_samples.lock().unwrap() {
+                            callback(samples);
+                        }
+                    }
+                    ToControllerMessage::OnPageSettled(page) => {
+                        if let Some(ref callback) = *on_page_settled.lock().unwrap() {
+                            callback(page);
+                        }
+                    }
+                    ToControllerMessage::OnPaintMetric(metric) => {
+                        if let Some(ref callback) = *on_paint_metric.lock().unwrap() {
+                            callback(metric);
+                        }
+                    }
+                    ToControllerMessage::OnSharedTextureFrame(frame) => {
+                        if let Some(ref callback) = *on_shared_texture_frame.lock().unwrap() {
+                            callback(frame);
+                        }
+                    }
                     ToControllerMessage::GetSizeResponse(id, size) => {
                         if let Some(sender) = size_response.lock().unwrap().get(&id).take() {
                             sender.send(size).unwrap();
@@ -163,6 +298,98 @@ impl VersoviewController {
                             sender.send(url).unwrap();
                         }
                     }
+                    ToControllerMessage::RasterizeSvgResponse(id, png) => {
+                        if let Some(sender) = rasterize_svg_response.lock().unwrap().remove(&id) {
+                            sender.send(png).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetScriptPolicyResponse(id, policy) => {
+                        if let Some(sender) = script_policy_response.lock().unwrap().remove(&id) {
+                            sender.send(policy).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetConnectionsResponse(id, connections) => {
+                        if let Some(sender) = connections_response.lock().unwrap().remove(&id) {
+                            sender.send(connections).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetAuditLogResponse(id, entries) => {
+                        if let Some(sender) = audit_log_response.lock().unwrap().remove(&id) {
+                            sender.send(entries).unwrap();
+                        }
+                    }
+                    ToControllerMessage::ExportSessionResponse(id, session) => {
+                        if let Some(sender) = export_session_response.lock().unwrap().remove(&id) {
+                            sender.send(session).unwrap();
+                        }
+                    }
+                    ToControllerMessage::DumpFlightRecorderResponse(id, dump) => {
+                        if let Some(sender) =
+                            dump_flight_recorder_response.lock().unwrap().remove(&id)
+                        {
+                            sender.send(dump).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetGlContextInfoResponse(id, info) => {
+                        if let Some(sender) = gl_context_info_response.lock().unwrap().remove(&id) {
+                            sender.send(info).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetSafeModeReportResponse(id, report) => {
+                        if let Some(sender) = safe_mode_report_response.lock().unwrap().remove(&id)
+                        {
+                            sender.send(report).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetEventLoopStatsResponse(id, stats) => {
+                        if let Some(sender) = event_loop_stats_response.lock().unwrap().remove(&id)
+                        {
+                            sender.send(stats).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetPlaylistStatusResponse(id, status) => {
+                        if let Some(sender) = playlist_status_response.lock().unwrap().remove(&id)
+                        {
+                            sender.send(status).unwrap();
+                        }
+                    }
+                    ToControllerMessage::GetCoverageResponse(id, report) => {
+                        if let Some(sender) = coverage_response.lock().unwrap().remove(&id) {
+                            sender.send(report).unwrap();
+                        }
+                    }
+                    ToControllerMessage::ValidateRenderingResponse(id, report) => {
+                        if let Some(sender) =
+                            render_validation_response.lock().unwrap().remove(&id)
+                        {
+                            sender.send(report).unwrap();
+                        }
+                    }
+                    ToControllerMessage::WaitUntilStableResponse(id, report) => {
+                        if let Some(sender) = stability_response.lock().unwrap().remove(&id) {
+                            sender.send(report).unwrap();
+                        }
+                    }
+                    ToControllerMessage::InterpretAddressInputResponse(id, target) => {
+                        if let Some(sender) = address_input_response.lock().unwrap().remove(&id) {
+                            sender.send(target).unwrap();
+                        }
+                    }
+                    ToControllerMessage::CaptureScreenshotResponse(id, screenshot) => {
+                        if let Some(sender) = screenshot_response.lock().unwrap().remove(&id) {
+                            sender.send(screenshot).unwrap();
+                        }
+                    }
+                    ToControllerMessage::StopRecordingResponse(id, report) => {
+                        if let Some(sender) = recording_response.lock().unwrap().remove(&id) {
+                            sender.send(report).unwrap();
+                        }
+                    }
+                    ToControllerMessage::HitTestResponse(id, result) => {
+                        if let Some(sender) = hit_test_response.lock().unwrap().remove(&id) {
+                            sender.send(result).unwrap();
+                        }
+                    }
                     _ => {}
                 },
                 Err(e) => error!("Error while receiving VersoMessage: {e}"),
@@ -184,6 +411,49 @@ impl VersoviewController {
         self.sender.send(ToVersoMessage::Exit)
     }
 
+    /// Tell Verso the system is about to sleep, forward this from the OS power notifications
+    pub fn suspend(&self) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::Suspend)
+    }
+
+    /// Tell Verso the system woke up from sleep
+    ///
+    /// Verso also detects sleeps on its own, but not as early as the OS notices them.
+    pub fn resume(&self) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::Resume)
+    }
+
+    /// Change how text is anti-aliased and hinted, takes effect on the next repaint
+    pub fn set_text_rendering(
+        &self,
+        text_rendering: TextRendering,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetTextRendering(text_rendering))
+    }
+
+    /// Set the commands bound to gestures and key chords, replacing the bindings set before
+    pub fn set_gesture_bindings(
+        &self,
+        bindings: GestureBindings,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetGestureBindings(bindings))
+    }
+
+    /// Replace the cursors of every window, see [`VersoBuilder::cursor_theme`]
+    pub fn set_cursor_theme(&self, theme: CursorTheme) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::SetCursorTheme(theme))
+    }
+
+    /// Set how address bar input is turned into a URL, for the panel and [`Self::interpret_address_input`]
+    pub fn set_address_input(
+        &self,
+        settings: AddressInputSettings,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::SetAddressInput(settings))
+    }
+
     /// Listen on close requested from the OS,
     /// if you decide to use it, verso will not close the window by itself anymore,
     /// so make sure you handle it properly by either do your own logic or call [`Self::exit`] as a fallback
@@ -218,6 +488,197 @@ impl VersoviewController {
         self.sender.send(ToVersoMessage::Reload)
     }
 
+    /// Enable or disable JavaScript and author styles of every webview,
+    /// takes effect on the next navigation or reload
+    pub fn set_content_settings(
+        &self,
+        settings: ContentSettings,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetContentSettings(settings))
+    }
+
+    /// Expose launch parameters to the pages of the current webview as `window.verso.params`,
+    /// takes effect on the next navigation or reload
+    ///
+    /// Only `app://` pages and pages of [`LaunchParams::allowed_origins`] can read them.
+    pub fn set_launch_params(
+        &self,
+        params: LaunchParams,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::SetLaunchParams(params))
+    }
+
+    /// Override the security policy of an origin such as `https://example.com`,
+    /// pass `None` to go back to the default policy, overrides are persisted across sessions
+    pub fn set_site_policy(
+        &self,
+        origin: impl Into<String>,
+        policy: Option<SitePolicy>,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetSitePolicy(origin.into(), policy))
+    }
+
+    /// Listen on blocked or upgraded mixed content and failed subresource integrity checks
+    pub fn on_security_policy_report(
+        &self,
+        callback: impl Fn(SecurityPolicyReport) + Send + 'static,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        let old_listener = self
+            .event_listeners
+            .on_security_policy_report
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+        if old_listener.is_none() {
+            self.sender
+                .send(ToVersoMessage::ListenToSecurityPolicyReports)?;
+        }
+        Ok(())
+    }
+
+    /// Listen on every frame a window presents, for keeping native overlays in sync with the pages
+    ///
+    /// The epochs of the webviews tell which of their display lists the frame shows.
+    pub fn on_frame_presented(
+        &self,
+        callback: impl Fn(FramePresented) + Send + 'static,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        let old_listener = self
+            .event_listeners
+            .on_frame_presented
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+        if old_listener.is_none() {
+            self.sender.send(ToVersoMessage::ListenToFramePresented)?;
+        }
+        Ok(())
+    }
+
+    /// Listen on every mouse position the OS reports in the first window, for drawing or
+    /// handwriting at the full rate of the mouse
+    ///
+    /// Pages only get mouse moves resampled to the frame rate. The positions are sent in batches.
+    pub fn on_raw_pointer_samples(
+        &self,
+        callback: impl Fn(Vec<RawPointerSample>) + Send + 'static,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        let old_listener = self
+            .event_listeners
+            .on_raw_pointer_samples
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+        if old_listener.is_none() {
+            self.sender
+                .send(ToVersoMessage::ListenToRawPointerSamples)?;
+        }
+        Ok(())
+    }
+
+    /// Listen on the pages tabs load getting settled, waited on as set by [`VersoBuilder::page_settled`]
+    ///
+    /// Pages are only waited on if the builder set when they count as settled.
+    pub fn on_page_settled(
+        &self,
+        callback: impl Fn(PageSettled) + Send + 'static,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.event_listeners
+            .on_page_settled
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+        Ok(())
+    }
+
+    /// Listen on the progress of Verso shutting down, for example to show a "closing…" UI
+    ///
+    /// Verso tears the renderer down without waiting any longer if the shutdown doesn't reach
+    /// [`ShutdownPhase::Finished`] before [`ShutdownProgress::remaining_ms`] runs out.
+    pub fn on_shutdown_progress(
+        &self,
+        callback: impl Fn(ShutdownProgress) + Send + 'static,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        let old_listener = self
+            .event_listeners
+            .on_shutdown_progress
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+        if old_listener.is_none() {
+            self.sender.send(ToVersoMessage::ListenToShutdownProgress)?;
+        }
+        Ok(())
+    }
+
+    /// Listen on the recovery actions Verso schedules for failing webviews, see [`VersoBuilder::recovery`]
+    ///
+    /// A [`RecoveryAction::RestartBrowser`] makes Verso exit with [`RECOVERY_RESTART_EXIT_CODE`],
+    /// start a new instance to restart it.
+    pub fn on_recovery(&self, callback: impl Fn(RecoveryEvent) + Send + 'static) {
+        self.event_listeners
+            .on_recovery
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// Listen on Verso starting in safe mode after repeated startup crashes, see [`VersoBuilder::safe_mode`]
+    ///
+    /// Sent once the safe mode startup succeeded, for example to tell the user which
+    /// [`SafeModeReport::implicated`] subsystem was turned off.
+    pub fn on_safe_mode(&self, callback: impl Fn(SafeModeReport) + Send + 'static) {
+        self.event_listeners
+            .on_safe_mode
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// Listen on the [`GestureCommand::Custom`] commands of the gestures and key chords the user makes,
+    /// see [`Self::set_gesture_bindings`]
+    pub fn on_gesture_command(&self, callback: impl Fn(String) + Send + 'static) {
+        self.event_listeners
+            .on_gesture_command
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// Listen on the first paint and first contentful paint of the pages webviews show, once the
+    /// frame with them was composited
+    pub fn on_paint_metric(&self, callback: impl Fn(PaintMetric) + Send + 'static) {
+        self.event_listeners
+            .on_paint_metric
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// Listen on the frames composited into shared textures, see [`VersoBuilder::shared_texture`]
+    ///
+    /// The texture of a frame is held until it's given back with [`Self::release_shared_texture`].
+    pub fn on_shared_texture_frame(&self, callback: impl Fn(SharedTextureFrame) + Send + 'static) {
+        self.event_listeners
+            .on_shared_texture_frame
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// Listen on GPU driver resets, after which Verso restarted its renderer
+    ///
+    /// The restart counts as a [`RecoveryTrigger::RendererRestart`] for the recovery policies.
+    pub fn on_device_lost(&self, callback: impl Fn(DeviceLostEvent) + Send + 'static) {
+        self.event_listeners
+            .on_device_lost
+            .lock()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
     /// Listen on navigation starting triggered by user click on a link,
     /// return a boolean in the callback to decide whether or not allowing this navigation
     pub fn on_navigation_starting(
@@ -510,6 +971,555 @@ impl VersoviewController {
         Ok(receiver.recv().unwrap())
     }
 
+    /// Rasterize an SVG to a PNG, e.g. to show an icon at the window's scale factor
+    pub fn rasterize_svg(
+        &self,
+        request: SvgRasterRequest,
+    ) -> Result<Result<Vec<u8>, String>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .rasterize_svg_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::RasterizeSvg(id, request)) {
+            self.event_listeners
+                .rasterize_svg_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Get the script engine policy in effect for pages of `url`, the profile's tightened by the origin's [`SitePolicy`]
+    pub fn get_script_policy(
+        &self,
+        url: url::Url,
+    ) -> Result<ScriptPolicy, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .script_policy_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetScriptPolicy(id, url)) {
+            self.event_listeners
+                .script_policy_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Get the WebSocket and EventSource connections of the webview, including the ones closed since the last call
+    pub fn get_connections(&self) -> Result<Vec<ConnectionInfo>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .connections_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetConnections(id)) {
+            self.event_listeners
+                .connections_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Start or stop collecting the CSS and JavaScript coverage of the webview's pages, stopping forgets it
+    pub fn set_coverage(&self, collecting: bool) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::SetCoverage(collecting))
+    }
+
+    /// Get which style rules of the webview's page were used since [`Self::set_coverage`], and its scripts,
+    /// `None` if the page couldn't report it
+    pub fn get_coverage(&self) -> Result<Option<CoverageReport>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .coverage_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetCoverage(id)) {
+            self.event_listeners
+                .coverage_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Route the webview through a network context added with [`VersoBuilder::network_context`],
+    /// or the default route with `None`
+    pub fn set_network_context(
+        &self,
+        context: Option<String>,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::SetNetworkContext(context))
+    }
+
+    /// Close a WebSocket or EventSource connection of the webview, see [`ConnectionInfo::id`]
+    pub fn close_connection(&self, id: u64) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::CloseConnection(id))
+    }
+
+    /// Get the blocking decisions versoview made for its webviews, oldest first
+    pub fn get_audit_log(&self) -> Result<Vec<AuditEntry>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .audit_log_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetAuditLog(id)) {
+            self.event_listeners
+                .audit_log_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Get the open windows and webviews as a session manifest, to restore with [`VersoBuilder::session`]
+    pub fn export_session(&self) -> Result<SessionManifest, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .export_session_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::ExportSession(id)) {
+            self.event_listeners
+                .export_session_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Get the attributes of the GL context versoview renders with, `None` if there's none
+    pub fn get_gl_context_info(
+        &self,
+    ) -> Result<Option<GlContextInfo>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .gl_context_info_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetGlContextInfo(id)) {
+            self.event_listeners
+                .gl_context_info_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Get the time spent per message type and the stalls of versoview's event loop, see [`VersoBuilder::stall_threshold_ms`]
+    ///
+    /// `None` once versoview's compositor shut down
+    pub fn get_event_loop_stats(
+        &self,
+    ) -> Result<Option<EventLoopStats>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .event_loop_stats_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetEventLoopStats(id)) {
+            self.event_listeners
+                .event_loop_stats_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Get how versoview started in safe mode, `None` if it didn't
+    pub fn get_safe_mode_report(
+        &self,
+    ) -> Result<Option<SafeModeReport>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .safe_mode_report_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetSafeModeReport(id)) {
+            self.event_listeners
+                .safe_mode_report_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Set, skip, rewind, pause or resume the kiosk playlist, see [`VersoBuilder::playlist`]
+    pub fn control_playlist(
+        &self,
+        command: PlaylistCommand,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::ControlPlaylist(command))
+    }
+
+    /// Get what the kiosk playlist shows, `None` if there's no playlist
+    pub fn get_playlist_status(
+        &self,
+    ) -> Result<Option<PlaylistStatus>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .playlist_status_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::GetPlaylistStatus(id)) {
+            self.event_listeners
+                .playlist_status_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Dump the flight recorder, see [`VersoBuilder::flight_recorder`]
+    ///
+    /// Returns the directory the dump is written to, or why there's no dump
+    pub fn dump_flight_recorder(
+        &self,
+    ) -> Result<Result<PathBuf, String>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .dump_flight_recorder_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::DumpFlightRecorder(id)) {
+            self.event_listeners
+                .dump_flight_recorder_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Render the current frame with both the GPU and the software renderer and compare them,
+    /// see [`VersoBuilder::render_validation`]
+    ///
+    /// Returns how the frames differ, or why they couldn't be compared
+    pub fn validate_rendering(
+        &self,
+    ) -> Result<Result<RenderValidationReport, String>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .render_validation_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::ValidateRendering(id)) {
+            self.event_listeners
+                .render_validation_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Block until the page of the current webview is loaded and its rendering settled,
+    /// as defined by `options`, for screenshots and tests
+    ///
+    /// Returns how long it took, or which conditions didn't hold before [`StabilityOptions::timeout_ms`].
+    /// Fails instead of blocking forever if versoview exits or stops answering.
+    pub fn wait_until_stable(
+        &self,
+        options: StabilityOptions,
+    ) -> Result<Result<StabilityReport, String>, Box<ipc_channel::ErrorKind>> {
+        let answer_timeout = Duration::from_millis(options.timeout_ms) + STABILITY_ANSWER_GRACE;
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .stability_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self
+            .sender
+            .send(ToVersoMessage::WaitUntilStable(id, options))
+        {
+            self.event_listeners
+                .stability_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        receiver.recv_timeout(answer_timeout).map_err(|error| {
+            self.event_listeners
+                .stability_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            Box::new(ipc_channel::ErrorKind::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("versoview didn't answer the stability wait: {error}"),
+            )))
+        })
+    }
+
+    /// Turn address bar input into the URL to navigate to, the way the panel does,
+    /// for embedders with their own address bar
+    ///
+    /// Returns `None` if the input is empty
+    pub fn interpret_address_input(
+        &self,
+        input: impl Into<String>,
+    ) -> Result<Option<AddressTarget>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .address_input_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self
+            .sender
+            .send(ToVersoMessage::InterpretAddressInput(id, input.into()))
+        {
+            self.event_listeners
+                .address_input_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Capture the next composited frame of the webview, as a PNG file or RGBA pixels
+    ///
+    /// A full page capture resizes the webview to the height of its page for a frame.
+    /// Returns the capture, or why it couldn't be taken
+    pub fn capture_screenshot(
+        &self,
+        options: ScreenshotOptions,
+    ) -> Result<Result<Screenshot, String>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .screenshot_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self
+            .sender
+            .send(ToVersoMessage::CaptureScreenshot(id, options))
+        {
+            self.event_listeners
+                .screenshot_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Show the webview identified by `webview`, like [`AuditEntry::webview`], at `rect` of its
+    /// window, so several tabs are shown side by side, each scrolling and taking input on its
+    /// own, pass `None` to put it back in the content area
+    ///
+    /// Rects are kept when the window is resized, lay the tabs out again after resizing it.
+    pub fn set_tab_rect(
+        &self,
+        webview: impl Into<String>,
+        rect: Option<TabRect>,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetTabRect(webview.into(), rect))
+    }
+
+    /// Zoom the page of the webview identified by `webview`, like [`AuditEntry::webview`], by
+    /// `factor`, laying it out again at the new size, unlike pinch zoom, pass `1.0` to reset it
+    ///
+    /// The factor is clamped between 0.25 and 5.
+    pub fn set_page_zoom(
+        &self,
+        webview: impl Into<String>,
+        factor: f32,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetPageZoom(webview.into(), factor))
+    }
+
+    /// Animate the pinch zoom of the tab at `index` of the tab order back to 1, see
+    /// [`VersoBuilder::pinch_zoom_limits`]
+    pub fn reset_pinch_zoom(&self, index: usize) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::ResetPinchZoom(index))
+    }
+
+    /// Show or hide the frame rate, frame times, dropped frames and scroll coalescing in the HUD,
+    /// also bindable to a key chord with [`GestureCommand::ToggleStatsHud`]
+    pub fn toggle_stats_hud(&self) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::ToggleStatsHud)
+    }
+
+    /// Turn the translation of mouse input into touch events on or off, to try touch UIs on a
+    /// desktop, also bindable to a key chord with [`GestureCommand::ToggleTouchEmulation`]
+    ///
+    /// Holding the left button is a finger on the screen, and each press is a new touch.
+    pub fn set_mouse_to_touch(&self, enabled: bool) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::SetMouseToTouch(enabled))
+    }
+
+    /// Save WebRender's scene and frame into `directory`, to load in wrench and attach to a
+    /// rendering bug report, or into a new directory of `webrender-captures` in versoview's
+    /// working directory if `None`
+    ///
+    /// Also bindable to a key chord with [`GestureCommand::CaptureWebRenderFrame`].
+    pub fn capture_webrender_frame(
+        &self,
+        directory: Option<PathBuf>,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::CaptureWebRenderFrame(directory))
+    }
+
+    /// Give back the shared texture of a [`SharedTextureFrame`] once done reading it, so Verso
+    /// composites into it again
+    pub fn release_shared_texture(&self, buffer: u32) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::ReleaseSharedTexture(buffer))
+    }
+
+    /// Change a WebRender option while it runs, see [`VersoBuilder::renderer_option`] for the
+    /// options set at startup
+    ///
+    /// Only `picture_tile_size`, `batch_lookback_count`, `multithreading` and `image_tile_size`
+    /// can change at runtime, others are ignored with a warning.
+    pub fn set_renderer_option(
+        &self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::SetRendererOption(name.into(), value.into()))
+    }
+
+    /// Turn a WebRender debug overlay, the profiler, the texture cache or the render targets,
+    /// on or off
+    pub fn toggle_webrender_debug(
+        &self,
+        option: WebRenderDebugOption,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender
+            .send(ToVersoMessage::ToggleWebRenderDebug(option))
+    }
+
+    /// Record the frames Verso composites as a PNG sequence, a raw RGBA file or through an encoder,
+    /// as defined by `settings`, for reproduction videos of rendering bugs
+    ///
+    /// Frames are only recorded when something is composited, a recording in progress is stopped.
+    pub fn start_recording(
+        &self,
+        settings: RecordingSettings,
+    ) -> Result<(), Box<ipc_channel::ErrorKind>> {
+        self.sender.send(ToVersoMessage::StartRecording(settings))
+    }
+
+    /// Stop recording the frames, waiting for the encoder to finish
+    ///
+    /// Returns what was recorded, or why the recording failed
+    pub fn stop_recording(
+        &self,
+    ) -> Result<Result<RecordingReport, String>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .recording_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self.sender.send(ToVersoMessage::StopRecording(id)) {
+            self.event_listeners
+                .recording_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
+    /// Find what the webview identified by `webview`, like [`AuditEntry::webview`], shows at a
+    /// point of its window, in physical pixels, for custom context menus and drag handling
+    ///
+    /// Tabs under the point are looked through, `None` if the webview shows nothing there or
+    /// doesn't exist.
+    pub fn hit_test(
+        &self,
+        webview: impl Into<String>,
+        point: PhysicalPosition<f64>,
+    ) -> Result<Option<HitTestResult>, Box<ipc_channel::ErrorKind>> {
+        let id = uuid::Uuid::new_v4();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.event_listeners
+            .hit_test_response
+            .lock()
+            .unwrap()
+            .insert(id, sender);
+        if let Err(error) = self
+            .sender
+            .send(ToVersoMessage::HitTest(id, webview.into(), point))
+        {
+            self.event_listeners
+                .hit_test_response
+                .lock()
+                .unwrap()
+                .remove(&id);
+            return Err(error);
+        };
+        Ok(receiver.recv().unwrap())
+    }
+
     // /// Add init script to run on document started to load
     // pub fn add_init_script(&self, script: String) -> Result<(), Box<ipc_channel::ErrorKind>> {
     //     self.sender.send(ToVersoMessage::AddInitScript(script))
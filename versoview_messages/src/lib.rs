@@ -1,6 +1,10 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    path::PathBuf,
+};
 
-use dpi::{PhysicalPosition, PhysicalSize, Position, Size};
+use dpi::{LogicalPosition, PhysicalPosition, PhysicalSize, Position, Size};
 use ipc_channel::ipc;
 use serde::{Deserialize, Serialize};
 
@@ -20,6 +24,10 @@ pub enum ToVersoMessage {
     SetConfig(ConfigFromController),
     /// Exit
     Exit,
+    /// The system is about to sleep, pause media and release GPU resources
+    Suspend,
+    /// The system woke up, revalidate the rendering and notify pages
+    Resume,
     /// Register a listener on versoview for getting notified on close requested from the OS,
     /// veroview will send a [`ToControllerMessage::OnCloseRequested`] when that happens
     ListenToOnCloseRequested,
@@ -70,6 +78,114 @@ pub enum ToVersoMessage {
     GetScaleFactor(uuid::Uuid),
     /// Get the current URL of the webview, need a response with [`ToControllerMessage::GetCurrentUrlResponse`]
     GetCurrentUrl(uuid::Uuid),
+    /// Enable or disable JavaScript and author styles of every webview, including ones opened later,
+    /// applied from the next navigation
+    SetContentSettings(ContentSettings),
+    /// Expose launch parameters to the webview's pages as `window.verso.params`, applied from the next navigation
+    SetLaunchParams(LaunchParams),
+    /// Register a listener on versoview for getting notified on blocked or upgraded mixed content
+    /// and failed integrity checks, veroview will send a [`ToControllerMessage::OnSecurityPolicyReport`] when that happens
+    ListenToSecurityPolicyReports,
+    /// Override the security policy of an origin (e.g. `https://example.com`) in the site settings,
+    /// `None` removes the override
+    SetSitePolicy(String, Option<SitePolicy>),
+    /// Rasterize an SVG to a PNG, need a response with [`ToControllerMessage::RasterizeSvgResponse`]
+    RasterizeSvg(uuid::Uuid, SvgRasterRequest),
+    /// Change how text is anti-aliased and hinted, applied with a repaint
+    SetTextRendering(TextRendering),
+    /// Get the script engine policy in effect for pages of a URL, need a response with [`ToControllerMessage::GetScriptPolicyResponse`]
+    GetScriptPolicy(uuid::Uuid, url::Url),
+    /// Get the WebSocket and EventSource connections of the webview, need a response with [`ToControllerMessage::GetConnectionsResponse`]
+    GetConnections(uuid::Uuid),
+    /// Close a connection of the webview by its [`ConnectionInfo::id`]
+    CloseConnection(u64),
+    /// Route the webview through a network context from [`ConfigFromController::network_contexts`],
+    /// or the default route with `None`, applied to requests from now on
+    SetNetworkContext(Option<String>),
+    /// Get the blocking decisions recorded for every webview, oldest first, need a response with [`ToControllerMessage::GetAuditLogResponse`]
+    GetAuditLog(uuid::Uuid),
+    /// Get the current windows and webviews as a session manifest, need a response with [`ToControllerMessage::ExportSessionResponse`]
+    ExportSession(uuid::Uuid),
+    /// Write the recent input events, compositor messages and frames of the flight recorder to disk,
+    /// need a response with [`ToControllerMessage::DumpFlightRecorderResponse`]
+    DumpFlightRecorder(uuid::Uuid),
+    /// Get the attributes of the GL context versoview renders with, need a response with [`ToControllerMessage::GetGlContextInfoResponse`]
+    GetGlContextInfo(uuid::Uuid),
+    /// Get the safe mode report of this run, need a response with [`ToControllerMessage::GetSafeModeReportResponse`]
+    GetSafeModeReport(uuid::Uuid),
+    /// Get the time spent per message type and the stalls of the event loop, need a response with [`ToControllerMessage::GetEventLoopStatsResponse`]
+    GetEventLoopStats(uuid::Uuid),
+    /// Control the playlist of the first window, see [`ConfigFromController::playlist`]
+    ControlPlaylist(PlaylistCommand),
+    /// Get what the playlist shows, need a response with [`ToControllerMessage::GetPlaylistStatusResponse`]
+    GetPlaylistStatus(uuid::Uuid),
+    /// Start or stop collecting the CSS and JavaScript coverage of the webview's pages, stopping forgets it
+    SetCoverage(bool),
+    /// Get the coverage of the webview's page, need a response with [`ToControllerMessage::GetCoverageResponse`]
+    GetCoverage(uuid::Uuid),
+    /// Render the current frame with both the hardware and the software WebRender and compare them,
+    /// need a response with [`ToControllerMessage::ValidateRenderingResponse`]
+    ValidateRendering(uuid::Uuid),
+    /// Replace the gesture to command table of every window, see [`ConfigFromController::gestures`]
+    SetGestureBindings(GestureBindings),
+    /// Wait until the webview's page is loaded and its rendering settled,
+    /// need a response with [`ToControllerMessage::WaitUntilStableResponse`]
+    WaitUntilStable(uuid::Uuid, StabilityOptions),
+    /// Replace how address bar input is interpreted, see [`ConfigFromController::address_input`]
+    SetAddressInput(AddressInputSettings),
+    /// Interpret text typed in an address bar the way Verso's own does,
+    /// need a response with [`ToControllerMessage::InterpretAddressInputResponse`]
+    InterpretAddressInput(uuid::Uuid, String),
+    /// Show the webview identified like [`AuditEntry::webview`] at a rect of its window,
+    /// next to the other tiled tabs, `None` puts it back in the content area
+    SetTabRect(String, Option<TabRect>),
+    /// Zoom the page of the webview identified like [`AuditEntry::webview`], laying it out
+    /// again, 1 resets it; clamped between 0.25 and 5
+    SetPageZoom(String, f32),
+    /// Animate the pinch zoom of the tab at this index of the first window's tab order back to 1
+    ResetPinchZoom(usize),
+    /// Show or hide the frame rate, frame times, dropped frames and scroll coalescing in the HUD
+    /// of the window being composited
+    ToggleStatsHud,
+    /// Turn a WebRender debug overlay of the window being composited on or off
+    ToggleWebRenderDebug(WebRenderDebugOption),
+    /// Save WebRender's scene and frame into a directory, to load in wrench for a rendering bug
+    /// report, or into a new directory of `webrender-captures` if `None`
+    CaptureWebRenderFrame(Option<PathBuf>),
+    /// Turn the translation of mouse input into touch events on or off, for trying touch UIs on
+    /// a desktop
+    SetMouseToTouch(bool),
+    /// Change a WebRender option while it runs, named and valued like the overrides of
+    /// [`ConfigFromController::renderer_options`]
+    ///
+    /// Only `picture_tile_size`, `batch_lookback_count`, `multithreading` and `image_tile_size`
+    /// can change at runtime, others are ignored with a warning.
+    SetRendererOption(String, String),
+    /// Capture the next composited frame of the webview,
+    /// need a response with [`ToControllerMessage::CaptureScreenshotResponse`]
+    CaptureScreenshot(uuid::Uuid, ScreenshotOptions),
+    /// Record the frames Verso composites, stopping a recording in progress
+    StartRecording(RecordingSettings),
+    /// Stop recording the frames,
+    /// need a response with [`ToControllerMessage::StopRecordingResponse`]
+    StopRecording(uuid::Uuid),
+    /// Find what the webview identified like [`AuditEntry::webview`] shows at a point of its
+    /// window, in physical pixels, need a response with [`ToControllerMessage::HitTestResponse`]
+    HitTest(uuid::Uuid, String, PhysicalPosition<f64>),
+    /// Replace the cursors of every window, see [`ConfigFromController::cursor_theme`]
+    SetCursorTheme(CursorTheme),
+    /// Register a listener on versoview for getting notified on every frame it presents,
+    /// versoview will send a [`ToControllerMessage::OnFramePresented`] when that happens
+    ListenToFramePresented,
+    /// Register a listener on versoview for getting every mouse position the OS reported, versoview
+    /// will send a [`ToControllerMessage::OnRawPointerSamples`] with the ones of each frame
+    ListenToRawPointerSamples,
+    /// Register a listener on versoview for getting the progress of its shutdown, versoview will
+    /// send a [`ToControllerMessage::OnShutdownProgress`] for every [`ShutdownPhase`] it enters
+    ListenToShutdownProgress,
+    /// Give back a shared texture of a [`ToControllerMessage::OnSharedTextureFrame`], by its
+    /// [`SharedTextureFrame::buffer`], once the controller is done reading it
+    ReleaseSharedTexture(u32),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -95,6 +211,29 @@ pub enum ToControllerMessage {
     OnNavigationStarting(SerializedPipelineId, url::Url),
     /// Sent on a new web resource request, need a response with [`ToVersoMessage::WebResourceRequestResponse`]
     OnWebResourceRequested(WebResourceRequest),
+    /// Sent when mixed content is blocked or upgraded, or a subresource integrity check fails
+    OnSecurityPolicyReport(SecurityPolicyReport),
+    /// Sent when versoview enters a new shutdown phase, so the controller can show a "closing…" UI
+    OnShutdownProgress(ShutdownProgress),
+    /// Sent when versoview schedules a recovery action for a failing webview, see [`ConfigFromController::recovery`]
+    OnRecovery(RecoveryEvent),
+    /// Sent once versoview started in safe mode after repeated startup crashes, see [`ConfigFromController::safe_mode`]
+    OnSafeMode(SafeModeReport),
+    /// Sent when a gesture bound to a [`GestureCommand::Custom`] command is recognized, with the command's name
+    OnGestureCommand(String),
+    /// Sent when the GPU driver reset and versoview restarted its renderer
+    OnDeviceLost(DeviceLostEvent),
+    /// Sent when a window presented a new frame
+    OnFramePresented(FramePresented),
+    /// Sent with the mouse positions of the first window the OS reported since the last time, at
+    /// full resolution while the ones given to pages are resampled to the frame rate
+    OnRawPointerSamples(Vec<RawPointerSample>),
+    /// Sent when a frame was composited into a shared texture, see [`ConfigFromController::shared_texture`]
+    OnSharedTextureFrame(SharedTextureFrame),
+    /// Sent when a page a tab loaded settled, or was given up on, see [`ConfigFromController::page_settled`]
+    OnPageSettled(PageSettled),
+    /// Sent when the first paint or first contentful paint of a page a webview shows was composited
+    OnPaintMetric(PaintMetric),
     /// Response to a [`ToVersoMessage::GetSize`]
     GetSizeResponse(uuid::Uuid, PhysicalSize<u32>),
     /// Response to a [`ToVersoMessage::GetPosition`]
@@ -111,6 +250,40 @@ pub enum ToControllerMessage {
     GetScaleFactorResponse(uuid::Uuid, f64),
     /// Response to a [`ToVersoMessage::GetCurrentUrl`]
     GetCurrentUrlResponse(uuid::Uuid, url::Url),
+    /// Response to a [`ToVersoMessage::RasterizeSvg`], the PNG or why rasterization failed
+    RasterizeSvgResponse(uuid::Uuid, Result<Vec<u8>, String>),
+    /// Response to a [`ToVersoMessage::GetScriptPolicy`]
+    GetScriptPolicyResponse(uuid::Uuid, ScriptPolicy),
+    /// Response to a [`ToVersoMessage::GetConnections`], live connections and the ones closed since the last query
+    GetConnectionsResponse(uuid::Uuid, Vec<ConnectionInfo>),
+    /// Response to a [`ToVersoMessage::GetAuditLog`]
+    GetAuditLogResponse(uuid::Uuid, Vec<AuditEntry>),
+    /// Response to a [`ToVersoMessage::ExportSession`]
+    ExportSessionResponse(uuid::Uuid, SessionManifest),
+    /// Response to a [`ToVersoMessage::DumpFlightRecorder`], the directory written to or why nothing was
+    DumpFlightRecorderResponse(uuid::Uuid, Result<PathBuf, String>),
+    /// Response to a [`ToVersoMessage::GetGlContextInfo`], `None` if there's no GL context
+    GetGlContextInfoResponse(uuid::Uuid, Option<GlContextInfo>),
+    /// Response to a [`ToVersoMessage::GetSafeModeReport`], `None` if this run isn't in safe mode
+    GetSafeModeReportResponse(uuid::Uuid, Option<SafeModeReport>),
+    /// Response to a [`ToVersoMessage::GetEventLoopStats`], `None` once the compositor shut down
+    GetEventLoopStatsResponse(uuid::Uuid, Option<EventLoopStats>),
+    /// Response to a [`ToVersoMessage::GetPlaylistStatus`], `None` if no window plays a playlist
+    GetPlaylistStatusResponse(uuid::Uuid, Option<PlaylistStatus>),
+    /// Response to a [`ToVersoMessage::GetCoverage`], `None` if the page couldn't report it
+    GetCoverageResponse(uuid::Uuid, Option<CoverageReport>),
+    /// Response to a [`ToVersoMessage::ValidateRendering`], or why the frames couldn't be compared
+    ValidateRenderingResponse(uuid::Uuid, Result<RenderValidationReport, String>),
+    /// Response to a [`ToVersoMessage::WaitUntilStable`] once the page is stable, or why it didn't get stable
+    WaitUntilStableResponse(uuid::Uuid, Result<StabilityReport, String>),
+    /// Response to a [`ToVersoMessage::InterpretAddressInput`], `None` if the input is empty
+    InterpretAddressInputResponse(uuid::Uuid, Option<AddressTarget>),
+    /// Response to a [`ToVersoMessage::CaptureScreenshot`], or why it couldn't be captured
+    CaptureScreenshotResponse(uuid::Uuid, Result<Screenshot, String>),
+    /// Response to a [`ToVersoMessage::StopRecording`], or why the recording failed
+    StopRecordingResponse(uuid::Uuid, Result<RecordingReport, String>),
+    /// Response to a [`ToVersoMessage::HitTest`], `None` if the tab shows nothing there
+    HitTestResponse(uuid::Uuid, Option<HitTestResult>),
     /// Verso have recieved a close request from the OS
     OnCloseRequested,
 }
@@ -136,7 +309,9 @@ pub struct ConfigFromController {
     pub focused: bool,
     /// Launch decorated or not for the initial winit window
     pub decorated: bool,
-    /// Launch transparent or not for the initial winit window
+    /// Launch transparent or not for the initial winit window, the parts of the pages
+    /// without a background show what's behind the window. Windows opened later share
+    /// the surface format of the initial one.
     pub transparent: bool,
     /// Title of the initial winit window in the title bar.
     pub title: Option<String>,
@@ -152,9 +327,72 @@ pub struct ConfigFromController {
     pub user_scripts: Vec<UserScript>,
     /// Initial window's zoom level
     pub zoom_level: Option<f32>,
+    /// Smallest pinch zoom of a webview, 1 by default so pages can't be pinched smaller
+    pub min_pinch_zoom: f32,
+    /// Largest pinch zoom of a webview, 8 by default
+    pub max_pinch_zoom: f32,
     /// Path to resource directory. If None, Verso will try to get default directory. And if that
     /// still doesn't exist, all resource configuration will set to default values.
     pub resources_directory: Option<PathBuf>,
+    /// WebRender renderer option overrides as `(name, value)` pairs,
+    /// invalid or out-of-range values are logged and ignored or clamped by versoview.
+    pub renderer_options: Vec<(String, String)>,
+    /// How frames are presented, falls back to [`PresentMode::Fifo`] where unsupported
+    pub present_mode: PresentMode,
+    /// JavaScript and author style switches applied to every webview
+    pub content_settings: ContentSettings,
+    /// Conversion of images to the display's color space
+    pub color_management: ColorManagement,
+    /// Text anti-aliasing and hinting, e.g. to fix text on embedded displays
+    pub text_rendering: TextRendering,
+    /// Emoji font and color glyph rendering
+    pub emoji: EmojiSettings,
+    /// Script engine limits of the profile, origins can tighten them with a [`SitePolicy`]
+    pub script_policy: ScriptPolicy,
+    /// Time zone, locale and clocks every page sees, frames included, the host's if `None`
+    pub clock_override: Option<ClockOverride>,
+    /// HTTP/3 for embedder-side fetches, the requests Verso makes itself; pages still load over TCP
+    pub http3: Http3Settings,
+    /// Named network contexts webviews can be routed through
+    pub network_contexts: HashMap<String, NetworkContext>,
+    /// Network context of new webviews, the default route if `None`
+    pub default_network_context: Option<String>,
+    /// Let page requests through most urgent first, images once the page said which are in view
+    pub prioritize_requests: bool,
+    /// Windows and webviews to open at startup instead of a single window showing [`Self::url`]
+    pub session: Option<SessionManifest>,
+    /// Automatic recovery of crashed or hung webviews, for unattended devices
+    pub recovery: RecoveryPolicy,
+    /// Keeps the last seconds of input, compositor messages and frames to debug what happened before a failure
+    pub flight_recorder: FlightRecorderSettings,
+    /// Requirements on the GL context, for platforms where GPU driver resets are common, and the color depth of the window surface
+    pub gl_context: GlContextSettings,
+    /// Start with risky subsystems disabled after startups crashed repeatedly
+    pub safe_mode: SafeModeSettings,
+    /// Log event loop iterations taking longer than this, in milliseconds, never if `None`
+    pub stall_threshold_ms: Option<u64>,
+    /// Pages the first window rotates through, for signage
+    pub playlist: Option<Playlist>,
+    /// Compare frames rendered by the GPU with the software renderer, to diagnose driver bugs
+    pub render_validation: RenderValidationSettings,
+    /// What swipes, long presses, touchpad gestures and key chords do
+    pub gestures: GestureBindings,
+    /// How text typed in the address bar becomes a URL, with the search engine and rewrites
+    pub address_input: AddressInputSettings,
+    /// Cursors shown in place of the ones pages ask for
+    pub cursor_theme: CursorTheme,
+    /// How mouse wheel and keyboard scrolls are animated
+    pub smooth_scrolling: SmoothScrolling,
+    /// Wait until every page a tab loads is stable and send a [`ToControllerMessage::OnPageSettled`],
+    /// never if `None`; also what the `-x` flag waits for before exiting
+    pub page_settled: Option<StabilityOptions>,
+    /// Composite the frames of the first window into textures shared with the controller
+    /// instead of presenting them, to embed them in another engine without copies;
+    /// presented to the window if `None`
+    pub shared_texture: Option<SharedTextureSettings>,
+    /// Drag links, images and selected text between webviews and onto the panel; pages that
+    /// drag links or images themselves, like maps and sliders, may want it off
+    pub drag_and_drop: bool,
 }
 
 impl Default for ConfigFromController {
@@ -177,11 +415,1608 @@ impl Default for ConfigFromController {
             user_agent: None,
             user_scripts: Vec::new(),
             zoom_level: None,
+            min_pinch_zoom: 1.0,
+            max_pinch_zoom: 8.0,
             resources_directory: None,
+            renderer_options: Vec::new(),
+            present_mode: PresentMode::Fifo,
+            content_settings: ContentSettings::default(),
+            color_management: ColorManagement::default(),
+            text_rendering: TextRendering::default(),
+            emoji: EmojiSettings::default(),
+            script_policy: ScriptPolicy::default(),
+            clock_override: None,
+            http3: Http3Settings::default(),
+            network_contexts: HashMap::new(),
+            default_network_context: None,
+            prioritize_requests: false,
+            session: None,
+            recovery: RecoveryPolicy::default(),
+            flight_recorder: FlightRecorderSettings::default(),
+            gl_context: GlContextSettings::default(),
+            safe_mode: SafeModeSettings::default(),
+            stall_threshold_ms: Some(100),
+            playlist: None,
+            render_validation: RenderValidationSettings::default(),
+            gestures: GestureBindings::default(),
+            address_input: AddressInputSettings::default(),
+            cursor_theme: CursorTheme::default(),
+            smooth_scrolling: SmoothScrolling::default(),
+            page_settled: None,
+            shared_texture: None,
+            drag_and_drop: true,
         }
     }
 }
 
+/// HTTP/3 settings of embedder-side fetches, see [`ConfigFromController::http3`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Http3Settings {
+    /// Use HTTP/3 over QUIC for Verso's own requests where available
+    pub enabled: bool,
+    /// Only use HTTP/3 for origins that advertised it with an `Alt-Svc` header
+    pub alt_svc_discovery: bool,
+    /// How long HTTP/3 gets before a TCP connection is raced against it, in milliseconds
+    pub fallback_delay_ms: u64,
+    /// Origins never reached over HTTP/3, e.g. behind middleboxes dropping QUIC
+    pub disabled_origins: Vec<String>,
+}
+
+impl Default for Http3Settings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            alt_svc_discovery: true,
+            fallback_delay_ms: 300,
+            disabled_origins: Vec::new(),
+        }
+    }
+}
+
+/// Proxy and DNS settings of a network context, see [`ConfigFromController::network_contexts`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NetworkContext {
+    /// Proxy every request through this URL, e.g. `socks5h://127.0.0.1:9050`, or connect directly with `None`
+    pub proxy: Option<String>,
+    /// DNS servers resolving host names, the system resolver if empty, unused with a `socks5h` proxy
+    pub dns_servers: Vec<IpAddr>,
+}
+
+/// Windows and webviews to open at startup, see [`ConfigFromController::session`]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct SessionManifest {
+    /// Windows in the order they are opened
+    #[serde(default)]
+    pub windows: Vec<SessionWindow>,
+}
+
+/// A window of a [`SessionManifest`] and its group of webviews
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SessionWindow {
+    /// Outer position, left to the window manager if `None`
+    #[serde(default)]
+    pub position: Option<PhysicalPosition<i32>>,
+    /// Inner size, the default size if `None`
+    #[serde(default)]
+    pub size: Option<PhysicalSize<u32>>,
+    /// Open maximized
+    #[serde(default)]
+    pub maximized: bool,
+    /// Open fullscreen
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Show the control panel with the tab bar, ignored for kiosk windows
+    #[serde(default = "default_true")]
+    pub panel: bool,
+    /// Fullscreen without panel or decorations
+    #[serde(default)]
+    pub kiosk: bool,
+    /// Zoom level of the window
+    #[serde(default)]
+    pub zoom: Option<f32>,
+    /// Webviews opened as tabs in this order, the last one is shown
+    pub webviews: Vec<SessionWebView>,
+}
+
+/// A webview of a [`SessionWindow`]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SessionWebView {
+    /// URL to load
+    pub url: url::Url,
+    /// Network context from [`ConfigFromController::network_contexts`] to route the webview through
+    #[serde(default)]
+    pub network_context: Option<String>,
+    /// JavaScript and author style switches, the profile's if `None`
+    #[serde(default)]
+    pub content_settings: Option<ContentSettings>,
+    /// Parameters exposed to the webview's pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch_params: Option<LaunchParams>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Pages a window rotates through, see [`ConfigFromController::playlist`]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Playlist {
+    /// Pages shown in turn
+    pub items: Vec<PlaylistItem>,
+    /// When the pages are shown, always if empty
+    pub schedules: Vec<DailySchedule>,
+    /// Page shown outside the schedules, a blank page if `None`
+    pub idle_url: Option<url::Url>,
+    /// How long a page may take to load before it's skipped, in seconds
+    pub load_timeout_secs: u64,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            schedules: Vec::new(),
+            idle_url: None,
+            load_timeout_secs: 30,
+        }
+    }
+}
+
+/// A page of a [`Playlist`]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PlaylistItem {
+    /// URL of the page
+    pub url: url::Url,
+    /// How long the page is shown, in seconds
+    pub duration_secs: u64,
+    /// How the page replaces the previous one
+    #[serde(default)]
+    pub transition: PlaylistTransition,
+}
+
+/// How a playlist page replaces the previous one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PlaylistTransition {
+    /// Replace it at once
+    #[default]
+    Cut,
+    /// Fade the page in over the previous one
+    CrossFade {
+        /// Length of the fade, in milliseconds
+        duration_ms: u64,
+    },
+}
+
+/// Daily time range a [`Playlist`] plays in, in local time
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DailySchedule {
+    /// Days of the week, 0 for Monday to 6 for Sunday, every day if empty
+    #[serde(default)]
+    pub days: Vec<u8>,
+    /// Start time, `HH:MM`
+    pub start: String,
+    /// End time, `HH:MM`, the range runs past midnight if it's before the start
+    pub end: String,
+}
+
+/// Runtime control of a playlist, see [`ToVersoMessage::ControlPlaylist`]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum PlaylistCommand {
+    /// Replace the playlist, starting from its first page
+    Set(Playlist),
+    /// Show the next page now
+    Next,
+    /// Show the previous page now
+    Previous,
+    /// Keep showing the current page
+    Pause,
+    /// Rotate again, the current page is shown for its whole duration
+    Resume,
+}
+
+/// What a playlist shows, see [`ToVersoMessage::GetPlaylistStatus`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PlaylistStatus {
+    /// Index of the page shown, `None` if it's the idle page or nothing loaded yet
+    pub current: Option<usize>,
+    /// URL of the page shown
+    pub url: Option<url::Url>,
+    /// Whether the rotation is paused
+    pub paused: bool,
+    /// Whether the time is within the schedules
+    pub scheduled: bool,
+    /// Pages skipped because they failed to load or crashed
+    pub skipped: u64,
+}
+
+/// How finished frames are handed to the display
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PresentMode {
+    /// Wait for vertical blank (vsync)
+    #[default]
+    Fifo,
+    /// Show the most recent frame at the next vertical blank without blocking, versoview's GL
+    /// renderer has no mailbox swap chain and uses [`PresentMode::Fifo`] instead
+    Mailbox,
+    /// Present right away, tearing is possible
+    Immediate,
+}
+
+/// Per-webview switches for page content
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ContentSettings {
+    /// Run scripts provided by the page
+    pub javascript_enabled: bool,
+    /// Apply style sheets and inline styles provided by the page
+    pub author_styles_enabled: bool,
+}
+
+impl Default for ContentSettings {
+    fn default() -> Self {
+        Self {
+            javascript_enabled: true,
+            author_styles_enabled: true,
+        }
+    }
+}
+
+/// Embedder-defined parameters exposed to a webview's pages as `window.verso.params`
+///
+/// Only pages of the `app://` scheme and of the allowed origins see them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LaunchParams {
+    /// Parameter values by name
+    pub params: BTreeMap<String, String>,
+    /// Origins allowed to read the parameters besides `app://` pages, e.g. `https://kiosk.example`
+    pub allowed_origins: Vec<String>,
+}
+
+/// Time zone, locale and clocks pages see instead of the host's, see [`ConfigFromController::clock_override`]
+///
+/// For tests and demos that need the same dates and times wherever they run.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClockOverride {
+    /// IANA time zone of local dates, e.g. `Pacific/Auckland`, the host's if `None`
+    pub time_zone: Option<String>,
+    /// BCP 47 locale of `navigator.language` and `Intl`, e.g. `de-CH`, the host's if `None`
+    pub locale: Option<String>,
+    /// Milliseconds since the Unix epoch `Date.now()` returns when a document starts,
+    /// the clock still advances from there
+    pub start_time_ms: Option<i64>,
+    /// Milliseconds added to `Date.now()`
+    pub clock_skew_ms: i64,
+    /// Milliseconds added to `performance.now()`
+    pub performance_skew_ms: f64,
+}
+
+/// Rect of a tab in its window, for showing several tabs side by side
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TabRect {
+    /// Top left corner, in physical pixels from the top left corner of the window
+    pub position: PhysicalPosition<i32>,
+    /// Size in physical pixels
+    pub size: PhysicalSize<u32>,
+}
+
+/// What a tab shows at a point, see [`ToVersoMessage::HitTest`]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct HitTestResult {
+    /// Whether the point is on the tab's page rather than one of its iframes
+    pub main_frame: bool,
+    /// The point in the viewport of the page or iframe, in CSS pixels
+    pub point_in_viewport: LogicalPosition<f32>,
+    /// The point relative to the top left corner of the item under it, in CSS pixels
+    pub point_relative_to_item: LogicalPosition<f32>,
+    /// CSS name of the cursor over the item, e.g. `pointer` over links, `None` if it's hidden
+    pub cursor: Option<String>,
+}
+
+/// Conversion of images with an embedded ICC profile to the output color space
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ColorManagement {
+    /// Convert images, off by default since it makes Verso fetch images itself
+    pub enabled: bool,
+    /// ICC profile of the display, `None` for sRGB
+    pub output_profile: Option<PathBuf>,
+}
+
+/// How text is anti-aliased and hinted, `None` fields keep the platform default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TextRendering {
+    /// Subpixel (LCD) anti-aliasing, grayscale anti-aliasing when off
+    pub subpixel_aa: Option<bool>,
+    /// Glyph hinting strength, FreeType only
+    pub hinting: Option<TextHinting>,
+    /// Filter reducing color fringes of subpixel anti-aliased text, FreeType only
+    pub lcd_filter: Option<LcdFilter>,
+    /// Gamma of the glyph coverage, from 1.0 to 3.0, DirectWrite only
+    pub gamma: Option<f32>,
+}
+
+/// Glyph hinting strength, see [`TextRendering::hinting`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TextHinting {
+    /// Don't hint, keeps glyph shapes exact
+    None,
+    /// Hint vertically only
+    Light,
+    /// Hint both directions
+    Normal,
+    /// Hint for the pixel grid of the display, sharpest on low DPI screens
+    Full,
+}
+
+/// Filter for subpixel anti-aliased text, see [`TextRendering::lcd_filter`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LcdFilter {
+    /// No filtering, strongest color fringes
+    None,
+    /// FreeType's default filter
+    Default,
+    /// Lighter filter, sharper but with more fringes
+    Light,
+    /// Filter of older FreeType versions
+    Legacy,
+}
+
+/// Emoji font and color glyph rendering
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EmojiSettings {
+    /// Emoji font file used when a page's fonts have no glyph for an emoji,
+    /// the system's emoji font if `None`
+    pub font: Option<PathBuf>,
+    /// Render color glyphs (CBDT, COLR, sbix), emoji are monochrome if off
+    pub color_glyphs: bool,
+}
+
+impl Default for EmojiSettings {
+    fn default() -> Self {
+        Self {
+            font: None,
+            color_glyphs: true,
+        }
+    }
+}
+
+/// How a secure page may load resources over insecure connections
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MixedContentPolicy {
+    /// Cancel the insecure load
+    #[default]
+    Block,
+    /// Load the resource over HTTPS instead
+    Upgrade,
+    /// Load the resource as requested
+    Allow,
+}
+
+/// Security policy of a site, stored per origin in the site settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SitePolicy {
+    /// What to do with insecure subresources of secure pages
+    pub mixed_content: MixedContentPolicy,
+    /// Report elements whose subresource integrity check failed, off by default as it costs a
+    /// round trip to the page when it has loaded
+    pub report_integrity_failures: bool,
+    /// Allow WebAssembly, `None` to follow the profile's [`ScriptPolicy`]
+    ///
+    /// Advisory, the page's `WebAssembly` global is hidden but a page can get around that.
+    #[serde(default)]
+    pub wasm_enabled: Option<bool>,
+    /// Most workers a page may run at once, `None` to follow the profile's [`ScriptPolicy`]
+    ///
+    /// Advisory, like [`ScriptPolicy::max_workers`].
+    #[serde(default)]
+    pub max_workers: Option<u32>,
+}
+
+impl Default for SitePolicy {
+    fn default() -> Self {
+        Self {
+            mixed_content: MixedContentPolicy::Block,
+            report_integrity_failures: false,
+            wasm_enabled: None,
+            max_workers: None,
+        }
+    }
+}
+
+/// JavaScript engine tiers allowed to compile code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JitPolicy {
+    /// Every tier, the fastest
+    #[default]
+    Full,
+    /// The baseline compilers only, no optimizing compilers
+    BaselineOnly,
+    /// No code generation at all, for high-security deployments, WebAssembly is disabled too
+    InterpreterOnly,
+}
+
+/// Script engine limits, see [`ConfigFromController::script_policy`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ScriptPolicy {
+    /// Allow WebAssembly
+    pub wasm_enabled: bool,
+    /// JIT tiers allowed, for the whole profile only since the engine is shared by all origins
+    pub jit: JitPolicy,
+    /// Most workers a page may run at once, `None` for no limit
+    ///
+    /// Advisory, the page's `Worker` constructor is wrapped but a page can get around that.
+    pub max_workers: Option<u32>,
+}
+
+impl Default for ScriptPolicy {
+    fn default() -> Self {
+        Self {
+            wasm_enabled: true,
+            jit: JitPolicy::Full,
+            max_workers: None,
+        }
+    }
+}
+
+/// Kind of a [`ConnectionInfo`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ConnectionKind {
+    /// A `WebSocket`
+    WebSocket,
+    /// An `EventSource` (server-sent events)
+    EventSource,
+}
+
+/// State of a [`ConnectionInfo`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ConnectionState {
+    /// Opening, or reconnecting for an `EventSource`
+    Connecting,
+    /// Open
+    Open,
+    /// Closing handshake in progress
+    Closing,
+    /// Closed
+    Closed,
+}
+
+/// A WebSocket or EventSource connection of a page
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConnectionInfo {
+    /// Identifier of the connection within its page
+    pub id: u64,
+    /// WebSocket or EventSource
+    pub kind: ConnectionKind,
+    /// URL of the connection
+    pub url: url::Url,
+    /// Current state
+    pub state: ConnectionState,
+    /// Payload bytes sent by the page
+    pub bytes_sent: u64,
+    /// Payload bytes received by the page
+    pub bytes_received: u64,
+    /// Messages sent by the page
+    pub messages_sent: u64,
+    /// Messages received by the page
+    pub messages_received: u64,
+}
+
+/// Kind of a [`ResourceCoverage`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CoverageKind {
+    /// A style sheet, linked or inline
+    Stylesheet,
+    /// A script, external or inline
+    Script,
+}
+
+/// How much of a style sheet of a page was used, or the size of a script
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ResourceCoverage {
+    /// Style sheet or script
+    pub kind: CoverageKind,
+    /// URL of the resource, `None` if it's inline
+    pub url: Option<String>,
+    /// Size of the resource in bytes, of its rules for style sheets
+    pub total_bytes: u64,
+    /// Bytes of the rules that matched, always 0 for scripts as their coverage isn't measured
+    pub used_bytes: u64,
+    /// Bytes of the rules that never matched, always 0 for scripts
+    #[serde(default)]
+    pub unused_bytes: u64,
+    /// Style rules of a style sheet
+    pub rules: u32,
+    /// Style rules that matched an element
+    pub used_rules: u32,
+    /// Selectors of the first unused style rules
+    pub unused_selectors: Vec<String>,
+    /// Whether the page can't read the rules, for cross-origin style sheets, they count as used
+    pub opaque: bool,
+}
+
+/// CSS and JavaScript coverage of a page, see [`ToVersoMessage::SetCoverage`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CoverageReport {
+    /// URL of the page
+    pub url: Option<url::Url>,
+    /// Style sheets and scripts of the page
+    pub resources: Vec<ResourceCoverage>,
+    /// Bytes of every resource
+    #[serde(default)]
+    pub total_bytes: u64,
+    /// Bytes of style rules never used
+    #[serde(default)]
+    pub unused_bytes: u64,
+}
+
+/// What a [`SecurityPolicyReport`] is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SecurityPolicyViolation {
+    /// An insecure subresource was cancelled
+    MixedContentBlocked,
+    /// An insecure subresource was loaded over HTTPS instead
+    MixedContentUpgraded,
+    /// A subresource didn't match its `integrity` attribute, or failed to load
+    IntegrityCheckFailed,
+}
+
+/// A security policy event, see [`ToControllerMessage::OnSecurityPolicyReport`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SecurityPolicyReport {
+    /// What happened
+    pub violation: SecurityPolicyViolation,
+    /// URL of the page that requested the resource
+    pub document_url: url::Url,
+    /// URL of the resource
+    pub resource_url: url::Url,
+}
+
+/// Which policy an [`AuditEntry`] records a decision of
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AuditCategory {
+    /// A script was cancelled because JavaScript is disabled for the webview
+    ContentBlocked,
+    /// An insecure subresource was cancelled or loaded over HTTPS instead
+    MixedContent,
+    /// The page's Content Security Policy blocked a resource or inline code
+    ContentSecurityPolicy,
+    /// A subresource didn't match its `integrity` attribute
+    Integrity,
+    /// A permission request was denied
+    PermissionDenied,
+    /// A navigation was refused by the controller
+    NavigationDenied,
+    /// A request couldn't go through the webview's network context
+    NetworkContext,
+}
+
+/// A blocking decision, see [`ToVersoMessage::GetAuditLog`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// When the decision was made, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Identifies the webview the decision was made for, stable for the lifetime of versoview
+    pub webview: String,
+    /// Which policy made the decision
+    pub category: AuditCategory,
+    /// URL of the page, if it was known
+    pub document_url: Option<url::Url>,
+    /// What was blocked: a resource URL, a permission or a navigation target
+    pub subject: String,
+    /// Why it was blocked
+    pub reason: String,
+}
+
+/// A failure counted towards recovering a webview, see [`RecoveryPolicy`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum RecoveryTrigger {
+    /// A pipeline of the webview panicked
+    Crash,
+    /// The webview didn't answer a liveness probe in time
+    Unresponsive,
+    /// The window's renderer was rebuilt, e.g. after the GPU changed
+    RendererRestart,
+}
+
+/// What versoview does to bring a failing webview back, from the mildest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum RecoveryAction {
+    /// Load the webview's URL again
+    Reload,
+    /// Close the webview and open its URL in a new one
+    RecreateWebView,
+    /// Restart versoview, or exit for the controller to restart it
+    RestartBrowser,
+}
+
+/// Take `action` once a webview failed `failures` times with `trigger` within the failure window
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RecoveryRule {
+    /// Failures this rule counts
+    pub trigger: RecoveryTrigger,
+    /// Failures needed within [`RecoveryPolicy::failure_window_secs`]
+    pub failures: u32,
+    /// Action to take, rules with more failures escalate those with fewer
+    pub action: RecoveryAction,
+}
+
+/// Escalation rules for recovering crashed or hung webviews, see [`ConfigFromController::recovery`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecoveryPolicy {
+    /// Watch webviews and recover them, off by default
+    pub enabled: bool,
+    /// Escalation rules, failures without a matching rule are only logged
+    pub rules: Vec<RecoveryRule>,
+    /// Failures older than this are forgotten, in seconds
+    pub failure_window_secs: u64,
+    /// Delay before the first recovery action, doubled for every following one, in milliseconds
+    pub initial_backoff_ms: u64,
+    /// Longest delay between recovery actions, in milliseconds
+    pub max_backoff_ms: u64,
+    /// How often the shown webview of each window is probed, in milliseconds
+    pub probe_interval_ms: u64,
+    /// How long a webview may take to answer a probe before it counts as unresponsive, in milliseconds
+    pub probe_timeout_ms: u64,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        let rule = |trigger, failures, action| RecoveryRule {
+            trigger,
+            failures,
+            action,
+        };
+        Self {
+            enabled: false,
+            rules: vec![
+                rule(RecoveryTrigger::Crash, 1, RecoveryAction::Reload),
+                rule(RecoveryTrigger::Crash, 3, RecoveryAction::RecreateWebView),
+                rule(RecoveryTrigger::Crash, 5, RecoveryAction::RestartBrowser),
+                rule(RecoveryTrigger::Unresponsive, 1, RecoveryAction::Reload),
+                rule(
+                    RecoveryTrigger::Unresponsive,
+                    2,
+                    RecoveryAction::RecreateWebView,
+                ),
+                rule(
+                    RecoveryTrigger::Unresponsive,
+                    4,
+                    RecoveryAction::RestartBrowser,
+                ),
+                rule(RecoveryTrigger::RendererRestart, 3, RecoveryAction::Reload),
+                rule(
+                    RecoveryTrigger::RendererRestart,
+                    6,
+                    RecoveryAction::RestartBrowser,
+                ),
+            ],
+            failure_window_secs: 600,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60_000,
+            probe_interval_ms: 5000,
+            probe_timeout_ms: 3000,
+        }
+    }
+}
+
+/// Flight recorder settings, see [`ConfigFromController::flight_recorder`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FlightRecorderSettings {
+    /// Record, off by default
+    pub enabled: bool,
+    /// How much history is kept, in seconds
+    pub duration_secs: u64,
+    /// Time between two frame captures, in milliseconds
+    pub capture_interval_ms: u64,
+    /// Width frames are scaled down to, keeping their aspect ratio
+    pub capture_width: u32,
+    /// Dump when a composite takes longer than this, in milliseconds, never if `None`
+    pub jank_threshold_ms: Option<u64>,
+    /// Dump when a pipeline panics
+    pub dump_on_crash: bool,
+    /// Directory dumps are written to, the temporary directory if `None`
+    pub dump_directory: Option<PathBuf>,
+}
+
+impl Default for FlightRecorderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration_secs: 10,
+            capture_interval_ms: 250,
+            capture_width: 320,
+            jank_threshold_ms: Some(250),
+            dump_on_crash: true,
+            dump_directory: None,
+        }
+    }
+}
+
+/// Requirements on the GL context, see [`ConfigFromController::gl_context`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GlContextSettings {
+    /// Fail to start unless the context has robust buffer access
+    pub require_robustness: bool,
+    /// Ask for a context that is lost on a GPU reset, instead of one that may keep rendering garbage
+    pub lose_context_on_reset: bool,
+    /// Bits per color channel of the window surface
+    pub color_depth: OutputColorDepth,
+}
+
+/// Color depth of the window surface, for HDR and wide-gamut displays
+///
+/// WebRender renders sRGB-encoded colors whatever the depth, a deeper surface
+/// keeps gradients from banding on 10-bit displays but doesn't map content to
+/// a wider gamut or to HDR brightness.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OutputColorDepth {
+    /// 8 bits per channel
+    #[default]
+    Standard,
+    /// 10 bits per channel if the display offers it, 8 otherwise
+    Auto,
+    /// 10 bits per channel, falling back to 8
+    Rgb10,
+    /// 16-bit floating point channels, falling back to 10 and then 8 bits
+    Float16,
+}
+
+/// GL flavor of a context
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GlApi {
+    /// Desktop OpenGL
+    OpenGl,
+    /// OpenGL ES
+    Gles,
+}
+
+/// Profile of a desktop OpenGL context
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GlProfile {
+    /// Core profile, without deprecated features
+    Core,
+    /// Compatibility profile
+    Compatibility,
+}
+
+/// What happens to a context when the GPU resets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GlResetStrategy {
+    /// The context isn't told and may keep rendering garbage
+    NoResetNotification,
+    /// The context is lost and has to be created again
+    LoseContextOnReset,
+}
+
+/// Platform interface a context was created with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GlPlatform {
+    /// EGL, on Wayland, Android and ANGLE
+    Egl,
+    /// GLX, on X11
+    Glx,
+    /// WGL, on Windows
+    Wgl,
+    /// CGL, on macOS
+    Cgl,
+}
+
+/// Attributes of the GL context versoview renders with, see [`ToVersoMessage::GetGlContextInfo`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GlContextInfo {
+    /// Desktop OpenGL or OpenGL ES
+    pub api: GlApi,
+    /// Major and minor version of the context
+    pub version: (u8, u8),
+    /// `GL_VERSION` string
+    pub version_string: String,
+    /// `GL_SHADING_LANGUAGE_VERSION` string
+    pub shading_language_version: String,
+    /// Profile of a desktop context, `None` for OpenGL ES and contexts older than 3.2
+    pub profile: Option<GlProfile>,
+    /// Whether out of bounds buffer accesses are guaranteed not to crash
+    pub robust_access: bool,
+    /// What happens on a GPU reset, `None` if the driver doesn't say
+    pub reset_strategy: Option<GlResetStrategy>,
+    /// Platform interface, `None` if unknown
+    pub platform: Option<GlPlatform>,
+    /// Version string of the platform interface, e.g. `EGL 1.5`
+    pub platform_version: String,
+    /// `GL_VENDOR` string
+    pub vendor: String,
+    /// `GL_RENDERER` string
+    pub renderer: String,
+    /// Multisampling samples of the framebuffer configuration
+    pub samples: u8,
+    /// Bits of the red channel of the framebuffer configuration
+    pub color_bits: u8,
+    /// Whether the framebuffer configuration has floating point channels
+    pub float_pixels: bool,
+    /// Bits of the alpha channel of the framebuffer configuration
+    pub alpha_bits: u8,
+    /// Whether the OS compositor blends the window with the alpha channel
+    pub transparent: bool,
+}
+
+/// Direction of a swipe
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum SwipeDirection {
+    /// Towards the left
+    Left,
+    /// Towards the right
+    Right,
+    /// Upwards
+    Up,
+    /// Downwards
+    Down,
+}
+
+/// Edge of a window
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum WindowEdge {
+    /// Left edge
+    Left,
+    /// Right edge
+    Right,
+    /// Top edge
+    Top,
+    /// Bottom edge
+    Bottom,
+}
+
+/// Direction of a rotation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum RotationDirection {
+    /// Clockwise
+    Clockwise,
+    /// Counterclockwise
+    CounterClockwise,
+}
+
+/// A gesture versoview recognizes, see [`GestureBindings`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Gesture {
+    /// Three fingers swiping together on a touchscreen
+    ThreeFingerSwipe(SwipeDirection),
+    /// A finger swiping into the window from one of its edges
+    EdgeSwipe(WindowEdge),
+    /// A finger held still on a touchscreen
+    LongPress,
+    /// Two fingers rotating on a touchpad
+    Rotate(RotationDirection),
+    /// Two fingers pinching on a touchpad
+    Pinch,
+    /// Keys pressed together, modifiers then a `KeyboardEvent.code` joined by `+`, like `CmdOrCtrl+Shift+KeyT`
+    KeyChord(String),
+}
+
+/// What a gesture does, see [`GestureBindings`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GestureCommand {
+    /// Go back in the history of the current tab
+    Back,
+    /// Go forward in the history of the current tab
+    Forward,
+    /// Reload the current tab
+    Reload,
+    /// Load a URL in the current tab
+    Navigate(url::Url),
+    /// Zoom by how much a pinch scales, or like [`Self::ZoomIn`] for other gestures
+    Zoom,
+    /// Zoom in one step
+    ZoomIn,
+    /// Zoom out one step
+    ZoomOut,
+    /// Reset the zoom
+    ResetZoom,
+    /// Open a new tab
+    NewTab,
+    /// Close the current tab
+    CloseTab,
+    /// Focus the URL bar of the panel
+    FocusUrlBar,
+    /// Show or hide the HUD with the GPU, the viewport and the devtools server port
+    ToggleHud,
+    /// Show or hide the frame rate, frame times, dropped frames and scroll coalescing in the HUD
+    ToggleStatsHud,
+    /// Turn the translation of mouse input into touch events on or off, bound to
+    /// `CmdOrCtrl+Shift+KeyM` by default
+    ToggleTouchEmulation,
+    /// Save WebRender's scene and frame into a new directory of `webrender-captures`, see
+    /// [`ToVersoMessage::CaptureWebRenderFrame`]
+    CaptureWebRenderFrame,
+    /// Send the name to the controller with [`ToControllerMessage::OnGestureCommand`]
+    Custom(String),
+    /// Do nothing, to turn off a default binding
+    Nothing,
+}
+
+/// A gesture and the command it runs
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GestureBinding {
+    /// Gesture to recognize
+    pub gesture: Gesture,
+    /// Command to run
+    pub command: GestureCommand,
+}
+
+/// Gesture to command table, see [`ConfigFromController::gestures`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GestureBindings {
+    /// Bindings, a later binding of the same gesture wins
+    pub bindings: Vec<GestureBinding>,
+    /// Keep versoview's default bindings of the gestures `bindings` doesn't bind
+    pub defaults: bool,
+}
+
+impl Default for GestureBindings {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+            defaults: true,
+        }
+    }
+}
+
+/// Hardware and software rendering comparison settings, see [`ConfigFromController::render_validation`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RenderValidationSettings {
+    /// Compare the first frame composited after each page load, off by default
+    pub validate_on_load: bool,
+    /// Largest difference of a color channel still counted as the same color
+    pub tolerance: u8,
+    /// Directory the outputs of divergent comparisons are kept in, the temporary directory if `None`
+    pub output_directory: Option<PathBuf>,
+}
+
+impl Default for RenderValidationSettings {
+    fn default() -> Self {
+        Self {
+            validate_on_load: false,
+            tolerance: 2,
+            output_directory: None,
+        }
+    }
+}
+
+/// Region of a frame where the renderers diverge, in device pixels from the top left
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DivergentRegion {
+    /// Left edge
+    pub x: u32,
+    /// Top edge
+    pub y: u32,
+    /// Width
+    pub width: u32,
+    /// Height
+    pub height: u32,
+}
+
+/// Comparison of a frame rendered by the GPU and by the software renderer, see [`ToVersoMessage::ValidateRendering`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RenderValidationReport {
+    /// Width of the frame in device pixels
+    pub width: u32,
+    /// Height of the frame in device pixels
+    pub height: u32,
+    /// `GL_RENDERER` string of the hardware path
+    pub hardware_renderer: String,
+    /// Pixels with a color channel differing by more than the tolerance
+    pub divergent_pixels: u64,
+    /// Largest difference of a color channel
+    pub max_difference: u8,
+    /// Smallest region holding every divergent pixel, `None` if the frames match
+    pub divergent_region: Option<DivergentRegion>,
+    /// Directory holding the WebRender capture and both frames with their difference, `None` if the frames match
+    pub output_directory: Option<PathBuf>,
+}
+
+/// How text typed in an address bar becomes a navigation target, see [`ConfigFromController::address_input`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AddressInputSettings {
+    /// Search URL with `%s` standing for the query, DuckDuckGo by default
+    pub search_template: String,
+    /// Search URLs by keyword, input starting with a keyword and a space searches the rest with it,
+    /// e.g. `w` for `https://en.wikipedia.org/w/index.php?search=%s`
+    pub keywords: BTreeMap<String, String>,
+    /// Rewrites applied in order to the input before it is interpreted
+    pub rewrites: Vec<AddressRewrite>,
+    /// Schemes navigated to as typed besides the web and internal ones, e.g. `app` or `mailto`
+    pub custom_schemes: Vec<String>,
+}
+
+impl Default for AddressInputSettings {
+    fn default() -> Self {
+        Self {
+            search_template: "https://duckduckgo.com/?q=%s".to_string(),
+            keywords: BTreeMap::new(),
+            rewrites: Vec::new(),
+            custom_schemes: Vec::new(),
+        }
+    }
+}
+
+/// Cursors shown in place of the ones pages ask for, see [`ConfigFromController::cursor_theme`]
+///
+/// Servo resolves CSS `cursor: url(...)` to its fallback keyword, so images for a page's
+/// custom cursors are given here, for the keyword they fall back to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CursorTheme {
+    /// Replacements by CSS cursor name, e.g. `pointer` or `text`
+    pub cursors: BTreeMap<String, CursorReplacement>,
+}
+
+/// What a cursor is replaced with, see [`CursorTheme`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum CursorReplacement {
+    /// Another cursor of the system theme, by CSS name
+    Named(String),
+    /// An encoded image, PNG or another format versoview decodes
+    Image {
+        /// Image data
+        data: Vec<u8>,
+        /// Point of the image the cursor points with, in pixels from its top left corner
+        hotspot: (u16, u16),
+    },
+    /// An image file, PNG or another format versoview decodes
+    ImageFile {
+        /// Path of the image
+        path: PathBuf,
+        /// Point of the image the cursor points with, in pixels from its top left corner
+        hotspot: (u16, u16),
+    },
+}
+
+/// Smooth scrolling, see [`ConfigFromController::smooth_scrolling`]
+///
+/// Mouse wheel notches and scrolling keys glide to where they scroll to instead of jumping
+/// there. Touchpads and touch screens already scroll in small steps and aren't animated.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SmoothScrolling {
+    /// Animate scrolls, on by default
+    pub enabled: bool,
+    /// How long a scroll takes, 150 ms by default; a scroll during the animation carries on
+    /// from where it is
+    pub duration_ms: u64,
+    /// How the offset moves over the animation
+    pub easing: ScrollEasing,
+}
+
+impl Default for SmoothScrolling {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration_ms: 150,
+            easing: ScrollEasing::default(),
+        }
+    }
+}
+
+/// Timing function of a smooth scroll, like the CSS easing functions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum ScrollEasing {
+    /// Constant speed
+    Linear,
+    /// Fast at first, settling at the end
+    #[default]
+    EaseOut,
+    /// Slow at both ends
+    EaseInOut,
+    /// A CSS `cubic-bezier(x1, y1, x2, y2)` curve, the x coordinates are clamped between 0 and 1
+    CubicBezier(f32, f32, f32, f32),
+}
+
+/// Replaces the start of address bar input, e.g. `gh/` with `https://github.com/`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AddressRewrite {
+    /// Start of the input to replace
+    pub prefix: String,
+    /// What replaces it
+    pub replacement: String,
+}
+
+/// What address bar input was taken for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AddressInputKind {
+    /// A web or file URL, or a host name
+    Url,
+    /// A search query, or a keyword search
+    Search,
+    /// A page of the browser, `about:` or `verso:`
+    Internal,
+    /// A URL of one of [`AddressInputSettings::custom_schemes`]
+    CustomScheme,
+}
+
+/// Where address bar input navigates to, see [`ToVersoMessage::InterpretAddressInput`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AddressTarget {
+    /// What the input was taken for
+    pub kind: AddressInputKind,
+    /// URL to load
+    pub url: url::Url,
+}
+
+/// What to capture and how, see [`ToVersoMessage::CaptureScreenshot`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScreenshotOptions {
+    /// Capture the whole height of the page by resizing the webview to it for a frame,
+    /// up to the largest framebuffer the GPU supports
+    pub full_page: bool,
+    /// Encoding of [`Screenshot::data`]
+    pub format: ScreenshotFormat,
+}
+
+/// Encoding of a [`Screenshot`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScreenshotFormat {
+    /// A PNG file
+    #[default]
+    Png,
+    /// RGBA pixels, rows top down
+    Rgba,
+}
+
+/// A composited frame of a webview, see [`ToVersoMessage::CaptureScreenshot`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Screenshot {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Encoding of the data
+    pub format: ScreenshotFormat,
+    /// The PNG file or the pixels
+    pub data: Vec<u8>,
+}
+
+/// Debug and profiling overlays of WebRender, see [`ToVersoMessage::ToggleWebRenderDebug`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum WebRenderDebugOption {
+    /// The profiler with CPU and GPU frame times
+    Profiler,
+    /// The texture cache pages
+    TextureCacheDebug,
+    /// The intermediate render targets
+    RenderTargetDebug,
+}
+
+/// How to record the composited frames, see [`ToVersoMessage::StartRecording`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecordingSettings {
+    /// Directory the frames are written to and the encoder runs in, created if needed,
+    /// a `verso-recording` directory in the temporary directory by default
+    pub directory: Option<PathBuf>,
+    /// Frames recorded a second at most, 30 by default
+    pub frames_per_second: u32,
+    /// How the frames are written without an encoder
+    pub format: RecordingFormat,
+    /// Command the frames are piped to as raw RGBA on its standard input, e.g.
+    /// `["ffmpeg", "-f", "rawvideo", "-pix_fmt", "rgba", "-s", "{width}x{height}", "-r", "{fps}", "-i", "-", "out.mp4"]`,
+    /// `{width}`, `{height}`, `{fps}` and `{directory}` are replaced in its arguments
+    pub encoder: Option<Vec<String>>,
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            frames_per_second: 30,
+            format: RecordingFormat::default(),
+            encoder: None,
+        }
+    }
+}
+
+/// How the recorded frames are written, see [`RecordingSettings`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RecordingFormat {
+    /// A PNG file per frame, named after its number and milliseconds since the start
+    #[default]
+    Png,
+    /// RGBA pixels of the frames one after another in a `frames-{width}x{height}.rgba` file,
+    /// rows top down, frames of another size than the first are skipped
+    Raw,
+}
+
+/// A finished recording, see [`ToVersoMessage::StopRecording`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RecordingReport {
+    /// Frames recorded
+    pub frames: u64,
+    /// Frames skipped because the window was resized, for raw and encoded recordings
+    pub skipped_frames: u64,
+    /// Width of the first frame in pixels
+    pub width: u32,
+    /// Height of the first frame in pixels
+    pub height: u32,
+    /// Milliseconds from the start to the end of the recording
+    pub duration_ms: u64,
+    /// Directory the frames were written to
+    pub directory: PathBuf,
+}
+
+/// What a page waits for to count as stable, see [`ToVersoMessage::WaitUntilStable`] and
+/// [`ConfigFromController::page_settled`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StabilityOptions {
+    /// Milliseconds the loaded page must go without a resource finishing loading, 500 by default
+    pub network_idle_ms: u64,
+    /// Wait for the page's fonts to load, on by default
+    pub fonts_loaded: bool,
+    /// Wait for WebRender to have no frame pending and for the page to have no animation running, on by default
+    pub no_pending_frames: bool,
+    /// Consecutive identical composites of the window, 2 by default, 0 to not compare them
+    pub identical_composites: u32,
+    /// Wait for the page's first contentful paint, off by default
+    pub first_contentful_paint: bool,
+    /// Milliseconds to wait before giving up, 30000 by default
+    pub timeout_ms: u64,
+}
+
+impl Default for StabilityOptions {
+    fn default() -> Self {
+        Self {
+            network_idle_ms: 500,
+            fonts_loaded: true,
+            no_pending_frames: true,
+            identical_composites: 2,
+            first_contentful_paint: false,
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+/// How a page got stable, see [`ToVersoMessage::WaitUntilStable`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StabilityReport {
+    /// Milliseconds from the request until the page was stable
+    pub elapsed_ms: u64,
+    /// Composites of the window while waiting
+    pub composites: u32,
+}
+
+/// A page of a tab that settled, see [`ToControllerMessage::OnPageSettled`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PageSettled {
+    /// Identifies the webview, like [`AuditEntry::webview`]
+    pub webview: String,
+    /// URL of the page
+    pub url: Option<url::Url>,
+    /// How the page got stable, or which conditions didn't hold before the timeout
+    pub result: Result<StabilityReport, String>,
+}
+
+/// Safe mode settings, see [`ConfigFromController::safe_mode`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SafeModeSettings {
+    /// Enter safe mode after repeated startup crashes, on by default
+    pub enabled: bool,
+    /// Startups in a row that have to crash before the next one is in safe mode
+    pub crash_threshold: u32,
+    /// Start in safe mode even if the previous startups didn't crash
+    pub force: bool,
+}
+
+impl Default for SafeModeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            crash_threshold: 2,
+            force: false,
+        }
+    }
+}
+
+/// Subsystems safe mode disables
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SafeModeSubsystem {
+    /// WebGL and WebGPU
+    WebGl,
+    /// Hardware accelerated media decoding
+    HardwareMediaDecode,
+    /// Shader compilation at startup and shaders cached on disk
+    ShaderCache,
+    /// Renderer option overrides and user scripts
+    ConfigOverrides,
+}
+
+/// How versoview started in safe mode, see [`ToControllerMessage::OnSafeMode`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SafeModeReport {
+    /// Startups in a row that crashed before this one
+    pub crashed_startups: u32,
+    /// Subsystems disabled for this run
+    pub disabled: Vec<SafeModeSubsystem>,
+    /// Subsystem that was starting when the last startup crashed, `None` if it crashed elsewhere
+    pub implicated: Option<SafeModeSubsystem>,
+    /// Whether safe mode was forced rather than entered after crashes
+    pub forced: bool,
+}
+
+/// Queue of messages the versoview event loop handles
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum EventQueue {
+    /// Window events, controller and internal messages, delivered by winit
+    Winit,
+    /// Messages from Servo to the compositor
+    Compositor,
+    /// Messages from Servo to the embedder
+    Embedder,
+}
+
+/// Time spent handling one type of message, see [`EventLoopStats`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MessageTypeStats {
+    /// Queue the messages came from
+    pub queue: EventQueue,
+    /// Type of the messages, the name of their variant
+    pub message: String,
+    /// Messages handled
+    pub count: u64,
+    /// Total time spent handling them, in microseconds
+    pub total_us: u64,
+    /// Longest time spent handling one, in microseconds
+    pub max_us: u64,
+}
+
+/// Messages found waiting in a queue when it was drained, see [`EventLoopStats`]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct QueueDepthStats {
+    /// Queue drained
+    pub queue: EventQueue,
+    /// Messages waiting the last time
+    pub last: u32,
+    /// Most messages ever waiting
+    pub max: u32,
+    /// Average messages waiting
+    pub average: f32,
+}
+
+/// An event loop iteration that took longer than [`ConfigFromController::stall_threshold_ms`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventLoopStall {
+    /// When the iteration ended, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Length of the iteration, in milliseconds
+    pub duration_ms: u64,
+    /// Queue of the message that took the longest
+    pub queue: EventQueue,
+    /// Type of the message that took the longest
+    pub message: String,
+    /// Time spent handling that message, in milliseconds
+    pub message_duration_ms: u64,
+}
+
+/// Instrumentation of the versoview event loop, see [`ToVersoMessage::GetEventLoopStats`]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct EventLoopStats {
+    /// Iterations of the event loop
+    pub iterations: u64,
+    /// Iterations that took longer than the stall threshold
+    pub stalls: u64,
+    /// The last stalls, oldest first
+    pub recent_stalls: Vec<EventLoopStall>,
+    /// Time spent per message type, the most total time first
+    pub messages: Vec<MessageTypeStats>,
+    /// Depth of the queues drained by Verso
+    pub queues: Vec<QueueDepthStats>,
+}
+
+/// Exit code of a versoview that exits for its controller to restart it, see [`RecoveryAction::RestartBrowser`]
+pub const RECOVERY_RESTART_EXIT_CODE: i32 = 4;
+
+/// A recovery action versoview scheduled, see [`ToControllerMessage::OnRecovery`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecoveryEvent {
+    /// When the failure was counted, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Identifies the failing webview, stable for the lifetime of versoview
+    pub webview: String,
+    /// URL the webview showed
+    pub url: Option<url::Url>,
+    /// Failure that triggered the action
+    pub trigger: RecoveryTrigger,
+    /// Failures of this kind within the failure window
+    pub failures: u32,
+    /// Action scheduled
+    pub action: RecoveryAction,
+    /// Backoff before the action is taken, in milliseconds
+    pub delay_ms: u64,
+}
+
+/// How versoview noticed the GPU driver reset, see [`ToControllerMessage::OnDeviceLost`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DeviceLostCause {
+    /// The driver reported a reset caused by versoview's rendering
+    GuiltyReset,
+    /// The driver reported a reset caused by another process
+    InnocentReset,
+    /// The driver reported a reset without saying who caused it
+    UnknownReset,
+    /// GL calls kept failing frame after frame
+    ConsecutiveErrors,
+}
+
+/// A GPU driver reset versoview restarted its renderer after, see [`ToControllerMessage::OnDeviceLost`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeviceLostEvent {
+    /// When the reset was noticed, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// How the reset was noticed
+    pub cause: DeviceLostCause,
+}
+
+/// Paint metrics of a page, see [`PaintMetric`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PaintMetricKind {
+    /// Anything of the page was painted
+    FirstPaint,
+    /// Text, an image, a canvas or an SVG of the page was painted
+    FirstContentfulPaint,
+}
+
+/// A paint metric of the page a webview shows, see [`ToControllerMessage::OnPaintMetric`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PaintMetric {
+    /// Identifies the webview, like [`AuditEntry::webview`]
+    pub webview: String,
+    /// Which paint it was
+    pub kind: PaintMetricKind,
+    /// When the frame with the paint was composited, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Whether the paint came from the page's first reflow
+    pub first_reflow: bool,
+}
+
+/// A frame a window presented, see [`ToControllerMessage::OnFramePresented`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FramePresented {
+    /// When the frame was presented, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Frames presented by versoview so far, counting this one
+    pub frame: u64,
+    /// What the webviews of the window showed in the frame
+    pub webviews: Vec<PresentedWebView>,
+}
+
+/// A mouse position the OS reported, see [`ToControllerMessage::OnRawPointerSamples`]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RawPointerSample {
+    /// Position in the window, in physical pixels
+    pub position: PhysicalPosition<f64>,
+    /// When the position was reported, in microseconds since the Unix epoch
+    pub timestamp_us: u64,
+}
+
+/// Textures frames are composited into, see [`ConfigFromController::shared_texture`]
+///
+/// The textures are exported with the platform's API for sharing GPU memory between processes,
+/// see [`SharedTextureHandle`]. Their rows are bottom up, like GL framebuffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SharedTextureSettings {
+    /// Textures composited into in turn, 3 by default; one the controller holds isn't
+    /// composited into again until it's given back with [`ToVersoMessage::ReleaseSharedTexture`]
+    pub buffers: u32,
+}
+
+impl Default for SharedTextureSettings {
+    fn default() -> Self {
+        Self { buffers: 3 }
+    }
+}
+
+/// A frame composited into a shared texture, see [`ToControllerMessage::OnSharedTextureFrame`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SharedTextureFrame {
+    /// Which of the textures the frame is in, held by the controller until it's released
+    pub buffer: u32,
+    /// Frames composited into shared textures so far, counting this one
+    pub frame: u64,
+    /// When the frame was composited, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Size of the texture in pixels
+    pub size: PhysicalSize<u32>,
+    /// Platform handle of the texture, the same for a buffer until the window is resized
+    pub handle: SharedTextureHandle,
+}
+
+/// Platform handle of a shared texture, see [`SharedTextureFrame`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SharedTextureHandle {
+    /// Linux DMA-BUF, its file descriptors are versoview's and are duplicated into the
+    /// controller with `pidfd_getfd`
+    DmaBuf {
+        /// Process id of versoview
+        pid: u32,
+        /// DRM fourcc code of the pixel format
+        fourcc: u32,
+        /// DRM format modifier of the layout
+        modifier: u64,
+        /// Memory planes of the texture
+        planes: Vec<DmaBufPlane>,
+    },
+    /// macOS IOSurface, looked up with `IOSurfaceLookup`
+    IoSurface(u32),
+    /// Windows D3D11 texture, opened with `ID3D11Device::OpenSharedResource`
+    Dxgi(u64),
+}
+
+/// A memory plane of a [`SharedTextureHandle::DmaBuf`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DmaBufPlane {
+    /// File descriptor of the plane in versoview's process
+    pub fd: i32,
+    /// Offset of the plane in bytes
+    pub offset: u32,
+    /// Bytes from one row to the next
+    pub stride: u32,
+}
+
+/// What a webview showed in a presented frame, see [`FramePresented`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PresentedWebView {
+    /// Identifies the webview, like [`AuditEntry::webview`]
+    pub webview: String,
+    /// Epoch of the display list of the webview's page, growing with each display list
+    pub epoch: u32,
+}
+
+/// Phases of a versoview shutdown, in the order they run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum ShutdownPhase {
+    /// Pages get a `beforeunload` event
+    BeforeUnload,
+    /// Unfinished downloads are stopped
+    FlushDownloads,
+    /// Bookmarks and site settings are written to disk
+    FlushStorage,
+    /// Script, media and WebGL threads are shut down by the constellation
+    Constellation,
+    /// The renderer is torn down
+    WebRender,
+    /// Shutdown finished, the process is about to exit
+    Finished,
+}
+
+/// Progress of a shutdown, see [`ToControllerMessage::OnShutdownProgress`]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ShutdownProgress {
+    /// Phase that just started
+    pub phase: ShutdownPhase,
+    /// Time since the shutdown started, in milliseconds
+    pub elapsed_ms: u64,
+    /// Time left before versoview forces the teardown, in milliseconds
+    pub remaining_ms: u64,
+}
+
+/// An SVG to rasterize, see [`ToVersoMessage::RasterizeSvg`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SvgRasterRequest {
+    /// The SVG document
+    pub svg: Vec<u8>,
+    /// Width in CSS pixels
+    pub width: u32,
+    /// Height in CSS pixels
+    pub height: u32,
+    /// Device pixels per CSS pixel, the window's scale factor if `None`
+    pub scale_factor: Option<f64>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Icon {
     /// RGBA bytes of the icon.